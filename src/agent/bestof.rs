@@ -0,0 +1,207 @@
+//! 多次采样（best-of-N）
+//!
+//! [`ChatOptions::best_of`](super::ChatOptions) 大于 1 时，模型给出最终回答（不再调用工具）后，
+//! 用同一请求再采样 N-1 次（temperature 大于 0），按 `agent.best_of_strategy` 选出与其他回答最一致的一个，
+//! 或让模型把各回答合并为一个。额外的调用同样计入本轮令牌和预算
+
+use futures_util::future::join_all;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::{Agent, TurnStats};
+use crate::config::BestOfStrategy;
+use crate::llm::{ChatRequest, LlmProvider, Message, Usage};
+
+/// 原请求未设置温度或温度为 0 时采样使用的温度
+const SAMPLE_TEMPERATURE: f32 = 0.7;
+
+const MERGE_PROMPT: &str = "下面是同一个问题的多个候选回答。综合它们给出一个最准确、完整的回答：\
+以多数回答一致的结论为准，去掉相互矛盾或明显错误的内容。直接输出最终回答，不要提及候选回答。";
+
+/// 调用提供商所需的信息
+pub(super) struct SampleTarget<'a> {
+    pub provider: &'a Arc<dyn LlmProvider>,
+    pub provider_name: &'a str,
+    /// 预算统计使用的用户
+    pub user: Option<&'a str>,
+}
+
+impl Agent {
+    /// 在首个回答之外再采样 `n - 1` 次，返回选出（或合并）的回答
+    pub(super) async fn best_of(
+        &self,
+        question: &str,
+        mut request: ChatRequest,
+        first: String,
+        n: usize,
+        target: &SampleTarget<'_>,
+        turn: &mut TurnStats,
+    ) -> String {
+        let n = n.min(self.config.agent.max_best_of);
+        if n <= 1 {
+            return first;
+        }
+        if request.temperature.is_none_or(|t| t <= 0.0) {
+            request.temperature = Some(SAMPLE_TEMPERATURE);
+        }
+
+        let mut samples = Vec::with_capacity(n - 1);
+        for _ in 1..n {
+            if let Some(ref budget) = self.budget {
+                if let Err(e) = budget.check(Some(target.provider_name), target.user).await {
+                    warn!("多次采样提前结束: {}", e);
+                    break;
+                }
+            }
+            samples.push(target.provider.chat(request.clone()));
+        }
+
+        let mut answers = vec![first];
+        for result in join_all(samples).await {
+            match result {
+                Ok(response) => {
+                    self.account(target, &request.model, response.usage.as_ref(), turn).await;
+                    // 采样中要求调用工具的回答不参与选择
+                    let has_tool_calls = response.message.tool_calls.as_ref().is_some_and(|c| !c.is_empty());
+                    if !has_tool_calls && !response.message.content.trim().is_empty() {
+                        answers.push(response.message.content);
+                    }
+                }
+                Err(e) => warn!("多次采样失败: {}", e),
+            }
+        }
+        info!("多次采样得到 {} 个回答", answers.len());
+        if answers.len() == 1 {
+            return answers.swap_remove(0);
+        }
+
+        if self.config.agent.best_of_strategy == BestOfStrategy::Merge {
+            match self.merge_answers(question, &request, &answers, target, turn).await {
+                Ok(merged) => return merged,
+                Err(e) => warn!("合并回答失败，改为选出最一致的回答: {}", e),
+            }
+        }
+        let index = most_consistent(&answers);
+        answers.swap_remove(index)
+    }
+
+    /// 让模型合并多个候选回答
+    async fn merge_answers(
+        &self,
+        question: &str,
+        request: &ChatRequest,
+        answers: &[String],
+        target: &SampleTarget<'_>,
+        turn: &mut TurnStats,
+    ) -> anyhow::Result<String> {
+        let mut content = format!("问题：{}\n", question);
+        for (i, answer) in answers.iter().enumerate() {
+            content.push_str(&format!("\n候选回答 {}：\n{}\n", i + 1, answer.trim()));
+        }
+        let mut merge = ChatRequest::new(
+            request.model.clone(),
+            vec![Message::system(MERGE_PROMPT), Message::user(content)],
+        );
+        merge.max_tokens = request.max_tokens;
+        merge.temperature = Some(0.2);
+
+        if let Some(ref budget) = self.budget {
+            budget.check(Some(target.provider_name), target.user).await?;
+        }
+        let response = target.provider.chat(merge).await?;
+        self.account(target, &request.model, response.usage.as_ref(), turn).await;
+        let merged = response.message.content;
+        if merged.trim().is_empty() {
+            return Err(anyhow::anyhow!("模型返回了空回答"));
+        }
+        Ok(merged)
+    }
+
+    /// 额外调用的令牌计入本轮和预算
+    async fn account(&self, target: &SampleTarget<'_>, model: &str, usage: Option<&Usage>, turn: &mut TurnStats) {
+        let Some(usage) = usage else {
+            return;
+        };
+        turn.tokens += usage.total_tokens as u64;
+        if let Some(ref budget) = self.budget {
+            if let Err(e) = budget.record(target.provider_name, model, target.user, usage).await {
+                warn!("记录用量失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 归一化后用于比较的回答：忽略大小写、空白和句末标点
+fn normalize(answer: &str) -> String {
+    answer
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', '。', '!', '！'])
+        .to_lowercase()
+}
+
+/// 字符二元组集合（中英文都适用的粗略相似度）
+fn bigrams(text: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+/// 两段文本的字符二元组 Jaccard 相似度（0~1）
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let (a, b) = (bigrams(a), bigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// 与其他回答平均相似度最高的回答的下标（相同时取靠前的）
+fn most_consistent(answers: &[String]) -> usize {
+    let normalized: Vec<String> = answers.iter().map(|a| normalize(a)).collect();
+    let scores: Vec<f64> = normalized
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            normalized
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, b)| similarity(a, b))
+                .sum()
+        })
+        .collect();
+    let mut best = 0;
+    for (i, score) in scores.iter().enumerate() {
+        if *score > scores[best] + f64::EPSILON {
+            best = i;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_consistent() {
+        let answers: Vec<String> = ["答案是 42。", "答案是 41", "答案是 42", "完全无关的内容"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(most_consistent(&answers), 0);
+
+        let answers: Vec<String> = ["Paris", "The capital is Lyon", "paris."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(most_consistent(&answers), 0);
+        assert_eq!(most_consistent(&answers[1..]), 0);
+    }
+}
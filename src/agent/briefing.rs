@@ -0,0 +1,323 @@
+//! 每日简报
+//!
+//! 按 `[briefing]` 的时间汇总天气、今天的日程（调度器中的提醒和任务）、昨天的每日笔记和长期记忆中的待办，
+//! 由模型一次整理成一条简报发送到指定通道。各部分独立获取，某部分失败时在素材中注明，不影响其他部分
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use super::Agent;
+use crate::channel::outbox::Outbox;
+use crate::channel::quiet::{send_proactive, QuietHours};
+use crate::channel::{Channel, ChannelTarget};
+use crate::config::{BriefingConfig, BriefingSection};
+use crate::cron::reminder::{parse_timezone, ReminderSchedule, Timezone};
+use crate::cron::{Job, JobHandler, JobStatus};
+use crate::llm::{ChatRequest, Message};
+use crate::tools::url_policy::UrlPolicy;
+
+/// 简报任务使用的处理器名称
+pub const BRIEFING_HANDLER: &str = "briefing";
+
+/// 天气服务地址
+const WEATHER_URL: &str = "https://wttr.in/";
+
+/// 每部分素材的最大字符数
+const SECTION_MAX_CHARS: usize = 3000;
+
+const BRIEFING_PROMPT: &str = "你负责撰写每日早间简报。根据用户提供的素材写一条简洁的简报：\
+开头问候并注明日期，按素材的顺序分段，每段用一两句话或几个要点概括，提醒需要留意的事项。\
+没有内容的部分一句带过或省略，获取失败的部分简单说明。不要编造素材中没有的信息，不要使用表格。";
+
+impl BriefingSection {
+    fn title(self) -> &'static str {
+        match self {
+            BriefingSection::Weather => "天气",
+            BriefingSection::Agenda => "今日日程",
+            BriefingSection::Notes => "昨日笔记",
+            BriefingSection::Todos => "待办",
+        }
+    }
+}
+
+/// 根据配置创建简报任务（不持久化，每次启动按配置重新创建）
+pub fn briefing_job(config: &BriefingConfig, timezone: Timezone) -> Result<Job> {
+    let job = match timezone.parse_when(&config.schedule, Utc::now())? {
+        ReminderSchedule::Cron(expression) => Job::new_cron("每日简报", expression, BRIEFING_HANDLER),
+        ReminderSchedule::ZonedCron(expression, tz) => {
+            Job::new_cron("每日简报", expression, BRIEFING_HANDLER).with_timezone(tz)
+        }
+        ReminderSchedule::Interval(seconds) => Job::new_interval("每日简报", seconds, BRIEFING_HANDLER),
+        ReminderSchedule::Once(_) => bail!("简报时间需要是重复的时间（如\"每天早上七点半\"）: {}", config.schedule),
+    };
+    Ok(job
+        .with_description(format!("每日简报（{}）", config.schedule))
+        .with_args(json!({
+            "channel": config.channel,
+            "chat_id": config.chat_id,
+        }))
+        .non_persistent())
+}
+
+impl Agent {
+    /// 汇总 `[briefing]` 中配置的各部分，由模型整理成简报
+    pub async fn briefing(&self) -> Result<String> {
+        let config = &self.config.briefing;
+        let offset = parse_timezone(self.config.agent.timezone.as_deref());
+        let now = Utc::now().with_timezone(&offset);
+
+        let mut material = Vec::new();
+        for &section in &config.sections {
+            let content = match self.briefing_section(section, now).await {
+                Ok(content) if content.trim().is_empty() => "（无）".to_string(),
+                Ok(content) => truncate_chars(content.trim(), SECTION_MAX_CHARS),
+                Err(e) => {
+                    warn!("获取简报的{}失败: {}", section.title(), e);
+                    format!("（获取失败: {}）", e)
+                }
+            };
+            material.push(format!("## {}\n{}", section.title(), content));
+        }
+        if material.is_empty() {
+            bail!("briefing.sections 为空");
+        }
+
+        let (provider, provider_name, model) = match config.model {
+            Some(ref spec) => self.resolve_model_override(spec)?,
+            None => (
+                self.llm_manager.default_provider()?,
+                self.config.agent.default_provider.clone(),
+                self.config.agent.default_model.clone(),
+            ),
+        };
+        let request = ChatRequest::new(
+            model.clone(),
+            vec![
+                Message::system(BRIEFING_PROMPT),
+                Message::user(format!(
+                    "当前时间：{}\n\n{}",
+                    now.format("%Y-%m-%d %H:%M（%A）"),
+                    material.join("\n\n")
+                )),
+            ],
+        );
+
+        if let Some(ref budget) = self.budget {
+            budget.check(Some(&provider_name), None).await?;
+        }
+        let response = provider.chat(request).await?;
+        if let (Some(budget), Some(usage)) = (&self.budget, &response.usage) {
+            if let Err(e) = budget.record(&provider_name, &model, None, usage).await {
+                warn!("记录用量失败: {}", e);
+            }
+        }
+        Ok(response.message.content.trim().to_string())
+    }
+
+    async fn briefing_section(&self, section: BriefingSection, now: DateTime<FixedOffset>) -> Result<String> {
+        match section {
+            BriefingSection::Weather => self.fetch_weather().await,
+            BriefingSection::Agenda => Ok(agenda(&self.jobs(false).await, now)),
+            BriefingSection::Notes => match self.memory {
+                Some(ref memory) => memory.read_day((now - Duration::days(1)).date_naive()).await,
+                None => bail!("未启用记忆存储"),
+            },
+            BriefingSection::Todos => {
+                let memory = self.memory.as_ref().ok_or_else(|| anyhow!("未启用记忆存储"))?;
+                // 简报接收者的待办和共享的待办
+                let config = &self.config.briefing;
+                let user = config
+                    .channel
+                    .as_ref()
+                    .zip(config.chat_id.as_ref())
+                    .map(|(channel, chat_id)| format!("{}:{}", channel, chat_id));
+                let mut todos: Vec<_> = memory
+                    .list_visible(user.as_deref())
+                    .await?
+                    .into_iter()
+                    .filter(|m| m.category.as_deref().is_some_and(|c| c.eq_ignore_ascii_case("todos")))
+                    .collect();
+                todos.sort_by_key(|m| std::cmp::Reverse(m.importance));
+                Ok(todos
+                    .iter()
+                    .map(|m| format!("- {}: {}", m.key, m.value))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+        }
+    }
+
+    /// 从 wttr.in 获取天气（经过出站网址策略检查）
+    async fn fetch_weather(&self) -> Result<String> {
+        let mut url = Url::parse(WEATHER_URL)?;
+        if let Some(ref location) = self.config.briefing.location {
+            url.path_segments_mut()
+                .map_err(|_| anyhow!("天气服务地址无效"))?
+                .pop_if_empty()
+                .push(location.trim());
+        }
+        url.query_pairs_mut().append_pair("format", "4");
+
+        let policy = UrlPolicy::new(&self.config.tools.url_policy);
+        let response = policy.get(url.as_str(), std::time::Duration::from_secs(15)).await?;
+        if !response.status().is_success() {
+            bail!("天气服务返回 {}", response.status());
+        }
+        let body = policy.read_body(response).await?;
+        Ok(String::from_utf8_lossy(&body).trim().to_string())
+    }
+}
+
+/// 今天（本地日期）尚待执行的定时任务，按时间排列，不包括简报任务本身
+fn agenda(jobs: &[Job], now: DateTime<FixedOffset>) -> String {
+    let today = now.date_naive();
+    let mut items: Vec<(DateTime<FixedOffset>, String)> = jobs
+        .iter()
+        .filter(|job| job.handler != BRIEFING_HANDLER && matches!(job.status, JobStatus::Pending | JobStatus::Running))
+        .filter_map(|job| {
+            let at = job.next_run_after(now.with_timezone(&Utc))?.with_timezone(now.offset());
+            (at >= now && at.date_naive() == today).then(|| {
+                let text = job
+                    .handler_args
+                    .as_ref()
+                    .and_then(|args| args.get("text"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&job.name);
+                (at, text.to_string())
+            })
+        })
+        .collect();
+    items.sort_by_key(|(at, _)| *at);
+    items
+        .iter()
+        .map(|(at, text)| format!("- {} {}", at.format("%H:%M"), text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// 简报任务处理器
+///
+/// 生成简报并按任务参数中的 channel / chat_id 发送，未指定目标时只写入日志。
+/// 通道处于免打扰时段时排队到时段结束后发送
+pub struct BriefingHandler {
+    agent: Arc<Agent>,
+    channels: Vec<Arc<dyn Channel>>,
+    quiet_hours: QuietHours,
+    outbox: Option<Arc<Outbox>>,
+}
+
+impl BriefingHandler {
+    pub fn new(agent: Arc<Agent>, channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self {
+            agent,
+            channels,
+            quiet_hours: QuietHours::default(),
+            outbox: None,
+        }
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// 设置发件箱（免打扰时段内的简报排队到其中）
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+}
+
+#[async_trait]
+impl JobHandler for BriefingHandler {
+    fn name(&self) -> &str {
+        BRIEFING_HANDLER
+    }
+
+    async fn execute(&self, _job: &Job, args: Option<Value>) -> Result<Option<String>> {
+        let args = args.unwrap_or(Value::Null);
+        let channel = args.get("channel").and_then(|v| v.as_str());
+        let chat_id = args.get("chat_id").and_then(|v| v.as_str());
+
+        let briefing = self.agent.briefing().await?;
+        match (channel, chat_id) {
+            (Some(channel), Some(chat_id)) => {
+                let target = self
+                    .channels
+                    .iter()
+                    .find(|c| c.name() == channel)
+                    .ok_or_else(|| anyhow!("简报目标通道不存在: {}", channel))?;
+                let to = ChannelTarget::parse(chat_id)?;
+                send_proactive(&self.quiet_hours, self.outbox.as_ref(), target.clone(), &to, &briefing).await?;
+                Ok(None)
+            }
+            // 未指定聊天时按任务的投递方式处理
+            _ => Ok(Some(briefing)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_briefing_job() {
+        let offset = Timezone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap());
+        let config = BriefingConfig {
+            channel: Some("telegram".to_string()),
+            chat_id: Some("42".to_string()),
+            ..Default::default()
+        };
+        let job = briefing_job(&config, offset).unwrap();
+        assert!(matches!(job.job_type, crate::cron::JobType::Cron { ref expression } if expression == "0 30 23 * * *"));
+        assert_eq!(job.handler, BRIEFING_HANDLER);
+        assert!(!job.persistent);
+        assert_eq!(job.handler_args.unwrap()["chat_id"], "42");
+
+        let once = BriefingConfig {
+            schedule: "明天早上八点".to_string(),
+            ..Default::default()
+        };
+        assert!(briefing_job(&once, offset).is_err());
+    }
+
+    #[test]
+    fn test_agenda() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let now = offset.with_ymd_and_hms(2024, 5, 15, 7, 30, 0).unwrap();
+        let at = |h, m| offset.with_ymd_and_hms(2024, 5, 15, h, m, 0).unwrap().with_timezone(&Utc);
+        let job = |name: &str, run_at: DateTime<Utc>| Job::new_once(name, run_at, "reminder");
+
+        let jobs = vec![
+            job("提醒: 开会", at(15, 0)).with_args(json!({"text": "开会"})),
+            job("交周报", at(9, 0)),
+            // 重复任务按表达式计算下次执行时间（Cron 表达式为 UTC，12:00 即本地 20:00）
+            Job::new_cron("站会", "0 0 12 * * *", "reminder"),
+            // 已经过去、明天和简报任务本身都不列出
+            job("晨跑", at(6, 0)),
+            job("明天的事", at(23, 0) + Duration::hours(2)),
+            {
+                let mut briefing = job("每日简报", at(10, 0));
+                briefing.handler = BRIEFING_HANDLER.to_string();
+                briefing
+            },
+        ];
+        assert_eq!(agenda(&jobs, now), "- 09:00 交周报\n- 15:00 开会\n- 20:00 站会");
+        assert_eq!(truncate_chars("天气晴朗", 2), "天气…");
+    }
+}
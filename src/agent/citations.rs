@@ -0,0 +1,108 @@
+//! 回复中的来源引用
+//!
+//! 本轮使用了 web_search / fetch_page 时，根据 [`AgentResponse::tool_trace`](super::AgentResponse)
+//! 在回复末尾附上编号的来源链接，不依赖模型自己记得引用。读取过的网页优先，
+//! 没有读取网页时列出搜索结果
+
+use super::ToolTrace;
+use crate::t;
+
+/// 从工具调用记录中提取来源网址（去重，按出现顺序，最多 `max` 个，0 表示不限制）
+pub fn collect_sources(trace: &[ToolTrace], max: usize) -> Vec<String> {
+    let succeeded = || trace.iter().filter(|t| t.success);
+    let pages: Vec<String> = succeeded()
+        .filter(|t| t.name == "fetch_page")
+        .filter_map(|t| t.arguments.get("url")?.as_str().map(str::to_string))
+        .collect();
+    let urls = if pages.is_empty() {
+        succeeded()
+            .filter(|t| t.name == "web_search")
+            .flat_map(|t| t.output.lines().filter_map(|l| l.trim().strip_prefix("URL:")).map(|u| u.trim().to_string()))
+            .collect()
+    } else {
+        pages
+    };
+
+    let mut sources: Vec<String> = Vec::new();
+    for url in urls {
+        if !url.is_empty() && !sources.contains(&url) {
+            sources.push(url);
+        }
+    }
+    if max > 0 {
+        sources.truncate(max);
+    }
+    sources
+}
+
+/// 在回复末尾附上来源列表，回复中已包含全部来源时不重复添加
+pub fn append_sources(content: &str, sources: &[String], channel: Option<&str>) -> String {
+    if sources.is_empty() || sources.iter().all(|s| content.contains(s.as_str())) {
+        return content.to_string();
+    }
+    let list = sources
+        .iter()
+        .enumerate()
+        .map(|(i, url)| match channel {
+            // Discord 中用尖括号包裹，避免每个链接都展开预览
+            Some("discord") => format!("[{}] <{}>", i + 1, url),
+            _ => format!("[{}] {}", i + 1, url),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n{}\n{}", content.trim_end(), t!("citations.header"), list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace(name: &str, arguments: serde_json::Value, output: &str, success: bool) -> ToolTrace {
+        ToolTrace {
+            name: name.to_string(),
+            arguments,
+            output: output.to_string(),
+            success,
+            duration: std::time::Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_collect_sources() {
+        let search = trace(
+            "web_search",
+            json!({"query": "rust"}),
+            "1. Rust\n   URL: https://www.rust-lang.org/\n   官网\n\n2. Book\n   URL: https://doc.rust-lang.org/book/\n   教程\n",
+            true,
+        );
+        assert_eq!(
+            collect_sources(std::slice::from_ref(&search), 0),
+            vec!["https://www.rust-lang.org/", "https://doc.rust-lang.org/book/"]
+        );
+
+        // 读取过网页时只列出网页，失败的读取不算
+        let trace = vec![
+            search,
+            trace("fetch_page", json!({"url": "https://doc.rust-lang.org/book/"}), "...", true),
+            trace("fetch_page", json!({"url": "https://down.example.com/"}), "读取网页失败", false),
+            trace("fetch_page", json!({"url": "https://doc.rust-lang.org/book/"}), "...", true),
+            trace("shell", json!({"command": "ls"}), "URL: x", true),
+        ];
+        assert_eq!(collect_sources(&trace, 5), vec!["https://doc.rust-lang.org/book/"]);
+        assert!(collect_sources(&trace[4..], 5).is_empty());
+    }
+
+    #[test]
+    fn test_append_sources() {
+        let sources = vec!["https://a.com/".to_string(), "https://b.com/".to_string()];
+        assert_eq!(
+            append_sources("答案。\n", &sources, Some("telegram")),
+            "答案。\n\n来源：\n[1] https://a.com/\n[2] https://b.com/"
+        );
+        assert!(append_sources("答案", &sources, Some("discord")).ends_with("[2] <https://b.com/>"));
+        // 回复中已包含全部来源
+        assert_eq!(append_sources("见 https://a.com/ 和 https://b.com/", &sources, None), "见 https://a.com/ 和 https://b.com/");
+        assert_eq!(append_sources("答案", &[], None), "答案");
+    }
+}
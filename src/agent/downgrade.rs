@@ -0,0 +1,119 @@
+//! 长对话自动降级模型
+//!
+//! 会话自上次清空上下文以来使用的令牌数（会话统计的累计值减去清空时记下的基线）超过
+//! `downgrade.token_threshold` 后改用 `downgrade.model`，切换和恢复时在回复前提示用户。
+//! 基线保存在会话上下文中，重启后仍然有效
+
+use tracing::{info, warn};
+
+use super::Agent;
+
+/// 会话上下文键：上次清空上下文时的累计令牌数
+const BASELINE_KEY: &str = "downgrade_baseline_tokens";
+
+impl Agent {
+    /// 当前会话应使用的降级模型，降级状态变化时附带给用户的提示
+    pub(super) async fn downgrade_model(&self, session_id: &str) -> (Option<String>, Option<String>) {
+        let config = &self.config.downgrade;
+        let Some(ref model) = config.model.as_ref().filter(|_| config.enabled) else {
+            return (None, None);
+        };
+        let Some(total) = self.session_tokens(session_id).await else {
+            return (None, None);
+        };
+
+        let used = total.saturating_sub(self.downgrade_baseline(session_id).await);
+        let downgrade = used >= config.token_threshold;
+        let changed = {
+            let mut downgraded = self.downgraded.lock().unwrap();
+            if downgrade {
+                downgraded.insert(session_id.to_string())
+            } else {
+                downgraded.remove(session_id)
+            }
+        };
+
+        let notice = changed.then(|| {
+            if downgrade {
+                info!("会话 {} 已使用 {} 令牌，降级到 {}", session_id, used, model);
+                format!(
+                    "💸 本会话已使用约 {} 令牌，已切换到更经济的模型 {}。发送 /clear 清空上下文后恢复。",
+                    used, model
+                )
+            } else {
+                info!("会话 {} 上下文已清空，恢复原模型", session_id);
+                "已恢复原模型。".to_string()
+            }
+        });
+        (downgrade.then(|| model.to_string()), notice)
+    }
+
+    /// 上下文清空后以当前累计用量为基线重新计算
+    pub(super) async fn reset_downgrade(&self, session_id: &str) {
+        if !self.config.downgrade.enabled {
+            return;
+        }
+        let (Some(sessions), Some(total)) = (self.sessions.as_ref(), self.session_tokens(session_id).await) else {
+            return;
+        };
+        if let Err(e) = sessions.set_context_value(session_id, BASELINE_KEY, &total.into()).await {
+            warn!("保存降级基线失败: {}", e);
+        }
+    }
+
+    async fn downgrade_baseline(&self, session_id: &str) -> u64 {
+        let Some(ref sessions) = self.sessions else {
+            return 0;
+        };
+        match sessions.get_context_value(session_id, BASELINE_KEY).await {
+            Ok(value) => value.and_then(|v| v.as_u64()).unwrap_or(0),
+            Err(e) => {
+                warn!("读取降级基线失败: {}", e);
+                0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agent::Agent;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_downgrade_after_threshold() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some("http://127.0.0.1:1/v1".to_string());
+        config.downgrade.enabled = true;
+        config.downgrade.model = Some("cheap-model".to_string());
+        config.downgrade.token_threshold = 1000;
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let sessions = agent.sessions().unwrap().clone();
+        let session = sessions.get_or_create("telegram:42", "telegram", "42").await.unwrap();
+        let add_tokens = |tokens: u64| {
+            let (sessions, session) = (sessions.clone(), session.clone());
+            async move { sessions.update(&session, |s| s.record_tokens(tokens)).await.unwrap() }
+        };
+
+        add_tokens(600).await;
+        assert_eq!(agent.downgrade_model("telegram:42").await, (None, None));
+
+        // 超过阈值后降级，只提示一次
+        add_tokens(600).await;
+        let (model, notice) = agent.downgrade_model("telegram:42").await;
+        assert_eq!(model.as_deref(), Some("cheap-model"));
+        assert!(notice.unwrap().contains("cheap-model"));
+        assert_eq!(agent.downgrade_model("telegram:42").await, (Some("cheap-model".to_string()), None));
+
+        // 清空上下文后恢复
+        agent.clear_context().await;
+        let (model, notice) = agent.downgrade_model("telegram:42").await;
+        assert_eq!(model, None);
+        assert!(notice.is_some());
+        add_tokens(500).await;
+        assert_eq!(agent.downgrade_model("telegram:42").await, (None, None));
+    }
+}
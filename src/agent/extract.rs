@@ -0,0 +1,180 @@
+//! 对话记忆提取
+//!
+//! 会话结束或空闲超时后，让模型从本会话新增的对话中提取值得长期记住的事实、偏好和待办，
+//! 保存到长期记忆并发布 [`MemoryExtractedEvent`]。已提取的位置记录在会话上下文中，避免重复提取
+
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::Agent;
+use crate::bus::{EventBus, EventHandler, MemoryExtractedEvent, SessionEndedEvent};
+use crate::llm::{ChatRequest, Message};
+use crate::memory::{ExtractedMemory, MAX_IMPORTANCE};
+
+/// 会话上下文键：已提取到的对话历史长度
+const EXTRACTED_LEN_KEY: &str = "memory_extracted_len";
+
+const EXTRACTION_PROMPT: &str = "你负责整理长期记忆。从用户提供的对话中提取以后仍然有用的信息：\
+关于用户的事实（Facts）、用户的偏好（Preferences）、尚未完成的待办（TODOs）。\
+忽略寒暄、一次性的问题和助手自己的推测。\
+以 JSON 数组输出，每项包含 category（Facts、Preferences 或 TODOs）、key（简短的名称）、value（具体内容）、importance（0-10）。\
+内容是对已有记忆的更新时使用相同的 key。没有值得记住的内容时输出 []。只输出 JSON。";
+
+impl Agent {
+    /// 从会话中尚未提取过的对话提取记忆并保存，返回提取到的记忆
+    ///
+    /// 无痕会话和新增对话过短时不提取
+    pub async fn extract_memories(&self, session_id: &str) -> Result<Vec<ExtractedMemory>> {
+        let config = &self.config.memory.extraction;
+        let Some(memory) = self.memory_for(session_id) else {
+            return Ok(Vec::new());
+        };
+
+        let len = memory.conversation_len(session_id).await;
+        // 撤销等操作使对话变短时从头提取
+        let offset = self.extracted_len(session_id).await.filter(|&o| o <= len).unwrap_or(0);
+        let text = memory.read_conversation_text(session_id, offset).await?;
+        let chars = text.trim().chars().count();
+        if chars < config.min_chars {
+            return Ok(Vec::new());
+        }
+        let text: String = if config.max_chars > 0 && chars > config.max_chars {
+            text.trim().chars().skip(chars - config.max_chars).collect()
+        } else {
+            text.trim().to_string()
+        };
+
+        // 会话 ID 即 <通道>:<聊天 ID>，与工具和用户资料使用的用户标识一致
+        let keys: Vec<String> = memory
+            .list_visible(Some(session_id))
+            .await?
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        let (provider, provider_name, model) = match config.model {
+            Some(ref spec) => self.resolve_model_override(spec)?,
+            None => (
+                self.llm_manager.default_provider()?,
+                self.config.agent.default_provider.clone(),
+                self.config.agent.default_model.clone(),
+            ),
+        };
+        let mut request = ChatRequest::new(
+            model.clone(),
+            vec![
+                Message::system(EXTRACTION_PROMPT),
+                Message::user(format!(
+                    "已有记忆的键：{}\n\n对话：\n{}",
+                    if keys.is_empty() { "无".to_string() } else { keys.join("、") },
+                    text
+                )),
+            ],
+        );
+        request.temperature = Some(0.0);
+
+        if let Some(ref budget) = self.budget {
+            budget.check(Some(&provider_name), Some(session_id)).await?;
+        }
+        let response = provider.chat(request).await?;
+        if let (Some(budget), Some(usage)) = (&self.budget, &response.usage) {
+            if let Err(e) = budget.record(&provider_name, &model, Some(session_id), usage).await {
+                warn!("记录用量失败: {}", e);
+            }
+        }
+
+        let extracted = parse_extracted(&response.message.content)?;
+        for m in &extracted {
+            memory
+                .submit_memory(Some(session_id), &m.key, &m.value, Some(&m.category), m.importance, "extraction")
+                .await?;
+        }
+        self.set_extracted_len(session_id, len).await?;
+        info!("从会话 {} 提取了 {} 条记忆", session_id, extracted.len());
+        Ok(extracted)
+    }
+
+    /// 会话已提取到的对话历史长度
+    async fn extracted_len(&self, session_id: &str) -> Option<u64> {
+        let sessions = self.sessions.as_ref()?;
+        match sessions.get_context_value(session_id, EXTRACTED_LEN_KEY).await {
+            Ok(value) => value?.as_u64(),
+            Err(e) => {
+                warn!("读取记忆提取位置失败: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn set_extracted_len(&self, session_id: &str, len: u64) -> Result<()> {
+        match self.sessions {
+            Some(ref sessions) => sessions.set_context_value(session_id, EXTRACTED_LEN_KEY, &len.into()).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// 解析模型输出的 JSON 数组（允许包裹在代码块中），丢弃键或内容为空的条目
+fn parse_extracted(output: &str) -> Result<Vec<ExtractedMemory>> {
+    let start = output.find('[').ok_or_else(|| anyhow!("模型输出中没有 JSON 数组: {}", output))?;
+    let end = output.rfind(']').filter(|&e| e > start).ok_or_else(|| anyhow!("模型输出的 JSON 不完整"))?;
+    let items: Vec<ExtractedMemory> = serde_json::from_str(&output[start..=end])
+        .map_err(|e| anyhow!("解析提取结果失败: {}", e))?;
+    Ok(items
+        .into_iter()
+        .filter(|m| !m.key.trim().is_empty() && !m.value.trim().is_empty() && !m.key.contains("**"))
+        .map(|m| ExtractedMemory {
+            category: m.category.trim().to_string(),
+            key: m.key.trim().replace('\n', " "),
+            value: m.value.trim().replace('\n', " "),
+            importance: m.importance.clamp(0, MAX_IMPORTANCE),
+        })
+        .collect())
+}
+
+/// 会话结束时提取记忆，并发布 [`MemoryExtractedEvent`]
+pub struct MemoryExtractionHandler {
+    agent: Arc<Agent>,
+    event_bus: Arc<EventBus>,
+}
+
+impl MemoryExtractionHandler {
+    pub fn new(agent: Arc<Agent>, event_bus: Arc<EventBus>) -> Self {
+        Self { agent, event_bus }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler<SessionEndedEvent> for MemoryExtractionHandler {
+    async fn handle(&self, event: &SessionEndedEvent) {
+        match self.agent.extract_memories(&event.session_id).await {
+            Ok(memories) if memories.is_empty() => {}
+            Ok(memories) => {
+                let _ = self.event_bus.publish(MemoryExtractedEvent {
+                    session_id: event.session_id.clone(),
+                    memories,
+                    timestamp: chrono::Utc::now(),
+                });
+            }
+            Err(e) => warn!("从会话 {} 提取记忆失败: {}", event.session_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extracted() {
+        let output = "```json\n[\n  {\"category\": \"Preferences\", \"key\": \"语言\", \"value\": \"喜欢用 Rust\", \"importance\": 12},\n  {\"key\": \"会议\", \"value\": \"周五前准备材料\"},\n  {\"key\": \"\", \"value\": \"无效\"}\n]\n```";
+        let memories = parse_extracted(output).unwrap();
+        assert_eq!(memories.len(), 2);
+        assert_eq!(memories[0].importance, MAX_IMPORTANCE);
+        assert_eq!(memories[1].category, "General");
+        assert_eq!(memories[1].importance, 5);
+
+        assert!(parse_extracted("[]").unwrap().is_empty());
+        assert!(parse_extracted("没有可提取的内容").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! 回答评分
+//!
+//! 评测（`nanobot eval`）中的 `llm` 断言由模型按给定标准判断回答是否合格
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::Agent;
+use crate::llm::{ChatRequest, Message};
+
+const GRADER_PROMPT: &str = "你是严格的评测员。根据给出的问题、回答和评分标准，判断回答是否满足标准。\
+以 JSON 输出 {\"pass\": true 或 false, \"reason\": \"简短理由\"}，只输出 JSON。";
+
+/// 评分结果
+#[derive(Debug, Clone, Deserialize)]
+pub struct Grade {
+    #[serde(rename = "pass")]
+    pub passed: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl Agent {
+    /// 用指定模型（未指定时用默认模型）判断回答是否满足标准
+    pub async fn grade_answer(&self, question: &str, answer: &str, criteria: &str, spec: Option<&str>) -> Result<Grade> {
+        let (provider, provider_name, model) = match spec {
+            Some(spec) => self.resolve_model_override(spec)?,
+            None => (
+                self.llm_manager.default_provider()?,
+                self.config.agent.default_provider.clone(),
+                self.config.agent.default_model.clone(),
+            ),
+        };
+        let content = format!("问题：\n{}\n\n回答：\n{}\n\n评分标准：\n{}", question, answer, criteria);
+        let mut request = ChatRequest::new(
+            model.clone(),
+            vec![Message::system(GRADER_PROMPT), Message::user(content)],
+        );
+        request.temperature = Some(0.0);
+
+        if let Some(ref budget) = self.budget {
+            budget.check(Some(&provider_name), None).await?;
+        }
+        let response = provider.chat(request).await?;
+        if let (Some(budget), Some(usage)) = (&self.budget, &response.usage) {
+            if let Err(e) = budget.record(&provider_name, &model, None, usage).await {
+                warn!("记录用量失败: {}", e);
+            }
+        }
+        parse_grade(&response.message.content)
+    }
+}
+
+/// 解析评分结果（允许包裹在代码块中）
+fn parse_grade(output: &str) -> Result<Grade> {
+    let json = output
+        .find('{')
+        .zip(output.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &output[start..=end])
+        .ok_or_else(|| anyhow!("评分结果不是 JSON: {}", output.trim()))?;
+    serde_json::from_str(json).map_err(|e| anyhow!("无法解析评分结果: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_grade() {
+        let grade = parse_grade("```json\n{\"pass\": true, \"reason\": \"给出了 2\"}\n```").unwrap();
+        assert!(grade.passed);
+        assert_eq!(grade.reason, "给出了 2");
+        assert!(!parse_grade("{\"pass\": false}").unwrap().passed);
+        assert!(parse_grade("合格").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! 回复语言检测
+//!
+//! 基础提示词是中文时，模型常用中文回答英文问题。这里按字符的书写系统
+//! （拉丁字母再按常用词区分）粗略判断用户消息的语言，作为提示交给模型
+
+/// 至少需要的字母/汉字数，太短的消息（如 "ok"、"好"）不判断
+const MIN_LETTERS: usize = 2;
+
+/// 拉丁字母语言的常用词
+const LATIN_WORDS: &[(&str, &[&str])] = &[
+    (
+        "English",
+        &["the", "is", "are", "what", "how", "you", "and", "to", "of", "can", "please", "this", "it", "i", "my", "do"],
+    ),
+    (
+        "Français",
+        &["le", "la", "les", "est", "je", "vous", "et", "des", "une", "pour", "que", "pas", "comment", "bonjour"],
+    ),
+    (
+        "Deutsch",
+        &["der", "die", "das", "ist", "ich", "und", "nicht", "sie", "wie", "was", "ein", "eine", "bitte", "mit"],
+    ),
+    (
+        "Español",
+        &["el", "los", "las", "es", "que", "y", "por", "para", "una", "cómo", "qué", "hola", "del", "gracias"],
+    ),
+];
+
+/// 检测用户消息的语言，无法判断时返回 None
+///
+/// 通道附加的引用、编辑说明（`[用户…「…」]`）不参与判断
+pub fn detect(text: &str) -> Option<&'static str> {
+    let text = strip_annotations(text);
+    let (mut han, mut kana, mut hangul, mut cyrillic, mut arabic, mut latin) = (0, 0, 0, 0, 0, 0);
+    for c in text.chars() {
+        match c {
+            '\u{3040}'..='\u{30ff}' => kana += 1,
+            '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' => han += 1,
+            '\u{ac00}'..='\u{d7af}' | '\u{1100}'..='\u{11ff}' => hangul += 1,
+            '\u{0400}'..='\u{04ff}' => cyrillic += 1,
+            '\u{0600}'..='\u{06ff}' => arabic += 1,
+            c if c.is_alphabetic() && (c.is_ascii() || ('\u{00c0}'..='\u{024f}').contains(&c)) => latin += 1,
+            _ => {}
+        }
+    }
+
+    // 日文中夹杂汉字，有假名即视为日文；汉字按字计数，与按字母计数的拉丁文相比放大权重
+    let scores = [
+        ("日本語", if kana > 0 { (kana + han) * 3 } else { 0 }),
+        ("中文", if kana == 0 { han * 3 } else { 0 }),
+        ("한국어", hangul * 3),
+        ("Русский", cyrillic),
+        ("العربية", arabic),
+    ];
+    let (script, score) = scores.into_iter().max_by_key(|&(_, n)| n)?;
+    let letters = han + kana + hangul + cyrillic + arabic + latin;
+    if letters < MIN_LETTERS {
+        return None;
+    }
+    if score >= latin {
+        return Some(script);
+    }
+    detect_latin(&text)
+}
+
+/// 按常用词区分拉丁字母语言，没有命中常用词时不判断
+fn detect_latin(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    LATIN_WORDS
+        .iter()
+        .map(|(language, common)| (*language, words.iter().filter(|w| common.contains(&w.as_str())).count()))
+        .filter(|&(_, hits)| hits > 0)
+        // 命中数相同时取列表中靠前的语言
+        .min_by_key(|&(_, hits)| std::cmp::Reverse(hits))
+        .map(|(language, _)| language)
+}
+
+/// 去掉通道附加的说明（以 `[用户` 开头、`」]` 结尾）
+fn strip_annotations(text: &str) -> String {
+    let mut rest = text;
+    let mut kept = String::new();
+    while let Some(start) = rest.find("[用户") {
+        let Some(len) = rest[start..].find("」]") else {
+            break;
+        };
+        kept.push_str(&rest[..start]);
+        rest = &rest[start + len + "」]".len()..];
+    }
+    kept.push_str(rest);
+    kept
+}
+
+/// 系统提示词中的回复语言说明
+pub fn reply_hint(language: &str) -> String {
+    format!(
+        "用户这条消息使用的是{}，请使用{}回答。用户资料、会话指令或用户本人要求了回复语言时以其为准",
+        language, language
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("What is the weather like tomorrow?"), Some("English"));
+        assert_eq!(detect("明天天气怎么样？"), Some("中文"));
+        assert_eq!(detect("明日の天気はどうですか"), Some("日本語"));
+        assert_eq!(detect("내일 날씨 어때요?"), Some("한국어"));
+        assert_eq!(detect("Какая завтра погода?"), Some("Русский"));
+        assert_eq!(detect("Quel temps fera-t-il demain, je vous prie ?"), Some("Français"));
+        assert_eq!(detect("帮我把这个函数改成 async fn"), Some("中文"));
+        // 太短或没有常用词时不判断
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("12345 !!!"), None);
+        assert_eq!(detect("kubectl rollout restart"), None);
+    }
+
+    #[test]
+    fn test_ignores_annotations() {
+        let text = "[用户回复了你之前的消息：「明天下雨，记得带伞」]\nHow do you know that?";
+        assert_eq!(detect(text), Some("English"));
+        assert_eq!(strip_annotations("[用户把之前的一条消息修改为：「hi」]\n好的"), "\n好的");
+    }
+}
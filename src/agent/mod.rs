@@ -4,26 +4,184 @@
 
 use anyhow::{anyhow, Result};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+pub mod bestof;
+pub mod briefing;
+pub mod citations;
+mod downgrade;
+mod grade;
+mod moderation;
+mod title;
+pub mod extract;
+mod language;
+pub mod research;
+pub mod snapshot;
+pub mod prompt;
+
 use crate::{
-    config::Config,
-    llm::{ChatRequest, LlmManager, Message, Role},
-    memory::MemoryStore,
+    budget::Budget,
+    config::{Config, ConversationAccess, PersonaConfig},
+    embeddings::EmbeddingProviderFactory,
+    llm::probe::ProviderHealth,
+    llm::queue::with_request_session,
+    llm::router::{LlmRouter, RouteContext},
+    llm::{ChatRequest, GenerationParams, LlmManager, LlmProvider, Message, Role, ToolCall},
+    memory::{day::DayRecall, semantic::SemanticIndex, ConversationMessage, MemoryStore},
+    session::{SessionManager, SessionStats, PERSONA_PROPERTY},
+    tasks::TaskManager,
     tools::{ToolContext, ToolRegistry},
+    vault::Vault,
 };
+use prompt::PromptBuilder;
+use snapshot::{AgentSnapshot, SNAPSHOT_VERSION};
 
 /// Agent 实例
 pub struct Agent {
     config: Config,
     llm_manager: LlmManager,
+    router: Option<LlmRouter>,
     tool_registry: ToolRegistry,
     memory: Option<Arc<MemoryStore>>,
-    session_id: Mutex<String>,
-    context: Mutex<AgentContext>,
+    sessions: Option<Arc<SessionManager>>,
+    /// 未指定会话时使用的会话（CLI 等单会话场景）
+    default_session: Arc<ActiveSession>,
+    /// 通过 [`Agent::in_session`] 使用过的各会话上下文
+    session_contexts: std::sync::Mutex<HashMap<String, Arc<ActiveSession>>>,
+    /// 各会话显式设置的无痕模式（未设置时按通道默认值）
+    incognito: std::sync::Mutex<HashMap<String, bool>>,
+    /// 运行时切换的模型（/model、/provider），优先于默认模型和路由
+    model_override: std::sync::RwLock<Option<String>>,
+    /// 定时任务调度器（gateway 模式）
+    scheduler: Option<Arc<crate::cron::Scheduler>>,
+    /// 各会话最后一轮开始前的对话历史长度（用于撤销最后一轮）
+    last_turn: std::sync::Mutex<HashMap<String, u64>>,
+    /// 用量预算（配置了 `[budget]` 时启用）
+    budget: Option<Arc<Budget>>,
+    /// 各会话切换的角色（/persona），空字符串表示默认角色；未设置时读取会话元数据
+    personas: std::sync::Mutex<HashMap<String, String>>,
+    /// 各用户已进行的引导轮数
+    onboarding_turns: std::sync::Mutex<HashMap<String, u32>>,
+    /// 已降级到更经济模型的会话（`[downgrade]`）
+    downgraded: std::sync::Mutex<HashSet<String>>,
+    /// 正在生成标题的会话
+    titling: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// 提供商可用状态（gateway 启用 `[llm.probe]` 时），不可用时按备用顺序切换
+    provider_health: Option<Arc<ProviderHealth>>,
+}
+
+/// 默认角色名（使用 `[agent]` 中的配置）
+pub const DEFAULT_PERSONA: &str = "default";
+
+/// 用户资料中记录引导状态的资料项
+const ONBOARDING_KEY: &str = "onboarding";
+const ONBOARDING_PENDING: &str = "pending";
+const ONBOARDING_DONE: &str = "done";
+
+/// 本轮对话用户的资料
+#[derive(Debug, Default)]
+struct UserContext {
+    /// 用户资料条目
+    profile: String,
+    /// 是否处于新用户引导中
+    onboarding: bool,
+}
+
+/// 消息来源
+#[derive(Debug, Clone, Copy)]
+struct MessageOrigin<'a> {
+    channel: &'a str,
+    chat_id: &'a str,
+}
+
+/// 单次对话选项
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    /// 截止时间，未设置时使用 `agent.turn_timeout_secs`
+    pub deadline: Option<Instant>,
+    /// 取消令牌，取消后中止本轮对话
+    pub cancel_token: Option<CancellationToken>,
+    /// 覆盖本轮使用的模型（"provider/model" 或仅模型名）
+    pub model_override: Option<String>,
+    /// 覆盖本轮的生成参数
+    pub params: Option<GenerationParams>,
+    /// 消息来源通道
+    pub channel: Option<String>,
+    /// 消息来源聊天 ID
+    pub chat_id: Option<String>,
+    /// 多次采样的次数（大于 1 时生成多个回答后选出或合并为最终回答）
+    pub best_of: Option<usize>,
+}
+
+impl ChatOptions {
+    /// 设置消息来源
+    pub fn with_origin(mut self, channel: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self.chat_id = Some(chat_id.into());
+        self
+    }
+
+    /// 设置超时时间
+    #[cfg(test)]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// 设置取消令牌
+    pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// 设置多次采样的次数
+    pub fn with_best_of(mut self, n: usize) -> Self {
+        self.best_of = Some(n);
+        self
+    }
+
+    fn origin(&self) -> Option<MessageOrigin<'_>> {
+        self.channel.as_deref().map(|channel| MessageOrigin {
+            channel,
+            chat_id: self.chat_id.as_deref().unwrap_or_default(),
+        })
+    }
+}
+
+/// 本轮对话被中止
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurnAborted {
+    /// 被取消
+    Cancelled,
+    /// 超过截止时间
+    DeadlineExceeded,
+}
+
+impl std::fmt::Display for TurnAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TurnAborted::Cancelled => write!(f, "本轮对话已取消"),
+            TurnAborted::DeadlineExceeded => write!(f, "本轮对话超时"),
+        }
+    }
+}
+
+impl std::error::Error for TurnAborted {}
+
+/// 单轮对话的统计，记录到会话
+#[derive(Debug, Default)]
+struct TurnStats {
+    tool_calls: u64,
+    tokens: u64,
+    replied: bool,
 }
 
 /// Agent 上下文
@@ -33,6 +191,17 @@ struct AgentContext {
     total_tokens: u32,
 }
 
+/// 一个会话的 ID 和上下文
+#[derive(Debug)]
+struct ActiveSession {
+    session_id: Mutex<String>,
+    context: Mutex<AgentContext>,
+}
+
+tokio::task_local! {
+    static ACTIVE_SESSION: Arc<ActiveSession>;
+}
+
 impl Agent {
     /// 创建新的 Agent 实例
     ///
@@ -40,12 +209,27 @@ impl Agent {
     /// * `session_id` - 可选的会话 ID，如果为 None 则生成新的 UUID
     pub async fn new(config: Config, session_id: Option<String>) -> Result<Self> {
         let llm_manager = LlmManager::new(&config)?;
+        let router = config
+            .llm
+            .router
+            .enabled
+            .then(|| LlmRouter::new(&config.llm.router));
         let tool_registry = ToolRegistry::default_with_config(&config);
         
+        // 启用加密时口令错误直接报错，避免以明文写入
+        let vault = Vault::from_config(&config)?;
+
         // 初始化内存系统
         let memory = if !config.memory.workspace_path.as_os_str().is_empty() {
             match MemoryStore::new(&config.memory.workspace_path).await {
-                Ok(m) => Some(Arc::new(m)),
+                Ok(m) => Some(Arc::new(
+                    m.with_vault(vault.clone())
+                        .with_max_memories(config.memory.max_memories)
+                        .with_per_user(config.memory.per_user)
+                        .with_review(config.memory.review.enabled)
+                        .with_semantic(semantic_index(&config))
+                        .with_conversation_format(config.memory.conversation_format),
+                )),
                 Err(e) => {
                     warn!("内存系统初始化失败: {}，继续运行", e);
                     None
@@ -55,6 +239,59 @@ impl Agent {
             None
         };
 
+        // 会话统计（消息数、工具调用、令牌用量）
+        let sessions = if !config.memory.workspace_path.as_os_str().is_empty() {
+            match SessionManager::from_config(
+                &config.session,
+                &config.sessions_db_path().to_string_lossy(),
+                None,
+                vault,
+            )
+            .await
+            {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!("会话统计初始化失败: {}，继续运行", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let budget = match Budget::from_config(&config).await {
+            Ok(b) => b,
+            Err(e) => {
+                warn!("用量预算初始化失败: {}，继续运行", e);
+                None
+            }
+        };
+
+        if let Some(ref sessions) = sessions {
+            tool_registry.register(crate::tools::typed::Typed::new(crate::tools::pin::PinContextTool::new(sessions.clone())));
+        }
+        if let Some(ref memory) = memory {
+            tool_registry.register(crate::tools::typed::Typed::new(crate::tools::profile::RememberUserTool::new(memory.clone())));
+            tool_registry.register(crate::tools::typed::Typed::new(crate::tools::memory::RememberTool::new(memory.clone())));
+            tool_registry.register(crate::tools::typed::Typed::new(crate::tools::memory::RecallTool::new(memory.clone())));
+            let access = config.tools.conversation_access;
+            tool_registry.register(crate::tools::typed::Typed::new(crate::tools::memory::RecallDayTool::new(memory.clone(), access)));
+            if access != crate::config::ConversationAccess::Off {
+                tool_registry.register(crate::tools::typed::Typed::new(crate::tools::conversation::ListConversationsTool::new(memory.clone(), access)));
+                tool_registry.register(crate::tools::typed::Typed::new(crate::tools::conversation::ReadConversationTool::new(memory.clone(), access)));
+            }
+        }
+        if config.tasks.enabled && !config.memory.workspace_path.as_os_str().is_empty() {
+            match TaskManager::new(&config.tasks_db_path().to_string_lossy(), config.tasks.max_running).await {
+                Ok(tasks) => {
+                    tool_registry.register(crate::tools::typed::Typed::new(crate::tools::task::StartTaskTool::new(tasks.clone(), tool_registry.clone())));
+                    tool_registry.register(crate::tools::typed::Typed::new(crate::tools::task::CheckTaskTool::new(tasks.clone())));
+                    tool_registry.register(crate::tools::typed::Typed::new(crate::tools::task::CancelTaskTool::new(tasks)));
+                }
+                Err(e) => warn!("后台任务初始化失败: {}，继续运行", e),
+            }
+        }
+
         // 如果提供了 session_id 则使用，否则生成新的 UUID
         let session_id = session_id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
@@ -64,72 +301,302 @@ impl Agent {
         // 如果有内存系统，加载之前的对话
         if let Some(ref mem) = memory {
             let history = mem.get_conversation(&session_id, config.agent.max_context as i64).await?;
-            for msg in history {
-                // DeepSeek API 要求 tool 消息必须有 tool_call_id，跳过无效的 tool 消息
-                if msg.role == "tool" && msg.tool_call_id.is_none() {
-                    continue;
-                }
-                
-                let role = match msg.role.as_str() {
-                    "user" => Role::User,
-                    "assistant" => Role::Assistant,
-                    "tool" => Role::Tool,
-                    _ => Role::System,
-                };
-                messages.push(Message {
-                    role,
-                    content: msg.content,
-                    tool_calls: msg.tool_calls.and_then(|t| serde_json::from_str(&t).ok()),
-                    tool_call_id: msg.tool_call_id,
-                });
-            }
+            messages.extend(restore_history(history));
         }
 
         Ok(Self {
             config,
             llm_manager,
+            router,
             tool_registry,
             memory,
-            session_id: Mutex::new(session_id),
-            context: Mutex::new(AgentContext {
-                messages,
-                total_tokens: 0,
+            sessions,
+            default_session: Arc::new(ActiveSession {
+                session_id: Mutex::new(session_id),
+                context: Mutex::new(AgentContext {
+                    messages,
+                    total_tokens: 0,
+                }),
             }),
+            session_contexts: std::sync::Mutex::new(HashMap::new()),
+            incognito: std::sync::Mutex::new(HashMap::new()),
+            model_override: std::sync::RwLock::new(None),
+            scheduler: None,
+            last_turn: std::sync::Mutex::new(HashMap::new()),
+            budget,
+            personas: std::sync::Mutex::new(HashMap::new()),
+            onboarding_turns: std::sync::Mutex::new(HashMap::new()),
+            downgraded: std::sync::Mutex::new(HashSet::new()),
+            titling: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            provider_health: None,
         })
     }
 
+    /// 启用定时提醒工具
+    pub fn with_scheduler(mut self, scheduler: Arc<crate::cron::Scheduler>) -> Self {
+        let mut timezone = crate::tools::reminder::UserTimezone::new(crate::cron::reminder::resolve_timezone(
+            self.config.agent.timezone.as_deref(),
+        ));
+        if let Some(ref memory) = self.memory {
+            timezone = timezone.with_memory(memory.clone());
+        }
+        self.tool_registry
+            .register(crate::tools::reminder::ScheduleReminderTool::new(scheduler.clone(), timezone.clone()));
+        self.tool_registry
+            .register(crate::tools::reminder::ListRemindersTool::new(scheduler.clone(), timezone.clone()));
+        self.tool_registry
+            .register(crate::tools::reminder::CancelReminderTool::new(scheduler.clone(), timezone));
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 注册（或替换）提供商，如评测时使用带预设回复的模拟提供商
+    pub fn with_llm_provider(mut self, name: &str, provider: Arc<dyn LlmProvider>) -> Self {
+        self.llm_manager.register(name, provider);
+        self
+    }
+
+    /// 按探测到的提供商可用状态故障切换
+    pub fn with_provider_health(mut self, health: Arc<ProviderHealth>) -> Self {
+        self.provider_health = Some(health);
+        self
+    }
+
+    /// 使用共享的会话管理器（如 gateway 中与空闲清理共用）
+    pub fn with_sessions(mut self, sessions: Arc<SessionManager>) -> Self {
+        self.tool_registry
+            .register(crate::tools::typed::Typed::new(crate::tools::pin::PinContextTool::new(sessions.clone())));
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// 工具注册表句柄，可在运行期间注册/注销工具
+    pub fn tools(&self) -> ToolRegistry {
+        self.tool_registry.clone()
+    }
+
     /// 发送消息给 Agent
     pub async fn chat(&self,
         content: impl Into<String>,
+    ) -> Result<AgentResponse> {
+        self.chat_with_options(content, ChatOptions::default()).await
+    }
+
+    /// 发送消息，可设置截止时间、取消令牌和模型覆盖
+    ///
+    /// 超时或取消时返回 [`TurnAborted`] 错误，并撤销本轮写入上下文和对话历史的消息
+    pub async fn chat_with_options(&self,
+        content: impl Into<String>,
+        options: ChatOptions,
     ) -> Result<AgentResponse> {
         let content = content.into();
-        info!("用户: {}", content);
+        let session_id = self.active().session_id.lock().await.clone();
+        if self.is_incognito_session(&session_id) {
+            info!("用户: [无痕模式，{} 字]", content.chars().count());
+        } else {
+            info!("用户: {}", content);
+        }
+
+        let checkpoint = self.checkpoint().await;
+        self.last_turn
+            .lock()
+            .unwrap()
+            .insert(checkpoint.0.clone(), checkpoint.2);
+        let origin = options.origin();
 
         // 添加用户消息到上下文
         {
-            let mut ctx = self.context.lock().await;
+            let active = self.active();
+            let mut ctx = active.context.lock().await;
             ctx.messages.push(Message::user(content.clone()));
             
             // 保存到内存
-            if let Some(ref memory) = self.memory {
-                let session_id = self.session_id.lock().await.clone();
+            if let Some(memory) = self.memory_for(&session_id) {
                 let _ = memory.add_message(&session_id, "user", &content, None).await;
             }
         }
 
-        // 执行对话循环
-        let response = self.run_loop().await?;
+        // 执行对话循环，失败时已消耗的令牌和工具调用同样计入会话
+        let mut turn = TurnStats::default();
+        let deadline = options.deadline.or_else(|| {
+            let secs = self.config.agent.turn_timeout_secs;
+            (secs > 0).then(|| Instant::now() + Duration::from_secs(secs))
+        });
+        let cancel_token = options.cancel_token.clone().unwrap_or_default();
+
+        let result = tokio::select! {
+            // 提供商并发受限时按会话轮流排队
+            result = with_request_session(
+                session_id.clone(),
+                self.run_loop(origin, &content, &options, &mut turn),
+            ) => result,
+            _ = cancel_token.cancelled() => Err(TurnAborted::Cancelled.into()),
+            _ = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => Err(TurnAborted::DeadlineExceeded.into()),
+        };
+        self.record_turn(origin.map(|o| o.channel), &turn).await;
+        let result = result.map(|response| AgentResponse {
+            tokens: turn.tokens,
+            ..response
+        });
+
+        if let Err(ref e) = result {
+            if let Some(aborted) = e.downcast_ref::<TurnAborted>() {
+                warn!("{}，撤销本轮消息", aborted);
+                self.rollback(checkpoint).await;
+            }
+        }
+
+        result
+    }
 
-        Ok(response)
+    /// 记录上下文和对话历史的当前位置
+    async fn checkpoint(&self) -> (String, usize, u64) {
+        let session_id = self.active().session_id.lock().await.clone();
+        let context_len = self.active().context.lock().await.messages.len();
+        let history_len = match self.memory {
+            Some(ref memory) => memory.conversation_len(&session_id).await,
+            None => 0,
+        };
+        (session_id, context_len, history_len)
+    }
+
+    /// 撤销检查点之后加入的消息
+    async fn rollback(&self, (session_id, context_len, history_len): (String, usize, u64)) {
+        self.active().context.lock().await.messages.truncate(context_len);
+        if let Some(ref memory) = self.memory {
+            if let Err(e) = memory.truncate_conversation(&session_id, history_len).await {
+                warn!("撤销对话历史失败: {}", e);
+            }
+        }
+    }
+
+    /// 撤销当前会话的最后一轮对话（最后一条用户消息及其后的回复），返回被撤销的用户消息
+    ///
+    /// 用于用户编辑最后一条消息后重新生成回复
+    pub async fn undo_last_turn(&self) -> Option<String> {
+        let session_id = self.active().session_id.lock().await.clone();
+        let removed = {
+            let active = self.active();
+            let mut ctx = active.context.lock().await;
+            let index = ctx.messages.iter().rposition(|m| m.role == Role::User)?;
+            let content = ctx.messages[index].content.clone();
+            ctx.messages.truncate(index);
+            content
+        };
+
+        let history_len = self.last_turn.lock().unwrap().remove(&session_id);
+        if let (Some(memory), Some(len)) = (self.memory_for(&session_id), history_len) {
+            if let Err(e) = memory.truncate_conversation(&session_id, len).await {
+                warn!("撤销对话历史失败: {}", e);
+            }
+        }
+        Some(removed)
+    }
+
+    /// 解析模型覆盖："provider/model" 指定提供商，否则使用默认提供商
+    fn resolve_model_override(&self, spec: &str) -> Result<(Arc<dyn LlmProvider>, String, String)> {
+        if let Some((name, model)) = spec.split_once('/') {
+            if let Ok(provider) = self.llm_manager.get_provider(Some(name)) {
+                return Ok((provider, name.to_string(), model.to_string()));
+            }
+        }
+        Ok((
+            self.llm_manager.default_provider()?,
+            self.config.agent.default_provider.clone(),
+            spec.to_string(),
+        ))
+    }
+
+    /// 将本轮统计写入会话
+    async fn record_turn(&self, channel: Option<&str>, turn: &TurnStats) {
+        let Some(ref sessions) = self.sessions else {
+            return;
+        };
+
+        let mut session_id = self.active().session_id.lock().await.clone();
+        let persona = match self.persona_name(&session_id).await {
+            Some(name) => name,
+            None => DEFAULT_PERSONA.to_string(),
+        };
+        let channel = channel.unwrap_or("cli");
+        // 通道会话 ID 形如 telegram:123
+        let mut channel_id = session_id
+            .strip_prefix(&format!("{}:", channel))
+            .unwrap_or(&session_id)
+            .to_string();
+        // 无痕会话只计入按通道汇总的统计
+        let incognito = self.is_incognito_session(&session_id);
+        if incognito {
+            session_id = format!("incognito:{}", channel);
+            channel_id = "incognito".to_string();
+        }
+
+        let mut untitled = None;
+        let result = async {
+            let session = sessions.get_or_create(&session_id, channel, &channel_id).await?;
+            sessions
+                .update(&session, |s| {
+                    s.record_persona(&persona, turn.tokens);
+                    s.record_message(true);
+                    for _ in 0..turn.tool_calls {
+                        s.record_tool_call();
+                    }
+                    if turn.replied {
+                        s.record_message(false);
+                    }
+                    s.record_tokens(turn.tokens);
+                    if s.title().is_none() {
+                        untitled = Some(s.stats.user_message_count);
+                    }
+                })
+                .await
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("记录会话统计失败: {}", e);
+        }
+        if let (Some(user_messages), false) = (untitled, incognito) {
+            self.schedule_title(&session_id, user_messages).await;
+        }
     }
 
     /// 核心对话循环
     async fn run_loop(&self,
+        origin: Option<MessageOrigin<'_>>,
+        text: &str,
+        options: &ChatOptions,
+        turn: &mut TurnStats,
     ) -> Result<AgentResponse> {
-        let provider = self.llm_manager.default_provider()?;
+        let channel = origin.map(|o| o.channel);
+        let default_provider = self.llm_manager.default_provider()?;
         let max_iterations = 10;
         let mut iterations = 0;
-        let session_id = self.session_id.lock().await.clone();
+        let session_id = self.active().session_id.lock().await.clone();
+        let incognito = self.is_incognito_session(&session_id);
+        let persona = self.active_persona(&session_id).await;
+        // 预算按 通道:聊天 ID 统计每个用户
+        let user = origin.map(|o| format!("{}:{}", o.channel, o.chat_id));
+        let user_context = self
+            .user_context(user.as_deref().unwrap_or(&session_id))
+            .await;
+        // 无痕会话的用量只计入汇总，不按用户记录
+        let budget_user = user.as_deref().filter(|_| !incognito);
+        // 单次请求、角色、运行时切换指定的模型不降级
+        let explicit_model = options.model_override.is_some()
+            || persona.as_ref().is_some_and(|p| p.model.is_some())
+            || self.model_override.read().unwrap().is_some();
+        let (downgrade, downgrade_notice) = if explicit_model {
+            (None, None)
+        } else {
+            self.downgrade_model(&session_id).await
+        };
+        let mut tool_trace: Vec<ToolTrace> = Vec::new();
 
         loop {
             iterations += 1;
@@ -137,14 +604,82 @@ impl Agent {
                 return Err(anyhow!("超过最大迭代次数"));
             }
 
+            // 选择模型（启用路由时按规则选择）
+            let mut provider = default_provider.clone();
+            let mut provider_name = self.config.agent.default_provider.clone();
+            let mut model = self.config.agent.default_model.clone();
+            if let Some(ref router) = self.router {
+                let route = router.route(
+                    &RouteContext {
+                        text,
+                        channel,
+                        has_tool_results: iterations > 1,
+                    },
+                    &model,
+                );
+                if let Some(ref name) = route.provider {
+                    match self.llm_manager.get_provider(Some(name)) {
+                        Ok(p) => {
+                            provider = p;
+                            provider_name = name.clone();
+                        }
+                        Err(e) => warn!("路由 {} 的提供商不可用: {}，使用默认提供商", route.name, e),
+                    }
+                }
+                debug!("路由命中: {} -> {}", route.name, route.model);
+                model = route.model;
+            }
+
+            // 单次请求指定的模型、角色的模型、运行时切换的模型、降级模型优先于路由
+            let model_override = options
+                .model_override
+                .clone()
+                .or_else(|| persona.as_ref().and_then(|p| p.model.clone()))
+                .or_else(|| self.model_override.read().unwrap().clone())
+                .or_else(|| downgrade.clone());
+            if let Some(ref spec) = model_override {
+                (provider, provider_name, model) = self.resolve_model_override(spec)?;
+            } else if let Some((name, p, m)) = self.failover(&provider_name) {
+                warn!("提供商 {} 当前不可用，改用 {}/{}", provider_name, name, m);
+                (provider, provider_name, model) = (p, name, m);
+            }
+
             // 准备请求
-            let tools = self.tool_registry.to_llm_tools();
+            let mut tools = self.tool_registry.to_llm_tools();
+            if let Some(ref persona) = persona {
+                tools.retain(|t| persona.allows_tool(&t.name));
+            }
+            let system_prompt = self
+                .system_prompt(channel, &session_id, persona.as_ref(), &user_context, text)
+                .await;
             let request = {
-                let ctx = self.context.lock().await;
-                let mut req = ChatRequest::new(
-                    self.config.agent.default_model.clone(),
-                    ctx.messages.clone(),
-                );
+                let active = self.active();
+                let ctx = active.context.lock().await;
+                let mut messages = ctx.messages.clone();
+                // 每次请求重新组合系统提示词，置顶内容等不受上下文裁剪影响
+                match messages.first_mut() {
+                    Some(first) if first.role == Role::System => first.content = system_prompt,
+                    _ => messages.insert(0, Message::system(system_prompt)),
+                }
+                // 生成参数优先级：单次请求 > 角色 > Agent 配置 > 提供商配置
+                let mut req = ChatRequest::new(model, messages);
+                if let Some(provider_config) = self.config.llm.provider(&provider_name) {
+                    req = req.with_params(&provider_config.generation);
+                }
+                req = req.with_params(&self.config.agent.generation);
+                if let Some(ref persona) = persona {
+                    if let Some(temperature) = persona.temperature {
+                        req.temperature = Some(temperature);
+                    }
+                    // 本轮还没有工具结果时模型在决定是否调用工具，之后在组织回答
+                    let tool_phase = !tools.is_empty() && iterations == 1;
+                    if let Some(params) = persona.phase_params(tool_phase) {
+                        req = req.with_params(params);
+                    }
+                }
+                if let Some(ref params) = options.params {
+                    req = req.with_params(params);
+                }
                 if !tools.is_empty() {
                     req = req.with_tools(tools);
                 }
@@ -152,11 +687,26 @@ impl Agent {
             };
 
             debug!("发送 LLM 请求，使用模型: {}", request.model);
+            let best_of = options.best_of.unwrap_or(1);
+            let sample_request = (best_of > 1).then(|| request.clone());
+
+            if let Some(ref budget) = self.budget {
+                budget.check(Some(&provider_name), budget_user).await?;
+            }
+            let model = request.model.clone();
 
             // 调用 LLM
             let llm_response = provider.chat(request).await?;
-            
-            let message = llm_response.message;
+            if let Some(ref usage) = llm_response.usage {
+                turn.tokens += usage.total_tokens as u64;
+                if let Some(ref budget) = self.budget {
+                    if let Err(e) = budget.record(&provider_name, &model, budget_user, usage).await {
+                        warn!("记录用量失败: {}", e);
+                    }
+                }
+            }
+
+            let mut message = llm_response.message;
             debug!("LLM 响应: {:?}", message);
 
             // 检查是否有工具调用
@@ -164,46 +714,69 @@ impl Agent {
                 if !tool_calls.is_empty() {
                     // 添加助手消息（带工具调用）到上下文
                     {
-                        let mut ctx = self.context.lock().await;
+                        let active = self.active();
+                        let mut ctx = active.context.lock().await;
                         ctx.messages.push(message.clone());
                     }
 
-                    // 保存到内存
-                    if let Some(ref memory) = self.memory {
-                        // 获取第一个 tool_call 的 id
-                        let tool_call_id = tool_calls.first()
-                            .map(|c| c.id.as_str());
-                        let _ = memory.add_message(
+                    // 保存到内存（连同工具调用，重新加载时才能与工具结果配对）
+                    if let Some(memory) = self.memory_for(&session_id) {
+                        let calls = serde_json::to_string(tool_calls).ok();
+                        let _ = memory.add_message_with_tool_calls(
                             &session_id,
                             "assistant",
                             &message.content,
-                            tool_call_id.as_deref(),
+                            calls.as_deref(),
+                            None,
                         ).await;
                     }
 
                     // 执行工具
-                    let tool_ctx = ToolContext::new(self.config.tools.clone());
+                    let tool_ctx = ToolContext::new(self.config.tools.clone())
+                        .with_session(session_id.clone())
+                        .with_origin(channel, origin.map(|o| o.chat_id))
+                        .with_incognito(incognito);
                     
                     for tool_call in tool_calls {
                         let tool_name = &tool_call.function.name;
-                        let tool_args: Value = serde_json::from_str(&tool_call.function.arguments)?;
-
-                        info!("执行工具: {} 参数: {}", tool_name, tool_call.function.arguments);
-
-                        let result = self.tool_registry.execute(
-                            tool_name,
-                            tool_args,
-                            &tool_ctx,
-                        ).await;
+                        if incognito {
+                            info!("执行工具: {}", tool_name);
+                        } else {
+                            info!("执行工具: {} 参数: {}", tool_name, tool_call.function.arguments);
+                        }
+                        turn.tool_calls += 1;
+                        let started = Instant::now();
 
-                        let result_str = match result {
-                            Ok(r) => r.to_string(),
-                            Err(e) => format!("工具执行错误: {}", e),
+                        // 参数不是合法 JSON 时把错误返回给模型，由其修正后重试
+                        let allowed = persona.as_ref().is_none_or(|p| p.allows_tool(tool_name));
+                        let parsed = serde_json::from_str::<Value>(&tool_call.function.arguments);
+                        let arguments = parsed.as_ref().cloned().unwrap_or_default();
+                        let (result_str, success) = match parsed {
+                            Ok(_) if !allowed => (format!("工具 {} 在当前角色中不可用", tool_name), false),
+                            Ok(tool_args) => match self.tool_registry.execute(
+                                tool_name,
+                                tool_args,
+                                &tool_ctx,
+                            ).await {
+                                Ok(r) => (r.to_string(), r.success),
+                                Err(e) => (format!("工具执行错误: {}", e), false),
+                            },
+                            Err(e) => (format!("工具参数不是合法的 JSON: {}，请修正后重试", e), false),
                         };
+                        let duration = started.elapsed();
+                        debug!("工具 {} 执行完成，耗时 {:?}", tool_name, duration);
+                        tool_trace.push(ToolTrace {
+                            name: tool_name.clone(),
+                            arguments,
+                            output: result_str.clone(),
+                            success,
+                            duration,
+                        });
 
                         // 添加工具结果到上下文
                         {
-                            let mut ctx = self.context.lock().await;
+                            let active = self.active();
+                            let mut ctx = active.context.lock().await;
                             ctx.messages.push(Message::tool_result(
                                 &tool_call.id,
                                 result_str.clone(),
@@ -211,7 +784,7 @@ impl Agent {
                         }
 
                         // 保存到内存
-                        if let Some(ref memory) = self.memory {
+                        if let Some(memory) = self.memory_for(&session_id) {
                             let _ = memory.add_message(
                                 &session_id,
                                 "tool",
@@ -226,9 +799,21 @@ impl Agent {
                 }
             }
 
-            // 没有工具调用，返回最终结果
+            // 没有工具调用，多次采样时选出最终回答
+            if let Some(request) = sample_request {
+                let target = bestof::SampleTarget {
+                    provider: &provider,
+                    provider_name: &provider_name,
+                    user: budget_user,
+                };
+                let first = std::mem::take(&mut message.content);
+                message.content = self.best_of(text, request, first, best_of, &target, turn).await;
+            }
+
+            // 返回最终结果
             {
-                let mut ctx = self.context.lock().await;
+                let active = self.active();
+                let mut ctx = active.context.lock().await;
                 ctx.messages.push(message.clone());
                 
                 // 清理上下文，保留最近的 N 条
@@ -242,12 +827,16 @@ impl Agent {
                             ctx.messages.remove(0);
                         }
                     }
+                    // 工具调用组被截断时丢弃开头的 tool 消息，否则请求的消息序列无效
+                    while ctx.messages.len() > 1 && ctx.messages[0].role == Role::Tool {
+                        ctx.messages.remove(0);
+                    }
                     ctx.messages.insert(0, system_msg);
                 }
             }
 
             // 保存到内存
-            if let Some(ref memory) = self.memory {
+            if let Some(memory) = self.memory_for(&session_id) {
                 let _ = memory.add_message(
                     &session_id,
                     "assistant",
@@ -256,81 +845,635 @@ impl Agent {
                 ).await;
             }
 
+            turn.replied = true;
+            let mut content = match downgrade_notice {
+                Some(notice) => format!("{}\n\n{}", notice, message.content),
+                None => message.content,
+            };
+            if self.config.citations.enabled {
+                let sources = citations::collect_sources(&tool_trace, self.config.citations.max_sources);
+                content = citations::append_sources(&content, &sources, channel);
+            }
             return Ok(AgentResponse {
-                content: message.content,
+                content,
                 model: llm_response.model,
+                tool_trace,
+                tokens: 0,
             });
         }
     }
 
+    /// 各路由的命中次数（未启用路由时返回 None）
+    pub fn route_stats(&self) -> Option<std::collections::HashMap<String, u64>> {
+        self.router.as_ref().map(|r| r.stats())
+    }
+
+    /// 组合分层系统提示词：基础（或角色）、通道、用户资料、置顶内容、会话指令
+    async fn system_prompt(
+        &self,
+        channel: Option<&str>,
+        session_id: &str,
+        persona: Option<&PersonaConfig>,
+        user: &UserContext,
+        text: &str,
+    ) -> String {
+        let agent = &self.config.agent;
+        let base = persona
+            .and_then(|p| p.system_prompt.as_deref())
+            .unwrap_or(&agent.system_prompt);
+        let profile = [agent.user_profile.as_deref().unwrap_or_default(), &user.profile]
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut builder = PromptBuilder::new(base, agent.prompt_budget_chars)
+            .channel(channel.and_then(|c| agent.channel_prompts.get(c)).map(String::as_str))
+            .user_profile(Some(&profile))
+            .onboarding(user.onboarding.then_some(self.config.onboarding.template.as_str()));
+        if agent.match_reply_language {
+            let hint = language::detect(text).map(language::reply_hint);
+            builder = builder.reply_language(hint.as_deref());
+        }
+
+        if let (true, Some(memory)) = (self.config.memory.inject_today_notes, &self.memory) {
+            match memory.recent_notes(self.config.memory.inject_notes_max_chars).await {
+                Ok(notes) => builder = builder.notes(Some(&notes)),
+                Err(e) => warn!("读取近期笔记失败: {}", e),
+            }
+        }
+
+        if let Some(ref sessions) = self.sessions {
+            match sessions.pins(session_id).await {
+                Ok(pins) => builder = builder.pinned(&pins),
+                Err(e) => warn!("读取置顶内容失败: {}", e),
+            }
+            match sessions.instructions(session_id).await {
+                Ok(instructions) => builder = builder.instructions(instructions.as_deref()),
+                Err(e) => warn!("读取会话指令失败: {}", e),
+            }
+        }
+
+        builder.build()
+    }
+
+    /// 当前用户的资料，以及本轮是否需要引导（每次调用计为一轮）
+    async fn user_context(&self, user: &str) -> UserContext {
+        let Some(ref memory) = self.memory else {
+            return UserContext::default();
+        };
+        let content = match memory.read_user_profile(user).await {
+            Ok(content) => content.unwrap_or_default(),
+            Err(e) => {
+                warn!("读取用户资料失败: {}", e);
+                return UserContext::default();
+            }
+        };
+
+        let state_prefix = format!("- **{}**:", ONBOARDING_KEY);
+        let mut onboarding = content
+            .lines()
+            .filter_map(|l| l.strip_prefix(&state_prefix))
+            .any(|state| state.trim() == ONBOARDING_PENDING);
+        if onboarding {
+            let turns = {
+                let mut turns = self.onboarding_turns.lock().unwrap();
+                let count = turns.entry(user.to_string()).or_default();
+                *count += 1;
+                *count
+            };
+            if turns > self.config.onboarding.max_turns {
+                info!("用户 {} 的引导已达到 {} 轮，结束引导", user, self.config.onboarding.max_turns);
+                self.onboarding_turns.lock().unwrap().remove(user);
+                if let Err(e) = memory.save_user_fact(user, ONBOARDING_KEY, ONBOARDING_DONE).await {
+                    warn!("保存引导状态失败: {}", e);
+                }
+                onboarding = false;
+            }
+        }
+
+        let profile = content
+            .lines()
+            .filter(|l| l.starts_with("- **") && !l.starts_with(&state_prefix))
+            .collect::<Vec<_>>()
+            .join("\n");
+        UserContext { profile, onboarding }
+    }
+
+    /// 首次对话时开始新用户引导，返回是否开始引导
+    ///
+    /// 当前会话没有对话历史且用户从未引导过时视为首次对话；无痕会话不引导
+    pub async fn start_onboarding_if_new(&self, user: &str) -> Result<bool> {
+        if !self.config.onboarding.enabled {
+            return Ok(false);
+        }
+        let session_id = self.active().session_id.lock().await.clone();
+        let Some(memory) = self.memory_for(&session_id) else {
+            return Ok(false);
+        };
+        if memory.has_conversation(&session_id) || memory.user_fact(user, ONBOARDING_KEY).await?.is_some() {
+            return Ok(false);
+        }
+        memory.save_user_fact(user, ONBOARDING_KEY, ONBOARDING_PENDING).await?;
+        info!("首次对话的用户: {}，开始新用户引导", user);
+        Ok(true)
+    }
+
+    /// 设置当前会话的指令（/instruct），None 表示清除
+    pub async fn set_instructions(&self, text: Option<&str>) -> Result<()> {
+        let sessions = self
+            .sessions
+            .as_ref()
+            .ok_or_else(|| anyhow!("未启用会话存储，无法设置会话指令"))?;
+        let session_id = self.active().session_id.lock().await.clone();
+        let text = text.map(str::trim).filter(|t| !t.is_empty());
+        sessions.set_instructions(&session_id, text).await
+    }
+
+    /// 置顶内容，未指定时置顶最近一条助手回复，返回置顶总数
+    pub async fn pin(&self, text: Option<&str>) -> Result<usize> {
+        let sessions = self
+            .sessions
+            .as_ref()
+            .ok_or_else(|| anyhow!("未启用会话存储，无法置顶"))?;
+
+        let text = match text.map(str::trim).filter(|t| !t.is_empty()) {
+            Some(t) => t.to_string(),
+            None => {
+                let active = self.active();
+                let ctx = active.context.lock().await;
+                ctx.messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.role == Role::Assistant && !m.content.trim().is_empty())
+                    .map(|m| m.content.clone())
+                    .ok_or_else(|| anyhow!("没有可置顶的回复"))?
+            }
+        };
+
+        let session_id = self.active().session_id.lock().await.clone();
+        sessions.add_pin(&session_id, &text).await
+    }
+
+    /// 按序号（从 1 开始）取消置顶
+    pub async fn unpin(&self, index: usize) -> Result<Option<String>> {
+        let sessions = self
+            .sessions
+            .as_ref()
+            .ok_or_else(|| anyhow!("未启用会话存储，无法置顶"))?;
+        let session_id = self.active().session_id.lock().await.clone();
+        sessions.remove_pin(&session_id, index).await
+    }
+
+    /// 当前会话的置顶内容
+    pub async fn pins(&self) -> Vec<String> {
+        let Some(ref sessions) = self.sessions else {
+            return Vec::new();
+        };
+        let session_id = self.active().session_id.lock().await.clone();
+        sessions.pins(&session_id).await.unwrap_or_default()
+    }
+
+    /// 某天的日常笔记和对话摘录，`session_id` 为提问的会话，可读的会话与 tools.conversation_access 相同
+    /// （无痕会话或未启用内存系统时返回 None）
+    pub async fn recall_day(&self, date: chrono::NaiveDate, session_id: &str) -> Result<Option<DayRecall>> {
+        let Some(memory) = self.memory_for(session_id) else {
+            return Ok(None);
+        };
+        let access = self.config.tools.conversation_access;
+        let recall = memory
+            .recall_day(date, |id| match access {
+                ConversationAccess::All => true,
+                ConversationAccess::Own => id == session_id,
+                ConversationAccess::Off => false,
+            })
+            .await?;
+        Ok(Some(recall))
+    }
+
+    /// 当前会话的累计统计（未启用会话统计时返回 None）
+    pub async fn session_stats(&self) -> Option<SessionStats> {
+        let session_id = self.active().session_id.lock().await.clone();
+        self.stats_for(&session_id).await
+    }
+
+    async fn stats_for(&self, session_id: &str) -> Option<SessionStats> {
+        let sessions = self.sessions.as_ref()?;
+        if let Some(session) = sessions.get_session(session_id).await {
+            return Some(session.read().await.stats.clone());
+        }
+        sessions
+            .load_session(session_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|s| s.stats)
+    }
+
+    /// 会话累计使用的令牌数
+    async fn session_tokens(&self, session_id: &str) -> Option<u64> {
+        self.stats_for(session_id).await.map(|s| s.total_tokens)
+    }
+
+    /// 会话是否处于无痕模式：显式设置优先，否则看通道是否在 `channel.incognito_channels` 中
+    pub fn is_incognito_session(&self, session_id: &str) -> bool {
+        if let Some(&enabled) = self.incognito.lock().unwrap().get(session_id) {
+            return enabled;
+        }
+        session_id.split_once(':').is_some_and(|(channel, _)| {
+            self.config.channel.incognito_channels.iter().any(|c| c == channel)
+        })
+    }
+
+    /// 可写入对话历史的记忆存储，无痕会话返回 None
+    fn memory_for(&self, session_id: &str) -> Option<&Arc<MemoryStore>> {
+        self.memory
+            .as_ref()
+            .filter(|_| !self.is_incognito_session(session_id))
+    }
+
+    /// 当前会话是否处于无痕模式
+    pub async fn is_incognito(&self) -> bool {
+        let session_id = self.active().session_id.lock().await.clone();
+        self.is_incognito_session(&session_id)
+    }
+
+    /// 开启或关闭当前会话的无痕模式（/incognito）
+    ///
+    /// 无痕模式下消息不写入对话历史，用量只计入按通道汇总的统计。
+    /// 设置只保存在内存中，重启后恢复通道默认值
+    pub async fn set_incognito(&self, enabled: bool) {
+        let session_id = self.active().session_id.lock().await.clone();
+        self.incognito.lock().unwrap().insert(session_id, enabled);
+    }
+
+    /// 会话的角色名，默认角色返回 None
+    async fn persona_name(&self, session_id: &str) -> Option<String> {
+        let switched = self.personas.lock().unwrap().get(session_id).cloned();
+        let name = match switched {
+            Some(name) => name,
+            // 重启后从会话元数据恢复
+            None => {
+                let sessions = self.sessions.as_ref()?;
+                let stored = match sessions.get_session(session_id).await {
+                    Some(session) => session.read().await.metadata.properties.get(PERSONA_PROPERTY).cloned(),
+                    None => sessions
+                        .load_session(session_id)
+                        .await
+                        .ok()
+                        .flatten()
+                        .and_then(|s| s.metadata.properties.get(PERSONA_PROPERTY).cloned()),
+                };
+                stored?
+            }
+        };
+        Some(name).filter(|n| !n.is_empty() && n != DEFAULT_PERSONA && self.config.personas.contains_key(n))
+    }
+
+    /// 会话的角色配置，默认角色返回 None
+    async fn active_persona(&self, session_id: &str) -> Option<PersonaConfig> {
+        let name = self.persona_name(session_id).await?;
+        self.config.personas.get(&name).cloned()
+    }
+
+    /// 当前会话的角色名，默认角色返回 None
+    pub async fn persona(&self) -> Option<String> {
+        let session_id = self.active().session_id.lock().await.clone();
+        self.persona_name(&session_id).await
+    }
+
+    /// 已配置的角色（名称, 简介），按名称排序
+    pub fn personas(&self) -> Vec<(String, Option<String>)> {
+        let mut personas: Vec<_> = self
+            .config
+            .personas
+            .iter()
+            .map(|(name, p)| (name.clone(), p.description.clone()))
+            .collect();
+        personas.sort();
+        personas
+    }
+
+    /// 切换当前会话的角色（/persona），None 或 "default" 恢复默认角色
+    pub async fn set_persona(&self, name: Option<&str>) -> Result<()> {
+        let name = name.map(str::trim).filter(|n| !n.is_empty() && *n != DEFAULT_PERSONA);
+        if let Some(name) = name {
+            if !self.config.personas.contains_key(name) {
+                let available: Vec<String> = self.personas().into_iter().map(|(n, _)| n).collect();
+                return Err(anyhow!("角色 '{}' 不存在，可用角色: {}", name, available.join(", ")));
+            }
+        }
+
+        let session_id = self.active().session_id.lock().await.clone();
+        let name = name.unwrap_or(DEFAULT_PERSONA).to_string();
+        self.personas
+            .lock()
+            .unwrap()
+            .insert(session_id.clone(), name.clone());
+
+        // 已有会话立即写入元数据，新会话在本轮结束时记录
+        if let Some(ref sessions) = self.sessions {
+            if let Some(session) = sessions.get_session(&session_id).await {
+                sessions
+                    .update(&session, |s| {
+                        s.metadata.properties.insert(PERSONA_PROPERTY.to_string(), name);
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
     /// 获取会话 ID
     pub async fn session_id(&self) -> String {
-        self.session_id.lock().await.clone()
+        self.active().session_id.lock().await.clone()
+    }
+
+    /// 当前使用的提供商和模型（provider/model），运行时切换过时返回切换后的模型
+    pub fn model_name(&self) -> String {
+        if let Some(ref spec) = *self.model_override.read().unwrap() {
+            if let Ok((_, provider, model)) = self.resolve_model_override(spec) {
+                return format!("{}/{}", provider, model);
+            }
+        }
+        format!("{}/{}", self.config.agent.default_provider, self.config.agent.default_model)
     }
+
+    /// 切换模型（"provider/model" 或仅模型名），None 恢复默认，返回切换后的模型
+    pub fn set_model(&self, spec: Option<&str>) -> Result<String> {
+        let spec = spec.map(str::trim).filter(|s| !s.is_empty());
+        if let Some(spec) = spec {
+            self.resolve_model_override(spec)?;
+        }
+        *self.model_override.write().unwrap() = spec.map(str::to_string);
+        Ok(self.model_name())
+    }
+
+    /// 切换到提供商的默认模型，返回切换后的模型
+    pub fn set_provider(&self, name: &str) -> Result<String> {
+        self.llm_manager.get_provider(Some(name))?;
+        let model = self
+            .config
+            .llm
+            .provider(name)
+            .and_then(|p| p.default_model.clone())
+            .ok_or_else(|| anyhow!("提供商 '{}' 未配置默认模型，请使用 /model {}/<模型>", name, name))?;
+        self.set_model(Some(&format!("{}/{}", name, model)))
+    }
+
+    /// 提供商不可用时第一个可用的备用提供商（提供商名、提供商、模型）
+    fn failover(&self, provider_name: &str) -> Option<(String, Arc<dyn LlmProvider>, String)> {
+        let health = self.provider_health.as_ref()?;
+        health.failover(provider_name).into_iter().find_map(|name| {
+            let provider = self.llm_manager.get_provider(Some(name)).ok()?;
+            let model = crate::llm::probe::probe_model(&self.config, name)?;
+            Some((name.to_string(), provider, model))
+        })
+    }
+
+    /// 已注册的提供商实例（如用于可用性探测）
+    pub fn llm_providers(&self) -> Vec<(String, Arc<dyn LlmProvider>)> {
+        self.providers()
+            .into_iter()
+            .filter_map(|name| Some((name.clone(), self.llm_manager.get_provider(Some(&name)).ok()?)))
+            .collect()
+    }
+
+    /// 可用的提供商
+    pub fn providers(&self) -> Vec<String> {
+        let mut providers: Vec<String> = self
+            .llm_manager
+            .list_providers()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        providers.sort();
+        providers
+    }
+
+    /// 会话管理器（未启用会话统计时返回 None）
+    pub fn sessions(&self) -> Option<&Arc<SessionManager>> {
+        self.sessions.as_ref()
+    }
+
+    /// 记忆存储（未设置工作目录时返回 None）
+    pub fn memory(&self) -> Option<&Arc<MemoryStore>> {
+        self.memory.as_ref()
+    }
+
+    /// 用量预算（未配置 `[budget]` 时返回 None）
+    pub fn budget(&self) -> Option<&Arc<Budget>> {
+        self.budget.as_ref()
+    }
+
+    /// 任务调度器（未启用时返回 None）
+    pub fn scheduler(&self) -> Option<&Arc<crate::cron::Scheduler>> {
+        self.scheduler.as_ref()
+    }
+
+    /// 数据库不可用、正以内存模式运行的存储（"jobs"、"sessions"）
+    pub async fn degraded_stores(&self) -> Vec<&'static str> {
+        let mut stores = Vec::new();
+        if let Some(ref scheduler) = self.scheduler {
+            if scheduler.is_degraded().await {
+                stores.push("jobs");
+            }
+        }
+        if self.sessions.as_ref().is_some_and(|s| s.is_degraded()) {
+            stores.push("sessions");
+        }
+        stores
+    }
+
+    /// 定时任务列表（未启用调度器时为空），`all` 为 true 时包括已完成的一次性任务
+    pub async fn jobs(&self, all: bool) -> Vec<crate::cron::Job> {
+        match self.scheduler {
+            Some(ref scheduler) if all => scheduler.list_all_jobs().await,
+            Some(ref scheduler) => scheduler.list_jobs().await,
+            None => Vec::new(),
+        }
+    }
+
     pub async fn context_length(&self) -> usize {
-        self.context.lock().await.messages.len()
+        self.active().context.lock().await.messages.len()
     }
 
     /// 清空上下文
     pub async fn clear_context(&self) {
-        let mut ctx = self.context.lock().await;
-        ctx.messages.clear();
-        ctx.messages.push(Message::system(&self.config.agent.system_prompt));
+        {
+            let active = self.active();
+            let mut ctx = active.context.lock().await;
+            ctx.messages.clear();
+            ctx.messages.push(Message::system(&self.config.agent.system_prompt));
+        }
+        let session_id = self.active().session_id.lock().await.clone();
+        self.reset_downgrade(&session_id).await;
     }
 
     /// 设置会话 ID（用于切换对话上下文）
     ///
-    /// 这会保存当前对话到旧会话，然后加载新会话的历史
+    /// 消息在对话过程中已逐条保存，这里只清空上下文并加载新会话的历史
+    #[allow(dead_code)]
     pub async fn set_session_id(&self, session_id: &str) {
-        // 保存当前消息到旧会话
-        if let Some(ref memory) = self.memory {
-            let old_session_id = self.session_id.lock().await.clone();
-            let ctx = self.context.lock().await;
-            
-            // 保存对话历史
-            for msg in &ctx.messages {
-                let role = match msg.role {
-                    Role::User => "user",
-                    Role::Assistant => "assistant",
-                    Role::Tool => "tool",
-                    Role::System => "system",
-                };
-                let tool_calls = msg.tool_calls.as_ref()
-                    .map(|t| serde_json::to_string(t).ok())
-                    .flatten();
-                let _ = memory.add_message(&old_session_id, role, &msg.content, tool_calls.as_deref()).await;
+        // 清除并重新加载上下文
+        let messages = self.load_messages(session_id).await;
+        let active = self.active();
+        active.context.lock().await.messages = messages;
+
+        // 更新会话 ID
+        *active.session_id.lock().await = session_id.to_string();
+    }
+
+    /// 在 `session_id` 自己的上下文中执行 `fut`
+    ///
+    /// 期间的对话、命令和快照都作用于该会话，不切换其他调用者的会话，不同会话可以同时进行；
+    /// 首次使用时加载该会话的历史，之后保留在内存中
+    pub async fn in_session<F: Future>(&self, session_id: &str, fut: F) -> F::Output {
+        let existing = self.session_contexts.lock().unwrap().get(session_id).cloned();
+        let active = match existing {
+            Some(active) => active,
+            None => {
+                let loaded = Arc::new(ActiveSession {
+                    session_id: Mutex::new(session_id.to_string()),
+                    context: Mutex::new(AgentContext {
+                        messages: self.load_messages(session_id).await,
+                        total_tokens: 0,
+                    }),
+                });
+                // 加载期间同一会话的其他调用可能已先放入
+                self.session_contexts
+                    .lock()
+                    .unwrap()
+                    .entry(session_id.to_string())
+                    .or_insert(loaded)
+                    .clone()
             }
+        };
+        ACTIVE_SESSION.scope(active, fut).await
+    }
+
+    /// 当前使用的会话：[`Agent::in_session`] 中为指定的会话，否则为默认会话
+    fn active(&self) -> Arc<ActiveSession> {
+        ACTIVE_SESSION
+            .try_with(Arc::clone)
+            .unwrap_or_else(|_| self.default_session.clone())
+    }
+
+    /// 系统提示词和会话最近的历史
+    async fn load_messages(&self, session_id: &str) -> Vec<Message> {
+        let mut messages = vec![Message::system(&self.config.agent.system_prompt)];
+        if let Some(ref memory) = self.memory {
+            let history = memory.get_conversation(session_id, self.config.agent.max_context as i64).await.unwrap_or_default();
+            messages.extend(restore_history(history));
         }
+        messages
+    }
 
-        // 清除并重新加载上下文
+    /// 当前上下文的快照
+    pub async fn snapshot(&self) -> AgentSnapshot {
+        let session_id = self.active().session_id.lock().await.clone();
+        let persona = self.persona_name(&session_id).await;
+        let active = self.active();
+        let ctx = active.context.lock().await;
+        AgentSnapshot {
+            version: SNAPSHOT_VERSION,
+            nanobot_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now(),
+            session_id,
+            model: self.model_name(),
+            persona,
+            messages: ctx.messages.clone(),
+            total_tokens: ctx.total_tokens,
+        }
+    }
+
+    /// 从快照恢复上下文、会话 ID、模型和角色
+    ///
+    /// 快照中的模型或角色在当前配置中不存在时保留当前设置
+    pub async fn restore(&self, snapshot: AgentSnapshot) {
+        if let Err(e) = self.set_model(Some(&snapshot.model)) {
+            warn!("快照中的模型 {} 不可用: {}，使用当前模型", snapshot.model, e);
+        }
         {
-            let mut ctx = self.context.lock().await;
-            ctx.messages.clear();
-            ctx.messages.push(Message::system(&self.config.agent.system_prompt));
+            let active = self.active();
+            let mut ctx = active.context.lock().await;
+            ctx.messages = snapshot.messages;
+            ctx.total_tokens = snapshot.total_tokens;
+        }
+        *self.active().session_id.lock().await = snapshot.session_id;
+        if let Some(ref persona) = snapshot.persona {
+            if let Err(e) = self.set_persona(Some(persona)).await {
+                warn!("快照中的角色 {} 不可用: {}，使用默认角色", persona, e);
+            }
+        }
+    }
+}
+
+/// 启用 `[embeddings]` 时创建记忆的语义检索，后端不可用时只按关键词检索
+fn semantic_index(config: &Config) -> Option<Arc<SemanticIndex>> {
+    if !config.embeddings.enabled {
+        return None;
+    }
+    match EmbeddingProviderFactory::create(&config.embeddings) {
+        Ok(provider) => Some(Arc::new(SemanticIndex::new(provider, config.embeddings.min_similarity))),
+        Err(e) => {
+            warn!("Embedding 后端初始化失败: {}，记忆只按关键词检索", e);
+            None
+        }
+    }
+}
+
+/// 把保存的对话历史还原为上下文消息
+///
+/// DeepSeek 等 API 要求 tool 消息紧跟带工具调用的助手消息且 tool_call_id 一一对应，
+/// 因此只保留完整的工具调用组：缺少结果（或被截断）的组和孤立的 tool 消息都会被丢弃。
+/// 系统消息由提示词重新生成，不从历史恢复。
+pub(crate) fn restore_history(history: Vec<ConversationMessage>) -> Vec<Message> {
+    let mut messages = Vec::new();
+    // 当前未闭合的工具调用组：助手消息 + 已收到的工具结果
+    let mut group: Vec<Message> = Vec::new();
+
+    fn flush(group: &mut Vec<Message>, messages: &mut Vec<Message>) {
+        let complete = group.first().and_then(|m| m.tool_calls.as_ref()).is_some_and(|calls| {
+            calls.iter().all(|c| group.iter().any(|m| m.tool_call_id.as_deref() == Some(c.id.as_str())))
+        });
+        if complete {
+            messages.append(group);
+        }
+        group.clear();
+    }
 
-            // 加载新会话的历史
-            if let Some(ref memory) = self.memory {
-                let history = memory.get_conversation(session_id, self.config.agent.max_context as i64).await.unwrap_or_default();
-                for msg in history {
-                    let role = match msg.role.as_str() {
-                        "user" => Role::User,
-                        "assistant" => Role::Assistant,
-                        "tool" => Role::Tool,
-                        _ => Role::System,
-                    };
-                    ctx.messages.push(Message {
-                        role,
+    for msg in history {
+        match msg.role.as_str() {
+            "tool" => {
+                let expected = group.first().and_then(|m| m.tool_calls.as_ref()).is_some_and(|calls| {
+                    calls.iter().any(|c| Some(c.id.as_str()) == msg.tool_call_id.as_deref())
+                });
+                if expected {
+                    group.push(Message {
+                        role: Role::Tool,
                         content: msg.content,
-                        tool_calls: msg.tool_calls.and_then(|t| serde_json::from_str(&t).ok()),
-                        tool_call_id: None,
+                        tool_calls: None,
+                        tool_call_id: msg.tool_call_id,
                     });
                 }
             }
+            "user" | "assistant" => {
+                flush(&mut group, &mut messages);
+                let calls: Option<Vec<ToolCall>> = msg.tool_calls.and_then(|t| serde_json::from_str(&t).ok());
+                match calls {
+                    Some(calls) if msg.role == "assistant" && !calls.is_empty() => {
+                        group.push(Message::assistant(msg.content).with_tool_calls(calls));
+                    }
+                    _ if msg.role == "user" => messages.push(Message::user(msg.content)),
+                    _ => messages.push(Message::assistant(msg.content)),
+                }
+            }
+            _ => {}
         }
-
-        // 更新会话 ID
-        *self.session_id.lock().await = session_id.to_string();
     }
+    flush(&mut group, &mut messages);
+    messages
 }
 
 /// Agent 响应
@@ -338,4 +1481,21 @@ impl Agent {
 pub struct AgentResponse {
     pub content: String,
     pub model: String,
+    /// 本轮执行的工具调用
+    pub tool_trace: Vec<ToolTrace>,
+    /// 本轮消耗的令牌数（含工具调用和多次采样的请求）
+    pub tokens: u64,
+}
+
+/// 一次工具调用的记录
+#[derive(Debug, Clone)]
+pub struct ToolTrace {
+    pub name: String,
+    /// 调用参数（不是合法 JSON 时为 Null）
+    pub arguments: Value,
+    /// 交给模型的结果
+    pub output: String,
+    pub success: bool,
+    /// 执行耗时
+    pub duration: Duration,
 }
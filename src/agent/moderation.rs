@@ -0,0 +1,67 @@
+//! 内容分类
+//!
+//! 内容过滤（`[content_filter]`）配置了 `classifier_model` 时，用该模型判断消息是否包含不当内容
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::warn;
+
+use super::Agent;
+use crate::llm::{ChatRequest, Message};
+
+const CLASSIFIER_PROMPT: &str = "你负责内容审核。判断用户提供的文本是否包含辱骂、仇恨、色情、暴力、\
+违法或其他不适合在聊天中出现的内容。正常讨论这些话题（如新闻、学术、求助）不算。\
+以 JSON 输出 {\"unsafe\": true 或 false, \"reason\": \"简短理由\"}，只输出 JSON。";
+
+#[derive(Debug, Deserialize)]
+struct Classification {
+    #[serde(default, rename = "unsafe")]
+    is_unsafe: bool,
+    #[serde(default)]
+    reason: String,
+}
+
+impl Agent {
+    /// 用指定模型判断文本是否包含不当内容，是则返回理由
+    pub async fn classify_content(&self, text: &str, spec: &str, session_id: Option<&str>) -> Result<Option<String>> {
+        let (provider, provider_name, model) = self.resolve_model_override(spec)?;
+        let mut request = ChatRequest::new(
+            model.clone(),
+            vec![Message::system(CLASSIFIER_PROMPT), Message::user(text)],
+        );
+        request.temperature = Some(0.0);
+        // 无痕会话的用量只计入汇总
+        let session_id = session_id.filter(|id| !self.is_incognito_session(id));
+
+        if let Some(ref budget) = self.budget {
+            budget.check(Some(&provider_name), session_id).await?;
+        }
+        let response = provider.chat(request).await?;
+        if let (Some(budget), Some(usage)) = (&self.budget, &response.usage) {
+            if let Err(e) = budget.record(&provider_name, &model, session_id, usage).await {
+                warn!("记录用量失败: {}", e);
+            }
+        }
+        Ok(parse_classification(&response.message.content))
+    }
+}
+
+/// 解析分类结果（允许包裹在代码块中），无法解析时视为正常内容
+fn parse_classification(output: &str) -> Option<String> {
+    let json = output
+        .find('{')
+        .zip(output.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &output[start..=end])?;
+    match serde_json::from_str::<Classification>(json) {
+        Ok(c) if c.is_unsafe => Some(match c.reason.trim() {
+            "" => "模型判定为不当内容".to_string(),
+            reason => reason.to_string(),
+        }),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("无法解析内容分类结果: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! 分层系统提示词
+//!
+//! 系统提示词由多层组成，按固定顺序拼接：
+//! 基础提示词 → 通道提示词 → 用户资料 → 近期笔记 → 置顶内容 → 会话指令 → 新用户引导 → 回复语言。
+//! 超过字符预算时按优先级从低到高截断，基础提示词优先级最高
+
+/// 提示词层
+#[derive(Debug, Clone)]
+struct PromptLayer {
+    /// 小节标题（基础提示词没有标题）
+    title: Option<&'static str>,
+    content: String,
+    /// 拼接顺序
+    order: u8,
+    /// 数值越大越先保留
+    priority: u8,
+}
+
+impl PromptLayer {
+    fn render(&self, content: &str) -> String {
+        match self.title {
+            Some(title) => format!("\n\n## {}\n{}", title, content),
+            None => content.to_string(),
+        }
+    }
+}
+
+/// 截断标记
+const ELLIPSIS: &str = "…";
+
+/// 系统提示词构建器
+#[derive(Debug, Clone)]
+pub struct PromptBuilder {
+    layers: Vec<PromptLayer>,
+    /// 最大字符数（0 表示不限制）
+    budget: usize,
+}
+
+impl PromptBuilder {
+    pub fn new(base: impl Into<String>, budget: usize) -> Self {
+        Self {
+            layers: vec![PromptLayer {
+                title: None,
+                content: base.into(),
+                order: 0,
+                priority: u8::MAX,
+            }],
+            budget,
+        }
+    }
+
+    /// 通道提示词
+    pub fn channel(self, content: Option<&str>) -> Self {
+        self.layer("通道说明", content, 1, 2)
+    }
+
+    /// 用户资料与偏好
+    pub fn user_profile(self, content: Option<&str>) -> Self {
+        self.layer("用户资料", content, 2, 1)
+    }
+
+    /// 昨天和今天的日常笔记，优先级最低
+    pub fn notes(self, content: Option<&str>) -> Self {
+        self.layer("近期笔记", content, 3, 0)
+    }
+
+    /// 置顶内容
+    pub fn pinned(self, pins: &[String]) -> Self {
+        if pins.is_empty() {
+            return self;
+        }
+        let content = pins
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{}. {}", i + 1, p))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.layer("置顶内容（请在整个对话中始终遵循和参考）", Some(&content), 4, 3)
+    }
+
+    /// 会话指令（/instruct）
+    pub fn instructions(self, content: Option<&str>) -> Self {
+        self.layer("本会话指令", content, 5, 4)
+    }
+
+    /// 新用户引导（仅在引导期间附加）
+    pub fn onboarding(self, content: Option<&str>) -> Self {
+        self.layer("新用户引导", content, 6, 5)
+    }
+
+    /// 按用户消息检测出的回复语言
+    pub fn reply_language(self, content: Option<&str>) -> Self {
+        self.layer("回复语言", content, 7, 6)
+    }
+
+    fn layer(
+        mut self,
+        title: &'static str,
+        content: Option<&str>,
+        order: u8,
+        priority: u8,
+    ) -> Self {
+        if let Some(content) = content.map(str::trim).filter(|c| !c.is_empty()) {
+            self.layers.retain(|l| l.order != order);
+            self.layers.push(PromptLayer {
+                title: Some(title),
+                content: content.to_string(),
+                order,
+                priority,
+            });
+            self.layers.sort_by_key(|l| l.order);
+        }
+        self
+    }
+
+    /// 组合最终的系统提示词
+    pub fn build(&self) -> String {
+        let full: Vec<String> = self.layers.iter().map(|l| l.render(&l.content)).collect();
+        if self.budget == 0 {
+            return full.concat();
+        }
+
+        // 按优先级分配预算
+        let mut order: Vec<usize> = (0..self.layers.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.layers[i].priority));
+
+        let mut remaining = self.budget;
+        let mut rendered: Vec<Option<String>> = vec![None; self.layers.len()];
+        for i in order {
+            let len = full[i].chars().count();
+            if len <= remaining {
+                remaining -= len;
+                rendered[i] = Some(full[i].clone());
+                continue;
+            }
+
+            // 放不下时截断正文，连标题都放不下则丢弃该层
+            let layer = &self.layers[i];
+            let overhead = layer.render("").chars().count() + ELLIPSIS.chars().count();
+            if remaining > overhead {
+                let keep: String = layer.content.chars().take(remaining - overhead).collect();
+                rendered[i] = Some(layer.render(&format!("{}{}", keep, ELLIPSIS)));
+            }
+            remaining = 0;
+        }
+
+        rendered.into_iter().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_order() {
+        let prompt = PromptBuilder::new("基础", 0)
+            .instructions(Some("用英文回答"))
+            .pinned(&["生日 5 月 1 日".to_string()])
+            .notes(Some("- 下午开会"))
+            .user_profile(Some("称呼我为老板"))
+            .channel(Some("  "))
+            .build();
+
+        let base = prompt.find("基础").unwrap();
+        let profile = prompt.find("称呼我为老板").unwrap();
+        let notes = prompt.find("下午开会").unwrap();
+        let pinned = prompt.find("1. 生日").unwrap();
+        let instruct = prompt.find("用英文回答").unwrap();
+        // 拼接顺序固定，与调用顺序无关
+        assert!(base < profile && profile < notes && notes < pinned && pinned < instruct);
+        assert!(!prompt.contains("通道说明"));
+    }
+
+    #[test]
+    fn test_budget_truncates_low_priority_first() {
+        let builder = PromptBuilder::new("基础提示词", 60)
+            .channel(Some("通道"))
+            .user_profile(Some(&"资料".repeat(100)))
+            .instructions(Some("会话指令"));
+
+        let prompt = builder.build();
+        assert!(prompt.chars().count() <= 60);
+        assert!(prompt.starts_with("基础提示词"));
+        assert!(prompt.contains("会话指令"));
+        assert!(prompt.contains("通道"));
+        // 用户资料被截断
+        assert!(prompt.contains(ELLIPSIS));
+    }
+}
@@ -0,0 +1,309 @@
+//! 深度调研
+//!
+//! 先让模型把问题拆成几个子问题，再在有限的轮数内反复调用 web_search / fetch_page，
+//! 把有用的要点连同来源记到草稿（note）中，最后只根据草稿写出带编号引用的报告。
+//! 迭代次数和令牌用量由 `[research]` 单独限制，进度通过 [`report_progress`] 推送
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::Agent;
+use crate::llm::{ChatRequest, LlmProvider, Message, Tool};
+use crate::tools::{report_progress, truncate_output, ToolContext};
+
+/// 调研中可用的工具
+const RESEARCH_TOOLS: [&str; 2] = ["web_search", "fetch_page"];
+
+/// 记录草稿的工具（只在调研中提供给模型）
+const NOTE_TOOL: &str = "note";
+
+const PLAN_PROMPT: &str = "你负责规划调研。把用户的问题拆分为若干个可以通过网络搜索回答的子问题，\
+按先后顺序排列，覆盖回答问题所需的关键方面。以 JSON 字符串数组输出，只输出 JSON。";
+
+const SEARCH_PROMPT: &str = "你是调研助手，通过 web_search 搜索、fetch_page 阅读网页来回答用户的问题。\
+逐个研究给出的子问题：先搜索，再阅读最相关的网页。每读到与问题相关的事实、数据或观点，\
+立即调用 note 工具记下要点和来源网址，最终报告只能使用草稿中的内容。\
+资料足够回答所有子问题后停止调用工具，简短说明调研已完成。";
+
+const REPORT_PROMPT: &str = "你负责撰写调研报告。只根据提供的草稿回答用户的问题，不要编造草稿中没有的信息。\
+结构清晰、先给结论再展开，每个事实后用 [编号] 标注来源（编号见草稿）。\
+资料之间有矛盾或不足时如实说明。不要在末尾列出来源列表。";
+
+/// 调研草稿中的一条笔记
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResearchNote {
+    /// 来源网址
+    pub source: String,
+    /// 要点
+    pub content: String,
+}
+
+/// 调研结果
+#[derive(Debug, Clone)]
+pub struct ResearchReport {
+    /// 拆分出的子问题
+    pub questions: Vec<String>,
+    /// 报告正文（含来源列表）
+    pub report: String,
+    /// 引用的来源，按编号排列
+    pub sources: Vec<String>,
+    /// 搜索阶段调用模型的次数
+    pub iterations: u32,
+    /// 使用的令牌数
+    pub tokens: u32,
+}
+
+/// 一次调研中的模型调用，统一检查预算并累计令牌
+struct ResearchRun<'a> {
+    agent: &'a Agent,
+    provider: Arc<dyn LlmProvider>,
+    provider_name: String,
+    model: String,
+    /// 用量按此会话记录（无痕会话为 None）
+    budget_user: Option<&'a str>,
+    tokens: u32,
+}
+
+impl ResearchRun<'_> {
+    async fn chat(&mut self, messages: Vec<Message>, tools: Option<Vec<Tool>>) -> Result<Message> {
+        let mut request = ChatRequest::new(self.model.clone(), messages);
+        request.temperature = Some(0.2);
+        if let Some(tools) = tools {
+            request = request.with_tools(tools);
+        }
+
+        if let Some(ref budget) = self.agent.budget {
+            budget.check(Some(&self.provider_name), self.budget_user).await?;
+        }
+        let response = self.provider.chat(request).await?;
+        if let Some(ref usage) = response.usage {
+            self.tokens += usage.total_tokens;
+            if let Some(ref budget) = self.agent.budget {
+                if let Err(e) = budget.record(&self.provider_name, &self.model, self.budget_user, usage).await {
+                    warn!("记录用量失败: {}", e);
+                }
+            }
+        }
+        Ok(response.message)
+    }
+}
+
+impl Agent {
+    /// 对问题进行多步调研，返回带引用的报告
+    pub async fn research(&self, question: &str, session_id: Option<&str>) -> Result<ResearchReport> {
+        let config = &self.config.research;
+        let question = question.trim();
+        if question.is_empty() {
+            bail!("调研问题不能为空");
+        }
+
+        let mut tools: Vec<Tool> = self
+            .tool_registry
+            .to_llm_tools()
+            .into_iter()
+            .filter(|t| RESEARCH_TOOLS.contains(&t.name.as_str()))
+            .collect();
+        if tools.is_empty() {
+            bail!("未启用 web_search 或 fetch_page 工具，无法调研");
+        }
+        tools.push(note_tool());
+
+        let (provider, provider_name, model) = match config.model {
+            Some(ref spec) => self.resolve_model_override(spec)?,
+            None => (
+                self.llm_manager.default_provider()?,
+                self.config.agent.default_provider.clone(),
+                self.config.agent.default_model.clone(),
+            ),
+        };
+        // 无痕会话的用量只计入汇总
+        let budget_user = session_id.filter(|id| !self.is_incognito_session(id));
+        let mut run = ResearchRun { agent: self, provider, provider_name, model, budget_user, tokens: 0 };
+
+        // 1. 拆分子问题
+        let plan = run
+            .chat(vec![Message::system(PLAN_PROMPT), Message::user(question)], None)
+            .await?;
+        let mut questions = parse_questions(&plan.content);
+        questions.truncate(config.max_questions.max(1));
+        if questions.is_empty() {
+            questions.push(question.to_string());
+        }
+        let plan_text = numbered(&questions);
+        report_progress("research", &format!("调研计划：\n{}", plan_text));
+
+        // 2. 搜索、阅读并记录草稿
+        let ctx = ToolContext::new(self.config.tools.clone()).with_session(session_id.unwrap_or_default());
+        let mut notes: Vec<ResearchNote> = Vec::new();
+        let mut messages = vec![
+            Message::system(SEARCH_PROMPT),
+            Message::user(format!("问题：{}\n\n子问题：\n{}", question, plan_text)),
+        ];
+        let mut iterations = 0;
+        while iterations < config.max_iterations {
+            if config.max_tokens > 0 && run.tokens >= config.max_tokens {
+                report_progress("research", "令牌预算已用完，根据已有资料撰写报告");
+                break;
+            }
+            iterations += 1;
+            let message = run.chat(messages.clone(), Some(tools.clone())).await?;
+            let calls = message.tool_calls.clone().unwrap_or_default();
+            if calls.is_empty() {
+                break;
+            }
+            messages.push(message);
+
+            for call in calls {
+                let args: Value = serde_json::from_str(&call.function.arguments).unwrap_or_default();
+                let result = match call.function.name.as_str() {
+                    NOTE_TOOL => match serde_json::from_value::<ResearchNote>(args) {
+                        Ok(note) if !note.content.trim().is_empty() => {
+                            notes.push(note);
+                            format!("已记录（草稿共 {} 条）", notes.len())
+                        }
+                        _ => "note 需要 source 和 content 参数".to_string(),
+                    },
+                    name if RESEARCH_TOOLS.contains(&name) => {
+                        report_progress(
+                            "research",
+                            &format!("（第 {}/{} 步）{}", iterations, config.max_iterations, describe_call(name, &args)),
+                        );
+                        match self.tool_registry.execute(name, args, &ctx).await {
+                            Ok(r) => truncate_output(&r.to_string(), config.max_result_chars),
+                            Err(e) => format!("工具执行错误: {}", e),
+                        }
+                    }
+                    name => format!("调研中不能使用工具 {}", name),
+                };
+                messages.push(Message::tool_result(&call.id, result));
+            }
+        }
+
+        if notes.is_empty() {
+            bail!("没有收集到可用的资料，无法生成报告");
+        }
+
+        // 3. 根据草稿撰写报告
+        report_progress("research", &format!("资料收集完成（{} 条笔记），正在撰写报告", notes.len()));
+        let mut sources: Vec<String> = Vec::new();
+        let mut draft = String::new();
+        for note in &notes {
+            let source = note.source.trim().to_string();
+            let index = match sources.iter().position(|s| *s == source) {
+                Some(i) => i + 1,
+                None => {
+                    sources.push(source);
+                    sources.len()
+                }
+            };
+            draft.push_str(&format!("[{}] {}\n", index, note.content.trim()));
+        }
+        let report = run
+            .chat(
+                vec![
+                    Message::system(REPORT_PROMPT),
+                    Message::user(format!("问题：{}\n\n草稿：\n{}", question, draft)),
+                ],
+                None,
+            )
+            .await?;
+        let report = format!(
+            "{}\n\n来源：\n{}",
+            report.content.trim(),
+            sources
+                .iter()
+                .enumerate()
+                .map(|(i, s)| format!("[{}] {}", i + 1, s))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        info!("调研完成：{} 轮，{} 条笔记，{} 个令牌", iterations, notes.len(), run.tokens);
+        Ok(ResearchReport {
+            questions,
+            report,
+            sources,
+            iterations,
+            tokens: run.tokens,
+        })
+    }
+}
+
+/// note 工具定义
+fn note_tool() -> Tool {
+    Tool {
+        name: NOTE_TOOL.to_string(),
+        description: "把与问题相关的要点记到调研草稿中，报告只根据草稿撰写".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "source": { "type": "string", "description": "来源网址" },
+                "content": { "type": "string", "description": "要点（事实、数据或观点），尽量具体" }
+            },
+            "required": ["source", "content"]
+        }),
+    }
+}
+
+/// 进度中显示的工具调用
+fn describe_call(name: &str, args: &Value) -> String {
+    let arg = |key: &str| args.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+    match name {
+        "web_search" => format!("🔍 搜索：{}", arg("query")),
+        "fetch_page" => format!("📄 阅读：{}", arg("url")),
+        _ => name.to_string(),
+    }
+}
+
+fn numbered(items: &[String]) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, q)| format!("{}. {}", i + 1, q))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 解析子问题：JSON 字符串数组（允许包裹在代码块中），否则按行（去掉序号）解析
+fn parse_questions(output: &str) -> Vec<String> {
+    let json = output
+        .find('[')
+        .zip(output.rfind(']'))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| serde_json::from_str::<Vec<String>>(&output[start..=end]).ok());
+    let questions = json.unwrap_or_else(|| {
+        output
+            .lines()
+            .map(|l| l.trim().trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | '、' | '-' | '*' | ')')))
+            .map(str::to_string)
+            .collect()
+    });
+    questions
+        .into_iter()
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty() && !q.starts_with("```"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_questions() {
+        let output = "```json\n[\"Rust 异步运行时有哪些？\", \" tokio 的调度模型 \", \"\"]\n```";
+        assert_eq!(parse_questions(output), vec!["Rust 异步运行时有哪些？", "tokio 的调度模型"]);
+
+        let output = "1. 什么是 RAG\n2、适用场景\n- 局限性";
+        assert_eq!(parse_questions(output), vec!["什么是 RAG", "适用场景", "局限性"]);
+    }
+
+    #[test]
+    fn test_describe_call() {
+        assert_eq!(describe_call("web_search", &json!({"query": "rust"})), "🔍 搜索：rust");
+        assert_eq!(describe_call("fetch_page", &json!({"url": "https://a.com"})), "📄 阅读：https://a.com");
+    }
+}
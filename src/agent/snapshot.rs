@@ -0,0 +1,86 @@
+//! 上下文快照
+//!
+//! 把 Agent 当前的上下文（消息、模型、角色）保存为 JSON，便于把出问题的对话状态分享给别人，
+//! 或换一个模型、换一个版本的代码重放同一轮对话（`nanobot session snapshot/restore`）
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::llm::Message;
+
+/// 快照格式版本，不兼容的改动时递增
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Agent 上下文快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSnapshot {
+    /// 格式版本
+    pub version: u32,
+    /// 生成快照的 nanobot 版本
+    pub nanobot_version: String,
+    pub created_at: DateTime<Utc>,
+    pub session_id: String,
+    /// 当时使用的模型（provider/model）
+    pub model: String,
+    /// 当时的角色，默认角色为 None
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// 上下文消息（含系统提示词）
+    pub messages: Vec<Message>,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+impl AgentSnapshot {
+    /// 从 JSON 解析，版本不兼容时报错
+    pub fn from_json(json: &str) -> Result<Self> {
+        let snapshot: Self = serde_json::from_str(json).context("快照格式无效")?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            bail!(
+                "快照版本 {} 高于当前支持的版本 {}，请升级 nanobot",
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+        }
+        Ok(snapshot)
+    }
+
+    /// 读取快照文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("读取快照失败: {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// 格式化为 JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = AgentSnapshot {
+            version: SNAPSHOT_VERSION,
+            nanobot_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            session_id: "telegram:42".to_string(),
+            model: "deepseek/deepseek-chat".to_string(),
+            persona: Some("coder".to_string()),
+            messages: vec![Message::system("你是助手"), Message::user("你好")],
+            total_tokens: 12,
+        };
+        let restored = AgentSnapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+        assert_eq!(restored.session_id, "telegram:42");
+        assert_eq!(restored.messages.len(), 2);
+        assert_eq!(restored.messages[1].content, "你好");
+
+        let newer = snapshot.to_json().unwrap().replace("\"version\": 1", "\"version\": 99");
+        assert!(AgentSnapshot::from_json(&newer).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! 会话标题
+//!
+//! 用户在会话中发送 `session.title_after_turns` 条消息后，在后台让模型根据当前上下文生成简短的标题，
+//! 保存在会话元数据中（`session list`、/sessions 中显示，可按标题搜索）。
+//! 已有标题（包括 /title 手动设置的）的会话不再生成，无痕会话不生成
+
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
+
+use super::Agent;
+use crate::llm::{ChatRequest, Message, Role};
+
+const TITLE_PROMPT: &str = "根据用户提供的对话为这次会话起一个简短的标题（不超过 20 个字），\
+概括讨论的主题，使用对话所用的语言。只输出标题，不加引号和句末标点。";
+
+/// 交给模型的对话最多字符数（取开头部分）
+const MAX_TRANSCRIPT_CHARS: usize = 3000;
+/// 标题最多字符数
+const MAX_TITLE_CHARS: usize = 40;
+
+impl Agent {
+    /// 当前会话的标题
+    pub async fn title(&self) -> Option<String> {
+        let sessions = self.sessions.as_ref()?;
+        let session_id = self.active().session_id.lock().await.clone();
+        if let Some(session) = sessions.get_session(&session_id).await {
+            return session.read().await.title().map(str::to_string);
+        }
+        match sessions.load_session(&session_id).await {
+            Ok(session) => session?.title().map(str::to_string),
+            Err(e) => {
+                warn!("读取会话标题失败: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 设置当前会话的标题（None 清除，清除后会重新自动生成）
+    pub async fn set_title(&self, title: Option<&str>) -> Result<()> {
+        let sessions = self.sessions.as_ref().ok_or_else(|| anyhow!("未启用会话存储"))?;
+        let session_id = self.active().session_id.lock().await.clone();
+        if self.is_incognito_session(&session_id) {
+            return Err(anyhow!("无痕模式下不保存会话标题"));
+        }
+        let title = title.map(clean_title).transpose()?;
+        let channel = session_id.split_once(':').map(|(c, _)| c).unwrap_or("cli");
+        let channel_id = session_id
+            .strip_prefix(&format!("{}:", channel))
+            .unwrap_or(&session_id);
+        sessions.get_or_create(&session_id, channel, channel_id).await?;
+        sessions.set_title(&session_id, title.as_deref()).await?;
+        Ok(())
+    }
+
+    /// 会话用户消息数达到阈值且尚无标题时，在后台生成标题
+    pub(super) async fn schedule_title(&self, session_id: &str, user_messages: u64) {
+        let after = self.config.session.title_after_turns;
+        if after == 0 || user_messages < after {
+            return;
+        }
+        let Some(sessions) = self.sessions.clone() else {
+            return;
+        };
+        if !self.titling.lock().unwrap().insert(session_id.to_string()) {
+            return;
+        }
+
+        let prepared = self.title_request();
+        let budget = self.budget.clone();
+        let session_id = session_id.to_string();
+        let text = transcript(&self.active().context.lock().await.messages);
+        let titling = self.titling.clone();
+        tokio::spawn(async move {
+            let result = async {
+                if text.is_empty() {
+                    return Err(anyhow!("没有可用的对话内容"));
+                }
+                let (provider, provider_name, model) = prepared?;
+                let mut request = ChatRequest::new(
+                    model.clone(),
+                    vec![Message::system(TITLE_PROMPT), Message::user(text)],
+                );
+                request.temperature = Some(0.3);
+
+                if let Some(ref budget) = budget {
+                    budget.check(Some(&provider_name), Some(&session_id)).await?;
+                }
+                let response = provider.chat(request).await?;
+                if let (Some(budget), Some(usage)) = (&budget, &response.usage) {
+                    if let Err(e) = budget.record(&provider_name, &model, Some(&session_id), usage).await {
+                        warn!("记录用量失败: {}", e);
+                    }
+                }
+
+                let title = clean_title(&response.message.content)?;
+                sessions.set_title(&session_id, Some(&title)).await?;
+                info!("会话 {} 的标题: {}", session_id, title);
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            if let Err(e) = result {
+                warn!("生成会话 {} 的标题失败: {}", session_id, e);
+            }
+            // 失败时下一轮重试；成功后会话已有标题，不会再次生成
+            titling.lock().unwrap().remove(&session_id);
+        });
+    }
+
+    /// 生成标题使用的提供商和模型
+    fn title_request(&self) -> Result<(std::sync::Arc<dyn crate::llm::LlmProvider>, String, String)> {
+        match self.config.session.title_model {
+            Some(ref spec) => self.resolve_model_override(spec),
+            None => Ok((
+                self.llm_manager.default_provider()?,
+                self.config.agent.default_provider.clone(),
+                self.config.agent.default_model.clone(),
+            )),
+        }
+    }
+}
+
+/// 对话开头部分的文本（只包含用户和助手的消息）
+fn transcript(messages: &[Message]) -> String {
+    let mut text = String::new();
+    for m in messages {
+        let speaker = match m.role {
+            Role::User => "用户",
+            Role::Assistant if !m.content.trim().is_empty() => "助手",
+            _ => continue,
+        };
+        text.push_str(&format!("{}: {}\n", speaker, m.content.trim()));
+        if text.chars().count() >= MAX_TRANSCRIPT_CHARS {
+            return text.chars().take(MAX_TRANSCRIPT_CHARS).collect();
+        }
+    }
+    text
+}
+
+/// 取模型输出的第一行，去掉引号、书名号和“标题：”前缀
+fn clean_title(output: &str) -> Result<String> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    let line = line
+        .strip_prefix("标题：")
+        .or_else(|| line.strip_prefix("标题:"))
+        .or_else(|| line.strip_prefix("Title:"))
+        .unwrap_or(line);
+    let quotes: &[char] = &['"', '\'', '“', '”', '‘', '’', '《', '》', '「', '」', '*', '#', '。', '.'];
+    let title: String = line.trim().trim_matches(quotes).trim().chars().take(MAX_TITLE_CHARS).collect();
+    if title.is_empty() {
+        return Err(anyhow!("标题为空"));
+    }
+    Ok(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(clean_title("《Rust 异步运行时》\n").unwrap(), "Rust 异步运行时");
+        assert_eq!(clean_title("\n标题：“周末旅行计划”。").unwrap(), "周末旅行计划");
+        assert_eq!(clean_title("\"Tokio vs async-std\"").unwrap(), "Tokio vs async-std");
+        assert_eq!(clean_title(&"长".repeat(100)).unwrap().chars().count(), MAX_TITLE_CHARS);
+        assert!(clean_title("  \n \"\" ").is_err());
+    }
+
+    #[test]
+    fn test_transcript() {
+        let messages = vec![
+            Message::system("系统提示"),
+            Message::user("怎么学 Rust？"),
+            Message::assistant("先读 The Book。"),
+        ];
+        assert_eq!(transcript(&messages), "用户: 怎么学 Rust？\n助手: 先读 The Book。\n");
+    }
+}
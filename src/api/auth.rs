@@ -0,0 +1,440 @@
+//! API 令牌认证与审计
+//!
+//! 令牌来自 `[api.tokens]` 配置和 `nanobot token create` 创建的令牌（数据库中只保存 SHA-256 哈希），
+//! 每个令牌带有权限范围和可选的过期时间。通过认证的调用按令牌名追加到审计日志
+//! （`<workspace>/api_audit.jsonl`）
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use super::server::ClientIp;
+use super::{error_response, secret_matches};
+use crate::config::{ApiConfig, ApiScope};
+
+/// 生成的令牌前缀（便于在日志和密钥扫描中识别）
+const TOKEN_PREFIX: &str = "nbt_";
+/// 令牌数据库中已有权限的缓存时间（令牌由 CLI 在其他进程中创建/吊销，过期后重新查询）
+const SCOPE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 一个令牌
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 来源：config 或 db
+    pub source: &'static str,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    /// 是否拥有指定权限（admin 包含全部权限）
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        scopes_allow(&self.scopes, scope)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+/// 令牌哈希（数据库中只保存哈希）
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn scopes_allow(scopes: &[ApiScope], scope: ApiScope) -> bool {
+    scopes.iter().any(|s| *s == scope || *s == ApiScope::Admin)
+}
+
+fn parse_scopes(text: &str) -> Vec<ApiScope> {
+    text.split(',').filter_map(|s| s.trim().parse().ok()).collect()
+}
+
+fn join_scopes(scopes: &[ApiScope]) -> String {
+    scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(",")
+}
+
+/// `nanobot token create` 创建的令牌
+pub struct TokenStore {
+    pool: Pool<Sqlite>,
+}
+
+impl TokenStore {
+    pub async fn open(db_path: &str) -> Result<Self> {
+        let pool = crate::db::connect(db_path, 2)
+            .await
+            .context("连接令牌数据库失败")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_tokens (
+                name TEXT PRIMARY KEY,
+                token_hash TEXT NOT NULL UNIQUE,
+                scopes TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                expires_at TIMESTAMP,
+                last_used_at TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    /// 创建令牌，返回令牌明文（只在创建时可见）
+    pub async fn create(
+        &self,
+        name: &str,
+        scopes: &[ApiScope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<String> {
+        let mut bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = format!("{}{}", TOKEN_PREFIX, hex::encode(bytes));
+
+        sqlx::query(
+            "INSERT INTO api_tokens (name, token_hash, scopes, created_at, expires_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(name)
+        .bind(hash_token(&token))
+        .bind(join_scopes(scopes))
+        .bind(Utc::now())
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("创建令牌 {} 失败（是否已存在同名令牌？）", name))?;
+        Ok(token)
+    }
+
+    /// 吊销令牌，返回是否存在
+    pub async fn revoke(&self, name: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiToken>> {
+        let rows = sqlx::query("SELECT * FROM api_tokens ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// 按令牌明文查找
+    pub async fn find(&self, token: &str) -> Result<Option<ApiToken>> {
+        let row = sqlx::query("SELECT * FROM api_tokens WHERE token_hash = ?")
+            .bind(hash_token(token))
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    /// 记录最近使用时间
+    pub async fn touch(&self, name: &str) -> Result<()> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE name = ?")
+            .bind(Utc::now())
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ApiToken> {
+        Ok(ApiToken {
+            name: row.try_get("name")?,
+            scopes: parse_scopes(&row.try_get::<String, _>("scopes")?),
+            expires_at: row.try_get("expires_at")?,
+            source: "db",
+            created_at: Some(row.try_get("created_at")?),
+            last_used_at: row.try_get("last_used_at")?,
+        })
+    }
+}
+
+/// 认证失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// 没有任何令牌拥有该权限，接口不开放
+    Disabled,
+    /// 缺少令牌或令牌无效
+    Invalid,
+    /// 令牌已过期
+    Expired,
+    /// 令牌没有该权限
+    Forbidden,
+}
+
+impl AuthError {
+    fn into_response(self, endpoint: ApiScope) -> Response {
+        match self {
+            Self::Disabled => error_response(StatusCode::NOT_FOUND, format!("未开放 {} 接口", endpoint.as_str())),
+            Self::Invalid => error_response(StatusCode::UNAUTHORIZED, "令牌无效"),
+            Self::Expired => error_response(StatusCode::UNAUTHORIZED, "令牌已过期"),
+            Self::Forbidden => error_response(StatusCode::FORBIDDEN, format!("令牌没有 {} 权限", endpoint.as_str())),
+        }
+    }
+}
+
+/// 审计日志中的一条记录
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    time: DateTime<Utc>,
+    token: &'a str,
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client: Option<String>,
+}
+
+/// API 认证（配置中的令牌 + 令牌数据库）
+pub struct ApiAuth {
+    /// 配置中的令牌（令牌明文, 令牌信息）
+    configured: Vec<(String, ApiToken)>,
+    store: Option<TokenStore>,
+    /// 令牌数据库中出现过的权限（查询时间, 权限），避免每个请求都列出全部令牌
+    scope_cache: std::sync::Mutex<Option<(Instant, Vec<ApiScope>)>>,
+    audit_path: Option<PathBuf>,
+}
+
+impl ApiAuth {
+    /// 读取 `[api.tokens]`，旧的 broadcast_token / jobs_token 视为 admin / jobs 令牌
+    pub fn new(config: &ApiConfig) -> Self {
+        let token = |name: &str, scopes: Vec<ApiScope>, expires_at| ApiToken {
+            name: name.to_string(),
+            scopes,
+            expires_at,
+            source: "config",
+            created_at: None,
+            last_used_at: None,
+        };
+        let mut configured: Vec<_> = config
+            .tokens
+            .iter()
+            .map(|(name, t)| (t.token.clone(), token(name, t.scopes.clone(), t.expires_at)))
+            .collect();
+        if let Some(ref secret) = config.broadcast_token {
+            configured.push((secret.clone(), token("broadcast_token", vec![ApiScope::Admin], None)));
+        }
+        if let Some(ref secret) = config.jobs_token {
+            configured.push((secret.clone(), token("jobs_token", vec![ApiScope::Jobs], None)));
+        }
+        configured.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        Self {
+            configured,
+            store: None,
+            scope_cache: std::sync::Mutex::new(None),
+            audit_path: None,
+        }
+    }
+
+    /// 同时使用令牌数据库中的令牌
+    pub fn with_store(mut self, store: Option<TokenStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// 通过认证的调用追加到审计日志
+    pub fn with_audit_log(mut self, path: PathBuf) -> Self {
+        self.audit_path = Some(path);
+        self
+    }
+
+    /// 配置中的令牌
+    pub fn configured(&self) -> impl Iterator<Item = &ApiToken> {
+        self.configured.iter().map(|(_, t)| t)
+    }
+
+    /// 是否有令牌拥有该权限（过期的令牌也计入，避免令牌过期后接口变成 404）
+    ///
+    /// 数据库中的令牌按 [`SCOPE_CACHE_TTL`] 缓存，新建或吊销令牌后最多延迟这么久生效
+    async fn enabled(&self, scope: ApiScope) -> bool {
+        if self.configured().any(|t| t.allows(scope)) {
+            return true;
+        }
+        let Some(ref store) = self.store else {
+            return false;
+        };
+        if let Some((at, ref scopes)) = *self.scope_cache.lock().unwrap() {
+            if at.elapsed() < SCOPE_CACHE_TTL {
+                return scopes_allow(scopes, scope);
+            }
+        }
+        match store.list().await {
+            Ok(tokens) => {
+                let scopes: Vec<ApiScope> = tokens.iter().flat_map(|t| t.scopes.iter().copied()).collect();
+                let enabled = scopes_allow(&scopes, scope);
+                *self.scope_cache.lock().unwrap() = Some((Instant::now(), scopes));
+                enabled
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// 校验令牌明文，返回令牌信息
+    pub async fn authenticate(&self, provided: Option<&str>, scope: ApiScope) -> std::result::Result<ApiToken, AuthError> {
+        if !self.enabled(scope).await {
+            return Err(AuthError::Disabled);
+        }
+        let provided = provided.map(str::trim).filter(|t| !t.is_empty()).ok_or(AuthError::Invalid)?;
+
+        let mut found = self
+            .configured
+            .iter()
+            .find(|(secret, _)| secret_matches(secret, provided))
+            .map(|(_, t)| t.clone());
+        if found.is_none() {
+            if let Some(ref store) = self.store {
+                found = store.find(provided).await.unwrap_or_else(|e| {
+                    warn!("查询令牌失败: {}", e);
+                    None
+                });
+            }
+        }
+
+        let token = found.ok_or(AuthError::Invalid)?;
+        if token.is_expired(Utc::now()) {
+            return Err(AuthError::Expired);
+        }
+        if !token.allows(scope) {
+            return Err(AuthError::Forbidden);
+        }
+        if let (Some(store), "db") = (&self.store, token.source) {
+            if let Err(e) = store.touch(&token.name).await {
+                warn!("更新令牌使用时间失败: {}", e);
+            }
+        }
+        Ok(token)
+    }
+
+    async fn audit(&self, entry: &AuditEntry<'_>) -> Result<()> {
+        let Some(ref path) = self.audit_path else {
+            return Ok(());
+        };
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// 请求头中的 Bearer 令牌
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// 认证中间件：校验令牌权限，通过后执行请求并写入审计日志
+pub async fn require_scope(
+    State((auth, scope)): State<(Arc<ApiAuth>, ApiScope)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = match auth.authenticate(bearer_token(request.headers()), scope).await {
+        Ok(token) => token,
+        Err(e) => {
+            if e != AuthError::Disabled {
+                warn!("{} 接口认证失败: {:?}", scope.as_str(), e);
+            }
+            return e.into_response(scope);
+        }
+    };
+
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let client = request.extensions().get::<ClientIp>().map(|ClientIp(ip)| ip.to_string());
+    // 接口可通过 Extension<ApiToken> 取得调用方的令牌
+    request.extensions_mut().insert(token.clone());
+    let response = next.run(request).await;
+
+    info!("API 调用: {} {} 令牌 {} -> {}", method, path, token.name, response.status());
+    let entry = AuditEntry {
+        time: Utc::now(),
+        token: &token.name,
+        method: &method,
+        path: &path,
+        status: response.status().as_u16(),
+        client,
+    };
+    if let Err(e) = auth.audit(&entry).await {
+        warn!("写入 API 审计日志失败: {}", e);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiTokenConfig;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = ApiConfig {
+            jobs_token: Some("legacy".to_string()),
+            tokens: HashMap::from([(
+                "old".to_string(),
+                ApiTokenConfig {
+                    token: "expired-secret".to_string(),
+                    scopes: vec![ApiScope::Metrics],
+                    expires_at: Some(Utc::now() - chrono::Duration::days(1)),
+                },
+            )]),
+            ..Default::default()
+        };
+        let store = TokenStore::open(&temp_dir.path().join("tokens.db").to_string_lossy())
+            .await
+            .unwrap();
+        let admin = store.create("ops", &[ApiScope::Admin], None).await.unwrap();
+        assert!(admin.starts_with(TOKEN_PREFIX));
+        assert!(store.create("ops", &[ApiScope::Chat], None).await.is_err());
+        let auth = ApiAuth::new(&config).with_store(Some(store));
+
+        assert_eq!(auth.authenticate(Some("legacy"), ApiScope::Jobs).await.unwrap().name, "jobs_token");
+        assert_eq!(auth.authenticate(Some("legacy"), ApiScope::Chat).await.unwrap_err(), AuthError::Forbidden);
+        assert_eq!(auth.authenticate(Some("expired-secret"), ApiScope::Metrics).await.unwrap_err(), AuthError::Expired);
+        assert_eq!(auth.authenticate(None, ApiScope::Jobs).await.unwrap_err(), AuthError::Invalid);
+
+        // admin 令牌拥有全部权限，使用后记录时间
+        assert_eq!(auth.authenticate(Some(&admin), ApiScope::Chat).await.unwrap().name, "ops");
+        let store = auth.store.as_ref().unwrap();
+        assert!(store.list().await.unwrap()[0].last_used_at.is_some());
+
+        assert!(store.revoke("ops").await.unwrap());
+        assert!(!store.revoke("ops").await.unwrap());
+        // 缓存期内接口仍开放，吊销的令牌无效；缓存过期后接口关闭
+        assert_eq!(auth.authenticate(Some(&admin), ApiScope::Chat).await.unwrap_err(), AuthError::Invalid);
+        *auth.scope_cache.lock().unwrap() = None;
+        assert_eq!(auth.authenticate(Some(&admin), ApiScope::Chat).await.unwrap_err(), AuthError::Disabled);
+    }
+}
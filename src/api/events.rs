@@ -0,0 +1,104 @@
+//! 事件流：以 Server-Sent Events 推送事件总线上的主题事件
+//!
+//! `GET /events?topics=agent.*,tool.call` 订阅匹配的主题（支持 `*` / `**` 通配符，默认全部），
+//! 每个事件的 `event` 字段为主题名，`data` 为 JSON 负载
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::Stream;
+use serde::Deserialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::ApiState;
+use crate::bus::{topic_matches, EventBus};
+
+/// 每个连接缓冲的事件数，客户端读取跟不上时丢弃新事件
+const STREAM_BUFFER: usize = 256;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct EventsQuery {
+    /// 逗号分隔的主题模式
+    topics: Option<String>,
+}
+
+/// 解析主题模式列表，未指定时订阅全部主题
+fn parse_topics(topics: Option<&str>) -> Vec<String> {
+    let patterns: Vec<String> = topics
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    if patterns.is_empty() {
+        vec!["**".to_string()]
+    } else {
+        patterns
+    }
+}
+
+/// 连接断开（流被丢弃）时取消主题订阅
+struct Subscription {
+    bus: Arc<EventBus>,
+    id: String,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let bus = self.bus.clone();
+        let id = std::mem::take(&mut self.id);
+        tokio::spawn(async move {
+            let _ = bus.unsubscribe_topic(&id).await;
+        });
+    }
+}
+
+/// 事件流
+pub(super) async fn events(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let patterns = parse_topics(query.topics.as_deref());
+    let (tx, rx) = mpsc::channel::<(String, Value)>(STREAM_BUFFER);
+
+    // 只订阅一次再按模式过滤，模式重叠时同一事件不会重复推送
+    let id = state
+        .event_bus
+        .subscribe_topic("**", move |topic: &str, payload: &Value| {
+            if patterns.iter().any(|p| topic_matches(p, topic))
+                && tx.try_send((topic.to_string(), payload.clone())).is_err()
+            {
+                debug!("事件流缓冲区已满或连接已断开，丢弃事件: {}", topic);
+            }
+        })
+        .await;
+    let subscription = Subscription {
+        bus: state.event_bus.clone(),
+        id,
+    };
+
+    let stream = futures_util::stream::unfold((rx, subscription), |(mut rx, subscription)| async move {
+        let (topic, payload) = rx.recv().await?;
+        let event = Event::default().event(topic).data(payload.to_string());
+        Some((Ok(event), (rx, subscription)))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_topics() {
+        assert_eq!(parse_topics(None), vec!["**"]);
+        assert_eq!(parse_topics(Some(" , ")), vec!["**"]);
+        assert_eq!(parse_topics(Some("agent.*, tool.call,")), vec!["agent.*", "tool.call"]);
+    }
+}
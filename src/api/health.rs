@@ -0,0 +1,190 @@
+//! 存活与就绪检查
+//!
+//! - `/healthz`：进程存活即返回 200
+//! - `/readyz`：提供商可连通、通道均在运行、数据库可写时返回 200，否则返回 503
+//!
+//! 提供商连通性检查结果缓存一段时间，避免频繁的探针请求打到上游
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::channel::health::{ChannelState, HealthRegistry};
+use crate::config::Config;
+
+/// 提供商连通性检查超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// 提供商检查结果缓存时间
+const PROBE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// 就绪检查报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadyReport {
+    pub ready: bool,
+    pub checks: Vec<Check>,
+}
+
+/// 就绪检查
+pub struct Readiness {
+    /// (提供商名称, 配置的 base_url)
+    providers: Vec<(String, Option<String>)>,
+    channels: HealthRegistry,
+    workspace: PathBuf,
+    databases: Vec<PathBuf>,
+    client: reqwest::Client,
+    provider_cache: Mutex<Option<(Instant, Vec<Check>)>>,
+}
+
+impl Readiness {
+    /// `providers` 为已启用的提供商名称
+    pub fn new(config: &Config, providers: Vec<String>, channels: HealthRegistry) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|name| {
+                let url = config.llm.provider(&name).and_then(|p| p.base_url.clone());
+                (name, url)
+            })
+            .collect();
+        Self {
+            providers,
+            channels,
+            workspace: config.memory.workspace_path.clone(),
+            databases: config.database_paths(),
+            client: reqwest::Client::builder()
+                .timeout(PROBE_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            provider_cache: Mutex::new(None),
+        }
+    }
+
+    /// 执行所有检查
+    pub async fn check(&self) -> ReadyReport {
+        let mut checks = self.check_providers().await;
+        checks.extend(self.check_channels());
+        checks.extend(self.check_storage());
+        ReadyReport {
+            ready: checks.iter().all(|c| c.ok),
+            checks,
+        }
+    }
+
+    /// 提供商可连通（收到任意 HTTP 响应即可，未配置 base_url 时只检查是否已配置）
+    async fn check_providers(&self) -> Vec<Check> {
+        let mut cache = self.provider_cache.lock().await;
+        if let Some((at, ref checks)) = *cache {
+            if at.elapsed() < PROBE_CACHE_TTL {
+                return checks.clone();
+            }
+        }
+
+        let mut checks = Vec::new();
+        if self.providers.is_empty() {
+            checks.push(Check::new("provider", false, "没有可用的 LLM 提供商"));
+        }
+        for (name, url) in &self.providers {
+            let check_name = format!("provider:{}", name);
+            let check = match url {
+                Some(url) => match self.client.get(url).send().await {
+                    Ok(resp) => Check::new(check_name, true, format!("HTTP {}", resp.status().as_u16())),
+                    Err(e) => Check::new(check_name, false, format!("无法连接 {}: {}", url, e)),
+                },
+                None => Check::new(check_name, true, "已配置（使用默认端点，未检查连通性）"),
+            };
+            checks.push(check);
+        }
+
+        *cache = Some((Instant::now(), checks.clone()));
+        checks
+    }
+
+    /// 通道均在运行
+    fn check_channels(&self) -> Vec<Check> {
+        let snapshot = self.channels.snapshot();
+        if snapshot.channels.is_empty() {
+            return vec![Check::new("channel", false, "没有运行中的通道")];
+        }
+        snapshot
+            .channels
+            .iter()
+            .map(|h| {
+                let ok = h.state == ChannelState::Running;
+                let mut detail = h.state.as_str().to_string();
+                if let (false, Some(ref e)) = (ok, &h.last_error) {
+                    detail = format!("{}: {}", detail, e);
+                }
+                Check::new(format!("channel:{}", h.name), ok, detail)
+            })
+            .collect()
+    }
+
+    /// 工作目录和已存在的数据库可写
+    fn check_storage(&self) -> Vec<Check> {
+        let probe = self.workspace.join(".readyz");
+        let mut checks = vec![match std::fs::write(&probe, b"ok").and_then(|_| std::fs::remove_file(&probe)) {
+            Ok(()) => Check::new("workspace", true, self.workspace.display().to_string()),
+            Err(e) => Check::new("workspace", false, format!("{} 不可写: {}", self.workspace.display(), e)),
+        }];
+
+        for path in self.databases.iter().filter(|p| p.exists()) {
+            let name = format!(
+                "db:{}",
+                path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+            );
+            // 以写方式打开但不修改内容
+            checks.push(match std::fs::OpenOptions::new().write(true).open(path) {
+                Ok(_) => Check::new(name, true, "可写"),
+                Err(e) => Check::new(name, false, format!("不可写: {}", e)),
+            });
+        }
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        std::fs::write(config.sessions_db_path(), b"").unwrap();
+
+        let channels = HealthRegistry::new();
+        let readiness = Readiness::new(&config, vec!["mock".to_string()], channels.clone());
+
+        // 通道尚未启动
+        let report = readiness.check().await;
+        assert!(!report.ready);
+        assert!(report.checks.iter().any(|c| c.name == "channel" && !c.ok));
+        assert!(report.checks.iter().any(|c| c.name == "db:sessions.db" && c.ok));
+
+        channels.set_state("telegram", ChannelState::Running);
+        let report = readiness.check().await;
+        assert!(report.ready, "{:?}", report.checks);
+
+        channels.set_state("telegram", ChannelState::Restarting);
+        assert!(!readiness.check().await.ready);
+    }
+}
@@ -0,0 +1,615 @@
+//! HTTP API 服务模块
+//!
+//! 基于 axum 对外提供 HTTP 接口，与通道的 Webhook 路由共用 [`server::HttpServer`] 的监听地址：
+//! - `POST /hooks/<job_id>`：触发 Webhook 任务，请求体 JSON 作为任务参数
+//! - `GET /healthz`、`GET /readyz`：存活与就绪检查（Docker HEALTHCHECK、k8s 探针），不需要令牌
+//! - `POST /chat`：与 Agent 对话，会话按令牌名和请求中的 chat_id 区分（需要 chat 权限的令牌）
+//! - `POST /broadcast`：不经过 Agent 直接向通道发送通知（需要 admin 权限的令牌）
+//! - `GET /channels/health`：各通道的运行状态、收发时间和错误计数（需要 metrics 权限的令牌）
+//! - `GET /events?topics=<模式>`：以 Server-Sent Events 实时推送消息、工具调用、会话、任务等事件（需要 events 权限的令牌）
+//! - `GET/POST /jobs`（`?all=true` 包括已完成的一次性任务）、`POST /jobs/<id>/pause|resume`、`DELETE /jobs/<id>`：管理定时任务（需要 jobs 权限的令牌）
+
+use anyhow::Result;
+use axum::{
+    body::Bytes,
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+pub mod auth;
+mod events;
+pub mod health;
+pub mod server;
+
+use auth::{ApiAuth, ApiToken};
+
+use crate::bus::EventBus;
+use crate::channel::health::HealthRegistry;
+use crate::channel::{BroadcastTarget, ChannelManager, InboundMessage, MessageHandler};
+use crate::config::ApiScope;
+use crate::cron::delivery::Delivery;
+use crate::cron::{Job, JobStatus, JobType, MisfirePolicy, Scheduler, MAX_INTERVAL_SECS};
+
+/// Webhook 密钥请求头
+const HOOK_TOKEN_HEADER: &str = "x-hook-token";
+
+/// API 服务共享状态
+pub struct ApiState {
+    pub scheduler: Arc<Scheduler>,
+    /// 通道健康状态
+    pub channels: HealthRegistry,
+    /// 就绪检查
+    pub readiness: health::Readiness,
+    /// 通道管理器（广播）
+    pub manager: Arc<ChannelManager>,
+    /// 对话请求交给与通道相同的消息处理器（`api` 通道）
+    pub handler: Arc<dyn MessageHandler>,
+    /// 令牌认证（对话需要 chat 权限，广播需要 admin 权限，任务管理需要 jobs 权限，通道状态需要 metrics 权限，事件流需要 events 权限）
+    pub auth: Arc<ApiAuth>,
+    /// 事件总线（`/events` 推送）
+    pub event_bus: Arc<EventBus>,
+    /// 允许远程创建任务的处理器（为空时不允许远程创建任务）
+    pub job_handlers: Vec<String>,
+}
+
+/// 构建路由
+pub fn router(state: Arc<ApiState>) -> Router {
+    let require = |scope| middleware::from_fn_with_state((state.auth.clone(), scope), auth::require_scope);
+    let chat = Router::new()
+        .route("/chat", post(chat))
+        .route_layer(require(ApiScope::Chat));
+    let admin = Router::new()
+        .route("/broadcast", post(broadcast))
+        .route_layer(require(ApiScope::Admin));
+    let jobs = Router::new()
+        .route("/jobs", get(list_jobs).post(create_job))
+        .route("/jobs/:job_id", delete(delete_job))
+        .route("/jobs/:job_id/pause", post(pause_job))
+        .route("/jobs/:job_id/resume", post(resume_job))
+        .route_layer(require(ApiScope::Jobs));
+    let metrics = Router::new()
+        .route("/channels/health", get(channel_health))
+        .route_layer(require(ApiScope::Metrics));
+    // 事件流包含消息内容和工具参数、结果，不随 metrics 权限开放
+    let events = Router::new()
+        .route("/events", get(events::events))
+        .route_layer(require(ApiScope::Events));
+    Router::new()
+        .route("/hooks/:job_id", post(trigger_hook))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .merge(chat)
+        .merge(admin)
+        .merge(jobs)
+        .merge(metrics)
+        .merge(events)
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct HookQuery {
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListJobsQuery {
+    #[serde(default)]
+    all: bool,
+}
+
+/// 对话请求
+#[derive(Debug, Deserialize)]
+struct ChatRequest {
+    content: String,
+    /// 区分同一令牌下的多个会话（默认 default）
+    #[serde(default)]
+    chat_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BroadcastRequest {
+    targets: Vec<BroadcastTarget>,
+    content: String,
+}
+
+/// 创建任务的请求
+#[derive(Debug, Deserialize)]
+struct CreateJobRequest {
+    name: String,
+    handler: String,
+    schedule: ScheduleSpec,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    args: Option<Value>,
+    #[serde(default)]
+    max_runs: Option<i64>,
+    #[serde(default)]
+    delivery: Option<Delivery>,
+    /// 错过执行策略（skip、run_once、run_all），未设置时使用调度方式的默认值
+    #[serde(default)]
+    misfire: Option<MisfirePolicy>,
+}
+
+/// 调度方式：`{"cron": "0 0 9 * * *"}`、`{"interval": 3600}`、`{"once": "<RFC 3339 时间>"}` 或 `"webhook"`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScheduleSpec {
+    Cron(String),
+    Interval(u64),
+    Once(DateTime<Utc>),
+    Webhook,
+}
+
+impl CreateJobRequest {
+    /// 校验请求并构造任务
+    fn into_job(self) -> std::result::Result<Job, String> {
+        if self.name.trim().is_empty() {
+            return Err("name 不能为空".to_string());
+        }
+        let job = match self.schedule {
+            ScheduleSpec::Cron(expression) => {
+                cron::Schedule::from_str(&expression)
+                    .map_err(|e| format!("Cron 表达式无效 {}: {}", expression, e))?;
+                Job::new_cron(self.name, expression, self.handler)
+            }
+            ScheduleSpec::Interval(0) => return Err("interval 必须大于 0".to_string()),
+            ScheduleSpec::Interval(seconds) if seconds > MAX_INTERVAL_SECS => {
+                return Err(format!("interval 不能超过 {} 秒", MAX_INTERVAL_SECS))
+            }
+            ScheduleSpec::Interval(seconds) => Job::new_interval(self.name, seconds, self.handler),
+            ScheduleSpec::Once(run_at) => {
+                let job = Job::new_once(self.name, run_at, self.handler);
+                job.check_run_at(Utc::now()).map_err(|e| e.to_string())?;
+                job
+            }
+            ScheduleSpec::Webhook => Job::new_webhook(self.name, self.handler),
+        };
+        let job = match self.description {
+            Some(description) => job.with_description(description),
+            None => job,
+        };
+        let job = match self.args {
+            Some(args) => job.with_args(args),
+            None => job,
+        };
+        let job = match self.max_runs {
+            Some(max) => job.with_max_runs(max),
+            None => job,
+        };
+        let job = match self.misfire {
+            Some(policy) => job.with_misfire_policy(policy),
+            None => job,
+        };
+        Ok(match self.delivery {
+            Some(delivery) => job.with_delivery(delivery),
+            None => job,
+        })
+    }
+}
+
+pub(crate) fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+/// 常量时间比较，避免通过响应时间猜测密钥
+pub(crate) fn secret_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// 通道健康状态
+async fn channel_health(State(state): State<Arc<ApiState>>) -> Response {
+    Json(state.channels.snapshot()).into_response()
+}
+
+/// 存活检查：进程能响应即为存活
+async fn healthz() -> Response {
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+/// 就绪检查：未就绪时返回 503
+async fn readyz(State(state): State<Arc<ApiState>>) -> Response {
+    let report = state.readiness.check().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// 与 Agent 对话：会话为 `api:<令牌名>/<chat_id>`，与通道消息一样排队处理、支持会话命令
+async fn chat(
+    State(state): State<Arc<ApiState>>,
+    Extension(token): Extension<ApiToken>,
+    Json(request): Json<ChatRequest>,
+) -> Response {
+    if request.content.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "content 不能为空");
+    }
+    let chat_id = request.chat_id.unwrap_or_else(|| "default".to_string());
+    let msg = InboundMessage::new("api", format!("{}/{}", token.name, chat_id), token.name.as_str(), request.content);
+    match state.handler.handle(msg).await {
+        Ok(reply) => Json(json!({ "reply": reply })).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("处理失败: {}", e)),
+    }
+}
+
+/// 广播通知：向请求中的每个目标发送同一条消息
+async fn broadcast(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<BroadcastRequest>,
+) -> Response {
+    if request.content.trim().is_empty() || request.targets.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "targets 和 content 不能为空");
+    }
+
+    let results = state.manager.broadcast(&request.targets, &request.content).await;
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    Json(json!({
+        "sent": results.len() - failed,
+        "failed": failed,
+        "results": results,
+    }))
+    .into_response()
+}
+
+/// 列出定时任务
+async fn list_jobs(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ListJobsQuery>,
+) -> Response {
+    let mut jobs = if query.all {
+        state.scheduler.list_all_jobs().await
+    } else {
+        state.scheduler.list_jobs().await
+    };
+    jobs.sort_by_key(|j| j.created_at);
+    Json(json!({ "jobs": jobs })).into_response()
+}
+
+/// 创建定时任务（只允许白名单中已注册的处理器）
+async fn create_job(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CreateJobRequest>,
+) -> Response {
+    if !state.job_handlers.contains(&request.handler) || !state.scheduler.handler_names().await.contains(&request.handler) {
+        return error_response(StatusCode::BAD_REQUEST, format!("不允许创建处理器为 {} 的任务", request.handler));
+    }
+    let job = match request.into_job() {
+        Ok(job) => job,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    match state.scheduler.add_job(job.clone()).await {
+        Ok(_) => {
+            info!("通过 API 创建任务: {} ({})", job.name, job.id);
+            (StatusCode::CREATED, Json(job)).into_response()
+        }
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// 暂停任务
+async fn pause_job(State(state): State<Arc<ApiState>>, Path(job_id): Path<String>) -> Response {
+    job_result(&state, &job_id, state.scheduler.pause_job(&job_id).await).await
+}
+
+/// 恢复任务
+async fn resume_job(State(state): State<Arc<ApiState>>, Path(job_id): Path<String>) -> Response {
+    job_result(&state, &job_id, state.scheduler.resume_job(&job_id).await).await
+}
+
+/// 删除任务
+async fn delete_job(State(state): State<Arc<ApiState>>, Path(job_id): Path<String>) -> Response {
+    if state.scheduler.get_job(&job_id).await.is_none() {
+        return error_response(StatusCode::NOT_FOUND, "任务不存在");
+    }
+    match state.scheduler.remove_job(&job_id).await {
+        Ok(()) => Json(json!({ "status": "deleted", "job_id": job_id })).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// 暂停/恢复的结果：成功时返回最新的任务
+async fn job_result(state: &ApiState, job_id: &str, result: Result<()>) -> Response {
+    let Some(job) = state.scheduler.get_job(job_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "任务不存在");
+    };
+    match result {
+        Ok(()) => Json(job).into_response(),
+        Err(e) => error_response(StatusCode::CONFLICT, e.to_string()),
+    }
+}
+
+/// 触发 Webhook 任务
+async fn trigger_hook(
+    State(state): State<Arc<ApiState>>,
+    Path(job_id): Path<String>,
+    Query(query): Query<HookQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let job = match state.scheduler.get_job(&job_id).await {
+        Some(job) => job,
+        None => return error_response(StatusCode::NOT_FOUND, "任务不存在"),
+    };
+
+    let secret = match &job.job_type {
+        JobType::Webhook { secret } => secret,
+        _ => return error_response(StatusCode::NOT_FOUND, "任务不存在"),
+    };
+
+    let token = headers
+        .get(HOOK_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(query.token);
+    if !token.map(|t| secret_matches(secret, &t)).unwrap_or(false) {
+        warn!("Webhook 密钥校验失败: {}", job_id);
+        return error_response(StatusCode::UNAUTHORIZED, "密钥无效");
+    }
+
+    if job.status == JobStatus::Paused {
+        return error_response(StatusCode::CONFLICT, "任务已暂停");
+    }
+
+    let payload = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                return error_response(StatusCode::BAD_REQUEST, format!("请求体不是合法 JSON: {}", e))
+            }
+        }
+    };
+
+    match state.scheduler.trigger_job(&job_id, payload).await {
+        Ok(_) => {
+            info!("Webhook 触发任务: {} ({})", job.name, job_id);
+            (
+                StatusCode::ACCEPTED,
+                Json(json!({ "status": "accepted", "job_id": job_id })),
+            )
+                .into_response()
+        }
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_matches() {
+        assert!(secret_matches("abc123", "abc123"));
+        assert!(!secret_matches("abc123", "abc124"));
+        assert!(!secret_matches("abc123", "abc"));
+        assert!(!secret_matches("abc123", ""));
+    }
+
+    #[test]
+    fn test_create_job_request() {
+        let request: CreateJobRequest = serde_json::from_value(json!({
+            "name": "早报",
+            "handler": "briefing",
+            "schedule": {"cron": "0 0 8 * * *"},
+            "args": {"chat_id": "123"},
+            "delivery": {"sink": "file"},
+        }))
+        .unwrap();
+        let job = request.into_job().unwrap();
+        assert_eq!(job.delivery, Some(Delivery::File { format: None }));
+        assert_eq!(job.job_type, JobType::Cron { expression: "0 0 8 * * *".to_string() });
+        assert_eq!(job.handler_args, Some(json!({"chat_id": "123"})));
+        assert_eq!(job.misfire_policy, MisfirePolicy::Skip);
+
+        let catch_up: CreateJobRequest = serde_json::from_value(json!({
+            "name": "日报",
+            "handler": "briefing",
+            "schedule": {"interval": 86400},
+            "misfire": "run_all",
+        }))
+        .unwrap();
+        assert_eq!(catch_up.into_job().unwrap().misfire_policy, MisfirePolicy::RunAll);
+        assert!(serde_json::from_value::<CreateJobRequest>(json!({
+            "name": "x", "handler": "reminder", "schedule": {"interval": 60}, "misfire": "sometimes",
+        }))
+        .is_err());
+
+        let webhook: CreateJobRequest =
+            serde_json::from_value(json!({"name": "hook", "handler": "reminder", "schedule": "webhook"})).unwrap();
+        assert!(matches!(webhook.into_job().unwrap().job_type, JobType::Webhook { .. }));
+
+        let invalid: CreateJobRequest =
+            serde_json::from_value(json!({"name": "x", "handler": "reminder", "schedule": {"cron": "每天"}})).unwrap();
+        assert!(invalid.into_job().is_err());
+        let zero: CreateJobRequest =
+            serde_json::from_value(json!({"name": "x", "handler": "reminder", "schedule": {"interval": 0}})).unwrap();
+        assert!(zero.into_job().is_err());
+        let huge: CreateJobRequest = serde_json::from_value(
+            json!({"name": "x", "handler": "reminder", "schedule": {"interval": u64::MAX}}),
+        )
+        .unwrap();
+        assert!(huge.into_job().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_and_scoped_routes() {
+        use crate::config::{ApiConfig, ApiTokenConfig, Config};
+        use std::collections::HashMap;
+
+        // 回显会话和内容的消息处理器
+        struct Echo;
+
+        #[async_trait::async_trait]
+        impl MessageHandler for Echo {
+            async fn handle(&self, msg: InboundMessage) -> Result<String> {
+                Ok(format!("{} {} {}", msg.session_key(), msg.sender, msg.content))
+            }
+        }
+
+        let token = |secret: &str, scope| ApiTokenConfig {
+            token: secret.to_string(),
+            scopes: vec![scope],
+            expires_at: None,
+        };
+        let api = ApiConfig {
+            tokens: HashMap::from([
+                ("app".to_string(), token("chat-secret", ApiScope::Chat)),
+                ("scraper".to_string(), token("metrics-secret", ApiScope::Metrics)),
+            ]),
+            ..Default::default()
+        };
+        let manager = Arc::new(ChannelManager::new());
+        let state = Arc::new(ApiState {
+            scheduler: Scheduler::new().await.unwrap(),
+            channels: manager.health(),
+            readiness: health::Readiness::new(&Config::default(), Vec::new(), manager.health()),
+            manager,
+            handler: Arc::new(Echo),
+            auth: Arc::new(ApiAuth::new(&api)),
+            event_bus: EventBus::new(),
+            job_handlers: Vec::new(),
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+
+        let client = reqwest::Client::new();
+        let chat = |token: &str, body: Value| {
+            client
+                .post(format!("http://{}/chat", addr))
+                .bearer_auth(token)
+                .json(&body)
+                .send()
+        };
+        let response = chat("chat-secret", json!({"content": "你好", "chat_id": "x"})).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["reply"], "api:app/x app 你好");
+        assert_eq!(chat("wrong", json!({"content": "你好"})).await.unwrap().status().as_u16(), 401);
+        assert_eq!(chat("chat-secret", json!({"content": " "})).await.unwrap().status().as_u16(), 400);
+
+        // 通道状态需要 metrics 权限，事件流需要单独的 events 权限（没有令牌拥有时不开放），探针不需要令牌
+        let get = |path: &str, token: &str| client.get(format!("http://{}{}", addr, path)).bearer_auth(token).send();
+        assert_eq!(get("/channels/health", "chat-secret").await.unwrap().status().as_u16(), 403);
+        assert_eq!(get("/channels/health", "metrics-secret").await.unwrap().status().as_u16(), 200);
+        assert_eq!(get("/events", "metrics-secret").await.unwrap().status().as_u16(), 404);
+        assert_eq!(get("/healthz", "").await.unwrap().status().as_u16(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_create_and_trigger_webhook_job() {
+        use crate::config::{ApiConfig, ApiTokenConfig, Config};
+        use crate::cron::JobHandler;
+        use std::collections::HashMap;
+        use tokio::sync::mpsc;
+
+        // 把收到的参数转发出来的任务处理器
+        struct Record(mpsc::UnboundedSender<Option<Value>>);
+
+        #[async_trait::async_trait]
+        impl JobHandler for Record {
+            fn name(&self) -> &str {
+                "record"
+            }
+
+            async fn execute(&self, _job: &Job, args: Option<Value>) -> Result<Option<String>> {
+                let _ = self.0.send(args);
+                Ok(None)
+            }
+        }
+
+        struct Echo;
+
+        #[async_trait::async_trait]
+        impl MessageHandler for Echo {
+            async fn handle(&self, msg: InboundMessage) -> Result<String> {
+                Ok(msg.content)
+            }
+        }
+
+        let api = ApiConfig {
+            tokens: HashMap::from([(
+                "ci".to_string(),
+                ApiTokenConfig {
+                    token: "jobs-secret".to_string(),
+                    scopes: vec![ApiScope::Jobs],
+                    expires_at: None,
+                },
+            )]),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let scheduler = Scheduler::new().await.unwrap();
+        scheduler.register_handler(Arc::new(Record(tx))).await;
+        let manager = Arc::new(ChannelManager::new());
+        let state = Arc::new(ApiState {
+            scheduler,
+            channels: manager.health(),
+            readiness: health::Readiness::new(&Config::default(), Vec::new(), manager.health()),
+            manager,
+            handler: Arc::new(Echo),
+            auth: Arc::new(ApiAuth::new(&api)),
+            event_bus: EventBus::new(),
+            job_handlers: vec!["record".to_string()],
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+
+        // 通过任务接口创建 Webhook 任务，响应中带有触发用的密钥
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/jobs", addr))
+            .bearer_auth("jobs-secret")
+            .json(&json!({"name": "部署", "handler": "record", "schedule": "webhook"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let job: Value = response.json().await.unwrap();
+
+        // 只能创建 job_handlers 中列出的处理器
+        let response = client
+            .post(format!("http://{}/jobs", addr))
+            .bearer_auth("jobs-secret")
+            .json(&json!({"name": "x", "handler": "shell_command", "schedule": "webhook"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        let job_id = job["id"].as_str().unwrap();
+        let secret = job["job_type"]["webhook"]["secret"].as_str().unwrap();
+
+        let hook = |token: &str| {
+            client
+                .post(format!("http://{}/hooks/{}", addr, job_id))
+                .header(HOOK_TOKEN_HEADER, token)
+                .json(&json!({"ref": "main"}))
+                .send()
+        };
+        assert_eq!(hook("wrong").await.unwrap().status().as_u16(), 401);
+        assert_eq!(hook(secret).await.unwrap().status().as_u16(), 202);
+        let args = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(args, Some(json!({"ref": "main"})));
+    }
+}
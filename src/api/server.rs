@@ -0,0 +1,333 @@
+//! 共享 HTTP 服务
+//!
+//! API、通道 Webhook 等子系统在注册时提供各自的路由，统一挂在同一个监听地址上，
+//! 部署时只需暴露一个端口。支持反向代理的路径前缀和 X-Forwarded-For，
+//! 没有反向代理时可直接启用 TLS
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use ipnet::IpNet;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rustls::TlsAcceptor;
+use tower_service::Service;
+use tracing::{debug, info, warn};
+
+use crate::config::{ApiConfig, TlsConfig};
+
+/// 经反向代理转发时携带客户端地址的请求头
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// 请求的客户端地址（经受信任代理转发时取 X-Forwarded-For 中的地址），
+/// 由服务写入请求扩展，处理函数可通过 `Extension<ClientIp>` 获取
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// 受信任的反向代理地址
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    /// 解析 IP 或网段列表
+    pub fn parse(entries: &[String]) -> Result<Self> {
+        entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .with_context(|| format!("受信任代理地址无效: {}", entry))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(ip))
+    }
+
+    /// 确定客户端 IP：对端是受信任代理时从右往左查看 X-Forwarded-For，
+    /// 跳过受信任的代理，第一个不受信任的地址即客户端（左侧内容可被客户端伪造）
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let mut client = peer;
+        if !self.contains(&peer) {
+            return client;
+        }
+        for hop in forwarded_for.unwrap_or("").rsplit(',') {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(&ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// 共享的 HTTP 服务
+pub struct HttpServer {
+    bind: String,
+    router: Router,
+    /// 已挂载的子系统（用于启动日志）
+    mounted: Vec<String>,
+    /// 路径前缀（已规范化为 "/xxx"，未设置时为空）
+    base_path: String,
+    proxies: TrustedProxies,
+    tls: Option<TlsAcceptor>,
+}
+
+impl HttpServer {
+    pub fn new(bind: impl Into<String>) -> Self {
+        Self {
+            bind: bind.into(),
+            router: Router::new(),
+            mounted: Vec::new(),
+            base_path: String::new(),
+            proxies: TrustedProxies::default(),
+            tls: None,
+        }
+    }
+
+    /// 按 `[api]` 配置创建（监听地址、路径前缀、受信任代理、TLS 证书）
+    pub fn from_config(config: &ApiConfig) -> Result<Self> {
+        let mut server = Self::new(config.bind.clone());
+        server.base_path = normalize_base_path(config.base_path.as_deref().unwrap_or(""));
+        server.proxies = TrustedProxies::parse(&config.trusted_proxies)?;
+        server.tls = config.tls.as_ref().map(tls_acceptor).transpose()?;
+        Ok(server)
+    }
+
+    /// 挂载子系统的路由，prefix 为空时直接合并到根路径
+    ///
+    /// 路由冲突时 axum 会 panic，各子系统应使用不同的前缀
+    pub fn mount(&mut self, name: &str, prefix: &str, router: Router) {
+        let prefix = prefix.trim_end_matches('/');
+        let current = std::mem::take(&mut self.router);
+        self.router = if prefix.is_empty() {
+            current.merge(router)
+        } else {
+            current.nest(prefix, router)
+        };
+        self.mounted.push(if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}({})", name, prefix)
+        });
+    }
+
+    /// 是否没有挂载任何路由
+    pub fn is_empty(&self) -> bool {
+        self.mounted.is_empty()
+    }
+
+    /// 合并后的路由（加上路径前缀和客户端地址识别）
+    pub fn into_router(self) -> Router {
+        let router = self
+            .router
+            .layer(middleware::from_fn_with_state(Arc::new(self.proxies), resolve_client_ip));
+        if self.base_path.is_empty() {
+            router
+        } else {
+            Router::new().nest(&self.base_path, router)
+        }
+    }
+
+    /// 启动服务（阻塞直到服务退出）
+    pub async fn serve(mut self) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(&self.bind)
+            .await
+            .with_context(|| format!("监听 {} 失败", self.bind))?;
+
+        let tls = self.tls.take();
+        info!(
+            "HTTP 服务已启动: {}://{}{}（{}）",
+            if tls.is_some() { "https" } else { "http" },
+            self.bind,
+            self.base_path,
+            self.mounted.join(", ")
+        );
+        let router = self.into_router();
+        match tls {
+            Some(acceptor) => serve_tls(listener, acceptor, router).await,
+            None => axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .context("HTTP 服务异常退出"),
+        }
+    }
+}
+
+/// 规范化路径前缀："nanobot/" -> "/nanobot"，"/" 或空串表示不使用前缀
+fn normalize_base_path(path: &str) -> String {
+    let path = path.trim().trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+/// 读取 PEM 格式的证书链和私钥
+fn tls_acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("读取 TLS 文件失败: {}", path.display()))
+    };
+
+    let certs: Vec<_> = rustls_pemfile::certs(&mut open(&config.cert_path)?)
+        .with_context(|| format!("解析证书失败: {}", config.cert_path.display()))?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+    if certs.is_empty() {
+        bail!("证书文件中没有证书: {}", config.cert_path.display());
+    }
+
+    let mut reader = open(&config.key_path)?;
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .with_context(|| format!("解析私钥失败: {}", config.key_path.display()))?
+        {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => break rustls::PrivateKey(key),
+            Some(_) => continue,
+            None => bail!("私钥文件中没有私钥: {}", config.key_path.display()),
+        }
+    };
+
+    let mut server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("TLS 证书与私钥不匹配")?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// 接受 TLS 连接并交给路由处理
+async fn serve_tls(listener: tokio::net::TcpListener, acceptor: TlsAcceptor, router: Router) -> Result<()> {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // 文件描述符耗尽等错误时稍等再接受，避免空转
+                warn!("接受连接失败: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let router = router.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("TLS 握手失败 {}: {}", peer, e);
+                    return;
+                }
+            };
+            let service = hyper::service::service_fn(move |mut request: Request<hyper::body::Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(peer));
+                router.clone().call(request)
+            });
+            if let Err(e) = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("连接 {} 异常结束: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// 识别客户端地址写入请求扩展，拒绝访问的请求记录客户端地址用于审计
+async fn resolve_client_ip(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| {
+            let forwarded_for = request
+                .headers()
+                .get(FORWARDED_FOR_HEADER)
+                .and_then(|v| v.to_str().ok());
+            proxies.client_ip(peer.ip(), forwarded_for)
+        });
+    if let Some(ip) = client {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    if let (Some(ip), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) = (client, response.status()) {
+        warn!("拒绝来自 {} 的请求: {} {}（{}）", ip, method, path, response.status());
+    } else {
+        debug!("{} {} {} -> {}", client.map(|ip| ip.to_string()).unwrap_or_default(), method, path, response.status());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn test_mount_routes() {
+        let mut server = HttpServer::from_config(&ApiConfig {
+            base_path: Some("nanobot/".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(server.is_empty());
+        server.mount("api", "", Router::new().route("/healthz", get(|| async { "ok" })));
+        server.mount("feishu", "/feishu/", Router::new().route("/events", get(|| async { "feishu" })));
+        assert!(!server.is_empty());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = server.into_router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let fetch = |path: &str| client.get(format!("http://{}{}", addr, path)).send();
+        assert_eq!(fetch("/nanobot/healthz").await.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(fetch("/nanobot/feishu/events").await.unwrap().text().await.unwrap(), "feishu");
+        assert_eq!(fetch("/healthz").await.unwrap().status(), 404);
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let proxies = TrustedProxies::parse(&["127.0.0.1".to_string(), "10.0.0.0/8".to_string()]).unwrap();
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // 不受信任的对端直接使用对端地址
+        assert_eq!(proxies.client_ip(ip("203.0.113.9"), Some("1.2.3.4")), ip("203.0.113.9"));
+        // 跳过受信任的代理，客户端伪造的左侧地址不生效
+        assert_eq!(
+            proxies.client_ip(ip("127.0.0.1"), Some("6.6.6.6, 198.51.100.7, 10.1.2.3")),
+            ip("198.51.100.7")
+        );
+        assert_eq!(proxies.client_ip(ip("127.0.0.1"), None), ip("127.0.0.1"));
+        assert!(TrustedProxies::parse(&["proxy.local".to_string()]).is_err());
+    }
+}
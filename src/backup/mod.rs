@@ -0,0 +1,507 @@
+//! 备份与恢复
+//!
+//! `nanobot backup create` 把配置文件、memory 目录（笔记、长期记忆、对话历史）、工作目录中的
+//! SQLite 数据库（会话、提醒、发件箱、用量等）和 vault.json 打包为一个 zstd 压缩的 tar 归档。
+//! 归档中的 `manifest.json` 记录每个文件的大小和 SHA-256，恢复前逐一校验。
+//!
+//! 配置中的密钥（API Key、Bot Token 等）默认不写入归档；`--include-secrets` 时单独保存在
+//! `secrets.toml.enc` 中，使用 vault 口令加密，恢复时用同一口令解密后合并回配置。
+//! 回收站、媒体文件、调试日志和缓存不备份
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+use crate::vault::Vault;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const CONFIG_NAME: &str = "config.toml";
+const SECRETS_NAME: &str = "secrets.toml.enc";
+/// 工作目录中的文件在归档中的前缀
+const WORKSPACE_PREFIX: &str = "workspace";
+const MANIFEST_VERSION: u32 = 1;
+const ZSTD_LEVEL: i32 = 3;
+
+/// 配置中密钥的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsMode {
+    /// 不包含密钥
+    Excluded,
+    /// 使用 vault 口令加密保存
+    Encrypted,
+}
+
+/// 归档清单
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub nanobot_version: String,
+    pub secrets: SecretsMode,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// 归档中的路径
+    pub path: String,
+    pub size: u64,
+    /// SHA-256（hex）
+    pub sha256: String,
+}
+
+/// 恢复结果
+#[derive(Debug, Default)]
+pub struct RestoreSummary {
+    /// 恢复的文件数（含配置文件）
+    pub files: usize,
+    /// 归档中的 workspace_path 与本机不同，已改为本机工作目录
+    pub workspace_rewritten: bool,
+    /// 已恢复加密保存的密钥
+    pub secrets_restored: bool,
+    /// 密钥解密失败的原因（配置文件不含密钥）
+    pub secrets_error: Option<String>,
+}
+
+/// 创建备份归档，`vault` 不为 None 时加密保存配置中的密钥
+pub fn create(config: &Config, config_path: &Path, output: &Path, vault: Option<&Vault>) -> Result<Manifest> {
+    let mut contents: Vec<(String, Vec<u8>)> = Vec::new();
+    if config_path.exists() {
+        let text = std::fs::read_to_string(config_path)
+            .with_context(|| format!("读取配置文件失败: {}", config_path.display()))?;
+        let mut value: toml::Value = toml::from_str(&text).context("解析配置文件失败")?;
+        let secrets = take_secrets(&mut value);
+        contents.push((CONFIG_NAME.to_string(), toml::to_string_pretty(&value)?.into_bytes()));
+        if let (Some(vault), Some(secrets)) = (vault, secrets) {
+            contents.push((SECRETS_NAME.to_string(), vault.encrypt(toml::to_string(&secrets)?.as_bytes())?));
+        }
+    }
+
+    let files = workspace_files(config)?;
+    let mut manifest = Manifest {
+        version: MANIFEST_VERSION,
+        created_at: Utc::now(),
+        nanobot_version: env!("CARGO_PKG_VERSION").to_string(),
+        secrets: if vault.is_some() { SecretsMode::Encrypted } else { SecretsMode::Excluded },
+        files: Vec::new(),
+    };
+    for (name, data) in &contents {
+        manifest.files.push(ManifestEntry {
+            path: name.clone(),
+            size: data.len() as u64,
+            sha256: hex::encode(Sha256::digest(data)),
+        });
+    }
+    for (name, path) in &files {
+        manifest.files.push(hash_file(name, path)?);
+    }
+
+    let result = write_archive(output, &manifest, &contents, &files);
+    if result.is_err() {
+        let _ = std::fs::remove_file(output);
+    }
+    result.map(|()| manifest)
+}
+
+fn write_archive(
+    output: &Path,
+    manifest: &Manifest,
+    contents: &[(String, Vec<u8>)],
+    files: &[(String, PathBuf)],
+) -> Result<()> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(output).with_context(|| format!("创建归档失败: {}", output.display()))?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    append_bytes(&mut builder, MANIFEST_NAME, &serde_json::to_vec_pretty(manifest)?)?;
+    for (name, data) in contents {
+        append_bytes(&mut builder, name, data)?;
+    }
+    for (name, path) in files {
+        builder
+            .append_path_with_name(path, name)
+            .with_context(|| format!("写入归档失败: {}", path.display()))?;
+    }
+
+    builder.into_inner()?.finish()?.flush()?;
+    Ok(())
+}
+
+fn append_bytes<W: Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+/// 工作目录中需要备份的文件（归档路径，本地路径）
+fn workspace_files(config: &Config) -> Result<Vec<(String, PathBuf)>> {
+    let workspace = &config.memory.workspace_path;
+    let mut files = Vec::new();
+
+    let mut dirs = vec![workspace.join("memory")];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            let file_type = std::fs::symlink_metadata(&path)?.file_type();
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if file_type.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    for path in config.database_paths() {
+        if !path.exists() {
+            continue;
+        }
+        // 有 WAL 文件说明数据库仍在使用，复制出的文件可能不完整
+        if wal_path(&path).exists() {
+            bail!("数据库正在使用: {}，请先停止 gateway", path.display());
+        }
+        files.push(path);
+    }
+    let header = config.vault_header_path();
+    if header.exists() {
+        files.push(header);
+    }
+
+    let mut named = Vec::with_capacity(files.len());
+    for path in files {
+        let relative = path.strip_prefix(workspace)?;
+        let name = Path::new(WORKSPACE_PREFIX).join(relative);
+        named.push((name.to_string_lossy().replace('\\', "/"), path));
+    }
+    named.sort();
+    Ok(named)
+}
+
+fn hash_file(name: &str, path: &Path) -> Result<ManifestEntry> {
+    let mut file = BufReader::new(File::open(path).with_context(|| format!("读取文件失败: {}", path.display()))?);
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)?;
+    Ok(ManifestEntry {
+        path: name.to_string(),
+        size,
+        sha256: hex::encode(hasher.finalize()),
+    })
+}
+
+/// 从归档恢复：配置文件写到 `config_path`，工作目录中的文件写到 `config` 的工作目录
+///
+/// 目标文件已存在时需要 `force`。所有文件先解压到临时目录并按清单校验，校验通过后才写入
+pub fn restore(archive: &Path, config: &Config, config_path: &Path, force: bool) -> Result<RestoreSummary> {
+    let staging = StagingDir::new()?;
+    let manifest = unpack(archive, &staging.0)?;
+
+    let workspace = &config.memory.workspace_path;
+    let mut targets = Vec::new();
+    for entry in &manifest.files {
+        let target = match entry.path.as_str() {
+            CONFIG_NAME => config_path.to_path_buf(),
+            SECRETS_NAME => continue,
+            path => match path.strip_prefix(&format!("{}/", WORKSPACE_PREFIX)) {
+                Some(relative) => workspace.join(relative),
+                None => bail!("归档中有无法识别的文件: {}", path),
+            },
+        };
+        targets.push((entry.path.clone(), target));
+    }
+
+    if !force {
+        if let Some((_, target)) = targets.iter().find(|(_, target)| target.exists()) {
+            bail!("{} 已存在，使用 --force 覆盖", target.display());
+        }
+    }
+    for path in config.database_paths() {
+        if wal_path(&path).exists() {
+            bail!("数据库正在使用: {}，请先停止 gateway", path.display());
+        }
+    }
+
+    let mut summary = RestoreSummary::default();
+    for (name, target) in &targets {
+        if name == CONFIG_NAME {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(staging.0.join(name), target).with_context(|| format!("写入 {} 失败", target.display()))?;
+        summary.files += 1;
+    }
+
+    // 配置文件最后写入：工作目录中的 vault.json 已恢复，可以解密密钥
+    if targets.iter().any(|(name, _)| name == CONFIG_NAME) {
+        let text = std::fs::read_to_string(staging.0.join(CONFIG_NAME))?;
+        let mut value: toml::Value = toml::from_str(&text).context("解析归档中的配置文件失败")?;
+        summary.workspace_rewritten = set_workspace_path(&mut value, workspace);
+
+        let secrets_path = staging.0.join(SECRETS_NAME);
+        if secrets_path.exists() {
+            match decrypt_secrets(config, &secrets_path) {
+                Ok(secrets) => {
+                    merge(&mut value, secrets);
+                    summary.secrets_restored = true;
+                }
+                Err(e) => summary.secrets_error = Some(e.to_string()),
+            }
+        }
+
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(config_path, toml::to_string_pretty(&value)?)
+            .with_context(|| format!("写入配置文件失败: {}", config_path.display()))?;
+        summary.files += 1;
+    }
+
+    Ok(summary)
+}
+
+/// 解压归档到目录并按清单校验，返回清单
+fn unpack(archive: &Path, dir: &Path) -> Result<Manifest> {
+    let file = File::open(archive).with_context(|| format!("打开归档失败: {}", archive.display()))?;
+    let decoder = zstd::Decoder::new(file).context("不是有效的备份归档")?;
+    let mut tar = tar::Archive::new(decoder);
+
+    let mut names = HashSet::new();
+    for entry in tar.entries().context("读取归档失败")? {
+        let mut entry = entry.context("读取归档失败")?;
+        let path = entry.path()?.into_owned();
+        let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+        if !safe || !entry.header().entry_type().is_file() {
+            bail!("归档中有不安全的条目: {}", path.display());
+        }
+        entry.unpack_in(dir)?;
+        names.insert(path.to_string_lossy().replace('\\', "/"));
+    }
+
+    let manifest_path = dir.join(MANIFEST_NAME);
+    if !names.remove(MANIFEST_NAME) {
+        bail!("归档中没有 {}", MANIFEST_NAME);
+    }
+    let manifest: Manifest = serde_json::from_slice(&std::fs::read(&manifest_path)?).context("解析清单失败")?;
+    if manifest.version > MANIFEST_VERSION {
+        bail!("不支持的备份版本: {}（请升级 nanobot）", manifest.version);
+    }
+
+    for entry in &manifest.files {
+        if !names.remove(&entry.path) {
+            bail!("归档不完整，缺少 {}", entry.path);
+        }
+        let actual = hash_file(&entry.path, &dir.join(&entry.path))?;
+        if actual.size != entry.size || actual.sha256 != entry.sha256 {
+            bail!("校验失败: {}（归档已损坏或被修改）", entry.path);
+        }
+    }
+    if let Some(extra) = names.into_iter().next() {
+        bail!("归档中有清单外的文件: {}", extra);
+    }
+    Ok(manifest)
+}
+
+fn decrypt_secrets(config: &Config, path: &Path) -> Result<toml::Value> {
+    let vault = Vault::load(config)?;
+    let data = vault
+        .decrypt(&std::fs::read(path)?)
+        .map_err(|_| anyhow!("vault 口令与备份时不同"))?;
+    Ok(toml::from_str(std::str::from_utf8(&data)?)?)
+}
+
+/// 是否为密钥类配置项
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key == "token"
+        || key == "authorization"
+        || key == "encrypt_key"
+        || key.ends_with("api_key")
+        || key.ends_with("_token")
+        || key.ends_with("secret")
+        || key.contains("password")
+}
+
+/// 从配置中移出密钥，返回只包含密钥的同结构表（没有密钥时返回 None）
+///
+/// `${VAR}` 形式的环境变量引用不是密钥本身，保留在配置中
+fn take_secrets(value: &mut toml::Value) -> Option<toml::Value> {
+    let toml::Value::Table(table) = value else {
+        return None;
+    };
+    let mut secrets = toml::map::Map::new();
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let secret = match table.get(&key) {
+            Some(toml::Value::String(s)) => is_secret_key(&key) && !s.contains("${"),
+            _ => false,
+        };
+        if secret {
+            if let Some(v) = table.remove(&key) {
+                secrets.insert(key, v);
+            }
+        } else if let Some(nested) = table.get_mut(&key).and_then(take_secrets) {
+            secrets.insert(key, nested);
+        }
+    }
+    (!secrets.is_empty()).then_some(toml::Value::Table(secrets))
+}
+
+/// 把 source 中的表项合并到 target
+fn merge(target: &mut toml::Value, source: toml::Value) {
+    match (target, source) {
+        (toml::Value::Table(target), toml::Value::Table(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(existing) if existing.is_table() && value.is_table() => merge(existing, value),
+                    _ => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source,
+    }
+}
+
+/// 配置中显式设置的工作目录与本机不同时改为本机工作目录，返回是否修改
+fn set_workspace_path(value: &mut toml::Value, workspace: &Path) -> bool {
+    let Some(memory) = value.get_mut("memory").and_then(toml::Value::as_table_mut) else {
+        return false;
+    };
+    let workspace = workspace.to_string_lossy().to_string();
+    match memory.get("workspace_path").and_then(toml::Value::as_str) {
+        Some(path) if path != workspace => {
+            memory.insert("workspace_path".to_string(), toml::Value::String(workspace));
+            true
+        }
+        _ => false,
+    }
+}
+
+fn wal_path(db: &Path) -> PathBuf {
+    let mut name = db.as_os_str().to_owned();
+    name.push("-wal");
+    PathBuf::from(name)
+}
+
+/// 恢复用的临时目录，结束时删除
+struct StagingDir(PathBuf);
+
+impl StagingDir {
+    fn new() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("nanobot-restore-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_secrets() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [llm.openai]
+            api_key = "sk-123"
+            default_model = "gpt-4o"
+            max_tokens = 100
+            [llm.deepseek]
+            api_key = "${DEEPSEEK_API_KEY}"
+            [channel.telegram]
+            bot_token = "123:abc"
+            "#,
+        )
+        .unwrap();
+        let secrets = take_secrets(&mut value).unwrap();
+
+        assert!(value["llm"]["openai"].get("api_key").is_none());
+        assert_eq!(value["llm"]["openai"]["default_model"].as_str(), Some("gpt-4o"));
+        assert_eq!(value["llm"]["deepseek"]["api_key"].as_str(), Some("${DEEPSEEK_API_KEY}"));
+        assert!(value["channel"]["telegram"].get("bot_token").is_none());
+        assert_eq!(secrets["llm"]["openai"]["api_key"].as_str(), Some("sk-123"));
+        assert!(secrets["llm"].get("deepseek").is_none());
+
+        merge(&mut value, secrets);
+        assert_eq!(value["channel"]["telegram"]["bot_token"].as_str(), Some("123:abc"));
+        assert_eq!(value["llm"]["openai"]["max_tokens"].as_integer(), Some(100));
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let mut config = Config::default();
+        config.memory.workspace_path = root.join("old");
+        let workspace = &config.memory.workspace_path;
+        std::fs::create_dir_all(workspace.join("memory/conversations")).unwrap();
+        std::fs::write(workspace.join("memory/MEMORY.md"), "# 记忆").unwrap();
+        std::fs::write(workspace.join("memory/conversations/cli_1.jsonl"), "{}\n").unwrap();
+        std::fs::write(config.sessions_db_path(), b"sqlite").unwrap();
+        std::fs::write(workspace.join("models_cache.json"), "{}").unwrap();
+        let config_path = root.join("config.toml");
+        std::fs::write(
+            &config_path,
+            format!(
+                "[memory]\nworkspace_path = \"{}\"\n[llm.openai]\napi_key = \"sk-123\"\n",
+                workspace.display()
+            ),
+        )
+        .unwrap();
+
+        let archive = root.join("backup.tar.zst");
+        let manifest = create(&config, &config_path, &archive, None).unwrap();
+        assert_eq!(manifest.secrets, SecretsMode::Excluded);
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "config.toml",
+                "workspace/memory/MEMORY.md",
+                "workspace/memory/conversations/cli_1.jsonl",
+                "workspace/sessions.db"
+            ]
+        );
+
+        // 恢复到另一台机器的工作目录
+        let mut target = Config::default();
+        target.memory.workspace_path = root.join("new");
+        let target_config = root.join("new-config.toml");
+        let summary = restore(&archive, &target, &target_config, false).unwrap();
+        assert_eq!(summary.files, 4);
+        assert!(summary.workspace_rewritten);
+        assert_eq!(std::fs::read_to_string(root.join("new/memory/MEMORY.md")).unwrap(), "# 记忆");
+        assert_eq!(std::fs::read(target.sessions_db_path()).unwrap(), b"sqlite");
+        let restored = std::fs::read_to_string(&target_config).unwrap();
+        assert!(!restored.contains("sk-123"));
+        assert!(restored.contains(&root.join("new").display().to_string()));
+
+        // 已存在时需要 --force
+        assert!(restore(&archive, &target, &target_config, false).is_err());
+        assert!(restore(&archive, &target, &target_config, true).is_ok());
+    }
+}
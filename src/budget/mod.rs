@@ -0,0 +1,532 @@
+//! 用量预算
+//!
+//! 按 `[budget.prices]` 把每次 LLM 调用的令牌用量折算为费用，记录到 `<workspace>/usage.db`，
+//! 按 UTC 自然日/自然月统计总预算、各提供商预算和每个用户的预算：
+//! - 用量达到 `alert_percent` 及超出预算时各告警一次（经事件总线推送给管理员）
+//! - 启用 `hard_cap` 后超出预算时拒绝非管理员的请求，并说明预算何时重置
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use sqlx::{Pool, Row, Sqlite};
+use std::sync::{Arc, OnceLock};
+use tracing::{info, warn};
+
+use crate::bus::{BudgetAlertEvent, EventBus, EventHandler};
+use crate::channel::template::{NotificationKind, NotificationTemplates};
+use crate::channel::{Channel, ChannelTarget};
+use crate::config::{BudgetConfig, BudgetLimits, Config};
+use crate::llm::Usage;
+use crate::t;
+
+/// 统计周期
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Period {
+    Daily,
+    Monthly,
+}
+
+impl Period {
+    fn as_str(self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Monthly => "monthly",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Period::Daily => "今日",
+            Period::Monthly => "本月",
+        }
+    }
+
+    /// 当前周期的键（usage.day 的前缀）
+    fn key(self, now: DateTime<Utc>) -> String {
+        match self {
+            Period::Daily => now.format("%Y-%m-%d").to_string(),
+            Period::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// 下一个周期的开始时间
+    fn reset_at(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = match self {
+            Period::Daily => now.date_naive() + Duration::days(1),
+            Period::Monthly => {
+                let (year, month) = if now.month() == 12 {
+                    (now.year() + 1, 1)
+                } else {
+                    (now.year(), now.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(now.date_naive())
+            }
+        };
+        Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+    }
+}
+
+/// 预算范围
+#[derive(Debug, Clone, PartialEq)]
+enum Scope {
+    Total,
+    Provider(String),
+    User(String),
+}
+
+impl Scope {
+    fn key(&self) -> String {
+        match self {
+            Scope::Total => "total".to_string(),
+            Scope::Provider(name) => format!("provider:{}", name),
+            Scope::User(user) => format!("user:{}", user),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Scope::Total => "预算".to_string(),
+            Scope::Provider(name) => format!("提供商 {} 的预算", name),
+            Scope::User(_) => "你的预算".to_string(),
+        }
+    }
+}
+
+/// 用量账本与预算检查
+pub struct Budget {
+    config: BudgetConfig,
+    pool: Pool<Sqlite>,
+    /// 告警事件总线（gateway 模式）
+    alert_bus: OnceLock<Arc<EventBus>>,
+}
+
+impl Budget {
+    /// 打开用量数据库
+    pub async fn new(config: BudgetConfig, db_path: &str) -> Result<Self> {
+        let pool = crate::db::connect(db_path, 2)
+            .await
+            .context("连接用量数据库失败")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS usage (
+                day TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                user TEXT,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL
+            )
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_usage_day ON usage(day)")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS budget_alerts (
+                scope TEXT NOT NULL,
+                period TEXT NOT NULL,
+                level TEXT NOT NULL,
+                PRIMARY KEY (scope, period, level)
+            )
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            config,
+            pool,
+            alert_bus: OnceLock::new(),
+        })
+    }
+
+    /// 配置了任一预算时打开用量数据库，否则返回 None
+    pub async fn from_config(config: &Config) -> Result<Option<Arc<Self>>> {
+        if !config.budget.is_enabled() {
+            return Ok(None);
+        }
+        let budget = Self::new(config.budget.clone(), &config.usage_db_path().to_string_lossy()).await?;
+        Ok(Some(Arc::new(budget)))
+    }
+
+    /// 设置告警事件总线（只能设置一次）
+    pub fn set_event_bus(&self, bus: Arc<EventBus>) {
+        let _ = self.alert_bus.set(bus);
+    }
+
+    /// 用户是否为预算管理员（不受硬上限限制）
+    pub fn is_admin(&self, user: &str) -> bool {
+        let chat_id = user.split_once(':').map(|(_, id)| id).unwrap_or(user);
+        self.config
+            .admin_users
+            .iter()
+            .any(|admin| admin == user || admin == chat_id)
+    }
+
+    /// 按配置的价格计算费用，未配置价格的模型不计费
+    pub fn cost(&self, provider: &str, model: &str, usage: &Usage) -> f64 {
+        let prices = &self.config.prices;
+        let price = prices
+            .get(&format!("{}/{}", provider, model))
+            .or_else(|| prices.get(model));
+        match price {
+            Some(price) => {
+                (usage.prompt_tokens as f64 * price.input + usage.completion_tokens as f64 * price.output)
+                    / 1_000_000.0
+            }
+            None => 0.0,
+        }
+    }
+
+    /// 适用于本次请求的预算
+    fn limits(&self, provider: Option<&str>, user: Option<&str>) -> Vec<(Scope, Period, f64)> {
+        let mut scopes = vec![(
+            Scope::Total,
+            BudgetLimits {
+                daily_usd: self.config.daily_usd,
+                monthly_usd: self.config.monthly_usd,
+            },
+        )];
+        if let Some(limits) = provider.and_then(|p| self.config.providers.get(p)) {
+            scopes.push((Scope::Provider(provider.unwrap_or_default().to_string()), limits.clone()));
+        }
+        if let Some(user) = user {
+            scopes.push((Scope::User(user.to_string()), self.config.per_user.clone()));
+        }
+
+        let mut limits = Vec::new();
+        for (scope, l) in scopes {
+            if l.daily_usd > 0.0 {
+                limits.push((scope.clone(), Period::Daily, l.daily_usd));
+            }
+            if l.monthly_usd > 0.0 {
+                limits.push((scope, Period::Monthly, l.monthly_usd));
+            }
+        }
+        limits
+    }
+
+    /// 当前周期内的花费
+    async fn spent(&self, scope: &Scope, period: Period, now: DateTime<Utc>) -> Result<f64> {
+        let prefix = format!("{}%", period.key(now));
+        let query = match scope {
+            Scope::Total => sqlx::query("SELECT COALESCE(SUM(cost_usd), 0.0) FROM usage WHERE day LIKE ?1")
+                .bind(prefix),
+            Scope::Provider(name) => sqlx::query(
+                "SELECT COALESCE(SUM(cost_usd), 0.0) FROM usage WHERE day LIKE ?1 AND provider = ?2",
+            )
+            .bind(prefix)
+            .bind(name.clone()),
+            Scope::User(user) => {
+                sqlx::query("SELECT COALESCE(SUM(cost_usd), 0.0) FROM usage WHERE day LIKE ?1 AND user = ?2")
+                    .bind(prefix)
+                    .bind(user.clone())
+            }
+        };
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(row.get::<f64, _>(0))
+    }
+
+    /// 请求前检查：启用硬上限且预算已用完时拒绝非管理员的请求
+    pub async fn check(&self, provider: Option<&str>, user: Option<&str>) -> Result<()> {
+        if !self.config.hard_cap || user.is_some_and(|u| self.is_admin(u)) {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        for (scope, period, limit) in self.limits(provider, user) {
+            let spent = self.spent(&scope, period, now).await?;
+            if spent >= limit {
+                bail!(
+                    "⛔ {}{}已用完（${:.2} / ${:.2}），将于 {} 重置",
+                    period.label(),
+                    scope.label(),
+                    spent,
+                    limit,
+                    period.reset_at(now).format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录一次调用的用量，达到告警阈值或超出预算时发布告警，返回本次费用
+    pub async fn record(&self, provider: &str, model: &str, user: Option<&str>, usage: &Usage) -> Result<f64> {
+        let now = Utc::now();
+        let cost = self.cost(provider, model, usage);
+        sqlx::query(
+            r#"
+            INSERT INTO usage (day, provider, user, model, prompt_tokens, completion_tokens, cost_usd)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#
+        )
+        .bind(Period::Daily.key(now))
+        .bind(provider)
+        .bind(user)
+        .bind(model)
+        .bind(usage.prompt_tokens as i64)
+        .bind(usage.completion_tokens as i64)
+        .bind(cost)
+        .execute(&self.pool)
+        .await?;
+
+        if cost > 0.0 {
+            for alert in self.pending_alerts(Some(provider), user, now).await? {
+                warn!(
+                    "{} {} 预算已用 ${:.2} / ${:.2}",
+                    alert.scope, alert.period, alert.spent_usd, alert.limit_usd
+                );
+                if let Some(bus) = self.alert_bus.get() {
+                    let _ = bus.publish(alert);
+                }
+            }
+        }
+        Ok(cost)
+    }
+
+    /// 用户（会话）按模型汇总的累计用量
+    pub async fn user_usage(&self, user: &str) -> Result<Vec<ModelUsage>> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT provider, model, SUM(prompt_tokens) AS prompt_tokens,
+                   SUM(completion_tokens) AS completion_tokens, SUM(cost_usd) AS cost_usd
+            FROM usage WHERE user = ?1
+            GROUP BY provider, model
+            ORDER BY cost_usd DESC, model
+            "#
+        )
+        .bind(user)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// 去掉匹配用户的用量记录中的用户标识（保留金额以维持总预算），返回处理的记录数
+    pub async fn forget_users(&self, matches: impl Fn(&str) -> bool) -> Result<u64> {
+        let users: Vec<String> =
+            sqlx::query_scalar("SELECT DISTINCT user FROM usage WHERE user IS NOT NULL")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut forgotten = 0;
+        for user in users.into_iter().filter(|u| matches(u)) {
+            forgotten += sqlx::query("UPDATE usage SET user = NULL WHERE user = ?1")
+                .bind(&user)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+            sqlx::query("DELETE FROM budget_alerts WHERE scope = ?1")
+                .bind(Scope::User(user).key())
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(forgotten)
+    }
+
+    /// 去掉 `before` 之前的用量记录中的用户标识，返回处理的记录数
+    pub async fn forget_users_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let forgotten = sqlx::query("UPDATE usage SET user = NULL WHERE user IS NOT NULL AND day < ?1")
+            .bind(Period::Daily.key(before))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(forgotten)
+    }
+
+    /// 本周期内首次达到告警阈值或超出预算的范围（每个级别只告警一次）
+    async fn pending_alerts(
+        &self,
+        provider: Option<&str>,
+        user: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<BudgetAlertEvent>> {
+        let mut alerts = Vec::new();
+        for (scope, period, limit) in self.limits(provider, user) {
+            let spent = self.spent(&scope, period, now).await?;
+            let exceeded = spent >= limit;
+            if !exceeded && spent < limit * self.config.alert_percent / 100.0 {
+                continue;
+            }
+
+            let level = if exceeded { "exceeded" } else { "alert" };
+            let inserted = sqlx::query(
+                "INSERT OR IGNORE INTO budget_alerts (scope, period, level) VALUES (?1, ?2, ?3)",
+            )
+            .bind(scope.key())
+            .bind(period.key(now))
+            .bind(level)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+            if inserted > 0 {
+                alerts.push(BudgetAlertEvent {
+                    scope: scope.key(),
+                    period: period.as_str().to_string(),
+                    spent_usd: spent,
+                    limit_usd: limit,
+                    exceeded,
+                    timestamp: now,
+                });
+            }
+        }
+        Ok(alerts)
+    }
+}
+
+/// 某个模型的累计用量
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// 把预算告警推送到配置的管理员聊天
+pub struct BudgetAlertHandler {
+    channels: Vec<Arc<dyn Channel>>,
+    channel: String,
+    target: ChannelTarget,
+    templates: NotificationTemplates,
+}
+
+impl BudgetAlertHandler {
+    /// 未配置告警目标时返回 None
+    pub fn new(channels: Vec<Arc<dyn Channel>>, config: &BudgetConfig) -> Option<Self> {
+        Some(Self {
+            channels,
+            channel: config.alert_channel.clone()?,
+            target: ChannelTarget::parse(config.alert_chat_id.as_deref()?)
+                .map_err(|e| warn!("预算告警目标无效: {}", e))
+                .ok()?,
+            templates: NotificationTemplates::default(),
+        })
+    }
+
+    /// 设置通知模板
+    pub fn with_templates(mut self, templates: NotificationTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// 生成告警内容
+    fn notice(&self, event: &BudgetAlertEvent) -> String {
+        let period = if event.period == "daily" {
+            t!("notify.period_daily")
+        } else {
+            t!("notify.period_monthly")
+        };
+        let kind = if event.exceeded {
+            NotificationKind::BudgetExceeded
+        } else {
+            NotificationKind::BudgetWarning
+        };
+        self.templates.render(
+            &self.channel,
+            kind,
+            &[
+                ("scope", event.scope.clone()),
+                ("period", period),
+                ("percent", format!("{:.0}", event.spent_usd / event.limit_usd * 100.0)),
+                ("spent", format!("{:.2}", event.spent_usd)),
+                ("limit", format!("{:.2}", event.limit_usd)),
+            ],
+        )
+    }
+}
+
+#[async_trait]
+impl EventHandler<BudgetAlertEvent> for BudgetAlertHandler {
+    async fn handle(&self, event: &BudgetAlertEvent) {
+        let Some(channel) = self.channels.iter().find(|c| c.name() == self.channel) else {
+            warn!("预算告警通道 {} 未启动", self.channel);
+            return;
+        };
+        let notice = self.notice(event);
+        match channel.send_message(&self.target, &notice).await {
+            Ok(()) => info!("已发送预算告警: {}", event.scope),
+            Err(e) => warn!("发送预算告警失败: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelPrice;
+
+    fn usage(prompt: u32, completion: u32) -> Usage {
+        Usage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt + completion,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_budget_hard_cap() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = BudgetConfig {
+            monthly_usd: 1.0,
+            hard_cap: true,
+            admin_users: vec!["telegram:1".to_string()],
+            ..Default::default()
+        };
+        config.prices.insert(
+            "deepseek-chat".to_string(),
+            ModelPrice {
+                input: 1.0,
+                output: 2.0,
+            },
+        );
+        let budget = Budget::new(config, &temp_dir.path().join("usage.db").to_string_lossy())
+            .await
+            .unwrap();
+
+        // 1M 输入 + 0.5M 输出 = $2，未配置价格的模型不计费
+        assert_eq!(budget.cost("deepseek", "deepseek-chat", &usage(1_000_000, 500_000)), 2.0);
+        assert_eq!(budget.cost("deepseek", "unknown", &usage(1_000_000, 0)), 0.0);
+
+        budget.check(Some("deepseek"), Some("telegram:2")).await.unwrap();
+        budget
+            .record("deepseek", "deepseek-chat", Some("telegram:2"), &usage(850_000, 0))
+            .await
+            .unwrap();
+        budget.check(Some("deepseek"), Some("telegram:2")).await.unwrap();
+
+        // 达到 80% 后只告警一次
+        let now = Utc::now();
+        assert!(budget.pending_alerts(None, None, now).await.unwrap().is_empty());
+
+        budget
+            .record("deepseek", "deepseek-chat", Some("telegram:2"), &usage(200_000, 0))
+            .await
+            .unwrap();
+        let err = budget.check(Some("deepseek"), Some("telegram:3")).await.unwrap_err();
+        assert!(err.to_string().contains("本月预算已用完"));
+        assert!(err.to_string().contains("-01 00:00 UTC"));
+        // 管理员不受限制
+        budget.check(Some("deepseek"), Some("telegram:1")).await.unwrap();
+
+        let used = budget.user_usage("telegram:2").await.unwrap();
+        assert_eq!(used.len(), 1);
+        assert_eq!(used[0].prompt_tokens, 1_050_000);
+        assert!((used[0].cost_usd - 1.05).abs() < 1e-9);
+        assert!(budget.user_usage("telegram:3").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_period_reset() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 15, 30, 0).unwrap();
+        assert_eq!(Period::Daily.key(now), "2026-12-31");
+        assert_eq!(Period::Monthly.key(now), "2026-12");
+        assert_eq!(Period::Daily.reset_at(now), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(Period::Monthly.reset_at(now), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+    }
+}
@@ -2,8 +2,13 @@
 //!
 //! 提供类型安全的事件系统，支持异步事件处理
 //! 用于解耦模块间通信
+//!
+//! 除按类型订阅外，还支持字符串主题订阅（如 `tool.*`），
+//! 类型事件会自动桥接到对应主题，便于脚本/插件等动态订阅者使用
 
 use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -15,6 +20,16 @@ use tracing::{debug, info, warn};
 pub trait Event: Send + Sync + Debug + 'static {
     /// 事件名称
     fn event_name(&self) -> &'static str;
+
+    /// 桥接到字符串主题时使用的主题名
+    fn topic(&self) -> String {
+        self.event_name().to_string()
+    }
+
+    /// 桥接到字符串主题时使用的负载
+    fn payload(&self) -> Value {
+        Value::Null
+    }
 }
 
 /// 事件处理器 trait
@@ -24,6 +39,44 @@ pub trait EventHandler<E: Event>: Send + Sync {
     async fn handle(&self, event: &E);
 }
 
+/// 主题事件处理器 trait
+#[async_trait::async_trait]
+pub trait TopicHandler: Send + Sync {
+    /// 处理主题事件
+    async fn handle(&self, topic: &str, payload: &Value);
+}
+
+#[async_trait::async_trait]
+impl<F> TopicHandler for F
+where
+    F: Fn(&str, &Value) + Send + Sync,
+{
+    async fn handle(&self, topic: &str, payload: &Value) {
+        self(topic, payload)
+    }
+}
+
+/// 判断主题是否匹配订阅模式
+///
+/// 以 `.` 分段，`*` 匹配单个分段，`**` 匹配任意多个（含零个）分段
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    fn matches(pattern: &[&str], topic: &[&str]) -> bool {
+        match (pattern.first(), topic.first()) {
+            (None, None) => true,
+            (Some(&"**"), _) => {
+                matches(&pattern[1..], topic) || (!topic.is_empty() && matches(pattern, &topic[1..]))
+            }
+            (Some(&"*"), Some(_)) => matches(&pattern[1..], &topic[1..]),
+            (Some(p), Some(t)) if p == t => matches(&pattern[1..], &topic[1..]),
+            _ => false,
+        }
+    }
+
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let topic: Vec<&str> = topic.split('.').collect();
+    matches(&pattern, &topic)
+}
+
 /// 类型擦除的事件处理器
 #[async_trait::async_trait]
 trait ErasedEventHandler: Send + Sync {
@@ -53,18 +106,40 @@ where
 
 /// 订阅者信息
 struct Subscriber {
+    #[allow(dead_code)]
     id: String,
     handler: Arc<dyn ErasedEventHandler>,
 }
 
+/// 主题订阅者信息
+struct TopicSubscriber {
+    id: String,
+    pattern: String,
+    handler: Arc<dyn TopicHandler>,
+}
+
+/// 总线内部消息
+enum BusMessage {
+    /// 类型事件（同时桥接到主题）
+    Typed {
+        event: Box<dyn Any + Send + Sync>,
+        topic: String,
+        payload: Value,
+    },
+    /// 纯主题事件
+    Topic { topic: String, payload: Value },
+}
+
 /// 事件总线
 pub struct EventBus {
     /// 订阅者映射：事件类型 -> 订阅者列表
     subscribers: Arc<RwLock<HashMap<TypeId, Vec<Subscriber>>>>,
+    /// 主题订阅者列表
+    topic_subscribers: Arc<RwLock<Vec<TopicSubscriber>>>,
     /// 事件通道发送端
-    sender: mpsc::UnboundedSender<Box<dyn Any + Send + Sync>>,
+    sender: mpsc::UnboundedSender<BusMessage>,
     /// 事件通道接收端（存储在 Option 中以便 take）
-    receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<Box<dyn Any + Send + Sync>>>>>,
+    receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<BusMessage>>>>,
 }
 
 impl EventBus {
@@ -74,6 +149,7 @@ impl EventBus {
 
         Arc::new(Self {
             subscribers: Arc::new(RwLock::new(HashMap::new())),
+            topic_subscribers: Arc::new(RwLock::new(Vec::new())),
             sender,
             receiver: Arc::new(RwLock::new(Some(receiver))),
         })
@@ -107,6 +183,7 @@ impl EventBus {
     }
 
     /// 取消订阅
+    #[allow(dead_code)]
     pub async fn unsubscribe<E>(&self, subscriber_id: &str) -> Result<()>
     where
         E: Event,
@@ -119,14 +196,58 @@ impl EventBus {
         Ok(())
     }
 
+    /// 订阅主题（支持 `*` / `**` 通配符）
+    pub async fn subscribe_topic<H>(&self, pattern: impl Into<String>, handler: H) -> String
+    where
+        H: TopicHandler + 'static,
+    {
+        let subscriber_id = uuid::Uuid::new_v4().to_string();
+        let pattern = pattern.into();
+
+        info!("订阅主题 {}: {}", pattern, subscriber_id);
+        self.topic_subscribers.write().await.push(TopicSubscriber {
+            id: subscriber_id.clone(),
+            pattern,
+            handler: Arc::new(handler),
+        });
+
+        subscriber_id
+    }
+
+    /// 取消主题订阅
+    pub async fn unsubscribe_topic(&self, subscriber_id: &str) -> Result<()> {
+        self.topic_subscribers
+            .write()
+            .await
+            .retain(|s| s.id != subscriber_id);
+        info!("取消订阅主题: {}", subscriber_id);
+        Ok(())
+    }
+
     /// 发布事件
     pub fn publish<E>(&self, event: E) -> Result<()>
     where
         E: Event,
     {
         debug!("发布事件: {}", event.event_name());
+        let topic = event.topic();
+        let payload = event.payload();
         self.sender
-            .send(Box::new(event))
+            .send(BusMessage::Typed {
+                event: Box::new(event),
+                topic,
+                payload,
+            })
+            .map_err(|_| anyhow::anyhow!("事件总线已关闭"))?;
+        Ok(())
+    }
+
+    /// 发布没有对应类型的主题事件（如网关的 `gateway.started` / `gateway.stopping`），只投递给主题订阅者
+    pub fn publish_topic(&self, topic: impl Into<String>, payload: Value) -> Result<()> {
+        let topic = topic.into();
+        debug!("发布主题事件: {}", topic);
+        self.sender
+            .send(BusMessage::Topic { topic, payload })
             .map_err(|_| anyhow::anyhow!("事件总线已关闭"))?;
         Ok(())
     }
@@ -142,20 +263,38 @@ impl EventBus {
 
         info!("启动事件总线...");
 
-        while let Some(event) = receiver.recv().await {
+        while let Some(message) = receiver.recv().await {
             let subs = self.subscribers.clone();
-
-            // 获取事件类型 ID
-            let type_id = (*event).type_id();
+            let topic_subs = self.topic_subscribers.clone();
 
             tokio::spawn(async move {
-                let subscribers = subs.read().await;
-                if let Some(handlers) = subscribers.get(&type_id) {
-                    for subscriber in handlers {
-                        let handler = subscriber.handler.clone();
-                        let event_ref: &(dyn Any + Send + Sync) = &*event;
-                        handler.handle_erased(event_ref).await;
+                let (topic, payload) = match message {
+                    BusMessage::Typed { event, topic, payload } => {
+                        // 获取事件类型 ID
+                        let type_id = (*event).type_id();
+
+                        let subscribers = subs.read().await;
+                        if let Some(handlers) = subscribers.get(&type_id) {
+                            for subscriber in handlers {
+                                let handler = subscriber.handler.clone();
+                                let event_ref: &(dyn Any + Send + Sync) = &*event;
+                                handler.handle_erased(event_ref).await;
+                            }
+                        }
+                        (topic, payload)
                     }
+                    BusMessage::Topic { topic, payload } => (topic, payload),
+                };
+
+                let handlers: Vec<Arc<dyn TopicHandler>> = topic_subs
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|s| topic_matches(&s.pattern, &topic))
+                    .map(|s| s.handler.clone())
+                    .collect();
+                for handler in handlers {
+                    handler.handle(&topic, &payload).await;
                 }
             });
         }
@@ -171,6 +310,7 @@ impl Default for EventBus {
 
         Self {
             subscribers: Arc::new(RwLock::new(HashMap::new())),
+            topic_subscribers: Arc::new(RwLock::new(Vec::new())),
             sender,
             receiver: Arc::new(RwLock::new(Some(receiver))),
         }
@@ -180,7 +320,7 @@ impl Default for EventBus {
 // ============== 预定义事件类型 ==============
 
 /// Agent 消息事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AgentMessageEvent {
     pub session_id: String,
     pub role: String,
@@ -192,10 +332,14 @@ impl Event for AgentMessageEvent {
     fn event_name(&self) -> &'static str {
         "agent.message"
     }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
 }
 
 /// 工具调用事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ToolCallEvent {
     pub session_id: String,
     pub tool_name: String,
@@ -209,10 +353,38 @@ impl Event for ToolCallEvent {
     fn event_name(&self) -> &'static str {
         "tool.call"
     }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 工具注册表变更事件
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolRegistryEvent {
+    pub tool_name: String,
+    /// registered / unregistered
+    pub action: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for ToolRegistryEvent {
+    fn event_name(&self) -> &'static str {
+        "tool.registry"
+    }
+
+    fn topic(&self) -> String {
+        format!("tool.{}", self.action)
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
 }
 
 /// 会话创建事件
-#[derive(Debug, Clone)]
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SessionCreatedEvent {
     pub session_id: String,
     pub channel: String,
@@ -224,10 +396,14 @@ impl Event for SessionCreatedEvent {
     fn event_name(&self) -> &'static str {
         "session.created"
     }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
 }
 
 /// 会话结束事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SessionEndedEvent {
     pub session_id: String,
     pub reason: String,
@@ -238,10 +414,178 @@ impl Event for SessionEndedEvent {
     fn event_name(&self) -> &'static str {
         "session.ended"
     }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 提供商限流等待事件（用于向发起请求的聊天推送提示）
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderBusyEvent {
+    pub provider: String,
+    /// 等待秒数
+    pub retry_after_secs: u64,
+    /// 第几次重试
+    pub attempt: u32,
+    pub channel: String,
+    pub chat_id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for ProviderBusyEvent {
+    fn event_name(&self) -> &'static str {
+        "provider.busy"
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 工具执行进度事件（长时间运行的工具报告的中间输出）
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolProgressEvent {
+    pub tool_name: String,
+    /// 进度内容（如最近几行输出）
+    pub message: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for ToolProgressEvent {
+    fn event_name(&self) -> &'static str {
+        "tool.progress"
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 内容过滤命中事件
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentFilteredEvent {
+    pub channel: String,
+    pub chat_id: String,
+    pub sender: String,
+    /// inbound（用户消息）或 outbound（回复）
+    pub direction: String,
+    /// block、redact 或 flag
+    pub action: String,
+    /// 命中的规则或模型给出的理由
+    pub reason: String,
+    /// 内容摘录（打码时为打码后的文本，无痕会话不记录）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for ContentFilteredEvent {
+    fn event_name(&self) -> &'static str {
+        "content.filtered"
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 用量预算告警事件（达到告警阈值或超出预算）
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetAlertEvent {
+    /// 预算范围，如 total、provider:openrouter、user:telegram:123
+    pub scope: String,
+    /// daily 或 monthly
+    pub period: String,
+    pub spent_usd: f64,
+    pub limit_usd: f64,
+    /// 是否已超出预算
+    pub exceeded: bool,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for BudgetAlertEvent {
+    fn event_name(&self) -> &'static str {
+        "budget.alert"
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 从会话中提取到长期记忆的事件（供审阅模型记住了什么）
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryExtractedEvent {
+    pub session_id: String,
+    pub memories: Vec<crate::memory::ExtractedMemory>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for MemoryExtractedEvent {
+    fn event_name(&self) -> &'static str {
+        "memory.extracted"
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 监视目录中的文件变化事件
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChangedEvent {
+    /// 监视目录的名称（[[watch.dirs]] name）
+    pub watch: String,
+    pub path: std::path::PathBuf,
+    pub kind: crate::config::FileChangeKind,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for FileChangedEvent {
+    fn event_name(&self) -> &'static str {
+        "file.changed"
+    }
+
+    fn topic(&self) -> String {
+        format!("file.{}", self.kind.as_str())
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// 定时任务执行事件
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub job_id: String,
+    pub name: String,
+    pub handler: String,
+    /// started、completed、failed
+    pub status: String,
+    pub error: Option<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl Event for JobEvent {
+    fn event_name(&self) -> &'static str {
+        "job"
+    }
+
+    fn topic(&self) -> String {
+        format!("job.{}", self.status)
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
 }
 
 /// 系统事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SystemEvent {
     pub event_type: String,
     pub data: serde_json::Value,
@@ -252,6 +596,14 @@ impl Event for SystemEvent {
     fn event_name(&self) -> &'static str {
         "system"
     }
+
+    fn topic(&self) -> String {
+        format!("system.{}", self.event_type)
+    }
+
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -313,4 +665,57 @@ mod tests {
         assert_eq!(msgs.len(), 1);
         assert_eq!(msgs[0], "Hello");
     }
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("tool.call", "tool.call"));
+        assert!(topic_matches("tool.*", "tool.call"));
+        assert!(!topic_matches("tool.*", "tool"));
+        assert!(!topic_matches("tool.*", "tool.call.done"));
+        assert!(topic_matches("tool.**", "tool.call.done"));
+        assert!(topic_matches("tool.**", "tool"));
+        assert!(topic_matches("**", "session.created"));
+        assert!(topic_matches("*.created", "session.created"));
+        assert!(!topic_matches("agent.*", "tool.call"));
+    }
+
+    #[tokio::test]
+    async fn test_topic_subscription() {
+        let bus = EventBus::new();
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let sink = received.clone();
+        bus.subscribe_topic("tool.*", move |topic: &str, payload: &Value| {
+            sink.lock().unwrap().push((topic.to_string(), payload.clone()));
+        })
+        .await;
+
+        let bus_clone = bus.clone();
+        tokio::spawn(async move {
+            bus_clone.start().await.unwrap();
+        });
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        // 类型事件自动桥接到主题
+        bus.publish(ToolCallEvent {
+            session_id: "s1".to_string(),
+            tool_name: "shell".to_string(),
+            args: serde_json::json!({}),
+            result: None,
+            success: true,
+            timestamp: chrono::Utc::now(),
+        })
+        .unwrap();
+        bus.publish_topic("tool.result", serde_json::json!({ "ok": true })).unwrap();
+        bus.publish_topic("agent.message", Value::Null).unwrap();
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut msgs = received.lock().unwrap().clone();
+        msgs.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].0, "tool.call");
+        assert_eq!(msgs[0].1["tool_name"], "shell");
+        assert_eq!(msgs[1].0, "tool.result");
+    }
 }
@@ -0,0 +1,279 @@
+//! 入站消息去重
+//!
+//! 各平台在网络抖动或回调超时时会重复推送同一条消息。
+//! 这里以 (通道, 消息 ID) 为键，内存中维护有界缓存，
+//! 可选地持久化到 SQLite，使重启后仍能识别重复消息。
+//! 配置了 `[redis]` 时改用 Redis 记录，多个进程之间共享去重
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::config::DedupeConfig;
+use crate::state::StateStore;
+
+/// 每写入多少条记录清理一次数据库中的过期记录
+const PURGE_EVERY: u64 = 500;
+
+/// 有界的 LRU 缓存：超出容量时淘汰最久未访问的记录（命中会延长保留）
+///
+/// 每次访问在队列末尾追加一条带代数的记录，旧记录留在队列中、淘汰时按代数跳过；
+/// 队列长度超过容量两倍时压缩一次，各操作均摊 O(1)
+struct LruCache {
+    capacity: usize,
+    /// 记录时间和最近一次访问的代数
+    entries: HashMap<String, (Instant, u64)>,
+    order: VecDeque<(String, u64)>,
+    generation: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            generation: 0,
+        }
+    }
+
+    /// 返回记录时间（若存在），并标记为最近访问
+    fn get(&mut self, key: &str) -> Option<Instant> {
+        let entry = self.entries.get_mut(key)?;
+        self.generation += 1;
+        entry.1 = self.generation;
+        let seen_at = entry.0;
+        self.order.push_back((key.to_string(), self.generation));
+        self.compact();
+        Some(seen_at)
+    }
+
+    fn insert(&mut self, key: String, seen_at: Instant) {
+        self.generation += 1;
+        self.entries.insert(key.clone(), (seen_at, self.generation));
+        self.order.push_back((key, self.generation));
+
+        while self.entries.len() > self.capacity {
+            let Some((oldest, generation)) = self.order.pop_front() else {
+                break;
+            };
+            if self.is_current(&oldest, generation) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.compact();
+    }
+
+    /// 队列中的记录是否为该键最近一次访问
+    fn is_current(&self, key: &str, generation: u64) -> bool {
+        self.entries.get(key).is_some_and(|&(_, g)| g == generation)
+    }
+
+    /// 丢弃队列中过时的访问记录
+    fn compact(&mut self) {
+        if self.order.len() > self.capacity * 2 {
+            let order = std::mem::take(&mut self.order);
+            self.order = order.into_iter().filter(|(k, g)| self.is_current(k, *g)).collect();
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// 入站消息去重存储（各通道共享）
+pub struct DedupeStore {
+    cache: Mutex<LruCache>,
+    ttl: std::time::Duration,
+    pool: Option<Pool<Sqlite>>,
+    inserts: AtomicU64,
+    /// 多进程共享的状态（设置后不使用内存缓存和 SQLite）
+    shared: Option<Arc<StateStore>>,
+}
+
+impl DedupeStore {
+    /// 创建内存模式的去重存储
+    pub fn new(capacity: usize, ttl_secs: u64) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            ttl: std::time::Duration::from_secs(ttl_secs),
+            pool: None,
+            inserts: AtomicU64::new(0),
+            shared: None,
+        }
+    }
+
+    /// 使用多进程共享的状态记录消息（只有共享后端才生效）
+    pub fn with_shared_state(mut self, state: Arc<StateStore>) -> Self {
+        if state.is_shared() {
+            self.shared = Some(state);
+        }
+        self
+    }
+
+    /// 创建带持久化的去重存储
+    pub async fn with_db(db_path: &str, capacity: usize, ttl_secs: u64) -> Result<Self> {
+        let pool = crate::db::connect(db_path, 2)
+            .await
+            .context("连接去重数据库失败")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS inbound_dedupe (
+                channel TEXT NOT NULL,
+                message_id TEXT NOT NULL,
+                seen_at TIMESTAMP NOT NULL,
+                PRIMARY KEY (channel, message_id)
+            )
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pool: Some(pool),
+            ..Self::new(capacity, ttl_secs)
+        })
+    }
+
+    /// 根据配置创建
+    pub async fn from_config(config: &DedupeConfig, db_path: &str) -> Result<Self> {
+        if config.persistent {
+            Self::with_db(db_path, config.capacity, config.ttl_secs).await
+        } else {
+            Ok(Self::new(config.capacity, config.ttl_secs))
+        }
+    }
+
+    /// 记录消息，首次出现返回 true，重复消息返回 false
+    pub async fn check_and_insert(&self, channel: &str, message_id: &str) -> Result<bool> {
+        let key = format!("{}:{}", channel, message_id);
+        if let Some(ref shared) = self.shared {
+            let first = shared.set_nx(&format!("dedupe:{}", key), self.ttl).await?;
+            if !first {
+                debug!("重复消息（共享记录）: {}", key);
+            }
+            return Ok(first);
+        }
+        let now = Instant::now();
+
+        {
+            let mut cache = self.cache.lock().await;
+            if let Some(seen_at) = cache.get(&key) {
+                if now.duration_since(seen_at) < self.ttl {
+                    debug!("重复消息: {}", key);
+                    return Ok(false);
+                }
+            }
+            cache.insert(key.clone(), now);
+        }
+
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return Ok(true),
+        };
+
+        // 内存未命中时再查数据库（重启后或已被淘汰的记录）
+        let now_utc = Utc::now();
+        let expire_before = now_utc - Duration::from_std(self.ttl).unwrap_or_else(|_| Duration::days(1));
+        let result = sqlx::query(
+            r#"
+            INSERT INTO inbound_dedupe (channel, message_id, seen_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT(channel, message_id) DO UPDATE SET seen_at = excluded.seen_at
+            WHERE inbound_dedupe.seen_at < ?4
+            "#
+        )
+        .bind(channel)
+        .bind(message_id)
+        .bind(now_utc)
+        .bind(expire_before)
+        .execute(pool)
+        .await?;
+
+        if self.inserts.fetch_add(1, Ordering::Relaxed) % PURGE_EVERY == PURGE_EVERY - 1 {
+            sqlx::query("DELETE FROM inbound_dedupe WHERE seen_at < ?1")
+                .bind(expire_before)
+                .execute(pool)
+                .await?;
+        }
+
+        if result.rows_affected() == 0 {
+            debug!("重复消息（持久化记录）: {}", key);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// 内存缓存中的记录数
+    #[cfg(test)]
+    pub async fn cached_count(&self) -> usize {
+        self.cache.lock().await.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dedupe_memory() {
+        let store = DedupeStore::new(2, 3600);
+        assert!(store.check_and_insert("telegram", "1").await.unwrap());
+        assert!(!store.check_and_insert("telegram", "1").await.unwrap());
+        // 不同通道互不影响
+        assert!(store.check_and_insert("feishu", "1").await.unwrap());
+
+        // 超出容量后最久未访问的记录被淘汰
+        assert!(store.check_and_insert("telegram", "2").await.unwrap());
+        assert_eq!(store.cached_count().await, 2);
+        assert!(store.check_and_insert("telegram", "1").await.unwrap());
+
+        // 命中的记录不会被淘汰：telegram:2 重复后，淘汰的是 telegram:1
+        assert!(!store.check_and_insert("telegram", "2").await.unwrap());
+        assert!(store.check_and_insert("feishu", "2").await.unwrap());
+        assert!(!store.check_and_insert("telegram", "2").await.unwrap());
+        assert!(store.check_and_insert("telegram", "1").await.unwrap());
+    }
+
+    #[test]
+    fn test_lru_cache_order_is_bounded() {
+        let now = Instant::now();
+        let mut cache = LruCache::new(3);
+        for i in 0..3 {
+            cache.insert(i.to_string(), now);
+        }
+        // 反复访问同一条记录，队列中的过时记录会被压缩
+        for _ in 0..100 {
+            assert_eq!(cache.get("0"), Some(now));
+        }
+        assert!(cache.order.len() <= 6);
+
+        cache.insert("3".to_string(), now);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get("1"), None);
+        assert_eq!(cache.get("0"), Some(now));
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_persistent() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("dedupe.db").to_string_lossy().to_string();
+
+        let store = DedupeStore::with_db(&db_path, 10, 3600).await.unwrap();
+        assert!(store.check_and_insert("feishu", "om_1").await.unwrap());
+        drop(store);
+
+        // 模拟重启：内存缓存为空，但数据库中仍有记录
+        let store = DedupeStore::with_db(&db_path, 10, 3600).await.unwrap();
+        assert!(!store.check_and_insert("feishu", "om_1").await.unwrap());
+        assert!(store.check_and_insert("feishu", "om_2").await.unwrap());
+    }
+}
@@ -4,27 +4,24 @@
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::info;
 
-use crate::channel::Channel;
+use crate::channel::{Channel, ChannelTarget};
 use crate::config::DiscordConfig;
 
 /// Discord 通道
+///
+/// 目前只实现了发送；接收消息需要完成下方的 serenity 事件处理器，届时再接入 `MessageHandler`
 pub struct DiscordChannel {
     config: DiscordConfig,
-    agent: Arc<crate::agent::Agent>,
     /// 运行状态
     running: RwLock<bool>,
 }
 
 impl DiscordChannel {
     /// 创建新的 Discord 通道
-    pub fn new(
-        config: DiscordConfig,
-        agent: Arc<crate::agent::Agent>,
-    ) -> Result<Self> {
+    pub fn new(config: DiscordConfig) -> Result<Self> {
         // 验证配置
         if config.bot_token.is_none() {
             anyhow::bail!("Discord Bot Token 未配置");
@@ -32,19 +29,10 @@ impl DiscordChannel {
 
         Ok(Self {
             config,
-            agent,
             running: RwLock::new(false),
         })
     }
 
-    /// 检查服务器是否在白名单中
-    fn is_guild_allowed(&self, guild_id: u64) -> bool {
-        if self.config.allowed_guilds.is_empty() {
-            return true;
-        }
-        self.config.allowed_guilds.contains(&guild_id)
-    }
-
     /// 检查频道是否在白名单中
     fn is_channel_allowed(&self, channel_id: u64) -> bool {
         if self.config.allowed_channels.is_empty() {
@@ -53,12 +41,22 @@ impl DiscordChannel {
         self.config.allowed_channels.contains(&channel_id)
     }
 
-    /// 检查用户是否在白名单中
-    fn is_user_allowed(&self, user_id: u64) -> bool {
-        if self.config.allowed_users.is_empty() {
-            return true;
+    /// 把发送目标转换为频道 ID（Discord 的子区本身也是频道，白名单按所在频道检查）
+    fn resolve_channel(&self, target: &ChannelTarget) -> Result<u64> {
+        let parse = |id: &str| -> Result<u64> {
+            id.parse().with_context(|| format!("无效的 Discord Channel ID: {}", id))
+        };
+        let (channel_id, send_to) = match target {
+            ChannelTarget::ChatId(id) | ChannelTarget::Reply { chat: id, .. } => (parse(id)?, parse(id)?),
+            ChannelTarget::Thread { chat, thread } => (parse(chat)?, parse(thread)?),
+            _ => return Err(target.unsupported("discord")),
+        };
+
+        // 检查白名单
+        if !self.is_channel_allowed(channel_id) {
+            anyhow::bail!("频道 {} 不在白名单中", channel_id);
         }
-        self.config.allowed_users.contains(&user_id)
+        Ok(send_to)
     }
 
     /// 分割长消息（Discord 限制 2000 字符）
@@ -122,29 +120,25 @@ impl Channel for DiscordChannel {
         Ok(())
     }
 
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        self.resolve_channel(target).map(|_| ())
+    }
+
     async fn send_message(
         &self,
-        target: &str,
+        target: &ChannelTarget,
         content: &str,
     ) -> Result<()> {
         info!("发送 Discord 消息到 {}: {}", target, content);
 
-        // 解析 target 为 channel_id
-        let channel_id: u64 = target
-            .parse()
-            .context("无效的 Discord Channel ID")?;
-
-        // 检查白名单
-        if !self.is_channel_allowed(channel_id) {
-            anyhow::bail!("频道 {} 不在白名单中", channel_id);
-        }
+        let channel_id = self.resolve_channel(target)?;
 
         // 分割长消息
         let chunks = Self::split_message(content, 2000);
 
         // TODO: 使用 serenity 发送消息
         for (i, chunk) in chunks.iter().enumerate() {
-            info!("发送消息块 {}/{}: {}", i + 1, chunks.len(), chunk);
+            info!("发送消息块 {}/{} 到频道 {}: {}", i + 1, chunks.len(), channel_id, chunk);
         }
 
         Ok(())
@@ -159,13 +153,16 @@ impl Channel for DiscordChannel {
 use serenity::async_trait as serenity_async_trait;
 use serenity::model::application::interaction::{Interaction, InteractionResponseType};
 use serenity::model::channel::Message;
+use serenity::model::event::MessageUpdateEvent;
 use serenity::model::gateway::Ready;
 use serenity::model::id::{ChannelId, GuildId, UserId};
 use serenity::prelude::*;
 
 struct DiscordHandler {
-    agent: Arc<crate::agent::Agent>,
+    handler: Arc<dyn MessageHandler>,
     config: DiscordConfig,
+    /// 各频道最后一条用户消息的 ID
+    last_message_ids: std::sync::Mutex<std::collections::HashMap<u64, u64>>,
 }
 
 #[serenity_async_trait]
@@ -196,12 +193,21 @@ impl EventHandler for DiscordHandler {
 
         // 处理消息
         info!("收到 Discord 消息: {}", msg.content);
-
-        // 调用 Agent 处理
-        match self.agent.chat(&msg.content).await {
+        self.last_message_ids.lock().unwrap().insert(msg.channel_id.0, msg.id.0);
+
+        // 交给处理器
+        // 回复某条消息时带上被引用的内容
+        let quoted = msg.referenced_message.as_ref().map(|m| QuotedMessage {
+            from_bot: m.author.bot,
+            content: m.content.clone(),
+        });
+        let inbound = InboundMessage::new("discord", msg.channel_id.to_string(), msg.author.id.to_string(), &msg.content)
+            .with_reply_to(quoted)
+            .with_group(msg.guild_id.is_some());
+        match self.handler.handle(inbound).await {
             Ok(response) => {
                 // 发送响应
-                let chunks = DiscordChannel::split_message(&response.content, 2000);
+                let chunks = DiscordChannel::split_message(&response, 2000);
                 for chunk in chunks {
                     if let Err(e) = msg.channel_id.say(&ctx.http, chunk).await {
                         error!("发送消息失败: {}", e);
@@ -215,6 +221,35 @@ impl EventHandler for DiscordHandler {
         }
     }
 
+    async fn message_update(&self,
+        ctx: Context,
+        _old: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // 用户编辑消息：最后一条时重新生成回复，否则随下一条消息告知 Agent
+        let (Some(author), Some(content)) = (event.author, event.content) else {
+            return;
+        };
+        if author.bot || !self.is_user_allowed(author.id.0) {
+            return;
+        }
+        let latest = self.last_message_ids.lock().unwrap().get(&event.channel_id.0) == Some(&event.id.0);
+        let inbound = InboundMessage::new("discord", event.channel_id.to_string(), author.id.to_string(), content)
+            .with_group(event.guild_id.is_some());
+        match self.handler.handle_edit(inbound, latest).await {
+            Ok(response) if !response.is_empty() => {
+                for chunk in DiscordChannel::split_message(&response, 2000) {
+                    if let Err(e) = event.channel_id.say(&ctx.http, chunk).await {
+                        error!("发送消息失败: {}", e);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("处理编辑消息失败: {}", e),
+        }
+    }
+
     async fn ready(&self,
         _ctx: Context,
         ready: Ready,
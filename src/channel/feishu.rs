@@ -1,20 +1,33 @@
 //! 飞书(Feishu/Lark) 通道实现
 //!
-//! 使用飞书开放平台的 Webhook 和 Bot API，支持 WebSocket 长连接模式
+//! 使用飞书开放平台的 Webhook 和 Bot API，支持 WebSocket 长连接模式；
+//! 事件订阅的请求地址为共享 HTTP 服务的 `/feishu/events`
+//!
+//! 私聊按发送者 Open ID 建立会话；群聊按 Chat ID 建立会话（默认仅在 @机器人 时回复），
+//! 并以引用或话题（root_id）方式回复原消息
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
 use regex::Regex;
-use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::LinkedList;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-use crate::channel::{Channel, Media, MediaType};
+use crate::channel::dedupe::DedupeStore;
+use crate::channel::outbox::{self, Outbox};
+use crate::channel::{Channel, ChannelTarget, InboundMessage, MessageHandler};
 use crate::config::FeishuConfig;
+use crate::crash;
 
 /// 消息类型映射
 const MSG_TYPE_MAP: &[(&str, &str)] = &[
@@ -39,13 +52,200 @@ struct TenantAccessTokenResponse {
 struct FeishuMessageResponse {
     code: i32,
     msg: String,
-    data: Option<serde_json::Value>,
+}
+
+/// 长文本使用卡片发送的字数阈值
+const CARD_THRESHOLD: usize = 500;
+
+/// 图片识别命令超时
+const OCR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+/// 图片识别结果最多保留的字符数
+const OCR_MAX_CHARS: usize = 4000;
+
+/// 消息中的媒体资源
+#[derive(Debug, Clone, PartialEq)]
+struct MediaResource {
+    /// 资源 key（image_key 或 file_key）
+    key: String,
+    /// 下载接口的资源类型：image 或 file
+    resource_type: &'static str,
+    /// 保存的文件名
+    file_name: String,
+}
+
+impl MediaResource {
+    /// 从消息内容中解析媒体资源（image / file / audio / media）
+    fn parse(msg_type: &str, content: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| content.get(name).and_then(|v| v.as_str());
+        let (key, resource_type, default_name) = match msg_type {
+            "image" => (field("image_key")?, "image", "image.png"),
+            "file" => (field("file_key")?, "file", "file"),
+            "audio" => (field("file_key")?, "file", "audio.opus"),
+            "media" => (field("file_key")?, "file", "video.mp4"),
+            _ => return None,
+        };
+
+        // 只保留文件名部分，防止路径穿越
+        let file_name = field("file_name")
+            .and_then(|name| Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or(default_name);
+
+        Some(Self {
+            key: key.to_string(),
+            resource_type,
+            file_name: file_name.to_string(),
+        })
+    }
+}
+
+/// 解析后的入站消息
+#[derive(Debug, Clone, PartialEq)]
+struct IncomingMessage {
+    /// 发送者 Open ID
+    sender: String,
+    /// 会话 Chat ID
+    chat_id: String,
+    /// 是否来自群聊
+    is_group: bool,
+    message_id: String,
+    /// 话题根消息 ID（话题内的消息才有）
+    root_id: Option<String>,
+    msg_type: String,
+    /// 消息内容（JSON 字符串）
+    content: String,
+    /// 被 @ 的 (占位符, Open ID)，如 ("@_user_1", "ou_xxx")
+    mentions: Vec<(String, String)>,
+}
+
+impl IncomingMessage {
+    /// 从 im.message.receive_v1 事件数据解析
+    fn parse(event_data: &serde_json::Value) -> Result<Self> {
+        let str_field = |v: Option<&serde_json::Value>| {
+            v.and_then(|v| v.as_str()).unwrap_or("").to_string()
+        };
+
+        let sender = str_field(
+            event_data
+                .get("sender")
+                .and_then(|s| s.get("sender_id"))
+                .and_then(|id| id.get("open_id")),
+        );
+
+        let message = event_data
+            .get("message")
+            .ok_or_else(|| anyhow::anyhow!("消息数据为空"))?;
+
+        let mentions = message
+            .get("mentions")
+            .and_then(|m| m.as_array())
+            .map(|mentions| {
+                mentions
+                    .iter()
+                    .map(|m| {
+                        (
+                            str_field(m.get("key")),
+                            str_field(m.get("id").and_then(|id| id.get("open_id"))),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            sender,
+            chat_id: str_field(message.get("chat_id")),
+            is_group: message.get("chat_type").and_then(|t| t.as_str()) == Some("group"),
+            message_id: str_field(message.get("message_id")),
+            root_id: message
+                .get("root_id")
+                .and_then(|id| id.as_str())
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string()),
+            msg_type: str_field(message.get("message_type")),
+            content: message
+                .get("content")
+                .and_then(|c| c.as_str())
+                .unwrap_or("{}")
+                .to_string(),
+            mentions,
+        })
+    }
+
+    /// 是否 @ 了机器人（未知机器人 Open ID 时，任意 @ 都视为提及）
+    fn mentions_bot(&self, bot_open_id: Option<&str>) -> bool {
+        match bot_open_id {
+            Some(bot) => self.mentions.iter().any(|(_, id)| id == bot),
+            None => !self.mentions.is_empty(),
+        }
+    }
+
+    /// 去掉文本中的 @ 占位符
+    fn strip_mentions(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (key, _) in &self.mentions {
+            if !key.is_empty() {
+                text = text.replace(key.as_str(), "");
+            }
+        }
+        text.trim().to_string()
+    }
+
+    /// 回复目标：话题内的消息在话题中回复，群消息引用回复，私聊直接发给发送者
+    fn reply_target(&self) -> ChannelTarget {
+        match (&self.root_id, self.is_group) {
+            (Some(root_id), _) => ChannelTarget::Thread {
+                chat: self.chat_id.clone(),
+                thread: root_id.clone(),
+            },
+            (None, true) if !self.message_id.is_empty() => ChannelTarget::Reply {
+                chat: self.chat_id.clone(),
+                message: self.message_id.clone(),
+            },
+            _ => ChannelTarget::chat(self.sender.as_str()),
+        }
+    }
+}
+
+/// 解析发送目标，返回 (接收者 ID, 可选的 (被回复消息 ID, 是否在话题中回复))
+fn resolve_target(target: &ChannelTarget) -> Result<(&str, Option<(&str, bool)>)> {
+    match target {
+        ChannelTarget::ChatId(id) | ChannelTarget::UserId(id) | ChannelTarget::Email(id) => Ok((id, None)),
+        ChannelTarget::Thread { chat, thread } => Ok((chat, Some((thread, true)))),
+        ChannelTarget::Reply { chat, message } => Ok((chat, Some((message, false)))),
+        ChannelTarget::Phone(_) => Err(target.unsupported("feishu")),
+    }
+}
+
+/// 根据 ID 前缀确定 receive_id_type：oc_ 为群 Chat ID，on_ 为 Union ID，含 @ 为邮箱，其余按 Open ID
+fn receive_id_type(receive_id: &str) -> &'static str {
+    if receive_id.starts_with("oc_") {
+        "chat_id"
+    } else if receive_id.contains('@') {
+        "email"
+    } else if receive_id.starts_with("on_") {
+        "union_id"
+    } else {
+        "open_id"
+    }
+}
+
+/// 长文本或含 Markdown（代码块、表格、标题、加粗）时使用卡片发送
+fn prefers_card(content: &str) -> bool {
+    content.chars().count() > CARD_THRESHOLD
+        || content.contains("```")
+        || content.contains("**")
+        || content
+            .lines()
+            .map(|l| l.trim_start())
+            .any(|l| l.starts_with('#') || l.starts_with('|'))
 }
 
 /// 飞书通道
 pub struct FeishuChannel {
     config: FeishuConfig,
-    agent: Arc<crate::agent::Agent>,
+    /// 入站消息处理器
+    handler: Arc<dyn MessageHandler>,
     /// 访问令牌
     access_token: RwLock<Option<String>>,
     /// 令牌过期时间
@@ -54,15 +254,21 @@ pub struct FeishuChannel {
     running: RwLock<bool>,
     /// HTTP 客户端
     http_client: reqwest::Client,
-    /// 消息去重缓存 (Ordered set - 只保存最近 1000 条)
-    processed_message_ids: RwLock<LinkedList<String>>,
+    /// 入站消息去重
+    dedupe: Option<Arc<DedupeStore>>,
+    /// 发件箱（启用时回复经发件箱投递）
+    outbox: Option<Arc<Outbox>>,
+    /// 机器人自身的 Open ID（用于识别群聊中的 @）
+    bot_open_id: RwLock<Option<String>>,
+    /// 收到的媒体文件保存目录
+    media_dir: Option<PathBuf>,
 }
 
 impl FeishuChannel {
     /// 创建新的飞书通道
     pub fn new(
         config: FeishuConfig,
-        agent: Arc<crate::agent::Agent>,
+        handler: Arc<dyn MessageHandler>,
     ) -> Result<Self> {
         // 验证配置
         if config.app_id.is_none() || config.app_secret.is_none() {
@@ -76,37 +282,34 @@ impl FeishuChannel {
 
         Ok(Self {
             config,
-            agent,
+            handler,
             access_token: RwLock::new(None),
             token_expire_at: RwLock::new(None),
             running: RwLock::new(false),
             http_client,
-            processed_message_ids: RwLock::new(LinkedList::new()),
+            dedupe: None,
+            outbox: None,
+            bot_open_id: RwLock::new(None),
+            media_dir: None,
         })
     }
 
-    /// 检查消息是否已处理（去重）
-    async fn is_message_processed(&self, message_id: &str) -> bool {
-        let cache = self.processed_message_ids.read().await;
-        cache.contains(message_id)
+    /// 设置发件箱
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
     }
 
-    /// 添加消息到已处理缓存
-    async fn add_processed_message(&self, message_id: &str) {
-        let mut cache = self.processed_message_ids.write().await;
-        // 保持缓存大小不超过 1000
-        if cache.len() >= 1000 {
-            cache.pop_front();
-        }
-        cache.push_back(message_id.to_string());
+    /// 设置入站消息去重
+    pub fn with_dedupe(mut self, dedupe: Option<Arc<DedupeStore>>) -> Self {
+        self.dedupe = dedupe;
+        self
     }
 
-    /// 清理过期的消息 ID
-    async fn trim_message_cache(&self) {
-        let mut cache = self.processed_message_ids.write().await;
-        while cache.len() > 1000 {
-            cache.pop_front();
-        }
+    /// 设置媒体文件保存目录（按会话分子目录）
+    pub fn with_media_dir(mut self, media_dir: PathBuf) -> Self {
+        self.media_dir = Some(media_dir);
+        self
     }
 
     /// 获取消息类型的显示文本
@@ -121,14 +324,6 @@ impl FeishuChannel {
             })
     }
 
-    /// 检查用户是否在白名单中
-    fn is_user_allowed(&self, user_id: &str) -> bool {
-        if self.config.allowed_users.is_empty() {
-            return true;
-        }
-        self.config.allowed_users.contains(&user_id.to_string())
-    }
-
     /// 检查 Open ID 是否在白名单中
     fn is_open_id_allowed(&self, open_id: &str) -> bool {
         if self.config.allowed_open_ids.is_empty() {
@@ -137,6 +332,23 @@ impl FeishuChannel {
         self.config.allowed_open_ids.contains(&open_id.to_string())
     }
 
+    /// 检查群是否在白名单中
+    fn is_chat_allowed(&self, chat_id: &str) -> bool {
+        if self.config.allowed_chats.is_empty() {
+            return true;
+        }
+        self.config.allowed_chats.contains(&chat_id.to_string())
+    }
+
+    /// 检查发送目标（群或用户）是否在白名单中
+    fn is_target_allowed(&self, receive_id: &str) -> bool {
+        if receive_id_type(receive_id) == "chat_id" {
+            self.is_chat_allowed(receive_id)
+        } else {
+            self.is_open_id_allowed(receive_id)
+        }
+    }
+
     /// 获取有效的访问令牌
     async fn get_access_token(&self) -> Result<String> {
         // 检查现有令牌是否有效
@@ -206,6 +418,28 @@ impl FeishuChannel {
         Ok(token)
     }
 
+    /// 获取机器人自身的 Open ID
+    async fn fetch_bot_open_id(&self) -> Result<String> {
+        let token = self.get_access_token().await?;
+
+        let response: serde_json::Value = self.http_client
+            .get("https://open.feishu.cn/open-apis/bot/v3/info")
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("获取机器人信息失败")?
+            .json()
+            .await
+            .context("解析机器人信息失败")?;
+
+        response
+            .get("bot")
+            .and_then(|b| b.get("open_id"))
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow::anyhow!("机器人信息中没有 open_id: {}", response))
+    }
+
     /// 发送文本消息
     async fn send_text_message(
         &self,
@@ -225,7 +459,7 @@ impl FeishuChannel {
         let response: reqwest::Response = self.http_client
             .post("https://open.feishu.cn/open-apis/im/v1/messages")
             .header("Authorization", format!("Bearer {}", token))
-            .query(&[("receive_id_type", "open_id")])
+            .query(&[("receive_id_type", receive_id_type(receive_id))])
             .json(&body)
             .send()
             .await
@@ -243,50 +477,28 @@ impl FeishuChannel {
         Ok(())
     }
 
-    /// 发送卡片消息
-    async fn send_card_message(
+    /// 发送增强型卡片消息（支持 Markdown + 表格）
+    async fn send_enhanced_card_message(
         &self,
         receive_id: &str,
-        title: &str,
         content: &str,
     ) -> Result<()> {
         let token = self.get_access_token().await?;
 
-        let card = serde_json::json!({
-            "config": {
-                "wide_screen_mode": true
-            },
-            "header": {
-                "title": {
-                    "tag": "plain_text",
-                    "content": title
-                }
-            },
-            "elements": [
-                {
-                    "tag": "div",
-                    "text": {
-                        "tag": "lark_md",
-                        "content": content
-                    }
-                }
-            ]
-        });
-
         let body = serde_json::json!({
             "receive_id": receive_id,
             "msg_type": "interactive",
-            "content": card.to_string(),
+            "content": self.build_enhanced_card(content).to_string(),
         });
 
         let response: reqwest::Response = self.http_client
             .post("https://open.feishu.cn/open-apis/im/v1/messages")
             .header("Authorization", format!("Bearer {}", token))
-            .query(&[("receive_id_type", "open_id")])
+            .query(&[("receive_id_type", receive_id_type(receive_id))])
             .json(&body)
             .send()
             .await
-            .context("发送卡片消息失败")?;
+            .context("发送增强型卡片消息失败")?;
 
         let msg_response: FeishuMessageResponse = response
             .json::<FeishuMessageResponse>()
@@ -294,51 +506,51 @@ impl FeishuChannel {
             .context("解析消息响应失败")?;
 
         if msg_response.code != 0 {
-            anyhow::bail!("发送卡片消息失败: {}", msg_response.msg);
+            let log_id = msg_response.msg.clone();
+            anyhow::bail!(
+                "发送飞书消息失败: code={}, msg={}, log_id={}",
+                msg_response.code,
+                msg_response.msg,
+                log_id
+            );
+        } else {
+            debug!("飞书消息已发送到 {}", receive_id);
         }
 
         Ok(())
     }
 
-    /// 发送增强型卡片消息（支持 Markdown + 表格）
-    async fn send_enhanced_card_message(
+    /// 回复指定消息（in_thread 为 true 时在话题中回复），长文本或 Markdown 使用卡片
+    async fn reply_message(
         &self,
-        receive_id: &str,
+        message_id: &str,
         content: &str,
+        in_thread: bool,
     ) -> Result<()> {
         let token = self.get_access_token().await?;
 
-        // 根据 chat_id 格式确定 receive_id_type
-        // open_id 以 "ou_" 开头，chat_id 以 "oc_" 开头
-        let receive_id_type = if receive_id.starts_with("oc_") {
-            "chat_id"
+        let (msg_type, content) = if prefers_card(content) {
+            ("interactive", self.build_enhanced_card(content).to_string())
         } else {
-            "open_id"
+            ("text", serde_json::json!({ "text": content }).to_string())
         };
 
-        // 构建支持 Markdown 表格的卡片
-        let elements = self.build_card_elements(content);
-        let card = serde_json::json!({
-            "config": {
-                "wide_screen_mode": true
-            },
-            "elements": elements
-        });
-
         let body = serde_json::json!({
-            "receive_id": receive_id,
-            "msg_type": "interactive",
-            "content": card.to_string(),
+            "msg_type": msg_type,
+            "content": content,
+            "reply_in_thread": in_thread,
         });
 
         let response: reqwest::Response = self.http_client
-            .post("https://open.feishu.cn/open-apis/im/v1/messages")
+            .post(format!(
+                "https://open.feishu.cn/open-apis/im/v1/messages/{}/reply",
+                message_id
+            ))
             .header("Authorization", format!("Bearer {}", token))
-            .query(&[("receive_id_type", receive_id_type)])
             .json(&body)
             .send()
             .await
-            .context("发送增强型卡片消息失败")?;
+            .context("回复消息失败")?;
 
         let msg_response: FeishuMessageResponse = response
             .json::<FeishuMessageResponse>()
@@ -346,20 +558,23 @@ impl FeishuChannel {
             .context("解析消息响应失败")?;
 
         if msg_response.code != 0 {
-            let log_id = msg_response.msg;
-            anyhow::bail!(
-                "发送飞书消息失败: code={}, msg={}, log_id={}",
-                msg_response.code,
-                msg_response.msg,
-                log_id
-            );
-        } else {
-            debug!("飞书消息已发送到 {}", receive_id);
+            anyhow::bail!("回复消息失败: code={}, msg={}", msg_response.code, msg_response.msg);
         }
 
+        debug!("已回复飞书消息 {}", message_id);
         Ok(())
     }
 
+    /// 构建支持 Markdown 表格的卡片
+    fn build_enhanced_card(&self, content: &str) -> serde_json::Value {
+        serde_json::json!({
+            "config": {
+                "wide_screen_mode": true
+            },
+            "elements": self.build_card_elements(content)
+        })
+    }
+
     /// 解析 Markdown 表格为飞书表格元素
     fn parse_md_table(&self, table_text: &str) -> Option<serde_json::Value> {
         let lines: Vec<&str> = table_text
@@ -373,7 +588,7 @@ impl FeishuChannel {
             return None;
         }
 
-        let split = |l: &str| l.trim_matches('|').split('|').map(|c| c.trim()).collect::<Vec<_>>();
+        fn split(l: &str) -> Vec<&str> { l.trim_matches('|').split('|').map(|c| c.trim()).collect() }
         let headers = split(lines[0]);
         let rows: Vec<Vec<_>> = lines[2..].iter().map(|l| split(l)).collect();
 
@@ -393,11 +608,9 @@ impl FeishuChannel {
         let table_rows: Vec<serde_json::Value> = rows
             .iter()
             .map(|row| {
-                let row_json: serde_json::Map<String, serde_json::Value> = headers
-                    .iter()
-                    .enumerate()
-                    .map(|(i, h)| {
-                        let value = row.get(i).map(|s| *s).unwrap_or("");
+                let row_json: serde_json::Map<String, serde_json::Value> = (0..headers.len())
+                    .map(|i| {
+                        let value = row.get(i).copied().unwrap_or("");
                         (format!("c{}", i), serde_json::Value::String(value.to_string()))
                     })
                     .collect();
@@ -463,206 +676,116 @@ impl FeishuChannel {
         elements
     }
 
-    /// 添加反应（反应类型如 THUMBSUP, OK, EYES, DONE, OnIt, HEART）
-    async fn add_reaction(&self, message_id: &str, emoji_type: &str) -> Result<()> {
+    /// 下载消息中的资源文件
+    async fn download_resource(
+        &self,
+        message_id: &str,
+        resource: &MediaResource,
+        dest: &Path,
+    ) -> Result<()> {
         let token = self.get_access_token().await?;
 
-        let body = serde_json::json!({
-            "reaction_type": {
-                "emoji_type": emoji_type
-            }
-        });
-
         let response: reqwest::Response = self.http_client
-            .post(&format!(
-                "https://open.feishu.cn/open-apis/im/v1/messages/{}/reactions",
-                message_id
+            .get(format!(
+                "https://open.feishu.cn/open-apis/im/v1/messages/{}/resources/{}",
+                message_id, resource.key
             ))
             .header("Authorization", format!("Bearer {}", token))
-            .json(&body)
+            .query(&[("type", resource.resource_type)])
             .send()
             .await
-            .context("添加反应失败")?;
-
-        let reaction_response: serde_json::Value = response
-            .json()
-            .await
-            .context("解析反应响应失败")?;
-
-        if let Some(code) = reaction_response.get("code") {
-            if code != 0 {
-                let msg = reaction_response.get("msg")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("未知错误");
-                warn!("添加反应失败: code={}, msg={}", code, msg);
-            } else {
-                debug!("已添加 {} 反应到消息 {}", emoji_type, message_id);
-            }
+            .context("下载资源失败")?;
+
+        // 失败时返回 JSON 错误信息
+        let is_json = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if !response.status().is_success() || is_json {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("下载资源失败: status={}, {}", status, body);
         }
 
-        Ok(())
-    }
-
-    /// 上传图片到飞书
-    async fn upload_image(&self, image_path: &str) -> Result<String> {
-        let token = self.get_access_token().await?;
-
-        // 读取图片文件内容
-        let file_content = tokio::fs::read(image_path).await
-            .context("读取图片文件失败")?;
-        let file_name = std::path::Path::new(image_path).file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("image.png");
-
-        let part = reqwest::multipart::Part::bytes(file_content)
-            .file_name(file_name.to_string())
-            .mime_str("image/png")?;
-
-        let response: reqwest::Response = self.http_client
-            .post("https://open.feishu.cn/open-apis/im/v1/images")
-            .header("Authorization", format!("Bearer {}", token))
-            .multipart(reqwest::multipart::Form::new().part("image", part))
-            .send()
-            .await
-            .context("上传图片失败")?;
-
-        #[derive(Debug, Clone, serde::Deserialize)]
-        struct UploadResponse {
-            code: i32,
-            msg: String,
-            data: Option<serde_json::Value>,
+        let bytes = response.bytes().await.context("读取资源内容失败")?;
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
         }
-
-        let upload_response: UploadResponse = response
-            .json::<UploadResponse>()
+        tokio::fs::write(dest, &bytes)
             .await
-            .context("解析上传响应失败")?;
-
-        if upload_response.code != 0 {
-            anyhow::bail!("上传图片失败: {}", upload_response.msg);
-        }
+            .with_context(|| format!("保存资源失败: {}", dest.display()))?;
 
-        let image_key = upload_response
-            .data
-            .and_then(|d| d.get("image_key").cloned())
-            .and_then(|k| k.as_str().map(|s| s.to_string()))
-            .ok_or_else(|| anyhow::anyhow!("图片上传成功但未返回 image_key"))?;
-
-        info!("图片上传成功: {}", image_key);
-        Ok(image_key)
+        info!("已下载飞书资源到 {} ({} 字节)", dest.display(), bytes.len());
+        Ok(())
     }
 
-    /// 上传文件到飞书
-    async fn upload_file(&self, file_path: &str, file_name: &str) -> Result<String> {
-        let token = self.get_access_token().await?;
-
-        // 读取文件内容
-        let file_content = tokio::fs::read(file_path).await
-            .context("读取文件失败")?;
-
-        let part = reqwest::multipart::Part::bytes(file_content)
-            .file_name(file_name.to_string())
-            .mime_str("application/octet-stream")?;
-
-        let response: reqwest::Response = self.http_client
-            .post("https://open.feishu.cn/open-apis/im/v1/files")
-            .header("Authorization", format!("Bearer {}", token))
-            .multipart(reqwest::multipart::Form::new().part("file", part))
-            .send()
-            .await
-            .context("上传文件失败")?;
-
-        #[derive(Debug, Clone, serde::Deserialize)]
-        struct UploadResponse {
-            code: i32,
-            msg: String,
-            data: Option<serde_json::Value>,
-        }
-
-        let upload_response: UploadResponse = response
-            .json::<UploadResponse>()
-            .await
-            .context("解析上传响应失败")?;
-
-        if upload_response.code != 0 {
-            anyhow::bail!("上传文件失败: {}", upload_response.msg);
+    /// 运行图片识别命令，返回识别出的文字
+    async fn run_ocr(&self, command: &str, image_path: &Path) -> Result<String> {
+        let path = image_path.to_string_lossy();
+        let args: Vec<String> = command
+            .split_whitespace()
+            .map(|arg| arg.replace("{path}", &path))
+            .collect();
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("图片识别命令为空"))?;
+
+        let output = tokio::time::timeout(
+            OCR_TIMEOUT,
+            tokio::process::Command::new(program).args(args).output(),
+        )
+        .await
+        .context("图片识别超时")?
+        .with_context(|| format!("执行图片识别命令失败: {}", program))?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "图片识别命令退出码 {}: {}",
+                output.status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
         }
 
-        let file_id = upload_response
-            .data
-            .and_then(|d| d.get("file").cloned())
-            .and_then(|f| f.get("file_id").cloned())
-            .and_then(|id| id.as_str().map(|s| s.to_string()))
-            .ok_or_else(|| anyhow::anyhow!("文件上传成功但未返回 file_id"))?;
-
-        info!("文件上传成功: {}", file_id);
-        Ok(file_id)
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(crate::tools::truncate_output(&text, OCR_MAX_CHARS))
     }
 
-    /// 发送图片消息
-    async fn send_image_message(&self, receive_id: &str, image_key: &str) -> Result<()> {
-        let token = self.get_access_token().await?;
+    /// 下载图片/文件消息的资源，返回交给 Agent 的描述文本
+    async fn describe_media(&self, incoming: &IncomingMessage, session_dir: &str) -> Option<String> {
+        let placeholder = self.get_msg_type_text(&incoming.msg_type).to_string();
 
-        let body = serde_json::json!({
-            "receive_id": receive_id,
-            "msg_type": "image",
-            "content": serde_json::json!({
-                "image_key": image_key
-            }).to_string(),
-        });
+        let content_json: serde_json::Value = serde_json::from_str(&incoming.content).ok()?;
+        let resource = MediaResource::parse(&incoming.msg_type, &content_json)?;
 
-        let response: reqwest::Response = self.http_client
-            .post("https://open.feishu.cn/open-apis/im/v1/messages")
-            .header("Authorization", format!("Bearer {}", token))
-            .query(&[("receive_id_type", "open_id")])
-            .json(&body)
-            .send()
-            .await
-            .context("发送图片消息失败")?;
-
-        let msg_response: FeishuMessageResponse = response
-            .json::<FeishuMessageResponse>()
-            .await
-            .context("解析消息响应失败")?;
+        let media_dir = match (&self.media_dir, self.config.download_media) {
+            (Some(dir), true) => dir.join(session_dir),
+            _ => return Some(placeholder),
+        };
+        let dest = media_dir.join(format!("{}_{}", incoming.message_id, resource.file_name));
 
-        if msg_response.code != 0 {
-            anyhow::bail!("发送图片消息失败: {}", msg_response.msg);
+        if let Err(e) = self.download_resource(&incoming.message_id, &resource, &dest).await {
+            warn!("下载飞书资源失败: {}", e);
+            return Some(placeholder);
         }
 
-        Ok(())
-    }
-
-    /// 发送文件消息
-    async fn send_file_message(&self, receive_id: &str, file_id: &str, file_name: &str) -> Result<()> {
-        let token = self.get_access_token().await?;
-
-        let body = serde_json::json!({
-            "receive_id": receive_id,
-            "msg_type": "file",
-            "content": serde_json::json!({
-                "file_id": file_id
-            }).to_string(),
-        });
-
-        let response: reqwest::Response = self.http_client
-            .post("https://open.feishu.cn/open-apis/im/v1/messages")
-            .header("Authorization", format!("Bearer {}", token))
-            .query(&[("receive_id_type", "open_id")])
-            .json(&body)
-            .send()
-            .await
-            .context("发送文件消息失败")?;
-
-        let msg_response: FeishuMessageResponse = response
-            .json::<FeishuMessageResponse>()
-            .await
-            .context("解析消息响应失败")?;
+        let mut text = if resource.resource_type == "image" {
+            format!("[用户发送了图片，已保存到 {}]", dest.display())
+        } else {
+            format!("[用户发送了文件 {}，已保存到 {}]", resource.file_name, dest.display())
+        };
 
-        if msg_response.code != 0 {
-            anyhow::bail!("发送文件消息失败: {}", msg_response.msg);
+        if let (Some(command), "image") = (&self.config.ocr_command, resource.resource_type) {
+            match self.run_ocr(command, &dest).await {
+                Ok(ocr) if !ocr.is_empty() => {
+                    text.push_str(&format!("\n图片中的文字：\n{}", ocr));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("图片识别失败: {}", e),
+            }
         }
 
-        Ok(())
+        Some(text)
     }
 
     /// 验证 Webhook 签名（用于事件订阅）
@@ -710,59 +833,74 @@ impl FeishuChannel {
                     .get("event")
                     .ok_or_else(|| anyhow::anyhow!("事件数据为空"))?;
 
-                let sender = event_data
-                    .get("sender")
-                    .and_then(|s| s.get("sender_id"))
-                    .and_then(|id| id.get("open_id"))
-                    .and_then(|id| id.as_str())
-                    .unwrap_or("");
-
-                let message = event_data
-                    .get("message")
-                    .ok_or_else(|| anyhow::anyhow!("消息数据为空"))?;
-
-                let msg_type = message
-                    .get("message_type")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("");
+                let incoming = IncomingMessage::parse(event_data)?;
+                let sender = incoming.sender.as_str();
 
                 // 检查白名单
                 if !self.is_open_id_allowed(sender) {
                     warn!("用户 {} 不在白名单中", sender);
                     return Ok(None);
                 }
-
-                // 只处理文本消息
-                if msg_type != "text" {
+                if incoming.is_group && !self.is_chat_allowed(&incoming.chat_id) {
+                    warn!("群 {} 不在白名单中", incoming.chat_id);
                     return Ok(None);
                 }
 
-                let content = message
-                    .get("content")
-                    .and_then(|c| c.as_str())
-                    .unwrap_or("{}");
+                // 去重（飞书在回调超时时会重推事件）
+                if let Some(dedupe) = &self.dedupe {
+                    if !incoming.message_id.is_empty()
+                        && !dedupe.check_and_insert("feishu", &incoming.message_id).await?
+                    {
+                        return Ok(None);
+                    }
+                }
+
+                // 群聊中默认只响应 @机器人 的消息
+                if incoming.is_group && self.config.require_mention {
+                    let bot_open_id = self.bot_open_id.read().await.clone();
+                    if !incoming.mentions_bot(bot_open_id.as_deref()) {
+                        debug!("群 {} 中的消息未 @机器人，忽略", incoming.chat_id);
+                        return Ok(None);
+                    }
+                }
 
-                let content_json: serde_json::Value = serde_json::from_str(content)?;
-                let text = content_json
-                    .get("text")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("");
+                // 群聊按群建立会话，私聊按用户
+                let chat_id = if incoming.is_group { incoming.chat_id.as_str() } else { sender };
+
+                let text = if incoming.msg_type == "text" {
+                    let content_json: serde_json::Value = serde_json::from_str(&incoming.content)?;
+                    incoming.strip_mentions(
+                        content_json
+                            .get("text")
+                            .and_then(|t| t.as_str())
+                            .unwrap_or(""),
+                    )
+                } else {
+                    // 图片、文件等下载到会话目录后以文字描述交给 Agent
+                    match self.describe_media(&incoming, &format!("feishu_{}", chat_id)).await {
+                        Some(text) => text,
+                        None => return Ok(None),
+                    }
+                };
 
                 info!("收到飞书消息: {}", text);
+                let reply_to = incoming.reply_target();
 
-                // 调用 Agent 处理
-                match self.agent.chat(text).await {
+                // 交给处理器
+                let inbound = InboundMessage::new("feishu", chat_id, sender, text).with_group(incoming.is_group);
+                match self.handler.handle(inbound).await {
+                    Ok(response) if response.is_empty() => Ok(None),
                     Ok(response) => {
                         // 发送响应
-                        if let Err(e) = self.send_text_message(sender, &response.content).await {
+                        if let Err(e) = outbox::deliver(self.outbox.as_ref(), self, &reply_to, &response).await {
                             error!("发送响应失败: {}", e);
                         }
-                        Ok(Some(response.content))
+                        Ok(Some(response))
                     }
                     Err(e) => {
-                        error!("Agent 处理失败: {}", e);
+                        error!("处理消息失败: {}", e);
                         let error_msg = "处理消息时出错，请稍后重试";
-                        if let Err(e) = self.send_text_message(sender, error_msg).await {
+                        if let Err(e) = self.send_message(&reply_to, error_msg).await {
                             error!("发送错误消息失败: {}", e);
                         }
                         Ok(Some(error_msg.to_string()))
@@ -777,6 +915,68 @@ impl FeishuChannel {
     }
 }
 
+/// 事件订阅回调（`POST /feishu/events`）
+///
+/// URL 验证请求直接返回 challenge；其余事件校验后在后台处理并立即应答，飞书要求 3 秒内响应
+async fn event_callback(
+    State(channel): State<Arc<FeishuChannel>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let event: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("请求体不是合法 JSON: {}", e)).into_response(),
+    };
+
+    // 校验 Verification Token（URL 验证在顶层，2.0 事件在 header 中）
+    if let Some(ref expected) = channel.config.verification_token {
+        let token = event
+            .get("token")
+            .or_else(|| event.get("header").and_then(|h| h.get("token")))
+            .and_then(|t| t.as_str());
+        if token != Some(expected.as_str()) {
+            warn!("飞书事件 Verification Token 校验失败");
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
+    if event.get("type").and_then(|t| t.as_str()) == Some("url_verification") {
+        let challenge = event.get("challenge").cloned().unwrap_or_default();
+        return Json(serde_json::json!({ "challenge": challenge })).into_response();
+    }
+    if event.get("encrypt").is_some() {
+        warn!("收到加密的飞书事件，暂不支持解密，请在开放平台关闭 Encrypt Key");
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    if channel.config.verify_signature {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or("");
+        match channel.verify_webhook_signature(
+            header("x-lark-request-timestamp"),
+            header("x-lark-request-nonce"),
+            &body,
+            header("x-lark-signature"),
+        ) {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("飞书事件签名校验失败");
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+            Err(e) => {
+                error!("飞书事件签名校验出错: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    }
+
+    crash::spawn("feishu:event", async move {
+        if let Err(e) = channel.handle_webhook_event(&event).await {
+            error!("处理飞书事件失败: {}", e);
+        }
+    });
+    StatusCode::OK.into_response()
+}
+
 #[async_trait]
 impl Channel for FeishuChannel {
     fn name(&self) -> &str {
@@ -789,12 +989,22 @@ impl Channel for FeishuChannel {
         // 预获取访问令牌
         self.get_access_token().await?;
 
+        // 获取机器人 Open ID，用于识别群聊中的 @
+        match self.fetch_bot_open_id().await {
+            Ok(open_id) => *self.bot_open_id.write().await = Some(open_id),
+            Err(e) => warn!("获取机器人 Open ID 失败，群聊中任意 @ 都将触发回复: {}", e),
+        }
+
         *self.running.write().await = true;
         info!("飞书 Bot 已启动");
 
         Ok(())
     }
 
+    fn routes(self: Arc<Self>) -> Option<Router> {
+        Some(Router::new().route("/events", post(event_callback)).with_state(self))
+    }
+
     async fn stop(&self) -> Result<()> {
         info!("停止飞书 Bot...");
         *self.running.write().await = false;
@@ -804,85 +1014,39 @@ impl Channel for FeishuChannel {
         Ok(())
     }
 
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        let (receive_id, _) = resolve_target(target)?;
+        if !self.is_target_allowed(receive_id) {
+            anyhow::bail!("用户 {} 不在白名单中", receive_id);
+        }
+        Ok(())
+    }
+
     async fn send_message(
         &self,
-        target: &str,
+        target: &ChannelTarget,
         content: &str,
     ) -> Result<()> {
         info!("发送飞书消息到 {}: {}", target, content);
 
-        // 检查白名单
-        if !self.is_open_id_allowed(target) {
-            anyhow::bail!("用户 {} 不在白名单中", target);
-        }
-
-        // 发送消息
-        self.send_text_message(target, content).await
-    }
-
-    async fn send_media(
-        &self,
-        target: &str,
-        media: &Media,
-    ) -> Result<()> {
-        info!("发送飞书媒体消息到 {}", target);
+        // 回复目标可能带有被回复的消息 ID
+        let (receive_id, reply) = resolve_target(target)?;
 
         // 检查白名单
-        if !self.is_open_id_allowed(target) {
-            anyhow::bail!("用户 {} 不在白名单中", target);
+        if !self.is_target_allowed(receive_id) {
+            anyhow::bail!("用户 {} 不在白名单中", receive_id);
         }
 
-        match media.media_type {
-            MediaType::Image => {
-                // 获取图片路径
-                let image_path = media
-                    .path
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("图片路径未提供"))?;
-
-                // 上传图片
-                let image_key = self.upload_image(image_path).await?;
-
-                // 发送图片消息
-                self.send_image_message(target, &image_key).await?;
-            }
-            MediaType::File => {
-                // 获取文件路径和名称
-                let file_path = media
-                    .path
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("文件路径未提供"))?;
-                let file_name = media
-                    .name
-                    .as_deref()
-                    .ok_or_else(|| anyhow::anyhow!("文件名未提供"))?;
-
-                // 上传文件
-                let file_id = self.upload_file(file_path, file_name).await?;
-
-                // 发送文件消息
-                self.send_file_message(target, &file_id, file_name).await?;
-            }
-            MediaType::Audio => {
-                // 飞书不支持直接发送音频消息类型，使用文件方式发送
-                let file_path = media
-                    .path
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("音频文件路径未提供"))?;
-                let file_name = media
-                    .name
-                    .as_deref()
-                    .unwrap_or("audio.wav");
-
-                // 上传文件
-                let file_id = self.upload_file(file_path, file_name).await?;
-
-                // 发送文件消息
-                self.send_file_message(target, &file_id, file_name).await?;
-            }
+        if let Some((message_id, in_thread)) = reply {
+            return self.reply_message(message_id, content, in_thread).await;
         }
 
-        Ok(())
+        // 长文本或 Markdown 使用卡片发送
+        if prefers_card(content) {
+            self.send_enhanced_card_message(receive_id, content).await
+        } else {
+            self.send_text_message(receive_id, content).await
+        }
     }
 }
 
@@ -900,12 +1064,83 @@ mod tests {
             allowed_users: vec![],
             allowed_open_ids: vec![],
             allowed_chats: vec![],
+            require_mention: true,
+            download_media: true,
+            ocr_command: None,
             verify_signature: true,
             card_template_id: None,
+            quiet_hours: None,
+            merge_window_ms: 0,
+            wake_words: None,
         };
 
         // 创建一个模拟的 agent
         // 注意：实际测试需要更完整的设置
         assert!(config.verify_signature);
     }
+
+    #[test]
+    fn test_parse_group_message() {
+        let event = serde_json::json!({
+            "sender": { "sender_id": { "open_id": "ou_user" } },
+            "message": {
+                "message_id": "om_2",
+                "root_id": "om_1",
+                "chat_id": "oc_group",
+                "chat_type": "group",
+                "message_type": "text",
+                "content": "{\"text\":\"@_user_1 你好\"}",
+                "mentions": [
+                    { "key": "@_user_1", "id": { "open_id": "ou_bot" }, "name": "nanobot" }
+                ]
+            }
+        });
+
+        let incoming = IncomingMessage::parse(&event).unwrap();
+        assert!(incoming.is_group);
+        assert_eq!(incoming.chat_id, "oc_group");
+        assert!(incoming.mentions_bot(Some("ou_bot")));
+        assert!(!incoming.mentions_bot(Some("ou_other")));
+        assert_eq!(incoming.strip_mentions("@_user_1 你好"), "你好");
+
+        // 话题内的消息在话题中回复
+        let target = incoming.reply_target();
+        assert_eq!(target.to_string(), "oc_group#thread:om_1");
+        assert_eq!(resolve_target(&target).unwrap(), ("oc_group", Some(("om_1", true))));
+        assert_eq!(receive_id_type("oc_group"), "chat_id");
+        assert_eq!(receive_id_type("alice@example.com"), "email");
+        assert!(resolve_target(&ChannelTarget::parse("tel:13800000000").unwrap()).is_err());
+
+        // 私聊直接回复发送者
+        let mut p2p = incoming.clone();
+        p2p.is_group = false;
+        p2p.root_id = None;
+        assert_eq!(p2p.reply_target(), ChannelTarget::chat("ou_user"));
+        assert_eq!(resolve_target(&p2p.reply_target()).unwrap(), ("ou_user", None));
+    }
+
+    #[test]
+    fn test_parse_media_resource() {
+        let image = serde_json::json!({ "image_key": "img_v2_xxx" });
+        let resource = MediaResource::parse("image", &image).unwrap();
+        assert_eq!(resource.key, "img_v2_xxx");
+        assert_eq!(resource.resource_type, "image");
+
+        // 文件名中的路径被去掉
+        let file = serde_json::json!({ "file_key": "file_v2_xxx", "file_name": "../../etc/passwd" });
+        let resource = MediaResource::parse("file", &file).unwrap();
+        assert_eq!(resource.resource_type, "file");
+        assert_eq!(resource.file_name, "passwd");
+
+        assert!(MediaResource::parse("sticker", &file).is_none());
+        assert!(MediaResource::parse("image", &file).is_none());
+    }
+
+    #[test]
+    fn test_prefers_card() {
+        assert!(!prefers_card("你好"));
+        assert!(prefers_card("```rust\nfn main() {}\n```"));
+        assert!(prefers_card("| a | b |\n|---|---|\n| 1 | 2 |"));
+        assert!(prefers_card(&"字".repeat(CARD_THRESHOLD + 1)));
+    }
 }
@@ -0,0 +1,250 @@
+//! 消息内容过滤
+//!
+//! 按 `[content_filter]` 的正则规则检查用户消息和回复：block 拦截整条消息，redact 把命中的部分打码，
+//! flag 只记录。多条规则命中时取最严格的处理方式。模型分类由 [`AgentHandler`](super::handler::AgentHandler)
+//! 调用 Agent 完成，结果同样经 [`ContentFilter::classified`] 转换为处理结果。
+//! 每次命中追加一行 JSON 到审计日志（`<workspace>/content_filter.jsonl`）
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::bus::ContentFilteredEvent;
+use crate::config::{ContentFilterConfig, FilterAction};
+
+/// 审计日志中保留的原文字符数
+const EXCERPT_CHARS: usize = 200;
+
+/// 检查的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterDirection {
+    /// 用户消息
+    Inbound,
+    /// 回复
+    Outbound,
+}
+
+impl FilterDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilterDirection::Inbound => "inbound",
+            FilterDirection::Outbound => "outbound",
+        }
+    }
+}
+
+impl FilterAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilterAction::Flag => "flag",
+            FilterAction::Redact => "redact",
+            FilterAction::Block => "block",
+        }
+    }
+}
+
+/// 内容命中过滤规则后的处理结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterVerdict {
+    pub action: FilterAction,
+    /// 处理后的文本（redact 时为打码后的内容，其他情况为原文）
+    pub text: String,
+    /// 命中的规则或模型给出的理由
+    pub reason: String,
+}
+
+struct CompiledRule {
+    regex: Regex,
+    action: FilterAction,
+}
+
+/// 内容过滤器
+pub struct ContentFilter {
+    config: ContentFilterConfig,
+    rules: Vec<CompiledRule>,
+    audit_path: PathBuf,
+}
+
+impl ContentFilter {
+    /// 编译规则，正则无效时返回错误
+    pub fn new(config: &ContentFilterConfig, audit_path: PathBuf) -> Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                Ok(CompiledRule {
+                    regex: Regex::new(&rule.pattern).with_context(|| format!("内容过滤规则无效: {}", rule.pattern))?,
+                    action: rule.action,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            config: config.clone(),
+            rules,
+            audit_path,
+        })
+    }
+
+    /// 通道的该方向是否启用过滤
+    pub fn applies_to(&self, channel: &str, direction: FilterDirection) -> bool {
+        let enabled = match direction {
+            FilterDirection::Inbound => self.config.inbound,
+            FilterDirection::Outbound => self.config.outbound,
+        };
+        enabled && (self.config.channels.is_empty() || self.config.channels.iter().any(|c| c == channel))
+    }
+
+    /// 按正则规则检查文本，未命中时返回 None
+    pub fn check(&self, text: &str) -> Option<FilterVerdict> {
+        let matched: Vec<&CompiledRule> = self.rules.iter().filter(|r| r.regex.is_match(text)).collect();
+        let action = matched.iter().map(|r| r.action).max()?;
+        let text = if action == FilterAction::Redact {
+            matched
+                .iter()
+                .filter(|r| r.action == FilterAction::Redact)
+                .fold(text.to_string(), |text, r| {
+                    r.regex.replace_all(&text, self.config.redact_with.as_str()).into_owned()
+                })
+        } else {
+            text.to_string()
+        };
+        let reason = matched
+            .iter()
+            .map(|r| format!("规则 {}", r.regex.as_str()))
+            .collect::<Vec<_>>()
+            .join("，");
+        Some(FilterVerdict { action, text, reason })
+    }
+
+    /// 内容分类使用的模型
+    pub fn classifier_model(&self) -> Option<&str> {
+        self.config.classifier_model.as_deref()
+    }
+
+    /// 模型判定为不当内容时的处理结果（无法只打码命中部分，redact 按 block 处理）
+    pub fn classified(&self, text: &str, reason: String) -> FilterVerdict {
+        let action = match self.config.classifier_action {
+            FilterAction::Flag => FilterAction::Flag,
+            FilterAction::Redact | FilterAction::Block => FilterAction::Block,
+        };
+        FilterVerdict {
+            action,
+            text: text.to_string(),
+            reason: format!("模型分类: {}", reason),
+        }
+    }
+
+    /// 消息被拦截时回复给用户的内容
+    pub fn block_message(&self) -> &str {
+        &self.config.block_message
+    }
+
+    /// 追加审计记录
+    pub async fn audit(&self, event: &ContentFilteredEvent) {
+        if let Err(e) = self.append_audit(event).await {
+            warn!("写入内容过滤审计日志失败: {}", e);
+        }
+    }
+
+    async fn append_audit(&self, event: &ContentFilteredEvent) -> Result<()> {
+        if let Some(parent) = self.audit_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.audit_path)
+            .await?;
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// 审计日志中的原文摘录
+pub fn excerpt(text: &str) -> String {
+    let mut excerpt: String = text.chars().take(EXCERPT_CHARS).collect();
+    if text.chars().count() > EXCERPT_CHARS {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ContentFilterRule;
+
+    fn rule(pattern: &str, action: FilterAction) -> ContentFilterRule {
+        ContentFilterRule {
+            pattern: pattern.to_string(),
+            action,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_filter() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = ContentFilterConfig {
+            enabled: true,
+            channels: vec!["telegram".to_string()],
+            outbound: false,
+            rules: vec![
+                rule(r"(?i)\bdamn\b", FilterAction::Redact),
+                rule(r"\d{17}[\dXx]", FilterAction::Redact),
+                rule("炸弹制作", FilterAction::Block),
+                rule("竞品", FilterAction::Flag),
+            ],
+            ..Default::default()
+        };
+        let filter = ContentFilter::new(&config, temp_dir.path().join("audit.jsonl")).unwrap();
+
+        assert!(filter.applies_to("telegram", FilterDirection::Inbound));
+        assert!(!filter.applies_to("telegram", FilterDirection::Outbound));
+        assert!(!filter.applies_to("discord", FilterDirection::Inbound));
+
+        assert_eq!(filter.check("你好"), None);
+
+        // 多条打码规则都生效
+        let verdict = filter.check("Damn, 身份证 11010519491231002X").unwrap();
+        assert_eq!(verdict.action, FilterAction::Redact);
+        assert_eq!(verdict.text, "***, 身份证 ***");
+
+        // 取最严格的处理方式
+        let verdict = filter.check("damn 炸弹制作").unwrap();
+        assert_eq!(verdict.action, FilterAction::Block);
+        assert!(verdict.reason.contains("炸弹制作"));
+
+        let verdict = filter.check("竞品怎么样").unwrap();
+        assert_eq!(verdict.action, FilterAction::Flag);
+        assert_eq!(verdict.text, "竞品怎么样");
+
+        assert_eq!(filter.classified("x", "辱骂".to_string()).action, FilterAction::Block);
+
+        let event = ContentFilteredEvent {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            sender: "2".to_string(),
+            direction: FilterDirection::Inbound.as_str().to_string(),
+            action: verdict.action.as_str().to_string(),
+            reason: verdict.reason,
+            excerpt: Some(excerpt("竞品怎么样")),
+            timestamp: chrono::Utc::now(),
+        };
+        filter.audit(&event).await;
+        filter.audit(&event).await;
+        let log = std::fs::read_to_string(temp_dir.path().join("audit.jsonl")).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.contains("\"action\":\"flag\""));
+
+        let invalid = ContentFilterConfig {
+            rules: vec![rule("(", FilterAction::Block)],
+            ..Default::default()
+        };
+        assert!(ContentFilter::new(&invalid, PathBuf::new()).is_err());
+    }
+}
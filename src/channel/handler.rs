@@ -0,0 +1,1571 @@
+//! 入站消息处理
+//!
+//! 通道只负责收发消息：收到的消息交给 [`MessageHandler`] 处理，
+//! 回复由通道自己的 `send_message` 发出。通道不再直接持有 Agent，
+//! 便于插入中间件、按会话使用不同的 Agent，以及在测试中替换处理器。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::agent::{Agent, AgentResponse, ChatOptions, TurnAborted};
+use crate::bus::{
+    AgentMessageEvent, ContentFilteredEvent, EventBus, EventHandler, ProviderBusyEvent, ToolCallEvent,
+    ToolProgressEvent,
+};
+use crate::channel::filter::{excerpt, ContentFilter, FilterDirection};
+use crate::channel::postprocess::PostProcessor;
+use crate::channel::wake::{WakeCheck, WakeWords};
+use crate::channel::{Channel, ChannelTarget};
+use crate::config::FilterAction;
+use crate::llm::queue::{with_busy_notifier, BusyNotifier};
+use crate::memory::day::split_day;
+use crate::memory::review::{render_pending, PendingMemory, ReviewAction};
+use crate::t;
+use crate::tools::{with_progress_notifier, ProgressNotifier};
+
+/// /sessions 最多列出的会话数
+const SESSION_LIST_LIMIT: i64 = 100;
+/// 引用消息最多保留的字符数
+const QUOTE_MAX_CHARS: usize = 500;
+/// /bestof 未指定次数时的采样次数
+const DEFAULT_BEST_OF: usize = 3;
+
+/// 被回复（引用）的消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotedMessage {
+    /// 是否为 Bot 自己发出的消息
+    pub from_bot: bool,
+    /// 消息文本
+    pub content: String,
+}
+
+/// 入站消息
+#[derive(Debug, Clone)]
+pub struct InboundMessage {
+    /// 通道名称
+    pub channel: String,
+    /// 聊天 ID（回复目标）
+    pub chat_id: String,
+    /// 发送者 ID
+    pub sender: String,
+    /// 消息文本
+    pub content: String,
+    /// 用户回复的消息
+    pub reply_to: Option<QuotedMessage>,
+    /// 是否来自群聊（唤醒词默认只在群聊中生效）
+    pub is_group: bool,
+}
+
+impl InboundMessage {
+    pub fn new(
+        channel: impl Into<String>,
+        chat_id: impl Into<String>,
+        sender: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        Self {
+            channel: channel.into(),
+            chat_id: chat_id.into(),
+            sender: sender.into(),
+            content: content.into(),
+            reply_to: None,
+            is_group: false,
+        }
+    }
+
+    /// 标记消息是否来自群聊
+    pub fn with_group(mut self, is_group: bool) -> Self {
+        self.is_group = is_group;
+        self
+    }
+
+    /// 设置用户回复的消息
+    pub fn with_reply_to(mut self, reply_to: Option<QuotedMessage>) -> Self {
+        self.reply_to = reply_to.filter(|q| !q.content.trim().is_empty());
+        self
+    }
+
+    /// 交给 Agent 的文本：回复某条消息时在前面注明被引用的内容
+    pub fn content_with_quote(&self) -> String {
+        let Some(ref quote) = self.reply_to else {
+            return self.content.clone();
+        };
+        let mut quoted: String = quote.content.trim().chars().take(QUOTE_MAX_CHARS).collect();
+        if quote.content.trim().chars().count() > QUOTE_MAX_CHARS {
+            quoted.push('…');
+        }
+        let whose = if quote.from_bot { "你之前的消息" } else { "一条消息" };
+        format!("[用户回复了{}：「{}」]\n{}", whose, quoted, self.content)
+    }
+
+    /// 会话 ID（通道:聊天 ID），同一聊天重启后仍使用同一会话
+    pub fn session_key(&self) -> String {
+        format!("{}:{}", self.channel, self.chat_id)
+    }
+}
+
+/// 与平台无关的会话命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelCommand {
+    /// 清空对话上下文
+    Clear,
+    /// 查看状态
+    Status,
+    /// 置顶内容（None 表示置顶上一条回复）
+    Pin(Option<String>),
+    /// 按序号（从 1 开始）取消置顶
+    Unpin(usize),
+    /// 查看置顶内容
+    Pins,
+    /// 设置会话指令（空字符串表示清除）
+    Instruct(String),
+    /// 取消正在进行的回复
+    Cancel,
+    /// 开启/关闭无痕模式（None 表示切换）
+    Incognito(Option<bool>),
+    /// 开启/关闭回复后的执行详情（None 表示切换）
+    Verbose(Option<bool>),
+    /// 查看或切换角色（None 表示列出可用角色，"default" 恢复默认）
+    Persona(Option<String>),
+    /// 对问题进行深度调研
+    Research(String),
+    /// 多次采样后选出最终回答（次数、问题）
+    BestOf(usize, String),
+    /// 查看或设置会话标题（None 表示查看）
+    Title(Option<String>),
+    /// 载入某天的日记和对话后提问（日期和问题都可省略）
+    OnThisDay(String),
+    // 以下为管理员命令，只有管理员可用（见 [`AgentHandler::with_admins`]）
+    /// 查看或切换模型（None 表示查看，空字符串恢复默认）
+    Model(Option<String>),
+    /// 查看或切换提供商（None 表示列出可用提供商）
+    Provider(Option<String>),
+    /// 列出最近的会话
+    Sessions,
+    /// 查看令牌用量
+    Usage,
+    /// 列出定时任务（true 时包括已完成的一次性任务）
+    Jobs(bool),
+    /// 管理定时任务（操作、任务 ID 或 ID 前缀）
+    Job(JobAction, String),
+    /// 查看、批准或拒绝待审核的记忆
+    Memories(ReviewAction),
+}
+
+/// 定时任务操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobAction {
+    Pause,
+    Resume,
+    Delete,
+}
+
+impl ChannelCommand {
+    /// 是否读写消息所在会话的上下文（需要在该会话中执行，并等同一会话进行中的回复结束）
+    ///
+    /// 取消、调研不使用上下文；多次采样和回顾某天自行作为普通消息回复
+    fn uses_session(&self) -> bool {
+        !matches!(
+            self,
+            ChannelCommand::Cancel
+                | ChannelCommand::Research(_)
+                | ChannelCommand::BestOf(..)
+                | ChannelCommand::OnThisDay(_)
+        )
+    }
+
+    /// 是否为只有管理员可用的命令
+    fn admin_only(&self) -> bool {
+        matches!(
+            self,
+            ChannelCommand::Model(_)
+                | ChannelCommand::Provider(_)
+                | ChannelCommand::Sessions
+                | ChannelCommand::Usage
+                | ChannelCommand::Jobs(_)
+                | ChannelCommand::Job(..)
+                | ChannelCommand::Memories(_)
+        )
+    }
+
+    /// 解析 /incognito [on|off]（不支持原生命令的通道以文本形式发送）
+    pub fn parse_incognito(text: &str) -> Option<Self> {
+        parse_switch(text, "/incognito").map(ChannelCommand::Incognito)
+    }
+
+    /// 解析 /verbose [on|off]
+    pub fn parse_verbose(text: &str) -> Option<Self> {
+        parse_switch(text, "/verbose").map(ChannelCommand::Verbose)
+    }
+
+    /// 解析 /research <问题>
+    pub fn parse_research(text: &str) -> Option<Self> {
+        let text = text.trim_start();
+        let (cmd, question) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let cmd = cmd.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/research") {
+            return None;
+        }
+        Some(ChannelCommand::Research(question.trim().to_string()))
+    }
+
+    /// 解析 /bestof [次数] <问题>
+    pub fn parse_best_of(text: &str) -> Option<Self> {
+        let text = text.trim_start();
+        let (cmd, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let cmd = cmd.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/bestof") {
+            return None;
+        }
+        let rest = rest.trim();
+        let (first, question) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        Some(match first.parse::<usize>() {
+            Ok(n) => ChannelCommand::BestOf(n, question.trim().to_string()),
+            Err(_) => ChannelCommand::BestOf(DEFAULT_BEST_OF, rest.to_string()),
+        })
+    }
+
+    /// 解析 /jobs [--all]
+    pub fn parse_jobs(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let cmd = parts.next()?.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/jobs") {
+            return None;
+        }
+        match parts.next() {
+            None => Some(ChannelCommand::Jobs(false)),
+            Some("--all" | "all") => Some(ChannelCommand::Jobs(true)),
+            Some(_) => None,
+        }
+    }
+
+    /// 解析 /job <pause|resume|delete> <任务 ID>
+    pub fn parse_job(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let cmd = parts.next()?.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/job") {
+            return None;
+        }
+        let action = match parts.next()?.to_lowercase().as_str() {
+            "pause" | "暂停" => JobAction::Pause,
+            "resume" | "恢复" => JobAction::Resume,
+            "delete" | "rm" | "删除" => JobAction::Delete,
+            _ => return None,
+        };
+        let id = parts.next()?;
+        Some(ChannelCommand::Job(action, id.to_string()))
+    }
+
+    /// 解析 /memories [pending|approve <序号...|all>|reject <序号...|all>]
+    pub fn parse_memories(text: &str) -> Option<Self> {
+        let text = text.trim_start();
+        let (cmd, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let cmd = cmd.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/memories") {
+            return None;
+        }
+        ReviewAction::parse(args).map(ChannelCommand::Memories)
+    }
+
+    /// 解析 /onthisday [日期] [问题]
+    pub fn parse_on_this_day(text: &str) -> Option<Self> {
+        let text = text.trim_start();
+        let (cmd, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let cmd = cmd.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/onthisday") {
+            return None;
+        }
+        Some(ChannelCommand::OnThisDay(args.trim().to_string()))
+    }
+
+    /// 解析 /title [标题]
+    pub fn parse_title(text: &str) -> Option<Self> {
+        let text = text.trim_start();
+        let (cmd, title) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let cmd = cmd.split('@').next().unwrap_or_default();
+        if !cmd.eq_ignore_ascii_case("/title") {
+            return None;
+        }
+        let title = title.trim();
+        Some(ChannelCommand::Title((!title.is_empty()).then(|| title.to_string())))
+    }
+}
+
+/// 解析开关命令的参数：on/off 或省略（表示切换），不是该命令或参数无效时返回 None
+fn parse_switch(text: &str, command: &str) -> Option<Option<bool>> {
+    let mut parts = text.split_whitespace();
+    let cmd = parts.next()?.split('@').next().unwrap_or_default();
+    if !cmd.eq_ignore_ascii_case(command) {
+        return None;
+    }
+    match parts.next().map(str::to_lowercase).as_deref() {
+        None => Some(None),
+        Some("on" | "开") => Some(Some(true)),
+        Some("off" | "关") => Some(Some(false)),
+        Some(_) => None,
+    }
+}
+
+/// 执行详情：工具调用及耗时、令牌数和模型
+///
+/// 例如 `🔧 shell 0.4s ✓, web_search 1.2s ✓ · 2,340 令牌 · deepseek-chat`
+fn trace_line(response: &AgentResponse) -> String {
+    let mut parts = Vec::new();
+    if !response.tool_trace.is_empty() {
+        let tools: Vec<String> = response
+            .tool_trace
+            .iter()
+            .map(|t| format!("{} {:.1}s {}", t.name, t.duration.as_secs_f64(), if t.success { "✓" } else { "✗" }))
+            .collect();
+        parts.push(format!("🔧 {}", tools.join(", ")));
+    }
+    parts.push(t!("verbose.tokens", tokens = group_digits(response.tokens)));
+    parts.push(response.model.clone());
+    parts.join(" · ")
+}
+
+/// 千位分隔（2340 → 2,340）
+fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// 是否为取消请求（stop、/cancel 等）
+pub fn is_cancel_request(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    // Telegram 群组中的命令可能带 @bot 后缀
+    let text = text.split('@').next().unwrap_or_default();
+    matches!(text, "stop" | "/stop" | "/cancel" | "取消" | "停止")
+}
+
+/// 入站消息处理器
+#[async_trait]
+pub trait MessageHandler: Send + Sync {
+    /// 处理消息，返回回复内容（为空时不回复）
+    async fn handle(&self, msg: InboundMessage) -> Result<String>;
+
+    /// 处理会话命令，返回回复内容（纯文本）
+    async fn command(&self, msg: &InboundMessage, cmd: ChannelCommand) -> Result<String> {
+        let _ = (msg, cmd);
+        Ok(t!("cmd.unsupported"))
+    }
+
+    /// 处理用户编辑过的消息（latest 表示编辑的是该聊天中最后一条消息），返回回复内容
+    async fn handle_edit(&self, msg: InboundMessage, latest: bool) -> Result<String> {
+        let _ = (msg, latest);
+        Ok(String::new())
+    }
+
+    /// 通道中有过会话的聊天 ID（用于广播）
+    async fn known_chats(&self, channel: &str) -> Result<Vec<String>> {
+        let _ = channel;
+        Ok(Vec::new())
+    }
+}
+
+/// 闭包作为处理器（只处理消息，不支持命令）
+#[async_trait]
+impl<F, Fut> MessageHandler for F
+where
+    F: Fn(InboundMessage) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String>> + Send,
+{
+    async fn handle(&self, msg: InboundMessage) -> Result<String> {
+        self(msg).await
+    }
+}
+
+/// 将消息交给 Agent 处理，每个聊天使用独立会话
+pub struct AgentHandler {
+    agent: Arc<Agent>,
+    /// 设置后提供商限流等待时发布 [`ProviderBusyEvent`]，工具报告进度时发布 [`ToolProgressEvent`]
+    event_bus: Option<Arc<EventBus>>,
+    /// 各会话进行中回复的取消令牌
+    in_flight: std::sync::Mutex<HashMap<String, CancellationToken>>,
+    /// 编辑最后一条消息时是否重新生成回复
+    regenerate_on_edit: bool,
+    /// 各会话中尚未告知 Agent 的消息修改，随下一条消息发送
+    edit_notes: std::sync::Mutex<HashMap<String, Vec<String>>>,
+    /// 用户消息和回复的内容过滤
+    content_filter: Option<Arc<ContentFilter>>,
+    /// 回复发出前的后处理
+    postprocessor: Option<Arc<PostProcessor>>,
+    /// 开启了执行详情（/verbose）的会话，只保存在内存中
+    verbose: std::sync::Mutex<HashSet<String>>,
+    /// 各会话的回复锁：同一会话的消息和会话命令按到达顺序逐条处理，不会交错修改上下文；不同会话互不等待
+    turns: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// 各通道合并连发消息的等待时间
+    merge_windows: HashMap<String, Duration>,
+    /// 各会话在合并窗口内等待的消息
+    pending: std::sync::Mutex<HashMap<String, PendingMessages>>,
+    /// 合并窗口内消息的序号
+    pending_seq: AtomicU64,
+    /// 各通道的唤醒词
+    wake_words: WakeWords,
+    /// 可使用管理命令的用户（通道:用户 ID）
+    admins: HashSet<String>,
+}
+
+/// 合并窗口内等待的消息
+#[derive(Default)]
+struct PendingMessages {
+    messages: Vec<InboundMessage>,
+    /// 最后一条消息的序号，由它负责合并处理
+    latest: u64,
+}
+
+/// 把连发的多条消息合并为一条（引用内容随各自的消息保留）
+fn merge_messages(mut messages: Vec<InboundMessage>) -> Option<InboundMessage> {
+    if messages.len() <= 1 {
+        return messages.pop();
+    }
+    let content = messages
+        .iter()
+        .map(InboundMessage::content_with_quote)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut merged = messages.pop()?;
+    merged.content = content;
+    merged.reply_to = None;
+    Some(merged)
+}
+
+impl AgentHandler {
+    pub fn new(agent: Arc<Agent>) -> Self {
+        Self {
+            agent,
+            event_bus: None,
+            in_flight: std::sync::Mutex::new(HashMap::new()),
+            regenerate_on_edit: true,
+            edit_notes: std::sync::Mutex::new(HashMap::new()),
+            content_filter: None,
+            postprocessor: None,
+            verbose: std::sync::Mutex::new(HashSet::new()),
+            turns: std::sync::Mutex::new(HashMap::new()),
+            merge_windows: HashMap::new(),
+            pending: std::sync::Mutex::new(HashMap::new()),
+            pending_seq: AtomicU64::new(0),
+            wake_words: WakeWords::default(),
+            admins: HashSet::new(),
+        }
+    }
+
+    /// 设置唤醒词（启用的通道中没有唤醒词的消息不交给 Agent）
+    pub fn with_wake_words(mut self, wake_words: WakeWords) -> Self {
+        self.wake_words = wake_words;
+        self
+    }
+
+    /// 设置可使用管理命令的用户（通道:用户 ID），未设置时所有通道都不能使用管理命令
+    pub fn with_admins(mut self, admins: HashSet<String>) -> Self {
+        self.admins = admins;
+        self
+    }
+
+    /// 消息发送者是否为管理员
+    fn is_admin(&self, msg: &InboundMessage) -> bool {
+        self.admins.contains(&format!("{}:{}", msg.channel, msg.sender))
+    }
+
+    /// 设置通道合并连发消息的等待时间（为 0 时不合并）
+    pub fn with_merge_window(mut self, channel: &str, window: Duration) -> Self {
+        if window.is_zero() {
+            self.merge_windows.remove(channel);
+        } else {
+            self.merge_windows.insert(channel.to_string(), window);
+        }
+        self
+    }
+
+    /// 设置编辑最后一条消息时是否重新生成回复
+    pub fn with_regenerate_on_edit(mut self, enabled: bool) -> Self {
+        self.regenerate_on_edit = enabled;
+        self
+    }
+
+    /// 设置事件总线
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// 设置内容过滤器
+    pub fn with_content_filter(mut self, filter: Option<Arc<ContentFilter>>) -> Self {
+        self.content_filter = filter;
+        self
+    }
+
+    /// 设置回复后处理流水线
+    pub fn with_postprocessor(mut self, postprocessor: Arc<PostProcessor>) -> Self {
+        self.postprocessor = Some(postprocessor);
+        self
+    }
+
+    /// 对回复执行后处理，再按内容过滤规则检查
+    async fn finish_reply(&self, msg: &InboundMessage, reply: String) -> String {
+        let reply = match self.postprocessor {
+            Some(ref p) => p.process(&msg.channel, reply),
+            None => reply,
+        };
+        self.filter_content(msg, FilterDirection::Outbound, reply)
+            .await
+            .unwrap_or_else(|| self.blocked_reply())
+    }
+
+    /// 等待合并窗口结束：窗口内同一聊天又有新消息时返回 None，由最后一条消息合并处理
+    async fn debounce(&self, msg: InboundMessage) -> Option<InboundMessage> {
+        let Some(&window) = self.merge_windows.get(&msg.channel) else {
+            return Some(msg);
+        };
+        let session_key = msg.session_key();
+        let seq = self.pending_seq.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut pending = self.pending.lock().unwrap();
+            let entry = pending.entry(session_key.clone()).or_default();
+            entry.messages.push(msg);
+            entry.latest = seq;
+        }
+
+        tokio::time::sleep(window).await;
+        let mut pending = self.pending.lock().unwrap();
+        if pending.get(&session_key).is_none_or(|p| p.latest != seq) {
+            return None;
+        }
+        let messages = pending.remove(&session_key)?.messages;
+        if messages.len() > 1 {
+            debug!("合并 {} 条连发消息: {}", messages.len(), session_key);
+        }
+        merge_messages(messages)
+    }
+
+    /// 处理普通消息：同一会话的消息排队逐条处理，不同会话的回复同时进行
+    async fn reply(&self, msg: InboundMessage, best_of: Option<usize>) -> Result<String> {
+        let session_key = msg.session_key();
+        self.in_turn(&session_key, self.reply_in_turn(msg, best_of)).await
+    }
+
+    /// 持有会话的回复锁，在该会话的上下文中执行 `fut`
+    async fn in_turn<F: Future>(&self, session_key: &str, fut: F) -> F::Output {
+        let lock = self
+            .turns
+            .lock()
+            .unwrap()
+            .entry(session_key.to_string())
+            .or_default()
+            .clone();
+        let result = {
+            let _session_turn = lock.lock().await;
+            // 回复和命令的 future 很大，装箱后在各层之间移动时不占用栈空间
+            self.agent.in_session(session_key, Box::pin(fut)).await
+        };
+
+        // 没有排队的消息时移除回复锁（只剩映射表和这里的引用）
+        let mut turns = self.turns.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            turns.remove(session_key);
+        }
+        result
+    }
+
+    /// 过滤后交给 Agent，回复经后处理和过滤后返回
+    async fn reply_in_turn(&self, mut msg: InboundMessage, best_of: Option<usize>) -> Result<String> {
+        let session_key = msg.session_key();
+        match self.filter_content(&msg, FilterDirection::Inbound, msg.content.clone()).await {
+            Some(content) => msg.content = content,
+            None => return Ok(self.blocked_reply()),
+        }
+
+        if let Err(e) = self.agent.start_onboarding_if_new(&session_key).await {
+            warn!("新用户检测失败: {}", e);
+        }
+        let token = CancellationToken::new();
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(session_key.clone(), token.clone());
+
+        let notifiers = self.event_bus.clone().map(|bus| {
+            (Self::busy_notifier(bus.clone(), &msg), Self::progress_notifier(bus, &msg))
+        });
+        let mut content = msg.content_with_quote();
+        if let Some(notes) = self.edit_notes.lock().unwrap().remove(&session_key) {
+            let notes: Vec<String> = notes
+                .iter()
+                .map(|n| format!("[用户把之前的一条消息修改为：「{}」]", n))
+                .collect();
+            content = format!("{}\n{}", notes.join("\n"), content);
+        }
+        let options = ChatOptions::default()
+            .with_origin(msg.channel.clone(), msg.chat_id.clone())
+            .with_cancel_token(token);
+        let options = match best_of {
+            Some(n) => options.with_best_of(n),
+            None => options,
+        };
+        let observed = self.observed_bus().await;
+        if let Some(ref bus) = observed {
+            Self::publish_message(bus, &session_key, "user", &msg.content);
+        }
+        let chat = self.agent.chat_with_options(content, options);
+        let result = match notifiers {
+            Some((busy, progress)) => with_busy_notifier(busy, with_progress_notifier(progress, chat)).await,
+            None => chat.await,
+        };
+        self.in_flight.lock().unwrap().remove(&session_key);
+
+        match result {
+            Ok(response) => {
+                let trace = self.verbose.lock().unwrap().contains(&session_key).then(|| trace_line(&response));
+                let reply = self.finish_reply(&msg, response.content.clone()).await;
+                if let Some(ref bus) = observed {
+                    Self::publish_turn(bus, &session_key, &response, &reply);
+                }
+                Ok(match trace {
+                    Some(trace) if !reply.is_empty() => format!("{}\n\n{}", reply, trace),
+                    _ => reply,
+                })
+            }
+            Err(e) if e.downcast_ref::<TurnAborted>() == Some(&TurnAborted::Cancelled) => {
+                Ok(t!("cmd.turn_cancelled"))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 按内容过滤规则检查文本，返回处理后的文本，被拦截时返回 None
+    ///
+    /// 命中时记录审计日志并发布 [`ContentFilteredEvent`]；模型分类失败时放行
+    async fn filter_content(&self, msg: &InboundMessage, direction: FilterDirection, text: String) -> Option<String> {
+        let Some(ref filter) = self.content_filter else {
+            return Some(text);
+        };
+        if text.trim().is_empty() || !filter.applies_to(&msg.channel, direction) {
+            return Some(text);
+        }
+
+        let mut verdict = filter.check(&text);
+        if let Some(model) = filter.classifier_model() {
+            if verdict.as_ref().is_none_or(|v| v.action != FilterAction::Block) {
+                match self.agent.classify_content(&text, model, Some(&msg.session_key())).await {
+                    Ok(Some(reason)) => {
+                        let classified = filter.classified(&text, reason);
+                        if verdict.as_ref().is_none_or(|v| classified.action > v.action) {
+                            verdict = Some(classified);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("内容分类失败，放行消息: {}", e),
+                }
+            }
+        }
+        let Some(verdict) = verdict else {
+            return Some(text);
+        };
+
+        warn!(
+            "内容过滤 {} {}:{} {}（{}）",
+            direction.as_str(),
+            msg.channel,
+            msg.chat_id,
+            verdict.action.as_str(),
+            verdict.reason
+        );
+        // 摘录取打码后的文本，避免规则要遮盖的内容写进审计日志；无痕会话不记录摘录
+        let incognito = self.agent.is_incognito_session(&msg.session_key());
+        let event = ContentFilteredEvent {
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            sender: msg.sender.clone(),
+            direction: direction.as_str().to_string(),
+            action: verdict.action.as_str().to_string(),
+            reason: verdict.reason,
+            excerpt: (!incognito).then(|| excerpt(&verdict.text)),
+            timestamp: chrono::Utc::now(),
+        };
+        filter.audit(&event).await;
+        if let Some(ref bus) = self.event_bus {
+            let _ = bus.publish(event);
+        }
+
+        match verdict.action {
+            FilterAction::Block => None,
+            FilterAction::Redact => Some(verdict.text),
+            FilterAction::Flag => Some(text),
+        }
+    }
+
+    /// 需要发布对话事件时返回事件总线（无痕模式下不发布对话内容）
+    async fn observed_bus(&self) -> Option<Arc<EventBus>> {
+        match self.event_bus {
+            Some(ref bus) if !self.agent.is_incognito().await => Some(bus.clone()),
+            _ => None,
+        }
+    }
+
+    fn publish_message(bus: &EventBus, session_id: &str, role: &str, content: &str) {
+        let _ = bus.publish(AgentMessageEvent {
+            session_id: session_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+        });
+    }
+
+    /// 发布本轮的工具调用和最终回复（API 的 `/events` 等订阅者实时观察对话）
+    fn publish_turn(bus: &EventBus, session_id: &str, response: &AgentResponse, reply: &str) {
+        for trace in &response.tool_trace {
+            let _ = bus.publish(ToolCallEvent {
+                session_id: session_id.to_string(),
+                tool_name: trace.name.clone(),
+                args: trace.arguments.clone(),
+                result: Some(trace.output.clone()),
+                success: trace.success,
+                timestamp: chrono::Utc::now(),
+            });
+        }
+        Self::publish_message(bus, session_id, "assistant", reply);
+    }
+
+    /// 限流等待时发布事件，由 [`BusyNoticeHandler`] 推送到消息所在聊天
+    fn busy_notifier(bus: Arc<EventBus>, msg: &InboundMessage) -> BusyNotifier {
+        let (channel, chat_id) = (msg.channel.clone(), msg.chat_id.clone());
+        Arc::new(move |provider: &str, wait: std::time::Duration, attempt: u32| {
+            let _ = bus.publish(ProviderBusyEvent {
+                provider: provider.to_string(),
+                retry_after_secs: wait.as_secs_f64().ceil() as u64,
+                attempt,
+                channel: channel.clone(),
+                chat_id: chat_id.clone(),
+                timestamp: chrono::Utc::now(),
+            });
+        })
+    }
+
+    /// 工具报告进度时发布事件，由 [`ProgressNoticeHandler`] 推送到消息所在聊天
+    fn progress_notifier(bus: Arc<EventBus>, msg: &InboundMessage) -> ProgressNotifier {
+        let (channel, chat_id) = (msg.channel.clone(), msg.chat_id.clone());
+        Arc::new(move |tool: &str, message: &str| {
+            let _ = bus.publish(ToolProgressEvent {
+                tool_name: tool.to_string(),
+                message: message.to_string(),
+                channel: channel.clone(),
+                chat_id: chat_id.clone(),
+                timestamp: chrono::Utc::now(),
+            });
+        })
+    }
+
+    /// 消息被拦截时的回复
+    fn blocked_reply(&self) -> String {
+        self.content_filter
+            .as_ref()
+            .map(|f| f.block_message().to_string())
+            .unwrap_or_default()
+    }
+
+    /// 取消会话中进行的回复，返回是否有回复被取消
+    fn cancel(&self, session_key: &str) -> bool {
+        match self.in_flight.lock().unwrap().remove(session_key) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 最近会话列表，首行为标题，每行一个会话
+    async fn list_sessions(&self) -> Result<String> {
+        let Some(sessions) = self.agent.sessions() else {
+            return Ok(t!("cmd.sessions_disabled"));
+        };
+        let list = sessions.list_sessions(SESSION_LIST_LIMIT).await?;
+        if list.is_empty() {
+            return Ok(t!("cmd.sessions_empty"));
+        }
+        let lines: Vec<String> = list
+            .iter()
+            .map(|s| {
+                let line = t!(
+                    "cmd.sessions_line",
+                    id = s.id,
+                    messages = s.stats.message_count,
+                    tokens = s.stats.total_tokens,
+                    time = s.last_activity.format("%m-%d %H:%M")
+                );
+                match s.title() {
+                    Some(title) => line + &t!("cmd.sessions_title", title = title),
+                    None => line,
+                }
+            })
+            .collect();
+        Ok(format!("{}\n{}", t!("cmd.sessions_header", count = list.len()), lines.join("\n")))
+    }
+
+    /// 总用量和当前会话用量
+    async fn usage(&self) -> Result<String> {
+        let Some(sessions) = self.agent.sessions() else {
+            return Ok(t!("cmd.sessions_disabled"));
+        };
+        let (count, total) = sessions.stored_stats().await?;
+        let mut reply = t!(
+            "cmd.usage",
+            sessions = count,
+            messages = total.message_count,
+            tool_calls = total.tool_call_count,
+            tokens = total.total_tokens
+        );
+        if !total.persona_tokens.is_empty() {
+            let mut personas: Vec<_> = total.persona_tokens.iter().collect();
+            personas.sort();
+            reply.push_str(&t!("cmd.usage_personas"));
+            for (persona, tokens) in personas {
+                reply.push_str(&t!("cmd.usage_persona_line", persona = persona, tokens = tokens));
+            }
+        }
+        if let Some(stats) = self.agent.session_stats().await {
+            reply.push_str(&t!(
+                "cmd.usage_session",
+                messages = stats.message_count,
+                tool_calls = stats.tool_call_count,
+                tokens = stats.total_tokens
+            ));
+        }
+        Ok(reply)
+    }
+
+    /// 可用角色列表，标出当前会话的角色
+    async fn list_personas(&self) -> String {
+        let personas = self.agent.personas();
+        if personas.is_empty() {
+            return t!("cmd.personas_none");
+        }
+        let current = self.agent.persona().await;
+        let mut lines = vec![t!(
+            "cmd.personas_current",
+            name = current.as_deref().unwrap_or(crate::agent::DEFAULT_PERSONA)
+        )];
+        for (name, description) in personas {
+            let marker = if current.as_deref() == Some(name.as_str()) { "▶" } else { "•" };
+            match description {
+                Some(d) => lines.push(format!("{} {} - {}", marker, name, d)),
+                None => lines.push(format!("{} {}", marker, name)),
+            }
+        }
+        lines.push(t!("cmd.personas_hint"));
+        lines.join("\n")
+    }
+
+    /// 暂停、恢复或删除定时任务
+    async fn control_job(&self, action: JobAction, id: &str) -> String {
+        let Some(scheduler) = self.agent.scheduler() else {
+            return t!("cmd.jobs_empty");
+        };
+        let job = match scheduler.find_job(id).await {
+            Ok(job) => job,
+            Err(e) => return t!("cmd.job_failed", error = e),
+        };
+        let result = match action {
+            JobAction::Pause => scheduler.pause_job(&job.id).await,
+            JobAction::Resume => scheduler.resume_job(&job.id).await,
+            JobAction::Delete => scheduler.remove_job(&job.id).await,
+        };
+        match (result, action) {
+            (Ok(()), JobAction::Pause) => t!("cmd.job_paused", name = job.name),
+            (Ok(()), JobAction::Resume) => t!("cmd.job_resumed", name = job.name),
+            (Ok(()), JobAction::Delete) => t!("cmd.job_deleted", name = job.name),
+            (Err(e), _) => t!("cmd.job_failed", error = e),
+        }
+    }
+
+    /// 处理待审核的记忆
+    async fn review_memories(&self, action: ReviewAction) -> String {
+        let Some(memory) = self.agent.memory() else {
+            return t!("memory_review.empty");
+        };
+        let keys = |memories: &[PendingMemory]| {
+            memories.iter().map(|m| m.key.as_str()).collect::<Vec<_>>().join("、")
+        };
+        match action {
+            ReviewAction::Pending => match memory.pending_memories().await {
+                Ok(pending) => render_pending(&pending),
+                Err(e) => t!("cmd.job_failed", error = e),
+            },
+            ReviewAction::Approve(ids) => match memory.approve_pending(ids.as_deref()).await {
+                Ok(approved) => t!("memory_review.approved", count = approved.len(), keys = keys(&approved)),
+                Err(e) => t!("cmd.job_failed", error = e),
+            },
+            ReviewAction::Reject(ids) => match memory.reject_pending(ids.as_deref()).await {
+                Ok(rejected) => t!("memory_review.rejected", count = rejected.len(), keys = keys(&rejected)),
+                Err(e) => t!("cmd.job_failed", error = e),
+            },
+        }
+    }
+
+    /// 定时任务列表，首行为标题，每行一个任务
+    async fn list_jobs(&self, all: bool) -> String {
+        let jobs = self.agent.jobs(all).await;
+        if jobs.is_empty() {
+            return t!("cmd.jobs_empty");
+        }
+        let lines: Vec<String> = jobs
+            .iter()
+            .map(|job| {
+                let next_run = job
+                    .next_run
+                    .map(|t| t.format("%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                t!(
+                    "cmd.jobs_line",
+                    id = &job.id[..8.min(job.id.len())],
+                    name = job.name,
+                    status = format!("{:?}", job.status),
+                    next = next_run
+                )
+            })
+            .collect();
+        format!("{}\n{}", t!("cmd.jobs_header", count = jobs.len()), lines.join("\n"))
+    }
+
+    /// 执行通道命令（调用者负责进入会话）
+    async fn run_command(&self, msg: &InboundMessage, cmd: ChannelCommand) -> Result<String> {
+        let reply = match cmd {
+            // 被取消的回复会自行回复"已取消"
+            ChannelCommand::Cancel if self.cancel(&msg.session_key()) => String::new(),
+            ChannelCommand::Cancel => t!("cmd.cancel_none"),
+            ChannelCommand::Clear => {
+                self.agent.clear_context().await;
+                t!("cmd.cleared")
+            }
+            ChannelCommand::Status => {
+                let mut status = t!(
+                    "cmd.status",
+                    session = self.agent.session_id().await,
+                    context = self.agent.context_length().await,
+                    model = self.agent.model_name(),
+                    incognito = if self.agent.is_incognito().await { t!("common.on") } else { t!("common.off") },
+                );
+                let degraded: Vec<String> = self
+                    .agent
+                    .degraded_stores()
+                    .await
+                    .into_iter()
+                    .map(|store| match store {
+                        "jobs" => t!("status.store_jobs"),
+                        _ => t!("status.store_sessions"),
+                    })
+                    .collect();
+                if !degraded.is_empty() {
+                    status.push_str(&t!("cmd.status_degraded", stores = degraded.join(", ")));
+                }
+                status
+            }
+            ChannelCommand::Pin(text) => match self.agent.pin(text.as_deref()).await {
+                Ok(count) => t!("cmd.pinned", count = count),
+                Err(e) => t!("cmd.pin_failed", error = e),
+            },
+            ChannelCommand::Unpin(index) => match self.agent.unpin(index).await {
+                Ok(Some(removed)) => t!("cmd.unpinned", content = removed),
+                Ok(None) => t!("cmd.unpin_missing", index = index),
+                Err(e) => t!("cmd.unpin_failed", error = e),
+            },
+            ChannelCommand::Pins => {
+                let pins = self.agent.pins().await;
+                if pins.is_empty() {
+                    t!("cmd.pins_empty")
+                } else {
+                    pins.iter()
+                        .enumerate()
+                        .map(|(i, p)| format!("{}. {}", i + 1, p))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            }
+            ChannelCommand::Instruct(text) => {
+                let text = text.trim();
+                match self.agent.set_instructions(Some(text)).await {
+                    Ok(()) if text.is_empty() => t!("cmd.instructions_cleared"),
+                    Ok(()) => t!("cmd.instructions_set", text = text),
+                    Err(e) => t!("cmd.set_failed", error = e),
+                }
+            }
+            ChannelCommand::Incognito(mode) => {
+                let enabled = mode.unwrap_or(!self.agent.is_incognito().await);
+                self.agent.set_incognito(enabled).await;
+                if enabled {
+                    t!("cmd.incognito_on")
+                } else {
+                    t!("cmd.incognito_off")
+                }
+            }
+            ChannelCommand::Verbose(mode) => {
+                let session_key = msg.session_key();
+                let mut verbose = self.verbose.lock().unwrap();
+                if mode.unwrap_or(!verbose.contains(&session_key)) {
+                    verbose.insert(session_key);
+                    t!("cmd.verbose_on")
+                } else {
+                    verbose.remove(&session_key);
+                    t!("cmd.verbose_off")
+                }
+            }
+            ChannelCommand::Persona(None) => self.list_personas().await,
+            ChannelCommand::Persona(Some(name)) => match self.agent.set_persona(Some(&name)).await {
+                Ok(()) => match self.agent.persona().await {
+                    Some(name) => t!("cmd.persona_switched", name = name),
+                    None => t!("cmd.persona_default"),
+                },
+                Err(e) => t!("cmd.switch_failed", error = e),
+            },
+            ChannelCommand::Research(question) if question.trim().is_empty() => t!("cmd.research_usage"),
+            ChannelCommand::BestOf(_, question) if question.trim().is_empty() => t!("cmd.bestof_usage"),
+            ChannelCommand::BestOf(n, question) => {
+                let mut msg = msg.clone();
+                msg.content = question;
+                self.reply(msg, Some(n)).await?
+            }
+            ChannelCommand::Research(question) => {
+                let session_key = msg.session_key();
+                let research = self.agent.research(&question, Some(&session_key));
+                let result = match self.event_bus.clone() {
+                    Some(bus) => with_progress_notifier(Self::progress_notifier(bus, msg), research).await,
+                    None => research.await,
+                };
+                match result {
+                    Ok(report) => self.finish_reply(msg, report.report).await,
+                    Err(e) => t!("cmd.research_failed", error = e),
+                }
+            }
+            ChannelCommand::OnThisDay(args) => {
+                let (date, question) = split_day(&args, chrono::Local::now().date_naive());
+                match self.agent.recall_day(date, &msg.session_key()).await {
+                    Ok(Some(recall)) if !recall.is_empty() => {
+                        // 素材随问题作为用户消息发送，后续追问时仍在上下文中
+                        let question = if question.is_empty() { t!("onthisday.question") } else { question };
+                        let mut msg = msg.clone();
+                        msg.content = format!("{}\n\n{}", recall.render(), question);
+                        self.reply(msg, None).await?
+                    }
+                    Ok(Some(_)) => t!("onthisday.empty", date = date),
+                    Ok(None) => t!("onthisday.unavailable"),
+                    Err(e) => t!("onthisday.failed", error = e),
+                }
+            }
+            ChannelCommand::Title(None) => match self.agent.title().await {
+                Some(title) => t!("cmd.title_current", title = title),
+                None => t!("cmd.title_none"),
+            },
+            ChannelCommand::Title(Some(title)) => match self.agent.set_title(Some(&title)).await {
+                Ok(()) => t!("cmd.title_set", title = self.agent.title().await.unwrap_or(title)),
+                Err(e) => t!("cmd.set_failed", error = e),
+            },
+            ChannelCommand::Model(None) => t!("cmd.model_current", model = self.agent.model_name()),
+            ChannelCommand::Model(Some(spec)) => match self.agent.set_model(Some(&spec)) {
+                Ok(model) if spec.trim().is_empty() => t!("cmd.model_default", model = model),
+                Ok(model) => t!("cmd.model_switched", model = model),
+                Err(e) => t!("cmd.switch_failed", error = e),
+            },
+            ChannelCommand::Provider(None) => t!(
+                "cmd.providers",
+                providers = self.agent.providers().join(", "),
+                model = self.agent.model_name()
+            ),
+            ChannelCommand::Provider(Some(name)) => match self.agent.set_provider(name.trim()) {
+                Ok(model) => t!("cmd.model_switched", model = model),
+                Err(e) => t!("cmd.switch_failed", error = e),
+            },
+            ChannelCommand::Sessions => self.list_sessions().await?,
+            ChannelCommand::Usage => self.usage().await?,
+            ChannelCommand::Jobs(all) => self.list_jobs(all).await,
+            ChannelCommand::Job(action, id) => self.control_job(action, &id).await,
+            ChannelCommand::Memories(action) => self.review_memories(action).await,
+        };
+        Ok(reply)
+    }
+}
+
+#[async_trait]
+impl MessageHandler for AgentHandler {
+    async fn handle(&self, mut msg: InboundMessage) -> Result<String> {
+        match self.wake_words.check(&msg) {
+            WakeCheck::Pass => {}
+            WakeCheck::Wake(text) => msg.content = text,
+            WakeCheck::Stop => return self.command(&msg, ChannelCommand::Cancel).await,
+            WakeCheck::Ignore => return Ok(String::new()),
+        }
+        if is_cancel_request(&msg.content) {
+            return self.command(&msg, ChannelCommand::Cancel).await;
+        }
+        if let Some(cmd) = ChannelCommand::parse_incognito(&msg.content)
+            .or_else(|| ChannelCommand::parse_verbose(&msg.content))
+            .or_else(|| ChannelCommand::parse_research(&msg.content))
+            .or_else(|| ChannelCommand::parse_title(&msg.content))
+            .or_else(|| ChannelCommand::parse_on_this_day(&msg.content))
+            .or_else(|| ChannelCommand::parse_best_of(&msg.content))
+            .or_else(|| ChannelCommand::parse_jobs(&msg.content))
+            .or_else(|| ChannelCommand::parse_job(&msg.content))
+        {
+            return self.command(&msg, cmd).await;
+        }
+        match self.debounce(msg).await {
+            Some(msg) => self.reply(msg, None).await,
+            None => Ok(String::new()),
+        }
+    }
+
+    async fn command(&self, msg: &InboundMessage, cmd: ChannelCommand) -> Result<String> {
+        if cmd.admin_only() && !self.is_admin(msg) {
+            warn!("非管理员用户 {}:{} 尝试使用管理命令", msg.channel, msg.sender);
+            return Ok(t!("cmd.admin_only"));
+        }
+        // 使用会话的命令等同一会话进行中的回复结束后执行；取消等命令不等待，以免被进行中的回复阻塞
+        match cmd.uses_session() {
+            true => self.in_turn(&msg.session_key(), self.run_command(msg, cmd)).await,
+            false => self.run_command(msg, cmd).await,
+        }
+    }
+
+    async fn handle_edit(&self, msg: InboundMessage, latest: bool) -> Result<String> {
+        if self.wake_words.check(&msg) == WakeCheck::Ignore {
+            return Ok(String::new());
+        }
+        if latest && self.regenerate_on_edit {
+            let undone = self
+                .in_turn(&msg.session_key(), async { self.agent.undo_last_turn().await.is_some() })
+                .await;
+            if undone {
+                return self.handle(msg).await;
+            }
+        }
+        // 不重新生成时记下修改，随下一条消息告知 Agent
+        self.edit_notes
+            .lock()
+            .unwrap()
+            .entry(msg.session_key())
+            .or_default()
+            .push(msg.content);
+        Ok(String::new())
+    }
+
+    async fn known_chats(&self, channel: &str) -> Result<Vec<String>> {
+        let Some(sessions) = self.agent.sessions() else {
+            return Ok(Vec::new());
+        };
+        let mut chats: Vec<String> = sessions
+            .list_sessions(i64::MAX)
+            .await?
+            .into_iter()
+            // 无痕会话只有按通道汇总的统计，没有真实的聊天 ID
+            .filter(|s| s.metadata.channel == channel && !s.id.starts_with("incognito:"))
+            .map(|s| s.metadata.channel_id)
+            .collect();
+        chats.sort();
+        chats.dedup();
+        Ok(chats)
+    }
+}
+
+/// 把提供商限流等待提示推送到发起请求的聊天
+pub struct BusyNoticeHandler {
+    channels: Vec<Arc<dyn Channel>>,
+}
+
+impl BusyNoticeHandler {
+    pub fn new(channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self { channels }
+    }
+}
+
+#[async_trait]
+impl EventHandler<ProviderBusyEvent> for BusyNoticeHandler {
+    async fn handle(&self, event: &ProviderBusyEvent) {
+        let Some(channel) = self.channels.iter().find(|c| c.name() == event.channel) else {
+            return;
+        };
+        let notice = t!(
+            "notice.busy",
+            provider = event.provider,
+            secs = event.retry_after_secs,
+            attempt = event.attempt
+        );
+        if let Err(e) = channel.send_message(&ChannelTarget::chat(event.chat_id.as_str()), &notice).await {
+            tracing::warn!("发送限流提示失败: {}", e);
+        }
+    }
+}
+
+/// 把工具执行进度推送到发起请求的聊天
+pub struct ProgressNoticeHandler {
+    channels: Vec<Arc<dyn Channel>>,
+}
+
+impl ProgressNoticeHandler {
+    pub fn new(channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self { channels }
+    }
+}
+
+#[async_trait]
+impl EventHandler<ToolProgressEvent> for ProgressNoticeHandler {
+    async fn handle(&self, event: &ToolProgressEvent) {
+        let Some(channel) = self.channels.iter().find(|c| c.name() == event.channel) else {
+            return;
+        };
+        let notice = t!("notice.progress", tool = event.tool_name, message = event.message);
+        if let Err(e) = channel.send_message(&ChannelTarget::chat(event.chat_id.as_str()), &notice).await {
+            tracing::warn!("发送工具进度失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cancel_request() {
+        assert!(is_cancel_request(" Stop "));
+        assert!(is_cancel_request("/cancel@nanobot_bot"));
+        assert!(is_cancel_request("取消"));
+        assert!(!is_cancel_request("stop using emoji"));
+        assert!(!is_cancel_request("/clear"));
+    }
+
+    #[test]
+    fn test_content_with_quote() {
+        let msg = InboundMessage::new("telegram", "1", "2", "为什么？");
+        assert_eq!(msg.content_with_quote(), "为什么？");
+
+        let msg = msg.with_reply_to(Some(QuotedMessage {
+            from_bot: true,
+            content: "明天会下雨".to_string(),
+        }));
+        assert_eq!(
+            msg.content_with_quote(),
+            "[用户回复了你之前的消息：「明天会下雨」]\n为什么？"
+        );
+
+        // 空引用被忽略
+        let msg = msg.with_reply_to(Some(QuotedMessage {
+            from_bot: false,
+            content: " ".to_string(),
+        }));
+        assert!(msg.reply_to.is_none());
+    }
+
+    #[test]
+    fn test_parse_incognito() {
+        assert_eq!(
+            ChannelCommand::parse_incognito("/incognito"),
+            Some(ChannelCommand::Incognito(None))
+        );
+        assert_eq!(
+            ChannelCommand::parse_incognito("/incognito@nanobot_bot ON"),
+            Some(ChannelCommand::Incognito(Some(true)))
+        );
+        assert_eq!(
+            ChannelCommand::parse_incognito("/incognito off"),
+            Some(ChannelCommand::Incognito(Some(false)))
+        );
+        assert_eq!(ChannelCommand::parse_incognito("/incognito maybe"), None);
+        assert_eq!(ChannelCommand::parse_incognito("incognito on"), None);
+        assert_eq!(
+            ChannelCommand::parse_verbose("/verbose 开"),
+            Some(ChannelCommand::Verbose(Some(true)))
+        );
+        assert_eq!(ChannelCommand::parse_verbose("/verbosely"), None);
+    }
+
+    #[test]
+    fn test_merge_messages() {
+        assert!(merge_messages(Vec::new()).is_none());
+        let single = merge_messages(vec![InboundMessage::new("telegram", "1", "u", "你好")]).unwrap();
+        assert_eq!(single.content, "你好");
+
+        let quoted = InboundMessage::new("telegram", "1", "u", "这个呢").with_reply_to(Some(QuotedMessage {
+            from_bot: true,
+            content: "天气晴".to_string(),
+        }));
+        let merged = merge_messages(vec![
+            InboundMessage::new("telegram", "1", "u", "帮我查一下"),
+            quoted,
+            InboundMessage::new("telegram", "1", "u", "明天的"),
+        ])
+        .unwrap();
+        assert_eq!(merged.content, "帮我查一下\n[用户回复了你之前的消息：「天气晴」]\n这个呢\n明天的");
+        assert!(merged.reply_to.is_none());
+        assert_eq!(merged.session_key(), "telegram:1");
+    }
+
+    #[test]
+    fn test_parse_job() {
+        assert_eq!(
+            ChannelCommand::parse_job("/job@nanobot_bot pause 3f2a9c1b"),
+            Some(ChannelCommand::Job(JobAction::Pause, "3f2a9c1b".to_string()))
+        );
+        assert_eq!(
+            ChannelCommand::parse_job("/job 删除 3f2a"),
+            Some(ChannelCommand::Job(JobAction::Delete, "3f2a".to_string()))
+        );
+        assert_eq!(ChannelCommand::parse_job("/job pause"), None);
+        assert_eq!(ChannelCommand::parse_job("/job run 3f2a"), None);
+        assert_eq!(ChannelCommand::parse_job("/jobs"), None);
+
+        assert_eq!(ChannelCommand::parse_jobs("/jobs"), Some(ChannelCommand::Jobs(false)));
+        assert_eq!(ChannelCommand::parse_jobs("/jobs@nanobot_bot --all"), Some(ChannelCommand::Jobs(true)));
+        assert_eq!(ChannelCommand::parse_jobs("/jobs x"), None);
+        assert_eq!(ChannelCommand::parse_jobs("/job pause 3f2a"), None);
+    }
+
+    #[tokio::test]
+    async fn test_job_commands_on_other_channels() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "mock".to_string();
+        config.channel.admin_users = vec!["discord:42".to_string()];
+        let scheduler = crate::cron::Scheduler::new().await.unwrap();
+        let id = scheduler
+            .add_job(crate::cron::Job::new_interval("备份", 3600, "test_handler").non_persistent())
+            .await
+            .unwrap();
+        let agent = Agent::new(config.clone(), None).await.unwrap().with_scheduler(scheduler.clone());
+        let handler = AgentHandler::new(Arc::new(agent)).with_admins(config.channel.admins());
+        let send = |sender: &str, text: &str| handler.handle(InboundMessage::new("discord", "7", sender, text));
+
+        // 非管理员不能查看或操作任务
+        assert_eq!(send("7", "/jobs").await.unwrap(), t!("cmd.admin_only"));
+        assert_eq!(send("7", &format!("/job delete {}", id)).await.unwrap(), t!("cmd.admin_only"));
+        assert!(scheduler.get_job(&id).await.is_some());
+
+        assert!(send("42", "/jobs").await.unwrap().contains("备份"));
+        let reply = send("42", &format!("/job pause {}", &id[..8])).await.unwrap();
+        assert_eq!(reply, t!("cmd.job_paused", name = "备份"));
+        assert_eq!(scheduler.get_job(&id).await.unwrap().status, crate::cron::JobStatus::Paused);
+    }
+
+    #[test]
+    fn test_parse_on_this_day() {
+        assert_eq!(
+            ChannelCommand::parse_on_this_day("/onthisday@nanobot_bot 上周二 我在做什么 "),
+            Some(ChannelCommand::OnThisDay("上周二 我在做什么".to_string()))
+        );
+        assert_eq!(ChannelCommand::parse_on_this_day("/onthisday"), Some(ChannelCommand::OnThisDay(String::new())));
+        assert_eq!(ChannelCommand::parse_on_this_day("/onthis day"), None);
+    }
+
+    #[test]
+    fn test_parse_memories() {
+        assert_eq!(
+            ChannelCommand::parse_memories("/memories@nanobot_bot"),
+            Some(ChannelCommand::Memories(ReviewAction::Pending))
+        );
+        assert_eq!(
+            ChannelCommand::parse_memories("/memories approve 2 5"),
+            Some(ChannelCommand::Memories(ReviewAction::Approve(Some(vec![2, 5]))))
+        );
+        assert_eq!(ChannelCommand::parse_memories("/memories reject"), None);
+        assert_eq!(ChannelCommand::parse_memories("/memory"), None);
+    }
+
+    #[test]
+    fn test_trace_line() {
+        let tool = |name: &str, millis: u64, success: bool| crate::agent::ToolTrace {
+            name: name.to_string(),
+            arguments: serde_json::Value::Null,
+            output: String::new(),
+            success,
+            duration: std::time::Duration::from_millis(millis),
+        };
+        let response = AgentResponse {
+            content: String::new(),
+            model: "deepseek-chat".to_string(),
+            tool_trace: vec![tool("shell", 400, true), tool("web_search", 1240, false)],
+            tokens: 2340,
+        };
+        assert_eq!(
+            trace_line(&response),
+            format!("🔧 shell 0.4s ✓, web_search 1.2s ✗ · {} · deepseek-chat", t!("verbose.tokens", tokens = "2,340"))
+        );
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn test_parse_research() {
+        assert_eq!(
+            ChannelCommand::parse_research("/research@nanobot_bot  Rust 异步运行时对比 "),
+            Some(ChannelCommand::Research("Rust 异步运行时对比".to_string()))
+        );
+        assert_eq!(ChannelCommand::parse_research("/research"), Some(ChannelCommand::Research(String::new())));
+        assert_eq!(ChannelCommand::parse_research("/researcher x"), None);
+        assert_eq!(ChannelCommand::parse_research("research x"), None);
+    }
+
+    #[test]
+    fn test_parse_best_of() {
+        assert_eq!(
+            ChannelCommand::parse_best_of("/bestof 5 1+1 等于几？"),
+            Some(ChannelCommand::BestOf(5, "1+1 等于几？".to_string()))
+        );
+        assert_eq!(
+            ChannelCommand::parse_best_of("/bestof@nanobot_bot 天空为什么是蓝的"),
+            Some(ChannelCommand::BestOf(DEFAULT_BEST_OF, "天空为什么是蓝的".to_string()))
+        );
+        assert_eq!(ChannelCommand::parse_best_of("/bestof"), Some(ChannelCommand::BestOf(DEFAULT_BEST_OF, String::new())));
+        assert_eq!(ChannelCommand::parse_best_of("/best x"), None);
+    }
+
+    #[test]
+    fn test_parse_title() {
+        assert_eq!(
+            ChannelCommand::parse_title("/title@nanobot_bot  周末旅行计划 "),
+            Some(ChannelCommand::Title(Some("周末旅行计划".to_string())))
+        );
+        assert_eq!(ChannelCommand::parse_title("/title"), Some(ChannelCommand::Title(None)));
+        assert_eq!(ChannelCommand::parse_title("/titles x"), None);
+        assert_eq!(ChannelCommand::parse_title("title x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sessions_do_not_interleave() {
+        use axum::{routing::post, Json, Router};
+        use serde_json::{json, Value};
+
+        // 延迟响应的提供商，回复它看到的全部用户消息
+        async fn complete(Json(body): Json<Value>) -> Json<Value> {
+            let seen: Vec<&str> = body["messages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|m| m["role"] == "user")
+                .filter_map(|m| m["content"].as_str())
+                .collect();
+            let content = seen.join("|");
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            Json(json!({
+                "id": "test",
+                "model": "test",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": "stop"
+                }]
+            }))
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/v1/chat/completions", post(complete));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        let agent = Agent::new(config, None).await.unwrap();
+        let handler = AgentHandler::new(Arc::new(agent));
+
+        let (a, b) = tokio::join!(
+            handler.handle(InboundMessage::new("telegram", "1", "alice", "a1")),
+            handler.handle(InboundMessage::new("telegram", "2", "bob", "b1")),
+        );
+        assert_eq!(a.unwrap(), "a1");
+        assert_eq!(b.unwrap(), "b1");
+
+        let conversations = temp_dir.path().join("memory/conversations");
+        let a = std::fs::read_to_string(conversations.join("telegram:1.md")).unwrap();
+        let b = std::fs::read_to_string(conversations.join("telegram:2.md")).unwrap();
+        assert!(a.contains("a1") && !a.contains("b1"));
+        assert!(b.contains("b1") && !b.contains("a1"));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_reply_concurrently() {
+        use axum::{extract::State, routing::post, Json, Router};
+        use serde_json::{json, Value};
+        use tokio::sync::Barrier;
+
+        // 两个请求同时到达后才回复；逐个处理时第一个请求等不到第二个
+        async fn complete(State(barrier): State<Arc<Barrier>>, Json(_): Json<Value>) -> Json<Value> {
+            let waited = tokio::time::timeout(std::time::Duration::from_secs(2), barrier.wait()).await;
+            let content = if waited.is_ok() { "concurrent" } else { "serialized" };
+            Json(json!({
+                "id": "test",
+                "model": "test",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": "stop"
+                }]
+            }))
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/v1/chat/completions", post(complete))
+            .with_state(Arc::new(Barrier::new(2)));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        let agent = Agent::new(config, None).await.unwrap();
+        let handler = AgentHandler::new(Arc::new(agent));
+
+        let (a, b) = tokio::join!(
+            handler.handle(InboundMessage::new("telegram", "1", "alice", "a1")),
+            handler.handle(InboundMessage::new("discord", "2", "bob", "b1")),
+        );
+        assert_eq!(a.unwrap(), "concurrent");
+        assert_eq!(b.unwrap(), "concurrent");
+    }
+
+    #[tokio::test]
+    async fn test_filter_audit_excerpt_is_redacted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = crate::config::Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "mock".to_string();
+        config.channel.incognito_channels = vec!["secret".to_string()];
+        let filter_config = crate::config::ContentFilterConfig {
+            enabled: true,
+            rules: vec![crate::config::ContentFilterRule {
+                pattern: r"\d{17}[\dXx]".to_string(),
+                action: crate::config::FilterAction::Redact,
+            }],
+            ..Default::default()
+        };
+        let audit_path = temp_dir.path().join("content_filter.jsonl");
+        let filter = ContentFilter::new(&filter_config, audit_path.clone()).unwrap();
+        let agent = Agent::new(config, None).await.unwrap();
+        let handler = AgentHandler::new(Arc::new(agent)).with_content_filter(Some(Arc::new(filter)));
+
+        let text = "身份证 11010119900101123X";
+        let _ = handler.handle(InboundMessage::new("telegram", "1", "alice", text)).await;
+        let _ = handler.handle(InboundMessage::new("secret", "1", "bob", text)).await;
+
+        let log = std::fs::read_to_string(&audit_path).unwrap();
+        assert!(!log.contains("11010119900101123X"));
+        let lines: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["excerpt"], format!("身份证 {}", filter_config.redact_with));
+        assert!(lines[1].get("excerpt").is_none());
+    }
+}
@@ -0,0 +1,263 @@
+//! 通道健康状态
+//!
+//! 记录各通道的运行状态、最近收发时间、错误和重启次数。
+//! 通道由 [`MonitoredChannel`]、入站处理器由 [`MonitoredHandler`] 包装后自动记录，
+//! 网关定期把快照写入工作目录，供 `nanobot status` 读取
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use super::{Channel, ChannelCommand, ChannelTarget, InboundMessage, MessageHandler};
+use crate::crash;
+
+/// 通道运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelState {
+    /// 运行中
+    Running,
+    /// 异常退出，等待重启
+    Restarting,
+    /// 已停止
+    Stopped,
+    /// 重启次数用尽
+    Failed,
+}
+
+impl ChannelState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelState::Running => "运行中",
+            ChannelState::Restarting => "等待重启",
+            ChannelState::Stopped => "已停止",
+            ChannelState::Failed => "已失败",
+        }
+    }
+}
+
+/// 单个通道的健康状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelHealth {
+    pub name: String,
+    pub state: ChannelState,
+    /// 本次启动时间
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_inbound: Option<DateTime<Utc>>,
+    pub last_outbound: Option<DateTime<Utc>>,
+    pub inbound_count: u64,
+    pub outbound_count: u64,
+    /// 发送失败、处理失败和异常退出的次数
+    pub error_count: u64,
+    pub restart_count: u32,
+    pub last_error: Option<String>,
+}
+
+impl ChannelHealth {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: ChannelState::Stopped,
+            started_at: None,
+            last_inbound: None,
+            last_outbound: None,
+            inbound_count: 0,
+            outbound_count: 0,
+            error_count: 0,
+            restart_count: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// 健康状态快照（写入文件）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    pub updated_at: DateTime<Utc>,
+    pub channels: Vec<ChannelHealth>,
+}
+
+impl HealthSnapshot {
+    /// 从文件读取快照
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取通道状态失败: {}", path.display()))?;
+        serde_json::from_str(&content).context("解析通道状态失败")
+    }
+}
+
+/// 各通道健康状态（可克隆共享）
+#[derive(Clone, Default)]
+pub struct HealthRegistry {
+    inner: Arc<RwLock<HashMap<String, ChannelHealth>>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut ChannelHealth)) {
+        let mut inner = self.inner.write().unwrap();
+        f(inner
+            .entry(name.to_string())
+            .or_insert_with(|| ChannelHealth::new(name)));
+    }
+
+    /// 设置运行状态
+    pub fn set_state(&self, name: &str, state: ChannelState) {
+        self.update(name, |h| {
+            if state == ChannelState::Running {
+                h.started_at = Some(Utc::now());
+            }
+            h.state = state;
+        });
+    }
+
+    /// 记录收到消息
+    pub fn record_inbound(&self, name: &str) {
+        self.update(name, |h| {
+            h.inbound_count += 1;
+            h.last_inbound = Some(Utc::now());
+        });
+    }
+
+    /// 记录发送成功
+    pub fn record_outbound(&self, name: &str) {
+        self.update(name, |h| {
+            h.outbound_count += 1;
+            h.last_outbound = Some(Utc::now());
+        });
+    }
+
+    /// 记录错误
+    pub fn record_error(&self, name: &str, error: impl ToString) {
+        self.update(name, |h| {
+            h.error_count += 1;
+            h.last_error = Some(error.to_string());
+        });
+    }
+
+    /// 记录一次重启
+    pub fn record_restart(&self, name: &str) {
+        self.update(name, |h| h.restart_count += 1);
+    }
+
+    /// 指定通道的状态
+    #[cfg(test)]
+    pub fn get(&self, name: &str) -> Option<ChannelHealth> {
+        self.inner.read().unwrap().get(name).cloned()
+    }
+
+    /// 所有通道的状态（按名称排序）
+    pub fn snapshot(&self) -> HealthSnapshot {
+        let mut channels: Vec<_> = self.inner.read().unwrap().values().cloned().collect();
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+        HealthSnapshot {
+            updated_at: Utc::now(),
+            channels,
+        }
+    }
+
+    /// 将快照写入文件
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.snapshot())?;
+        tokio::fs::write(path, content)
+            .await
+            .with_context(|| format!("写入通道状态失败: {}", path.display()))
+    }
+}
+
+/// 记录发送情况的通道包装
+pub struct MonitoredChannel {
+    inner: Arc<dyn Channel>,
+    health: HealthRegistry,
+}
+
+impl MonitoredChannel {
+    pub fn new(inner: Arc<dyn Channel>, health: HealthRegistry) -> Self {
+        Self { inner, health }
+    }
+
+    fn record<T>(&self, result: &Result<T>) {
+        match result {
+            Ok(_) => self.health.record_outbound(self.inner.name()),
+            Err(e) => self.health.record_error(self.inner.name(), format!("发送失败: {}", e)),
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for MonitoredChannel {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn start(&self) -> Result<()> {
+        self.inner.start().await
+    }
+
+    async fn stop(&self) -> Result<()> {
+        self.inner.stop().await
+    }
+
+    async fn send_message(&self, target: &ChannelTarget, content: &str) -> Result<()> {
+        let result = self.inner.send_message(target, content).await;
+        self.record(&result);
+        result
+    }
+
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        self.inner.check_target(target)
+    }
+
+    fn routes(self: Arc<Self>) -> Option<axum::Router> {
+        self.inner.clone().routes()
+    }
+}
+
+/// 记录入站消息的处理器包装
+pub struct MonitoredHandler {
+    inner: Arc<dyn MessageHandler>,
+    channel: String,
+    health: HealthRegistry,
+}
+
+impl MonitoredHandler {
+    pub fn new(inner: Arc<dyn MessageHandler>, channel: &str, health: HealthRegistry) -> Self {
+        Self {
+            inner,
+            channel: channel.to_string(),
+            health,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageHandler for MonitoredHandler {
+    async fn handle(&self, msg: InboundMessage) -> Result<String> {
+        self.health.record_inbound(&self.channel);
+        // handler 中的 panic 作为处理失败返回，不影响通道继续接收消息
+        let component = format!("channel:{}", self.channel);
+        let result = crash::catch(component, self.inner.handle(msg)).await.and_then(|r| r);
+        if let Err(ref e) = result {
+            self.health.record_error(&self.channel, format!("处理失败: {}", e));
+        }
+        result
+    }
+
+    async fn command(&self, msg: &InboundMessage, cmd: ChannelCommand) -> Result<String> {
+        self.health.record_inbound(&self.channel);
+        let result = crash::catch(format!("channel:{}", self.channel), self.inner.command(msg, cmd))
+            .await
+            .and_then(|r| r);
+        if let Err(ref e) = result {
+            self.health.record_error(&self.channel, format!("处理失败: {}", e));
+        }
+        result
+    }
+}
@@ -4,45 +4,41 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
+use crate::config::SupervisorConfig;
+use crate::crash;
+use health::{ChannelState, HealthRegistry, MonitoredChannel, MonitoredHandler};
+
+pub mod dedupe;
 pub mod discord;
 pub mod feishu;
+pub mod filter;
+pub mod handler;
+pub mod health;
+pub mod outbox;
+pub mod postprocess;
+pub mod quiet;
+pub mod target;
+pub mod template;
 pub mod telegram;
+pub mod wake;
 pub mod whatsapp;
 
-/// 媒体类型枚举
-#[derive(Debug, Clone)]
-pub enum MediaType {
-    Image,
-    Audio,
-    File,
-}
-
-/// 媒体消息结构
-#[derive(Debug, Clone)]
-pub struct Media {
-    pub media_type: MediaType,
-    pub path: Option<String>,
-    pub url: Option<String>,
-    pub name: Option<String>,
-}
-
-impl Media {
-    pub fn new_image(path: Option<String>, url: Option<String>, name: Option<String>) -> Self {
-        Self { media_type: MediaType::Image, path, url, name }
-    }
-
-    pub fn new_audio(path: Option<String>, url: Option<String>, name: Option<String>) -> Self {
-        Self { media_type: MediaType::Audio, path, url, name }
-    }
-
-    pub fn new_file(path: Option<String>, url: Option<String>, name: Option<String>) -> Self {
-        Self { media_type: MediaType::File, path, url, name }
-    }
-}
+pub use handler::{AgentHandler, ChannelCommand, InboundMessage, MessageHandler, QuotedMessage};
+pub use target::ChannelTarget;
 
 /// 通道 trait - 定义消息通道的基本接口
+///
+/// 通道收到的消息交给创建时传入的 [`MessageHandler`] 处理，
+/// 主动发送（回复、提醒、发件箱投递）统一通过 `send_message`，目标由各通道转换为平台 ID
 #[async_trait]
 pub trait Channel: Send + Sync {
     /// 通道名称
@@ -57,57 +53,75 @@ pub trait Channel: Send + Sync {
     /// 发送文本消息
     async fn send_message(
         &self,
-        target: &str,
+        target: &ChannelTarget,
         content: &str,
     ) -> Result<()>;
     
-    /// 发送媒体消息（可选实现）
-    async fn send_media(
-        &self,
-        target: &str,
-        media: &Media,
-    ) -> Result<()> {
-        Err(anyhow::anyhow!("{} 不支持发送媒体消息", self.name()))
+    /// 校验目标能否转换为通道的 ID（入队或发送前调用，不支持的目标类型立即报错）
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// 通道需要接收回调时提供的 HTTP 路由（如飞书事件订阅），
+    /// 挂载到共享 HTTP 服务的 `/<通道名>` 下
+    fn routes(self: Arc<Self>) -> Option<axum::Router> {
+        None
     }
 }
 
+/// 通道共享服务
+#[derive(Clone, Default)]
+pub struct ChannelServices {
+    /// 发件箱，设置后通道回复经发件箱投递
+    pub outbox: Option<Arc<outbox::Outbox>>,
+    /// 入站消息去重
+    pub dedupe: Option<Arc<dedupe::DedupeStore>>,
+}
+
 /// 通道工厂
 pub struct ChannelFactory;
 
 impl ChannelFactory {
-    /// 创建通道实例
+    /// 创建通道实例，入站消息交给 handler 处理
     pub fn create(
         name: &str,
         config: &crate::config::Config,
-        agent: Arc<crate::agent::Agent>,
+        handler: Arc<dyn MessageHandler>,
+        services: &ChannelServices,
     ) -> Result<Arc<dyn Channel>> {
         match name {
             "telegram" => {
                 let channel = telegram::TelegramChannel::new(
                     config.channel.telegram.clone(),
-                    agent,
-                )?;
+                    handler,
+                )?
+                .with_outbox(services.outbox.clone())
+                .with_dedupe(services.dedupe.clone());
                 Ok(Arc::new(channel))
             }
             "discord" => {
-                let channel = discord::DiscordChannel::new(
-                    config.channel.discord.clone(),
-                    agent,
-                )?;
+                // Discord 暂不接收消息，不需要 handler
+                let channel = discord::DiscordChannel::new(config.channel.discord.clone())?;
                 Ok(Arc::new(channel))
             }
             "feishu" => {
                 let channel = feishu::FeishuChannel::new(
                     config.channel.feishu.clone(),
-                    agent,
-                )?;
+                    handler,
+                )?
+                .with_outbox(services.outbox.clone())
+                .with_dedupe(services.dedupe.clone())
+                .with_media_dir(config.media_dir());
                 Ok(Arc::new(channel))
             }
             "whatsapp" => {
                 let channel = whatsapp::WhatsAppChannel::new(
                     config.channel.whatsapp.clone(),
-                    agent,
-                )?;
+                    handler,
+                )?
+                .with_outbox(services.outbox.clone())
+                .with_dedupe(services.dedupe.clone());
                 Ok(Arc::new(channel))
             }
             _ => Err(anyhow::anyhow!("未知的通道: {}", name)),
@@ -115,40 +129,334 @@ impl ChannelFactory {
     }
 }
 
+/// 广播目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastTarget {
+    /// 通道名（如 telegram）
+    pub channel: String,
+    /// 发送目标（文本形式，如 `123456`、`user:ou_xxx`、`tel:+8613800000000`）
+    pub to: ChannelTarget,
+}
+
+/// 广播到单个目标的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastResult {
+    pub channel: String,
+    pub to: ChannelTarget,
+    /// 发送失败的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// 通道管理器
+///
+/// 注册的通道经 [`MonitoredChannel`] 包装以记录收发情况；`start_all` 为每个通道启动
+/// 监督任务，通道出错或 panic 退出后按指数退避自动重启
 pub struct ChannelManager {
     channels: Vec<Arc<dyn Channel>>,
+    health: HealthRegistry,
+    supervisor: SupervisorConfig,
+    shutdown: CancellationToken,
+    /// 设置后广播经发件箱投递
+    outbox: Option<Arc<outbox::Outbox>>,
 }
 
 impl ChannelManager {
     pub fn new() -> Self {
+        Self::with_supervisor(SupervisorConfig::default())
+    }
+
+    /// 使用指定的重启策略
+    pub fn with_supervisor(supervisor: SupervisorConfig) -> Self {
         Self {
             channels: Vec::new(),
+            health: HealthRegistry::new(),
+            supervisor,
+            shutdown: CancellationToken::new(),
+            outbox: None,
         }
     }
 
+    /// 设置发件箱（广播消息经发件箱投递，失败重试）
+    pub fn with_outbox(mut self, outbox: Option<Arc<outbox::Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// 创建并注册通道，入站消息交给 handler 处理
+    pub fn create(
+        &mut self,
+        name: &str,
+        config: &crate::config::Config,
+        handler: Arc<dyn MessageHandler>,
+        services: &ChannelServices,
+    ) -> Result<()> {
+        let handler = Arc::new(MonitoredHandler::new(handler, name, self.health.clone()));
+        let channel = ChannelFactory::create(name, config, handler, services)?;
+        self.register(channel);
+        Ok(())
+    }
+
     /// 注册通道
     pub fn register(&mut self, channel: Arc<dyn Channel>) {
-        self.channels.push(channel);
+        self.health.set_state(channel.name(), ChannelState::Stopped);
+        self.channels
+            .push(Arc::new(MonitoredChannel::new(channel, self.health.clone())));
+    }
+
+    /// 获取已注册的通道
+    pub fn channels(&self) -> Vec<Arc<dyn Channel>> {
+        self.channels.clone()
+    }
+
+    /// 各通道提供的 HTTP 路由（通道名, 路由）
+    pub fn routes(&self) -> Vec<(String, axum::Router)> {
+        self.channels
+            .iter()
+            .filter_map(|c| c.clone().routes().map(|r| (c.name().to_string(), r)))
+            .collect()
+    }
+
+    /// 各通道的健康状态
+    pub fn health(&self) -> HealthRegistry {
+        self.health.clone()
+    }
+
+    /// 不经过 Agent，直接向多个目标发送同一条消息
+    ///
+    /// 设置发件箱时成功只表示已入队，实际投递由发件箱重试
+    pub async fn broadcast(&self, targets: &[BroadcastTarget], content: &str) -> Vec<BroadcastResult> {
+        let mut results = Vec::with_capacity(targets.len());
+        for target in targets {
+            let result = match self.channels.iter().find(|c| c.name() == target.channel) {
+                Some(channel) => {
+                    outbox::deliver(self.outbox.as_ref(), channel.as_ref(), &target.to, content).await
+                }
+                None => Err(anyhow::anyhow!("通道未注册: {}", target.channel)),
+            };
+            if let Err(ref e) = result {
+                warn!("广播到 {}:{} 失败: {}", target.channel, target.to, e);
+            }
+            results.push(BroadcastResult {
+                channel: target.channel.clone(),
+                to: target.to.clone(),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+        let failed = results.iter().filter(|r| r.error.is_some()).count();
+        info!("广播完成: {} 个目标，失败 {} 个", results.len(), failed);
+        results
     }
 
-    /// 启动所有通道
+    /// 启动所有通道（每个通道在独立的监督任务中运行，立即返回）
     pub async fn start_all(&self) -> Result<()> {
         for channel in &self.channels {
             info!("启动通道: {}", channel.name());
-            channel.start().await?;
+            tokio::spawn(supervise(
+                channel.clone(),
+                self.health.clone(),
+                self.supervisor.clone(),
+                self.shutdown.clone(),
+            ));
         }
         Ok(())
     }
 
+    /// 定期把健康状态写入文件
+    pub fn start_health_reporter(&self, path: PathBuf) -> JoinHandle<()> {
+        let health = self.health.clone();
+        let shutdown = self.shutdown.clone();
+        let interval = Duration::from_secs(self.supervisor.health_report_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = health.save(&path).await {
+                    warn!("{}", e);
+                }
+            }
+            // 退出前写入最终状态
+            let _ = health.save(&path).await;
+        })
+    }
+
     /// 停止所有通道
     pub async fn stop_all(&self) -> Result<()> {
+        self.shutdown.cancel();
         for channel in &self.channels {
             info!("停止通道: {}", channel.name());
             channel.stop().await?;
+            self.health.set_state(channel.name(), ChannelState::Stopped);
         }
         Ok(())
     }
 }
 
-use tracing::info;
+/// 监督单个通道：异常退出后按指数退避重启
+///
+/// `start` 正常返回视为通道由外部驱动（如飞书 Webhook）或已按要求停止，不再重启
+async fn supervise(
+    channel: Arc<dyn Channel>,
+    health: HealthRegistry,
+    config: SupervisorConfig,
+    shutdown: CancellationToken,
+) {
+    let name = channel.name().to_string();
+    let initial_backoff = Duration::from_secs(config.restart_backoff_secs);
+    let max_backoff = Duration::from_secs(config.max_restart_backoff_secs.max(config.restart_backoff_secs));
+    let mut backoff = initial_backoff;
+    let mut restarts = 0;
+
+    loop {
+        health.set_state(&name, ChannelState::Running);
+        let started = Instant::now();
+
+        // 在独立任务中运行，panic 不会影响其他通道
+        let task = crash::spawn(format!("channel:{}", name), {
+            let channel = channel.clone();
+            async move { channel.start().await }
+        });
+        let error = match task.await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => format!("异常退出: {}", e),
+            Err(e) if e.is_panic() => format!("panic: {}", crash::panic_message(e.into_panic().as_ref())),
+            Err(e) => format!("任务被取消: {}", e),
+        };
+        if shutdown.is_cancelled() {
+            return;
+        }
+
+        error!("通道 {} {}", name, error);
+        health.record_error(&name, &error);
+
+        // 稳定运行超过退避上限视为已恢复，重新计算退避时间和重启次数
+        if started.elapsed() >= max_backoff {
+            backoff = initial_backoff;
+            restarts = 0;
+        }
+        if config.max_restarts > 0 && restarts >= config.max_restarts {
+            error!("通道 {} 连续重启 {} 次仍失败，不再重启", name, restarts);
+            health.set_state(&name, ChannelState::Failed);
+            return;
+        }
+
+        restarts += 1;
+        health.set_state(&name, ChannelState::Restarting);
+        warn!("{} 秒后重启通道 {}（第 {} 次）", backoff.as_secs(), name, restarts);
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = shutdown.cancelled() => return,
+        }
+        health.record_restart(&name);
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// 前两次启动失败（一次出错、一次 panic）的通道
+    struct CrashingChannel {
+        starts: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Channel for CrashingChannel {
+        fn name(&self) -> &str {
+            "crashy"
+        }
+
+        async fn start(&self) -> Result<()> {
+            match self.starts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(anyhow::anyhow!("连接断开")),
+                1 => panic!("boom"),
+                _ => std::future::pending().await,
+            }
+        }
+
+        async fn stop(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_message(&self, target: &ChannelTarget, _content: &str) -> Result<()> {
+            self.check_target(target)
+        }
+
+        fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+            match target {
+                ChannelTarget::ChatId(_) => Ok(()),
+                other => Err(other.unsupported(self.name())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_crashed_channel() {
+        let channel = Arc::new(CrashingChannel {
+            starts: AtomicU32::new(0),
+        });
+        let mut manager = ChannelManager::with_supervisor(SupervisorConfig {
+            restart_backoff_secs: 0,
+            ..Default::default()
+        });
+        manager.register(channel.clone());
+        manager.start_all().await.unwrap();
+
+        for _ in 0..100 {
+            if channel.starts.load(Ordering::SeqCst) >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let health = manager.health().get("crashy").unwrap();
+        assert_eq!(health.state, ChannelState::Running);
+        assert_eq!(health.restart_count, 2);
+        assert_eq!(health.error_count, 2);
+        assert!(health.last_error.unwrap().contains("boom"));
+
+        manager.channels()[0].send_message(&ChannelTarget::chat("1"), "hi").await.unwrap();
+        let health = manager.health().get("crashy").unwrap();
+        assert_eq!(health.outbound_count, 1);
+        assert!(health.last_outbound.is_some());
+
+        manager.stop_all().await.unwrap();
+        assert_eq!(manager.health().get("crashy").unwrap().state, ChannelState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let mut manager = ChannelManager::new();
+        manager.register(Arc::new(CrashingChannel {
+            starts: AtomicU32::new(0),
+        }));
+        let target = |channel: &str, to: &str| BroadcastTarget {
+            channel: channel.to_string(),
+            to: ChannelTarget::parse(to).unwrap(),
+        };
+
+        let results = manager
+            .broadcast(
+                &[
+                    target("crashy", "1"),
+                    target("crashy", "2"),
+                    target("missing", "3"),
+                    target("crashy", "tel:+8613800000000"),
+                ],
+                "通知",
+            )
+            .await;
+        assert_eq!(results.len(), 4);
+        assert!(results[0].error.is_none() && results[1].error.is_none());
+        assert!(results[2].error.as_deref().unwrap().contains("missing"));
+        // 不支持的目标类型在发送前报错
+        assert!(results[3].error.as_deref().unwrap().contains("手机号"));
+        assert_eq!(manager.health().get("crashy").unwrap().outbound_count, 2);
+    }
+}
@@ -0,0 +1,413 @@
+//! 消息发件箱（Outbox）
+//!
+//! 通道回复先写入 SQLite 发件箱，再由投递任务发送，失败时按指数退避重试，
+//! 进程重启后继续投递未完成的消息，避免平台接口报错或进程崩溃导致回复丢失
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::{Channel, ChannelTarget};
+use crate::crash;
+use crate::vault::Vault;
+
+/// 最大投递尝试次数，超过后标记为失败
+const MAX_ATTEMPTS: i64 = 8;
+/// 首次重试间隔（秒）
+const BASE_BACKOFF_SECS: i64 = 2;
+/// 最大重试间隔（秒）
+const MAX_BACKOFF_SECS: i64 = 600;
+/// 空闲时的轮询间隔
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// 单次取出的最大消息数
+const BATCH_SIZE: i64 = 50;
+
+/// 单个通道的投递统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeliveryStats {
+    /// 待投递（含重试中）
+    pub pending: u64,
+    /// 已投递
+    pub delivered: u64,
+    /// 投递失败（已放弃）
+    pub failed: u64,
+}
+
+/// 待投递消息
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct OutboxRow {
+    id: String,
+    channel: String,
+    target: String,
+    content: String,
+    attempts: i64,
+}
+
+/// 消息发件箱
+pub struct Outbox {
+    pool: Pool<Sqlite>,
+    /// 设置后消息内容加密存储
+    vault: Option<Arc<Vault>>,
+    /// 有新消息入队时唤醒投递任务
+    notify: Notify,
+}
+
+impl Outbox {
+    /// 打开（或创建）发件箱数据库
+    pub async fn new(db_path: &str) -> Result<Arc<Self>> {
+        Self::open(db_path, None).await
+    }
+
+    /// 打开发件箱数据库，设置 vault 时消息内容加密存储
+    pub async fn open(db_path: &str, vault: Option<Arc<Vault>>) -> Result<Arc<Self>> {
+        let pool = crate::db::connect(db_path, 5)
+            .await
+            .context("连接发件箱数据库失败")?;
+
+        let outbox = Arc::new(Self {
+            pool,
+            vault,
+            notify: Notify::new(),
+        });
+        outbox.init_db().await?;
+
+        Ok(outbox)
+    }
+
+    /// 初始化数据库表
+    async fn init_db(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS outbox (
+                id TEXT PRIMARY KEY,
+                channel TEXT NOT NULL,
+                target TEXT NOT NULL,
+                content TEXT NOT NULL,
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                next_attempt_at TIMESTAMP NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                delivered_at TIMESTAMP
+            )
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_outbox_pending ON outbox(status, next_attempt_at)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 消息入队
+    pub async fn enqueue(&self, channel: &str, target: &ChannelTarget, content: &str) -> Result<String> {
+        self.enqueue_at(channel, target, content, Utc::now()).await
+    }
+
+    /// 消息入队，不早于 `not_before` 投递（如免打扰时段结束后）
+    pub async fn enqueue_at(
+        &self,
+        channel: &str,
+        target: &ChannelTarget,
+        content: &str,
+        not_before: DateTime<Utc>,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let content = match self.vault {
+            Some(ref vault) => vault.encrypt_text(content)?,
+            None => content.to_string(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO outbox (id, channel, target, content, status, attempts, next_attempt_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, 'pending', 0, ?5, ?6)
+            "#
+        )
+        .bind(&id)
+        .bind(channel)
+        .bind(target.to_string())
+        .bind(&content)
+        .bind(not_before.max(now))
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        self.notify.notify_one();
+        Ok(id)
+    }
+
+    /// 启动投递任务
+    pub fn start_worker(self: Arc<Self>, channels: Vec<Arc<dyn Channel>>) {
+        crash::spawn_supervised("outbox", move || self.clone().run_worker(channels.clone()));
+    }
+
+    async fn run_worker(self: Arc<Self>, channels: Vec<Arc<dyn Channel>>) {
+        info!("发件箱投递任务已启动");
+        loop {
+            match self.deliver_due(&channels).await {
+                // 本批次取满时可能还有积压，立即继续
+                Ok(n) if n as i64 >= BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => error!("发件箱投递失败: {}", e),
+            }
+
+            let _ = tokio::time::timeout(POLL_INTERVAL, self.notify.notified()).await;
+        }
+    }
+
+    /// 投递所有到期的消息，返回处理条数
+    async fn deliver_due(&self, channels: &[Arc<dyn Channel>]) -> Result<usize> {
+        let rows: Vec<OutboxRow> = sqlx::query_as(
+            r#"
+            SELECT id, channel, target, content, attempts FROM outbox
+            WHERE status = 'pending' AND next_attempt_at <= ?1
+            ORDER BY created_at
+            LIMIT ?2
+            "#
+        )
+        .bind(Utc::now())
+        .bind(BATCH_SIZE)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let count = rows.len();
+        for row in rows {
+            let content = match self.vault {
+                Some(ref vault) => vault.decrypt_text(&row.content),
+                None => Ok(row.content.clone()),
+            };
+            let result = match (content, channels.iter().find(|c| c.name() == row.channel)) {
+                (Ok(content), Some(channel)) => match ChannelTarget::parse(&row.target) {
+                    Ok(target) => channel.send_message(&target, &content).await,
+                    Err(e) => Err(e),
+                },
+                (Err(e), _) => Err(e),
+                (_, None) => Err(anyhow::anyhow!("通道未注册: {}", row.channel)),
+            };
+
+            match result {
+                Ok(_) => self.mark_delivered(&row.id).await?,
+                Err(e) => self.mark_failed_attempt(&row, &e.to_string()).await?,
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn mark_delivered(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE outbox SET status = 'delivered', delivered_at = ?1 WHERE id = ?2")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn mark_failed_attempt(&self, row: &OutboxRow, err: &str) -> Result<()> {
+        let attempts = row.attempts + 1;
+
+        if attempts >= MAX_ATTEMPTS {
+            error!(
+                "消息投递失败，已放弃 {} -> {}:{}: {}",
+                row.id, row.channel, row.target, err
+            );
+            sqlx::query(
+                "UPDATE outbox SET status = 'failed', attempts = ?1, last_error = ?2 WHERE id = ?3"
+            )
+            .bind(attempts)
+            .bind(err)
+            .bind(&row.id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(());
+        }
+
+        let next_attempt_at = Utc::now() + backoff(attempts);
+        warn!(
+            "消息投递失败（第 {} 次），{} 后重试 {} -> {}:{}: {}",
+            attempts,
+            next_attempt_at.format("%H:%M:%S"),
+            row.id,
+            row.channel,
+            row.target,
+            err
+        );
+        sqlx::query(
+            "UPDATE outbox SET attempts = ?1, last_error = ?2, next_attempt_at = ?3 WHERE id = ?4"
+        )
+        .bind(attempts)
+        .bind(err)
+        .bind(next_attempt_at)
+        .bind(&row.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 删除发往指定聊天的消息（未指定通道时匹配所有通道），返回删除数量
+    pub async fn purge_target(&self, channel: Option<&str>, target: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM outbox WHERE target = ?1 AND (?2 IS NULL OR channel = ?2)"
+        )
+        .bind(target)
+        .bind(channel)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 删除创建时间早于 `before` 的已投递或失败消息，返回删除数量
+    pub async fn prune_finished(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM outbox WHERE status != 'pending' AND created_at < ?1"
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 删除所有消息
+    pub async fn purge_all(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM outbox").execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 按通道统计投递情况
+    pub async fn stats(&self) -> Result<HashMap<String, DeliveryStats>> {
+        let rows: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT channel, status, COUNT(*) FROM outbox GROUP BY channel, status"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut stats: HashMap<String, DeliveryStats> = HashMap::new();
+        for (channel, status, count) in rows {
+            let entry = stats.entry(channel).or_default();
+            match status.as_str() {
+                "pending" => entry.pending += count as u64,
+                "delivered" => entry.delivered += count as u64,
+                "failed" => entry.failed += count as u64,
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// 第 n 次失败后的重试间隔（指数退避，封顶 MAX_BACKOFF_SECS）
+fn backoff(attempts: i64) -> Duration {
+    let secs = BASE_BACKOFF_SECS
+        .saturating_mul(1i64 << attempts.clamp(0, 20))
+        .min(MAX_BACKOFF_SECS);
+    Duration::seconds(secs)
+}
+
+/// 通过发件箱投递回复，未启用发件箱或入队失败时直接发送
+///
+/// 入队前先校验目标，通道不支持的目标立即报错而不是在发件箱中反复重试
+pub async fn deliver(
+    outbox: Option<&Arc<Outbox>>,
+    channel: &dyn Channel,
+    target: &ChannelTarget,
+    content: &str,
+) -> Result<()> {
+    channel.check_target(target)?;
+    if let Some(outbox) = outbox {
+        match outbox.enqueue(channel.name(), target, content).await {
+            Ok(_) => return Ok(()),
+            Err(e) => warn!("消息入队失败，直接发送: {}", e),
+        }
+    }
+    channel.send_message(target, content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 前 N 次发送失败的测试通道
+    struct FlakyChannel {
+        fail_times: usize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Channel for FlakyChannel {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn start(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_message(&self, _target: &ChannelTarget, _content: &str) -> Result<()> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            if n < self.fail_times {
+                anyhow::bail!("模拟发送失败");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::seconds(4));
+        assert_eq!(backoff(3), Duration::seconds(16));
+        assert_eq!(backoff(30), Duration::seconds(MAX_BACKOFF_SECS));
+    }
+
+    #[tokio::test]
+    async fn test_outbox_retry() {
+        let dir = std::env::temp_dir().join(format!("nanobot-outbox-{}", Uuid::new_v4()));
+        let outbox = Outbox::new(&dir.join("outbox.db").to_string_lossy()).await.unwrap();
+        let channel: Arc<dyn Channel> = Arc::new(FlakyChannel {
+            fail_times: 1,
+            calls: AtomicUsize::new(0),
+        });
+        let channels = vec![channel];
+
+        outbox.enqueue("flaky", &ChannelTarget::chat("u1"), "hello").await.unwrap();
+        outbox.enqueue("missing", &ChannelTarget::chat("u2"), "hello").await.unwrap();
+
+        outbox.deliver_due(&channels).await.unwrap();
+        let stats = outbox.stats().await.unwrap();
+        assert_eq!(stats["flaky"].pending, 1);
+        assert_eq!(stats["missing"].pending, 1);
+
+        // 跳过退避等待，立即重试
+        sqlx::query("UPDATE outbox SET next_attempt_at = ?1")
+            .bind(Utc::now() - Duration::seconds(1))
+            .execute(&outbox.pool)
+            .await
+            .unwrap();
+        outbox.deliver_due(&channels).await.unwrap();
+
+        let stats = outbox.stats().await.unwrap();
+        assert_eq!(stats["flaky"].delivered, 1);
+        assert_eq!(stats["flaky"].pending, 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
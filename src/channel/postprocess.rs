@@ -0,0 +1,262 @@
+//! 回复后处理
+//!
+//! Agent 的回复发出之前按 `[channel.postprocess]` 依次经过一组过滤器（去掉 `<think>` 块、
+//! 整理空白、截断过长的回复、转换裸 URL 等），各通道可以在 `overrides` 中使用不同的过滤器列表。
+//! 过滤器实现 [`ReplyFilter`]
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::config::{PostFilterKind, PostProcessConfig};
+use crate::t;
+
+/// 回复过滤器
+pub trait ReplyFilter: Send + Sync {
+    /// 处理回复文本
+    fn apply(&self, text: String) -> String;
+}
+
+/// 按通道执行的回复后处理流水线
+pub struct PostProcessor {
+    default: Vec<Arc<dyn ReplyFilter>>,
+    channels: HashMap<String, Vec<Arc<dyn ReplyFilter>>>,
+}
+
+impl PostProcessor {
+    pub fn new(config: &PostProcessConfig) -> Self {
+        let channels = config
+            .overrides
+            .iter()
+            .map(|(channel, o)| {
+                let filters = o.filters.as_deref().unwrap_or(&config.filters);
+                let max_chars = o.max_reply_chars.unwrap_or(config.max_reply_chars);
+                (channel.clone(), build_filters(filters, max_chars))
+            })
+            .collect();
+        Self {
+            default: build_filters(&config.filters, config.max_reply_chars),
+            channels,
+        }
+    }
+
+    /// 处理发往指定通道的回复
+    pub fn process(&self, channel: &str, text: String) -> String {
+        self.channels
+            .get(channel)
+            .unwrap_or(&self.default)
+            .iter()
+            .fold(text, |text, filter| filter.apply(text))
+    }
+}
+
+fn build_filters(kinds: &[PostFilterKind], max_chars: usize) -> Vec<Arc<dyn ReplyFilter>> {
+    kinds
+        .iter()
+        .filter_map(|kind| -> Option<Arc<dyn ReplyFilter>> {
+            Some(match kind {
+                PostFilterKind::StripThink => Arc::new(StripThink),
+                PostFilterKind::TrimWhitespace => Arc::new(TrimWhitespace),
+                PostFilterKind::CollapseBlankLines => Arc::new(CollapseBlankLines),
+                PostFilterKind::Truncate if max_chars == 0 => return None,
+                PostFilterKind::Truncate => Arc::new(Truncate { max_chars }),
+                PostFilterKind::LinkUrls => Arc::new(LinkUrls),
+            })
+        })
+        .collect()
+}
+
+/// 逐行处理，代码块（``` 围起的部分）内的行原样保留
+fn map_prose_lines(text: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut in_code = false;
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                return line.to_string();
+            }
+            if in_code {
+                line.to_string()
+            } else {
+                f(line)
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// 去掉 `<think>…</think>` 块
+///
+/// 未闭合的 `<think>` 丢弃其后的全部内容；只有 `</think>`（部分模型省略开始标签）时丢弃其前的内容
+pub struct StripThink;
+
+impl ReplyFilter for StripThink {
+    fn apply(&self, text: String) -> String {
+        static THINK: OnceLock<Regex> = OnceLock::new();
+        if !text.contains("think>") {
+            return text;
+        }
+        let re = THINK.get_or_init(|| Regex::new(r"(?s)<think>.*?</think>").unwrap());
+        let mut text = re.replace_all(&text, "").into_owned();
+        if let Some(pos) = text.find("<think>") {
+            text.truncate(pos);
+        }
+        if let Some(pos) = text.rfind("</think>") {
+            text.drain(..pos + "</think>".len());
+        }
+        text.trim_start().to_string()
+    }
+}
+
+/// 去掉行尾空白和首尾空行
+pub struct TrimWhitespace;
+
+impl ReplyFilter for TrimWhitespace {
+    fn apply(&self, text: String) -> String {
+        let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+        lines.join("\n").trim_matches('\n').to_string()
+    }
+}
+
+/// 连续多个空行合并为一个（代码块内不处理）
+pub struct CollapseBlankLines;
+
+impl ReplyFilter for CollapseBlankLines {
+    fn apply(&self, text: String) -> String {
+        let mut out: Vec<&str> = Vec::new();
+        let mut in_code = false;
+        for line in text.split('\n') {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            let blank = line.trim().is_empty();
+            if !in_code && blank && out.last().is_some_and(|l| l.trim().is_empty()) {
+                continue;
+            }
+            out.push(line);
+        }
+        out.join("\n")
+    }
+}
+
+/// 超过字符上限时截断，并注明已截断（截断处在代码块中时补上结束标记）
+pub struct Truncate {
+    pub max_chars: usize,
+}
+
+impl ReplyFilter for Truncate {
+    fn apply(&self, text: String) -> String {
+        if text.chars().count() <= self.max_chars {
+            return text;
+        }
+        let suffix = t!("postprocess.truncated");
+        let keep = self.max_chars.saturating_sub(suffix.chars().count() + 5);
+        let mut out: String = text.chars().take(keep).collect::<String>().trim_end().to_string();
+        let fences = out.lines().filter(|l| l.trim_start().starts_with("```")).count();
+        if fences % 2 == 1 {
+            out.push_str("\n```");
+        }
+        out.push('\n');
+        out.push_str(&suffix);
+        out
+    }
+}
+
+/// 把裸 URL 转为 `[域名](URL)` 形式的 Markdown 链接
+///
+/// 已在链接、尖括号、引号或行内代码中的 URL 以及代码块内的内容不处理
+pub struct LinkUrls;
+
+impl ReplyFilter for LinkUrls {
+    fn apply(&self, text: String) -> String {
+        static URL: OnceLock<Regex> = OnceLock::new();
+        let re = URL.get_or_init(|| Regex::new(r"https?://[A-Za-z0-9\-._~:/?#@!$&*+,;=%]+").unwrap());
+        map_prose_lines(&text, |line| {
+            let mut out = String::with_capacity(line.len());
+            let mut last = 0;
+            for m in re.find_iter(line) {
+                let before = &line[..m.start()];
+                let quoted = before.ends_with(['(', '[', '<', '"', '\'', '='])
+                    || before.matches('`').count() % 2 == 1;
+                let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '。', '，', '；', '：', '！', '？']);
+                let Some(host) = url_host(url).filter(|_| !quoted) else {
+                    continue;
+                };
+                out.push_str(&line[last..m.start()]);
+                out.push_str(&format!("[{}]({})", host, url));
+                last = m.start() + url.len();
+            }
+            out.push_str(&line[last..]);
+            out
+        })
+    }
+}
+
+/// URL 的主机名（去掉 www. 前缀）
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PostProcessOverride;
+
+    #[test]
+    fn test_default_pipeline() {
+        let processor = PostProcessor::new(&PostProcessConfig::default());
+        let reply = "<think>先想一想\n\n用户要什么</think>\n\n答案如下：  \n\n\n\n1. 第一点\n\n```\na\n\n\n\nb\n```\n\n";
+        assert_eq!(
+            processor.process("telegram", reply.to_string()),
+            "答案如下：\n\n1. 第一点\n\n```\na\n\n\n\nb\n```"
+        );
+    }
+
+    #[test]
+    fn test_strip_think_unbalanced() {
+        assert_eq!(StripThink.apply("推理过程</think>回答".to_string()), "回答");
+        assert_eq!(StripThink.apply("回答<think>没想完".to_string()), "回答");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let filter = Truncate { max_chars: 40 };
+        assert_eq!(filter.apply("短回复".to_string()), "短回复");
+        let out = filter.apply(format!("```\n{}\n```", "x".repeat(100)));
+        assert!(out.chars().count() <= 40, "{}", out);
+        assert!(out.ends_with(&t!("postprocess.truncated")));
+        assert_eq!(out.matches("```").count(), 2);
+    }
+
+    #[test]
+    fn test_link_urls() {
+        let out = LinkUrls.apply(
+            "见 https://www.rust-lang.org/learn。另见 [文档](https://docs.rs) 和 `https://x.io`\n```\nhttps://example.com\n```"
+                .to_string(),
+        );
+        assert_eq!(
+            out,
+            "见 [rust-lang.org](https://www.rust-lang.org/learn)。另见 [文档](https://docs.rs) 和 `https://x.io`\n```\nhttps://example.com\n```"
+        );
+    }
+
+    #[test]
+    fn test_channel_overrides() {
+        let mut config = PostProcessConfig::default();
+        config.overrides.insert(
+            "discord".to_string(),
+            PostProcessOverride {
+                filters: Some(vec![PostFilterKind::LinkUrls]),
+                max_reply_chars: None,
+            },
+        );
+        let processor = PostProcessor::new(&config);
+        assert_eq!(processor.process("discord", "  https://a.com  ".to_string()), "  [a.com](https://a.com)  ");
+        assert_eq!(processor.process("telegram", "  https://a.com  ".to_string()), "  https://a.com");
+    }
+}
@@ -0,0 +1,180 @@
+//! 免打扰时段
+//!
+//! 各通道可配置 `[channel.<通道>.quiet_hours]`，时段内的主动消息（定时提醒等）不立即发送，
+//! 而是经发件箱排队到时段结束后投递。对用户消息的直接回复不经过这里，不受影响
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::outbox::Outbox;
+use super::{Channel, ChannelTarget};
+use crate::config::{Config, QuietHoursConfig};
+use crate::crash;
+use crate::cron::reminder::parse_timezone;
+
+/// 支持免打扰时段的通道
+const CHANNELS: [&str; 4] = ["telegram", "discord", "feishu", "whatsapp"];
+
+/// 单个通道的免打扰时段
+#[derive(Debug, Clone)]
+pub struct QuietWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    offset: FixedOffset,
+}
+
+impl QuietWindow {
+    /// 解析配置，未设置时区时使用 `default_timezone`
+    pub fn parse(config: &QuietHoursConfig, default_timezone: Option<&str>) -> Result<Self> {
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| anyhow!("时间格式应为 HH:MM: {}", s))
+        };
+        Ok(Self {
+            start: time(&config.start)?,
+            end: time(&config.end)?,
+            offset: parse_timezone(config.timezone.as_deref().or(default_timezone)),
+        })
+    }
+
+    /// 处于时段内时返回时段结束的时间
+    pub fn deferred_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now.with_timezone(&self.offset);
+        let (date, time) = (local.date_naive(), local.time());
+        let end_date = if self.start < self.end {
+            (self.start..self.end).contains(&time).then_some(date)?
+        } else if self.start > self.end {
+            // 跨夜：开始时间之后结束于次日
+            if time >= self.start {
+                date.succ_opt()?
+            } else if time < self.end {
+                date
+            } else {
+                return None;
+            }
+        } else {
+            return None;
+        };
+        let end = self.offset.from_local_datetime(&end_date.and_time(self.end)).single()?;
+        Some(end.with_timezone(&Utc))
+    }
+}
+
+/// 各通道的免打扰时段
+#[derive(Debug, Clone, Default)]
+pub struct QuietHours {
+    windows: HashMap<String, QuietWindow>,
+}
+
+impl QuietHours {
+    /// 从配置加载，格式错误的时段记录警告后忽略
+    pub fn from_config(config: &Config) -> Self {
+        let mut windows = HashMap::new();
+        for channel in CHANNELS {
+            let Some(quiet) = config.channel.quiet_hours(channel) else {
+                continue;
+            };
+            match QuietWindow::parse(quiet, config.agent.timezone.as_deref()) {
+                Ok(window) => {
+                    info!("通道 {} 免打扰时段: {} - {}", channel, quiet.start, quiet.end);
+                    windows.insert(channel.to_string(), window);
+                }
+                Err(e) => warn!("通道 {} 免打扰时段配置无效: {}", channel, e),
+            }
+        }
+        Self { windows }
+    }
+
+    /// 通道处于免打扰时段时返回时段结束的时间
+    pub fn deferred_until(&self, channel: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.windows.get(channel)?.deferred_until(now)
+    }
+}
+
+/// 发送主动消息，通道处于免打扰时段时排队到时段结束后投递
+///
+/// 未启用发件箱时在内存中等待，进程重启会丢失
+pub async fn send_proactive(
+    quiet: &QuietHours,
+    outbox: Option<&Arc<Outbox>>,
+    channel: Arc<dyn Channel>,
+    target: &ChannelTarget,
+    content: &str,
+) -> Result<()> {
+    channel.check_target(target)?;
+    let now = Utc::now();
+    let Some(until) = quiet.deferred_until(channel.name(), now) else {
+        return channel.send_message(target, content).await;
+    };
+    info!(
+        "通道 {} 处于免打扰时段，消息将在 {} 后发送",
+        channel.name(),
+        until.with_timezone(&chrono::Local).format("%m-%d %H:%M")
+    );
+
+    if let Some(outbox) = outbox {
+        match outbox.enqueue_at(channel.name(), target, content, until).await {
+            Ok(_) => return Ok(()),
+            Err(e) => warn!("消息入队失败，在内存中等待: {}", e),
+        }
+    }
+    let (target, content) = (target.clone(), content.to_string());
+    let wait = (until - now).max(Duration::zero()).to_std().unwrap_or_default();
+    crash::spawn("quiet", async move {
+        tokio::time::sleep(wait).await;
+        if let Err(e) = channel.send_message(&target, &content).await {
+            warn!("发送免打扰时段后的消息失败: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn window(start: &str, end: &str) -> QuietWindow {
+        let config = QuietHoursConfig {
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: Some("+08:00".to_string()),
+        };
+        QuietWindow::parse(&config, None).unwrap()
+    }
+
+    #[test]
+    fn test_quiet_window() {
+        // 跨夜
+        let night = window("22:00", "08:00");
+        assert_eq!(
+            night.deferred_until(at("2024-05-15T23:30:00+08:00")),
+            Some(at("2024-05-16T08:00:00+08:00"))
+        );
+        assert_eq!(
+            night.deferred_until(at("2024-05-16T07:59:00+08:00")),
+            Some(at("2024-05-16T08:00:00+08:00"))
+        );
+        assert_eq!(night.deferred_until(at("2024-05-16T08:00:00+08:00")), None);
+        assert_eq!(night.deferred_until(at("2024-05-16T12:00:00+08:00")), None);
+
+        // 同一天内
+        let noon = window("12:00", "14:00");
+        assert_eq!(
+            noon.deferred_until(at("2024-05-16T05:30:00Z")),
+            Some(at("2024-05-16T14:00:00+08:00"))
+        );
+        assert_eq!(noon.deferred_until(at("2024-05-16T15:00:00+08:00")), None);
+
+        assert!(QuietWindow::parse(
+            &QuietHoursConfig { start: "25:00".to_string(), end: "08:00".to_string(), timezone: None },
+            None
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! 消息发送目标
+//!
+//! 主动发送（回复、广播、提醒、message 工具、发件箱重试）使用 [`ChannelTarget`] 说明发给谁，
+//! 各通道在发送前把它转换为平台的 ID 并校验，不支持的目标类型或格式错误的 ID 直接报错，
+//! 而不是发到错误的地方或在发件箱中反复重试。
+//!
+//! 配置、API 和发件箱中使用文本形式：
+//! `<chat_id>`、`user:<id>`、`<chat_id>#thread:<thread_id>`、`<chat_id>#<message_id>`（回复某条消息）、
+//! `tel:<号码>`、`mailto:<邮箱>`
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// 消息发送目标
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChannelTarget {
+    /// 用户（私聊）
+    UserId(String),
+    /// 聊天、群组或频道
+    ChatId(String),
+    /// 聊天中的话题/子区
+    Thread { chat: String, thread: String },
+    /// 在聊天中回复某条消息
+    Reply { chat: String, message: String },
+    /// 手机号
+    Phone(String),
+    /// 邮箱
+    Email(String),
+}
+
+impl ChannelTarget {
+    /// 聊天目标（如收到消息的聊天 ID）
+    pub fn chat(id: impl Into<String>) -> Self {
+        Self::ChatId(id.into())
+    }
+
+    /// 解析文本形式的目标
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let non_empty = |kind: &str, value: &str| -> Result<String> {
+            if value.is_empty() {
+                bail!("{}不能为空: {}", kind, text);
+            }
+            Ok(value.to_string())
+        };
+        if text.is_empty() {
+            bail!("发送目标不能为空");
+        }
+        if let Some(id) = text.strip_prefix("user:") {
+            return Ok(Self::UserId(non_empty("用户 ID", id)?));
+        }
+        if let Some(number) = text.strip_prefix("tel:") {
+            return Ok(Self::Phone(non_empty("手机号", number)?));
+        }
+        if let Some(email) = text.strip_prefix("mailto:") {
+            if !email.contains('@') {
+                bail!("邮箱格式无效: {}", email);
+            }
+            return Ok(Self::Email(email.to_string()));
+        }
+        match text.split_once('#') {
+            Some((chat, rest)) => {
+                let chat = non_empty("聊天 ID", chat)?;
+                match rest.strip_prefix("thread:") {
+                    Some(thread) => Ok(Self::Thread {
+                        chat,
+                        thread: non_empty("话题 ID", thread)?,
+                    }),
+                    None => Ok(Self::Reply {
+                        chat,
+                        message: non_empty("消息 ID", rest)?,
+                    }),
+                }
+            }
+            None => Ok(Self::ChatId(text.to_string())),
+        }
+    }
+
+    /// 目标类型说明（用于错误信息）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UserId(_) => "用户",
+            Self::ChatId(_) => "聊天",
+            Self::Thread { .. } => "话题",
+            Self::Reply { .. } => "消息回复",
+            Self::Phone(_) => "手机号",
+            Self::Email(_) => "邮箱",
+        }
+    }
+
+    /// 通道不支持该类型的目标
+    pub fn unsupported(&self, channel: &str) -> anyhow::Error {
+        anyhow::anyhow!("{} 不支持发送到{}: {}", channel, self.kind(), self)
+    }
+}
+
+impl fmt::Display for ChannelTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserId(id) => write!(f, "user:{}", id),
+            Self::ChatId(id) => write!(f, "{}", id),
+            Self::Thread { chat, thread } => write!(f, "{}#thread:{}", chat, thread),
+            Self::Reply { chat, message } => write!(f, "{}#{}", chat, message),
+            Self::Phone(number) => write!(f, "tel:{}", number),
+            Self::Email(email) => write!(f, "mailto:{}", email),
+        }
+    }
+}
+
+impl FromStr for ChannelTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for ChannelTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChannelTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trip() {
+        let cases = [
+            ("-1001234", ChannelTarget::chat("-1001234")),
+            ("user:ou_abc", ChannelTarget::UserId("ou_abc".to_string())),
+            (
+                "oc_group#thread:om_1",
+                ChannelTarget::Thread {
+                    chat: "oc_group".to_string(),
+                    thread: "om_1".to_string(),
+                },
+            ),
+            (
+                "oc_group#om_2",
+                ChannelTarget::Reply {
+                    chat: "oc_group".to_string(),
+                    message: "om_2".to_string(),
+                },
+            ),
+            ("tel:+8613800000000", ChannelTarget::Phone("+8613800000000".to_string())),
+            ("mailto:a@example.com", ChannelTarget::Email("a@example.com".to_string())),
+        ];
+        for (text, target) in cases {
+            assert_eq!(ChannelTarget::parse(text).unwrap(), target);
+            assert_eq!(target.to_string(), text);
+        }
+
+        assert!(ChannelTarget::parse("").is_err());
+        assert!(ChannelTarget::parse("user:").is_err());
+        assert!(ChannelTarget::parse("#thread:1").is_err());
+        assert!(ChannelTarget::parse("mailto:nobody").is_err());
+    }
+}
@@ -4,16 +4,24 @@
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::sync::Arc;
 use teloxide::dispatching::{HandlerExt, UpdateFilterExt};
 use teloxide::prelude::*;
-use teloxide::types::{Message, ParseMode, Update};
+use teloxide::types::{
+    BotCommandScope, CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ParseMode,
+    Recipient, Update, UpdateKind,
+};
 use teloxide::utils::command::BotCommands;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
-use crate::channel::Channel;
+use crate::channel::dedupe::DedupeStore;
+use crate::channel::outbox::{self, Outbox};
+use crate::channel::handler::is_cancel_request;
+use crate::channel::{Channel, ChannelCommand, ChannelTarget, InboundMessage, MessageHandler, QuotedMessage};
 use crate::config::TelegramConfig;
+use crate::t;
 
 /// Telegram Bot 命令
 #[derive(BotCommands, Clone, Debug)]
@@ -27,20 +35,78 @@ enum Command {
     Clear,
     #[command(description = "查看当前状态")]
     Status,
+    #[command(description = "置顶内容（不带参数时置顶上一条回复）")]
+    Pin(String),
+    #[command(description = "按序号取消置顶")]
+    Unpin(String),
+    #[command(description = "查看置顶内容")]
+    Pins,
+    #[command(description = "设置本会话指令（不带参数时清除）")]
+    Instruct(String),
+    #[command(description = "取消正在进行的回复")]
+    Cancel,
+    #[command(description = "开启/关闭无痕模式（on/off，不带参数时切换）")]
+    Incognito(String),
+    #[command(description = "回复后显示工具耗时、令牌数和模型（on/off，不带参数时切换）")]
+    Verbose(String),
+    #[command(description = "查看或切换角色（default 恢复默认）")]
+    Persona(String),
+    #[command(description = "深度调研（多轮搜索后生成带引用的报告）")]
+    Research(String),
+    #[command(description = "多次采样后给出最一致的回答（/bestof 3 问题）")]
+    Bestof(String),
+    #[command(description = "查看或设置会话标题")]
+    Title(String),
+    #[command(description = "回顾某天的日记和对话（/onthisday 上周二 我在做什么）")]
+    Onthisday(String),
 }
 
+/// 管理员命令（仅 `admin_users` 可用，只在管理员的私聊中显示）
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase", description = "管理员命令:")]
+enum AdminCommand {
+    #[command(description = "查看或切换模型（default 恢复默认）")]
+    Model(String),
+    #[command(description = "查看或切换提供商")]
+    Provider(String),
+    #[command(description = "列出最近的会话")]
+    Sessions,
+    #[command(description = "查看令牌用量")]
+    Usage,
+    #[command(description = "列出定时任务（/jobs --all 包括已完成的）")]
+    Jobs(String),
+    #[command(description = "暂停、恢复或删除定时任务（/job pause <ID>）")]
+    Job(String),
+    #[command(description = "查看、批准或拒绝待审核的记忆（/memories approve <序号|all>）")]
+    Memories(String),
+    #[command(description = "向所有 Telegram 会话广播消息")]
+    Broadcast(String),
+}
+
+/// 列表每页条数
+const PAGE_SIZE: usize = 10;
+/// 翻页按钮回调数据前缀（page:<列表>:<页码>）
+const PAGE_CALLBACK_PREFIX: &str = "page:";
+
 /// Telegram 通道
 pub struct TelegramChannel {
     config: TelegramConfig,
     bot: Bot,
-    agent: Arc<crate::agent::Agent>,
+    /// 入站消息处理器
+    handler: Arc<dyn MessageHandler>,
     running: RwLock<bool>,
+    /// 发件箱（启用时回复经发件箱投递）
+    outbox: Option<Arc<Outbox>>,
+    /// 入站消息去重
+    dedupe: Option<Arc<DedupeStore>>,
+    /// 各聊天最后一条用户消息的 ID（用于判断编辑的是否为最后一条）
+    last_message_ids: std::sync::Mutex<HashMap<i64, i32>>,
 }
 
 impl TelegramChannel {
     pub fn new(
         config: TelegramConfig,
-        agent: Arc<crate::agent::Agent>,
+        handler: Arc<dyn MessageHandler>,
     ) -> Result<Self> {
         let token = config.bot_token.as_ref()
             .ok_or_else(|| anyhow!("Telegram Bot Token 未配置"))?;
@@ -50,11 +116,26 @@ impl TelegramChannel {
         Ok(Self {
             config,
             bot,
-            agent,
+            handler,
             running: RwLock::new(false),
+            outbox: None,
+            dedupe: None,
+            last_message_ids: std::sync::Mutex::new(HashMap::new()),
         })
     }
 
+    /// 设置发件箱
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// 设置入站消息去重
+    pub fn with_dedupe(mut self, dedupe: Option<Arc<DedupeStore>>) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
     /// 检查用户是否有权限
     fn is_allowed(&self,
         user_id: i64,
@@ -65,6 +146,11 @@ impl TelegramChannel {
         self.config.allowed_users.contains(&user_id)
     }
 
+    /// 检查用户是否为管理员（未配置时没有管理员）
+    fn is_admin(&self, user_id: i64) -> bool {
+        self.config.admin_users.contains(&user_id)
+    }
+
     /// 处理命令
     async fn handle_command(
         &self,
@@ -74,38 +160,53 @@ impl TelegramChannel {
     ) -> Result<()> {
         let text = match cmd {
             Command::Help => {
-                "🤖 *Nanobot 帮助*\n\n\
-                    可用命令:\n\
-                    /help - 显示此帮助\n\
-                    /start - 开始对话\n\
-                    /clear - 清空对话上下文\n\
-                    /status - 查看状态\n\n\
-                    直接发送消息即可与 AI 对话。".to_string()
+                t!("telegram.help") + &Self::admin_help(&msg, self)
             }
-            Command::Start => {
-                "👋 你好！我是 Nanobot，你的个人 AI 助手。\n\n直接发送消息即可开始对话。".to_string()
+            Command::Start => t!("telegram.start"),
+            Command::Clear => self.run_command(&msg, ChannelCommand::Clear).await,
+            Command::Status => self.run_command(&msg, ChannelCommand::Status).await,
+            Command::Pin(content) => {
+                let content = Some(content).filter(|c| !c.trim().is_empty());
+                self.run_command(&msg, ChannelCommand::Pin(content)).await
             }
-            Command::Clear => {
-                self.agent.clear_context().await;
-                "🧹 对话上下文已清空。".to_string()
+            Command::Unpin(index) => match index.trim().parse::<usize>() {
+                Ok(i) => self.run_command(&msg, ChannelCommand::Unpin(i)).await,
+                Err(_) => Self::escape_markdown(&t!("telegram.unpin_usage")),
+            },
+            Command::Pins => self.run_command(&msg, ChannelCommand::Pins).await,
+            Command::Instruct(text) => self.run_command(&msg, ChannelCommand::Instruct(text)).await,
+            Command::Cancel => self.run_command(&msg, ChannelCommand::Cancel).await,
+            Command::Incognito(mode) => {
+                match ChannelCommand::parse_incognito(&format!("/incognito {}", mode)) {
+                    Some(cmd) => self.run_command(&msg, cmd).await,
+                    None => Self::escape_markdown(&t!("telegram.incognito_usage")),
+                }
+            }
+            Command::Verbose(mode) => match ChannelCommand::parse_verbose(&format!("/verbose {}", mode)) {
+                Some(cmd) => self.run_command(&msg, cmd).await,
+                None => Self::escape_markdown(&t!("cmd.verbose_usage")),
+            },
+            Command::Persona(name) => {
+                let name = Some(name.trim().to_string()).filter(|n| !n.is_empty());
+                self.run_command(&msg, ChannelCommand::Persona(name)).await
+            }
+            Command::Research(question) => self.run_command(&msg, ChannelCommand::Research(question)).await,
+            Command::Bestof(args) => match ChannelCommand::parse_best_of(&format!("/bestof {}", args)) {
+                Some(cmd) => self.run_command(&msg, cmd).await,
+                None => Self::escape_markdown(&t!("cmd.bestof_usage")),
+            },
+            Command::Title(title) => {
+                let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+                self.run_command(&msg, ChannelCommand::Title(title)).await
             }
-            Command::Status => {
-                let ctx_len = self.agent.context_length().await;
-                let session_id = self.agent.session_id().await;
-                format!(
-                    "📊 *状态信息*\n\n\
-                    会话 ID: `{}`\n\
-                    上下文消息数: {}\n\
-                    提供商: {}\n\
-                    模型: {}",
-                    session_id,
-                    ctx_len,
-                    "deepseek",
-                    "deepseek-chat"
-                )
+            Command::Onthisday(args) => {
+                self.run_command(&msg, ChannelCommand::OnThisDay(args.trim().to_string())).await
             }
         };
 
+        if text.is_empty() {
+            return Ok(());
+        }
         bot.send_message(msg.chat.id, text)
             .parse_mode(ParseMode::MarkdownV2)
             .await?;
@@ -113,6 +214,263 @@ impl TelegramChannel {
         Ok(())
     }
 
+    /// 管理员的帮助中附加管理命令说明（已转义）
+    fn admin_help(msg: &Message, channel: &Self) -> String {
+        let user_id = msg.from().map(|u| u.id.0 as i64).unwrap_or(0);
+        if !channel.is_admin(user_id) {
+            return String::new();
+        }
+        format!("\n\n{}", Self::escape_markdown(&t!("telegram.admin_help")))
+    }
+
+    /// 处理管理员命令
+    async fn handle_admin_command(
+        &self,
+        bot: Bot,
+        msg: Message,
+        cmd: AdminCommand,
+    ) -> Result<()> {
+        let user_id = msg.from().map(|u| u.id.0 as i64).unwrap_or(0);
+        if !self.is_admin(user_id) {
+            warn!("非管理员用户 {} 尝试使用管理命令", user_id);
+            bot.send_message(msg.chat.id, t!("telegram.admin_only")).await?;
+            return Ok(());
+        }
+
+        let text = match cmd {
+            AdminCommand::Model(spec) => {
+                let spec = match spec.trim() {
+                    "" => None,
+                    "default" | "reset" | "默认" => Some(String::new()),
+                    spec => Some(spec.to_string()),
+                };
+                self.run_command(&msg, ChannelCommand::Model(spec)).await
+            }
+            AdminCommand::Provider(name) => {
+                let name = Some(name.trim().to_string()).filter(|n| !n.is_empty());
+                self.run_command(&msg, ChannelCommand::Provider(name)).await
+            }
+            AdminCommand::Usage => self.run_command(&msg, ChannelCommand::Usage).await,
+            AdminCommand::Sessions => return self.send_page(&bot, &msg, "sessions", 0).await,
+            AdminCommand::Jobs(args) => {
+                let kind = if args.trim() == "--all" { "jobs-all" } else { "jobs" };
+                return self.send_page(&bot, &msg, kind, 0).await;
+            }
+            AdminCommand::Job(args) => match ChannelCommand::parse_job(&format!("/job {}", args)) {
+                Some(cmd) => self.run_command(&msg, cmd).await,
+                None => Self::escape_markdown(&t!("cmd.job_usage")),
+            },
+            AdminCommand::Memories(args) => match ChannelCommand::parse_memories(&format!("/memories {}", args)) {
+                Some(cmd) => self.run_command(&msg, cmd).await,
+                None => Self::escape_markdown(&t!("memory_review.usage")),
+            },
+            AdminCommand::Broadcast(content) => {
+                Self::escape_markdown(&self.broadcast(content.trim()).await)
+            }
+        };
+
+        bot.send_message(msg.chat.id, text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .await?;
+        Ok(())
+    }
+
+    /// 把发送目标转换为聊天 ID、话题 ID 和被回复的消息 ID
+    fn resolve_target(target: &ChannelTarget) -> Result<(ChatId, Option<i32>, Option<teloxide::types::MessageId>)> {
+        let chat_id = |id: &str| -> Result<ChatId> {
+            Ok(ChatId(id.parse().with_context(|| format!("无效的 chat ID: {}", id))?))
+        };
+        let message_id = |id: &str| -> Result<i32> {
+            id.parse().with_context(|| format!("无效的消息 ID: {}", id))
+        };
+        match target {
+            ChannelTarget::ChatId(id) | ChannelTarget::UserId(id) => Ok((chat_id(id)?, None, None)),
+            ChannelTarget::Thread { chat, thread } => Ok((chat_id(chat)?, Some(message_id(thread)?), None)),
+            ChannelTarget::Reply { chat, message } => Ok((
+                chat_id(chat)?,
+                None,
+                Some(teloxide::types::MessageId(message_id(message)?)),
+            )),
+            ChannelTarget::Phone(_) | ChannelTarget::Email(_) => Err(target.unsupported("telegram")),
+        }
+    }
+
+    /// 向所有有过会话的 Telegram 聊天发送消息，返回结果说明
+    async fn broadcast(&self, content: &str) -> String {
+        if content.is_empty() {
+            return t!("telegram.broadcast_usage");
+        }
+        let chats = match self.handler.known_chats(self.name()).await {
+            Ok(chats) => chats,
+            Err(e) => return t!("telegram.broadcast_list_failed", error = e),
+        };
+
+        let mut failed = 0;
+        for chat in &chats {
+            let target = ChannelTarget::chat(chat.as_str());
+            if let Err(e) = outbox::deliver(self.outbox.as_ref(), self, &target, content).await {
+                warn!("广播到 {} 失败: {}", chat, e);
+                failed += 1;
+            }
+        }
+        info!("广播完成: {} 个会话，失败 {} 个", chats.len(), failed);
+        t!("telegram.broadcast_done", sent = chats.len() - failed, failed = failed)
+    }
+
+    /// 分页列表对应的命令
+    fn list_command(kind: &str) -> Option<ChannelCommand> {
+        match kind {
+            "sessions" => Some(ChannelCommand::Sessions),
+            "jobs" => Some(ChannelCommand::Jobs(false)),
+            "jobs-all" => Some(ChannelCommand::Jobs(true)),
+            _ => None,
+        }
+    }
+
+    /// 发送分页列表的第一页
+    async fn send_page(&self, bot: &Bot, msg: &Message, kind: &str, page: usize) -> Result<()> {
+        let cmd = Self::list_command(kind).ok_or_else(|| anyhow!("未知列表: {}", kind))?;
+        let listing = self.command_reply(&Self::inbound(msg, ""), cmd).await;
+        let (text, pages) = Self::paginate(&listing, page);
+
+        let request = bot
+            .send_message(msg.chat.id, Self::escape_markdown(&text))
+            .parse_mode(ParseMode::MarkdownV2);
+        match Self::page_keyboard(kind, page, pages) {
+            Some(keyboard) => request.reply_markup(keyboard).await?,
+            None => request.await?,
+        };
+        Ok(())
+    }
+
+    /// 处理翻页按钮
+    async fn handle_callback(&self, bot: Bot, query: CallbackQuery) -> Result<()> {
+        bot.answer_callback_query(query.id.clone()).await?;
+
+        let Some((kind, page)) = query
+            .data
+            .as_deref()
+            .and_then(|d| d.strip_prefix(PAGE_CALLBACK_PREFIX))
+            .and_then(|d| d.split_once(':'))
+        else {
+            return Ok(());
+        };
+        if !self.is_admin(query.from.id.0 as i64) {
+            return Ok(());
+        }
+        let (Some(msg), Some(cmd), Ok(page)) =
+            (query.message.as_ref(), Self::list_command(kind), page.parse::<usize>())
+        else {
+            return Ok(());
+        };
+
+        // 列表消息由 Bot 发出，命令按点击按钮的用户执行
+        let mut inbound = Self::inbound(msg, "");
+        inbound.sender = query.from.id.0.to_string();
+        let listing = self.command_reply(&inbound, cmd).await;
+        let (text, pages) = Self::paginate(&listing, page);
+        let request = bot
+            .edit_message_text(msg.chat.id, msg.id, Self::escape_markdown(&text))
+            .parse_mode(ParseMode::MarkdownV2);
+        match Self::page_keyboard(kind, page, pages) {
+            Some(keyboard) => request.reply_markup(keyboard).await?,
+            None => request.await?,
+        };
+        Ok(())
+    }
+
+    /// 把列表（首行为标题，其余每行一项）切分成页，返回第 page 页的内容和总页数
+    fn paginate(listing: &str, page: usize) -> (String, usize) {
+        let mut lines = listing.lines();
+        let header = lines.next().unwrap_or_default();
+        let items: Vec<&str> = lines.filter(|l| !l.trim().is_empty()).collect();
+        if items.is_empty() {
+            return (header.to_string(), 1);
+        }
+
+        let pages = items.len().div_ceil(PAGE_SIZE);
+        let page = page.min(pages - 1);
+        let body = items
+            .iter()
+            .skip(page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if pages > 1 {
+            (format!("{}\n\n{}", t!("telegram.page", header = header, page = page + 1, pages = pages), body), pages)
+        } else {
+            (format!("{}\n\n{}", header, body), pages)
+        }
+    }
+
+    /// 翻页按钮（只有一页时不显示）
+    fn page_keyboard(kind: &str, page: usize, pages: usize) -> Option<InlineKeyboardMarkup> {
+        if pages <= 1 {
+            return None;
+        }
+        let page = page.min(pages - 1);
+        let mut buttons = Vec::new();
+        if page > 0 {
+            buttons.push(InlineKeyboardButton::callback(
+                t!("telegram.prev_page"),
+                format!("{}{}:{}", PAGE_CALLBACK_PREFIX, kind, page - 1),
+            ));
+        }
+        if page + 1 < pages {
+            buttons.push(InlineKeyboardButton::callback(
+                t!("telegram.next_page"),
+                format!("{}{}:{}", PAGE_CALLBACK_PREFIX, kind, page + 1),
+            ));
+        }
+        Some(InlineKeyboardMarkup::new(vec![buttons]))
+    }
+
+    /// 为管理员的私聊设置包含管理命令的命令菜单
+    async fn set_admin_commands(&self, bot: &Bot) {
+        let mut commands = Command::bot_commands();
+        commands.extend(AdminCommand::bot_commands());
+        for &admin in &self.config.admin_users {
+            let scope = BotCommandScope::Chat {
+                chat_id: Recipient::Id(ChatId(admin)),
+            };
+            // 管理员尚未与 Bot 私聊时会失败
+            if let Err(e) = bot.set_my_commands(commands.clone()).scope(scope).await {
+                warn!("为管理员 {} 设置命令菜单失败: {}", admin, e);
+            }
+        }
+    }
+
+    /// 将命令交给处理器，返回已转义的回复
+    async fn run_command(&self, msg: &Message, cmd: ChannelCommand) -> String {
+        Self::escape_markdown(&self.command_reply(&Self::inbound(msg, ""), cmd).await)
+    }
+
+    /// 将命令交给处理器，返回原始回复
+    async fn command_reply(&self, inbound: &InboundMessage, cmd: ChannelCommand) -> String {
+        match self.handler.command(inbound, cmd).await {
+            Ok(reply) => reply,
+            Err(e) => t!("common.error", error = e),
+        }
+    }
+
+    /// 构造入站消息
+    fn inbound(msg: &Message, text: &str) -> InboundMessage {
+        let sender = msg.from().map(|u| u.id.0.to_string()).unwrap_or_default();
+        InboundMessage::new("telegram", msg.chat.id.0.to_string(), sender, text)
+            .with_group(msg.chat.is_group() || msg.chat.is_supergroup())
+    }
+
+    /// 用户回复的消息（文本或图片说明）
+    fn quoted(msg: &Message) -> Option<QuotedMessage> {
+        let reply = msg.reply_to_message()?;
+        let content = reply.text().or_else(|| reply.caption())?;
+        Some(QuotedMessage {
+            from_bot: reply.from().is_some_and(|u| u.is_bot),
+            content: content.to_string(),
+        })
+    }
+
     /// 处理文本消息
     async fn handle_message(
         &self,
@@ -126,39 +484,72 @@ impl TelegramChannel {
         // 检查权限
         if !self.is_allowed(user_id) {
             warn!("用户 {} 尝试访问但被拒绝", user_id);
-            bot.send_message(msg.chat.id, "⛔ 你无权使用此 Bot。")
+            bot.send_message(msg.chat.id, t!("telegram.unauthorized"))
                 .await?;
             return Ok(());
         }
 
+        // 去重（message_id 仅在同一会话内唯一）
+        if let Some(ref dedupe) = self.dedupe {
+            let key = format!("{}:{}", msg.chat.id.0, msg.id.0);
+            if !dedupe.check_and_insert("telegram", &key).await? {
+                return Ok(());
+            }
+        }
+
         // 获取消息文本
         let text = msg.text()
             .ok_or_else(|| anyhow!("消息没有文本内容"))?;
+        self.last_message_ids.lock().unwrap().insert(msg.chat.id.0, msg.id.0);
 
         // 显示"正在输入"状态
         bot.send_chat_action(msg.chat.id, teloxide::types::ChatAction::Typing)
             .await?;
 
-        // 设置会话 ID 为 telegram:chat_id，这样重启后能记住对话
-        let session_key = format!("telegram:{}", msg.chat.id.0);
-        self.agent.set_session_id(&session_key).await;
+        // 交给处理器（会话 ID 为 telegram:chat_id，这样重启后能记住对话）
+        let inbound = Self::inbound(&msg, text).with_reply_to(Self::quoted(&msg));
+        let result = self.handler.handle(inbound).await;
+        self.send_result(&bot, msg.chat.id, result).await
+    }
 
-        // 调用 Agent
-        match self.agent.chat(text).await {
+    /// 处理用户编辑过的消息：编辑最后一条时可重新生成回复
+    async fn handle_edited_message(
+        &self,
+        bot: Bot,
+        msg: Message,
+    ) -> Result<()> {
+        let user_id = msg.from().map(|u| u.id.0 as i64).unwrap_or(0);
+        if !self.is_allowed(user_id) {
+            return Ok(());
+        }
+        // 编辑后的命令不再执行
+        let Some(text) = msg.text().filter(|t| !t.starts_with('/')) else {
+            return Ok(());
+        };
+
+        let latest = self.last_message_ids.lock().unwrap().get(&msg.chat.id.0) == Some(&msg.id.0);
+        info!("用户编辑了消息 {}（{}）", msg.id.0, if latest { "最后一条" } else { "较早的消息" });
+
+        if latest {
+            bot.send_chat_action(msg.chat.id, teloxide::types::ChatAction::Typing)
+                .await?;
+        }
+        let result = self.handler.handle_edit(Self::inbound(&msg, text), latest).await;
+        self.send_result(&bot, msg.chat.id, result).await
+    }
+
+    /// 发送处理结果（回复为空时不发送）
+    async fn send_result(&self, bot: &Bot, chat_id: ChatId, result: Result<String>) -> Result<()> {
+        match result {
+            Ok(response) if response.is_empty() => {}
             Ok(response) => {
-                // 转义 Markdown 特殊字符
-                let escaped = Self::escape_markdown(&response.content);
-                
-                // 分段发送长消息
-                for chunk in Self::split_message(&escaped, 4096) {
-                    bot.send_message(msg.chat.id, chunk)
-                        .parse_mode(ParseMode::MarkdownV2)
-                        .await?;
-                }
+                // 经过发件箱与直接发送都由 send_message 转义和分段，两种情况下显示一致
+                let target = ChannelTarget::chat(chat_id.0.to_string());
+                outbox::deliver(self.outbox.as_ref(), self, &target, &response).await?;
             }
             Err(e) => {
-                error!("Agent 错误: {}", e);
-                bot.send_message(msg.chat.id, format!("❌ 错误: {}", e))
+                error!("处理消息失败: {}", e);
+                bot.send_message(chat_id, t!("common.error", error = e))
                     .await?;
             }
         }
@@ -166,6 +557,16 @@ impl TelegramChannel {
         Ok(())
     }
 
+    /// 同一聊天的消息按顺序处理，取消请求不排队，以便中止进行中的回复
+    fn distribution_key(update: &Update) -> Option<ChatId> {
+        if let UpdateKind::Message(ref msg) = update.kind {
+            if msg.text().is_some_and(is_cancel_request) {
+                return None;
+            }
+        }
+        update.chat().map(|c| c.id)
+    }
+
     /// 转义 Markdown 特殊字符
     fn escape_markdown(text: &str) -> String {
         let special_chars = ['_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!'];
@@ -181,6 +582,11 @@ impl TelegramChannel {
         result
     }
 
+    /// 转义为 MarkdownV2 并按 Telegram 的长度限制分段
+    fn markdown_chunks(text: &str) -> Vec<String> {
+        Self::split_message(&Self::escape_markdown(text), 4096)
+    }
+
     /// 分割长消息
     fn split_message(text: &str, max_len: usize) -> Vec<String> {
         if text.len() <= max_len {
@@ -217,26 +623,32 @@ impl Channel for TelegramChannel {
         info!("启动 Telegram Bot...");
 
         let bot = self.bot.clone();
-        let agent = self.agent.clone();
         let config = self.config.clone();
         let channel = Arc::new(TelegramChannel {
             config,
             bot: bot.clone(),
-            agent,
+            handler: self.handler.clone(),
             running: RwLock::new(true),
+            outbox: self.outbox.clone(),
+            dedupe: self.dedupe.clone(),
+            last_message_ids: std::sync::Mutex::new(HashMap::new()),
         });
 
-        // 设置命令
+        // 设置命令（管理员的私聊额外显示管理命令）
         bot.set_my_commands(Command::bot_commands()).await?;
+        channel.set_admin_commands(&bot).await;
 
         info!("Telegram Bot 已启动，正在监听消息...");
 
         // 为每个分支克隆 channel
         let channel_cmd = channel.clone();
+        let channel_admin = channel.clone();
         let channel_msg = channel.clone();
+        let channel_callback = channel.clone();
+        let channel_edit = channel.clone();
 
         // 启动消息处理
-        let handler = Update::filter_message()
+        let messages = Update::filter_message()
             .branch(
                 dptree::entry()
                     .filter_command::<Command>()
@@ -250,6 +662,19 @@ impl Channel for TelegramChannel {
                         }
                     }),
             )
+            .branch(
+                dptree::entry()
+                    .filter_command::<AdminCommand>()
+                    .endpoint(move |bot: Bot, msg: Message, cmd: AdminCommand| {
+                        let channel = channel_admin.clone();
+                        async move {
+                            if let Err(e) = channel.handle_admin_command(bot, msg, cmd).await {
+                                error!("处理管理命令错误: {}", e);
+                            }
+                            Ok::<(), anyhow::Error>(())
+                        }
+                    }),
+            )
             .branch(
                 dptree::endpoint(move |bot: Bot, msg: Message| {
                     let channel = channel_msg.clone();
@@ -261,8 +686,34 @@ impl Channel for TelegramChannel {
                     }
                 }),
             );
+        let callbacks = Update::filter_callback_query().endpoint(move |bot: Bot, query: CallbackQuery| {
+            let channel = channel_callback.clone();
+            async move {
+                if let Err(e) = channel.handle_callback(bot, query).await {
+                    error!("处理按钮回调错误: {}", e);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+        });
+        let edits = Update::filter_edited_message().endpoint(move |bot: Bot, msg: Message| {
+            let channel = channel_edit.clone();
+            async move {
+                if let Err(e) = channel.handle_edited_message(bot, msg).await {
+                    error!("处理编辑消息错误: {}", e);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+        });
+        let handler = dptree::entry().branch(messages).branch(callbacks).branch(edits);
 
+        // 合并连发消息时同一聊天的消息要同时进入处理器，由处理器按会话排队
+        let distribution: fn(&Update) -> Option<ChatId> = if self.config.merge_window_ms > 0 {
+            |_| None
+        } else {
+            Self::distribution_key
+        };
         Dispatcher::builder(bot, handler)
+            .distribution_function(distribution)
             .enable_ctrlc_handler()
             .build()
             .dispatch()
@@ -277,16 +728,28 @@ impl Channel for TelegramChannel {
         Ok(())
     }
 
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        Self::resolve_target(target).map(|_| ())
+    }
+
     async fn send_message(
         &self,
-        target: &str,
+        target: &ChannelTarget,
         content: &str,
     ) -> Result<()> {
-        let chat_id: i64 = target.parse()
-            .context("无效的 chat ID")?;
+        let (chat_id, thread_id, reply_to) = Self::resolve_target(target)?;
         
-        self.bot.send_message(ChatId(chat_id), content)
-            .await?;
+        // 转义 Markdown 特殊字符后分段发送（话题消息每段都发到话题内，只有第一段作为回复）
+        for (i, chunk) in Self::markdown_chunks(content).into_iter().enumerate() {
+            let mut request = self.bot.send_message(chat_id, chunk).parse_mode(ParseMode::MarkdownV2);
+            if let Some(thread_id) = thread_id {
+                request = request.message_thread_id(thread_id);
+            }
+            if let Some(message_id) = reply_to.filter(|_| i == 0) {
+                request = request.reply_to_message_id(message_id);
+            }
+            request.await?;
+        }
         
         Ok(())
     }
@@ -294,3 +757,50 @@ impl Channel for TelegramChannel {
 
 use teloxide::dispatching::Dispatcher;
 use teloxide::dptree;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<String> = (1..=25).map(|i| format!("item {}", i)).collect();
+        let listing = format!("标题\n{}", items.join("\n"));
+
+        let (text, pages) = TelegramChannel::paginate(&listing, 0);
+        assert_eq!(pages, 3);
+        assert!(text.starts_with("标题（第 1/3 页）"));
+        assert!(text.contains("item 10") && !text.contains("item 11"));
+
+        // 超出范围时显示最后一页
+        let (text, _) = TelegramChannel::paginate(&listing, 9);
+        assert!(text.contains("item 25") && !text.contains("item 20\n"));
+
+        let keyboard = TelegramChannel::page_keyboard("jobs", 1, pages).unwrap();
+        assert_eq!(keyboard.inline_keyboard[0].len(), 2);
+        assert!(TelegramChannel::page_keyboard("jobs", 0, 1).is_none());
+
+        assert_eq!(TelegramChannel::paginate("暂无会话记录", 0), ("暂无会话记录".to_string(), 1));
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let resolve = |text: &str| TelegramChannel::resolve_target(&ChannelTarget::parse(text).unwrap());
+        let (chat, thread, reply) = resolve("-1001234#thread:7").unwrap();
+        assert_eq!((chat, thread, reply), (ChatId(-1001234), Some(7), None));
+        let (_, _, reply) = resolve("42#100").unwrap();
+        assert_eq!(reply, Some(teloxide::types::MessageId(100)));
+        assert!(resolve("tel:+8613800000000").is_err());
+        assert!(resolve("alice").is_err());
+    }
+
+    #[test]
+    fn test_markdown_chunks() {
+        // 直接回复和发件箱重发都经过 send_message，使用同样的转义
+        assert_eq!(TelegramChannel::markdown_chunks("1.5 * 2 = 3!"), vec!["1\\.5 \\* 2 \\= 3\\!"]);
+        let long = "a.\n".repeat(1500);
+        let chunks = TelegramChannel::markdown_chunks(&long);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.len() <= 4096 && c.ends_with('\n')));
+    }
+}
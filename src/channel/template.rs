@@ -0,0 +1,148 @@
+//! 系统通知模板
+//!
+//! 定时提醒、任务失败、预算告警、文件监视等系统主动发出的通知统一经 [`NotificationTemplates`] 生成。
+//! 内置模板来自界面语言包（`notify.*`），可在 `[notifications.templates]` 中覆盖，
+//! 并按通道在 `[notifications.channels.<通道>]` 中再覆盖（如 WhatsApp 用 `*粗体*`）。
+//! 模板中的 `{name}` 替换为通知的参数，未定义的参数保持原样
+
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::i18n;
+
+/// 通知类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    /// 定时提醒触发，参数 {text}
+    Reminder,
+    /// 定时任务执行失败，参数 {job}、{error}
+    JobFailed,
+    /// 预算达到告警阈值，参数 {scope}、{period}、{percent}、{spent}、{limit}
+    BudgetWarning,
+    /// 预算超出，参数同 BudgetWarning
+    BudgetExceeded,
+    /// 监视目录中的文件变化，参数 {watch}、{action}、{path}、{name}
+    WatchUpdate,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 5] = [
+        Self::Reminder,
+        Self::JobFailed,
+        Self::BudgetWarning,
+        Self::BudgetExceeded,
+        Self::WatchUpdate,
+    ];
+
+    /// 配置中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reminder => "reminder",
+            Self::JobFailed => "job_failed",
+            Self::BudgetWarning => "budget_warning",
+            Self::BudgetExceeded => "budget_exceeded",
+            Self::WatchUpdate => "watch_update",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == name)
+    }
+
+    /// 语言包中内置模板的键
+    fn i18n_key(&self) -> &'static str {
+        match self {
+            Self::Reminder => "notify.reminder",
+            Self::JobFailed => "notify.job_failed",
+            Self::BudgetWarning => "notify.budget_warning",
+            Self::BudgetExceeded => "notify.budget_exceeded",
+            Self::WatchUpdate => "notify.watch_update",
+        }
+    }
+}
+
+/// 通知模板注册表
+#[derive(Debug, Clone, Default)]
+pub struct NotificationTemplates {
+    /// 覆盖内置模板
+    templates: HashMap<NotificationKind, String>,
+    /// 按通道覆盖
+    channels: HashMap<String, HashMap<NotificationKind, String>>,
+}
+
+impl NotificationTemplates {
+    /// 从 `[notifications]` 加载，未知的通知类型记录警告后忽略
+    pub fn from_config(config: &Config) -> Self {
+        let parse = |scope: &str, templates: &HashMap<String, String>| -> HashMap<NotificationKind, String> {
+            templates
+                .iter()
+                .filter_map(|(name, template)| match NotificationKind::parse(name) {
+                    Some(kind) => Some((kind, template.clone())),
+                    None => {
+                        warn!("{} 中的通知类型未知，已忽略: {}", scope, name);
+                        None
+                    }
+                })
+                .collect()
+        };
+        let notifications = &config.notifications;
+        Self {
+            templates: parse("notifications.templates", &notifications.templates),
+            channels: notifications
+                .channels
+                .iter()
+                .map(|(channel, templates)| {
+                    (channel.clone(), parse(&format!("notifications.channels.{}", channel), templates))
+                })
+                .collect(),
+        }
+    }
+
+    /// 生成发往某个通道的通知（通道模板 → 全局模板 → 内置模板）
+    pub fn render(&self, channel: &str, kind: NotificationKind, args: &[(&str, String)]) -> String {
+        let template = self
+            .channels
+            .get(channel)
+            .and_then(|t| t.get(&kind))
+            .or_else(|| self.templates.get(&kind))
+            .map(String::as_str)
+            .unwrap_or_else(|| i18n::lookup(i18n::language(), kind.i18n_key()));
+        i18n::format(template, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_overrides() {
+        let mut config = Config::default();
+        config
+            .notifications
+            .templates
+            .insert("reminder".to_string(), "🔔 {text}".to_string());
+        config.notifications.channels.insert(
+            "whatsapp".to_string(),
+            HashMap::from([
+                ("reminder".to_string(), "*提醒* {text}".to_string()),
+                ("unknown".to_string(), "x".to_string()),
+            ]),
+        );
+        let templates = NotificationTemplates::from_config(&config);
+        let args = [("text", "开会".to_string())];
+
+        assert_eq!(templates.render("telegram", NotificationKind::Reminder, &args), "🔔 开会");
+        assert_eq!(templates.render("whatsapp", NotificationKind::Reminder, &args), "*提醒* 开会");
+
+        // 未覆盖的类型使用内置模板
+        let failed = templates.render(
+            "whatsapp",
+            NotificationKind::JobFailed,
+            &[("job", "备份".to_string()), ("error", "磁盘已满".to_string())],
+        );
+        assert!(failed.contains("磁盘已满"));
+        assert!(!failed.contains('{'));
+    }
+}
@@ -0,0 +1,151 @@
+//! 唤醒词
+//!
+//! 各通道可配置 `[channel.<通道>.wake_words]`，启用后（默认只在群聊中）只有以唤醒词开头的消息
+//! 才交给 Agent，唤醒词本身会被去掉；唤醒词后跟停止词（如 "nanobot stop"）时中止进行中的回复。
+//! 由 [`AgentHandler`](super::handler::AgentHandler) 在交给 Agent 之前检查，机器人可以待在热闹的群里而不必每条都回
+
+use std::collections::HashMap;
+use tracing::info;
+
+use super::handler::InboundMessage;
+use crate::config::{Config, WakeWordConfig};
+
+/// 支持唤醒词的通道
+const CHANNELS: [&str; 4] = ["telegram", "discord", "feishu", "whatsapp"];
+
+/// 唤醒词与后面内容之间的分隔符（空白之外）
+const SEPARATORS: &[char] = &[',', '，', ':', '：', '、', '!', '！', '.', '。', '?', '？', '~', '～'];
+
+/// 唤醒词检查结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum WakeCheck {
+    /// 通道未启用唤醒词或不需要唤醒词（如私聊），照常处理
+    Pass,
+    /// 以唤醒词开头，去掉唤醒词后的文本
+    Wake(String),
+    /// 唤醒词后跟停止词
+    Stop,
+    /// 没有唤醒词，忽略
+    Ignore,
+}
+
+/// 单个通道的唤醒词
+#[derive(Debug, Clone)]
+struct ChannelWake {
+    phrases: Vec<String>,
+    stop_phrases: Vec<String>,
+    groups_only: bool,
+}
+
+impl ChannelWake {
+    fn new(config: &WakeWordConfig) -> Self {
+        let clean = |phrases: &[String]| {
+            phrases
+                .iter()
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        };
+        Self {
+            phrases: clean(&config.phrases),
+            stop_phrases: clean(&config.stop_phrases),
+            groups_only: config.groups_only,
+        }
+    }
+
+    fn check(&self, text: &str, is_group: bool) -> WakeCheck {
+        if self.groups_only && !is_group {
+            return WakeCheck::Pass;
+        }
+        let text = text.trim();
+        let Some(rest) = self.phrases.iter().find_map(|p| strip_phrase(text, p)) else {
+            return WakeCheck::Ignore;
+        };
+
+        let is_separator = |c: char| c.is_whitespace() || SEPARATORS.contains(&c);
+        let rest = rest.trim_start_matches(is_separator);
+        let command = rest.trim_end_matches(is_separator);
+        if self.stop_phrases.iter().any(|s| strip_phrase(command, s) == Some("")) {
+            WakeCheck::Stop
+        } else if rest.is_empty() {
+            // 只喊了唤醒词，原样交给 Agent 打招呼
+            WakeCheck::Wake(text.to_string())
+        } else {
+            WakeCheck::Wake(rest.to_string())
+        }
+    }
+}
+
+/// 不区分大小写地去掉开头的短语；英文短语要求完整单词（"nanobot" 不匹配 "nanobots"）
+fn strip_phrase<'a>(text: &'a str, phrase: &str) -> Option<&'a str> {
+    let mut chars = text.chars();
+    for expected in phrase.chars() {
+        let c = chars.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    let rest = chars.as_str();
+    let ends_word = phrase.chars().last().is_some_and(|c| c.is_ascii_alphanumeric());
+    if ends_word && rest.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(rest)
+}
+
+/// 各通道的唤醒词
+#[derive(Debug, Clone, Default)]
+pub struct WakeWords {
+    channels: HashMap<String, ChannelWake>,
+}
+
+impl WakeWords {
+    /// 从配置加载，没有有效唤醒词的通道不启用
+    pub fn from_config(config: &Config) -> Self {
+        let mut channels = HashMap::new();
+        for channel in CHANNELS {
+            let Some(wake) = config.channel.wake_words(channel).map(ChannelWake::new) else {
+                continue;
+            };
+            if wake.phrases.is_empty() {
+                continue;
+            }
+            info!("通道 {} 启用唤醒词: {}", channel, wake.phrases.join(", "));
+            channels.insert(channel.to_string(), wake);
+        }
+        Self { channels }
+    }
+
+    /// 检查消息是否需要处理
+    pub fn check(&self, msg: &InboundMessage) -> WakeCheck {
+        match self.channels.get(&msg.channel) {
+            Some(wake) => wake.check(&msg.content, msg.is_group),
+            None => WakeCheck::Pass,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wake_check() {
+        let wake = ChannelWake::new(&WakeWordConfig {
+            phrases: vec!["nanobot".to_string(), "小纳".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(wake.check("Nanobot, what's the weather?", true), WakeCheck::Wake("what's the weather?".to_string()));
+        assert_eq!(wake.check("小纳，明天天气怎么样", true), WakeCheck::Wake("明天天气怎么样".to_string()));
+        assert_eq!(wake.check("nanobot stop", true), WakeCheck::Stop);
+        assert_eq!(wake.check("小纳 停！", true), WakeCheck::Stop);
+        assert_eq!(wake.check("nanobot", true), WakeCheck::Wake("nanobot".to_string()));
+        // 停止词只在唤醒词之后生效
+        assert_eq!(wake.check("stop", true), WakeCheck::Ignore);
+        assert_eq!(wake.check("nanobots are cool", true), WakeCheck::Ignore);
+        assert_eq!(wake.check("今天吃什么", true), WakeCheck::Ignore);
+        // 私聊不需要唤醒词
+        assert_eq!(wake.check("今天吃什么", false), WakeCheck::Pass);
+    }
+}
@@ -5,6 +5,7 @@
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use futures_util::stream::FuturesUnordered;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -13,7 +14,9 @@ use tokio::sync::RwLock;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tracing::{error, info, warn};
 
-use crate::channel::Channel;
+use crate::channel::dedupe::DedupeStore;
+use crate::channel::outbox::{self, Outbox};
+use crate::channel::{Channel, ChannelTarget, InboundMessage, MessageHandler};
 use crate::config::WhatsAppConfig;
 
 /// WebSocket 消息类型
@@ -50,16 +53,21 @@ struct SendMessage {
 /// WhatsApp 通道
 pub struct WhatsAppChannel {
     config: WhatsAppConfig,
-    agent: Arc<crate::agent::Agent>,
+    /// 入站消息处理器
+    handler: Arc<dyn MessageHandler>,
     ws_stream: RwLock<Option<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
     connected: RwLock<bool>,
     running: Arc<RwLock<bool>>,
+    /// 发件箱（启用时回复经发件箱投递）
+    outbox: Option<Arc<Outbox>>,
+    /// 入站消息去重
+    dedupe: Option<Arc<DedupeStore>>,
 }
 
 impl WhatsAppChannel {
     pub fn new(
         config: WhatsAppConfig,
-        agent: Arc<crate::agent::Agent>,
+        handler: Arc<dyn MessageHandler>,
     ) -> Result<Self> {
         if config.bridge_url.is_none() {
             return Err(anyhow!("WhatsApp Bridge URL 未配置"));
@@ -67,13 +75,27 @@ impl WhatsAppChannel {
 
         Ok(Self {
             config,
-            agent,
+            handler,
             ws_stream: RwLock::new(None),
             connected: RwLock::new(false),
             running: Arc::new(RwLock::new(false)),
+            outbox: None,
+            dedupe: None,
         })
     }
 
+    /// 设置发件箱
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// 设置入站消息去重
+    pub fn with_dedupe(mut self, dedupe: Option<Arc<DedupeStore>>) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
     /// 检查用户是否有权限
     fn is_allowed(&self, phone_number: &str) -> bool {
         if self.config.allowed_users.is_empty() {
@@ -91,7 +113,7 @@ impl WhatsAppChannel {
             .with_context(|| format!("解析 Bridge 消息失败: {}", raw))?;
 
         match msg {
-            BridgeMessage::Message { sender, content, message_id: _, timestamp: _, is_group: _ } => {
+            BridgeMessage::Message { sender, content, message_id, timestamp: _, is_group } => {
                 // 提取手机号（sender 格式通常是: <phone>@s.whatsapp.net）
                 let phone_number = sender.split('@').next().unwrap_or(&sender);
                 
@@ -101,6 +123,13 @@ impl WhatsAppChannel {
                     return Ok(());
                 }
 
+                // 去重（Bridge 重连后可能重放消息）
+                if let (Some(dedupe), Some(id)) = (&self.dedupe, message_id.as_deref()) {
+                    if !dedupe.check_and_insert("whatsapp", id).await? {
+                        return Ok(());
+                    }
+                }
+
                 info!("收到 WhatsApp 消息 from={}: {}", phone_number, content);
 
                 // 处理语音消息
@@ -110,16 +139,21 @@ impl WhatsAppChannel {
                     content
                 };
 
-                // 调用 Agent
-                match self.agent.chat(&content).await {
+                // 交给处理器
+                // 群消息的发送方 ID 以 @g.us 结尾
+                let is_group = is_group.unwrap_or_else(|| sender.ends_with("@g.us"));
+                let inbound = InboundMessage::new("whatsapp", sender.as_str(), phone_number, content).with_group(is_group);
+                match self.handler.handle(inbound).await {
+                    Ok(response) if response.is_empty() => {}
                     Ok(response) => {
                         // 发送回复
-                        if let Err(e) = self.send_message_internal(&sender, &response.content).await {
+                        let target = ChannelTarget::chat(sender.as_str());
+                        if let Err(e) = outbox::deliver(self.outbox.as_ref(), self, &target, &response).await {
                             error!("发送 WhatsApp 消息失败: {}", e);
                         }
                     }
                     Err(e) => {
-                        error!("Agent 错误: {}", e);
+                        error!("处理消息失败: {}", e);
                         let _ = self.send_message_internal(&sender, &format!("❌ 错误: {}", e)).await;
                     }
                 }
@@ -193,7 +227,6 @@ impl WhatsAppChannel {
         };
 
         // 启动心跳任务
-        let running = self.running.clone();
         let heartbeat_handle: tokio::task::JoinHandle<()> = tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
             loop {
@@ -207,13 +240,21 @@ impl WhatsAppChannel {
             }
         });
 
-        // 处理接收到的消息
-        while let Some(msg) = read.next().await {
+        // 处理接收到的消息（并发处理，回复进行中时仍能收到 stop 等取消请求）
+        let mut pending = FuturesUnordered::new();
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => msg,
+                Some(()) = pending.next(), if !pending.is_empty() => continue,
+            };
+            let Some(msg) = msg else { break };
             match msg {
                 Ok(tokio_tungstenite::tungstenite::Message::Text(text)) => {
-                    if let Err(e) = self.handle_bridge_message(&text).await {
-                        error!("处理 Bridge 消息错误: {}", e);
-                    }
+                    pending.push(async move {
+                        if let Err(e) = self.handle_bridge_message(&text).await {
+                            error!("处理 Bridge 消息错误: {}", e);
+                        }
+                    });
                 }
                 Ok(tokio_tungstenite::tungstenite::Message::Close(_)) => {
                     info!("WhatsApp Bridge 连接已关闭");
@@ -226,6 +267,8 @@ impl WhatsAppChannel {
                 }
             }
         }
+        // 等待已收到的消息处理完
+        while pending.next().await.is_some() {}
 
         *self.running.write().await = false;
         heartbeat_handle.abort();
@@ -288,18 +331,83 @@ impl Channel for WhatsAppChannel {
         Ok(())
     }
 
+    fn check_target(&self, target: &ChannelTarget) -> Result<()> {
+        jid(target).map(|_| ())
+    }
+
     async fn send_message(
         &self,
-        target: &str,
+        target: &ChannelTarget,
         content: &str,
     ) -> Result<()> {
-        // 确保目标格式正确（添加 @s.whatsapp.net 后缀）
-        let to = if target.contains('@') {
-            target.to_string()
-        } else {
-            format!("{}@s.whatsapp.net", target)
+        let to = jid(target)?;
+        self.send_message_internal(&to, content).await
+    }
+}
+
+/// 把发送目标转换为 WhatsApp JID（不带后缀的 ID 和手机号添加 @s.whatsapp.net）
+fn jid(target: &ChannelTarget) -> Result<String> {
+    match target {
+        ChannelTarget::ChatId(id) | ChannelTarget::UserId(id) if id.contains('@') => Ok(id.clone()),
+        ChannelTarget::ChatId(id) | ChannelTarget::UserId(id) => Ok(format!("{}@s.whatsapp.net", id)),
+        ChannelTarget::Phone(number) => {
+            let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+            if !(7..=15).contains(&digits.len()) {
+                anyhow::bail!("无效的手机号: {}", number);
+            }
+            Ok(format!("{}@s.whatsapp.net", digits))
+        }
+        _ => Err(target.unsupported("whatsapp")),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_inbound_goes_to_handler() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let handler = move |msg: InboundMessage| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push(msg);
+                Ok("收到".to_string())
+            }
         };
 
-        self.send_message_internal(&to, content).await
+        let config = WhatsAppConfig {
+            bridge_url: Some("ws://localhost:3001".to_string()),
+            allowed_users: vec!["8613800000000".to_string()],
+            reconnect_interval_secs: 5,
+            auto_reconnect: false,
+            quiet_hours: None,
+            merge_window_ms: 0,
+            wake_words: None,
+        };
+        let channel = WhatsAppChannel::new(config, Arc::new(handler)).unwrap();
+
+        let raw = r#"{"type":"message","sender":"8613800000000@s.whatsapp.net","content":"你好"}"#;
+        channel.handle_bridge_message(raw).await.unwrap();
+        // 不在白名单中的用户
+        let raw = r#"{"type":"message","sender":"8613900000000@s.whatsapp.net","content":"你好"}"#;
+        channel.handle_bridge_message(raw).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].sender, "8613800000000");
+        assert_eq!(received[0].session_key(), "whatsapp:8613800000000@s.whatsapp.net");
+        assert_eq!(received[0].content, "你好");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_jid() {
+        let jid_of = |text: &str| jid(&ChannelTarget::parse(text).unwrap());
+        assert_eq!(jid_of("8613800000000").unwrap(), "8613800000000@s.whatsapp.net");
+        assert_eq!(jid_of("123456@g.us").unwrap(), "123456@g.us");
+        assert_eq!(jid_of("tel:+86 138-0000-0000").unwrap(), "8613800000000@s.whatsapp.net");
+        assert!(jid_of("tel:12").is_err());
+        assert!(jid_of("mailto:a@example.com").is_err());
+    }
+}
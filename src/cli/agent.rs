@@ -3,20 +3,25 @@
 use anyhow::Result;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::info;
 
-use crate::agent::Agent;
+use crate::agent::{Agent, DEFAULT_PERSONA};
 use crate::config::Config;
 
-pub async fn run(config: Config, initial_prompt: Option<String>) -> Result<()> {
+pub async fn run(config: Config, initial_prompt: Option<String>, persona: Option<String>) -> Result<()> {
     info!("启动 Nanobot Agent 模式...");
 
     // 创建 Agent
     let agent = Arc::new(Agent::new(config, None).await?);
+    if let Some(ref persona) = persona {
+        agent.set_persona(Some(persona)).await?;
+        println!("🎭 角色: {}", persona);
+    }
 
     println!("🤖 Nanobot Agent 模式");
-    println!("输入 'exit' 或 'quit' 退出，'clear' 清空上下文\n");
+    println!("输入 'exit' 或 'quit' 退出，'clear' 清空上下文，'/pin' 置顶内容，'/snapshot <文件>' 保存上下文快照\n");
 
     // 如果有初始提示词，先执行
     if let Some(prompt) = initial_prompt {
@@ -31,7 +36,11 @@ pub async fn run(config: Config, initial_prompt: Option<String>) -> Result<()> {
         }
     }
 
-    // 启动交互式循环
+    interactive(&agent).await
+}
+
+/// 交互式对话循环
+pub async fn interactive(agent: &Agent) -> Result<()> {
     let mut rl = DefaultEditor::new()?;
 
     loop {
@@ -61,12 +70,110 @@ pub async fn run(config: Config, initial_prompt: Option<String>) -> Result<()> {
                         let ctx_len = agent.context_length().await;
                         let sid = agent.session_id().await;
                         println!("会话 ID: {}", sid);
-                        println!("上下文消息数: {}\n", ctx_len);
+                        println!("上下文消息数: {}", ctx_len);
+                        if let Some(stats) = agent.session_stats().await {
+                            println!(
+                                "会话统计: 消息 {}，工具调用 {}，令牌 {}",
+                                stats.message_count, stats.tool_call_count, stats.total_tokens
+                            );
+                        }
+                        if let Some(stats) = agent.route_stats() {
+                            let mut routes: Vec<_> = stats.into_iter().collect();
+                            routes.sort_by(|a, b| a.0.cmp(&b.0));
+                            for (route, hits) in routes {
+                                println!("路由 {}: {} 次", route, hits);
+                            }
+                        }
+                        println!();
+                        continue;
+                    }
+                    "/pins" => {
+                        let pins = agent.pins().await;
+                        if pins.is_empty() {
+                            println!("暂无置顶内容\n");
+                        } else {
+                            for (i, pin) in pins.iter().enumerate() {
+                                println!("{}. {}", i + 1, pin);
+                            }
+                            println!();
+                        }
                         continue;
                     }
                     _ => {}
                 }
 
+                // /pin [内容]：置顶内容（不带参数时置顶上一条回复）
+                if let Some(rest) = input.strip_prefix("/pin") {
+                    if rest.is_empty() || rest.starts_with(' ') {
+                        match agent.pin(Some(rest)).await {
+                            Ok(count) => println!("📌 已置顶，当前共 {} 条\n", count),
+                            Err(e) => eprintln!("置顶失败: {}\n", e),
+                        }
+                        continue;
+                    }
+                }
+
+                // /instruct [指令]：设置本会话指令（不带参数时清除）
+                if let Some(rest) = input.strip_prefix("/instruct") {
+                    if rest.is_empty() || rest.starts_with(' ') {
+                        let text = rest.trim();
+                        match agent.set_instructions(Some(text)).await {
+                            Ok(()) if text.is_empty() => println!("已清除会话指令\n"),
+                            Ok(()) => println!("📝 已设置会话指令\n"),
+                            Err(e) => eprintln!("设置会话指令失败: {}\n", e),
+                        }
+                        continue;
+                    }
+                }
+
+                // /persona [名称]：查看或切换角色（default 恢复默认）
+                if let Some(rest) = input.strip_prefix("/persona") {
+                    if rest.is_empty() || rest.starts_with(' ') {
+                        let name = rest.trim();
+                        if name.is_empty() {
+                            let current = agent.persona().await;
+                            println!("当前角色: {}", current.as_deref().unwrap_or(DEFAULT_PERSONA));
+                            for (name, description) in agent.personas() {
+                                println!("  {} {}", name, description.unwrap_or_default());
+                            }
+                            println!();
+                        } else {
+                            match agent.set_persona(Some(name)).await {
+                                Ok(()) => println!("🎭 已切换角色: {}\n", name),
+                                Err(e) => eprintln!("切换角色失败: {}\n", e),
+                            }
+                        }
+                        continue;
+                    }
+                }
+
+                // /snapshot <文件>：保存当前上下文快照，可用 nanobot session restore 重放
+                if let Some(rest) = input.strip_prefix("/snapshot") {
+                    if rest.is_empty() || rest.starts_with(' ') {
+                        match rest.trim() {
+                            "" => println!("用法: /snapshot <文件>\n"),
+                            path => match save_snapshot(agent, Path::new(path)).await {
+                                Ok(()) => println!("📸 已保存快照: {}\n", path),
+                                Err(e) => eprintln!("保存快照失败: {}\n", e),
+                            },
+                        }
+                        continue;
+                    }
+                }
+
+                // /unpin <序号>
+                if let Some(rest) = input.strip_prefix("/unpin ") {
+                    match rest.trim().parse::<usize>() {
+                        Ok(i) => match agent.unpin(i).await {
+                            Ok(Some(removed)) => println!("已取消置顶: {}\n", removed),
+                            Ok(None) => println!("没有第 {} 条置顶\n", i),
+                            Err(e) => eprintln!("取消置顶失败: {}\n", e),
+                        },
+                        Err(_) => println!("用法: /unpin <序号>\n"),
+                    }
+                    continue;
+                }
+
                 // 发送给 Agent
                 match agent.chat(input).await {
                     Ok(response) => {
@@ -93,3 +200,10 @@ pub async fn run(config: Config, initial_prompt: Option<String>) -> Result<()> {
 
     Ok(())
 }
+
+/// 把当前上下文快照写入文件
+pub async fn save_snapshot(agent: &Agent, path: &Path) -> Result<()> {
+    let json = agent.snapshot().await.to_json()?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
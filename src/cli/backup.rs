@@ -0,0 +1,81 @@
+//! backup 命令 - 把配置、记忆和数据库打包为一个归档，或从归档恢复
+
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+use crate::backup::{self, SecretsMode};
+use crate::config::Config;
+use crate::t;
+use crate::vault::Vault;
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// 创建备份（需先停止 gateway）
+    Create {
+        /// 输出文件（默认 nanobot-backup-<时间>.tar.zst）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 用 vault 口令加密保存配置中的密钥（默认不包含密钥）
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// 从备份恢复到本机的配置文件和工作目录
+    Restore {
+        /// 备份文件
+        file: PathBuf,
+        /// 覆盖已存在的文件
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+pub async fn run(config: Config, config_path: Option<&str>, command: BackupCommand) -> Result<()> {
+    let config_path = match config_path {
+        Some(path) => PathBuf::from(path),
+        None => Config::default_config_path()?,
+    };
+
+    match command {
+        BackupCommand::Create { output, include_secrets } => {
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from(format!("nanobot-backup-{}.tar.zst", chrono::Local::now().format("%Y%m%d-%H%M%S")))
+            });
+            let vault = if include_secrets { Some(Vault::load(&config)?) } else { None };
+            let path = output.clone();
+            let manifest = tokio::task::spawn_blocking(move || {
+                backup::create(&config, &config_path, &path, vault.as_deref())
+            })
+            .await??;
+            let bytes: u64 = manifest.files.iter().map(|f| f.size).sum();
+            println!(
+                "{}",
+                t!("backup.created", path = output.display(), files = manifest.files.len(), size = bytes / 1024)
+            );
+            match manifest.secrets {
+                SecretsMode::Excluded => println!("{}", t!("backup.secrets_excluded")),
+                SecretsMode::Encrypted => println!("{}", t!("backup.secrets_encrypted")),
+            }
+        }
+        BackupCommand::Restore { file, force } => {
+            let workspace = config.memory.workspace_path.clone();
+            let path = config_path.clone();
+            let summary = tokio::task::spawn_blocking(move || backup::restore(&file, &config, &path, force)).await??;
+            println!(
+                "{}",
+                t!("backup.restored", files = summary.files, workspace = workspace.display(), config = config_path.display())
+            );
+            if summary.workspace_rewritten {
+                println!("{}", t!("backup.workspace_rewritten", workspace = workspace.display()));
+            }
+            if summary.secrets_restored {
+                println!("{}", t!("backup.secrets_restored"));
+            }
+            if let Some(error) = summary.secrets_error {
+                println!("{}", t!("backup.secrets_failed", error = error));
+            }
+        }
+    }
+
+    Ok(())
+}
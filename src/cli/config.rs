@@ -0,0 +1,55 @@
+//! config 命令 - 导出配置的 JSON Schema、检查配置文件
+
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use std::path::PathBuf;
+
+use crate::config::{schema, Config};
+use crate::t;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// 导出配置的 JSON Schema（可供编辑器补全和校验）
+    Schema {
+        /// 输出文件（默认输出到标准输出）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 检查配置文件能否解析、是否有未知的配置项（有问题时以非零状态退出）
+    Check,
+}
+
+pub async fn run(config_path: Option<&str>, command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Schema { output } => {
+            let content = serde_json::to_string_pretty(&schema::schema())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)
+                        .with_context(|| format!("写入文件失败: {}", path.display()))?;
+                    println!("{}", t!("config.schema_written", path = path.display()));
+                }
+                None => println!("{}", content),
+            }
+        }
+        ConfigCommand::Check => {
+            let path = match config_path {
+                Some(p) => PathBuf::from(p),
+                None => Config::default_config_path()?,
+            };
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("读取配置文件失败: {}", path.display()))?;
+            let (_, unknown) = Config::parse(&content).with_context(|| format!("配置文件 {} 有误", path.display()))?;
+            if !unknown.is_empty() {
+                println!("{}", t!("config.unknown_keys", path = path.display(), count = unknown.len()));
+                for key in &unknown {
+                    println!("  - {}", key);
+                }
+                bail!("配置文件检查未通过");
+            }
+            println!("{}", t!("config.check_ok", path = path.display()));
+        }
+    }
+
+    Ok(())
+}
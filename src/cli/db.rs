@@ -0,0 +1,68 @@
+//! db 命令 - SQLite 数据库维护
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use crate::config::Config;
+use crate::db;
+use crate::t;
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// 检查完整性、整理（VACUUM）并重建索引，输出各表大小
+    Maintain,
+}
+
+pub async fn run(config: Config, command: DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Maintain => maintain(&config).await,
+    }
+}
+
+async fn maintain(config: &Config) -> Result<()> {
+    let mut failed = 0;
+    for path in config.database_paths() {
+        if !path.exists() {
+            continue;
+        }
+        println!("{}", t!("db.file", path = path.display()));
+        if db::is_encrypted(&path).await? {
+            println!("{}", t!("db.encrypted"));
+            continue;
+        }
+        let report = match db::maintain(&path).await {
+            Ok(report) => report,
+            Err(e) => {
+                println!("{}", t!("db.failed", error = format!("{:#}", e)));
+                failed += 1;
+                continue;
+            }
+        };
+        if report.problems.is_empty() {
+            println!("{}", t!("db.integrity_ok"));
+            println!(
+                "{}",
+                t!("db.size", before = format_kb(report.size_before), after = format_kb(report.size_after))
+            );
+        } else {
+            failed += 1;
+            println!("{}", t!("db.integrity_failed", count = report.problems.len()));
+            for problem in report.problems.iter().take(10) {
+                println!("    {}", problem);
+            }
+        }
+        for table in &report.tables {
+            let size = table.bytes.map(|b| format_kb(b as u64)).unwrap_or_else(|| "-".to_string());
+            println!("{}", t!("db.table", name = table.name, rows = table.rows, size = size));
+        }
+        println!();
+    }
+    if failed > 0 {
+        anyhow::bail!("{} 个数据库维护失败或未通过完整性检查", failed);
+    }
+    Ok(())
+}
+
+fn format_kb(bytes: u64) -> String {
+    format!("{:.1} KB", bytes as f64 / 1024.0)
+}
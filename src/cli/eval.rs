@@ -0,0 +1,115 @@
+//! eval 命令 - 运行评测用例，与基线比较
+
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::agent::Agent;
+use crate::config::Config;
+use crate::eval::{self, Baseline, CaseResult, Suite};
+use crate::llm::mock::MockProvider;
+
+pub struct EvalArgs {
+    pub suite: PathBuf,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub baseline: Option<PathBuf>,
+    pub save_baseline: Option<PathBuf>,
+}
+
+pub async fn run(mut config: Config, args: EvalArgs) -> Result<()> {
+    let mut suite = Suite::load(&args.suite)?;
+    if let Some(ref provider) = args.provider {
+        if provider != "mock" {
+            config.agent.default_model = config
+                .llm
+                .provider(provider)
+                .and_then(|p| p.default_model.clone())
+                .unwrap_or(config.agent.default_model);
+        }
+        config.agent.default_provider = provider.clone();
+        suite.model = None;
+    }
+    if let Some(model) = args.model {
+        suite.model = Some(model);
+    }
+    if let Some(ref prompt) = suite.system_prompt {
+        config.agent.system_prompt = prompt.clone();
+    }
+    // 评测对话不写入历史
+    config.channel.incognito_channels.push("eval".to_string());
+
+    let mock = config.agent.default_provider == "mock";
+    let mut agent = Agent::new(config, Some(format!("eval:{}", suite.name))).await?;
+    if mock {
+        let provider = suite
+            .cases
+            .iter()
+            .filter_map(|c| c.mock_response.as_ref().map(|r| (&c.prompt, r)))
+            .fold(MockProvider::new(), |p, (prompt, reply)| p.with_response(prompt, reply.clone()));
+        agent = agent.with_llm_provider("mock", Arc::new(provider));
+    }
+    let baseline = args.baseline.as_deref().map(Baseline::load).transpose()?;
+
+    let title = if suite.name.is_empty() { args.suite.display().to_string() } else { suite.name.clone() };
+    println!("🧪 评测: {}（{} 条用例，模型 {}）\n", title, suite.cases.len(), suite.model.as_deref().unwrap_or(&agent.model_name()));
+    // mock 提供商无法评分，跳过 llm 断言
+    let results = eval::run(&agent, &suite, !mock || suite.grader_model.is_some()).await;
+
+    let mut regressions = 0;
+    for result in &results {
+        let previous = baseline.as_ref().and_then(|b| b.cases.get(&result.name));
+        let mut line = format!(
+            "{} {}  {} ms{}  {} 令牌{}",
+            if result.passed { "✅" } else { "❌" },
+            result.name,
+            result.latency_ms,
+            delta(previous.map(|p| p.latency_ms), result.latency_ms),
+            result.tokens,
+            delta(previous.map(|p| p.tokens), result.tokens),
+        );
+        if result.skipped > 0 {
+            line.push_str(&format!("  （跳过 {} 条断言）", result.skipped));
+        }
+        if previous.is_some_and(|p| p.passed && !result.passed) {
+            regressions += 1;
+            line.push_str("  ⚠️ 退化");
+        }
+        println!("{}", line);
+        for failure in &result.failures {
+            println!("     - {}", failure);
+        }
+    }
+
+    let (passed, total) = (results.iter().filter(|r| r.passed).count(), results.len());
+    println!("\n通过 {}/{}，平均耗时 {} ms，共 {} 令牌", passed, total, average_latency(&results), results.iter().map(|r| r.tokens).sum::<u64>());
+    if let Some(ref baseline) = baseline {
+        let missing = baseline.cases.keys().filter(|name| !results.iter().any(|r| &r.name == *name)).count();
+        println!("与基线相比: {} 条退化{}", regressions, if missing > 0 { format!("，{} 条基线用例已不存在", missing) } else { String::new() });
+    }
+
+    if let Some(ref path) = args.save_baseline {
+        Baseline::from_results(&results).save(path)?;
+        println!("💾 已保存基线: {}", path.display());
+    }
+
+    if passed < total {
+        return Err(anyhow!("{} 条用例未通过", total - passed));
+    }
+    Ok(())
+}
+
+/// 相对基线的变化（无基线时为空）
+fn delta(baseline: Option<u64>, current: u64) -> String {
+    match baseline.and_then(|b| eval::percent_change(b, current)) {
+        Some(change) => format!(" ({:+.0}%)", change),
+        None => String::new(),
+    }
+}
+
+fn average_latency(results: &[CaseResult]) -> u64 {
+    if results.is_empty() {
+        return 0;
+    }
+    results.iter().map(|r| r.latency_ms).sum::<u64>() / results.len() as u64
+}
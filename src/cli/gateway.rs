@@ -1,20 +1,169 @@
 //! gateway 命令 - 启动网关服务
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::sync::Arc;
 use tracing::{info, warn};
 
+use crate::agent::briefing::{briefing_job, BriefingHandler};
+use crate::agent::extract::MemoryExtractionHandler;
 use crate::agent::Agent;
-use crate::channel::ChannelManager;
+use crate::api::health::Readiness;
+use crate::api::auth::{ApiAuth, TokenStore};
+use crate::api::server::HttpServer;
+use crate::api::ApiState;
+use crate::budget::BudgetAlertHandler;
+use crate::crash::{self, PanicNoticeHandler};
+use crate::bus::EventBus;
+use crate::channel::dedupe::DedupeStore;
+use crate::channel::filter::ContentFilter;
+use crate::channel::outbox::Outbox;
+use crate::channel::postprocess::PostProcessor;
+use crate::channel::quiet::QuietHours;
+use crate::channel::template::NotificationTemplates;
+use crate::channel::wake::WakeWords;
+use crate::channel::handler::{BusyNoticeHandler, ProgressNoticeHandler};
+use crate::channel::{AgentHandler, ChannelManager, ChannelServices, MessageHandler};
 use crate::config::Config;
+use crate::cron::delivery::DeliveryRouter;
+use crate::cron::exclusive::ExclusiveHandler;
+use crate::cron::http::HttpCallHandler;
+use crate::cron::reminder::{resolve_timezone, ReminderHandler, Timezone};
+use crate::cron::shell::ShellCommandHandler;
+use crate::cron::Scheduler;
+use crate::llm::probe::{ProbeStore, Prober};
+use crate::memory::review::{digest_job, MemoryDigestHandler};
+use crate::privacy::DataStores;
+use crate::session::SessionManager;
+use crate::state::StateStore;
+use crate::tools::message::MessageTool;
+use crate::vault::Vault;
+use crate::watch::{FileWatchHandler, Watcher};
 
 pub async fn run(config: Config, channel: Option<String>) -> Result<()> {
     info!("启动 Nanobot Gateway...");
 
+    // 时区无法识别时直接退出，避免提醒按系统时区触发
+    if let Some(ref timezone) = config.agent.timezone {
+        timezone.parse::<Timezone>().context("agent.timezone 配置无效")?;
+    }
+
+    // 解密 nanobot vault lock 加密的数据库
+    let vault = Vault::from_config(&config)?;
+    if let Some(ref vault) = vault {
+        let unlocked = vault.unlock_databases(&config).await?;
+        if unlocked > 0 {
+            info!("已解密 {} 个数据库，gateway 正常退出时重新加密", unlocked);
+        }
+    }
+
+    // 创建定时任务调度器（提醒等持久化任务），数据库不可用时以内存模式运行
+    let scheduler = Scheduler::with_db_or_memory(&config.cron_db_path().to_string_lossy()).await?;
+
+    // 事件总线（会话结束、任务执行等事件）
+    let event_bus = EventBus::new();
+    tokio::spawn(event_bus.clone().start());
+    crash::set_event_bus(event_bus.clone());
+    scheduler.set_event_bus(event_bus.clone()).await;
+
     // 创建 Agent（不指定 session_id，使用默认值）
-    let agent = Arc::new(Agent::new(config.clone(), None).await?);
+    let mut agent = Agent::new(config.clone(), None).await?;
+    // 运行期间注册/注销工具时发布事件
+    agent.tools().set_event_bus(event_bus.clone());
+    agent = agent.with_scheduler(scheduler.clone());
+
+    // 会话统计与空闲清理
+    let mut shared_sessions = None;
+    match SessionManager::from_config(
+        &config.session,
+        &config.sessions_db_path().to_string_lossy(),
+        Some(event_bus.clone()),
+        vault.clone(),
+    )
+    .await
+    {
+        Ok(sessions) => {
+            sessions.clone().start_cleanup(config.session.cleanup_interval_secs);
+            agent = agent.with_sessions(sessions.clone());
+            shared_sessions = Some(sessions);
+        }
+        Err(e) => warn!("会话管理器初始化失败: {}，不清理空闲会话", e),
+    }
+
+    // 定期探测提供商可用性，不可用时按备用顺序切换
+    if config.llm.probe.enabled {
+        let store = match ProbeStore::open(&config.usage_db_path().to_string_lossy()).await {
+            Ok(store) => Some(store),
+            Err(e) => {
+                warn!("打开用量数据库失败: {}，不记录探测结果", e);
+                None
+            }
+        };
+        let prober = Prober::new(&config, agent.llm_providers(), store);
+        agent = agent.with_provider_health(prober.health());
+        prober.start();
+        info!("提供商可用性探测已启用，间隔 {} 秒", config.llm.probe.interval_secs);
+    }
+    let agent = Arc::new(agent);
+
+    // 按保留期定期清理过期数据
+    if config.privacy.retention_days > 0 {
+        match DataStores::open(&config).await {
+            Ok(stores) => {
+                let stores = match shared_sessions {
+                    Some(sessions) => stores.with_sessions(sessions),
+                    None => stores,
+                };
+                Arc::new(stores).start_retention(config.privacy.retention_days);
+                info!("数据保留期: {} 天", config.privacy.retention_days);
+            }
+            Err(e) => warn!("打开数据存储失败: {}，不清理过期数据", e),
+        }
+    }
+
+    // 定期清理回收站中过期的文件
+    if config.tools.trash_retention_days > 0 {
+        Arc::new(crate::tools::trash::Trash::new(config.trash_dir())).start_sweep(config.tools.trash_retention_days);
+    }
+
+    // 发件箱：回复先落库再投递，失败重试
+    let outbox = if config.channel.outbox {
+        match Outbox::open(&config.outbox_db_path().to_string_lossy(), vault.clone()).await {
+            Ok(o) => Some(o),
+            Err(e) => {
+                warn!("发件箱初始化失败: {}，回复将直接发送", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // 多进程部署时的共享状态（配置 [redis] 时使用 Redis）
+    let state = StateStore::from_config(&config.redis).await;
+    let shared_state = state.is_shared().then(|| state.clone());
 
-    let mut manager = ChannelManager::new();
+    // 入站消息去重（各通道共享）
+    let dedupe = match DedupeStore::from_config(
+        &config.channel.dedupe,
+        &config.dedupe_db_path().to_string_lossy(),
+    )
+    .await
+    {
+        Ok(d) => d,
+        Err(e) => {
+            warn!("去重存储初始化失败: {}，仅使用内存去重", e);
+            DedupeStore::new(config.channel.dedupe.capacity, config.channel.dedupe.ttl_secs)
+        }
+    }
+    .with_shared_state(state);
+
+    let mut manager = ChannelManager::with_supervisor(config.channel.supervisor.clone())
+        .with_outbox(outbox.clone());
+
+    let services = ChannelServices {
+        outbox: outbox.clone(),
+        dedupe: Some(Arc::new(dedupe)),
+    };
 
     // 确定要启动的通道
     let channels_to_start: Vec<String> = if let Some(ch) = channel {
@@ -35,23 +184,256 @@ pub async fn run(config: Config, channel: Option<String>) -> Result<()> {
         return Ok(());
     }
 
+    let content_filter = if config.content_filter.enabled {
+        let filter = ContentFilter::new(&config.content_filter, config.content_filter_log_path())?;
+        info!("内容过滤已启用（{} 条规则）", config.content_filter.rules.len());
+        Some(Arc::new(filter))
+    } else {
+        None
+    };
+
+    // 通道收到的消息交给 Agent 处理（提供商限流等待时经事件总线提示用户）
+    // 同一聊天的消息排队处理，可按通道把连发的消息合并为一轮；启用唤醒词的通道只响应以唤醒词开头的消息
+    let mut agent_handler = AgentHandler::new(agent.clone())
+        .with_event_bus(event_bus.clone())
+        .with_regenerate_on_edit(config.channel.regenerate_on_edit)
+        .with_content_filter(content_filter)
+        .with_postprocessor(Arc::new(PostProcessor::new(&config.channel.postprocess)))
+        .with_wake_words(WakeWords::from_config(&config))
+        .with_admins(config.channel.admins());
+    for name in &channels_to_start {
+        agent_handler = agent_handler.with_merge_window(name, config.channel.merge_window(name));
+    }
+    let handler: Arc<dyn MessageHandler> = Arc::new(agent_handler);
+
     // 注册并启动通道
     for channel_name in channels_to_start {
         info!("注册通道: {}", channel_name);
         
-        match crate::channel::ChannelFactory::create(&channel_name, &config, agent.clone()
-        ) {
-            Ok(channel) => {
-                manager.register(channel);
+        if let Err(e) = manager.create(&channel_name, &config, handler.clone(), &services) {
+            warn!("无法创建通道 '{}': {}", channel_name, e);
+        }
+    }
+
+    let manager = Arc::new(manager);
+
+    // Agent 可通过 message 工具主动向通道发送消息（默认发往当前对话）
+    agent.tools().register(
+        MessageTool::new(manager.channels()).with_outbox(outbox.clone()),
+    );
+
+    // 提供商限流时提示用户正在排队重试
+    event_bus
+        .subscribe(BusyNoticeHandler::new(manager.channels()))
+        .await;
+    // 长时间运行的工具（如流式 shell）推送中间输出
+    event_bus
+        .subscribe(ProgressNoticeHandler::new(manager.channels()))
+        .await;
+
+    // 提醒、任务失败、预算告警、文件监视等系统通知的模板
+    let templates = NotificationTemplates::from_config(&config);
+
+    // 用量达到预算告警阈值时通知管理员
+    if let Some(budget) = agent.budget() {
+        budget.set_event_bus(event_bus.clone());
+        match BudgetAlertHandler::new(manager.channels(), &config.budget) {
+            Some(alerts) => {
+                event_bus
+                    .subscribe(alerts.with_templates(templates.clone()))
+                    .await;
             }
+            None => warn!("未配置 budget.alert_channel/alert_chat_id，预算告警只写入日志"),
+        }
+    }
+
+    // 后台任务 panic 时通知管理员
+    if let Some(notices) = PanicNoticeHandler::new(manager.channels(), &config.crash) {
+        event_bus.subscribe(notices).await;
+    }
+
+    // 会话结束或空闲超时后从对话中提取长期记忆
+    if config.memory.extraction.enabled {
+        if agent.memory().is_some() {
+            event_bus
+                .subscribe::<crate::bus::SessionEndedEvent, _>(MemoryExtractionHandler::new(
+                    agent.clone(),
+                    event_bus.clone(),
+                ))
+                .await;
+        } else {
+            warn!("未启用记忆存储，不提取对话记忆");
+        }
+    }
+
+    // 监视目录中的文件变化，按目录配置通知或交给 Agent 处理
+    if config.watch.enabled && !config.watch.dirs.is_empty() {
+        event_bus
+            .subscribe(
+                FileWatchHandler::new(&config.watch, handler.clone(), manager.channels())
+                    .with_quiet_hours(QuietHours::from_config(&config))
+                    .with_templates(templates.clone())
+                    .with_outbox(outbox.clone()),
+            )
+            .await;
+        Watcher::new(&config.watch, event_bus.clone()).start();
+    }
+
+    // 共享 HTTP 服务：API 接口与通道回调（如飞书事件订阅）共用 api.bind 一个端口
+    let mut http = HttpServer::from_config(&config.api)?;
+    if config.api.enabled {
+        // nanobot token create 创建的令牌，数据库不可用时只使用配置中的令牌
+        let tokens = match TokenStore::open(&config.api_tokens_db_path().to_string_lossy()).await {
+            Ok(store) => Some(store),
             Err(e) => {
-                warn!("无法创建通道 '{}': {}", channel_name, e);
+                warn!("令牌数据库初始化失败: {}，只使用配置中的令牌", e);
+                None
+            }
+        };
+        let auth = ApiAuth::new(&config.api)
+            .with_store(tokens)
+            .with_audit_log(config.api_audit_log_path());
+        let state = Arc::new(ApiState {
+            scheduler: scheduler.clone(),
+            channels: manager.health(),
+            readiness: Readiness::new(&config, agent.providers(), manager.health()),
+            manager: manager.clone(),
+            handler: handler.clone(),
+            auth: Arc::new(auth),
+            event_bus: event_bus.clone(),
+            job_handlers: config.api.job_handlers.clone(),
+        });
+        http.mount("api", "", crate::api::router(state));
+    }
+    for (name, routes) in manager.routes() {
+        http.mount(&name, &format!("/{}", name), routes);
+    }
+    if !http.is_empty() {
+        crash::spawn("http", async move {
+            if let Err(e) = http.serve().await {
+                warn!("HTTP 服务退出: {}", e);
             }
+        });
+    }
+
+    // 启动发件箱投递任务（包括上次未投递完的消息）
+    if let Some(ref outbox) = outbox {
+        outbox.clone().start_worker(manager.channels());
+    }
+
+    // 提醒通过已注册的通道发送（免打扰时段内排队），多进程部署时每次执行只由一个进程处理
+    scheduler
+        .register_handler(ExclusiveHandler::wrap(
+            Arc::new(
+                ReminderHandler::new(manager.channels())
+                    .with_quiet_hours(QuietHours::from_config(&config))
+                    .with_templates(templates.clone())
+                    .with_outbox(outbox.clone()),
+            ),
+            shared_state.clone(),
+        ))
+        .await;
+
+    // 定时执行 shell 命令（沿用 shell 工具的白名单），输出按任务的投递方式发送；需要显式启用
+    if config.cron.shell_jobs {
+        scheduler
+            .register_handler(ExclusiveHandler::wrap(
+                Arc::new(ShellCommandHandler::new(config.tools.clone())),
+                shared_state.clone(),
+            ))
+            .await;
+    }
+    // 定时请求外部服务（经过出站网址策略检查）
+    scheduler
+        .register_handler(ExclusiveHandler::wrap(
+            Arc::new(HttpCallHandler::new(config.tools.clone())),
+            shared_state.clone(),
+        ))
+        .await;
+
+    // 每日简报（任务不持久化，每次启动按配置创建）
+    if config.briefing.enabled {
+        scheduler
+            .register_handler(ExclusiveHandler::wrap(
+                Arc::new(
+                    BriefingHandler::new(agent.clone(), manager.channels())
+                        .with_quiet_hours(QuietHours::from_config(&config))
+                        .with_outbox(outbox.clone()),
+                ),
+                shared_state.clone(),
+            ))
+            .await;
+        match briefing_job(&config.briefing, resolve_timezone(config.agent.timezone.as_deref())) {
+            Ok(job) => {
+                scheduler.add_job(job).await?;
+            }
+            Err(e) => warn!("每日简报未启用: {}", e),
+        }
+    }
+    // 待审核记忆摘要（任务不持久化，每次启动按配置创建）
+    if let (true, Some(memory)) = (config.memory.review.enabled, agent.memory()) {
+        match digest_job(&config.memory.review, resolve_timezone(config.agent.timezone.as_deref())) {
+            Ok(Some(job)) => {
+                scheduler
+                    .register_handler(ExclusiveHandler::wrap(
+                        Arc::new(
+                            MemoryDigestHandler::new(memory.clone(), manager.channels())
+                                .with_quiet_hours(QuietHours::from_config(&config))
+                                .with_outbox(outbox.clone()),
+                        ),
+                        shared_state.clone(),
+                    ))
+                    .await;
+                scheduler.add_job(job).await?;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("待审核记忆摘要未启用: {}", e),
         }
     }
+    // 任务输出按各任务的投递方式发送到通道、写入日常笔记或只写日志
+    scheduler
+        .set_delivery_router(Arc::new(
+            DeliveryRouter::new(manager.channels())
+                .with_memory(agent.memory().cloned())
+                .with_quiet_hours(QuietHours::from_config(&config))
+                .with_templates(templates.clone())
+                .with_outbox(outbox.clone()),
+        ))
+        .await;
+    scheduler.start().await?;
+    if config.cron.completed_retention_hours > 0 {
+        scheduler.clone().start_cleanup(config.cron.completed_retention_hours);
+    }
 
-    // 启动所有通道
+    // 启动所有通道（异常退出后自动重启），健康状态定期写入工作目录
     manager.start_all().await?;
+    manager.start_health_reporter(config.channel_health_path());
+    let channels: Vec<String> = manager.channels().iter().map(|c| c.name().to_string()).collect();
+    if let Err(e) = event_bus.publish_topic("gateway.started", serde_json::json!({ "channels": channels })) {
+        warn!("发布网关启动事件失败: {}", e);
+    }
+
+    tokio::signal::ctrl_c().await?;
+    info!("收到退出信号，正在停止...");
+    // 事件流订阅者在连接断开前收到停止通知
+    if let Err(e) = event_bus.publish_topic("gateway.stopping", serde_json::Value::Null) {
+        warn!("发布网关停止事件失败: {}", e);
+    }
+    // 停止通道前注销 message 工具，进行中的回复不再向通道主动发送
+    agent.tools().unregister("message");
+    // 关闭数据库前停止调度器，退出过程中不再触发任务
+    if let Err(e) = scheduler.stop().await {
+        warn!("停止任务调度器失败: {}", e);
+    }
+    manager.stop_all().await?;
+    crate::db::close_all().await;
+    // 数据库关闭后整体重新加密，异常退出时需要手动运行 nanobot vault lock
+    if let Some(ref vault) = vault {
+        match vault.lock_databases(&config).await {
+            Ok(locked) => info!("已重新加密 {} 个数据库", locked),
+            Err(e) => warn!("重新加密数据库失败: {}，请运行 nanobot vault lock", e),
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,77 @@
+//! health 命令 - 检查运行中网关的存活/就绪状态
+//!
+//! 请求 API 服务的 `/readyz`（或 `--live` 时的 `/healthz`），不健康时以非零状态退出，
+//! 可用作 Docker HEALTHCHECK 或 k8s exec 探针
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::time::Duration;
+
+use crate::api::health::ReadyReport;
+use crate::config::Config;
+
+/// 请求超时（需大于提供商连通性检查的超时）
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Args)]
+pub struct HealthArgs {
+    /// 只检查进程存活（/healthz）
+    #[arg(long)]
+    live: bool,
+    /// API 服务地址，默认按 api.bind 推断（如 http://127.0.0.1:8787）
+    #[arg(long)]
+    url: Option<String>,
+}
+
+/// 监听所有地址时改为访问本机
+fn base_url(bind: &str) -> String {
+    let bind = bind
+        .replace("0.0.0.0:", "127.0.0.1:")
+        .replace("[::]:", "127.0.0.1:");
+    format!("http://{}", bind)
+}
+
+pub async fn run(config: Config, args: HealthArgs) -> Result<()> {
+    let base = args.url.unwrap_or_else(|| base_url(&config.api.bind));
+    let path = if args.live { "healthz" } else { "readyz" };
+    let url = format!("{}/{}", base.trim_end_matches('/'), path);
+
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("无法连接 {}，网关未运行或未启用 [api]", url))?;
+    let status = resp.status();
+
+    if args.live {
+        if !status.is_success() {
+            bail!("存活检查失败: HTTP {}", status.as_u16());
+        }
+        println!("✅ 存活");
+        return Ok(());
+    }
+
+    let report: ReadyReport = resp.json().await.context("解析就绪检查结果失败")?;
+    for check in &report.checks {
+        let icon = if check.ok { "✅" } else { "❌" };
+        println!("{} {}: {}", icon, check.name, check.detail);
+    }
+    if !report.ready || !status.is_success() {
+        bail!("未就绪");
+    }
+    println!("\n✅ 就绪");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("127.0.0.1:8787"), "http://127.0.0.1:8787");
+        assert_eq!(base_url("0.0.0.0:8787"), "http://127.0.0.1:8787");
+        assert_eq!(base_url("[::]:9000"), "http://127.0.0.1:9000");
+    }
+}
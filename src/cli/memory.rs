@@ -0,0 +1,167 @@
+//! memory 命令 - 查看长期记忆、审核模型写入的记忆、把共享记忆迁移到用户分区、转换对话历史格式
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use clap::Subcommand;
+
+use crate::config::{Config, ConversationFormat};
+use crate::memory::review::{render_pending, PendingMemory};
+use crate::memory::{MemoryStore, DEFAULT_CATEGORY};
+use crate::vault::Vault;
+
+#[derive(Subcommand)]
+pub enum MemoryCommand {
+    /// 列出长期记忆
+    List {
+        /// 按得分（重要性、最近使用、使用次数）从高到低排序
+        #[arg(long)]
+        by_importance: bool,
+        /// 只显示指定分类
+        #[arg(short, long)]
+        category: Option<String>,
+        /// 显示数量（0 表示全部）
+        #[arg(short, long, default_value_t = 0)]
+        limit: usize,
+        /// 显示该用户可见的记忆（用户自己的和共享的），如 telegram:123456
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// 列出待审核的记忆（memory.review.enabled 时模型写入的记忆）
+    Pending,
+    /// 批准待审核的记忆，使其成为长期记忆
+    Approve {
+        /// 待审核记忆的序号，或 all 表示全部
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// 拒绝待审核的记忆
+    Reject {
+        /// 待审核记忆的序号，或 all 表示全部
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// 把共享的长期记忆移动到用户分区（默认移动全部）
+    Migrate {
+        /// 用户（<通道>:<聊天 ID>，如 telegram:123456）
+        user: String,
+        /// 只移动指定的键（可重复）
+        #[arg(short, long = "key")]
+        keys: Vec<String>,
+        /// 只移动指定分类
+        #[arg(short, long)]
+        category: Option<String>,
+    },
+    /// 把对话历史转换为指定格式（markdown 或 jsonl）
+    Convert {
+        /// 目标格式
+        format: ConversationFormat,
+    },
+}
+
+pub async fn run(config: Config, command: MemoryCommand) -> Result<()> {
+    let vault = Vault::from_config(&config)?;
+    let store = MemoryStore::new(&config.memory.workspace_path)
+        .await?
+        .with_vault(vault)
+        .with_max_memories(config.memory.max_memories)
+        .with_per_user(config.memory.per_user)
+        .with_conversation_format(config.memory.conversation_format);
+
+    match command {
+        MemoryCommand::List { by_importance, category, limit, user } => {
+            let mut memories = store.list_visible(user.as_deref()).await?;
+            if let Some(ref category) = category {
+                memories.retain(|m| m.category.as_deref() == Some(category.as_str()));
+            }
+            if memories.is_empty() {
+                println!("暂无长期记忆");
+                if category.is_some() {
+                    println!("已有分类: {}", store.categories().await?.join("、"));
+                }
+                return Ok(());
+            }
+            let now = Utc::now();
+            if by_importance {
+                memories.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+            }
+            let total = memories.len();
+            if limit > 0 {
+                memories.truncate(limit);
+            }
+
+            println!("🧠 长期记忆（{} 条，上限 {}）:\n", total, config.memory.max_memories);
+            for m in memories {
+                println!(
+                    "  [{:>2}] {:>5.1}  {}: {}  ({}，使用 {} 次，更新于 {})",
+                    m.importance,
+                    m.score(now),
+                    m.key,
+                    m.value,
+                    m.category.as_deref().unwrap_or(DEFAULT_CATEGORY),
+                    m.hits,
+                    m.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+        MemoryCommand::Pending => {
+            println!("{}", render_pending(&store.pending_memories().await?));
+        }
+        MemoryCommand::Approve { ids } => {
+            let approved = store.approve_pending(parse_ids(&ids)?.as_deref()).await?;
+            println!("✅ 已批准 {} 条记忆: {}", approved.len(), keys_of(&approved));
+        }
+        MemoryCommand::Reject { ids } => {
+            let rejected = store.reject_pending(parse_ids(&ids)?.as_deref()).await?;
+            println!("🗑️ 已拒绝 {} 条记忆: {}", rejected.len(), keys_of(&rejected));
+        }
+        MemoryCommand::Migrate { user, keys, category } => {
+            let selected: Vec<String> = store
+                .list_memories()
+                .await?
+                .into_iter()
+                .filter(|m| keys.is_empty() || keys.contains(&m.key))
+                .filter(|m| category.is_none() || m.category == category)
+                .map(|m| m.key)
+                .collect();
+            if selected.is_empty() {
+                println!("没有需要迁移的共享记忆");
+                return Ok(());
+            }
+            let moved = store.move_memories(&store.for_user(&user), Some(&selected)).await?;
+            println!("✅ 已把 {} 条共享记忆移动到 {} 的分区: {}", moved.len(), user, moved.join("、"));
+            if !config.memory.per_user {
+                println!("提示: 请在配置中设置 memory.per_user = true，否则用户分区中的记忆不会被使用");
+            }
+        }
+        MemoryCommand::Convert { format } => {
+            let converted = store.convert_conversations(format).await?;
+            println!("✅ 已转换 {} 个对话历史为 {}", converted, format.extension());
+            if format != config.memory.conversation_format {
+                println!("提示: 请在配置中设置 memory.conversation_format = \"{}\"", match format {
+                    ConversationFormat::Markdown => "markdown",
+                    ConversationFormat::Jsonl => "jsonl",
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 解析待审核记忆的序号，`all` 表示全部（返回 None）
+fn parse_ids(ids: &[String]) -> Result<Option<Vec<u64>>> {
+    if ids.iter().any(|id| id == "all") {
+        return Ok(None);
+    }
+    ids.iter()
+        .map(|id| match id.trim_start_matches('#').parse() {
+            Ok(id) => Ok(id),
+            Err(_) => bail!("无效的序号: {}", id),
+        })
+        .collect::<Result<Vec<u64>>>()
+        .map(Some)
+}
+
+fn keys_of(memories: &[PendingMemory]) -> String {
+    memories.iter().map(|m| m.key.as_str()).collect::<Vec<_>>().join("、")
+}
@@ -1,7 +1,25 @@
 //! CLI 命令实现
 
 pub mod agent;
+pub mod backup;
+pub mod config;
+pub mod db;
+pub mod eval;
 pub mod gateway;
+pub mod health;
 pub mod init;
+pub mod memory;
+pub mod models;
+pub mod purge;
+pub mod remind;
+pub mod report;
+pub mod research;
+pub mod send;
+pub mod session;
+pub mod share;
 pub mod status;
+pub mod tasks;
+pub mod token;
 pub mod tool;
+pub mod trash;
+pub mod vault;
@@ -0,0 +1,88 @@
+//! models 命令 - 查询已配置提供商的可用模型
+
+use anyhow::Result;
+use chrono::Duration;
+
+use crate::config::Config;
+use crate::llm::models::{ModelCache, ModelInfo};
+use crate::llm::LlmManager;
+use crate::t;
+
+/// 模型列表缓存的有效期（小时）
+const CACHE_HOURS: i64 = 24;
+
+pub async fn run(config: Config, provider: Option<String>, filter: Option<String>, refresh: bool) -> Result<()> {
+    let manager = LlmManager::new(&config)?;
+    let names: Vec<String> = match provider {
+        Some(provider) => vec![provider],
+        None => {
+            let mut names: Vec<String> = manager.list_providers().into_iter().map(str::to_string).collect();
+            names.sort();
+            names
+        }
+    };
+
+    let cache_path = config.models_cache_path();
+    let mut cache = ModelCache::load(&cache_path);
+    let mut updated = false;
+    for name in &names {
+        let cached = cache.get(name, Duration::hours(CACHE_HOURS)).filter(|_| !refresh);
+        let models = match cached {
+            Some(models) => models.to_vec(),
+            None => match manager.get_provider(Some(name))?.list_models().await {
+                Ok(models) => {
+                    cache.insert(name, models.clone());
+                    updated = true;
+                    models
+                }
+                Err(e) => {
+                    println!("{}", t!("models.failed", provider = name, error = e));
+                    continue;
+                }
+            },
+        };
+
+        let models: Vec<&ModelInfo> = models
+            .iter()
+            .filter(|m| filter.as_ref().is_none_or(|f| m.id.to_lowercase().contains(&f.to_lowercase())))
+            .collect();
+        println!("{}", t!("models.header", provider = name, count = models.len()));
+        if models.is_empty() {
+            continue;
+        }
+        println!("  {:<50} {:>8}  {}", t!("models.col_id"), t!("models.col_context"), t!("models.col_price"));
+        for model in models {
+            let is_default = *name == config.agent.default_provider && model.id == config.agent.default_model;
+            println!(
+                "{} {:<50} {:>8}  {}",
+                if is_default { "⭐" } else { " " },
+                model.id,
+                model.context_length.map(format_tokens).unwrap_or_else(|| "-".to_string()),
+                format_price(model)
+            );
+        }
+    }
+    if updated {
+        cache.save(&cache_path)?;
+    }
+    println!("{}", t!("models.hint"));
+
+    Ok(())
+}
+
+/// 上下文长度：128000 -> 128K
+fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        n if n >= 1_000_000 && n % 1_000_000 == 0 => format!("{}M", n / 1_000_000),
+        n if n >= 1000 => format!("{}K", n / 1000),
+        n => n.to_string(),
+    }
+}
+
+fn format_price(model: &ModelInfo) -> String {
+    let price = |p: Option<f64>| p.map_or_else(|| "-".to_string(), |p| format!("${:.2}", p));
+    match (model.input_price, model.output_price) {
+        (None, None) => "-".to_string(),
+        (input, output) => format!("{} / {}", price(input), price(output)),
+    }
+}
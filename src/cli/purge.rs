@@ -0,0 +1,44 @@
+//! purge 命令 - 清除用户数据
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::config::Config;
+use crate::privacy::{DataStores, PurgeTarget};
+
+#[derive(Args)]
+pub struct PurgeArgs {
+    /// 清除指定用户（用户 ID 或聊天 ID）在所有通道的数据
+    #[arg(long, conflicts_with_all = ["session", "all"])]
+    user: Option<String>,
+    /// 清除指定会话（如 telegram:123456）
+    #[arg(long, conflicts_with = "all")]
+    session: Option<String>,
+    /// 清除所有对话、会话、发件箱、提醒、笔记和调试日志
+    #[arg(long, requires = "yes")]
+    all: bool,
+    /// 确认清除全部数据
+    #[arg(long)]
+    yes: bool,
+}
+
+pub async fn run(config: Config, args: PurgeArgs) -> Result<()> {
+    let target = match (args.user, args.session, args.all) {
+        (Some(user), _, _) => PurgeTarget::User(user),
+        (_, Some(session), _) => PurgeTarget::Session(session),
+        (_, _, true) => PurgeTarget::All,
+        _ => bail!("请指定 --user、--session 或 --all"),
+    };
+
+    let stores = DataStores::open(&config).await?;
+    let report = stores.purge(&target).await?;
+
+    if report.total() == 0 {
+        println!("没有找到需要清除的数据");
+    } else {
+        println!("🗑 已清除: {}", report);
+    }
+    println!("提示: 网关运行时内存中的会话和提醒不受影响，建议在网关停止后执行");
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! remind 命令 - 用自然语言创建定时提醒
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+
+use crate::config::Config;
+use crate::cron::reminder::{split_reminder, Timezone};
+use crate::cron::{MisfirePolicy, Scheduler};
+
+pub async fn run(
+    config: Config,
+    input: &str,
+    channel: Option<String>,
+    chat_id: Option<String>,
+    misfire: Option<MisfirePolicy>,
+) -> Result<()> {
+    let (when, text) = split_reminder(input)
+        .ok_or_else(|| anyhow!("格式错误，应为 \"<时间>: <内容>\"，如 \"明天早上八点: 开会\""))?;
+
+    let timezone = match config.agent.timezone.as_deref() {
+        Some(tz) => tz.parse::<Timezone>().context("agent.timezone 配置无效")?,
+        None => Timezone::local(),
+    };
+    let schedule = timezone.parse_when(when, Utc::now())?;
+    let description = schedule.describe(timezone);
+
+    // 写入持久化任务库，由 gateway 中的调度器执行
+    let scheduler = Scheduler::with_db(&config.cron_db_path().to_string_lossy()).await?;
+    let job = schedule.into_job(text, channel.as_deref(), chat_id.as_deref());
+    let job = match misfire {
+        Some(policy) => job.with_misfire_policy(policy),
+        None => job,
+    };
+    let job_id = scheduler.add_job(job).await?;
+
+    println!("⏰ 已创建提醒: {}", text);
+    println!("   时间: {}", description);
+    println!("   任务: {}", job_id);
+
+    if channel.is_none() || chat_id.is_none() {
+        println!("   提示: 未指定 --channel/--chat-id，提醒将只记录在 gateway 日志中");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,237 @@
+//! report 命令 - 把会话导出为独立的 HTML 报告
+//!
+//! 报告包含聊天气泡、可折叠的工具调用（参数与结果）、时间戳以及令牌/费用汇总，
+//! 样式内联在页面中，可直接发给同事查看
+
+use anyhow::{bail, Result};
+use chrono::Local;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::budget::{Budget, ModelUsage};
+use crate::config::Config;
+use crate::llm::ToolCall;
+use crate::memory::{ConversationMessage, MemoryStore};
+use crate::session::{Session, SessionManager};
+use crate::vault::Vault;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", "PingFang SC", "Microsoft YaHei", sans-serif; background: #f4f5f7; color: #222; margin: 0; }
+main { max-width: 860px; margin: 0 auto; padding: 24px 16px 48px; }
+h1 { font-size: 1.4em; margin-bottom: 4px; }
+.meta { color: #666; font-size: 0.9em; margin-bottom: 16px; }
+table { border-collapse: collapse; margin: 8px 0 24px; font-size: 0.9em; background: #fff; }
+th, td { border: 1px solid #ddd; padding: 4px 10px; text-align: left; }
+th { background: #fafafa; }
+.msg { display: flex; flex-direction: column; margin: 12px 0; }
+.msg.user { align-items: flex-end; }
+.bubble { max-width: 80%; padding: 10px 14px; border-radius: 12px; white-space: pre-wrap; word-break: break-word; line-height: 1.5; }
+.user .bubble { background: #95ec69; }
+.assistant .bubble { background: #fff; border: 1px solid #e3e3e3; }
+.system .bubble { background: #fff8e1; border: 1px dashed #e0c060; font-size: 0.9em; }
+.time { color: #999; font-size: 0.75em; margin: 2px 6px; }
+details { max-width: 80%; margin: 4px 0; background: #eef2f7; border-radius: 8px; padding: 6px 10px; font-size: 0.85em; }
+summary { cursor: pointer; font-weight: 600; }
+pre { white-space: pre-wrap; word-break: break-word; background: #fff; padding: 8px; border-radius: 6px; margin: 6px 0; max-height: 480px; overflow: auto; }
+"#;
+
+pub async fn run(config: Config, session_id: &str, output: Option<PathBuf>) -> Result<()> {
+    let vault = Vault::from_config(&config)?;
+    let store = MemoryStore::new(&config.memory.workspace_path)
+        .await?
+        .with_vault(vault)
+        .with_conversation_format(config.memory.conversation_format);
+    let messages = store.get_conversation(session_id, 0).await?;
+    if messages.is_empty() {
+        bail!("会话 {} 没有对话记录", session_id);
+    }
+
+    let session = SessionManager::with_db(&config.sessions_db_path().to_string_lossy())
+        .await?
+        .load_session(session_id)
+        .await?;
+    let usage = match Budget::from_config(&config).await? {
+        Some(budget) => budget.user_usage(session_id).await?,
+        None => Vec::new(),
+    };
+
+    let html = render_report(session_id, &messages, session.as_ref(), &usage);
+    let path = output.unwrap_or_else(|| PathBuf::from(format!("{}.html", session_id.replace([':', '/', '\\'], "_"))));
+    tokio::fs::write(&path, html).await?;
+    println!("📄 已生成报告: {}（{} 条消息）", path.display(), messages.len());
+    Ok(())
+}
+
+/// 渲染 HTML 报告
+pub(crate) fn render_report(
+    session_id: &str,
+    messages: &[ConversationMessage],
+    session: Option<&Session>,
+    usage: &[ModelUsage],
+) -> String {
+    let mut body = format!("<h1>💬 会话 {}</h1>\n", escape(session_id));
+    let first = messages.first().map(|m| m.created_at.with_timezone(&Local));
+    let last = messages.last().map(|m| m.created_at.with_timezone(&Local));
+    if let (Some(first), Some(last)) = (first, last) {
+        body.push_str(&format!(
+            "<div class=\"meta\">{} — {}，共 {} 条消息</div>\n",
+            first.format("%Y-%m-%d %H:%M:%S"),
+            last.format("%Y-%m-%d %H:%M:%S"),
+            messages.len()
+        ));
+    }
+    body.push_str(&render_summary(session, usage));
+
+    // 工具结果按调用 ID 归入对应的工具调用
+    let results: HashMap<&str, &ConversationMessage> = messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .filter_map(|m| Some((m.tool_call_id.as_deref()?, m)))
+        .collect();
+    let mut shown: HashSet<String> = HashSet::new();
+
+    for message in messages {
+        let time = message.created_at.with_timezone(&Local).format("%m-%d %H:%M:%S");
+        match message.role.as_str() {
+            "tool" => {
+                // 找不到对应调用的工具结果单独显示
+                if message.tool_call_id.as_deref().is_some_and(|id| shown.contains(id)) {
+                    continue;
+                }
+                body.push_str(&format!(
+                    "<div class=\"msg assistant\"><details><summary>🔧 工具结果</summary><pre>{}</pre></details><span class=\"time\">{}</span></div>\n",
+                    escape(&message.content),
+                    time
+                ));
+            }
+            role => {
+                let class = match role {
+                    "user" => "user",
+                    "system" => "system",
+                    _ => "assistant",
+                };
+                body.push_str(&format!("<div class=\"msg {}\">", class));
+                if !message.content.trim().is_empty() {
+                    body.push_str(&format!("<div class=\"bubble\">{}</div>", escape(message.content.trim())));
+                }
+                let calls: Vec<ToolCall> = message
+                    .tool_calls
+                    .as_deref()
+                    .and_then(|c| serde_json::from_str(c).ok())
+                    .unwrap_or_default();
+                for call in &calls {
+                    let arguments = serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                        .and_then(|v| serde_json::to_string_pretty(&v))
+                        .unwrap_or_else(|_| call.function.arguments.clone());
+                    body.push_str(&format!(
+                        "<details><summary>🔧 {}</summary><div>参数</div><pre>{}</pre>",
+                        escape(&call.function.name),
+                        escape(&arguments)
+                    ));
+                    if let Some(result) = results.get(call.id.as_str()) {
+                        body.push_str(&format!("<div>结果</div><pre>{}</pre>", escape(&result.content)));
+                        shown.insert(call.id.clone());
+                    }
+                    body.push_str("</details>");
+                }
+                body.push_str(&format!("<span class=\"time\">{}</span></div>\n", time));
+            }
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>会话 {}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(session_id),
+        STYLE,
+        body
+    )
+}
+
+/// 会话统计和按模型汇总的用量
+fn render_summary(session: Option<&Session>, usage: &[ModelUsage]) -> String {
+    let mut html = String::new();
+    if let Some(s) = session {
+        html.push_str(&format!(
+            "<table><tr><th>通道</th><th>消息</th><th>工具调用</th><th>令牌</th></tr>\
+             <tr><td>{} ({})</td><td>{}（用户 {}，助手 {}）</td><td>{}</td><td>{}</td></tr></table>\n",
+            escape(&s.metadata.channel),
+            escape(&s.metadata.channel_id),
+            s.stats.message_count,
+            s.stats.user_message_count,
+            s.stats.assistant_message_count,
+            s.stats.tool_call_count,
+            s.stats.total_tokens
+        ));
+    }
+    if !usage.is_empty() {
+        html.push_str("<table><tr><th>模型</th><th>输入令牌</th><th>输出令牌</th><th>费用</th></tr>");
+        for u in usage {
+            html.push_str(&format!(
+                "<tr><td>{}/{}</td><td>{}</td><td>{}</td><td>${:.4}</td></tr>",
+                escape(&u.provider),
+                escape(&u.model),
+                u.prompt_tokens,
+                u.completion_tokens,
+                u.cost_usd
+            ));
+        }
+        let total: f64 = usage.iter().map(|u| u.cost_usd).sum();
+        html.push_str(&format!("<tr><th colspan=\"3\">合计</th><th>${:.4}</th></tr></table>\n", total));
+    }
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn message(role: &str, content: &str, tool_calls: Option<&str>, tool_call_id: Option<&str>) -> ConversationMessage {
+        ConversationMessage {
+            id: 0,
+            session_id: "cli:test".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: tool_calls.map(str::to_string),
+            tool_call_id: tool_call_id.map(str::to_string),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_render_report() {
+        let calls = r#"[{"id":"c1","type":"function","function":{"name":"shell","arguments":"{\"command\":\"ls\"}"}}]"#;
+        let messages = vec![
+            message("user", "列出 <src> 目录", None, None),
+            message("assistant", "", Some(calls), None),
+            message("tool", "main.rs", None, Some("c1")),
+            message("tool", "孤立的结果", None, Some("c9")),
+            message("assistant", "有 main.rs", None, None),
+        ];
+        let usage = vec![ModelUsage {
+            provider: "deepseek".to_string(),
+            model: "deepseek-chat".to_string(),
+            prompt_tokens: 1200,
+            completion_tokens: 300,
+            cost_usd: 0.0123,
+        }];
+
+        let html = render_report("cli:test", &messages, None, &usage);
+        assert!(html.contains("列出 &lt;src&gt; 目录"));
+        assert!(html.contains("<summary>🔧 shell</summary>"));
+        assert!(html.contains("&quot;command&quot;: &quot;ls&quot;"));
+        // 工具结果只在对应的调用中显示一次
+        assert_eq!(html.matches("main.rs</pre>").count(), 1);
+        assert!(html.contains("孤立的结果"));
+        assert!(html.contains("$0.0123"));
+    }
+}
@@ -0,0 +1,26 @@
+//! research 命令 - 对问题进行深度调研，输出带引用的报告
+
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::agent::Agent;
+use crate::config::Config;
+use crate::tools::{with_progress_notifier, ProgressNotifier};
+
+pub async fn run(config: Config, question: &str) -> Result<()> {
+    let agent = Agent::new(config, None).await?;
+
+    println!("🔬 调研: {}\n", question);
+    let progress: ProgressNotifier = Arc::new(|_tool: &str, message: &str| eprintln!("{}", message));
+    let report = with_progress_notifier(progress, agent.research(question, None)).await?;
+
+    println!("{}\n", report.report);
+    println!(
+        "共 {} 个子问题、{} 轮搜索、{} 个来源，使用 {} 个令牌",
+        report.questions.len(),
+        report.iterations,
+        report.sources.len(),
+        report.tokens
+    );
+    Ok(())
+}
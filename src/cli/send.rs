@@ -0,0 +1,54 @@
+//! send 命令 - 不经过 Agent，直接通过通道发送通知（供脚本和定时任务使用）
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::channel::{BroadcastTarget, ChannelManager, ChannelTarget, ChannelServices, InboundMessage, MessageHandler};
+use crate::config::Config;
+
+/// 只用于发送，忽略收到的消息
+struct SendOnlyHandler;
+
+#[async_trait]
+impl MessageHandler for SendOnlyHandler {
+    async fn handle(&self, _msg: InboundMessage) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+pub async fn run(config: Config, channel: &str, to: Vec<String>, message: &str) -> Result<()> {
+    if message.trim().is_empty() {
+        bail!("消息内容不能为空");
+    }
+
+    // 本进程发送后即退出，不经发件箱
+    let mut manager = ChannelManager::new();
+    manager.create(channel, &config, Arc::new(SendOnlyHandler), &ChannelServices::default())?;
+
+    let targets = to
+        .iter()
+        .map(|to| {
+            Ok(BroadcastTarget {
+                channel: channel.to_string(),
+                to: ChannelTarget::parse(to)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let results = manager.broadcast(&targets, message).await;
+
+    let mut failed = 0;
+    for result in &results {
+        match result.error {
+            Some(ref e) => {
+                failed += 1;
+                eprintln!("❌ {}:{} 发送失败: {}", result.channel, result.to, e);
+            }
+            None => println!("✅ 已发送到 {}:{}", result.channel, result.to),
+        }
+    }
+    if failed > 0 {
+        bail!("{} 个目标发送失败", failed);
+    }
+    Ok(())
+}
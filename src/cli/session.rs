@@ -0,0 +1,183 @@
+//! session 命令 - 查看会话统计，保存和重放上下文快照
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use std::path::PathBuf;
+
+use crate::agent::snapshot::AgentSnapshot;
+use crate::agent::Agent;
+use crate::config::Config;
+use crate::session::{Session, SessionManager, PERSONA_PROPERTY};
+
+#[derive(Subcommand)]
+pub enum SessionCommand {
+    /// 列出最近的会话
+    List {
+        /// 显示数量
+        #[arg(short, long, default_value_t = 20)]
+        limit: i64,
+        /// 只列出标题包含该文本的会话
+        #[arg(short, long)]
+        search: Option<String>,
+    },
+    /// 查看会话详情
+    Show {
+        /// 会话 ID（如 telegram:123456）
+        id: String,
+    },
+    /// 保存会话上下文快照（从对话历史加载）
+    Snapshot {
+        /// 会话 ID（如 telegram:123456）
+        id: String,
+        /// 输出文件，未指定时输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 从快照恢复上下文，发送一条消息重放，或进入交互式对话
+    Restore {
+        /// 快照文件
+        file: PathBuf,
+        /// 使用的模型（provider/model），未指定时使用快照中的模型
+        #[arg(short, long)]
+        model: Option<String>,
+        /// 恢复后使用的会话 ID（新的对话会写入该会话的历史），默认生成新的 ID，不影响原会话
+        #[arg(short, long)]
+        session: Option<String>,
+        /// 发送这条消息并输出回复后退出
+        #[arg(long)]
+        message: Option<String>,
+    },
+}
+
+pub async fn run(config: Config, command: SessionCommand) -> Result<()> {
+    let db_path = config.sessions_db_path().to_string_lossy().to_string();
+
+    match command {
+        SessionCommand::List { limit, search } => {
+            let manager = SessionManager::with_db(&db_path).await?;
+            let sessions = match search {
+                Some(ref query) => manager.search_sessions(query, limit).await?,
+                None => manager.list_sessions(limit).await?,
+            };
+            if sessions.is_empty() {
+                println!("暂无会话记录");
+                return Ok(());
+            }
+            println!("💬 最近会话:\n");
+            for s in sessions {
+                println!(
+                    "  {}  [{}]  消息 {}，工具调用 {}，令牌 {}，最后活动 {}",
+                    s.id,
+                    s.state.as_str(),
+                    s.stats.message_count,
+                    s.stats.tool_call_count,
+                    s.stats.total_tokens,
+                    s.last_activity.format("%Y-%m-%d %H:%M:%S")
+                );
+                if let Some(title) = s.title() {
+                    println!("    📝 {}", title);
+                }
+            }
+        }
+        SessionCommand::Show { id } => {
+            let session = SessionManager::with_db(&db_path)
+                .await?
+                .load_session(&id)
+                .await?
+                .ok_or_else(|| anyhow!("会话不存在: {}", id))?;
+            print_session(&session);
+        }
+        SessionCommand::Snapshot { id, output } => snapshot(config, &id, output).await?,
+        SessionCommand::Restore {
+            file,
+            model,
+            session,
+            message,
+        } => restore(config, file, model, session, message).await?,
+    }
+
+    Ok(())
+}
+
+async fn snapshot(config: Config, id: &str, output: Option<PathBuf>) -> Result<()> {
+    let agent = Agent::new(config, Some(id.to_string())).await?;
+    if agent.context_length().await <= 1 {
+        return Err(anyhow!("会话 {} 没有对话历史", id));
+    }
+    match output {
+        Some(path) => {
+            crate::cli::agent::save_snapshot(&agent, &path).await?;
+            eprintln!("📸 已保存会话 {} 的快照: {}", id, path.display());
+        }
+        None => println!("{}", agent.snapshot().await.to_json()?),
+    }
+    Ok(())
+}
+
+async fn restore(
+    config: Config,
+    file: PathBuf,
+    model: Option<String>,
+    session: Option<String>,
+    message: Option<String>,
+) -> Result<()> {
+    let mut snapshot = AgentSnapshot::load(&file)?;
+    println!(
+        "📸 快照: 会话 {}，{} 条消息，模型 {}，创建于 {}（nanobot {}）",
+        snapshot.session_id,
+        snapshot.messages.len(),
+        snapshot.model,
+        snapshot.created_at.format("%Y-%m-%d %H:%M:%S"),
+        snapshot.nanobot_version
+    );
+    snapshot.session_id = session.unwrap_or_else(|| format!("replay:{}", uuid::Uuid::new_v4()));
+
+    let agent = Agent::new(config, None).await?;
+    agent.restore(snapshot).await;
+    if let Some(ref model) = model {
+        agent.set_model(Some(model))?;
+    }
+    println!("🤖 会话 {}，模型 {}\n", agent.session_id().await, agent.model_name());
+
+    match message {
+        Some(message) => {
+            let response = agent.chat(message).await?;
+            println!("{}", response.content);
+            Ok(())
+        }
+        None => crate::cli::agent::interactive(&agent).await,
+    }
+}
+
+fn print_session(s: &Session) {
+    println!("💬 会话 {}\n", s.id);
+    if let Some(title) = s.title() {
+        println!("  标题: {}", title);
+    }
+    println!("  状态: {}", s.state.as_str());
+    println!("  通道: {} ({})", s.metadata.channel, s.metadata.channel_id);
+    if let Some(ref user_id) = s.metadata.user_id {
+        println!("  用户: {}", user_id);
+    }
+    println!("  创建时间: {}", s.created_at.format("%Y-%m-%d %H:%M:%S"));
+    println!("  最后活动: {}", s.last_activity.format("%Y-%m-%d %H:%M:%S"));
+    if let Some(ended_at) = s.ended_at {
+        println!("  结束时间: {}", ended_at.format("%Y-%m-%d %H:%M:%S"));
+    }
+
+    println!("\n📊 统计:");
+    println!(
+        "  消息数: {}（用户 {}，助手 {}）",
+        s.stats.message_count, s.stats.user_message_count, s.stats.assistant_message_count
+    );
+    println!("  工具调用: {}", s.stats.tool_call_count);
+    println!("  令牌用量: {}", s.stats.total_tokens);
+    if let Some(persona) = s.metadata.properties.get(PERSONA_PROPERTY) {
+        println!("  当前角色: {}", persona);
+    }
+    let mut personas: Vec<_> = s.stats.persona_tokens.iter().collect();
+    personas.sort();
+    for (persona, tokens) in personas {
+        println!("  角色 {}: {} 令牌", persona, tokens);
+    }
+}
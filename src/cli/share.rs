@@ -0,0 +1,207 @@
+//! share 命令 - 导出打码后的会话，发给他人排查问题
+//!
+//! 对话内容和工具调用参数经 [`Redactor`] 去掉密钥和个人信息后写成独立的 HTML 或 Markdown 文件；
+//! 配置了 `[share] upload_url` 时可用 `--upload` 上传，输出分享链接
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use clap::Args;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::{Config, ShareConfig};
+use crate::llm::ToolCall;
+use crate::memory::{ConversationMessage, MemoryStore};
+use crate::privacy::redact::Redactor;
+use crate::vault::Vault;
+
+/// 上传超时
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Args)]
+pub struct ShareArgs {
+    /// 会话 ID（如 telegram:123456）
+    #[arg(long)]
+    session: String,
+    /// 导出为 Markdown（默认 HTML）
+    #[arg(long)]
+    markdown: bool,
+    /// 输出文件（默认 share-<通道>-<时间>.html/.md）
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// 上传到 `[share] upload_url`，输出分享链接
+    #[arg(long)]
+    upload: bool,
+}
+
+pub async fn run(config: Config, args: ShareArgs) -> Result<()> {
+    if args.upload && config.share.upload_url.is_none() {
+        bail!("未配置 [share] upload_url，无法上传");
+    }
+
+    let vault = Vault::from_config(&config)?;
+    let store = MemoryStore::new(&config.memory.workspace_path)
+        .await?
+        .with_vault(vault)
+        .with_conversation_format(config.memory.conversation_format);
+    let messages = store.get_conversation(&args.session, 0).await?;
+    if messages.is_empty() {
+        bail!("会话 {} 没有对话记录", args.session);
+    }
+
+    let redactor = Redactor::from_config(&config)?;
+    let session_id = redactor.redact_session_id(&args.session);
+    let messages: Vec<ConversationMessage> = messages.iter().map(|m| redactor.redact_message(m)).collect();
+    let content = if args.markdown {
+        render_markdown(&session_id, &messages)
+    } else {
+        super::report::render_report(&session_id, &messages, None, &[])
+    };
+
+    let extension = if args.markdown { "md" } else { "html" };
+    let path = args.output.unwrap_or_else(|| {
+        let channel = args.session.split(':').next().unwrap_or("session");
+        PathBuf::from(format!("share-{}-{}.{}", channel, Local::now().format("%Y%m%d%H%M%S"), extension))
+    });
+    tokio::fs::write(&path, &content).await?;
+    println!("🔒 已导出打码后的会话: {}（{} 条消息）", path.display(), messages.len());
+    println!("   分享前请再检查一遍，自动打码无法保证覆盖所有敏感信息");
+
+    if args.upload {
+        let content_type = if args.markdown { "text/markdown" } else { "text/html" };
+        let url = upload(&config.share, content, content_type).await?;
+        println!("🔗 分享链接: {}", url);
+    }
+    Ok(())
+}
+
+/// 上传内容，返回响应中的链接（JSON 的 url / link 字段或纯文本响应）
+async fn upload(config: &ShareConfig, content: String, content_type: &str) -> Result<String> {
+    let url = config.upload_url.as_deref().unwrap_or_default();
+    let client = reqwest::Client::builder().timeout(UPLOAD_TIMEOUT).build()?;
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, format!("{}; charset=utf-8", content_type))
+        .body(content);
+    for (name, value) in &config.upload_headers {
+        request = request.header(name, value);
+    }
+    let resp = request.send().await.with_context(|| format!("上传到 {} 失败", url))?;
+    let status = resp.status();
+    let body = resp.text().await?;
+    if !status.is_success() {
+        bail!("上传失败: HTTP {} {}", status.as_u16(), body.trim());
+    }
+    share_link(&body).ok_or_else(|| anyhow::anyhow!("无法从上传响应中解析链接: {}", body.trim()))
+}
+
+/// 从上传响应中取出链接
+fn share_link(body: &str) -> Option<String> {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(body) {
+        return ["url", "link"]
+            .iter()
+            .find_map(|k| value.get(k).and_then(|v| v.as_str()))
+            .map(str::to_string);
+    }
+    let body = body.trim();
+    body.starts_with("http").then(|| body.to_string())
+}
+
+/// 渲染 Markdown
+fn render_markdown(session_id: &str, messages: &[ConversationMessage]) -> String {
+    let mut md = format!("# 💬 会话 {}\n\n", session_id);
+    if let (Some(first), Some(last)) = (messages.first(), messages.last()) {
+        md.push_str(&format!(
+            "> {} — {}，共 {} 条消息（已打码）\n\n",
+            first.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            last.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            messages.len()
+        ));
+    }
+
+    // 工具结果按调用 ID 归入对应的工具调用
+    let results: HashMap<&str, &ConversationMessage> = messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .filter_map(|m| Some((m.tool_call_id.as_deref()?, m)))
+        .collect();
+    let calls_of = |message: &ConversationMessage| -> Vec<ToolCall> {
+        message
+            .tool_calls
+            .as_deref()
+            .and_then(|c| serde_json::from_str(c).ok())
+            .unwrap_or_default()
+    };
+    let called: HashSet<String> = messages.iter().flat_map(calls_of).map(|c| c.id).collect();
+
+    for message in messages {
+        let time = message.created_at.with_timezone(&Local).format("%m-%d %H:%M:%S");
+        let speaker = match message.role.as_str() {
+            "user" => "🧑 用户",
+            "system" => "⚙️ 系统",
+            "tool" => {
+                // 找不到对应调用的工具结果单独显示
+                if message.tool_call_id.as_ref().is_some_and(|id| called.contains(id)) {
+                    continue;
+                }
+                "🔧 工具结果"
+            }
+            _ => "🤖 助手",
+        };
+        md.push_str(&format!("**{}** · {}\n\n", speaker, time));
+        if !message.content.trim().is_empty() {
+            md.push_str(message.content.trim());
+            md.push_str("\n\n");
+        }
+        for call in &calls_of(message) {
+            md.push_str(&format!("🔧 `{}`\n\n```json\n{}\n```\n\n", call.function.name, call.function.arguments));
+            if let Some(result) = results.get(call.id.as_str()) {
+                md.push_str(&format!("```\n{}\n```\n\n", result.content.trim()));
+            }
+        }
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_render_markdown() {
+        let message = |role: &str, content: &str, tool_calls: Option<&str>, tool_call_id: Option<&str>| ConversationMessage {
+            id: 0,
+            session_id: "telegram:42".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: tool_calls.map(str::to_string),
+            tool_call_id: tool_call_id.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        let calls = r#"[{"id":"c1","type":"function","function":{"name":"shell","arguments":"{\"command\":\"ls\"}"}}]"#;
+        let messages = vec![
+            message("user", "列出目录", None, None),
+            message("assistant", "", Some(calls), None),
+            message("tool", "main.rs", None, Some("c1")),
+            message("tool", "孤立的结果", None, Some("c9")),
+            message("assistant", "有 main.rs", None, None),
+        ];
+
+        let md = render_markdown("telegram:***", &messages);
+        assert!(md.starts_with("# 💬 会话 telegram:***"));
+        assert!(md.contains("🔧 `shell`\n\n```json\n{\"command\":\"ls\"}\n```"));
+        // 工具结果只在对应的调用下显示一次
+        assert_eq!(md.matches("main.rs\n```").count(), 1);
+        assert_eq!(md.matches("🔧 工具结果").count(), 1);
+        assert!(md.contains("孤立的结果"));
+    }
+
+    #[test]
+    fn test_share_link() {
+        assert_eq!(share_link(r#"{"url":"https://paste.example/abc"}"#).as_deref(), Some("https://paste.example/abc"));
+        assert_eq!(share_link("https://0x0.st/abc.md\n").as_deref(), Some("https://0x0.st/abc.md"));
+        assert_eq!(share_link("error"), None);
+    }
+}
@@ -2,68 +2,190 @@
 
 use anyhow::Result;
 
+use crate::channel::health::HealthSnapshot;
+use crate::channel::outbox::Outbox;
 use crate::config::Config;
+use crate::llm::probe::ProbeStore;
+use crate::session::SessionManager;
+use crate::t;
 
 pub async fn run(config: Config) -> Result<()> {
-    println!("🤖 Nanobot 状态\n");
+    println!("{}", t!("status.title"));
 
     // 显示配置信息
-    println!("📁 配置:");
-    println!("  默认提供商: {}", config.agent.default_provider);
-    println!("  默认模型: {}", config.agent.default_model);
-    println!("  最大上下文: {}", config.agent.max_context);
+    println!("{}", t!("status.config"));
+    println!("{}", t!("status.default_provider", value = config.agent.default_provider));
+    println!("{}", t!("status.default_model", value = config.agent.default_model));
+    println!("{}", t!("status.max_context", value = config.agent.max_context));
 
     // 检查 LLM 提供商
-    println!("\n🧠 LLM 提供商:");
+    println!("{}", t!("status.providers"));
     
     if config.llm.openrouter.api_key.is_some() {
-        println!("  ✅ OpenRouter");
+        println!("{}", t!("status.configured", name = "OpenRouter"));
     } else {
-        println!("  ❌ OpenRouter（未配置）");
+        println!("{}", t!("status.not_configured", name = "OpenRouter"));
     }
 
     if config.llm.deepseek.api_key.is_some() {
-        println!("  ✅ DeepSeek");
+        println!("{}", t!("status.configured", name = "DeepSeek"));
     } else {
-        println!("  ❌ DeepSeek（未配置）");
+        println!("{}", t!("status.not_configured", name = "DeepSeek"));
     }
 
     if config.llm.openai.api_key.is_some() {
-        println!("  ✅ OpenAI");
+        println!("{}", t!("status.configured", name = "OpenAI"));
     } else {
-        println!("  ❌ OpenAI（未配置）");
+        println!("{}", t!("status.not_configured", name = "OpenAI"));
     }
 
     if config.llm.anthropic.api_key.is_some() {
-        println!("  ✅ Anthropic");
+        println!("{}", t!("status.configured", name = "Anthropic"));
     } else {
-        println!("  ❌ Anthropic（未配置）");
+        println!("{}", t!("status.not_configured", name = "Anthropic"));
+    }
+
+    // 网关后台探测记录的提供商可用性
+    if config.llm.probe.enabled && config.usage_db_path().exists() {
+        if let Ok(store) = ProbeStore::open(&config.usage_db_path().to_string_lossy()).await {
+            if let Ok(summary) = store.availability(chrono::Utc::now() - chrono::Duration::hours(24)).await {
+                println!("{}", t!("status.provider_health"));
+                if summary.is_empty() {
+                    println!("{}", t!("status.provider_health_empty"));
+                }
+                for p in &summary {
+                    let latency = p.avg_latency_ms.map_or_else(|| "-".to_string(), |ms| format!("{} ms", ms));
+                    println!(
+                        "{}",
+                        t!(
+                            "status.provider_health_line",
+                            name = p.provider,
+                            rate = format!("{:.1}", p.success_rate()),
+                            successes = p.successes,
+                            checks = p.checks,
+                            latency = latency
+                        )
+                    );
+                    if let Some(ref last) = p.last {
+                        let time = last.checked_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S");
+                        match last.error {
+                            Some(ref e) => println!("{}", t!("status.provider_last_failed", time = time, error = e)),
+                            None => println!("{}", t!("status.provider_last_ok", time = time, latency = last.latency_ms)),
+                        }
+                    }
+                }
+            }
+        }
     }
 
     // 检查通道
-    println!("\n📡 通道:");
+    println!("{}", t!("status.channels"));
     
     if config.channel.telegram.bot_token.is_some() {
-        println!("  ✅ Telegram Bot");
+        println!("{}", t!("status.configured", name = "Telegram Bot"));
     } else {
-        println!("  ❌ Telegram Bot（未配置）");
+        println!("{}", t!("status.not_configured", name = "Telegram Bot"));
+    }
+
+    // 网关运行时写入的通道健康状态
+    if let Ok(snapshot) = HealthSnapshot::load(&config.channel_health_path()) {
+        let age = (chrono::Utc::now() - snapshot.updated_at).num_seconds();
+        println!("{}", t!("status.health", age = age.max(0)));
+        for h in &snapshot.channels {
+            println!(
+                "{}",
+                t!(
+                    "status.health_line",
+                    name = h.name,
+                    state = h.state.as_str(),
+                    inbound = h.inbound_count,
+                    outbound = h.outbound_count,
+                    errors = h.error_count,
+                    restarts = h.restart_count
+                )
+            );
+            if let Some(last) = h.last_inbound.max(h.last_outbound) {
+                let time = last.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S");
+                println!("{}", t!("status.last_activity", time = time));
+            }
+            if let Some(ref e) = h.last_error {
+                println!("{}", t!("status.last_error", error = e));
+            }
+        }
+    }
+
+    // 发件箱投递统计
+    if config.channel.outbox && config.outbox_db_path().exists() {
+        if let Ok(outbox) = Outbox::new(&config.outbox_db_path().to_string_lossy()).await {
+            if let Ok(stats) = outbox.stats().await {
+                println!("{}", t!("status.outbox"));
+                if stats.is_empty() {
+                    println!("{}", t!("status.outbox_empty"));
+                }
+                let mut channels: Vec<_> = stats.into_iter().collect();
+                channels.sort_by(|a, b| a.0.cmp(&b.0));
+                for (channel, s) in channels {
+                    println!(
+                        "{}",
+                        t!(
+                            "status.outbox_line",
+                            channel = channel,
+                            delivered = s.delivered,
+                            pending = s.pending,
+                            failed = s.failed
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    // 会话统计
+    if config.sessions_db_path().exists() {
+        if let Ok(manager) =
+            SessionManager::with_db(&config.sessions_db_path().to_string_lossy()).await
+        {
+            if let Ok((count, stats)) = manager.stored_stats().await {
+                println!("{}", t!("status.sessions"));
+                println!("{}", t!("status.session_count", count = count));
+                println!(
+                    "{}",
+                    t!(
+                        "status.message_count",
+                        total = stats.message_count,
+                        user = stats.user_message_count,
+                        assistant = stats.assistant_message_count
+                    )
+                );
+                println!("{}", t!("status.tool_calls", count = stats.tool_call_count));
+                println!("{}", t!("status.tokens", count = stats.total_tokens));
+            }
+        }
     }
 
     // 检查工具
-    println!("\n🔧 工具:");
+    println!("{}", t!("status.tools"));
     if config.tools.search_api_key.is_some() {
-        println!("  ✅ Web 搜索");
+        println!("{}", t!("status.configured", name = t!("status.web_search")));
     } else {
-        println!("  ❌ Web 搜索（未配置）");
+        println!("{}", t!("status.not_configured", name = t!("status.web_search")));
     }
 
     // 内存系统
-    println!("\n💾 内存:");
-    println!("  工作目录: {}", config.memory.workspace_path.display());
-    println!("  最大记忆数: {}", config.memory.max_memories);
+    println!("{}", t!("status.memory"));
+    println!("{}", t!("status.workspace", path = config.memory.workspace_path.display()));
+    println!("{}", t!("status.max_memories", count = config.memory.max_memories));
+
+    println!("{}", t!("status.embeddings"));
+    match crate::embeddings::EmbeddingProviderFactory::create(&config.embeddings) {
+        Ok(provider) => {
+            let model = config.embeddings.model.clone().unwrap_or_else(|| t!("status.embedding_default_model"));
+            println!("{}", t!("status.embedding_ok", name = provider.name(), model = model));
+        }
+        Err(e) => println!("{}", t!("status.embedding_error", backend = config.embeddings.backend, error = e)),
+    }
 
-    println!("\n使用 `nanobot agent` 启动交互式对话");
-    println!("使用 `nanobot gateway` 启动网关服务");
+    println!("{}", t!("status.hint"));
 
     Ok(())
 }
@@ -0,0 +1,66 @@
+//! tasks 命令 - 查看后台任务
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+
+use crate::config::Config;
+use crate::tasks::TaskManager;
+
+#[derive(Subcommand)]
+pub enum TasksCommand {
+    /// 列出最近的后台任务
+    List {
+        /// 显示数量
+        #[arg(short, long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// 查看任务详情和输出
+    Show {
+        /// 任务 ID
+        id: String,
+    },
+}
+
+pub async fn run(config: Config, command: TasksCommand) -> Result<()> {
+    let tasks = TaskManager::new(&config.tasks_db_path().to_string_lossy(), config.tasks.max_running).await?;
+
+    match command {
+        TasksCommand::List { limit } => {
+            let list = tasks.list(limit).await?;
+            if list.is_empty() {
+                println!("暂无后台任务");
+                return Ok(());
+            }
+            println!("🧵 后台任务:\n");
+            for t in list {
+                println!(
+                    "  {}  [{}]  {}  {}  会话 {}，创建于 {}",
+                    t.id,
+                    t.status.label(),
+                    t.tool,
+                    t.args,
+                    t.session_id.as_deref().unwrap_or("-"),
+                    t.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+                );
+            }
+        }
+        TasksCommand::Show { id } => {
+            let t = tasks.get(&id).await?.ok_or_else(|| anyhow!("任务不存在: {}", id))?;
+            println!("🧵 任务 {}\n", t.id);
+            println!("  状态: {}", t.status.label());
+            println!("  工具: {} {}", t.tool, t.args);
+            if let Some(ref session_id) = t.session_id {
+                println!("  会话: {}", session_id);
+            }
+            println!("  创建时间: {}", t.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"));
+            if let Some(finished_at) = t.finished_at {
+                println!("  结束时间: {}", finished_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"));
+            }
+            if let Some(ref output) = t.output {
+                println!("\n{}", output);
+            }
+        }
+    }
+
+    Ok(())
+}
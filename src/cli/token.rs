@@ -0,0 +1,98 @@
+//! token 命令 - 管理 HTTP API 的访问令牌
+
+use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
+use clap::Subcommand;
+
+use crate::api::auth::{ApiAuth, ApiToken, TokenStore};
+use crate::config::{ApiScope, Config};
+use crate::t;
+
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    /// 创建令牌（令牌只显示这一次，数据库中只保存哈希）
+    Create {
+        /// 令牌名（记录在审计日志中）
+        name: String,
+        /// 权限范围：chat、admin、jobs、metrics、events（可指定多个，admin 包含全部权限）
+        #[arg(short, long = "scope", required = true, value_delimiter = ',')]
+        scopes: Vec<ApiScope>,
+        /// 有效天数，未设置时不过期
+        #[arg(long)]
+        days: Option<i64>,
+    },
+    /// 吊销令牌
+    Revoke {
+        /// 令牌名
+        name: String,
+    },
+    /// 列出令牌（包括配置文件中的令牌）
+    List,
+}
+
+pub async fn run(config: Config, command: TokenCommand) -> Result<()> {
+    let store = TokenStore::open(&config.api_tokens_db_path().to_string_lossy()).await?;
+
+    match command {
+        TokenCommand::Create { name, scopes, days } => {
+            if config.api.tokens.contains_key(&name) {
+                bail!("配置文件中已有名为 {} 的令牌", name);
+            }
+            if days.is_some_and(|d| d <= 0) {
+                bail!("有效天数必须大于 0");
+            }
+            let expires_at = days.map(|d| Utc::now() + Duration::days(d));
+            let token = store.create(&name, &scopes, expires_at).await?;
+            println!("{}", t!("token.created", name = name));
+            println!("\n  {}\n", token);
+            println!("{}", t!("token.created_hint"));
+        }
+        TokenCommand::Revoke { name } => {
+            if store.revoke(&name).await? {
+                println!("{}", t!("token.revoked", name = name));
+            } else if config.api.tokens.contains_key(&name) {
+                bail!("令牌 {} 在配置文件中，请从 [api.tokens] 中删除", name);
+            } else {
+                bail!("令牌不存在: {}", name);
+            }
+        }
+        TokenCommand::List => {
+            let auth = ApiAuth::new(&config.api);
+            let mut tokens: Vec<ApiToken> = auth.configured().cloned().collect();
+            tokens.extend(store.list().await?);
+            if tokens.is_empty() {
+                println!("{}", t!("token.none"));
+                return Ok(());
+            }
+
+            let now = Utc::now();
+            let time = |at: Option<chrono::DateTime<Utc>>| {
+                at.map(|at| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            println!("{}", t!("token.header", count = tokens.len()));
+            for token in tokens {
+                let scopes: Vec<_> = token.scopes.iter().map(|s| s.as_str()).collect();
+                let expires = match token.expires_at {
+                    Some(_) if token.is_expired(now) => t!("token.expired"),
+                    Some(_) => time(token.expires_at),
+                    None => t!("token.never"),
+                };
+                println!(
+                    "{}",
+                    t!(
+                        "token.line",
+                        name = token.name,
+                        source = token.source,
+                        scopes = scopes.join(","),
+                        created = time(token.created_at),
+                        expires = expires,
+                        last_used = time(token.last_used_at)
+                    )
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! trash 命令 - 查看和恢复 delete_file 移到回收站的文件
+
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::t;
+use crate::tools::trash::Trash;
+
+#[derive(Subcommand)]
+pub enum TrashCommand {
+    /// 列出回收站中的文件
+    List,
+    /// 恢复文件到原路径
+    Restore {
+        /// 条目 ID（见 trash list）
+        id: String,
+        /// 恢复到指定路径（默认原路径）
+        #[arg(long)]
+        to: Option<PathBuf>,
+    },
+}
+
+pub async fn run(config: Config, command: TrashCommand) -> Result<()> {
+    let trash = Trash::new(config.trash_dir());
+
+    match command {
+        TrashCommand::List => {
+            let entries = trash.list()?;
+            if entries.is_empty() {
+                println!("{}", t!("trash.empty"));
+                return Ok(());
+            }
+            println!("{}", t!("trash.header", count = entries.len()));
+            for entry in entries {
+                println!(
+                    "  {}  {}  {}",
+                    entry.id,
+                    entry.deleted_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+                    entry.original_path.display()
+                );
+            }
+            if config.tools.trash_retention_days > 0 {
+                println!("{}", t!("trash.retention", days = config.tools.trash_retention_days));
+            }
+        }
+        TrashCommand::Restore { id, to } => {
+            let path = trash.restore(&id, to.as_deref())?;
+            println!("{}", t!("trash.restored", path = path.display()));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! vault 命令 - 加密/解密工作目录中的数据
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+
+use crate::config::Config;
+use crate::vault::Vault;
+
+#[derive(Subcommand)]
+pub enum VaultCommand {
+    /// 加密 memory 目录和 SQLite 数据库（需先停止 gateway）
+    Lock,
+    /// 解密所有已加密的文件
+    Unlock,
+}
+
+pub async fn run(config: Config, command: VaultCommand) -> Result<()> {
+    let vault = Vault::load(&config)?;
+
+    match command {
+        VaultCommand::Lock => {
+            if !config.vault.enabled {
+                bail!("未启用 [vault]，加密后将无法读取数据，请先在配置中设置 vault.enabled = true");
+            }
+            let count = vault.lock(&config).await?;
+            println!("🔒 已加密 {} 个文件", count);
+        }
+        VaultCommand::Unlock => {
+            let count = vault.unlock(&config).await?;
+            println!("🔓 已解密 {} 个文件", count);
+            if config.vault.enabled {
+                println!("提示: 启用 [vault] 时新写入的数据仍会加密");
+            }
+        }
+    }
+
+    Ok(())
+}
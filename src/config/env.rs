@@ -0,0 +1,116 @@
+//! 配置值中的环境变量引用
+//!
+//! 加载配置时把字符串中的 `${VAR}` 替换为环境变量的值，`${VAR:-默认值}` 在变量未设置或为空时使用默认值，
+//! `$${VAR}` 表示字面的 `${VAR}`。引用的变量未设置且没有默认值时报错并指出所在的配置项
+
+use anyhow::{anyhow, bail, Result};
+
+/// 展开配置中所有字符串值（包括数组和嵌套表中的）里的环境变量引用
+pub fn expand_value(value: &mut toml::Value) -> Result<()> {
+    expand_at(value, "", &|name| std::env::var(name).ok())
+}
+
+fn expand_at(value: &mut toml::Value, path: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<()> {
+    match value {
+        toml::Value::String(text) if text.contains('$') => {
+            *text = expand(text, lookup).map_err(|e| anyhow!("配置项 {}: {}", path, e))?;
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expand_at(item, &format!("{}[{}]", path, i), lookup)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                expand_at(item, &child, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 展开文本中的环境变量引用
+fn expand(text: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            // $${VAR} 保留为字面的 ${VAR}
+            result.push_str("${");
+            rest = escaped;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or_else(|| anyhow!("环境变量引用缺少右括号: ${{{}", body))?;
+            let (name, default) = match body[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&body[..end], None),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                bail!("环境变量名无效: ${{{}}}", &body[..end]);
+            }
+            let value = match (lookup(name).filter(|v| !v.is_empty()), default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => default.to_string(),
+                (None, None) => bail!("引用的环境变量 {} 未设置", name),
+            };
+            result.push_str(&value);
+            rest = &body[end + 1..];
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/bot".to_string()),
+            "API_KEY" => Some("sk-123".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("${HOME}/.nanobot", &lookup).unwrap(), "/home/bot/.nanobot");
+        assert_eq!(expand("Bearer ${API_KEY}", &lookup).unwrap(), "Bearer sk-123");
+        assert_eq!(expand("${MISSING:-http://localhost:3001}", &lookup).unwrap(), "http://localhost:3001");
+        assert_eq!(expand("${EMPTY:-x}", &lookup).unwrap(), "x");
+        assert_eq!(expand("$${HOME} 和 $5", &lookup).unwrap(), "${HOME} 和 $5");
+        assert!(expand("${MISSING}", &lookup).unwrap_err().to_string().contains("MISSING"));
+        assert!(expand("${HOME", &lookup).is_err());
+        assert!(expand("${A-B}", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_expand_value() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [llm.openai]
+            api_key = "${API_KEY}"
+            [channel.telegram]
+            allowed_users = [1]
+            [agent]
+            channel_prompts = { telegram = "home: ${HOME}" }
+            "#,
+        )
+        .unwrap();
+        expand_at(&mut value, "", &lookup).unwrap();
+        assert_eq!(value["llm"]["openai"]["api_key"].as_str(), Some("sk-123"));
+        assert_eq!(value["agent"]["channel_prompts"]["telegram"].as_str(), Some("home: /home/bot"));
+
+        let mut value: toml::Value = toml::from_str("[tools]\nallowed = [\"ok\", \"${NOPE}\"]").unwrap();
+        let error = expand_at(&mut value, "", &lookup).unwrap_err().to_string();
+        assert!(error.contains("tools.allowed[1]") && error.contains("NOPE"), "{}", error);
+    }
+}
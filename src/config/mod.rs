@@ -3,12 +3,21 @@
 //! 支持 TOML 配置文件和环境变量覆盖
 
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+pub mod env;
+pub mod schema;
+
 /// 主配置结构
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct Config {
+    /// 严格模式：配置文件中有未知的配置项（如拼写错误）时加载失败，否则只记录警告
+    #[serde(default)]
+    pub strict: bool,
+
     /// Agent 配置
     #[serde(default)]
     pub agent: AgentConfig,
@@ -28,9 +37,415 @@ pub struct Config {
     /// 工具配置
     #[serde(default)]
     pub tools: ToolsConfig,
+    
+    /// API 服务配置
+    #[serde(default)]
+    pub api: ApiConfig,
+
+    /// 向量嵌入配置
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+
+    /// 会话配置
+    #[serde(default)]
+    pub session: SessionConfig,
+
+    /// 隐私与数据保留配置
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+
+    /// 静态数据加密配置
+    #[serde(default)]
+    pub vault: VaultConfig,
+
+    /// 用量预算配置
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// Agent 角色（键为角色名，通过 /persona 或 --persona 切换）
+    #[serde(default)]
+    pub personas: HashMap<String, PersonaConfig>,
+
+    /// 新用户引导配置
+    #[serde(default)]
+    pub onboarding: OnboardingConfig,
+
+    /// 后台任务配置
+    #[serde(default)]
+    pub tasks: TasksConfig,
+
+    /// 深度调研配置
+    #[serde(default)]
+    pub research: ResearchConfig,
+
+    /// 长对话自动降级模型配置
+    #[serde(default)]
+    pub downgrade: DowngradeConfig,
+
+    /// 消息内容过滤配置
+    #[serde(default)]
+    pub content_filter: ContentFilterConfig,
+
+    /// 界面配置
+    #[serde(default)]
+    pub ui: UiConfig,
+
+    /// 回复来源引用配置
+    #[serde(default)]
+    pub citations: CitationsConfig,
+
+    /// 每日简报配置
+    #[serde(default)]
+    pub briefing: BriefingConfig,
+
+    /// 文件监视配置
+    #[serde(default)]
+    pub watch: WatchConfig,
+
+    /// Redis 共享状态配置（多进程部署）
+    #[serde(default)]
+    pub redis: RedisConfig,
+
+    /// panic 处理配置
+    #[serde(default)]
+    pub crash: CrashConfig,
+
+    /// 定时任务配置
+    #[serde(default)]
+    pub cron: CronConfig,
+
+    /// 会话分享配置
+    #[serde(default)]
+    pub share: ShareConfig,
+
+    /// 系统通知模板配置
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+}
+
+/// 系统通知模板配置（提醒、任务失败、预算告警、文件监视通知）
+///
+/// 键为通知类型：reminder、job_failed、budget_warning、budget_exceeded、watch_update
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationsConfig {
+    /// 覆盖内置模板
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+    /// 按通道覆盖模板（`[notifications.channels.<通道>]`）
+    #[serde(default)]
+    pub channels: HashMap<String, HashMap<String, String>>,
+}
+
+/// 会话分享配置（`nanobot share`）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ShareConfig {
+    /// 上传地址（可选），`--upload` 时把导出的内容 POST 到该地址，响应中的链接作为分享地址
+    #[serde(default)]
+    pub upload_url: Option<String>,
+    /// 上传时附加的请求头（如鉴权）
+    #[serde(default)]
+    pub upload_headers: HashMap<String, String>,
+    /// 额外需要打码的正则（内置规则之外，如内部域名、工号）
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+}
+
+/// 定时任务配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CronConfig {
+    /// 已完成的一次性任务（如提醒）保留的小时数，gateway 定期删除（0 表示永久保留）
+    #[serde(default = "default_cron_completed_retention_hours")]
+    pub completed_retention_hours: u64,
+    /// 是否注册 shell_command 任务处理器（按计划执行白名单中的命令），默认关闭
+    #[serde(default)]
+    pub shell_jobs: bool,
+}
+
+impl Default for CronConfig {
+    fn default() -> Self {
+        Self {
+            completed_retention_hours: default_cron_completed_retention_hours(),
+            shell_jobs: false,
+        }
+    }
+}
+
+fn default_cron_completed_retention_hours() -> u64 {
+    72
+}
+
+/// Redis 共享状态配置
+///
+/// 多个 gateway 进程共同处理通道时，入站消息去重和定时任务执行标记保存在 Redis 中共享；
+/// 未配置 url 或连接失败时使用进程内存
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RedisConfig {
+    /// Redis 地址（如 redis://127.0.0.1:6379/0），未设置时不使用 Redis
+    #[serde(default)]
+    pub url: Option<String>,
+    /// 键前缀（多个部署共用一个 Redis 时区分）
+    #[serde(default = "default_redis_prefix")]
+    pub prefix: String,
+    /// 连接超时（秒），超时后回退到进程内存
+    #[serde(default = "default_redis_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            prefix: default_redis_prefix(),
+            connect_timeout_secs: default_redis_connect_timeout_secs(),
+        }
+    }
+}
+
+fn default_redis_prefix() -> String {
+    "nanobot:".to_string()
+}
+
+fn default_redis_connect_timeout_secs() -> u64 {
+    5
+}
+
+/// panic 处理配置
+///
+/// 后台任务（通道、定时任务、发件箱等）panic 时记录调用栈并发布 `system.panic` 事件，
+/// 可通知管理员聊天，并按配置重启出错的组件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrashConfig {
+    /// 日志中是否记录 panic 的调用栈
+    #[serde(default = "default_true")]
+    pub backtrace: bool,
+    /// 后台组件 panic 后是否自动重启
+    #[serde(default = "default_true")]
+    pub restart: bool,
+    /// 重启前等待的时间（秒）
+    #[serde(default = "default_crash_restart_delay_secs")]
+    pub restart_delay_secs: u64,
+    /// panic 通知发送的通道
+    #[serde(default)]
+    pub notify_channel: Option<String>,
+    /// panic 通知发送的聊天 ID
+    #[serde(default)]
+    pub notify_chat_id: Option<String>,
+}
+
+impl Default for CrashConfig {
+    fn default() -> Self {
+        Self {
+            backtrace: true,
+            restart: true,
+            restart_delay_secs: default_crash_restart_delay_secs(),
+            notify_channel: None,
+            notify_chat_id: None,
+        }
+    }
+}
+
+fn default_crash_restart_delay_secs() -> u64 {
+    5
+}
+
+/// 回复来源引用配置
+///
+/// 本轮使用了 web_search / fetch_page 时在回复末尾附上编号的来源链接
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CitationsConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 最多列出的来源数（0 表示不限制）
+    #[serde(default = "default_citations_max_sources")]
+    pub max_sources: usize,
+}
+
+impl Default for CitationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_sources: default_citations_max_sources(),
+        }
+    }
+}
+
+fn default_citations_max_sources() -> usize {
+    5
+}
+
+/// 每日简报配置
+///
+/// 按时汇总天气、今天的日程、昨天的笔记和待办，由模型整理成一条简报发送到指定通道
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BriefingConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 发送时间，写法与 /remind 相同且必须是重复的时间（如 "每天早上七点半"、"every weekday at 8am"），
+    /// 按 agent.timezone 解析
+    #[serde(default = "default_briefing_schedule")]
+    pub schedule: String,
+    /// 发送到的通道（如 telegram），未设置时只写入日志
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// 发送到的聊天 ID
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// 简报包含的部分，按顺序排列
+    #[serde(default = "default_briefing_sections")]
+    pub sections: Vec<BriefingSection>,
+    /// 天气的地点（城市名或机场代码），未设置时按服务器 IP 定位
+    #[serde(default)]
+    pub location: Option<String>,
+    /// 整理简报使用的模型（provider/model），未设置时使用默认模型
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl Default for BriefingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: default_briefing_schedule(),
+            channel: None,
+            chat_id: None,
+            sections: default_briefing_sections(),
+            location: None,
+            model: None,
+        }
+    }
+}
+
+/// 简报的组成部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BriefingSection {
+    /// 天气（wttr.in）
+    Weather,
+    /// 今天的定时提醒和任务
+    Agenda,
+    /// 昨天的每日笔记
+    Notes,
+    /// 长期记忆中的待办
+    Todos,
+}
+
+fn default_briefing_schedule() -> String {
+    "每天早上七点半".to_string()
+}
+
+fn default_briefing_sections() -> Vec<BriefingSection> {
+    vec![
+        BriefingSection::Weather,
+        BriefingSection::Agenda,
+        BriefingSection::Notes,
+        BriefingSection::Todos,
+    ]
+}
+
+/// 文件监视配置
+///
+/// gateway 定期扫描配置的目录，文件新增、修改或删除时发布 file.* 事件，
+/// 可按目录配置通知到通道或让 Agent 处理（如"收件箱出现新文件时总结它"）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WatchConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 扫描间隔（秒），文件在两次扫描之间没有变化才视为写入完成
+    #[serde(default = "default_watch_interval_secs")]
+    pub interval_secs: u64,
+    /// 监视的目录
+    #[serde(default)]
+    pub dirs: Vec<WatchDirConfig>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_watch_interval_secs(),
+            dirs: Vec::new(),
+        }
+    }
+}
+
+/// 监视的目录
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WatchDirConfig {
+    /// 名称（事件和通知中使用）
+    pub name: String,
+    /// 目录路径
+    pub path: PathBuf,
+    /// 只关注文件名匹配这些 glob 模式的文件（如 "*.pdf"），为空表示所有文件；以 . 开头的隐藏文件总是忽略
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// 是否包括子目录
+    #[serde(default)]
+    pub recursive: bool,
+    /// 关注的变化
+    #[serde(default = "default_watch_events")]
+    pub events: Vec<FileChangeKind>,
+    /// 通知或回复发送到的通道（如 telegram），未设置时只发布事件
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// 发送到的聊天 ID
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// 交给 Agent 处理的提示，{path}、{name}、{event} 会被替换；未设置时只发送通知
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+/// 文件变化类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl FileChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileChangeKind::Created => "created",
+            FileChangeKind::Modified => "modified",
+            FileChangeKind::Removed => "removed",
+        }
+    }
+}
+
+fn default_watch_interval_secs() -> u64 {
+    5
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_watch_events() -> Vec<FileChangeKind> {
+    vec![FileChangeKind::Created]
+}
+
+/// 界面配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UiConfig {
+    /// 命令行输出和通道命令回复使用的语言
+    #[serde(default)]
+    pub language: Language,
+}
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Language {
+    /// 简体中文
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    /// 英文
+    #[serde(rename = "en")]
+    En,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AgentConfig {
     /// 系统提示词
     #[serde(default = "default_system_prompt")]
@@ -44,6 +459,68 @@ pub struct AgentConfig {
     /// 默认模型
     #[serde(default = "default_model")]
     pub default_model: String,
+    /// 默认时区（如 "+08:00"、"UTC"、"Asia/Shanghai"），用于解析提醒时间，
+    /// 用户资料中保存了时区时以用户的为准，未设置时使用系统时区
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// 各通道追加的提示词（键为通道名，如 telegram）
+    #[serde(default)]
+    pub channel_prompts: HashMap<String, String>,
+    /// 用户资料与偏好，作为系统提示词的一层
+    #[serde(default)]
+    pub user_profile: Option<String>,
+    /// 组合后系统提示词的最大字符数，超出时优先截断低优先级的层
+    #[serde(default = "default_prompt_budget_chars")]
+    pub prompt_budget_chars: usize,
+    /// 按用户消息的语言提示模型用同一语言回答
+    #[serde(default = "default_true")]
+    pub match_reply_language: bool,
+    /// 单轮对话（含工具调用）的超时时间（秒），0 表示不限制
+    #[serde(default = "default_turn_timeout_secs")]
+    pub turn_timeout_secs: u64,
+    /// 多次采样（/bestof）时选出最终回答的方式
+    #[serde(default)]
+    pub best_of_strategy: BestOfStrategy,
+    /// 多次采样的最大次数
+    #[serde(default = "default_max_best_of")]
+    pub max_best_of: usize,
+    /// 生成参数（优先于提供商配置中的同名参数）
+    #[serde(flatten)]
+    pub generation: GenerationParams,
+}
+
+/// 多次采样时选出最终回答的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BestOfStrategy {
+    /// 选出与其他回答最一致的一个（相同回答占多数时即为该回答）
+    #[default]
+    Vote,
+    /// 让模型把各回答合并为一个
+    Merge,
+}
+
+/// 生成参数，未设置的字段不会发送给提供商
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq)]
+pub struct GenerationParams {
+    /// 采样温度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// 最大输出 token 数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// 核采样概率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// 停止序列
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// 存在惩罚
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// 频率惩罚
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
 }
 
 impl Default for AgentConfig {
@@ -53,218 +530,1645 @@ impl Default for AgentConfig {
             max_context: default_max_context(),
             default_provider: default_provider(),
             default_model: default_model(),
+            timezone: None,
+            channel_prompts: HashMap::new(),
+            user_profile: None,
+            prompt_budget_chars: default_prompt_budget_chars(),
+            match_reply_language: true,
+            turn_timeout_secs: default_turn_timeout_secs(),
+            best_of_strategy: BestOfStrategy::default(),
+            max_best_of: default_max_best_of(),
+            generation: GenerationParams::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Default)]
+pub struct LlmConfig {
+    /// OpenRouter 配置
+    #[serde(default)]
+    pub openrouter: ProviderConfig,
+    /// DeepSeek 配置
+    #[serde(default)]
+    pub deepseek: ProviderConfig,
+    /// MiniMax 配置
+    #[serde(default)]
+    pub minimax: ProviderConfig,
+    /// Moonshot 配置
+    #[serde(default)]
+    pub moonshot: ProviderConfig,
+    /// vLLM 配置
+    #[serde(default)]
+    pub vllm: ProviderConfig,
+    /// OpenAI 配置
+    #[serde(default)]
+    pub openai: ProviderConfig,
+    /// Anthropic 配置
+    #[serde(default)]
+    pub anthropic: ProviderConfig,
+    /// Google Gemini 配置
+    #[serde(default)]
+    pub gemini: ProviderConfig,
+    /// 智谱 AI (Zhipu) 配置
+    #[serde(default)]
+    pub zhipu: ProviderConfig,
+    /// 阿里云 DashScope (Qwen) 配置
+    #[serde(default)]
+    pub dashscope: ProviderConfig,
+    /// Groq 配置
+    #[serde(default)]
+    pub groq: ProviderConfig,
+    /// SiliconFlow（硅基流动）配置
+    #[serde(default)]
+    pub siliconflow: ProviderConfig,
+    /// Together.ai 配置
+    #[serde(default)]
+    pub together: ProviderConfig,
+    /// LLM 调试日志目录，设置后每次请求/响应都会写成 JSON 文件（密钥已脱敏）
+    #[serde(default)]
+    pub debug_log_dir: Option<PathBuf>,
+    /// 模型路由规则
+    #[serde(default)]
+    pub router: RouterConfig,
+    /// 提供商可用性探测与故障切换
+    #[serde(default)]
+    pub probe: ProbeConfig,
+}
+
+impl LlmConfig {
+    /// 按名称获取提供商配置
+    pub fn provider(&self, name: &str) -> Option<&ProviderConfig> {
+        match name {
+            "openrouter" => Some(&self.openrouter),
+            "deepseek" => Some(&self.deepseek),
+            "minimax" => Some(&self.minimax),
+            "moonshot" => Some(&self.moonshot),
+            "vllm" => Some(&self.vllm),
+            "openai" => Some(&self.openai),
+            "anthropic" => Some(&self.anthropic),
+            "gemini" => Some(&self.gemini),
+            "zhipu" => Some(&self.zhipu),
+            "dashscope" => Some(&self.dashscope),
+            "groq" => Some(&self.groq),
+            "siliconflow" => Some(&self.siliconflow),
+            "together" => Some(&self.together),
+            _ => None,
+        }
+    }
+}
+
+/// 模型路由配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct RouterConfig {
+    /// 是否启用路由
+    #[serde(default)]
+    pub enabled: bool,
+    /// 路由规则，按顺序匹配
+    #[serde(default)]
+    pub rules: Vec<RouteRule>,
+}
+
+/// 提供商可用性探测配置（gateway 模式）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProbeConfig {
+    /// 是否定期探测各提供商
+    #[serde(default)]
+    pub enabled: bool,
+    /// 探测间隔（秒）
+    #[serde(default = "default_probe_interval_secs")]
+    pub interval_secs: u64,
+    /// 单次探测超时（秒）
+    #[serde(default = "default_probe_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 连续失败多少次后视为不可用
+    #[serde(default = "default_probe_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 备用提供商，按顺序使用第一个可用的；当前提供商被探测为不可用时切换
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    /// 探测记录保留天数
+    #[serde(default = "default_probe_history_days")]
+    pub history_days: u32,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_probe_interval_secs(),
+            timeout_secs: default_probe_timeout_secs(),
+            failure_threshold: default_probe_failure_threshold(),
+            fallback: Vec::new(),
+            history_days: default_probe_history_days(),
+        }
+    }
+}
+
+fn default_probe_interval_secs() -> u64 {
+    300
+}
+
+fn default_probe_timeout_secs() -> u64 {
+    20
+}
+
+fn default_probe_failure_threshold() -> u32 {
+    2
+}
+
+fn default_probe_history_days() -> u32 {
+    7
+}
+
+/// 路由规则，所有已设置的条件都满足时命中
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct RouteRule {
+    /// 规则名（记录在使用统计中）
+    pub name: String,
+    /// 使用的提供商，未设置时使用默认提供商
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// 使用的模型，未设置时使用默认模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 消息最少字符数
+    #[serde(default)]
+    pub min_chars: Option<usize>,
+    /// 消息最多字符数
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    /// 本轮是否已产生工具调用（true 匹配工具密集型请求，false 匹配普通对话）
+    #[serde(default)]
+    pub has_tools: Option<bool>,
+    /// 包含任一关键词时匹配（不区分大小写）
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// 限定消息来源通道
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProviderConfig {
+    /// API Key
+    pub api_key: Option<String>,
+    /// 基础 URL（用于自定义端点）
+    pub base_url: Option<String>,
+    /// 默认模型
+    pub default_model: Option<String>,
+    /// 超时时间（秒）
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    /// 自定义请求头（用于 API Gateway 等，如 AiHubMix 的 APP-Code）
+    #[serde(default)]
+    pub extra_headers: std::collections::HashMap<String, String>,
+    /// 代理地址（支持 http://、https://、socks5://）
+    /// 未设置时使用 HTTPS_PROXY / HTTP_PROXY 环境变量
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// 是否校验 TLS 证书（自签名证书的内网网关可关闭）
+    #[serde(default = "default_true")]
+    pub verify_tls: bool,
+    /// 最大并发请求数（0 表示不限制），超出的请求按会话轮流排队
+    #[serde(default)]
+    pub max_concurrent_requests: usize,
+    /// 遇到限流（HTTP 429）时的最大重试次数
+    #[serde(default = "default_rate_limit_retries")]
+    pub rate_limit_retries: u32,
+    /// 限流重试的最长等待时间（秒），提供商要求等待更久时直接返回错误
+    #[serde(default = "default_rate_limit_max_wait_secs")]
+    pub rate_limit_max_wait_secs: u64,
+    /// 空闲连接保留时间（秒，0 表示不回收）
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    /// 每个主机最多保留的空闲连接数
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// HTTP/2 与 TCP keepalive 探测间隔（秒，0 表示关闭）
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u64,
+    /// 该提供商的默认生成参数
+    #[serde(flatten)]
+    pub generation: GenerationParams,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            base_url: None,
+            default_model: None,
+            timeout_secs: default_timeout(),
+            extra_headers: std::collections::HashMap::new(),
+            proxy: None,
+            verify_tls: true,
+            max_concurrent_requests: 0,
+            rate_limit_retries: default_rate_limit_retries(),
+            rate_limit_max_wait_secs: default_rate_limit_max_wait_secs(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            keepalive_secs: default_keepalive_secs(),
+            generation: GenerationParams::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelConfig {
+    /// Telegram 配置
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    /// Discord 配置
+    #[serde(default)]
+    pub discord: DiscordConfig,
+    /// 飞书配置
+    #[serde(default)]
+    pub feishu: FeishuConfig,
+    /// WhatsApp 配置
+    #[serde(default)]
+    pub whatsapp: WhatsAppConfig,
+    /// 是否通过持久化发件箱投递回复（失败重试、重启后续发）
+    #[serde(default = "default_true")]
+    pub outbox: bool,
+    /// 入站消息去重配置
+    #[serde(default)]
+    pub dedupe: DedupeConfig,
+    /// 通道监控与自动重启配置
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    /// 默认开启无痕模式的通道（对话不落盘，可用 /incognito 切换）
+    #[serde(default)]
+    pub incognito_channels: Vec<String>,
+    /// 用户编辑最后一条消息时是否撤销上一轮并重新生成回复
+    #[serde(default = "default_true")]
+    pub regenerate_on_edit: bool,
+    /// 回复后处理配置
+    #[serde(default)]
+    pub postprocess: PostProcessConfig,
+    /// 可在各通道使用管理命令（/jobs、/job 等）的用户（通道:用户 ID，如 discord:123456）
+    #[serde(default)]
+    pub admin_users: Vec<String>,
+}
+
+impl ChannelConfig {
+    /// 通道合并连发消息的等待时间（未设置时为 0，不合并）
+    pub fn merge_window(&self, channel: &str) -> std::time::Duration {
+        let ms = match channel {
+            "telegram" => self.telegram.merge_window_ms,
+            "discord" => self.discord.merge_window_ms,
+            "feishu" => self.feishu.merge_window_ms,
+            "whatsapp" => self.whatsapp.merge_window_ms,
+            _ => 0,
+        };
+        std::time::Duration::from_millis(ms)
+    }
+
+    /// 全部管理员（通道:用户 ID），包括 Telegram 的 admin_users
+    pub fn admins(&self) -> HashSet<String> {
+        self.telegram
+            .admin_users
+            .iter()
+            .map(|id| format!("telegram:{}", id))
+            .chain(self.admin_users.iter().map(|u| u.trim().to_string()))
+            .collect()
+    }
+
+    /// 通道的唤醒词配置
+    pub fn wake_words(&self, channel: &str) -> Option<&WakeWordConfig> {
+        match channel {
+            "telegram" => self.telegram.wake_words.as_ref(),
+            "discord" => self.discord.wake_words.as_ref(),
+            "feishu" => self.feishu.wake_words.as_ref(),
+            "whatsapp" => self.whatsapp.wake_words.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// 通道的免打扰时段
+    pub fn quiet_hours(&self, channel: &str) -> Option<&QuietHoursConfig> {
+        match channel {
+            "telegram" => self.telegram.quiet_hours.as_ref(),
+            "discord" => self.discord.quiet_hours.as_ref(),
+            "feishu" => self.feishu.quiet_hours.as_ref(),
+            "whatsapp" => self.whatsapp.quiet_hours.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            telegram: TelegramConfig::default(),
+            discord: DiscordConfig::default(),
+            feishu: FeishuConfig::default(),
+            whatsapp: WhatsAppConfig::default(),
+            outbox: true,
+            dedupe: DedupeConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            incognito_channels: Vec::new(),
+            regenerate_on_edit: true,
+            postprocess: PostProcessConfig::default(),
+            admin_users: Vec::new(),
+        }
+    }
+}
+
+/// 回复后处理配置
+///
+/// 回复发出之前依次经过 `filters` 中的过滤器，`overrides` 可按通道替换过滤器列表和长度上限
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PostProcessConfig {
+    /// 依次执行的过滤器
+    #[serde(default = "default_postprocess_filters")]
+    pub filters: Vec<PostFilterKind>,
+    /// 回复最多字符数（truncate 过滤器使用，0 表示不限制）
+    #[serde(default)]
+    pub max_reply_chars: usize,
+    /// 按通道覆盖的设置（键为通道名）
+    #[serde(default)]
+    pub overrides: std::collections::HashMap<String, PostProcessOverride>,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            filters: default_postprocess_filters(),
+            max_reply_chars: 0,
+            overrides: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// 单个通道的回复后处理设置，未设置的字段沿用全局值
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PostProcessOverride {
+    /// 依次执行的过滤器
+    #[serde(default)]
+    pub filters: Option<Vec<PostFilterKind>>,
+    /// 回复最多字符数（0 表示不限制）
+    #[serde(default)]
+    pub max_reply_chars: Option<usize>,
+}
+
+/// 回复后处理过滤器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PostFilterKind {
+    /// 去掉推理模型输出的 `<think>` 块
+    StripThink,
+    /// 去掉行尾空白和首尾空行
+    TrimWhitespace,
+    /// 连续多个空行合并为一个
+    CollapseBlankLines,
+    /// 超过 `max_reply_chars` 时截断
+    Truncate,
+    /// 把裸 URL 转为以域名为标题的 Markdown 链接
+    LinkUrls,
+}
+
+fn default_postprocess_filters() -> Vec<PostFilterKind> {
+    vec![
+        PostFilterKind::StripThink,
+        PostFilterKind::TrimWhitespace,
+        PostFilterKind::CollapseBlankLines,
+        PostFilterKind::Truncate,
+    ]
+}
+
+/// 入站消息去重配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DedupeConfig {
+    /// 内存中保留的最近消息数
+    #[serde(default = "default_dedupe_capacity")]
+    pub capacity: usize,
+    /// 去重有效期（秒）
+    #[serde(default = "default_dedupe_ttl")]
+    pub ttl_secs: u64,
+    /// 是否持久化到 SQLite（重启后仍能去重）
+    #[serde(default = "default_true")]
+    pub persistent: bool,
+}
+
+impl Default for DedupeConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_dedupe_capacity(),
+            ttl_secs: default_dedupe_ttl(),
+            persistent: true,
+        }
+    }
+}
+
+fn default_dedupe_capacity() -> usize {
+    10000
+}
+
+fn default_dedupe_ttl() -> u64 {
+    86400
+}
+
+/// 通道监控与自动重启配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SupervisorConfig {
+    /// 通道异常退出后首次重启前的等待时间（秒），之后每次翻倍
+    #[serde(default = "default_restart_backoff_secs")]
+    pub restart_backoff_secs: u64,
+    /// 重启等待时间上限（秒）
+    #[serde(default = "default_max_restart_backoff_secs")]
+    pub max_restart_backoff_secs: u64,
+    /// 最大连续重启次数（0 表示不限制）
+    #[serde(default)]
+    pub max_restarts: u32,
+    /// 健康状态写入间隔（秒）
+    #[serde(default = "default_health_report_interval_secs")]
+    pub health_report_interval_secs: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            restart_backoff_secs: default_restart_backoff_secs(),
+            max_restart_backoff_secs: default_max_restart_backoff_secs(),
+            max_restarts: 0,
+            health_report_interval_secs: default_health_report_interval_secs(),
+        }
+    }
+}
+
+fn default_restart_backoff_secs() -> u64 {
+    1
+}
+
+fn default_max_restart_backoff_secs() -> u64 {
+    300
+}
+
+fn default_health_report_interval_secs() -> u64 {
+    30
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TelegramConfig {
+    /// Bot Token
+    pub bot_token: Option<String>,
+    /// 允许的用户 ID 列表
+    #[serde(default)]
+    pub allowed_users: Vec<i64>,
+    /// 管理员用户 ID 列表（可使用 /model、/sessions、/broadcast 等管理命令）
+    #[serde(default)]
+    pub admin_users: Vec<i64>,
+    /// Webhook URL（可选）
+    pub webhook_url: Option<String>,
+    /// 免打扰时段
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// 合并连发消息的等待时间（毫秒），窗口内同一聊天的多条消息合并为一轮对话，0 表示不合并
+    #[serde(default)]
+    pub merge_window_ms: u64,
+    /// 唤醒词（设置后只响应以唤醒词开头的消息）
+    #[serde(default)]
+    pub wake_words: Option<WakeWordConfig>,
+}
+
+/// Discord 配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct DiscordConfig {
+    /// Bot Token
+    pub bot_token: Option<String>,
+    /// Application ID
+    pub application_id: Option<u64>,
+    /// 允许的服务器 ID 列表
+    #[serde(default)]
+    pub allowed_guilds: Vec<u64>,
+    /// 允许的频道 ID 列表
+    #[serde(default)]
+    pub allowed_channels: Vec<u64>,
+    /// 允许的用户 ID 列表
+    #[serde(default)]
+    pub allowed_users: Vec<u64>,
+    /// 默认前缀
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    /// Webhook URL（可选）
+    pub webhook_url: Option<String>,
+    /// 是否启用 Slash Command
+    #[serde(default = "default_true")]
+    pub enable_slash_commands: bool,
+    /// 免打扰时段
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// 合并连发消息的等待时间（毫秒），窗口内同一聊天的多条消息合并为一轮对话，0 表示不合并
+    #[serde(default)]
+    pub merge_window_ms: u64,
+    /// 唤醒词（设置后只响应以唤醒词开头的消息）
+    #[serde(default)]
+    pub wake_words: Option<WakeWordConfig>,
+}
+
+/// 飞书配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct FeishuConfig {
+    /// App ID
+    pub app_id: Option<String>,
+    /// App Secret
+    pub app_secret: Option<String>,
+    /// Verification Token
+    pub verification_token: Option<String>,
+    /// Encrypt Key
+    pub encrypt_key: Option<String>,
+    /// 允许的用户 Open ID 列表
+    #[serde(default)]
+    pub allowed_users: Vec<String>,
+    /// 允许的用户 Open ID 列表（别名）
+    #[serde(default)]
+    pub allowed_open_ids: Vec<String>,
+    /// 允许的群 Chat ID 列表（为空时允许所有群）
+    #[serde(default)]
+    pub allowed_chats: Vec<String>,
+    /// 群聊中是否仅在 @机器人 时回复
+    #[serde(default = "default_true")]
+    pub require_mention: bool,
+    /// 是否下载收到的图片和文件到 `<workspace>/media/<会话>/`
+    #[serde(default = "default_true")]
+    pub download_media: bool,
+    /// 图片识别命令（`{path}` 替换为图片路径），输出作为图片内容提供给 Agent，
+    /// 如 `tesseract {path} stdout -l chi_sim+eng`
+    #[serde(default)]
+    pub ocr_command: Option<String>,
+    /// 是否验证请求签名
+    #[serde(default = "default_true")]
+    pub verify_signature: bool,
+    /// 消息卡片模板 ID
+    pub card_template_id: Option<String>,
+    /// 免打扰时段
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// 合并连发消息的等待时间（毫秒），窗口内同一聊天的多条消息合并为一轮对话，0 表示不合并
+    #[serde(default)]
+    pub merge_window_ms: u64,
+    /// 唤醒词（设置后只响应以唤醒词开头的消息）
+    #[serde(default)]
+    pub wake_words: Option<WakeWordConfig>,
+}
+
+/// WhatsApp 配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct WhatsAppConfig {
+    /// WebSocket Bridge URL
+    pub bridge_url: Option<String>,
+    /// 允许的用户手机号列表
+    #[serde(default)]
+    pub allowed_users: Vec<String>,
+    /// 自动重连间隔（秒）
+    #[serde(default = "default_reconnect_interval")]
+    pub reconnect_interval_secs: u64,
+    /// 是否自动重连
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+    /// 免打扰时段
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// 合并连发消息的等待时间（毫秒），窗口内同一聊天的多条消息合并为一轮对话，0 表示不合并
+    #[serde(default)]
+    pub merge_window_ms: u64,
+    /// 唤醒词（设置后只响应以唤醒词开头的消息）
+    #[serde(default)]
+    pub wake_words: Option<WakeWordConfig>,
+}
+
+/// 免打扰时段配置
+///
+/// 时段内的主动消息（定时提醒等）排队到时段结束后发送，对用户消息的直接回复不受影响
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHoursConfig {
+    /// 开始时间（HH:MM）
+    pub start: String,
+    /// 结束时间（HH:MM），早于开始时间表示跨夜（如 22:00 - 08:00）
+    pub end: String,
+    /// 时区（如 "+08:00"），未设置时使用 agent.timezone
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// 唤醒词配置
+///
+/// 启用后（默认只在群聊中）只有以唤醒词开头的消息交给 Agent，如 "nanobot，明天天气怎么样"；
+/// 唤醒词后跟停止词（如 "nanobot stop"）时中止进行中的回复
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WakeWordConfig {
+    /// 唤醒词（不区分大小写）
+    #[serde(default = "default_wake_phrases")]
+    pub phrases: Vec<String>,
+    /// 停止词，跟在唤醒词后面使用
+    #[serde(default = "default_stop_phrases")]
+    pub stop_phrases: Vec<String>,
+    /// 是否只在群聊中要求唤醒词（私聊照常回复）
+    #[serde(default = "default_true")]
+    pub groups_only: bool,
+}
+
+impl Default for WakeWordConfig {
+    fn default() -> Self {
+        Self {
+            phrases: default_wake_phrases(),
+            stop_phrases: default_stop_phrases(),
+            groups_only: true,
+        }
+    }
+}
+
+fn default_wake_phrases() -> Vec<String> {
+    vec!["nanobot".to_string()]
+}
+
+fn default_stop_phrases() -> Vec<String> {
+    ["stop", "停", "停止", "取消"].iter().map(|s| s.to_string()).collect()
+}
+
+fn default_reconnect_interval() -> u64 {
+    5
+}
+
+fn default_prefix() -> String {
+    "!".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 内存系统配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryConfig {
+    /// 工作目录路径（用于存储 Markdown 记忆文件）
+    #[serde(default = "default_workspace_path")]
+    pub workspace_path: PathBuf,
+    /// 长期记忆条数上限，超过时淘汰得分最低的记忆（0 表示不限制，按用户分区时每个用户分别计算）
+    #[serde(default = "default_max_memories")]
+    pub max_memories: usize,
+    /// 按用户分区长期记忆（memory/users/<用户>/MEMORY.md），每个用户只看到自己的和共享的记忆
+    #[serde(default = "default_true")]
+    pub per_user: bool,
+    /// 是否将今天和昨天的日常笔记附加到系统提示词
+    #[serde(default)]
+    pub inject_today_notes: bool,
+    /// 每天的笔记附加的最大字符数，超出时保留最新的部分
+    #[serde(default = "default_inject_notes_max_chars")]
+    pub inject_notes_max_chars: usize,
+    /// 会话结束后从对话中提取长期记忆
+    #[serde(default)]
+    pub extraction: MemoryExtractionConfig,
+    /// 模型写入的记忆先经管理员审核
+    #[serde(default)]
+    pub review: MemoryReviewConfig,
+    /// 对话历史的存储格式
+    #[serde(default)]
+    pub conversation_format: ConversationFormat,
+}
+
+/// 对话历史的存储格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationFormat {
+    /// 便于阅读的 Markdown（`{session_id}.md`）
+    #[default]
+    Markdown,
+    /// 每行一条完整消息的 JSON（`{session_id}.jsonl`），保留工具调用等全部字段
+    Jsonl,
+}
+
+impl ConversationFormat {
+    /// 对话历史文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl std::str::FromStr for ConversationFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => anyhow::bail!("未知的对话历史格式: {}（可选 markdown、jsonl）", s),
+        }
+    }
+}
+
+/// 对话记忆提取配置（会话结束或空闲超时后由模型提取事实、偏好和待办）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryExtractionConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 使用的模型（"provider/model" 或仅模型名），未设置时使用默认模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 新增对话少于该字符数时不提取
+    #[serde(default = "default_extraction_min_chars")]
+    pub min_chars: usize,
+    /// 发送给模型的对话最大字符数，超出时保留最新的部分
+    #[serde(default = "default_extraction_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for MemoryExtractionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: None,
+            min_chars: default_extraction_min_chars(),
+            max_chars: default_extraction_max_chars(),
+        }
+    }
+}
+
+/// 记忆审核配置
+///
+/// 启用后 remember 工具和对话记忆提取写入的记忆进入待审核队列，
+/// 管理员用 `/memories approve|reject` 或 `nanobot memory approve|reject` 处理后才成为长期记忆
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MemoryReviewConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 发送待审核摘要的时间，写法与 /remind 相同且必须是重复的时间（如 "每天晚上八点"），
+    /// 按 agent.timezone 解析，未设置时不发送摘要
+    #[serde(default)]
+    pub digest_schedule: Option<String>,
+    /// 摘要发送到的通道（如 telegram），未设置时只写入日志
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// 摘要发送到的聊天 ID（通常是管理员的私聊）
+    #[serde(default)]
+    pub chat_id: Option<String>,
+}
+
+fn default_extraction_min_chars() -> usize {
+    200
+}
+
+fn default_extraction_max_chars() -> usize {
+    12000
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            workspace_path: default_workspace_path(),
+            max_memories: default_max_memories(),
+            per_user: true,
+            inject_today_notes: false,
+            inject_notes_max_chars: default_inject_notes_max_chars(),
+            extraction: MemoryExtractionConfig::default(),
+            review: MemoryReviewConfig::default(),
+            conversation_format: ConversationFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolsConfig {
+    /// Shell 命令白名单
+    #[serde(default)]
+    pub shell_whitelist: Vec<String>,
+    /// shell 工具可通过 env 参数设置的环境变量名
+    #[serde(default)]
+    pub shell_env_allowlist: Vec<String>,
+    /// 执行命令时清空继承的环境变量（避免泄露 API Key 等），只保留 `shell_pass_env`
+    #[serde(default)]
+    pub shell_clear_env: bool,
+    /// 清空环境变量时保留的变量
+    #[serde(default = "default_shell_pass_env")]
+    pub shell_pass_env: Vec<String>,
+    /// 流式执行时推送进度的间隔（秒）
+    #[serde(default = "default_shell_progress_interval_secs")]
+    pub shell_progress_interval_secs: u64,
+    /// 流式执行时最多收集的输出字节数，超出后终止命令
+    #[serde(default = "default_shell_max_stream_bytes")]
+    pub shell_max_stream_bytes: usize,
+    /// 允许读写的文件路径（同 write_paths，保留兼容）
+    #[serde(default)]
+    pub allowed_paths: Vec<String>,
+    /// 只允许读取的文件路径（目录或 glob 模式）
+    #[serde(default)]
+    pub read_paths: Vec<String>,
+    /// 允许读写的文件路径（目录或 glob 模式）
+    #[serde(default)]
+    pub write_paths: Vec<String>,
+    /// 禁止读写的路径（目录或 glob 模式，如 **/.env），优先于以上配置
+    #[serde(default)]
+    pub deny_paths: Vec<String>,
+    /// delete_file 移到回收站的文件保留天数，gateway 定期清理（0 表示永久保留）
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u64,
+    /// Web 搜索 API Key
+    pub search_api_key: Option<String>,
+    /// 单次工具输出的最大字符数，超出部分截断（0 表示不限制）
+    #[serde(default = "default_max_output_chars")]
+    pub max_output_chars: usize,
+    /// 按工具名覆盖输出上限，如 { shell = 4000 }
+    #[serde(default)]
+    pub output_limits: HashMap<String, usize>,
+    /// list_conversations / read_conversation 可访问的对话历史
+    #[serde(default)]
+    pub conversation_access: ConversationAccess,
+    /// 工具输出的提示注入防护
+    #[serde(default)]
+    pub injection_guard: InjectionGuardConfig,
+    /// 访问外部网址的工具共用的网址策略
+    #[serde(default)]
+    pub url_policy: UrlPolicyConfig,
+    /// 演练模式：有副作用的工具（shell、write_file、message 等）不实际执行，只返回将要执行的操作
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// 出站网址策略配置（fetch_page、web_search 结果等共用）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UrlPolicyConfig {
+    /// 允许访问的域名（包括子域名），为空表示不限制
+    #[serde(default)]
+    pub allow_domains: Vec<String>,
+    /// 禁止访问的域名（包括子域名），优先于白名单
+    #[serde(default)]
+    pub deny_domains: Vec<String>,
+    /// 拒绝访问内网、回环、链路本地等地址（防止 SSRF）
+    #[serde(default = "default_true")]
+    pub block_private: bool,
+    /// 最多跟随的重定向次数
+    #[serde(default = "default_url_max_redirects")]
+    pub max_redirects: usize,
+    /// 最多读取的响应字节数（0 表示不限制）
+    #[serde(default = "default_url_max_content_bytes")]
+    pub max_content_bytes: usize,
+}
+
+impl Default for UrlPolicyConfig {
+    fn default() -> Self {
+        Self {
+            allow_domains: Vec::new(),
+            deny_domains: Vec::new(),
+            block_private: true,
+            max_redirects: default_url_max_redirects(),
+            max_content_bytes: default_url_max_content_bytes(),
+        }
+    }
+}
+
+fn default_url_max_redirects() -> usize {
+    5
+}
+
+fn default_url_max_content_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+/// 工具输出的提示注入防护配置
+///
+/// 网页、文件等外部内容可能夹带劫持 Agent 的指令。启用后按工具配置的级别处理输出：
+/// 用带随机标记的分隔符包裹并注明其中是数据而不是指令，可选启发式检测和删除可疑指令
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InjectionGuardConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 各工具的防护级别，未列出的工具不处理
+    #[serde(default = "default_injection_guard_tools")]
+    pub tools: HashMap<String, GuardLevel>,
+}
+
+impl Default for InjectionGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tools: default_injection_guard_tools(),
+        }
+    }
+}
+
+/// 工具输出的防护级别（每一级包含前一级的处理）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardLevel {
+    /// 不处理
+    Off,
+    /// 用分隔符包裹并附加说明
+    Wrap,
+    /// 启发式检测疑似注入，命中时附加警告
+    Detect,
+    /// 删除包含疑似注入指令的行
+    Strip,
+}
+
+fn default_injection_guard_tools() -> HashMap<String, GuardLevel> {
+    HashMap::from([
+        ("fetch_page".to_string(), GuardLevel::Strip),
+        ("web_search".to_string(), GuardLevel::Detect),
+        ("read_file".to_string(), GuardLevel::Detect),
+    ])
+}
+
+/// 对话历史工具的访问范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationAccess {
+    /// 只能访问当前聊天的对话（本地命令行不受限制）
+    #[default]
+    Own,
+    /// 可访问所有对话
+    All,
+    /// 不提供对话历史工具
+    Off,
+}
+
+impl ToolsConfig {
+    /// 指定工具的输出上限
+    pub fn output_limit(&self, tool: &str) -> usize {
+        self.output_limits
+            .get(tool)
+            .copied()
+            .unwrap_or(self.max_output_chars)
+    }
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            shell_whitelist: vec!["echo".to_string(), "cat".to_string(), "ls".to_string()],
+            shell_env_allowlist: Vec::new(),
+            shell_clear_env: false,
+            shell_pass_env: default_shell_pass_env(),
+            shell_progress_interval_secs: default_shell_progress_interval_secs(),
+            shell_max_stream_bytes: default_shell_max_stream_bytes(),
+            allowed_paths: vec!["/home".to_string(), "/tmp".to_string()],
+            read_paths: Vec::new(),
+            write_paths: Vec::new(),
+            deny_paths: Vec::new(),
+            trash_retention_days: default_trash_retention_days(),
+            search_api_key: None,
+            max_output_chars: default_max_output_chars(),
+            output_limits: HashMap::new(),
+            conversation_access: ConversationAccess::default(),
+            injection_guard: InjectionGuardConfig::default(),
+            url_policy: UrlPolicyConfig::default(),
+            dry_run: false,
+        }
+    }
+}
+
+fn default_shell_pass_env() -> Vec<String> {
+    ["PATH", "HOME", "USER", "LANG", "LC_ALL", "TZ", "TERM"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_shell_progress_interval_secs() -> u64 {
+    15
+}
+
+fn default_shell_max_stream_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_trash_retention_days() -> u64 {
+    30
+}
+
+fn default_max_output_chars() -> usize {
+    16000
+}
+
+/// API 服务配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiConfig {
+    /// 是否启用 API 服务（gateway 模式下启动）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 监听地址（API 与通道回调如飞书事件订阅共用，未启用 API 时通道回调仍使用此地址）
+    #[serde(default = "default_api_bind")]
+    pub bind: String,
+    /// `POST /broadcast` 的访问令牌（相当于 admin 权限的令牌，未配置任何 admin 令牌时不开放广播接口）
+    #[serde(default)]
+    pub broadcast_token: Option<String>,
+    /// `/jobs` 任务管理接口的访问令牌（相当于 jobs 权限的令牌，未配置任何 jobs 令牌时不开放）
+    #[serde(default)]
+    pub jobs_token: Option<String>,
+    /// 允许通过接口创建任务的处理器（默认只有 reminder，为空时不允许通过接口创建任务）
+    #[serde(default = "default_api_job_handlers")]
+    pub job_handlers: Vec<String>,
+    /// 路径前缀（如 "/nanobot"），反向代理按子路径转发时设置，所有接口挂在该前缀下
+    #[serde(default)]
+    pub base_path: Option<String>,
+    /// 受信任的反向代理地址（IP 或网段，如 "10.0.0.0/8"），
+    /// 来自这些地址的请求按 X-Forwarded-For 识别客户端 IP（用于审计日志）
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// 内置 TLS，未设置时使用明文 HTTP（通常由反向代理终止 TLS）
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// 访问令牌（键为令牌名，记录在审计日志中），也可以用 `nanobot token create` 创建
+    #[serde(default)]
+    pub tokens: HashMap<String, ApiTokenConfig>,
+}
+
+/// API 访问令牌
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiTokenConfig {
+    /// 令牌值（请求头 `Authorization: Bearer <令牌>`）
+    pub token: String,
+    /// 权限范围，admin 包含全部权限
+    #[serde(default)]
+    pub scopes: Vec<ApiScope>,
+    /// 过期时间（RFC 3339），未设置时不过期
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// API 令牌的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    /// 与 Agent 对话
+    Chat,
+    /// 管理操作（广播等），包含全部权限
+    Admin,
+    /// 定时任务管理（/jobs）
+    Jobs,
+    /// 运行状态（/channels/health）
+    Metrics,
+    /// 事件流（/events，包含消息内容与工具参数、结果）
+    Events,
+}
+
+impl ApiScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Admin => "admin",
+            Self::Jobs => "jobs",
+            Self::Metrics => "metrics",
+            Self::Events => "events",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "chat" => Ok(Self::Chat),
+            "admin" => Ok(Self::Admin),
+            "jobs" => Ok(Self::Jobs),
+            "metrics" => Ok(Self::Metrics),
+            "events" => Ok(Self::Events),
+            _ => anyhow::bail!("未知的权限范围: {}（可选 chat、admin、jobs、metrics、events）", s),
+        }
+    }
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_api_bind(),
+            broadcast_token: None,
+            jobs_token: None,
+            job_handlers: default_api_job_handlers(),
+            base_path: None,
+            trusted_proxies: Vec::new(),
+            tls: None,
+            tokens: HashMap::new(),
+        }
+    }
+}
+
+/// HTTP 服务的 TLS 证书配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TlsConfig {
+    /// 证书链文件（PEM）
+    pub cert_path: PathBuf,
+    /// 私钥文件（PEM，PKCS#8 或 RSA）
+    pub key_path: PathBuf,
+}
+
+fn default_api_bind() -> String {
+    "127.0.0.1:8787".to_string()
+}
+
+fn default_api_job_handlers() -> Vec<String> {
+    vec!["reminder".to_string()]
+}
+
+/// 会话配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionConfig {
+    /// 无活动多久后结束会话（秒）
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// gateway 中检查空闲会话的间隔（秒）
+    #[serde(default = "default_cleanup_interval_secs")]
+    pub cleanup_interval_secs: u64,
+    /// 用户发送多少条消息后自动生成会话标题（0 表示不生成）
+    #[serde(default = "default_title_after_turns")]
+    pub title_after_turns: u64,
+    /// 生成标题使用的模型（"provider/model" 或仅模型名），未设置时使用默认模型
+    #[serde(default)]
+    pub title_model: Option<String>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: default_idle_timeout_secs(),
+            cleanup_interval_secs: default_cleanup_interval_secs(),
+            title_after_turns: default_title_after_turns(),
+            title_model: None,
+        }
+    }
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_cleanup_interval_secs() -> u64 {
+    300
+}
+
+fn default_title_after_turns() -> u64 {
+    3
+}
+
+/// 隐私与数据保留配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct PrivacyConfig {
+    /// 数据保留天数，gateway 定期删除更早的对话历史、会话统计、发件箱记录和调试日志（0 表示永久保留）
+    #[serde(default)]
+    pub retention_days: u64,
+}
+
+/// 静态数据加密配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct VaultConfig {
+    /// 是否加密存储笔记、长期记忆、对话历史和数据库中的敏感字段
+    #[serde(default)]
+    pub enabled: bool,
+    /// 密钥文件（内容作为口令），未设置时读取环境变量 NANOBOT_VAULT_PASSPHRASE
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+}
+
+/// Agent 角色配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct PersonaConfig {
+    /// 简介（显示在角色列表中）
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 系统提示词（替换 agent.system_prompt）
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// 使用的模型（"provider/model" 或仅模型名）
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 采样温度
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// 允许使用的工具，未设置时可使用全部工具
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// 工具决策阶段的生成参数（本轮还没有工具结果且可以调用工具时）
+    #[serde(default)]
+    pub tool_params: Option<GenerationParams>,
+    /// 回答阶段的生成参数（已拿到工具结果或没有可用工具时）
+    #[serde(default)]
+    pub answer_params: Option<GenerationParams>,
+}
+
+impl PersonaConfig {
+    /// 是否允许使用指定工具
+    pub fn allows_tool(&self, name: &str) -> bool {
+        self.tools.as_ref().is_none_or(|tools| tools.iter().any(|t| t == name))
+    }
+
+    /// 指定阶段的生成参数
+    pub fn phase_params(&self, tool_phase: bool) -> Option<&GenerationParams> {
+        if tool_phase {
+            self.tool_params.as_ref()
+        } else {
+            self.answer_params.as_ref()
+        }
+    }
+}
+
+/// 新用户引导配置
+///
+/// 启用后，通道中首次对话的用户会先回答几个问题（称呼、时区、偏好），
+/// 回答通过 remember_user 工具保存到该用户的资料中，之后不再引导
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OnboardingConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 引导提示词，引导期间附加到系统提示词
+    #[serde(default = "default_onboarding_template")]
+    pub template: String,
+    /// 最多引导的轮数，超过后不再引导
+    #[serde(default = "default_onboarding_max_turns")]
+    pub max_turns: u32,
+}
+
+impl Default for OnboardingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: default_onboarding_template(),
+            max_turns: default_onboarding_max_turns(),
+        }
+    }
+}
+
+/// 后台任务配置
+///
+/// 启用后模型可通过 start_task 把耗时的工具调用放到后台执行，之后用 check_task / cancel_task 查询或取消
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TasksConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 同时运行的任务数上限（0 表示不限制）
+    #[serde(default = "default_tasks_max_running")]
+    pub max_running: usize,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_running: default_tasks_max_running(),
+        }
+    }
+}
+
+fn default_tasks_max_running() -> usize {
+    4
+}
+
+/// 深度调研配置（/research、`nanobot research`）
+///
+/// 调研先拆分子问题，再多轮调用 web_search / fetch_page 收集资料，最后生成带引用的报告。
+/// 迭代次数和令牌用量单独限制，不占用普通对话的工具调用轮数
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResearchConfig {
+    /// 使用的模型（"provider/model" 或仅模型名），未设置时使用默认模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 最多拆分的子问题数
+    #[serde(default = "default_research_max_questions")]
+    pub max_questions: usize,
+    /// 搜索阶段最多调用模型的次数
+    #[serde(default = "default_research_max_iterations")]
+    pub max_iterations: u32,
+    /// 整个调研最多使用的令牌数（0 表示不限制），用完后直接根据已有资料生成报告
+    #[serde(default = "default_research_max_tokens")]
+    pub max_tokens: u32,
+    /// 单次工具结果交给模型的最大字符数
+    #[serde(default = "default_research_max_result_chars")]
+    pub max_result_chars: usize,
+}
+
+impl Default for ResearchConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            max_questions: default_research_max_questions(),
+            max_iterations: default_research_max_iterations(),
+            max_tokens: default_research_max_tokens(),
+            max_result_chars: default_research_max_result_chars(),
+        }
+    }
+}
+
+fn default_research_max_questions() -> usize {
+    4
+}
+
+fn default_research_max_iterations() -> u32 {
+    12
+}
+
+fn default_research_max_tokens() -> u32 {
+    100_000
+}
+
+fn default_research_max_result_chars() -> usize {
+    6000
+}
+
+/// 长对话自动降级模型配置
+///
+/// 会话自上次清空上下文（/clear）以来使用的令牌数超过阈值后改用更便宜的模型并提示用户，
+/// 上下文清空后恢复。单次请求、角色或 /model 指定的模型优先，不会被降级
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DowngradeConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 降级后使用的模型（"provider/model" 或仅模型名）
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 触发降级的令牌数
+    #[serde(default = "default_downgrade_token_threshold")]
+    pub token_threshold: u64,
+}
+
+impl Default for DowngradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: None,
+            token_threshold: default_downgrade_token_threshold(),
+        }
+    }
+}
+
+fn default_downgrade_token_threshold() -> u64 {
+    200_000
+}
+
+/// 消息内容过滤配置
+///
+/// 用户消息交给 Agent 之前、回复发出之前按正则规则（以及可选的模型分类）检查内容，
+/// 命中后拦截、打码或仅标记，每次命中记录到审计日志并发布 `content.filtered` 事件
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContentFilterConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 启用过滤的通道（为空表示所有通道）
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// 是否检查用户消息
+    #[serde(default = "default_true")]
+    pub inbound: bool,
+    /// 是否检查回复
+    #[serde(default = "default_true")]
+    pub outbound: bool,
+    /// 正则规则
+    #[serde(default)]
+    pub rules: Vec<ContentFilterRule>,
+    /// 内容分类使用的模型（"provider/model" 或仅模型名），未设置时只使用正则规则
+    #[serde(default)]
+    pub classifier_model: Option<String>,
+    /// 模型判定为不当内容时的处理方式（block 或 flag）
+    #[serde(default)]
+    pub classifier_action: FilterAction,
+    /// 打码时替换成的文本
+    #[serde(default = "default_filter_redact_with")]
+    pub redact_with: String,
+    /// 消息被拦截时回复给用户的内容
+    #[serde(default = "default_filter_block_message")]
+    pub block_message: String,
+}
+
+impl Default for ContentFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channels: Vec::new(),
+            inbound: true,
+            outbound: true,
+            rules: Vec::new(),
+            classifier_model: None,
+            classifier_action: FilterAction::default(),
+            redact_with: default_filter_redact_with(),
+            block_message: default_filter_block_message(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[derive(Default)]
-pub struct LlmConfig {
-    /// OpenRouter 配置
-    #[serde(default)]
-    pub openrouter: ProviderConfig,
-    /// DeepSeek 配置
+/// 内容过滤规则
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContentFilterRule {
+    /// 正则表达式（大小写不敏感可用 `(?i)` 前缀）
+    pub pattern: String,
+    /// 命中后的处理方式
     #[serde(default)]
-    pub deepseek: ProviderConfig,
-    /// MiniMax 配置
+    pub action: FilterAction,
+}
+
+/// 内容过滤的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// 只记录，不修改内容
+    Flag,
+    /// 把命中的部分替换为 `redact_with`
+    Redact,
+    /// 拦截整条消息
+    #[default]
+    Block,
+}
+
+fn default_filter_redact_with() -> String {
+    "***".to_string()
+}
+
+fn default_filter_block_message() -> String {
+    "⚠️ 消息包含不允许的内容，已被拦截。".to_string()
+}
+
+fn default_onboarding_template() -> String {
+    "这是用户第一次和你对话。先简短地欢迎用户并回应用户的消息，然后每次只问一个问题，依次了解：\n\
+     1. 希望怎么称呼对方\n\
+     2. 所在时区或城市\n\
+     3. 回答风格等偏好（如语言、详略）\n\
+     每得到一个回答就调用 remember_user 工具保存（key 分别为 name、timezone、preferences），\
+     时区保存为 IANA 时区名（如城市是北京时保存 Asia/Shanghai）。\
+     用户不愿回答时不要追问。问完或用户跳过后，调用 remember_user 保存 key 为 onboarding、value 为 done。"
+        .to_string()
+}
+
+fn default_onboarding_max_turns() -> u32 {
+    5
+}
+
+/// 用量预算配置（金额单位为美元，0 表示不限制）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BudgetConfig {
+    /// 每日总预算
     #[serde(default)]
-    pub minimax: ProviderConfig,
-    /// Moonshot 配置
+    pub daily_usd: f64,
+    /// 每月总预算
     #[serde(default)]
-    pub moonshot: ProviderConfig,
-    /// vLLM 配置
+    pub monthly_usd: f64,
+    /// 每个用户（通道:聊天 ID）的预算
     #[serde(default)]
-    pub vllm: ProviderConfig,
-    /// OpenAI 配置
+    pub per_user: BudgetLimits,
+    /// 各提供商的预算
     #[serde(default)]
-    pub openai: ProviderConfig,
-    /// Anthropic 配置
+    pub providers: HashMap<String, BudgetLimits>,
+    /// 用量达到预算的百分比时告警
+    #[serde(default = "default_budget_alert_percent")]
+    pub alert_percent: f64,
+    /// 超出预算时是否拒绝非管理员的请求
     #[serde(default)]
-    pub anthropic: ProviderConfig,
-    /// Google Gemini 配置
+    pub hard_cap: bool,
+    /// 不受预算限制的管理员（通道:聊天 ID 或聊天 ID）
     #[serde(default)]
-    pub gemini: ProviderConfig,
-    /// 智谱 AI (Zhipu) 配置
+    pub admin_users: Vec<String>,
+    /// 告警发送的通道
     #[serde(default)]
-    pub zhipu: ProviderConfig,
-    /// 阿里云 DashScope (Qwen) 配置
+    pub alert_channel: Option<String>,
+    /// 告警发送的聊天 ID
     #[serde(default)]
-    pub dashscope: ProviderConfig,
-    /// Groq 配置
+    pub alert_chat_id: Option<String>,
+    /// 模型价格（键为模型名或 provider/model），单位为美元每百万令牌
     #[serde(default)]
-    pub groq: ProviderConfig,
+    pub prices: HashMap<String, ModelPrice>,
 }
 
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            daily_usd: 0.0,
+            monthly_usd: 0.0,
+            per_user: BudgetLimits::default(),
+            providers: HashMap::new(),
+            alert_percent: default_budget_alert_percent(),
+            hard_cap: false,
+            admin_users: Vec::new(),
+            alert_channel: None,
+            alert_chat_id: None,
+            prices: HashMap::new(),
+        }
+    }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct ProviderConfig {
-    /// API Key
-    pub api_key: Option<String>,
-    /// 基础 URL（用于自定义端点）
-    pub base_url: Option<String>,
-    /// 默认模型
-    pub default_model: Option<String>,
-    /// 超时时间（秒）
-    #[serde(default = "default_timeout")]
-    pub timeout_secs: u64,
-    /// 自定义请求头（用于 API Gateway 等，如 AiHubMix 的 APP-Code）
-    #[serde(default)]
-    pub extra_headers: std::collections::HashMap<String, String>,
+impl BudgetConfig {
+    /// 是否配置了任一预算
+    pub fn is_enabled(&self) -> bool {
+        self.daily_usd > 0.0
+            || self.monthly_usd > 0.0
+            || self.per_user.is_set()
+            || self.providers.values().any(BudgetLimits::is_set)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[derive(Default)]
-pub struct ChannelConfig {
-    /// Telegram 配置
-    #[serde(default)]
-    pub telegram: TelegramConfig,
-    /// Discord 配置
-    #[serde(default)]
-    pub discord: DiscordConfig,
-    /// 飞书配置
+/// 每日/每月预算（美元，0 表示不限制）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct BudgetLimits {
     #[serde(default)]
-    pub feishu: FeishuConfig,
-    /// WhatsApp 配置
+    pub daily_usd: f64,
     #[serde(default)]
-    pub whatsapp: WhatsAppConfig,
+    pub monthly_usd: f64,
 }
 
-
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct TelegramConfig {
-    /// Bot Token
-    pub bot_token: Option<String>,
-    /// 允许的用户 ID 列表
-    #[serde(default)]
-    pub allowed_users: Vec<i64>,
-    /// Webhook URL（可选）
-    pub webhook_url: Option<String>,
+impl BudgetLimits {
+    pub fn is_set(&self) -> bool {
+        self.daily_usd > 0.0 || self.monthly_usd > 0.0
+    }
 }
 
-/// Discord 配置
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct DiscordConfig {
-    /// Bot Token
-    pub bot_token: Option<String>,
-    /// Application ID
-    pub application_id: Option<u64>,
-    /// 允许的服务器 ID 列表
-    #[serde(default)]
-    pub allowed_guilds: Vec<u64>,
-    /// 允许的频道 ID 列表
+/// 模型价格（美元每百万令牌）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct ModelPrice {
+    /// 输入（提示词）价格
     #[serde(default)]
-    pub allowed_channels: Vec<u64>,
-    /// 允许的用户 ID 列表
+    pub input: f64,
+    /// 输出（补全）价格
     #[serde(default)]
-    pub allowed_users: Vec<u64>,
-    /// 默认前缀
-    #[serde(default = "default_prefix")]
-    pub prefix: String,
-    /// Webhook URL（可选）
-    pub webhook_url: Option<String>,
-    /// 是否启用 Slash Command
-    #[serde(default = "default_true")]
-    pub enable_slash_commands: bool,
+    pub output: f64,
 }
 
-/// 飞书配置
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct FeishuConfig {
-    /// App ID
-    pub app_id: Option<String>,
-    /// App Secret
-    pub app_secret: Option<String>,
-    /// Verification Token
-    pub verification_token: Option<String>,
-    /// Encrypt Key
-    pub encrypt_key: Option<String>,
-    /// 允许的用户 Open ID 列表
+fn default_budget_alert_percent() -> f64 {
+    80.0
+}
+
+/// 向量嵌入配置
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddingsConfig {
+    /// 是否启用（recall 工具按语义相似度补充关键词未命中的记忆）
     #[serde(default)]
-    pub allowed_users: Vec<String>,
-    /// 允许的用户 Open ID 列表（别名）
+    pub enabled: bool,
+    /// 后端：openai（OpenAI 兼容接口）、ollama、local（本地模型）
+    #[serde(default = "default_embedding_backend")]
+    pub backend: String,
+    /// 模型名称（openai / ollama）
     #[serde(default)]
-    pub allowed_open_ids: Vec<String>,
-    /// 允许的群 Chat ID 列表
+    pub model: Option<String>,
+    /// 服务地址（openai / ollama）
     #[serde(default)]
-    pub allowed_chats: Vec<String>,
-    /// 是否验证请求签名
-    #[serde(default = "default_true")]
-    pub verify_signature: bool,
-    /// 消息卡片模板 ID
-    pub card_template_id: Option<String>,
-}
-
-/// WhatsApp 配置
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct WhatsAppConfig {
-    /// WebSocket Bridge URL
-    pub bridge_url: Option<String>,
-    /// 允许的用户手机号列表
+    pub base_url: Option<String>,
+    /// API Key（openai）
     #[serde(default)]
-    pub allowed_users: Vec<String>,
-    /// 自动重连间隔（秒）
-    #[serde(default = "default_reconnect_interval")]
-    pub reconnect_interval_secs: u64,
-    /// 是否自动重连
-    #[serde(default = "default_true")]
-    pub auto_reconnect: bool,
+    pub api_key: Option<String>,
+    /// 本地模型目录（local），包含 config.json、tokenizer.json、model.safetensors
+    #[serde(default)]
+    pub model_path: Option<PathBuf>,
+    /// 单次请求的最大文本数
+    #[serde(default = "default_embedding_batch_size")]
+    pub batch_size: usize,
+    /// 超时时间（秒）
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    /// 语义检索记忆时的最低相似度（余弦相似度，0-1）
+    #[serde(default = "default_embedding_min_similarity")]
+    pub min_similarity: f32,
 }
 
-fn default_reconnect_interval() -> u64 {
-    5
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: default_embedding_backend(),
+            model: None,
+            base_url: None,
+            api_key: None,
+            model_path: None,
+            batch_size: default_embedding_batch_size(),
+            timeout_secs: default_timeout(),
+            min_similarity: default_embedding_min_similarity(),
+        }
+    }
 }
 
-fn default_prefix() -> String {
-    "!".to_string()
+fn default_embedding_backend() -> String {
+    "openai".to_string()
 }
 
-fn default_true() -> bool {
-    true
+fn default_embedding_batch_size() -> usize {
+    64
 }
 
-/// 内存系统配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MemoryConfig {
-    /// 工作目录路径（用于存储 Markdown 记忆文件）
-    #[serde(default = "default_workspace_path")]
-    pub workspace_path: PathBuf,
-    /// 最大记忆条数
-    #[serde(default = "default_max_memories")]
-    pub max_memories: usize,
+fn default_embedding_min_similarity() -> f32 {
+    0.5
 }
 
-impl Default for MemoryConfig {
-    fn default() -> Self {
-        Self {
-            workspace_path: default_workspace_path(),
-            max_memories: default_max_memories(),
-        }
-    }
+// 默认值函数
+fn default_prompt_budget_chars() -> usize {
+    8000
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ToolsConfig {
-    /// Shell 命令白名单
-    #[serde(default)]
-    pub shell_whitelist: Vec<String>,
-    /// 允许的文件路径
-    #[serde(default)]
-    pub allowed_paths: Vec<String>,
-    /// Web 搜索 API Key
-    pub search_api_key: Option<String>,
+fn default_turn_timeout_secs() -> u64 {
+    300
 }
 
-impl Default for ToolsConfig {
-    fn default() -> Self {
-        Self {
-            shell_whitelist: vec!["echo".to_string(), "cat".to_string(), "ls".to_string()],
-            allowed_paths: vec!["/home".to_string(), "/tmp".to_string()],
-            search_api_key: None,
-        }
-    }
+fn default_max_best_of() -> usize {
+    5
 }
 
-// 默认值函数
 fn default_system_prompt() -> String {
     "你是一个有帮助的 AI 助手。你可以使用工具来完成用户的请求。".to_string()
 }
@@ -285,6 +2189,26 @@ fn default_timeout() -> u64 {
     60
 }
 
+fn default_rate_limit_retries() -> u32 {
+    3
+}
+
+fn default_rate_limit_max_wait_secs() -> u64 {
+    60
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    8
+}
+
+fn default_keepalive_secs() -> u64 {
+    30
+}
+
 fn default_workspace_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
     home.join(".nanobot")
@@ -294,6 +2218,10 @@ fn default_max_memories() -> usize {
     1000
 }
 
+fn default_inject_notes_max_chars() -> usize {
+    2000
+}
+
 impl Config {
     /// 加载配置文件
     pub fn load(path: Option<&str>) -> Result<Self> {
@@ -309,9 +2237,16 @@ impl Config {
 
         let content = std::fs::read_to_string(&config_path)
             .with_context(|| format!("读取配置文件失败: {}", config_path.display()))?;
-        
-        let mut config: Config = toml::from_str(&content)
-            .with_context(|| "解析配置文件失败")?;
+        let (mut config, unknown) = Self::parse(&content)?;
+
+        // 未知的配置项不会生效，严格模式下直接报错
+        if config.strict && !unknown.is_empty() {
+            let list: Vec<String> = unknown.iter().map(|k| k.to_string()).collect();
+            anyhow::bail!("配置文件 {} 中有未知的配置项:\n{}", config_path.display(), list.join("\n"));
+        }
+        for key in &unknown {
+            tracing::warn!("{}", key);
+        }
 
         // 环境变量覆盖
         config.apply_env_overrides();
@@ -319,7 +2254,17 @@ impl Config {
         Ok(config)
     }
 
+    /// 解析配置文件内容：展开字符串中的 `${VAR}` 环境变量引用，同时返回不会生效的未知配置项
+    pub fn parse(content: &str) -> Result<(Self, Vec<schema::UnknownKey>)> {
+        let mut value: toml::Value = toml::from_str(content).with_context(|| "解析配置文件失败")?;
+        env::expand_value(&mut value)?;
+        let unknown = schema::unknown_keys(&value);
+        let config = Config::deserialize(value).with_context(|| "解析配置文件失败")?;
+        Ok((config, unknown))
+    }
+
     /// 保存配置文件
+    #[allow(dead_code)]
     pub fn save(&self, path: Option<&str>) -> Result<()> {
         let config_path = if let Some(p) = path {
             PathBuf::from(p)
@@ -338,6 +2283,94 @@ impl Config {
         Ok(())
     }
 
+    /// 定时任务数据库路径
+    pub fn cron_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("cron.db")
+    }
+
+    /// 消息发件箱数据库路径
+    pub fn outbox_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("outbox.db")
+    }
+
+    /// 会话统计数据库路径
+    pub fn sessions_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("sessions.db")
+    }
+
+    /// 默认的 LLM 调试日志目录（--debug-llm）
+    pub fn default_llm_debug_dir(&self) -> PathBuf {
+        self.memory.workspace_path.join("llm-debug")
+    }
+
+    /// 入站消息去重数据库路径
+    pub fn dedupe_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("dedupe.db")
+    }
+
+    /// 提供商模型列表缓存路径（models 命令使用）
+    pub fn models_cache_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("models_cache.json")
+    }
+
+    /// 内容过滤审计日志路径（每行一条 JSON）
+    pub fn content_filter_log_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("content_filter.jsonl")
+    }
+
+    /// 通道健康状态快照路径（gateway 写入，status 命令读取）
+    pub fn channel_health_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("channel_health.json")
+    }
+
+    /// 通道收到的媒体文件目录
+    pub fn media_dir(&self) -> PathBuf {
+        self.memory.workspace_path.join("media")
+    }
+
+    /// 回收站目录（delete_file 删除的文件）
+    pub fn trash_dir(&self) -> PathBuf {
+        self.memory.workspace_path.join("trash")
+    }
+
+    /// 用量账本数据库路径（预算）
+    pub fn usage_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("usage.db")
+    }
+
+    /// 后台任务数据库路径
+    pub fn tasks_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("tasks.db")
+    }
+
+    /// API 令牌数据库路径（`nanobot token create` 创建的令牌）
+    pub fn api_tokens_db_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("api_tokens.db")
+    }
+
+    /// API 调用审计日志路径（每行一条 JSON）
+    pub fn api_audit_log_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("api_audit.jsonl")
+    }
+
+    /// 工作目录中的所有 SQLite 数据库路径（不检查是否存在）
+    pub fn database_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.sessions_db_path(),
+            self.cron_db_path(),
+            self.outbox_db_path(),
+            self.dedupe_db_path(),
+            self.usage_db_path(),
+            self.tasks_db_path(),
+            self.api_tokens_db_path(),
+        ]
+    }
+
+    /// vault 密钥派生参数路径
+    pub fn vault_header_path(&self) -> PathBuf {
+        self.memory.workspace_path.join("vault.json")
+    }
+
     /// 默认配置文件路径
     pub fn default_config_path() -> Result<PathBuf> {
         let home = dirs::home_dir()
@@ -401,6 +2434,22 @@ impl Config {
         if let Ok(key) = std::env::var("GROQ_API_KEY") {
             self.llm.groq.api_key = Some(key);
         }
+
+        // SiliconFlow
+        if let Ok(key) = std::env::var("SILICONFLOW_API_KEY") {
+            self.llm.siliconflow.api_key = Some(key);
+        }
+        if let Ok(url) = std::env::var("SILICONFLOW_BASE_URL") {
+            self.llm.siliconflow.base_url = Some(url);
+        }
+
+        // Together.ai
+        if let Ok(key) = std::env::var("TOGETHER_API_KEY") {
+            self.llm.together.api_key = Some(key);
+        }
+        if let Ok(url) = std::env::var("TOGETHER_BASE_URL") {
+            self.llm.together.base_url = Some(url);
+        }
         
         // Telegram
         if let Ok(token) = std::env::var("TELEGRAM_BOT_TOKEN") {
@@ -436,6 +2485,11 @@ impl Config {
             self.channel.whatsapp.bridge_url = Some(bridge_url);
         }
         
+        // 时区
+        if let Ok(tz) = std::env::var("NANOBOT_TIMEZONE") {
+            self.agent.timezone = Some(tz);
+        }
+        
         // 搜索 API
         if let Ok(key) = std::env::var("SEARCH_API_KEY") {
             self.tools.search_api_key = Some(key);
@@ -445,11 +2499,24 @@ impl Config {
     /// 生成示例配置
     pub fn example() -> Self {
         Self {
+            strict: false,
             agent: AgentConfig {
                 system_prompt: "你是一个有帮助的 AI 助手。".to_string(),
                 max_context: 20,
                 default_provider: "openrouter".to_string(),
                 default_model: "openrouter/optimus-alpha".to_string(),
+                timezone: Some("+08:00".to_string()),
+                channel_prompts: HashMap::from([(
+                    "telegram".to_string(),
+                    "回复会显示在手机上，尽量简短。".to_string(),
+                )]),
+                user_profile: Some("称呼我为老板，偏好中文回答。".to_string()),
+                prompt_budget_chars: default_prompt_budget_chars(),
+                match_reply_language: true,
+                turn_timeout_secs: default_turn_timeout_secs(),
+                best_of_strategy: BestOfStrategy::Vote,
+                max_best_of: default_max_best_of(),
+                generation: GenerationParams::default(),
             },
             llm: LlmConfig {
                 openrouter: ProviderConfig {
@@ -457,67 +2524,97 @@ impl Config {
                     base_url: Some("https://openrouter.ai/api/v1".to_string()),
                     default_model: Some("openrouter/optimus-alpha".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
                 deepseek: ProviderConfig {
                     api_key: Some("your-deepseek-api-key".to_string()),
                     base_url: Some("https://api.deepseek.com".to_string()),
                     default_model: Some("deepseek-chat".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
                 minimax: ProviderConfig {
                     api_key: Some("your-minimax-api-key".to_string()),
                     base_url: Some("https://api.minimax.io/v1".to_string()),
                     default_model: Some("MiniMax-M2.1".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
                 moonshot: ProviderConfig {
                     api_key: Some("your-moonshot-api-key".to_string()),
                     base_url: Some("https://api.moonshot.cn/v1".to_string()),
                     default_model: Some("moonshot-v1-8k".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
                 vllm: ProviderConfig {
                     api_key: Some("".to_string()),
                     base_url: Some("http://localhost:8000/v1".to_string()),
                     default_model: Some("default".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
                 openai: ProviderConfig::default(),
                 anthropic: ProviderConfig::default(),
-                /// Google Gemini 配置
+                // Google Gemini 配置
                 gemini: ProviderConfig {
                     api_key: Some("your-gemini-api-key".to_string()),
                     base_url: Some("https://generativelanguage.googleapis.com/v1beta".to_string()),
                     default_model: Some("gemini-pro".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
-                /// 智谱 AI (Zhipu) 配置
+                // 智谱 AI (Zhipu) 配置
                 zhipu: ProviderConfig {
                     api_key: Some("your-zhipu-api-key".to_string()),
                     base_url: Some("https://open.bigmodel.cn/api/paas/v4".to_string()),
                     default_model: Some("glm-4".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
-                /// 阿里云 DashScope (Qwen) 配置
+                // 阿里云 DashScope (Qwen) 配置
                 dashscope: ProviderConfig {
                     api_key: Some("your-dashscope-api-key".to_string()),
                     base_url: Some("https://dashscope.aliyuncs.com/compatible-mode/v1".to_string()),
                     default_model: Some("qwen-max".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
                 },
-                /// Groq 配置
+                // Groq 配置
                 groq: ProviderConfig {
                     api_key: Some("your-groq-api-key".to_string()),
                     base_url: Some("https://api.groq.com/openai/v1".to_string()),
                     default_model: Some("llama3-8b-8192".to_string()),
                     timeout_secs: 60,
+                    ..Default::default()
+                },
+                siliconflow: ProviderConfig {
+                    api_key: Some("your-siliconflow-api-key".to_string()),
+                    base_url: Some("https://api.siliconflow.cn/v1".to_string()),
+                    default_model: Some("deepseek-ai/DeepSeek-V3".to_string()),
+                    timeout_secs: 60,
+                    ..Default::default()
+                },
+                together: ProviderConfig {
+                    api_key: Some("your-together-api-key".to_string()),
+                    base_url: Some("https://api.together.xyz/v1".to_string()),
+                    default_model: Some("meta-llama/Llama-3.3-70B-Instruct-Turbo".to_string()),
+                    timeout_secs: 60,
+                    ..Default::default()
                 },
+                debug_log_dir: None,
+                router: RouterConfig::default(),
+                probe: ProbeConfig::default(),
             },
             channel: ChannelConfig {
                 telegram: TelegramConfig {
                     bot_token: Some("your-telegram-bot-token".to_string()),
                     allowed_users: vec![],
+                    admin_users: vec![],
                     webhook_url: None,
+                    quiet_hours: None,
+                    merge_window_ms: 0,
+                    wake_words: None,
                 },
                 discord: DiscordConfig {
                     bot_token: Some("your-discord-bot-token".to_string()),
@@ -528,6 +2625,9 @@ impl Config {
                     prefix: "!".to_string(),
                     webhook_url: None,
                     enable_slash_commands: true,
+                    quiet_hours: None,
+                    merge_window_ms: 0,
+                    wake_words: None,
                 },
                 feishu: FeishuConfig {
                     app_id: Some("cli_xxxxxxxxxxxxxxxx".to_string()),
@@ -537,25 +2637,146 @@ impl Config {
                     allowed_users: vec![],
                     allowed_open_ids: vec![],
                     allowed_chats: vec![],
+                    require_mention: true,
+                    download_media: true,
+                    ocr_command: None,
                     verify_signature: true,
                     card_template_id: None,
+                    quiet_hours: None,
+                    merge_window_ms: 0,
+                    wake_words: None,
                 },
                 whatsapp: WhatsAppConfig {
                     bridge_url: Some("ws://localhost:3000".to_string()),
                     allowed_users: vec![],
                     reconnect_interval_secs: 5,
                     auto_reconnect: true,
+                    quiet_hours: None,
+                    merge_window_ms: 0,
+                    wake_words: None,
                 },
+                outbox: true,
+                dedupe: DedupeConfig::default(),
+                supervisor: SupervisorConfig::default(),
+                incognito_channels: vec![],
+                regenerate_on_edit: true,
+                postprocess: PostProcessConfig::default(),
+                admin_users: vec![],
             },
             memory: MemoryConfig {
                 workspace_path: default_workspace_path(),
                 max_memories: 1000,
+                per_user: true,
+                inject_today_notes: false,
+                inject_notes_max_chars: default_inject_notes_max_chars(),
+                extraction: MemoryExtractionConfig::default(),
+                review: MemoryReviewConfig::default(),
+                conversation_format: ConversationFormat::Markdown,
             },
             tools: ToolsConfig {
                 shell_whitelist: vec!["echo".to_string(), "cat".to_string(), "ls".to_string(), "pwd".to_string()],
-                allowed_paths: vec!["/home".to_string(), "/tmp".to_string()],
+                shell_env_allowlist: vec!["RUST_LOG".to_string()],
+                shell_clear_env: true,
+                shell_pass_env: default_shell_pass_env(),
+                shell_progress_interval_secs: default_shell_progress_interval_secs(),
+                shell_max_stream_bytes: default_shell_max_stream_bytes(),
+                allowed_paths: Vec::new(),
+                read_paths: vec!["/home/user/projects".to_string()],
+                write_paths: vec!["/home/user/scratch".to_string(), "/tmp".to_string()],
+                deny_paths: vec!["**/.env".to_string(), "**/.ssh".to_string()],
+                trash_retention_days: default_trash_retention_days(),
                 search_api_key: Some("your-search-api-key".to_string()),
+                max_output_chars: default_max_output_chars(),
+                output_limits: HashMap::from([("shell".to_string(), 8000)]),
+                conversation_access: ConversationAccess::Own,
+                injection_guard: InjectionGuardConfig::default(),
+                url_policy: UrlPolicyConfig::default(),
+                dry_run: false,
+            },
+            api: ApiConfig {
+                enabled: false,
+                bind: default_api_bind(),
+                broadcast_token: None,
+                jobs_token: None,
+                job_handlers: vec!["reminder".to_string()],
+                ..Default::default()
+            },
+            embeddings: EmbeddingsConfig {
+                backend: "openai".to_string(),
+                model: Some("text-embedding-3-small".to_string()),
+                base_url: Some("https://api.openai.com/v1".to_string()),
+                api_key: Some("your-openai-api-key".to_string()),
+                ..Default::default()
+            },
+            session: SessionConfig {
+                idle_timeout_secs: 3600,
+                cleanup_interval_secs: 300,
+                title_after_turns: 3,
+                title_model: None,
+            },
+            privacy: PrivacyConfig::default(),
+            vault: VaultConfig::default(),
+            budget: BudgetConfig::default(),
+            personas: HashMap::from([(
+                "coder".to_string(),
+                PersonaConfig {
+                    description: Some("编程助手".to_string()),
+                    system_prompt: Some("你是一名资深软件工程师，回答简洁并给出可运行的代码。".to_string()),
+                    model: None,
+                    temperature: Some(0.2),
+                    tools: Some(vec![
+                        "read_file".to_string(),
+                        "write_file".to_string(),
+                        "list_dir".to_string(),
+                        "shell".to_string(),
+                    ]),
+                    tool_params: Some(GenerationParams {
+                        temperature: Some(0.0),
+                        ..Default::default()
+                    }),
+                    answer_params: None,
+                },
+            )]),
+            onboarding: OnboardingConfig::default(),
+            tasks: TasksConfig::default(),
+            research: ResearchConfig::default(),
+            downgrade: DowngradeConfig::default(),
+            content_filter: ContentFilterConfig::default(),
+            ui: UiConfig::default(),
+            citations: CitationsConfig::default(),
+            briefing: BriefingConfig::default(),
+            watch: WatchConfig {
+                enabled: false,
+                interval_secs: default_watch_interval_secs(),
+                dirs: vec![WatchDirConfig {
+                    name: "inbox".to_string(),
+                    path: PathBuf::from("/home/user/inbox"),
+                    patterns: vec!["*.pdf".to_string(), "*.md".to_string()],
+                    recursive: false,
+                    events: default_watch_events(),
+                    channel: Some("telegram".to_string()),
+                    chat_id: Some("123456789".to_string()),
+                    prompt: Some("收件箱出现了新文件 {path}，请阅读并总结要点".to_string()),
+                }],
+            },
+            redis: RedisConfig {
+                url: Some("redis://127.0.0.1:6379/0".to_string()),
+                prefix: default_redis_prefix(),
+                connect_timeout_secs: default_redis_connect_timeout_secs(),
+            },
+            crash: CrashConfig {
+                backtrace: true,
+                restart: true,
+                restart_delay_secs: default_crash_restart_delay_secs(),
+                notify_channel: Some("telegram".to_string()),
+                notify_chat_id: Some("123456789".to_string()),
+            },
+            cron: CronConfig {
+                completed_retention_hours: default_cron_completed_retention_hours(),
+                shell_jobs: false,
             },
+            share: ShareConfig::default(),
+            notifications: NotificationsConfig::default(),
         }
     }
 }
@@ -0,0 +1,206 @@
+//! 配置的 JSON Schema 与未知配置项检查
+//!
+//! Schema 由配置结构体派生，`nanobot config schema` 导出后可供编辑器补全和校验。
+//! 加载配置时按 Schema 逐层比对 TOML 中的键，找出拼写错误等不会生效的配置项，并给出最相近的正确写法
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+use super::Config;
+
+/// 配置的 JSON Schema
+pub fn schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+}
+
+/// 配置文件中不会生效的配置项
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownKey {
+    /// 完整路径（如 channel.telegram.alowed_users）
+    pub path: String,
+    /// 最相近的正确键名
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "未知的配置项 {}", self.path)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, "（是否应为 {}？）", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// 找出配置中 Schema 未定义的键
+pub fn unknown_keys(value: &toml::Value) -> Vec<UnknownKey> {
+    let schema = schema();
+    let empty = Map::new();
+    let mut walker = Walker {
+        definitions: schema.get("definitions").and_then(Value::as_object).unwrap_or(&empty),
+        found: Vec::new(),
+    };
+    walker.walk(value, &schema, "");
+    walker.found
+}
+
+struct Walker<'a> {
+    definitions: &'a Map<String, Value>,
+    found: Vec<UnknownKey>,
+}
+
+impl<'a> Walker<'a> {
+    /// 展开引用和 allOf / anyOf / oneOf，得到所有可能匹配的子 Schema
+    fn leaves(&self, schema: &'a Value, out: &mut Vec<&'a Value>) {
+        let schema = match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => match self.definitions.get(reference.trim_start_matches("#/definitions/")) {
+                Some(resolved) => resolved,
+                None => return,
+            },
+            None => schema,
+        };
+        out.push(schema);
+        for key in ["allOf", "anyOf", "oneOf"] {
+            for sub in schema.get(key).and_then(Value::as_array).into_iter().flatten() {
+                self.leaves(sub, out);
+            }
+        }
+    }
+
+    fn walk(&mut self, value: &toml::Value, schema: &'a Value, path: &str) {
+        let mut leaves = Vec::new();
+        self.leaves(schema, &mut leaves);
+
+        match value {
+            toml::Value::Table(table) => {
+                if leaves.iter().any(|s| is_open(s)) {
+                    return;
+                }
+                let properties: Vec<(&String, &Value)> = leaves
+                    .iter()
+                    .filter_map(|s| s.get("properties").and_then(Value::as_object))
+                    .flatten()
+                    .collect();
+                let additional = leaves
+                    .iter()
+                    .filter_map(|s| s.get("additionalProperties"))
+                    .find(|s| !matches!(s, Value::Bool(false)));
+
+                for (key, value) in table {
+                    let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    if let Some((_, schema)) = properties.iter().find(|(name, _)| *name == key) {
+                        self.walk(value, schema, &child);
+                    } else if let Some(schema) = additional {
+                        self.walk(value, schema, &child);
+                    } else {
+                        self.found.push(UnknownKey {
+                            path: child,
+                            suggestion: suggest(key, properties.iter().map(|(name, _)| name.as_str())),
+                        });
+                    }
+                }
+            }
+            toml::Value::Array(items) => {
+                if let Some(schema) = leaves.iter().find_map(|s| s.get("items").filter(|i| i.is_object())) {
+                    for (i, item) in items.iter().enumerate() {
+                        self.walk(item, schema, &format!("{}[{}]", path, i));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 不限制键名的 Schema（如 `true` 或没有任何约束的 `{}`）
+fn is_open(schema: &Value) -> bool {
+    match schema {
+        Value::Bool(allowed) => *allowed,
+        Value::Object(object) => {
+            let constrained = ["properties", "additionalProperties", "items", "enum", "allOf", "anyOf", "oneOf", "$ref"];
+            let typed = object.get("type").is_some_and(|t| match t {
+                Value::String(t) => t != "object",
+                Value::Array(types) => !types.iter().any(|t| t == "object"),
+                _ => false,
+            });
+            !typed && !constrained.iter().any(|k| object.contains_key(*k))
+        }
+        _ => false,
+    }
+}
+
+/// 编辑距离足够小时给出最相近的键名
+fn suggest<'b>(key: &str, candidates: impl Iterator<Item = &'b str>) -> Option<String> {
+    let normalized = key.to_lowercase().replace('-', "_");
+    candidates
+        .map(|c| (edit_distance(&normalized, c), c))
+        .filter(|(distance, c)| *distance <= (c.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb { previous } else { previous.min(row[j]).min(current) + 1 };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str) -> Vec<String> {
+        unknown_keys(&toml::from_str(text).unwrap()).iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let found = check(
+            r#"
+            strict = false
+            [agent]
+            default_model = "x"
+            temprature = 0.2
+            temperature = 0.3
+            [llm.vllm]
+            base_url = "http://localhost:8000/v1"
+            [channel.telegram]
+            alowed_users = ["1"]
+            [personas.coder]
+            system_prompt = "x"
+            [[content_filter.rules]]
+            pattern = "x"
+            actoin = "block"
+            [tools.injection_guard.tools]
+            fetch_page = "strip"
+            [no_such_section]
+            x = 1
+            "#,
+        );
+        assert_eq!(
+            found,
+            vec![
+                "未知的配置项 agent.temprature（是否应为 temperature？）",
+                "未知的配置项 channel.telegram.alowed_users（是否应为 allowed_users？）",
+                "未知的配置项 content_filter.rules[0].actoin（是否应为 action？）",
+                "未知的配置项 no_such_section",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_example_config_has_no_unknown_keys() {
+        assert_eq!(check(include_str!("../../config.example.toml")), Vec::<String>::new());
+        let example = toml::to_string(&Config::example()).unwrap();
+        assert_eq!(check(&example), Vec::<String>::new());
+    }
+}
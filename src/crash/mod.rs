@@ -0,0 +1,245 @@
+//! panic 处理
+//!
+//! tokio 任务中的 panic 默认只会让该任务悄悄结束。[`install_hook`] 安装全局 panic hook：
+//! 记录 panic 信息和调用栈（带上所在组件），并在设置了事件总线时发布 `system.panic` 事件，
+//! 由 [`PanicNoticeHandler`] 通知管理员聊天。
+//!
+//! - [`catch`]：把 future 中的 panic 转为错误返回（消息处理、定时任务执行）
+//! - [`spawn`]：在标记了组件名的任务中运行
+//! - [`spawn_supervised`]：后台循环类组件 panic 后按 `[crash]` 配置重启
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::bus::{EventBus, EventHandler, SystemEvent};
+use crate::channel::{Channel, ChannelTarget};
+use crate::config::CrashConfig;
+
+/// 同一组件的相同错误在该时间内只通知一次
+const NOTICE_INTERVAL: Duration = Duration::from_secs(600);
+
+tokio::task_local! {
+    /// 当前任务所属的组件
+    static COMPONENT: String;
+}
+
+static SETTINGS: OnceLock<CrashConfig> = OnceLock::new();
+static EVENT_BUS: OnceLock<Arc<EventBus>> = OnceLock::new();
+
+fn settings() -> CrashConfig {
+    SETTINGS.get().cloned().unwrap_or_default()
+}
+
+/// 安装全局 panic hook（只有第一次调用生效）
+pub fn install_hook(config: &CrashConfig) {
+    if SETTINGS.set(config.clone()).is_err() {
+        return;
+    }
+    std::panic::set_hook(Box::new(|info| {
+        let component = COMPONENT.try_with(|c| c.clone()).unwrap_or_else(|_| "main".to_string());
+        let message = panic_message(info.payload());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        if settings().backtrace {
+            let backtrace = std::backtrace::Backtrace::force_capture();
+            error!("组件 {} panic: {}（{}）\n{}", component, message, location, backtrace);
+        } else {
+            error!("组件 {} panic: {}（{}）", component, message, location);
+        }
+
+        if let Some(bus) = EVENT_BUS.get() {
+            let _ = bus.publish(SystemEvent {
+                event_type: "panic".to_string(),
+                data: serde_json::json!({
+                    "component": component,
+                    "message": message,
+                    "location": location,
+                }),
+                timestamp: chrono::Utc::now(),
+            });
+        }
+    }));
+}
+
+/// 设置发布 `system.panic` 事件的事件总线
+pub fn set_event_bus(bus: Arc<EventBus>) {
+    let _ = EVENT_BUS.set(bus);
+}
+
+/// 提取 panic 信息
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "未知错误".to_string())
+}
+
+/// 以组件名运行 future，其中的 panic 转为错误返回
+pub async fn catch<F: Future>(component: impl Into<String>, fut: F) -> Result<F::Output> {
+    let component = component.into();
+    COMPONENT
+        .scope(component.clone(), AssertUnwindSafe(fut).catch_unwind())
+        .await
+        .map_err(|payload| anyhow!("{} panic: {}", component, panic_message(payload.as_ref())))
+}
+
+/// 在标记了组件名的任务中运行（panic 由 hook 记录）
+pub fn spawn<F>(component: impl Into<String>, fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(COMPONENT.scope(component.into(), fut))
+}
+
+/// 运行后台组件，panic 后按 `[crash]` 配置重启（正常结束时不再重启）
+pub fn spawn_supervised<F, Fut>(component: impl Into<String>, factory: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let config = settings();
+    let restart = config.restart.then(|| Duration::from_secs(config.restart_delay_secs));
+    tokio::spawn(supervise(component.into(), restart, factory))
+}
+
+async fn supervise<F, Fut>(component: String, restart: Option<Duration>, mut factory: F)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    loop {
+        match spawn(component.clone(), factory()).await {
+            Ok(()) => return,
+            Err(e) if e.is_panic() => match restart {
+                Some(delay) => {
+                    warn!("{} 秒后重启组件 {}", delay.as_secs(), component);
+                    tokio::time::sleep(delay).await;
+                    info!("重启组件 {}", component);
+                }
+                None => {
+                    error!("组件 {} 因 panic 停止", component);
+                    return;
+                }
+            },
+            Err(_) => return,
+        }
+    }
+}
+
+/// 把 panic 通知推送到配置的管理员聊天
+pub struct PanicNoticeHandler {
+    channels: Vec<Arc<dyn Channel>>,
+    channel: String,
+    target: ChannelTarget,
+    /// 各组件最近一次通知的错误和时间
+    last_notice: std::sync::Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl PanicNoticeHandler {
+    /// 未配置通知目标时返回 None
+    pub fn new(channels: Vec<Arc<dyn Channel>>, config: &CrashConfig) -> Option<Self> {
+        Some(Self {
+            channels,
+            channel: config.notify_channel.clone()?,
+            target: ChannelTarget::parse(config.notify_chat_id.as_deref()?)
+                .map_err(|e| warn!("panic 通知目标无效: {}", e))
+                .ok()?,
+            last_notice: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 同一组件的相同错误在间隔内只通知一次
+    fn should_notify(&self, component: &str, message: &str) -> bool {
+        let mut last = self.last_notice.lock().unwrap();
+        let now = Instant::now();
+        if let Some((previous, at)) = last.get(component) {
+            if previous == message && now.duration_since(*at) < NOTICE_INTERVAL {
+                return false;
+            }
+        }
+        last.insert(component.to_string(), (message.to_string(), now));
+        true
+    }
+}
+
+#[async_trait]
+impl EventHandler<SystemEvent> for PanicNoticeHandler {
+    async fn handle(&self, event: &SystemEvent) {
+        if event.event_type != "panic" {
+            return;
+        }
+        let component = event.data["component"].as_str().unwrap_or("unknown");
+        let message = event.data["message"].as_str().unwrap_or_default();
+        if !self.should_notify(component, message) {
+            return;
+        }
+        let Some(channel) = self.channels.iter().find(|c| c.name() == self.channel) else {
+            warn!("panic 通知通道 {} 未启动", self.channel);
+            return;
+        };
+        let location = event.data["location"].as_str().unwrap_or_default();
+        let notice = format!("💥 组件 {} panic: {}\n{}", component, message, location);
+        if let Err(e) = channel.send_message(&self.target, &notice).await {
+            warn!("发送 panic 通知失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        assert_eq!(catch("test", async { 42 }).await.unwrap(), 42);
+        let err = catch("job:备份", async {
+            assert_eq!(COMPONENT.with(|c| c.clone()), "job:备份");
+            panic!("磁盘已满");
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "job:备份 panic: 磁盘已满");
+    }
+
+    #[tokio::test]
+    async fn test_supervise_restarts_after_panic() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        supervise("worker".to_string(), Some(Duration::from_millis(10)), move || {
+            let runs = counter.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("boom");
+                }
+            }
+        })
+        .await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // 不重启时 panic 后停止
+        runs.store(0, Ordering::SeqCst);
+        let counter = runs.clone();
+        supervise("worker".to_string(), None, move || {
+            let runs = counter.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                panic!("boom");
+            }
+        })
+        .await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,169 @@
+//! 任务输出投递
+//!
+//! 处理器只负责产生输出，输出发到哪里由任务自身的 [`Delivery`] 决定：
+//! 发送到某个通道的聊天、只写日志，或追加到当天的日常笔记（`memory/YYYY-MM-DD.md`）。
+//! 未设置投递方式的任务只记录日志
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use super::Job;
+use crate::channel::outbox::Outbox;
+use crate::channel::quiet::{send_proactive, QuietHours};
+use crate::channel::template::{NotificationKind, NotificationTemplates};
+use crate::channel::{Channel, ChannelTarget};
+use crate::memory::MemoryStore;
+
+/// 发送到通道时的默认格式
+const DEFAULT_CHANNEL_FORMAT: &str = "📋 {job}\n{output}";
+/// 写入日常笔记时的默认格式
+const DEFAULT_FILE_FORMAT: &str = "## {time} {job}\n{output}";
+
+/// 任务输出的投递方式
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "sink", rename_all = "snake_case")]
+pub enum Delivery {
+    /// 发送到通道（免打扰时段内排队）
+    Channel {
+        channel: String,
+        target: String,
+        /// 消息格式，可用 {job}、{output}、{time}
+        #[serde(default)]
+        format: Option<String>,
+    },
+    /// 只写日志
+    Silent,
+    /// 追加到当天的日常笔记
+    File {
+        #[serde(default)]
+        format: Option<String>,
+    },
+}
+
+impl Delivery {
+    /// 按格式模板生成投递内容
+    fn render(&self, job: &Job, output: &str) -> String {
+        let format = match self {
+            Delivery::Channel { format, .. } => format.as_deref().unwrap_or(DEFAULT_CHANNEL_FORMAT),
+            Delivery::File { format } => format.as_deref().unwrap_or(DEFAULT_FILE_FORMAT),
+            Delivery::Silent => "{output}",
+        };
+        format
+            .replace("{job}", &job.name)
+            .replace("{time}", &Local::now().format("%H:%M").to_string())
+            .replace("{output}", output.trim())
+    }
+}
+
+/// 把任务输出投递到任务配置的位置
+pub struct DeliveryRouter {
+    channels: Vec<Arc<dyn Channel>>,
+    memory: Option<Arc<MemoryStore>>,
+    quiet_hours: QuietHours,
+    outbox: Option<Arc<Outbox>>,
+    templates: NotificationTemplates,
+}
+
+impl DeliveryRouter {
+    pub fn new(channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self {
+            channels,
+            memory: None,
+            quiet_hours: QuietHours::default(),
+            outbox: None,
+            templates: NotificationTemplates::default(),
+        }
+    }
+
+    /// 设置通知模板（任务失败通知）
+    pub fn with_templates(mut self, templates: NotificationTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// 设置日常笔记所在的记忆存储
+    pub fn with_memory(mut self, memory: Option<Arc<MemoryStore>>) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// 设置发件箱（免打扰时段内的消息排队到其中）
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// 投递任务失败通知（按 job_failed 模板生成后同输出一样投递）
+    pub async fn deliver_failure(&self, job: &Job, error: &str) -> Result<()> {
+        let channel = match job.delivery {
+            Some(Delivery::Channel { ref channel, .. }) => channel.as_str(),
+            _ => "",
+        };
+        let notice = self.templates.render(
+            channel,
+            NotificationKind::JobFailed,
+            &[("job", job.name.clone()), ("error", error.to_string())],
+        );
+        self.deliver(job, &notice).await
+    }
+
+    /// 投递任务输出
+    pub async fn deliver(&self, job: &Job, output: &str) -> Result<()> {
+        let Some(ref delivery) = job.delivery else {
+            info!("任务 {} 输出:\n{}", job.name, output);
+            return Ok(());
+        };
+        let content = delivery.render(job, output);
+        match delivery {
+            Delivery::Silent => {
+                info!("任务 {} 输出:\n{}", job.name, content);
+                Ok(())
+            }
+            Delivery::Channel { channel, target, .. } => {
+                let channel = self
+                    .channels
+                    .iter()
+                    .find(|c| c.name() == channel)
+                    .ok_or_else(|| anyhow!("投递目标通道不存在: {}", channel))?;
+                let target = ChannelTarget::parse(target)?;
+                send_proactive(&self.quiet_hours, self.outbox.as_ref(), channel.clone(), &target, &content).await
+            }
+            Delivery::File { .. } => {
+                let memory = self.memory.as_ref().ok_or_else(|| anyhow!("未启用记忆存储，无法写入日常笔记"))?;
+                memory.append_today(format!("{}\n", content)).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_render() {
+        let job = Job::new_interval("磁盘检查", 3600, "shell_command");
+        let channel: Delivery = serde_json::from_value(serde_json::json!({
+            "sink": "channel",
+            "channel": "telegram",
+            "target": "42",
+        }))
+        .unwrap();
+        assert_eq!(channel.render(&job, "  剩余 20G\n"), "📋 磁盘检查\n剩余 20G");
+
+        let file = Delivery::File { format: Some("- {job}: {output}".to_string()) };
+        assert_eq!(file.render(&job, "正常"), "- 磁盘检查: 正常");
+
+        let silent: Delivery = serde_json::from_str(r#"{"sink": "silent"}"#).unwrap();
+        assert_eq!(silent, Delivery::Silent);
+    }
+}
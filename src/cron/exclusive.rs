@@ -0,0 +1,125 @@
+//! 多进程部署中的任务去重
+//!
+//! 多个 gateway 共用同一个任务数据库时，每个进程都会按计划触发同一个任务。
+//! [`ExclusiveHandler`] 在执行前用共享状态为本次执行抢占标记，只有抢到的进程执行，其余进程跳过。
+//! 持久化任务以任务 ID + 执行序号标识一次执行；各进程启动时创建的非持久化任务（如每日简报）ID 不同，
+//! 以任务名称 + 触发时间（精确到分钟）标识。Webhook 任务由各进程收到的请求触发，不做去重
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+use super::{Job, JobHandler, JobType};
+use crate::state::StateStore;
+
+/// 执行标记的保留时间（应长于各进程触发同一次执行的时间差）
+const RUN_MARK_TTL: Duration = Duration::from_secs(3600);
+
+/// 同一次执行只在一个进程中运行的处理器包装
+pub struct ExclusiveHandler {
+    inner: Arc<dyn JobHandler>,
+    state: Arc<StateStore>,
+}
+
+impl ExclusiveHandler {
+    /// 有共享状态时包装处理器，否则原样返回
+    pub fn wrap(inner: Arc<dyn JobHandler>, state: Option<Arc<StateStore>>) -> Arc<dyn JobHandler> {
+        match state {
+            Some(state) => Arc::new(Self { inner, state }),
+            None => inner,
+        }
+    }
+}
+
+#[async_trait]
+impl JobHandler for ExclusiveHandler {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn execute(&self, job: &Job, args: Option<serde_json::Value>) -> Result<Option<String>> {
+        if let Some(key) = run_key(job) {
+            if !self.state.set_nx(&key, RUN_MARK_TTL).await? {
+                debug!("任务 {} 的本次执行已由其他进程处理（{}）", job.name, key);
+                return Ok(None);
+            }
+        }
+        self.inner.execute(job, args).await
+    }
+}
+
+/// 标识一次执行的键，Webhook 任务返回 None
+fn run_key(job: &Job) -> Option<String> {
+    if matches!(job.job_type, JobType::Webhook { .. }) {
+        return None;
+    }
+    if job.persistent {
+        // 执行序号在调用处理器前已递增，各进程对同一次执行得到相同的序号
+        Some(format!("cron:{}:{}", job.id, job.run_count))
+    } else {
+        let at = job.last_run.unwrap_or_else(Utc::now);
+        Some(format!("cron:{}:{}:{}", job.handler, job.name, at.format("%Y%m%d%H%M")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingHandler(AtomicUsize);
+
+    #[async_trait]
+    impl JobHandler for CountingHandler {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        async fn execute(&self, _job: &Job, _args: Option<serde_json::Value>) -> Result<Option<String>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exclusive_runs_once_per_run() {
+        let counter = Arc::new(CountingHandler(AtomicUsize::new(0)));
+        let state = StateStore::memory();
+        // 两个进程共享同一个状态
+        let first = ExclusiveHandler::wrap(counter.clone(), Some(state.clone()));
+        let second = ExclusiveHandler::wrap(counter.clone(), Some(state));
+
+        let mut job = Job::new_interval("心跳", 60, "count");
+        job.run_count = 1;
+        first.execute(&job, None).await.unwrap();
+        second.execute(&job, None).await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        job.run_count = 2;
+        second.execute(&job, None).await.unwrap();
+        first.execute(&job, None).await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+
+        // Webhook 任务每次请求都执行
+        let hook = Job::new_webhook("部署", "count");
+        first.execute(&hook, None).await.unwrap();
+        second.execute(&hook, None).await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 4);
+
+        // 各进程各自创建的非持久化任务按名称和触发时间去重
+        let now = Utc::now();
+        let mut briefing = [
+            Job::new_cron("简报", "0 0 8 * * *", "count").non_persistent(),
+            Job::new_cron("简报", "0 0 8 * * *", "count").non_persistent(),
+        ];
+        for job in &mut briefing {
+            job.last_run = Some(now);
+        }
+        first.execute(&briefing[0], None).await.unwrap();
+        second.execute(&briefing[1], None).await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 5);
+    }
+}
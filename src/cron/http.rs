@@ -0,0 +1,233 @@
+//! http_call 任务处理器
+//!
+//! 按计划请求外部服务（心跳、Webhook 回调等），不需要编写代码。请求经过出站网址策略
+//! （`[tools.url_policy]`）检查，响应状态和正文摘要按任务的投递方式发送。处理器参数：
+//!
+//! ```json
+//! {"method": "POST", "url": "https://example.com/hook", "headers": {"Authorization": "Bearer ..."},
+//!  "body": {"text": "{job} 于 {date} 执行"}, "vars": {"env": "prod"}, "timeout": 30}
+//! ```
+//!
+//! url、headers 和 body 中的 `{name}` 按以下变量替换：`{job}`、`{date}`、`{time}`、`{datetime}`、
+//! `{timestamp}`、`vars` 中的变量，以及 Webhook 触发时请求体中的顶层字段。替换进 url 的值按 URL 组件编码，
+//! 出站网址策略检查替换后的地址
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::{Local, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Method, Url};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+
+use super::{Job, JobHandler};
+use crate::config::ToolsConfig;
+use crate::tools::url_policy::UrlPolicy;
+
+/// 处理器名称
+pub const HTTP_CALL_HANDLER: &str = "http_call";
+/// 默认超时（秒）
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+/// 投递的响应正文最多保留的字符数
+const MAX_BODY_CHARS: usize = 1000;
+/// 替换进 URL 的值中需要编码的字符（RFC 3986 非保留字符以外的全部字符）
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// 请求外部服务的任务处理器
+pub struct HttpCallHandler {
+    config: ToolsConfig,
+}
+
+impl HttpCallHandler {
+    pub fn new(config: ToolsConfig) -> Self {
+        Self { config }
+    }
+}
+
+/// 模板变量
+fn template_vars(job: &Job, spec: &Value, args: Option<&Value>) -> HashMap<String, String> {
+    let now = Local::now();
+    let mut vars = HashMap::from([
+        ("job".to_string(), job.name.clone()),
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        ("datetime".to_string(), now.to_rfc3339()),
+        ("timestamp".to_string(), Utc::now().timestamp().to_string()),
+    ]);
+    let objects = [spec.get("vars"), args.filter(|a| *a != spec)];
+    for object in objects.into_iter().flatten().filter_map(Value::as_object) {
+        for (name, value) in object {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Object(_) | Value::Array(_) => continue,
+                other => other.to_string(),
+            };
+            vars.insert(name.clone(), value);
+        }
+    }
+    vars
+}
+
+/// 替换文本中的 `{name}`，未定义的变量保持原样
+fn render(template: &str, vars: &HashMap<String, String>) -> String {
+    render_with(template, vars, str::to_string)
+}
+
+/// 替换 URL 中的 `{name}`，值按 URL 组件编码，不会改变地址的主机、路径或查询参数的结构
+fn render_url(template: &str, vars: &HashMap<String, String>) -> String {
+    render_with(template, vars, |value| utf8_percent_encode(value, URL_COMPONENT).to_string())
+}
+
+/// 从左到右扫描一次模板，替换进来的值不再展开
+fn render_with(template: &str, vars: &HashMap<String, String>, encode: impl Fn(&str) -> String) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let found = after
+            .find('}')
+            .and_then(|end| vars.get(&after[..end]).map(|value| (end, value)));
+        match found {
+            Some((end, value)) => {
+                text.push_str(&encode(value));
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// 递归替换 JSON 中的字符串
+fn render_value(value: &Value, vars: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(s) => Value::String(render(s, vars)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_value(v, vars)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), render_value(v, vars))).collect()),
+        other => other.clone(),
+    }
+}
+
+#[async_trait]
+impl JobHandler for HttpCallHandler {
+    fn name(&self) -> &str {
+        HTTP_CALL_HANDLER
+    }
+
+    async fn execute(&self, job: &Job, args: Option<Value>) -> Result<Option<String>> {
+        // 请求定义来自任务自身的参数，Webhook 触发时请求体只作为模板变量
+        let spec = job.handler_args.clone().or_else(|| args.clone()).unwrap_or(Value::Null);
+        let vars = template_vars(job, &spec, args.as_ref());
+
+        let url = spec
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("任务 {} 缺少 url 参数", job.name))?;
+        let url = Url::parse(&render_url(url, &vars)).map_err(|e| anyhow!("URL 无效: {}", e))?;
+        let method = spec.get("method").and_then(|v| v.as_str()).unwrap_or("GET").to_uppercase();
+        let method = Method::from_bytes(method.as_bytes()).map_err(|_| anyhow!("请求方法无效: {}", method))?;
+
+        let policy = UrlPolicy::new(&self.config.url_policy);
+        policy.check(&url).await?;
+        if self.config.dry_run {
+            info!("演练模式：跳过任务 {} 的请求 {} {}", job.name, method, url);
+            return Ok(Some(format!("演练模式：未发送请求 {} {}", method, url)));
+        }
+
+        let timeout = spec.get("timeout").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let timeout = Duration::from_secs(timeout);
+        let response = if method == Method::GET && spec.get("headers").is_none() {
+            policy.get(url.as_str(), timeout).await?
+        } else {
+            // 非 GET 请求不跟随重定向，只连接检查过的地址
+            let client = policy.client(&url, timeout).await?;
+            let mut request = client.request(method.clone(), url.clone());
+            if let Some(headers) = spec.get("headers").and_then(|v| v.as_object()) {
+                for (name, value) in headers {
+                    let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                    request = request.header(name.as_str(), render(&value, &vars));
+                }
+            }
+            request = match spec.get("body") {
+                None | Some(Value::Null) => request,
+                Some(Value::String(body)) => request.body(render(body, &vars)),
+                Some(body) => request.json(&render_value(body, &vars)),
+            };
+            request.send().await?
+        };
+
+        let status = response.status();
+        let body = String::from_utf8_lossy(&policy.read_body(response).await?).trim().to_string();
+        let mut excerpt: String = body.chars().take(MAX_BODY_CHARS).collect();
+        if body.chars().count() > MAX_BODY_CHARS {
+            excerpt.push('…');
+        }
+        if !status.is_success() {
+            bail!("{} {} 返回 {}\n{}", method, url, status, excerpt);
+        }
+        Ok(Some(format!("{} {} → {}\n{}", method, url, status, excerpt).trim_end().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_templates() {
+        let job = Job::new_cron("心跳", "0 * * * * *", HTTP_CALL_HANDLER);
+        let spec = json!({"url": "https://example.com", "vars": {"env": "prod", "port": 8080}});
+        let payload = json!({"ref": "main"});
+        let vars = template_vars(&job, &spec, Some(&payload));
+
+        let url = render("https://example.com/{env}/{date}?ref={ref}&x={unknown}", &vars);
+        assert_eq!(
+            url,
+            format!("https://example.com/prod/{}?ref=main&x={{unknown}}", Local::now().format("%Y-%m-%d"))
+        );
+        let body = render_value(&json!({"text": "{job} 在 {env}:{port}", "n": 1}), &vars);
+        assert_eq!(body, json!({"text": "心跳 在 prod:8080", "n": 1}));
+
+        // 替换进来的值不再展开，结果与变量顺序无关
+        let vars = HashMap::from([
+            ("a".to_string(), "{b}".to_string()),
+            ("b".to_string(), "{a}".to_string()),
+        ]);
+        assert_eq!(render("{a}-{b}-{{a}}-{c}", &vars), "{b}-{a}-{{b}}-{c}");
+
+        // URL 中的值按组件编码
+        let vars = HashMap::from([("q".to_string(), "a b&c=/d?#中".to_string())]);
+        assert_eq!(
+            render_url("https://example.com/search?q={q}", &vars),
+            "https://example.com/search?q=a%20b%26c%3D%2Fd%3F%23%E4%B8%AD"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_call_policy() {
+        let handler = HttpCallHandler::new(ToolsConfig::default());
+        let job = Job::new_interval("内网", 60, HTTP_CALL_HANDLER).with_args(json!({"url": "http://127.0.0.1:9/ping"}));
+        let err = handler.execute(&job, None).await.unwrap_err();
+        assert!(err.to_string().contains("内网"));
+
+        // 检查的是替换后的地址：Webhook 请求体中的变量不能把请求引向内网
+        let templated = Job::new_webhook("回调", HTTP_CALL_HANDLER)
+            .with_args(json!({"url": "http://{host}/ping", "vars": {"host": "example.com"}}));
+        let err = handler
+            .execute(&templated, Some(json!({"host": "127.0.0.1"})))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("内网"));
+
+        let missing = Job::new_interval("缺少地址", 60, HTTP_CALL_HANDLER);
+        assert!(handler.execute(&missing, None).await.is_err());
+    }
+}
@@ -2,18 +2,30 @@
 //! 
 //! 使用 tokio-cron-scheduler 实现定时任务调度
 //! 支持 cron 表达式和时间间隔
-//! 任务持久化到 SQLite
+//! 任务持久化到 SQLite，数据库不可用时以内存模式运行并在后台重试连接
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
-use std::sync::Arc;
+use sqlx::{Pool, Sqlite};
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
 use tokio::sync::RwLock;
 use tokio_cron_scheduler::{Job as CronJob, JobScheduler};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::bus::{EventBus, JobEvent};
+use crate::crash;
+
+pub mod delivery;
+pub mod exclusive;
+pub mod http;
+pub mod reminder;
+pub mod shell;
+
+use delivery::{Delivery, DeliveryRouter};
+
 /// 任务类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -24,6 +36,8 @@ pub enum JobType {
     Interval { seconds: u64 },
     /// 一次性任务
     Once { run_at: DateTime<Utc> },
+    /// Webhook 触发任务（通过 API 服务的 /hooks/<job_id> 触发）
+    Webhook { secret: String },
 }
 
 /// 任务状态
@@ -42,6 +56,64 @@ pub enum JobStatus {
     Failed,
 }
 
+/// 错过执行（misfire）策略
+///
+/// 进程停机期间错过的执行窗口在重启加载持久化任务时按此策略处理
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// 跳过所有错过的执行
+    #[default]
+    Skip,
+    /// 立即补执行一次
+    RunOnce,
+    /// 补执行所有错过的次数
+    RunAll,
+}
+
+impl MisfirePolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MisfirePolicy::Skip => "skip",
+            MisfirePolicy::RunOnce => "run_once",
+            MisfirePolicy::RunAll => "run_all",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "run_once" => MisfirePolicy::RunOnce,
+            "run_all" => MisfirePolicy::RunAll,
+            _ => MisfirePolicy::Skip,
+        }
+    }
+}
+
+impl FromStr for MisfirePolicy {
+    type Err = String;
+
+    /// 严格解析（用于命令行等用户输入），未知取值报错
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(MisfirePolicy::Skip),
+            "run_once" => Ok(MisfirePolicy::RunOnce),
+            "run_all" => Ok(MisfirePolicy::RunAll),
+            _ => Err(format!("未知的错过执行策略: {}（可选 skip、run_once、run_all）", s)),
+        }
+    }
+}
+
+/// 间隔任务的最大间隔（约 100 年）
+pub const MAX_INTERVAL_SECS: u64 = 100 * 365 * 86400;
+/// 单个任务最多补执行的次数，避免长时间停机后集中触发
+const MAX_CATCH_UP_RUNS: u32 = 100;
+/// 一次性任务的执行时间最多可以早于创建时间多久（立即执行并记录警告），更早的拒绝创建
+const PAST_DUE_GRACE_SECS: i64 = 60;
+/// 清理已完成一次性任务的间隔
+const CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+/// 数据库不可用时重试连接的间隔
+const DB_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
 /// 任务定义
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
@@ -71,6 +143,18 @@ pub struct Job {
     pub max_runs: Option<i64>,
     /// 是否持久化
     pub persistent: bool,
+    /// 错过执行策略
+    #[serde(default)]
+    pub misfire_policy: MisfirePolicy,
+    /// 输出投递方式（未设置时只写日志）
+    #[serde(default)]
+    pub delivery: Option<Delivery>,
+    /// 创建者（通道:聊天 ID），Agent 通过工具为用户创建的任务才有
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Cron 表达式所在的 IANA 时区（如 Asia/Shanghai），未设置时按 UTC 计算
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 impl Job {
@@ -96,6 +180,10 @@ impl Job {
             run_count: 0,
             max_runs: None,
             persistent: true,
+            misfire_policy: MisfirePolicy::Skip,
+            delivery: None,
+            owner: None,
+            timezone: None,
         }
     }
 
@@ -119,6 +207,10 @@ impl Job {
             run_count: 0,
             max_runs: None,
             persistent: true,
+            misfire_policy: MisfirePolicy::Skip,
+            delivery: None,
+            owner: None,
+            timezone: None,
         }
     }
 
@@ -142,6 +234,38 @@ impl Job {
             run_count: 0,
             max_runs: Some(1),
             persistent: true,
+            // 提醒类任务即使错过也应尽快送达
+            misfire_policy: MisfirePolicy::RunOnce,
+            delivery: None,
+            owner: None,
+            timezone: None,
+        }
+    }
+
+    /// 创建新的 Webhook 触发任务
+    ///
+    /// 调用方需携带自动生成的 secret 才能触发，POST 的 JSON 作为处理器参数
+    pub fn new_webhook(name: impl Into<String>, handler: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            description: None,
+            job_type: JobType::Webhook {
+                secret: Uuid::new_v4().simple().to_string(),
+            },
+            status: JobStatus::Pending,
+            handler: handler.into(),
+            handler_args: None,
+            created_at: Utc::now(),
+            last_run: None,
+            next_run: None,
+            run_count: 0,
+            max_runs: None,
+            persistent: true,
+            misfire_policy: MisfirePolicy::Skip,
+            delivery: None,
+            owner: None,
+            timezone: None,
         }
     }
 
@@ -168,6 +292,135 @@ impl Job {
         self.persistent = false;
         self
     }
+
+    /// 设置错过执行策略
+    pub fn with_misfire_policy(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire_policy = policy;
+        self
+    }
+
+    /// 设置输出投递方式
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = Some(delivery);
+        self
+    }
+
+    /// 设置创建者
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// 设置 Cron 表达式所在的时区（按该时区计算执行时间，含夏令时）
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Cron 表达式所在的时区，未设置或无法识别时为 None（按 UTC 计算）
+    fn cron_timezone(&self) -> Option<chrono_tz::Tz> {
+        let name = self.timezone.as_deref()?;
+        match name.parse() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                warn!("任务 {} 的时区 {} 无法识别，按 UTC 计算", self.name, name);
+                None
+            }
+        }
+    }
+
+    /// 检查一次性任务的执行时间：稍早于 `now` 时记录警告（创建后立即执行），
+    /// 早于宽限时间时返回错误
+    pub fn check_run_at(&self, now: DateTime<Utc>) -> Result<()> {
+        let JobType::Once { run_at } = &self.job_type else {
+            return Ok(());
+        };
+        if *run_at >= now {
+            return Ok(());
+        }
+        if now.signed_duration_since(*run_at).num_seconds() > PAST_DUE_GRACE_SECS {
+            anyhow::bail!("执行时间 {} 已经过去", run_at.to_rfc3339());
+        }
+        warn!("任务 {} 的执行时间 {} 已过，将立即执行", self.name, run_at.to_rfc3339());
+        Ok(())
+    }
+
+    /// 计算 `now` 之后的下次执行时间
+    ///
+    /// 已达到最大执行次数、已执行过的单次任务和 Webhook 任务返回 None，Cron 表达式无法解析时也返回 None
+    pub fn next_run_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.max_runs.is_some_and(|max| self.run_count >= max) {
+            return None;
+        }
+        match &self.job_type {
+            JobType::Once { run_at } => (self.run_count == 0).then_some(*run_at),
+            JobType::Cron { expression } => {
+                let schedule = cron::Schedule::from_str(expression).ok()?;
+                match self.cron_timezone() {
+                    Some(tz) => schedule.after(&now.with_timezone(&tz)).next().map(|t| t.with_timezone(&Utc)),
+                    None => schedule.after(&now).next(),
+                }
+            }
+            JobType::Interval { seconds } => {
+                if *seconds == 0 {
+                    return None;
+                }
+                let since = self.last_run.unwrap_or(self.created_at);
+                let elapsed = now.signed_duration_since(since).num_seconds().max(0) as u64;
+                // 间隔过大时视为不再执行，避免溢出
+                (elapsed / seconds + 1)
+                    .checked_mul(*seconds)
+                    .and_then(|offset| i64::try_from(offset).ok())
+                    .and_then(chrono::Duration::try_seconds)
+                    .and_then(|offset| since.checked_add_signed(offset))
+            }
+            JobType::Webhook { .. } => None,
+        }
+    }
+
+    /// 计算从上次执行（或创建时间）到 `now` 之间错过的执行次数
+    ///
+    /// 结果上限为 `limit`，Cron 表达式无法解析时返回 0
+    pub fn missed_runs(&self, now: DateTime<Utc>, limit: u32) -> u32 {
+        // 单次任务只看是否已执行过，与创建时间无关
+        if let JobType::Once { run_at } = &self.job_type {
+            return u32::from(self.run_count == 0 && *run_at <= now);
+        }
+
+        let since = self.last_run.unwrap_or(self.created_at);
+        if since >= now {
+            return 0;
+        }
+
+        match &self.job_type {
+            JobType::Cron { expression } => match cron::Schedule::from_str(expression) {
+                Ok(schedule) => match self.cron_timezone() {
+                    Some(tz) => schedule
+                        .after(&since.with_timezone(&tz))
+                        .take_while(|t| t.with_timezone(&Utc) <= now)
+                        .take(limit as usize)
+                        .count() as u32,
+                    None => schedule
+                        .after(&since)
+                        .take_while(|t| *t <= now)
+                        .take(limit as usize)
+                        .count() as u32,
+                },
+                Err(e) => {
+                    warn!("解析 Cron 表达式失败 {}: {}", expression, e);
+                    0
+                }
+            },
+            JobType::Interval { seconds } => {
+                if *seconds == 0 {
+                    return 0;
+                }
+                let elapsed = now.signed_duration_since(since).num_seconds().max(0) as u64;
+                (elapsed / seconds).min(limit as u64) as u32
+            }
+            JobType::Once { .. } | JobType::Webhook { .. } => 0,
+        }
+    }
 }
 
 /// 任务处理器 trait
@@ -176,134 +429,295 @@ pub trait JobHandler: Send + Sync {
     /// 处理器名称
     fn name(&self) -> &str;
     
-    /// 执行任务
-    async fn execute(&self, job: &Job, args: Option<serde_json::Value>) -> Result<()>;
+    /// 执行任务，返回需要投递的输出（None 表示没有输出，或处理器已自行发送）
+    async fn execute(&self, job: &Job, args: Option<serde_json::Value>) -> Result<Option<String>>;
 }
 
 /// 任务处理器注册表
 type HandlerRegistry = Arc<RwLock<std::collections::HashMap<String, Arc<dyn JobHandler>>>>;
 
+/// 任务输出投递（通道启动后设置）
+type RouterSlot = Arc<RwLock<Option<Arc<DeliveryRouter>>>>;
+
+/// 任务执行事件发布（gateway 启动时设置）
+type EventSlot = Arc<RwLock<Option<Arc<EventBus>>>>;
+
+/// 数据库连接池（内存模式或数据库不可用时为空）
+type PoolSlot = Arc<RwLock<Option<Pool<Sqlite>>>>;
+
 /// 任务调度器
 pub struct Scheduler {
     /// 内部调度器
     scheduler: Arc<RwLock<JobScheduler>>,
     /// 数据库连接池
-    pool: Option<Pool<Sqlite>>,
+    pool: PoolSlot,
+    /// 数据库路径（内存模式时为空）
+    db_path: Option<String>,
     /// 处理器注册表
     handlers: HandlerRegistry,
     /// 已注册任务
     jobs: Arc<RwLock<std::collections::HashMap<String, Job>>>,
     /// 运行状态
     running: Arc<RwLock<bool>>,
+    /// 启动时需要补执行的任务（任务 ID -> 次数）
+    catch_up: Arc<RwLock<std::collections::HashMap<String, u32>>>,
+    /// 已加入内部调度器的任务（任务 ID -> 调度 ID）
+    scheduled: Arc<RwLock<std::collections::HashMap<String, Uuid>>>,
+    /// 任务输出投递
+    router: RouterSlot,
+    /// 任务执行事件发布
+    events: EventSlot,
+    /// 自身引用（按时区计算的 Cron 任务执行后重新调度）
+    this: Weak<Self>,
 }
 
 impl Scheduler {
     /// 创建新的调度器（内存模式）
+    #[allow(dead_code)]
     pub async fn new() -> Result<Arc<Self>> {
+        Self::build(None, None).await
+    }
+
+    async fn build(pool: Option<Pool<Sqlite>>, db_path: Option<&str>) -> Result<Arc<Self>> {
         let scheduler = JobScheduler::new()
             .await
             .context("创建任务调度器失败")?;
 
-        Ok(Arc::new(Self {
+        Ok(Arc::new_cyclic(|this| Self {
             scheduler: Arc::new(RwLock::new(scheduler)),
-            pool: None,
+            pool: Arc::new(RwLock::new(pool)),
+            db_path: db_path.map(str::to_string),
             handlers: Arc::new(RwLock::new(std::collections::HashMap::new())),
             jobs: Arc::new(RwLock::new(std::collections::HashMap::new())),
             running: Arc::new(RwLock::new(false)),
+            catch_up: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            scheduled: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            router: Arc::new(RwLock::new(None)),
+            events: Arc::new(RwLock::new(None)),
+            this: this.clone(),
         }))
     }
 
     /// 创建带持久化的调度器
     pub async fn with_db(db_path: &str) -> Result<Arc<Self>> {
-        // 确保目录存在
-        if let Some(parent) = std::path::Path::new(db_path).parent() {
-            tokio::fs::create_dir_all(parent).await?;
+        let pool = Self::connect(db_path).await?;
+        let instance = Self::build(Some(pool), Some(db_path)).await?;
+
+        // 加载持久化任务
+        instance.load_persistent_jobs().await?;
+
+        Ok(instance)
+    }
+
+    /// 创建带持久化的调度器，数据库不可用（文件被锁定、目录只读等）时以内存模式运行，
+    /// 并在后台重试连接，连接成功后保存期间创建的任务并加载持久化任务
+    pub async fn with_db_or_memory(db_path: &str) -> Result<Arc<Self>> {
+        match Self::with_db(db_path).await {
+            Ok(instance) => Ok(instance),
+            Err(e) => {
+                warn!("任务数据库 {} 不可用: {:#}，以内存模式运行，每 {} 秒重试连接", db_path, e, DB_RETRY_INTERVAL.as_secs());
+                let instance = Self::build(None, Some(db_path)).await?;
+                instance.clone().start_reconnect();
+                Ok(instance)
+            }
         }
+    }
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(&format!("sqlite:{}", db_path))
-            .await
-            .context("连接数据库失败")?;
+    /// 是否处于降级模式（配置了数据库但当前不可用）
+    pub async fn is_degraded(&self) -> bool {
+        self.db_path.is_some() && self.pool.read().await.is_none()
+    }
 
-        let scheduler = JobScheduler::new()
-            .await
-            .context("创建任务调度器失败")?;
+    async fn pool(&self) -> Option<Pool<Sqlite>> {
+        self.pool.read().await.clone()
+    }
 
-        let instance = Arc::new(Self {
-            scheduler: Arc::new(RwLock::new(scheduler)),
-            pool: Some(pool),
-            handlers: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            jobs: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            running: Arc::new(RwLock::new(false)),
-        });
+    /// 连接数据库并初始化表
+    async fn connect(db_path: &str) -> Result<Pool<Sqlite>> {
+        let pool = crate::db::connect(db_path, 5)
+            .await
+            .context("连接数据库失败")?;
 
         // 初始化数据库表
-        instance.init_db().await?;
+        Self::init_db(&pool).await?;
+        Ok(pool)
+    }
 
-        // 加载持久化任务
-        instance.load_persistent_jobs().await?;
+    /// 后台重试连接数据库，成功后退出
+    fn start_reconnect(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let Some(db_path) = self.db_path.clone() else {
+                return;
+            };
+            loop {
+                tokio::time::sleep(DB_RETRY_INTERVAL).await;
+                match Self::connect(&db_path).await {
+                    Ok(pool) => {
+                        *self.pool.write().await = Some(pool);
+                        match self.restore_persistence().await {
+                            Ok(()) => {
+                                info!("任务数据库已恢复: {}", db_path);
+                                return;
+                            }
+                            Err(e) => {
+                                warn!("任务数据库恢复失败: {:#}", e);
+                                *self.pool.write().await = None;
+                            }
+                        }
+                    }
+                    Err(e) => warn!("任务数据库仍不可用: {:#}", e),
+                }
+            }
+        })
+    }
 
-        Ok(instance)
+    /// 数据库恢复后：保存降级期间的任务，加载并调度数据库中的其它任务
+    async fn restore_persistence(&self) -> Result<()> {
+        let jobs: Vec<Job> = self.jobs.read().await.values().cloned().collect();
+        for job in &jobs {
+            self.save_job(job).await?;
+        }
+        let loaded = self.load_persistent_jobs().await?;
+        if *self.running.read().await {
+            for job in loaded.iter().filter(|j| j.status == JobStatus::Pending) {
+                if let Err(e) = self.schedule_job(job).await {
+                    warn!("调度任务失败 {}: {}", job.id, e);
+                }
+            }
+            self.run_catch_up().await;
+        }
+        Ok(())
     }
 
     /// 初始化数据库表
-    async fn init_db(&self) -> Result<()> {
-        if let Some(ref pool) = self.pool {
-            sqlx::query(
-                r#"
-                CREATE TABLE IF NOT EXISTS cron_jobs (
-                    id TEXT PRIMARY KEY,
-                    name TEXT NOT NULL,
-                    description TEXT,
-                    job_type TEXT NOT NULL,
-                    job_type_data TEXT NOT NULL,
-                    status TEXT NOT NULL,
-                    handler TEXT NOT NULL,
-                    handler_args TEXT,
-                    created_at TIMESTAMP NOT NULL,
-                    last_run TIMESTAMP,
-                    next_run TIMESTAMP,
-                    run_count INTEGER DEFAULT 0,
-                    max_runs INTEGER,
-                    persistent BOOLEAN DEFAULT 1
-                )
-                "#
+    async fn init_db(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cron_jobs (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT,
+                job_type TEXT NOT NULL,
+                job_type_data TEXT NOT NULL,
+                status TEXT NOT NULL,
+                handler TEXT NOT NULL,
+                handler_args TEXT,
+                created_at TIMESTAMP NOT NULL,
+                last_run TIMESTAMP,
+                next_run TIMESTAMP,
+                run_count INTEGER DEFAULT 0,
+                max_runs INTEGER,
+                persistent BOOLEAN DEFAULT 1,
+                misfire_policy TEXT,
+                delivery TEXT,
+                owner TEXT,
+                timezone TEXT
             )
-            .execute(pool)
-            .await?;
+            "#
+        )
+        .execute(pool)
+        .await?;
 
-            sqlx::query(
-                "CREATE INDEX IF NOT EXISTS idx_jobs_status ON cron_jobs(status)"
-            )
+        // 兼容旧版本数据库：补充 misfire_policy 列（已存在时忽略错误）
+        let _ = sqlx::query("ALTER TABLE cron_jobs ADD COLUMN misfire_policy TEXT")
             .execute(pool)
-            .await?;
-        }
+            .await;
+        let _ = sqlx::query("ALTER TABLE cron_jobs ADD COLUMN delivery TEXT")
+            .execute(pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE cron_jobs ADD COLUMN owner TEXT")
+            .execute(pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE cron_jobs ADD COLUMN timezone TEXT")
+            .execute(pool)
+            .await;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_jobs_status ON cron_jobs(status)"
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_jobs_owner ON cron_jobs(owner)"
+        )
+        .execute(pool)
+        .await?;
         Ok(())
     }
 
-    /// 加载持久化任务
-    async fn load_persistent_jobs(&self) -> Result<()> {
-        if let Some(ref pool) = self.pool {
+    /// 加载持久化任务（跳过内存中已有的任务），返回新加载的任务
+    async fn load_persistent_jobs(&self) -> Result<Vec<Job>> {
+        let mut loaded = Vec::new();
+        if let Some(ref pool) = self.pool().await {
             let rows: Vec<JobRow> = sqlx::query_as(
                 "SELECT * FROM cron_jobs WHERE status != 'completed' AND persistent = 1"
             )
             .fetch_all(pool)
             .await?;
 
+            let now = Utc::now();
             for row in rows {
-                if let Ok(job) = row.to_job() {
+                if let Ok(mut job) = row.to_job() {
+                    if self.jobs.read().await.contains_key(&job.id) {
+                        continue;
+                    }
                     info!("加载持久化任务: {} ({})", job.name, job.id);
-                    self.jobs.write().await.insert(job.id.clone(), job);
+
+                    if job.status == JobStatus::Pending {
+                        self.apply_misfire_policy(&mut job, now).await?;
+                    }
+                    // 旧版本没有记录下次执行时间，停机期间记录的也已过期
+                    let next_run = job.next_run_after(now);
+                    if next_run != job.next_run {
+                        job.next_run = next_run;
+                        self.save_job(&job).await?;
+                    }
+
+                    self.jobs.write().await.insert(job.id.clone(), job.clone());
+                    loaded.push(job);
                 }
             }
         }
+        Ok(loaded)
+    }
+
+    /// 根据任务的 misfire 策略处理停机期间错过的执行
+    async fn apply_misfire_policy(&self, job: &mut Job, now: DateTime<Utc>) -> Result<()> {
+        let missed = job.missed_runs(now, MAX_CATCH_UP_RUNS);
+        if missed == 0 {
+            return Ok(());
+        }
+
+        info!(
+            "任务 {} ({}) 错过 {} 次执行，策略: {}",
+            job.name, job.id, missed, job.misfire_policy.as_str()
+        );
+
+        match (&job.job_type, job.misfire_policy) {
+            // 一次性任务在调度时会立即执行，跳过策略下直接标记完成
+            (JobType::Once { .. }, MisfirePolicy::Skip) => {
+                job.status = JobStatus::Completed;
+                self.save_job(job).await?;
+            }
+            (JobType::Once { .. }, _) => {}
+            (_, MisfirePolicy::Skip) => {}
+            (_, MisfirePolicy::RunOnce) => {
+                self.catch_up.write().await.insert(job.id.clone(), 1);
+            }
+            (_, MisfirePolicy::RunAll) => {
+                if missed >= MAX_CATCH_UP_RUNS {
+                    warn!("任务 {} 错过次数过多，仅补执行 {} 次", job.id, MAX_CATCH_UP_RUNS);
+                }
+                self.catch_up.write().await.insert(job.id.clone(), missed);
+            }
+        }
+
         Ok(())
     }
 
     /// 保存任务到数据库
     async fn save_job(&self, job: &Job) -> Result<()> {
-        if let Some(ref pool) = self.pool {
+        if let Some(ref pool) = self.pool().await {
             if !job.persistent {
                 return Ok(());
             }
@@ -314,8 +728,8 @@ impl Scheduler {
                 r#"
                 INSERT OR REPLACE INTO cron_jobs 
                 (id, name, description, job_type, job_type_data, status, handler, handler_args,
-                 created_at, last_run, next_run, run_count, max_runs, persistent)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                 created_at, last_run, next_run, run_count, max_runs, persistent, misfire_policy, delivery, owner, timezone)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
                 "#
             )
             .bind(&job.id)
@@ -325,6 +739,7 @@ impl Scheduler {
                 JobType::Cron { .. } => "cron",
                 JobType::Interval { .. } => "interval",
                 JobType::Once { .. } => "once",
+                JobType::Webhook { .. } => "webhook",
             })
             .bind(job_type_data)
             .bind(match job.status {
@@ -342,6 +757,10 @@ impl Scheduler {
             .bind(job.run_count)
             .bind(job.max_runs)
             .bind(job.persistent)
+            .bind(job.misfire_policy.as_str())
+            .bind(job.delivery.as_ref().map(serde_json::to_string).transpose()?)
+            .bind(&job.owner)
+            .bind(&job.timezone)
             .execute(pool)
             .await?;
         }
@@ -355,8 +774,25 @@ impl Scheduler {
         self.handlers.write().await.insert(name, handler);
     }
 
+    /// 设置任务输出投递
+    pub async fn set_delivery_router(&self, router: Arc<DeliveryRouter>) {
+        *self.router.write().await = Some(router);
+    }
+
+    /// 设置事件总线，任务开始、完成、失败时发布 `job.*` 事件
+    pub async fn set_event_bus(&self, bus: Arc<EventBus>) {
+        *self.events.write().await = Some(bus);
+    }
+
     /// 添加任务
-    pub async fn add_job(&self, job: Job) -> Result<String> {
+    ///
+    /// 执行时间已经过去的一次性任务拒绝创建（见 [`Job::check_run_at`]）
+    pub async fn add_job(&self, mut job: Job) -> Result<String> {
+        let now = Utc::now();
+        if job.run_count == 0 {
+            job.check_run_at(now)?;
+        }
+        job.next_run = job.next_run_after(now);
         let job_id = job.id.clone();
         
         // 保存到内存
@@ -374,24 +810,74 @@ impl Scheduler {
         Ok(job_id)
     }
 
+    /// 按时区计算的 Cron 任务执行后重新调度（任务已完成、暂停、删除或调度器已释放时不再调度）
+    fn reschedule(this: Weak<Self>, job_id: String) -> futures_util::future::BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let Some(scheduler) = this.upgrade() else {
+                return;
+            };
+            // 执行期间被暂停或删除的任务已从内部调度器移除，不再调度
+            if !scheduler.scheduled.read().await.contains_key(&job_id) {
+                return;
+            }
+            let job = scheduler.jobs.read().await.get(&job_id).cloned();
+            if let Some(job) = job.filter(|job| !matches!(job.status, JobStatus::Completed | JobStatus::Paused)) {
+                if let Err(e) = scheduler.schedule_job(&job).await {
+                    error!("重新调度任务失败 {}: {}", job_id, e);
+                }
+            }
+        })
+    }
+
     /// 调度任务到内部调度器
     async fn schedule_job(&self, job: &Job) -> Result<()> {
         let handlers = self.handlers.clone();
         let jobs = self.jobs.clone();
         let pool = self.pool.clone();
+        let router = self.router.clone();
+        let events = self.events.clone();
         let job_id = job.id.clone();
 
         let cron_job = match &job.job_type {
+            // Webhook 任务由外部请求触发，不进入内部调度器
+            JobType::Webhook { .. } => return Ok(()),
+            // 内部调度器只按 UTC 计算 Cron：带时区的任务按时区算出下次执行时间单次调度，
+            // 执行后重新调度，夏令时切换后仍在本地时刻执行
+            JobType::Cron { .. } if job.cron_timezone().is_some() => {
+                let Some(next) = job.next_run_after(Utc::now()) else {
+                    return Ok(());
+                };
+                let delay = next.signed_duration_since(Utc::now()).to_std().unwrap_or_default();
+                let this = self.this.clone();
+                CronJob::new_one_shot_async(delay, move |_uuid, _l| {
+                    let handlers = handlers.clone();
+                    let jobs = jobs.clone();
+                    let pool = pool.clone();
+                    let router = router.clone();
+                    let events = events.clone();
+                    let job_id = job_id.clone();
+                    let this = this.clone();
+
+                    Box::pin(async move {
+                        if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool, router, events, None).await {
+                            error!("任务执行失败 {}: {}", job_id, e);
+                        }
+                        Self::reschedule(this, job_id).await;
+                    })
+                })?
+            }
             JobType::Cron { expression } => {
                 let expression = expression.as_str();
                 CronJob::new_async(expression, move |_uuid, _l| {
                     let handlers = handlers.clone();
                     let jobs = jobs.clone();
                     let pool = pool.clone();
+                    let router = router.clone();
+                    let events = events.clone();
                     let job_id = job_id.clone();
                     
                     Box::pin(async move {
-                        if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool).await {
+                        if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool, router, events, None).await {
                             error!("任务执行失败 {}: {}", job_id, e);
                         }
                     })
@@ -405,10 +891,12 @@ impl Scheduler {
                         let handlers = handlers.clone();
                         let jobs = jobs.clone();
                         let pool = pool.clone();
+                        let router = router.clone();
+                        let events = events.clone();
                         let job_id = job_id.clone();
                         
                         Box::pin(async move {
-                            if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool).await {
+                            if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool, router, events, None).await {
                                 error!("任务执行失败 {}: {}", job_id, e);
                             }
                         })
@@ -427,10 +915,12 @@ impl Scheduler {
                     let handlers = handlers.clone();
                     let jobs = jobs.clone();
                     let pool = pool.clone();
+                    let router = router.clone();
+                    let events = events.clone();
                     let job_id = job_id.clone();
                     
                     Box::pin(async move {
-                        if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool).await {
+                        if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool, router, events, None).await {
                             error!("任务执行失败 {}: {}", job_id, e);
                         }
                     })
@@ -438,16 +928,30 @@ impl Scheduler {
             }
         };
 
-        self.scheduler.write().await.add(cron_job).await?;
+        let uuid = self.scheduler.write().await.add(cron_job).await?;
+        self.scheduled.write().await.insert(job.id.clone(), uuid);
+        Ok(())
+    }
+
+    /// 从内部调度器移除任务（不再按计划触发）
+    async fn unschedule_job(&self, job_id: &str) -> Result<()> {
+        if let Some(uuid) = self.scheduled.write().await.remove(job_id) {
+            self.scheduler.write().await.remove(&uuid).await?;
+        }
         Ok(())
     }
 
     /// 执行任务
+    ///
+    /// `args` 不为空时替代任务自带的 handler_args（如 Webhook 请求体）
     async fn execute_job(
         job_id: &str,
         handlers: HandlerRegistry,
         jobs: Arc<RwLock<std::collections::HashMap<String, Job>>>,
-        pool: Option<Pool<Sqlite>>,
+        pool: PoolSlot,
+        router: RouterSlot,
+        events: EventSlot,
+        args: Option<serde_json::Value>,
     ) -> Result<()> {
         // 获取任务
         let job = {
@@ -463,6 +967,10 @@ impl Scheduler {
                     return Ok(());
                 }
             }
+            if job.status == JobStatus::Paused {
+                info!("任务 {} 已暂停，跳过执行", job_id);
+                return Ok(());
+            }
 
             // 更新状态
             job.status = JobStatus::Running;
@@ -475,12 +983,42 @@ impl Scheduler {
                 handlers_guard.get(&job.handler).cloned()
             };
 
+            let bus = events.read().await.clone();
+            let publish = |job: &Job, status: &str, error: Option<String>| {
+                if let Some(ref bus) = bus {
+                    let _ = bus.publish(JobEvent {
+                        job_id: job.id.clone(),
+                        name: job.name.clone(),
+                        handler: job.handler.clone(),
+                        status: status.to_string(),
+                        error,
+                        timestamp: Utc::now(),
+                    });
+                }
+            };
+
             if let Some(handler) = handler {
                 info!("执行任务: {} ({})", job.name, job_id);
-                
-                match handler.execute(&job, job.handler_args.clone()).await {
-                    Ok(_) => {
+                publish(&job, "started", None);
+
+                let args = args.or_else(|| job.handler_args.clone());
+                // 处理器 panic 时任务标记为失败
+                let component = format!("job:{}", job.name);
+                match crash::catch(component, handler.execute(&job, args)).await.and_then(|r| r) {
+                    Ok(output) => {
                         info!("任务执行成功: {} ({})", job.name, job_id);
+                        publish(&job, "completed", None);
+                        if let Some(output) = output.filter(|o| !o.trim().is_empty()) {
+                            let router = router.read().await.clone();
+                            match router {
+                                Some(router) => {
+                                    if let Err(e) = router.deliver(&job, &output).await {
+                                        warn!("任务输出投递失败: {} ({}): {}", job.name, job_id, e);
+                                    }
+                                }
+                                None => info!("任务 {} 输出:\n{}", job.name, output),
+                            }
+                        }
                         
                         // 更新任务状态
                         if matches!(job.job_type, JobType::Once { .. }) {
@@ -491,21 +1029,41 @@ impl Scheduler {
                     }
                     Err(e) => {
                         error!("任务执行失败: {} ({}): {}", job.name, job_id, e);
+                        publish(&job, "failed", Some(e.to_string()));
                         job.status = JobStatus::Failed;
+                        // 设置了投递方式的任务同样投递失败信息
+                        let router = router.read().await.clone();
+                        if let (Some(router), Some(_)) = (router, &job.delivery) {
+                            if let Err(e) = router.deliver_failure(&job, &e.to_string()).await {
+                                warn!("任务输出投递失败: {} ({}): {}", job.name, job_id, e);
+                            }
+                        }
                     }
                 }
             } else {
                 warn!("未找到处理器: {} for job {}", job.handler, job_id);
+                publish(&job, "failed", Some(format!("未找到处理器: {}", job.handler)));
                 job.status = JobStatus::Failed;
             }
 
-            // 更新内存中的任务
-            jobs.write().await.insert(job_id.to_string(), job.clone());
+            job.next_run = job.next_run_after(Utc::now());
+
+            // 更新内存中的任务（执行期间被删除的任务不再写回，被暂停的保持暂停）
+            {
+                let mut jobs_guard = jobs.write().await;
+                match jobs_guard.get(job_id) {
+                    None => return Ok(()),
+                    Some(current) if current.status == JobStatus::Paused => job.status = JobStatus::Paused,
+                    Some(_) => {}
+                }
+                jobs_guard.insert(job_id.to_string(), job.clone());
+            }
 
             // 持久化
+            let pool = pool.read().await.clone();
             if let Some(ref pool) = pool {
                 let _ = sqlx::query(
-                    "UPDATE cron_jobs SET status = ?1, last_run = ?2, run_count = ?3 WHERE id = ?4"
+                    "UPDATE cron_jobs SET status = ?1, last_run = ?2, run_count = ?3, next_run = ?4 WHERE id = ?5"
                 )
                 .bind(match job.status {
                     JobStatus::Pending => "pending",
@@ -516,6 +1074,7 @@ impl Scheduler {
                 })
                 .bind(job.last_run)
                 .bind(job.run_count)
+                .bind(job.next_run)
                 .bind(&job.id)
                 .execute(pool)
                 .await;
@@ -549,10 +1108,55 @@ impl Scheduler {
         self.scheduler.write().await.start().await?;
         *self.running.write().await = true;
 
+        self.run_catch_up().await;
+
         info!("任务调度器已启动");
         Ok(())
     }
 
+    /// 补执行停机期间错过的任务
+    async fn run_catch_up(&self) {
+        let catch_up: Vec<(String, u32)> = self.catch_up.write().await.drain().collect();
+        for (job_id, times) in catch_up {
+            let handlers = self.handlers.clone();
+            let jobs = self.jobs.clone();
+            let pool = self.pool.clone();
+            let router = self.router.clone();
+            let events = self.events.clone();
+
+            tokio::spawn(async move {
+                for _ in 0..times {
+                    if let Err(e) = Self::execute_job(&job_id, handlers.clone(), jobs.clone(), pool.clone(), router.clone(), events.clone(), None).await {
+                        error!("补执行任务失败 {}: {}", job_id, e);
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// 触发任务（异步执行，立即返回）
+    pub async fn trigger_job(&self, job_id: &str, args: Option<serde_json::Value>) -> Result<()> {
+        if !self.jobs.read().await.contains_key(job_id) {
+            anyhow::bail!("任务不存在: {}", job_id);
+        }
+
+        let handlers = self.handlers.clone();
+        let jobs = self.jobs.clone();
+        let pool = self.pool.clone();
+        let router = self.router.clone();
+        let events = self.events.clone();
+        let job_id = job_id.to_string();
+
+        tokio::spawn(async move {
+            if let Err(e) = Self::execute_job(&job_id, handlers, jobs, pool, router, events, args).await {
+                error!("任务执行失败 {}: {}", job_id, e);
+            }
+        });
+
+        Ok(())
+    }
+
     /// 停止调度器
     pub async fn stop(&self) -> Result<()> {
         info!("停止任务调度器...");
@@ -567,16 +1171,102 @@ impl Scheduler {
         self.jobs.read().await.get(job_id).cloned()
     }
 
-    /// 列出所有任务
+    /// 列出任务（不含已完成的一次性任务）
     pub async fn list_jobs(&self) -> Vec<Job> {
+        self.jobs
+            .read()
+            .await
+            .values()
+            .filter(|j| j.status != JobStatus::Completed)
+            .cloned()
+            .collect()
+    }
+
+    /// 列出所有任务（包括已完成的一次性任务）
+    pub async fn list_all_jobs(&self) -> Vec<Job> {
         self.jobs.read().await.values().cloned().collect()
     }
 
+    /// 删除在 `cutoff` 之前完成的一次性任务，返回删除的数量
+    pub async fn purge_completed(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        let expired: Vec<String> = self
+            .jobs
+            .read()
+            .await
+            .values()
+            .filter(|j| {
+                j.status == JobStatus::Completed
+                    && matches!(j.job_type, JobType::Once { .. })
+                    && j.last_run.unwrap_or(j.created_at) < cutoff
+            })
+            .map(|j| j.id.clone())
+            .collect();
+        for id in &expired {
+            self.remove_job(id).await?;
+        }
+        Ok(expired.len())
+    }
+
+    /// 启动后台任务，定期删除完成超过 `retention_hours` 小时的一次性任务（启动时立即执行一次）
+    pub fn start_cleanup(self: Arc<Self>, retention_hours: u64) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let cutoff = Utc::now() - chrono::Duration::hours(retention_hours as i64);
+                match self.purge_completed(cutoff).await {
+                    Ok(0) => {}
+                    Ok(removed) => info!("已清理完成超过 {} 小时的 {} 个一次性任务", retention_hours, removed),
+                    Err(e) => warn!("清理已完成的任务失败: {}", e),
+                }
+            }
+        })
+    }
+
+    /// 某个用户创建的未完成任务（按下次执行时间排序）
+    pub async fn jobs_for_owner(&self, owner: &str) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .jobs
+            .read()
+            .await
+            .values()
+            .filter(|j| j.owner.as_deref() == Some(owner) && j.status != JobStatus::Completed)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|j| (j.next_run.is_none(), j.next_run, j.created_at));
+        jobs
+    }
+
+    /// 按完整 ID 或唯一的 ID 前缀查找任务（频道中的任务列表只显示 ID 前 8 位）
+    pub async fn find_job(&self, id: &str) -> Result<Job> {
+        let id = id.trim();
+        let jobs = self.jobs.read().await;
+        if let Some(job) = jobs.get(id) {
+            return Ok(job.clone());
+        }
+        let mut matches = jobs.values().filter(|j| !id.is_empty() && j.id.starts_with(id));
+        match (matches.next(), matches.next()) {
+            (Some(job), None) => Ok(job.clone()),
+            (Some(_), Some(_)) => anyhow::bail!("任务 ID 前缀 {} 匹配多个任务", id),
+            (None, _) => anyhow::bail!("任务不存在: {}", id),
+        }
+    }
+
+    /// 已注册的处理器名称
+    pub async fn handler_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.handlers.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
     /// 删除任务
     pub async fn remove_job(&self, job_id: &str) -> Result<()> {
-        self.jobs.write().await.remove(job_id);
-        
-        if let Some(ref pool) = self.pool {
+        if self.jobs.write().await.remove(job_id).is_none() {
+            anyhow::bail!("任务不存在: {}", job_id);
+        }
+        self.unschedule_job(job_id).await?;
+
+        if let Some(ref pool) = self.pool().await {
             sqlx::query("DELETE FROM cron_jobs WHERE id = ?1")
                 .bind(job_id)
                 .execute(pool)
@@ -589,19 +1279,33 @@ impl Scheduler {
 
     /// 暂停任务
     pub async fn pause_job(&self, job_id: &str) -> Result<()> {
-        if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+        {
+            let mut jobs = self.jobs.write().await;
+            let job = jobs.get_mut(job_id).ok_or_else(|| anyhow::anyhow!("任务不存在: {}", job_id))?;
             job.status = JobStatus::Paused;
             self.save_job(job).await?;
         }
+        self.unschedule_job(job_id).await?;
+        info!("暂停任务: {}", job_id);
         Ok(())
     }
 
-    /// 恢复任务
+    /// 恢复任务（已完成的一次性任务不能恢复）
     pub async fn resume_job(&self, job_id: &str) -> Result<()> {
-        if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+        let job = {
+            let mut jobs = self.jobs.write().await;
+            let job = jobs.get_mut(job_id).ok_or_else(|| anyhow::anyhow!("任务不存在: {}", job_id))?;
+            if job.status == JobStatus::Completed {
+                anyhow::bail!("任务已完成: {}", job_id);
+            }
             job.status = JobStatus::Pending;
             self.save_job(job).await?;
+            job.clone()
+        };
+        if *self.running.read().await && !self.scheduled.read().await.contains_key(job_id) {
+            self.schedule_job(&job).await?;
         }
+        info!("恢复任务: {}", job_id);
         Ok(())
     }
 }
@@ -612,6 +1316,7 @@ struct JobRow {
     id: String,
     name: String,
     description: Option<String>,
+    #[allow(dead_code)]
     job_type: String,
     job_type_data: String,
     status: String,
@@ -623,6 +1328,10 @@ struct JobRow {
     run_count: i64,
     max_runs: Option<i64>,
     persistent: bool,
+    misfire_policy: Option<String>,
+    delivery: Option<String>,
+    owner: Option<String>,
+    timezone: Option<String>,
 }
 
 impl JobRow {
@@ -653,6 +1362,13 @@ impl JobRow {
             run_count: self.run_count,
             max_runs: self.max_runs,
             persistent: self.persistent,
+            misfire_policy: self.misfire_policy.as_deref()
+                .map(MisfirePolicy::parse)
+                .unwrap_or_default(),
+            delivery: self.delivery.as_ref()
+                .and_then(|s| serde_json::from_str(s).ok()),
+            owner: self.owner.clone(),
+            timezone: self.timezone.clone(),
         })
     }
 }
@@ -660,6 +1376,7 @@ impl JobRow {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     struct TestHandler;
 
@@ -669,9 +1386,9 @@ mod tests {
             "test_handler"
         }
 
-        async fn execute(&self, _job: &Job, _args: Option<serde_json::Value>) -> Result<()> {
+        async fn execute(&self, _job: &Job, _args: Option<serde_json::Value>) -> Result<Option<String>> {
             info!("测试处理器执行");
-            Ok(())
+            Ok(None)
         }
     }
 
@@ -686,4 +1403,169 @@ mod tests {
         assert_eq!(job.max_runs, Some(10));
         assert!(job.description.is_some());
     }
+
+    #[test]
+    fn test_webhook_job() {
+        let job = Job::new_webhook("hook", "test_handler");
+        match &job.job_type {
+            JobType::Webhook { secret } => assert_eq!(secret.len(), 32),
+            _ => panic!("应为 Webhook 任务"),
+        }
+        assert_eq!(job.missed_runs(Utc::now(), MAX_CATCH_UP_RUNS), 0);
+
+        let other = Job::new_webhook("hook", "test_handler");
+        assert_ne!(job.job_type, other.job_type);
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_remove() {
+        let scheduler = Scheduler::new().await.unwrap();
+        scheduler.register_handler(Arc::new(TestHandler)).await;
+        assert_eq!(scheduler.handler_names().await, vec!["test_handler"]);
+
+        let job = Job::new_interval("interval", 3600, "test_handler").non_persistent();
+        let id = scheduler.add_job(job).await.unwrap();
+        assert_eq!(scheduler.find_job(&id[..8]).await.unwrap().id, id);
+        assert!(scheduler.find_job("").await.is_err());
+
+        scheduler.pause_job(&id).await.unwrap();
+        scheduler.trigger_job(&id, None).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let job = scheduler.get_job(&id).await.unwrap();
+        assert_eq!((job.status, job.run_count), (JobStatus::Paused, 0));
+
+        scheduler.resume_job(&id).await.unwrap();
+        assert_eq!(scheduler.get_job(&id).await.unwrap().status, JobStatus::Pending);
+
+        scheduler.remove_job(&id).await.unwrap();
+        assert!(scheduler.remove_job(&id).await.is_err());
+        assert!(scheduler.pause_job(&id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_next_run() {
+        let now = Utc::now();
+
+        let mut interval = Job::new_interval("interval", 60, "test_handler");
+        interval.last_run = Some(now - chrono::Duration::seconds(130));
+        assert_eq!(interval.next_run_after(now), Some(now + chrono::Duration::seconds(50)));
+        // 间隔过大时不会溢出
+        let huge = Job::new_interval("huge", u64::MAX, "test_handler");
+        assert_eq!(huge.next_run_after(now), None);
+
+        let cron = Job::new_cron("cron", "0 0 * * * *", "test_handler");
+        let next = cron.next_run_after(now).unwrap();
+        assert!(next > now && next <= now + chrono::Duration::hours(1));
+        assert_eq!(cron.clone().with_max_runs(0).next_run_after(now), None);
+        assert_eq!(Job::new_webhook("hook", "test_handler").next_run_after(now), None);
+
+        // 添加时计算，执行后更新
+        let scheduler = Scheduler::new().await.unwrap();
+        scheduler.register_handler(Arc::new(TestHandler)).await;
+        let id = scheduler.add_job(cron.non_persistent()).await.unwrap();
+        assert_eq!(scheduler.get_job(&id).await.unwrap().next_run, Some(next));
+
+        let id = scheduler
+            .add_job(Job::new_interval("interval", 3600, "test_handler").non_persistent())
+            .await
+            .unwrap();
+        scheduler.trigger_job(&id, None).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let job = scheduler.get_job(&id).await.unwrap();
+        assert_eq!(job.next_run, job.last_run.map(|t| t + chrono::Duration::seconds(3600)));
+
+        let id = scheduler.add_job(huge.non_persistent()).await.unwrap();
+        assert_eq!(scheduler.get_job(&id).await.unwrap().next_run, None);
+    }
+
+    #[tokio::test]
+    async fn test_zoned_cron_dst() {
+        let at = |d, h| Utc.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap();
+        // 纽约每天 9:00，2024-03-10 凌晨切换到夏令时
+        let mut job = Job::new_cron("zoned", "0 0 9 * * *", "test_handler").with_timezone("America/New_York");
+        assert_eq!(job.next_run_after(at(8, 15)), Some(at(9, 14)));
+        assert_eq!(job.next_run_after(at(9, 15)), Some(at(10, 13)));
+        assert_eq!(job.next_run_after(at(10, 14)), Some(at(11, 13)));
+
+        job.last_run = Some(at(9, 14));
+        assert_eq!(job.missed_runs(at(11, 14), MAX_CATCH_UP_RUNS), 2);
+
+        // 无法识别的时区按 UTC 计算
+        let invalid = Job::new_cron("invalid", "0 0 9 * * *", "test_handler").with_timezone("Mars/Olympus");
+        assert_eq!(invalid.next_run_after(at(9, 15)), Some(at(10, 9)));
+
+        let scheduler = Scheduler::new().await.unwrap();
+        scheduler.register_handler(Arc::new(TestHandler)).await;
+        let job = Job::new_cron("zoned", "0 0 9 * * *", "test_handler").with_timezone("America/New_York");
+        let next = job.next_run_after(Utc::now());
+        let id = scheduler.add_job(job.non_persistent()).await.unwrap();
+        let job = scheduler.get_job(&id).await.unwrap();
+        assert_eq!(job.next_run, next);
+        assert_eq!(job.timezone.as_deref(), Some("America/New_York"));
+    }
+
+    #[test]
+    fn test_missed_runs() {
+        let now = Utc::now();
+
+        let mut interval = Job::new_interval("interval", 60, "test_handler");
+        interval.last_run = Some(now - chrono::Duration::seconds(330));
+        assert_eq!(interval.missed_runs(now, MAX_CATCH_UP_RUNS), 5);
+        assert_eq!(interval.missed_runs(now, 3), 3);
+
+        let mut cron = Job::new_cron("cron", "0 0 * * * *", "test_handler");
+        cron.last_run = Some(now - chrono::Duration::hours(3));
+        assert_eq!(cron.missed_runs(now, MAX_CATCH_UP_RUNS), 3);
+
+        let once = Job::new_once("once", now - chrono::Duration::minutes(5), "test_handler");
+        assert_eq!(once.missed_runs(now, MAX_CATCH_UP_RUNS), 1);
+        assert_eq!(once.misfire_policy, MisfirePolicy::RunOnce);
+
+        let future = Job::new_once("future", now + chrono::Duration::minutes(5), "test_handler");
+        assert_eq!(future.missed_runs(now, MAX_CATCH_UP_RUNS), 0);
+    }
+
+    #[tokio::test]
+    async fn test_db_unavailable_falls_back_to_memory() {
+        // 父路径是普通文件，无法创建数据库
+        let dir = tempfile::tempdir().unwrap();
+        let blocker = dir.path().join("blocker");
+        std::fs::write(&blocker, "").unwrap();
+        let db_path = blocker.join("cron.db");
+        let db_path = db_path.to_string_lossy();
+
+        assert!(Scheduler::with_db(&db_path).await.is_err());
+        let scheduler = Scheduler::with_db_or_memory(&db_path).await.unwrap();
+        assert!(scheduler.is_degraded().await);
+        scheduler.add_job(Job::new_interval("内存任务", 60, "test_handler")).await.unwrap();
+        assert_eq!(scheduler.list_jobs().await.len(), 1);
+
+        assert!(!Scheduler::new().await.unwrap().is_degraded().await);
+    }
+
+    #[tokio::test]
+    async fn test_once_job_lifecycle() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let now = Utc::now();
+
+        // 执行时间早于宽限时间的拒绝创建，刚过去的立即执行
+        let past = Job::new_once("past", now - chrono::Duration::hours(1), "test_handler");
+        assert!(scheduler.add_job(past).await.is_err());
+        let just_now = Job::new_once("just_now", now - chrono::Duration::seconds(5), "test_handler");
+        assert!(scheduler.add_job(just_now).await.is_ok());
+
+        let id = scheduler.add_job(Job::new_once("done", now + chrono::Duration::minutes(1), "test_handler")).await.unwrap();
+        {
+            let mut jobs = scheduler.jobs.write().await;
+            let job = jobs.get_mut(&id).unwrap();
+            job.status = JobStatus::Completed;
+            job.last_run = Some(now - chrono::Duration::hours(2));
+        }
+        assert_eq!(scheduler.list_jobs().await.len(), 1);
+        assert_eq!(scheduler.list_all_jobs().await.len(), 2);
+
+        assert_eq!(scheduler.purge_completed(now - chrono::Duration::hours(3)).await.unwrap(), 0);
+        assert_eq!(scheduler.purge_completed(now - chrono::Duration::hours(1)).await.unwrap(), 1);
+        assert_eq!(scheduler.list_all_jobs().await.len(), 1);
+    }
 }
@@ -0,0 +1,979 @@
+//! 自然语言提醒解析
+//!
+//! 将 "in 20 minutes"、"every weekday at 9am"、"next Friday 15:00"、
+//! "明天早上八点" 等时间描述转换为 Once / Cron / Interval 任务，
+//! 时间按用户所在时区解释，再换算为调度器使用的 UTC。
+//! IANA 时区下单次提醒按目标日期的偏移换算，周期提醒保留本地时间的 cron 表达式，
+//! 由调度器按该时区计算每次执行时间（均含夏令时）；固定偏移下周期提醒换算为 UTC cron
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveTime, Offset, TimeZone,
+    Utc, Weekday,
+};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::{Job, JobHandler, MAX_INTERVAL_SECS};
+use crate::channel::outbox::Outbox;
+use crate::channel::quiet::{send_proactive, QuietHours};
+use crate::channel::template::{NotificationKind, NotificationTemplates};
+use crate::channel::{Channel, ChannelTarget};
+
+/// 提醒任务使用的处理器名称
+pub const REMINDER_HANDLER: &str = "reminder";
+
+/// 未指定具体时刻时的默认提醒时间
+const DEFAULT_HOUR: u32 = 9;
+
+/// 周日到周六的 cron 名称（cron 表达式按 UTC 计算）
+const CRON_DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// 解析后的提醒时间
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReminderSchedule {
+    /// 单次提醒
+    Once(DateTime<Utc>),
+    /// 周期提醒（UTC cron 表达式）
+    Cron(String),
+    /// 按 IANA 时区计算的周期提醒（本地时间 cron 表达式, 时区名）
+    ZonedCron(String, String),
+    /// 固定间隔提醒（秒）
+    Interval(u64),
+}
+
+impl ReminderSchedule {
+    /// 转换为调度任务
+    pub fn into_job(self, text: &str, channel: Option<&str>, chat_id: Option<&str>) -> Job {
+        let name = format!("提醒: {}", text);
+        let job = match self {
+            ReminderSchedule::Once(run_at) => Job::new_once(name, run_at, REMINDER_HANDLER),
+            ReminderSchedule::Cron(expression) => Job::new_cron(name, expression, REMINDER_HANDLER),
+            ReminderSchedule::ZonedCron(expression, timezone) => {
+                Job::new_cron(name, expression, REMINDER_HANDLER).with_timezone(timezone)
+            }
+            ReminderSchedule::Interval(seconds) => {
+                Job::new_interval(name, seconds, REMINDER_HANDLER)
+            }
+        };
+
+        job.with_args(json!({
+            "text": text,
+            "channel": channel,
+            "chat_id": chat_id,
+        }))
+    }
+
+    /// 生成便于阅读的描述（按用户时区显示）
+    pub fn describe(&self, timezone: Timezone) -> String {
+        match self {
+            ReminderSchedule::Once(run_at) => format!(
+                "{}",
+                run_at.with_timezone(&timezone.offset_at(*run_at)).format("%Y-%m-%d %H:%M %:z")
+            ),
+            ReminderSchedule::Cron(expression) => format!("cron(UTC) {}", expression),
+            ReminderSchedule::ZonedCron(expression, timezone) => format!("cron({}) {}", timezone, expression),
+            ReminderSchedule::Interval(seconds) => format!("每 {} 秒", seconds),
+        }
+    }
+}
+
+/// 用户时区：固定 UTC 偏移，或按日期应用夏令时的 IANA 时区
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timezone {
+    Fixed(FixedOffset),
+    Named(chrono_tz::Tz),
+}
+
+impl Timezone {
+    /// 系统本地时区（当前偏移）
+    pub fn local() -> Self {
+        Timezone::Fixed(Local::now().offset().fix())
+    }
+
+    /// 指定时刻的 UTC 偏移
+    pub fn offset_at(&self, at: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Timezone::Fixed(offset) => *offset,
+            Timezone::Named(tz) => tz.offset_from_utc_datetime(&at.naive_utc()).fix(),
+        }
+    }
+
+    /// 按该时区的当前时间解析自然语言时间描述
+    pub fn parse_when(&self, text: &str, now: DateTime<Utc>) -> Result<ReminderSchedule> {
+        match self {
+            Timezone::Fixed(offset) => parse_when(text, now.with_timezone(offset)),
+            Timezone::Named(tz) => parse_when(text, now.with_timezone(tz)),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = anyhow::Error;
+
+    /// 支持 "UTC"、"+08:00"、"UTC-0530" 等固定偏移写法和 "Asia/Shanghai" 等 IANA 时区名
+    fn from_str(tz: &str) -> Result<Self> {
+        let tz = tz.trim();
+        if tz.eq_ignore_ascii_case("utc") || tz.eq_ignore_ascii_case("z") {
+            return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+        if let Ok(named) = tz.parse::<chrono_tz::Tz>() {
+            return Ok(Timezone::Named(named));
+        }
+
+        let body = tz
+            .strip_prefix("UTC")
+            .or_else(|| tz.strip_prefix("GMT"))
+            .unwrap_or(tz);
+        let (sign, rest) = match body.chars().next() {
+            Some('+') => (1, &body[1..]),
+            Some('-') => (-1, &body[1..]),
+            _ => bail!("无法识别的时区: {}", tz),
+        };
+
+        let digits: String = rest.chars().filter(|c| c.is_ascii_digit()).collect();
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<i32>().ok(), Some(0)),
+            4 => (digits[..2].parse::<i32>().ok(), digits[2..].parse::<i32>().ok()),
+            _ => (None, None),
+        };
+
+        match (hours, minutes) {
+            (Some(h), Some(m)) if h <= 14 && m < 60 => FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+                .map(Timezone::Fixed)
+                .ok_or_else(|| anyhow!("无法识别的时区: {}", tz)),
+            _ => bail!("无法识别的时区: {}", tz),
+        }
+    }
+}
+
+/// 提醒解析支持的时区：决定周期提醒生成 UTC cron 还是按时区计算的 cron
+pub trait ReminderZone: TimeZone {
+    /// 每周 `days` 的 `hour:minute`（本地时间）重复的提醒
+    fn weekly(&self, days: &[Weekday], hour: u32, minute: u32) -> ReminderSchedule;
+}
+
+impl ReminderZone for FixedOffset {
+    fn weekly(&self, days: &[Weekday], hour: u32, minute: u32) -> ReminderSchedule {
+        ReminderSchedule::Cron(weekly_cron(days, hour, minute, *self))
+    }
+}
+
+impl ReminderZone for chrono_tz::Tz {
+    fn weekly(&self, days: &[Weekday], hour: u32, minute: u32) -> ReminderSchedule {
+        let local = weekly_cron(days, hour, minute, FixedOffset::east_opt(0).unwrap());
+        ReminderSchedule::ZonedCron(local, self.name().to_string())
+    }
+}
+
+/// 解析时区配置，未配置或无法识别时使用系统本地时区
+pub fn resolve_timezone(timezone: Option<&str>) -> Timezone {
+    match timezone.map(str::trim) {
+        Some(tz) if !tz.is_empty() => tz.parse().unwrap_or_else(|e| {
+            warn!("{}，使用系统本地时区", e);
+            Timezone::local()
+        }),
+        _ => Timezone::local(),
+    }
+}
+
+/// 解析时区配置为当前的 UTC 偏移
+pub fn parse_timezone(timezone: Option<&str>) -> FixedOffset {
+    resolve_timezone(timezone).offset_at(Utc::now())
+}
+
+/// 拆分 "<时间>: <内容>" 形式的提醒
+///
+/// 以第一个 ": " 或全角冒号分隔，避免把 "15:00" 中的冒号当作分隔符
+pub fn split_reminder(input: &str) -> Option<(&str, &str)> {
+    let ascii = input.find(": ").map(|i| (i, 2));
+    let full = input.find('：').map(|i| (i, '：'.len_utf8()));
+
+    let (idx, len) = match (ascii, full) {
+        (Some(a), Some(f)) => {
+            if a.0 < f.0 {
+                a
+            } else {
+                f
+            }
+        }
+        (Some(a), None) => a,
+        (None, Some(f)) => f,
+        (None, None) => return None,
+    };
+
+    let when = input[..idx].trim();
+    let text = input[idx + len..].trim();
+    if when.is_empty() || text.is_empty() {
+        return None;
+    }
+    Some((when, text))
+}
+
+/// 解析自然语言时间描述
+pub fn parse_when<Tz: ReminderZone>(text: &str, now: DateTime<Tz>) -> Result<ReminderSchedule> {
+    let normalized = text.trim().to_lowercase();
+    if normalized.is_empty() {
+        bail!("时间描述为空");
+    }
+
+    let parsed = if normalized.is_ascii() {
+        parse_english(&normalized, now)
+    } else {
+        parse_chinese(&normalized, now)
+    };
+
+    parsed.ok_or_else(|| anyhow!("无法理解的时间描述: {}", text))?
+}
+
+/// 当前时间之后 `amount` 个单位（每单位 `unit` 秒）的单次提醒，延迟为 0 或过大时返回错误
+fn once_after<Tz: TimeZone>(now: DateTime<Tz>, amount: u64, unit: u64) -> Result<ReminderSchedule> {
+    if amount == 0 {
+        bail!("提醒时间必须在当前时间之后");
+    }
+    amount
+        .checked_mul(unit)
+        .and_then(|secs| i64::try_from(secs).ok())
+        .and_then(Duration::try_seconds)
+        .and_then(|delay| now.checked_add_signed(delay))
+        .map(|at| ReminderSchedule::Once(at.with_timezone(&Utc)))
+        .ok_or_else(|| anyhow!("提醒时间太远"))
+}
+
+/// 每 `amount` 个单位（每单位 `unit` 秒）重复的提醒，间隔为 0 或过大时返回错误
+fn interval_of(amount: u64, unit: u64) -> Result<ReminderSchedule> {
+    match amount.checked_mul(unit) {
+        Some(0) => bail!("重复间隔必须大于 0"),
+        Some(seconds) if seconds <= MAX_INTERVAL_SECS => Ok(ReminderSchedule::Interval(seconds)),
+        _ => bail!("重复间隔太长"),
+    }
+}
+
+/// 时间单位（秒）
+fn unit_seconds(unit: &str) -> Option<u64> {
+    match unit.trim_end_matches(',') {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(60),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(3600),
+        "d" | "day" | "days" => Some(86400),
+        "w" | "week" | "weeks" => Some(7 * 86400),
+        _ => None,
+    }
+}
+
+pub(crate) fn english_weekday(word: &str) -> Option<Weekday> {
+    match word.trim_end_matches(',').trim_end_matches('s') {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// 解析英文时刻，如 "9am"、"9:30 pm"、"15:00"、"noon"
+fn parse_english_time(text: &str) -> Option<(u32, u32)> {
+    let compact: String = text.split_whitespace().collect();
+    match compact.as_str() {
+        "noon" | "midday" => return Some((12, 0)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+
+    let (body, meridiem) = if let Some(b) = compact.strip_suffix("am") {
+        (b, Some(false))
+    } else if let Some(b) = compact.strip_suffix("pm") {
+        (b, Some(true))
+    } else {
+        (compact.as_str(), None)
+    };
+
+    let (hour, minute) = match body.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (body.parse::<u32>().ok()?, 0),
+    };
+
+    let hour = match meridiem {
+        Some(pm) => {
+            if hour == 0 || hour > 12 {
+                return None;
+            }
+            match (pm, hour) {
+                (false, 12) => 0,
+                (true, 12) => 12,
+                (true, h) => h + 12,
+                (false, h) => h,
+            }
+        }
+        None => hour,
+    };
+
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+    Some((hour, minute))
+}
+
+fn parse_english<Tz: ReminderZone>(text: &str, now: DateTime<Tz>) -> Option<Result<ReminderSchedule>> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let first = *tokens.first()?;
+
+    // in 20 minutes / in an hour
+    if first == "in" {
+        let (amount, unit) = match tokens.get(1..)? {
+            [n, unit, ..] => (*n, *unit),
+            _ => return None,
+        };
+        let amount = match amount {
+            "a" | "an" | "one" => 1,
+            n => n.parse::<u64>().ok()?,
+        };
+        return Some(once_after(now, amount, unit_seconds(unit)?));
+    }
+
+    // every day at 9am / every weekday / every 30 minutes
+    if first == "every" {
+        let rest = &tokens[1..];
+        let at = rest.iter().position(|t| *t == "at");
+        let (days_part, time_part) = match at {
+            Some(i) => (&rest[..i], Some(rest[i + 1..].join(" "))),
+            None => (rest, None),
+        };
+
+        if let [n, unit] = days_part {
+            if let (Ok(n), Some(secs)) = (n.parse::<u64>(), unit_seconds(unit)) {
+                return Some(interval_of(n, secs));
+            }
+        }
+        if let [unit] = days_part {
+            if *unit == "hour" || *unit == "minute" {
+                return Some(Ok(ReminderSchedule::Interval(unit_seconds(unit)?)));
+            }
+        }
+
+        let days = match days_part {
+            ["day"] | ["morning"] => all_days(),
+            ["weekday"] | ["weekdays"] => weekdays(),
+            ["weekend"] | ["weekends"] => vec![Weekday::Sat, Weekday::Sun],
+            parts => {
+                let days: Vec<Weekday> = parts
+                    .iter()
+                    .filter(|p| **p != "and")
+                    .map(|p| english_weekday(p))
+                    .collect::<Option<_>>()?;
+                if days.is_empty() {
+                    return None;
+                }
+                days
+            }
+        };
+
+        let (hour, minute) = match time_part {
+            Some(t) => parse_english_time(&t)?,
+            None => (DEFAULT_HOUR, 0),
+        };
+        return Some(Ok(now.timezone().weekly(&days, hour, minute)));
+    }
+
+    // 日期部分
+    let today = now.date_naive();
+    let mut idx = 0;
+    let mut date: Option<NaiveDate> = None;
+    let mut weekday: Option<Weekday> = None;
+
+    match tokens[0] {
+        "today" => {
+            date = Some(today);
+            idx = 1;
+        }
+        "tomorrow" => {
+            date = Some(today + Duration::days(1));
+            idx = 1;
+        }
+        "next" | "on" | "this" => {
+            weekday = Some(english_weekday(tokens.get(1)?)?);
+            idx = 2;
+        }
+        t => {
+            if let Some(d) = english_weekday(t) {
+                weekday = Some(d);
+                idx = 1;
+            } else if let Ok(d) = NaiveDate::parse_from_str(t, "%Y-%m-%d") {
+                date = Some(d);
+                idx = 1;
+            }
+        }
+    }
+
+    let mut rest = &tokens[idx..];
+    if rest.first() == Some(&"at") {
+        rest = &rest[1..];
+    }
+    let time = if rest.is_empty() {
+        None
+    } else {
+        Some(parse_english_time(&rest.join(" "))?)
+    };
+
+    if idx == 0 && time.is_none() {
+        return None;
+    }
+
+    Some(resolve_once(now, date, weekday, false, time))
+}
+
+/// 解析中文数字（支持阿拉伯数字与 "二十三"、"两" 等写法）
+fn chinese_number(text: &str) -> Option<u32> {
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().all(|c| c.is_ascii_digit()) {
+        return text.parse().ok();
+    }
+
+    let digit = |c: char| -> Option<u32> {
+        match c {
+            '零' | '〇' => Some(0),
+            '一' => Some(1),
+            '二' | '两' => Some(2),
+            '三' => Some(3),
+            '四' => Some(4),
+            '五' => Some(5),
+            '六' => Some(6),
+            '七' => Some(7),
+            '八' => Some(8),
+            '九' => Some(9),
+            _ => None,
+        }
+    };
+
+    match text.split_once('十') {
+        Some((tens, ones)) => {
+            let tens = if tens.is_empty() {
+                1
+            } else {
+                chinese_number(tens)?
+            };
+            let ones = if ones.is_empty() {
+                0
+            } else {
+                chinese_number(ones)?
+            };
+            tens.checked_mul(10)?.checked_add(ones)
+        }
+        None => {
+            let mut value: u32 = 0;
+            for c in text.chars() {
+                value = value.checked_mul(10)?.checked_add(digit(c)?)?;
+            }
+            Some(value)
+        }
+    }
+}
+
+/// 截取开头的数字部分
+pub(crate) fn take_number(text: &str) -> Option<(u32, &str)> {
+    let end = text
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_digit() || "零〇一二两三四五六七八九十".contains(*c)))
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    Some((chinese_number(&text[..end])?, &text[end..]))
+}
+
+fn chinese_weekday(c: char) -> Option<Weekday> {
+    match c {
+        '一' | '1' => Some(Weekday::Mon),
+        '二' | '2' => Some(Weekday::Tue),
+        '三' | '3' => Some(Weekday::Wed),
+        '四' | '4' => Some(Weekday::Thu),
+        '五' | '5' => Some(Weekday::Fri),
+        '六' | '6' => Some(Weekday::Sat),
+        '日' | '天' | '7' => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// 截取 "周五"、"星期五"、"礼拜五"
+pub(crate) fn take_chinese_weekday(text: &str) -> Option<(Weekday, &str)> {
+    let rest = ["星期", "礼拜", "周"]
+        .iter()
+        .find_map(|p| text.strip_prefix(p))?;
+    let c = rest.chars().next()?;
+    Some((chinese_weekday(c)?, &rest[c.len_utf8()..]))
+}
+
+/// 解析中文时刻，如 "早上八点"、"下午3点半"、"晚上9:30"
+fn parse_chinese_time(text: &str) -> Option<(u32, u32)> {
+    let mut rest = text.trim();
+    let mut period = "";
+    for p in ["早上", "早晨", "上午", "中午", "下午", "傍晚", "晚上", "夜里", "凌晨"] {
+        if let Some(r) = rest.strip_prefix(p) {
+            period = p;
+            rest = r;
+            break;
+        }
+    }
+
+    let (mut hour, minute) = if let Some((h, m)) = rest.split_once(':') {
+        (h.parse::<u32>().ok()?, m.trim().parse::<u32>().ok()?)
+    } else if rest.is_empty() {
+        // 只有时段，如 "明天早上"
+        match period {
+            "早上" | "早晨" | "上午" => (DEFAULT_HOUR, 0),
+            "中午" => (12, 0),
+            "下午" => (15, 0),
+            "傍晚" => (18, 0),
+            "晚上" | "夜里" => (20, 0),
+            _ => return None,
+        }
+    } else {
+        let (hour, r) = take_number(rest)?;
+        let r = r
+            .strip_prefix('点')
+            .or_else(|| r.strip_prefix('时'))?
+            .trim_end_matches('钟');
+        let minute = match r {
+            "" | "整" => 0,
+            "半" => 30,
+            "一刻" => 15,
+            "三刻" => 45,
+            m => {
+                let (minute, tail) = take_number(m)?;
+                if !tail.is_empty() && tail != "分" {
+                    return None;
+                }
+                minute
+            }
+        };
+        (hour, minute)
+    };
+
+    match period {
+        "下午" | "傍晚" | "晚上" | "夜里" if hour < 12 => hour += 12,
+        "中午" if hour < 11 => hour += 12,
+        _ => {}
+    }
+
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+    Some((hour, minute))
+}
+
+fn parse_chinese<Tz: ReminderZone>(text: &str, now: DateTime<Tz>) -> Option<Result<ReminderSchedule>> {
+    let text: String = text.split_whitespace().collect();
+    let text = text.as_str();
+
+    // 20分钟后 / 半小时后 / 两个小时以后
+    if let Some(body) = text
+        .strip_suffix("以后")
+        .or_else(|| text.strip_suffix("之后"))
+        .or_else(|| text.strip_suffix('后'))
+    {
+        let (amount, secs) = if body == "半小时" || body == "半个小时" {
+            (1, 1800)
+        } else {
+            let (amount, unit) = take_number(body)?;
+            let unit = unit.trim_start_matches('个');
+            let secs = match unit {
+                "秒" | "秒钟" => 1,
+                "分" | "分钟" => 60,
+                "小时" | "钟头" => 3600,
+                "天" => 86400,
+                "周" | "星期" | "礼拜" => 7 * 86400,
+                _ => return None,
+            };
+            (amount as u64, secs)
+        };
+        return Some(once_after(now, amount, secs));
+    }
+
+    // 每天 / 每个工作日 / 每周一 / 每小时 / 每30分钟
+    if let Some(body) = text.strip_prefix('每') {
+        let body = body.trim_start_matches('个');
+        if body == "小时" {
+            return Some(Ok(ReminderSchedule::Interval(3600)));
+        }
+        if let Some((n, unit)) = take_number(body) {
+            let secs = match unit.trim_start_matches('个') {
+                "分钟" => 60,
+                "小时" => 3600,
+                _ => return None,
+            };
+            return Some(interval_of(n as u64, secs));
+        }
+
+        let (days, rest) = if let Some(r) = body.strip_prefix("天").or_else(|| body.strip_prefix("日")) {
+            (all_days(), r)
+        } else if let Some(r) = body.strip_prefix("工作日") {
+            (weekdays(), r)
+        } else if let Some(r) = body.strip_prefix("周末") {
+            (vec![Weekday::Sat, Weekday::Sun], r)
+        } else {
+            let (day, r) = take_chinese_weekday(body)?;
+            (vec![day], r)
+        };
+
+        let (hour, minute) = if rest.is_empty() {
+            (DEFAULT_HOUR, 0)
+        } else {
+            parse_chinese_time(rest)?
+        };
+        return Some(Ok(now.timezone().weekly(&days, hour, minute)));
+    }
+
+    // 日期部分
+    let today = now.date_naive();
+    let mut date: Option<NaiveDate> = None;
+    let mut weekday: Option<Weekday> = None;
+    let mut next_week = false;
+    let mut rest = text;
+
+    for (prefix, days) in [("今天", 0), ("明天", 1), ("后天", 2), ("大后天", 3)] {
+        if let Some(r) = text.strip_prefix(prefix) {
+            date = Some(today + Duration::days(days));
+            rest = r;
+        }
+    }
+
+    if date.is_none() {
+        let (r, is_next) = match text.strip_prefix('下') {
+            Some(r) => (r.trim_start_matches('个'), true),
+            None => (text, false),
+        };
+        if let Some((day, r)) = take_chinese_weekday(r) {
+            weekday = Some(day);
+            next_week = is_next;
+            rest = r;
+        }
+    }
+
+    let time = if rest.is_empty() {
+        None
+    } else {
+        Some(parse_chinese_time(rest)?)
+    };
+
+    Some(resolve_once(now, date, weekday, next_week, time))
+}
+
+/// 计算单次提醒的具体时间
+///
+/// * 只有时刻：今天该时刻，已过则顺延到明天
+/// * 只有星期：下一个该星期几（今天同一天但时刻已过则顺延一周）
+/// * `next_week`：下周对应的星期几（周一为一周开始）
+///
+/// 顺延按本地日期计算，跨越夏令时切换时仍落在同一本地时刻
+fn resolve_once<Tz: TimeZone>(
+    now: DateTime<Tz>,
+    date: Option<NaiveDate>,
+    weekday: Option<Weekday>,
+    next_week: bool,
+    time: Option<(u32, u32)>,
+) -> Result<ReminderSchedule> {
+    let timezone = now.timezone();
+    let today = now.date_naive();
+    let (hour, minute) = time.unwrap_or((DEFAULT_HOUR, 0));
+    let at = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(|| anyhow!("无效的时间"))?;
+    // 夏令时回拨造成的重复时刻取较早的一个，跳过的时刻视为无效
+    let to_local = |d: NaiveDate| {
+        timezone
+            .from_local_datetime(&d.and_time(at))
+            .earliest()
+            .ok_or_else(|| anyhow!("无效的时间"))
+    };
+
+    let target = match (date, weekday) {
+        (Some(d), _) => {
+            let target = to_local(d)?;
+            if target <= now {
+                bail!("提醒时间已过: {}", target.naive_local().format("%Y-%m-%d %H:%M"));
+            }
+            target
+        }
+        (None, Some(day)) => {
+            let today_idx = today.weekday().num_days_from_monday() as i64;
+            let day_idx = day.num_days_from_monday() as i64;
+            if next_week {
+                to_local(today + Duration::days(7 - today_idx + day_idx))?
+            } else {
+                let day = today + Duration::days((day_idx - today_idx).rem_euclid(7));
+                let target = to_local(day)?;
+                if target <= now {
+                    to_local(day + Duration::days(7))?
+                } else {
+                    target
+                }
+            }
+        }
+        (None, None) => {
+            let target = to_local(today)?;
+            if target <= now {
+                to_local(today + Duration::days(1))?
+            } else {
+                target
+            }
+        }
+    };
+
+    Ok(ReminderSchedule::Once(target.with_timezone(&Utc)))
+}
+
+fn all_days() -> Vec<Weekday> {
+    vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+}
+
+fn weekdays() -> Vec<Weekday> {
+    vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]
+}
+
+/// 生成 `offset` 换算到 UTC 后的每周 cron 表达式（偏移为 0 时即本地时间）
+///
+/// 本地时刻换算为 UTC 后可能跨日，此时星期也需要相应平移
+fn weekly_cron(days: &[Weekday], hour: u32, minute: u32, offset: FixedOffset) -> String {
+    let local_minutes = (hour * 60 + minute) as i32;
+    let utc_minutes = local_minutes - offset.local_minus_utc() / 60;
+    let day_shift = utc_minutes.div_euclid(24 * 60);
+    let utc_minutes = utc_minutes.rem_euclid(24 * 60);
+
+    let dow = if days.len() == 7 {
+        "*".to_string()
+    } else {
+        let mut indexes: Vec<usize> = days
+            .iter()
+            .map(|d| (d.num_days_from_sunday() as i32 + day_shift).rem_euclid(7) as usize)
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        indexes
+            .iter()
+            .map(|i| CRON_DAYS[*i])
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    format!("0 {} {} * * {}", utc_minutes % 60, utc_minutes / 60, dow)
+}
+
+/// 提醒任务处理器
+///
+/// 根据任务参数中的 channel / chat_id 将提醒发送到对应通道，
+/// 未指定目标时仅记录日志。通道处于免打扰时段时排队到时段结束后发送
+pub struct ReminderHandler {
+    channels: Vec<Arc<dyn Channel>>,
+    quiet_hours: QuietHours,
+    outbox: Option<Arc<Outbox>>,
+    templates: NotificationTemplates,
+}
+
+impl ReminderHandler {
+    pub fn new(channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self {
+            channels,
+            quiet_hours: QuietHours::default(),
+            outbox: None,
+            templates: NotificationTemplates::default(),
+        }
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// 设置通知模板
+    pub fn with_templates(mut self, templates: NotificationTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// 设置发件箱（免打扰时段内的提醒排队到其中）
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+}
+
+#[async_trait]
+impl JobHandler for ReminderHandler {
+    fn name(&self) -> &str {
+        REMINDER_HANDLER
+    }
+
+    async fn execute(&self, job: &Job, args: Option<Value>) -> Result<Option<String>> {
+        let args = args.unwrap_or(Value::Null);
+        let text = args
+            .get("text")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&job.name);
+        let channel = args.get("channel").and_then(|v| v.as_str());
+        let chat_id = args.get("chat_id").and_then(|v| v.as_str());
+        let notice = |channel: &str| {
+            self.templates
+                .render(channel, NotificationKind::Reminder, &[("text", text.to_string())])
+        };
+
+        match (channel, chat_id) {
+            (Some(channel), Some(chat_id)) => {
+                let target = self
+                    .channels
+                    .iter()
+                    .find(|c| c.name() == channel)
+                    .ok_or_else(|| anyhow!("提醒目标通道不存在: {}", channel))?;
+                send_proactive(
+                    &self.quiet_hours,
+                    self.outbox.as_ref(),
+                    target.clone(),
+                    &ChannelTarget::parse(chat_id)?,
+                    &notice(channel),
+                )
+                .await?;
+                Ok(None)
+            }
+            // 未指定聊天时按任务的投递方式处理
+            _ => Ok(Some(notice(""))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-05-15 (周三) 10:00 +08:00
+    fn now() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 5, 15, 10, 0, 0)
+            .unwrap()
+    }
+
+    fn local(s: &str) -> ReminderSchedule {
+        let dt = DateTime::parse_from_str(&format!("{} +08:00", s), "%Y-%m-%d %H:%M %:z").unwrap();
+        ReminderSchedule::Once(dt.with_timezone(&Utc))
+    }
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(parse_when("in 20 minutes", now()).unwrap(), local("2024-05-15 10:20"));
+        assert_eq!(parse_when("in an hour", now()).unwrap(), local("2024-05-15 11:00"));
+        assert_eq!(parse_when("20分钟后", now()).unwrap(), local("2024-05-15 10:20"));
+        assert_eq!(parse_when("半小时后", now()).unwrap(), local("2024-05-15 10:30"));
+        assert_eq!(parse_when("两个小时以后", now()).unwrap(), local("2024-05-15 12:00"));
+    }
+
+    #[test]
+    fn test_absolute() {
+        assert_eq!(parse_when("tomorrow at 9am", now()).unwrap(), local("2024-05-16 09:00"));
+        assert_eq!(parse_when("next Friday 15:00", now()).unwrap(), local("2024-05-17 15:00"));
+        assert_eq!(parse_when("at 8:30pm", now()).unwrap(), local("2024-05-15 20:30"));
+        assert_eq!(parse_when("9am", now()).unwrap(), local("2024-05-16 09:00"));
+        assert_eq!(parse_when("明天早上八点", now()).unwrap(), local("2024-05-16 08:00"));
+        assert_eq!(parse_when("下午3点半", now()).unwrap(), local("2024-05-15 15:30"));
+        assert_eq!(parse_when("下周一上午十点", now()).unwrap(), local("2024-05-20 10:00"));
+        assert_eq!(parse_when("周三晚上9:30", now()).unwrap(), local("2024-05-15 21:30"));
+        assert!(parse_when("today at 8am", now()).is_err());
+    }
+
+    #[test]
+    fn test_recurring() {
+        assert_eq!(
+            parse_when("every weekday at 9am", now()).unwrap(),
+            ReminderSchedule::Cron("0 0 1 * * Mon,Tue,Wed,Thu,Fri".to_string())
+        );
+        // 本地 7 点对应 UTC 前一天 23 点
+        assert_eq!(
+            parse_when("每个工作日早上七点", now()).unwrap(),
+            ReminderSchedule::Cron("0 0 23 * * Sun,Mon,Tue,Wed,Thu".to_string())
+        );
+        assert_eq!(
+            parse_when("每天晚上十点", now()).unwrap(),
+            ReminderSchedule::Cron("0 0 14 * * *".to_string())
+        );
+        assert_eq!(parse_when("every 30 minutes", now()).unwrap(), ReminderSchedule::Interval(1800));
+
+        if let ReminderSchedule::Cron(expr) = parse_when("every monday and friday at 6pm", now()).unwrap() {
+            assert!(cron::Schedule::from_str(&expr).is_ok());
+        } else {
+            panic!("应解析为 cron");
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        // 数值过大时返回错误而不是溢出
+        for text in [
+            "in 9999999999999 days",
+            "in 18446744073709551615 weeks",
+            "every 9999999999999999999 weeks",
+            "every 1000000000000 days",
+            "每876001小时",
+            "九九九九九九九九九九九天后",
+            "4294967295天后",
+            "每九九九九九九九九九九九分钟",
+        ] {
+            assert!(parse_when(text, now()).is_err(), "{}", text);
+        }
+        assert!(parse_when("every 0 minutes", now()).is_err());
+        assert!(parse_when("每0分钟", now()).is_err());
+        // 延迟为 0 时在解析阶段拒绝，而不是创建一个已过期的任务
+        assert!(parse_when("0分钟后", now()).is_err());
+        assert!(parse_when("in 0 minutes", now()).is_err());
+    }
+
+    #[test]
+    fn test_split_and_timezone() {
+        assert_eq!(
+            split_reminder("next Friday 15:00: call mom"),
+            Some(("next Friday 15:00", "call mom"))
+        );
+        assert_eq!(split_reminder("明天早上八点：开会"), Some(("明天早上八点", "开会")));
+        assert_eq!(split_reminder("no separator"), None);
+
+        assert_eq!(parse_timezone(Some("+08:00")).local_minus_utc(), 8 * 3600);
+        assert_eq!(parse_timezone(Some("UTC-0530")).local_minus_utc(), -(5 * 3600 + 1800));
+        assert_eq!(parse_timezone(Some("utc")).local_minus_utc(), 0);
+        assert_eq!(parse_timezone(Some("Asia/Shanghai")).local_minus_utc(), 8 * 3600);
+        assert!("Mars/Olympus".parse::<Timezone>().is_err());
+        assert!("北京".parse::<Timezone>().is_err());
+    }
+
+    #[test]
+    fn test_named_timezone_dst() {
+        let timezone: Timezone = "America/New_York".parse().unwrap();
+        // 2024-03-09 10:00 EST，次日凌晨切换到夏令时（EDT，-04:00）
+        let now = Utc.with_ymd_and_hms(2024, 3, 9, 15, 0, 0).unwrap();
+        let at = |d, h| ReminderSchedule::Once(Utc.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap());
+        assert_eq!(timezone.parse_when("tomorrow at 9am", now).unwrap(), at(10, 13));
+        assert_eq!(timezone.parse_when("8am", now).unwrap(), at(10, 12));
+        assert_eq!(timezone.parse_when("in 2 hours", now).unwrap(), at(9, 17));
+        assert_eq!(at(10, 13).describe(timezone), "2024-03-10 09:00 -04:00");
+
+        // 周期提醒保留本地时间，由调度器按时区计算，跨夏令时仍在 9:00 触发
+        let daily = timezone.parse_when("every day at 9am", now).unwrap();
+        assert_eq!(
+            daily,
+            ReminderSchedule::ZonedCron("0 0 9 * * *".to_string(), "America/New_York".to_string())
+        );
+        assert_eq!(daily.describe(timezone), "cron(America/New_York) 0 0 9 * * *");
+        let job = daily.into_job("standup", None, None);
+        assert_eq!(job.next_run_after(now), Some(Utc.with_ymd_and_hms(2024, 3, 10, 13, 0, 0).unwrap()));
+        assert_eq!(job.next_run_after(now - chrono::Duration::days(1)), Some(Utc.with_ymd_and_hms(2024, 3, 9, 14, 0, 0).unwrap()));
+    }
+}
@@ -0,0 +1,109 @@
+//! shell_command 任务处理器
+//!
+//! 按计划执行 shell 命令，不经过模型。需要在 `[cron] shell_jobs` 中显式启用，
+//! 沿用 shell 工具的命令白名单、工作目录和环境变量限制；白名单只检查第一个词，
+//! 因此拒绝包含 shell 元字符（`;`、`&`、`|`、`$`、反引号、重定向等）的命令。
+//! 输出的最后若干行按任务的投递方式发送。处理器参数：
+//! `{"command": "/opt/backup.sh", "timeout": 600, "cwd": "...", "env": {...}, "tail": 20}`
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::{Job, JobHandler};
+use crate::config::ToolsConfig;
+use crate::tools::shell::{tail_lines, ShellTool};
+use crate::tools::{Tool, ToolContext};
+
+/// 处理器名称
+pub const SHELL_COMMAND_HANDLER: &str = "shell_command";
+/// 默认超时（秒），定时脚本通常比对话中的命令运行得久
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// 默认投递的输出行数
+const DEFAULT_TAIL_LINES: u64 = 20;
+/// 可以串联或嵌入其他命令的字符
+const SHELL_METACHARACTERS: &[char] = &[';', '&', '|', '$', '`', '<', '>', '(', ')', '\n', '\r'];
+
+/// 执行 shell 命令的任务处理器
+pub struct ShellCommandHandler {
+    config: ToolsConfig,
+}
+
+impl ShellCommandHandler {
+    pub fn new(config: ToolsConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl JobHandler for ShellCommandHandler {
+    fn name(&self) -> &str {
+        SHELL_COMMAND_HANDLER
+    }
+
+    async fn execute(&self, job: &Job, args: Option<Value>) -> Result<Option<String>> {
+        let mut args = args.unwrap_or(Value::Null);
+        let command = args
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("任务 {} 缺少 command 参数", job.name))?
+            .to_string();
+        if command.contains(SHELL_METACHARACTERS) {
+            return Err(anyhow!("任务 {} 的命令包含 shell 元字符，只能执行单条白名单命令: {}", job.name, command));
+        }
+        // tail 为 0 时投递全部输出
+        let tail = args.get("tail").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_TAIL_LINES) as usize;
+        if args.get("timeout").is_none() {
+            args["timeout"] = json!(DEFAULT_TIMEOUT_SECS);
+        }
+
+        if self.config.dry_run {
+            info!("演练模式：跳过任务 {} 的命令 {}", job.name, command);
+            return Ok(Some(format!("演练模式：未执行命令 {}", command)));
+        }
+
+        let result = ShellTool.execute(args, &ToolContext::new(self.config.clone())).await?;
+        let tail_of = |text: &str| if tail == 0 { text.to_string() } else { tail_lines(text, tail) };
+        if result.success {
+            Ok(Some(tail_of(&result.output)))
+        } else {
+            Err(anyhow!("{}", tail_of(result.error.as_deref().unwrap_or_default())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shell_command_handler() {
+        // 默认白名单为 echo、cat、ls
+        let handler = ShellCommandHandler::new(ToolsConfig::default());
+        let job = Job::new_cron("备份", "0 0 3 * * *", SHELL_COMMAND_HANDLER);
+
+        let args = json!({"command": "echo 1 2 3", "tail": 2});
+        assert_eq!(handler.execute(&job, Some(args)).await.unwrap().as_deref(), Some("1 2 3"));
+
+        // 多行输出只投递最后 tail 行，tail 为 0 时投递全部
+        let mut config = ToolsConfig::default();
+        config.shell_whitelist.push("printf".to_string());
+        let printf = ShellCommandHandler::new(config);
+        let args = json!({"command": "printf '1\\n2\\n3\\n'", "tail": 2});
+        assert_eq!(printf.execute(&job, Some(args)).await.unwrap().unwrap().trim_end(), "2\n3");
+        let args = json!({"command": "printf '1\\n2\\n3\\n'", "tail": 0});
+        assert_eq!(printf.execute(&job, Some(args)).await.unwrap().unwrap().trim_end(), "1\n2\n3");
+
+        // 白名单只检查第一个词，串联的命令一律拒绝
+        for command in ["echo 1; rm x", "echo 1 && rm x", "echo 1 | sh", "echo $(rm x)", "echo `rm x`", "echo 1 > x"] {
+            let err = handler.execute(&job, Some(json!({ "command": command }))).await.unwrap_err();
+            assert!(err.to_string().contains("元字符"), "{}", command);
+        }
+
+        let err = handler.execute(&job, Some(json!({"command": "ls /nonexistent-dir"}))).await.unwrap_err();
+        assert!(err.to_string().contains("nonexistent-dir"));
+        assert!(handler.execute(&job, None).await.is_err());
+        assert!(handler.execute(&job, Some(json!({"command": "rm -rf /tmp/x"}))).await.is_err());
+    }
+}
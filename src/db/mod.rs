@@ -0,0 +1,188 @@
+//! SQLite 连接与维护
+//!
+//! 各组件的数据库连接池都通过 [`connect`] 创建：启用 WAL 和 busy_timeout，gateway 与 CLI 命令
+//! 同时访问时等待而不是直接报 "database is locked"。gateway 退出时 [`close_all`] 对所有连接池执行
+//! `PRAGMA optimize` 并关闭（WAL 文件随之合并删除）；`nanobot db maintain` 用 [`maintain`]
+//! 检查完整性、整理数据库文件
+
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::vault::Vault;
+
+/// 等待其它连接释放锁的时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 已打开的连接池（退出时统一优化并关闭）
+static POOLS: Mutex<Vec<Pool<Sqlite>>> = Mutex::new(Vec::new());
+
+/// 打开（或创建）数据库，启用 WAL 和 busy_timeout
+pub async fn connect(db_path: &str, max_connections: u32) -> Result<Pool<Sqlite>> {
+    // 确保目录存在
+    if let Some(parent) = Path::new(db_path).parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(BUSY_TIMEOUT);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
+        .await?;
+
+    let mut pools = POOLS.lock().unwrap();
+    pools.retain(|p| !p.is_closed());
+    pools.push(pool.clone());
+    Ok(pool)
+}
+
+/// 对所有连接池执行 `PRAGMA optimize` 后关闭
+pub async fn close_all() {
+    let pools: Vec<Pool<Sqlite>> = POOLS.lock().unwrap().drain(..).collect();
+    for pool in pools.iter().filter(|p| !p.is_closed()) {
+        if let Err(e) = sqlx::query("PRAGMA optimize").execute(pool).await {
+            debug!("PRAGMA optimize 失败: {}", e);
+        }
+        pool.close().await;
+    }
+}
+
+/// 单个表的统计
+#[derive(Debug, Clone)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: i64,
+    /// 占用的字节数（SQLite 未启用 dbstat 时为空）
+    pub bytes: Option<i64>,
+}
+
+/// 数据库维护结果
+#[derive(Debug, Clone)]
+pub struct MaintenanceReport {
+    /// 完整性检查发现的问题（为空表示正常）
+    pub problems: Vec<String>,
+    /// 维护前后的文件大小（含 WAL 文件）
+    pub size_before: u64,
+    pub size_after: u64,
+    pub tables: Vec<TableStats>,
+}
+
+/// 数据库文件加上 WAL 文件的大小
+fn file_size(path: &Path) -> u64 {
+    let wal = PathBuf::from(format!("{}-wal", path.display()));
+    [path, wal.as_path()]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// 数据库文件是否已被 vault 加密
+pub async fn is_encrypted(path: &Path) -> Result<bool> {
+    let data = tokio::fs::read(path).await?;
+    Ok(Vault::is_encrypted(&data))
+}
+
+/// 检查完整性；没有问题时整理文件（VACUUM）、重建索引并更新查询优化统计
+pub async fn maintain(path: &Path) -> Result<MaintenanceReport> {
+    let size_before = file_size(path);
+    let pool = connect(&path.to_string_lossy(), 1)
+        .await
+        .with_context(|| format!("打开数据库失败: {}", path.display()))?;
+
+    let problems: Vec<String> = sqlx::query("PRAGMA integrity_check")
+        .fetch_all(&pool)
+        .await?
+        .iter()
+        .map(|row| row.get::<String, _>(0))
+        .filter(|line| line != "ok")
+        .collect();
+
+    // 损坏的数据库不整理，避免进一步破坏
+    if problems.is_empty() {
+        sqlx::query("VACUUM").execute(&pool).await?;
+        sqlx::query("REINDEX").execute(&pool).await?;
+        sqlx::query("PRAGMA optimize").execute(&pool).await?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await?;
+    }
+
+    let names: Vec<String> = sqlx::query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&pool)
+    .await?
+    .iter()
+    .map(|row| row.get(0))
+    .collect();
+    let mut tables = Vec::with_capacity(names.len());
+    for name in names {
+        let rows: i64 = sqlx::query(&format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")))
+            .fetch_one(&pool)
+            .await?
+            .get(0);
+        let bytes = sqlx::query("SELECT SUM(pgsize) FROM dbstat WHERE name = ?1")
+            .bind(&name)
+            .fetch_one(&pool)
+            .await
+            .ok()
+            .and_then(|row| row.try_get::<Option<i64>, _>(0).ok().flatten());
+        tables.push(TableStats { name, rows, bytes });
+    }
+    pool.close().await;
+
+    let report = MaintenanceReport {
+        problems,
+        size_before,
+        size_after: file_size(path),
+        tables,
+    };
+    info!(
+        "数据库维护完成: {}（{} → {} 字节）",
+        path.display(),
+        report.size_before,
+        report.size_after
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_and_maintain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("test.db");
+        let pool = connect(&path.to_string_lossy(), 1).await.unwrap();
+
+        let mode: String = sqlx::query("PRAGMA journal_mode").fetch_one(&pool).await.unwrap().get(0);
+        assert_eq!(mode, "wal");
+
+        sqlx::query("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)").execute(&pool).await.unwrap();
+        for i in 0..50 {
+            sqlx::query("INSERT INTO notes (body) VALUES (?1)")
+                .bind("x".repeat(1000 + i))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("DELETE FROM notes WHERE id > 10").execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let report = maintain(&path).await.unwrap();
+        assert!(report.problems.is_empty());
+        assert!(report.size_after < report.size_before);
+        assert_eq!(report.tables.len(), 1);
+        assert_eq!(report.tables[0].name, "notes");
+        assert_eq!(report.tables[0].rows, 10);
+        assert!(!is_encrypted(&path).await.unwrap());
+    }
+}
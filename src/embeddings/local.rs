@@ -0,0 +1,132 @@
+//! 本地 Embedding 模型（candle）
+//!
+//! 在进程内运行 BERT 类句向量模型（如 all-MiniLM-L6-v2、bge-small-zh），
+//! `model_path` 目录需包含 `config.json`、`tokenizer.json` 和 `model.safetensors`
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig, DTYPE};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use super::EmbeddingProvider;
+use crate::config::EmbeddingsConfig;
+
+/// 单条文本的最大 token 数
+const MAX_TOKENS: usize = 512;
+
+struct LocalModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    hidden_size: usize,
+}
+
+pub struct LocalEmbeddingProvider {
+    inner: Arc<LocalModel>,
+    batch_size: usize,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
+        let dir: PathBuf = config
+            .model_path
+            .clone()
+            .ok_or_else(|| anyhow!("本地 Embedding 需要配置 model_path"))?;
+        let device = Device::Cpu;
+
+        let bert_config: BertConfig = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("config.json"))
+                .with_context(|| format!("读取 {}/config.json 失败", dir.display()))?,
+        )?;
+
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow!("加载 tokenizer 失败: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("设置 tokenizer 截断失败: {}", e))?;
+
+        // SAFETY: 模型文件在运行期间不会被修改
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DTYPE, &device)?
+        };
+        let model = BertModel::load(vb, &bert_config).context("加载本地 Embedding 模型失败")?;
+
+        Ok(Self {
+            inner: Arc::new(LocalModel {
+                model,
+                tokenizer,
+                device,
+                hidden_size: bert_config.hidden_size,
+            }),
+            batch_size: config.batch_size.max(1),
+        })
+    }
+}
+
+impl LocalModel {
+    /// 均值池化 + L2 归一化
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("分词失败: {}", e))?;
+
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let masks = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+
+        let input_ids = Tensor::stack(&ids, 0)?;
+        let attention_mask = Tensor::stack(&masks, 0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+        let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let counts = mask.sum(1)?.clamp(1e-9, f64::MAX)?;
+        let pooled = summed.broadcast_div(&counts)?;
+        let norms = pooled.sqr()?.sum_keepdim(1)?.sqrt()?.clamp(1e-12, f64::MAX)?;
+
+        Ok(pooled.broadcast_div(&norms)?.to_vec2::<f32>()?)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(self.inner.hidden_size)
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(self.batch_size) {
+            // 推理是 CPU 密集型任务，避免阻塞异步运行时
+            let model = self.inner.clone();
+            let chunk = chunk.to_vec();
+            let batch = tokio::task::spawn_blocking(move || model.embed_batch(&chunk)).await??;
+            vectors.extend(batch);
+        }
+
+        Ok(vectors)
+    }
+}
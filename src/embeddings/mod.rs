@@ -0,0 +1,101 @@
+//! 向量嵌入模块
+//!
+//! 与 LLM 提供商并列的 Embedding 抽象，供语义记忆、知识库检索等功能共用。
+//! 支持的后端：
+//! - `openai`：OpenAI 兼容的 `/embeddings` 接口（OpenAI、SiliconFlow、vLLM 等）
+//! - `ollama`：本地 Ollama 服务
+//! - `local`：进程内运行的 BERT 类模型（需启用 `local-embeddings` 特性编译）
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::config::EmbeddingsConfig;
+
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+pub mod ollama;
+pub mod openai;
+
+/// Embedding 提供商 trait
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// 获取提供商名称
+    fn name(&self) -> &str;
+
+    /// 向量维度（未知时返回 None，首次请求后可确定）
+    fn dimensions(&self) -> Option<usize> {
+        None
+    }
+
+    /// 批量计算文本向量，返回顺序与输入一致
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// 计算单条文本向量
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&[text.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Embedding 结果为空"))
+    }
+}
+
+/// Embedding 提供商工厂
+pub struct EmbeddingProviderFactory;
+
+impl EmbeddingProviderFactory {
+    /// 根据 `[embeddings]` 配置创建提供商
+    pub fn create(config: &EmbeddingsConfig) -> Result<Arc<dyn EmbeddingProvider>> {
+        match config.backend.as_str() {
+            "openai" => Ok(Arc::new(openai::OpenAiEmbeddingProvider::new(config)?)),
+            "ollama" => Ok(Arc::new(ollama::OllamaEmbeddingProvider::new(config)?)),
+            #[cfg(feature = "local-embeddings")]
+            "local" => Ok(Arc::new(local::LocalEmbeddingProvider::new(config)?)),
+            #[cfg(not(feature = "local-embeddings"))]
+            "local" => Err(anyhow!(
+                "本地 Embedding 模型需要启用 local-embeddings 特性编译: cargo build --features local-embeddings"
+            )),
+            other => Err(anyhow!("未知的 Embedding 后端: {}", other)),
+        }
+    }
+}
+
+/// 余弦相似度，任一向量为零向量时返回 0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_factory_unknown_backend() {
+        let config = EmbeddingsConfig {
+            backend: "unknown".to_string(),
+            ..Default::default()
+        };
+        assert!(EmbeddingProviderFactory::create(&config).is_err());
+    }
+}
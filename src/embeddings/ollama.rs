@@ -0,0 +1,86 @@
+//! Ollama Embedding 后端
+//!
+//! 调用本地 Ollama 服务的 `/api/embed` 接口
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::EmbeddingProvider;
+use crate::config::{EmbeddingsConfig, ProviderConfig};
+
+pub struct OllamaEmbeddingProvider {
+    base_url: String,
+    model: String,
+    batch_size: usize,
+    client: Client,
+}
+
+impl OllamaEmbeddingProvider {
+    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
+        let client = crate::llm::build_http_client(&ProviderConfig {
+            timeout_secs: config.timeout_secs,
+            ..Default::default()
+        })?;
+
+        Ok(Self {
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "http://localhost:11434".to_string()),
+            model: config
+                .model
+                .clone()
+                .unwrap_or_else(|| "nomic-embed-text".to_string()),
+            batch_size: config.batch_size.max(1),
+            client,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url.trim_end_matches('/'));
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(self.batch_size) {
+            let response = self
+                .client
+                .post(&url)
+                .json(&json!({ "model": self.model, "input": chunk }))
+                .send()
+                .await
+                .map_err(|e| anyhow!("Ollama 请求失败（服务是否已启动？）: {}", e))?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                return Err(anyhow!("Ollama Embedding 错误 ({}): {}", status, text));
+            }
+
+            let body: OllamaEmbedResponse = response.json().await?;
+            if body.embeddings.len() != chunk.len() {
+                return Err(anyhow!(
+                    "Embedding 结果数量不匹配: 期望 {}，实际 {}",
+                    chunk.len(),
+                    body.embeddings.len()
+                ));
+            }
+            vectors.extend(body.embeddings);
+        }
+
+        Ok(vectors)
+    }
+}
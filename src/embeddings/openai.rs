@@ -0,0 +1,97 @@
+//! OpenAI 兼容的 Embedding 接口
+//!
+//! 适用于 OpenAI、SiliconFlow、DashScope 兼容模式、vLLM 等提供 `/embeddings` 的服务
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::EmbeddingProvider;
+use crate::config::{EmbeddingsConfig, ProviderConfig};
+
+pub struct OpenAiEmbeddingProvider {
+    api_key: Option<String>,
+    base_url: String,
+    model: String,
+    batch_size: usize,
+    client: Client,
+}
+
+impl OpenAiEmbeddingProvider {
+    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
+        let client = crate::llm::build_http_client(&ProviderConfig {
+            timeout_secs: config.timeout_secs,
+            ..Default::default()
+        })?;
+
+        Ok(Self {
+            api_key: config.api_key.clone(),
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+            model: config
+                .model
+                .clone()
+                .unwrap_or_else(|| "text-embedding-3-small".to_string()),
+            batch_size: config.batch_size.max(1),
+            client,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddingProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.base_url.trim_end_matches('/'));
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(self.batch_size) {
+            let mut request = self
+                .client
+                .post(&url)
+                .json(&json!({ "model": self.model, "input": chunk }));
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                return Err(anyhow!("Embedding API 错误 ({}): {}", status, text));
+            }
+
+            let mut body: EmbeddingResponse = response.json().await?;
+            if body.data.len() != chunk.len() {
+                return Err(anyhow!(
+                    "Embedding 结果数量不匹配: 期望 {}，实际 {}",
+                    chunk.len(),
+                    body.data.len()
+                ));
+            }
+            // 部分服务不保证按输入顺序返回
+            body.data.sort_by_key(|d| d.index);
+            vectors.extend(body.data.into_iter().map(|d| d.embedding));
+        }
+
+        Ok(vectors)
+    }
+}
@@ -0,0 +1,414 @@
+//! 评测
+//!
+//! `nanobot eval <用例文件>` 读取 YAML 格式的用例，逐条交给 Agent 回答并检查断言
+//! （contains / not_contains / regex / json_path / llm），记录每条用例的耗时和令牌数。
+//! 指定基线文件时与上一次保存的结果比较，便于在部署前发现提示词或工具改动引起的退化。
+//!
+//! ```yaml
+//! name: 基础回归
+//! model: deepseek/deepseek-chat    # 可选
+//! grader_model: deepseek/deepseek-chat  # llm 断言使用的模型，可选
+//! cases:
+//!   - name: 算术
+//!     prompt: 1+1 等于几？只回答数字
+//!     mock_response: "2"           # 使用 mock 提供商时的回复
+//!     assert:
+//!       - contains: "2"
+//!       - regex: '^\d+$'
+//!       - llm: 回答给出了正确结果 2
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::agent::{Agent, ChatOptions};
+
+/// 评测用例集
+#[derive(Debug, Clone, Deserialize)]
+pub struct Suite {
+    /// 用例集名称
+    #[serde(default)]
+    pub name: String,
+    /// 使用的模型（"provider/model" 或仅模型名），未设置时使用默认模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 覆盖系统提示词
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// llm 断言使用的评分模型，未设置时使用默认模型
+    #[serde(default)]
+    pub grader_model: Option<String>,
+    pub cases: Vec<Case>,
+}
+
+impl Suite {
+    /// 读取用例文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("读取用例文件 {} 失败", path.display()))?;
+        let suite = Self::parse(&content).with_context(|| format!("解析用例文件 {} 失败", path.display()))?;
+        if suite.cases.is_empty() {
+            return Err(anyhow!("用例文件 {} 中没有用例", path.display()));
+        }
+        let mut names = std::collections::HashSet::new();
+        for case in &suite.cases {
+            if !names.insert(case.name.as_str()) {
+                return Err(anyhow!("用例名称重复: {}", case.name));
+            }
+        }
+        Ok(suite)
+    }
+
+    /// 解析 YAML 用例
+    ///
+    /// 先转为 JSON 值再反序列化，断言可以写成 `- contains: "x"` 这样的单键映射（而不是 YAML 标签）
+    pub fn parse(content: &str) -> Result<Self> {
+        let value: Value = serde_yaml::from_str(content)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// 单条用例
+#[derive(Debug, Clone, Deserialize)]
+pub struct Case {
+    /// 用例名称（与基线比较时的键）
+    pub name: String,
+    /// 发送给 Agent 的消息
+    pub prompt: String,
+    /// 使用 mock 提供商时的回复
+    #[serde(default)]
+    pub mock_response: Option<String>,
+    /// 断言
+    #[serde(default, rename = "assert")]
+    pub assertions: Vec<Assertion>,
+}
+
+/// 断言
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Assertion {
+    /// 回答包含文本
+    Contains(String),
+    /// 回答不包含文本
+    NotContains(String),
+    /// 回答匹配正则表达式
+    Regex(String),
+    /// 回答中的 JSON 在路径处存在（设置 equals 时还要求值相等）
+    JsonPath {
+        path: String,
+        #[serde(default)]
+        equals: Option<Value>,
+    },
+    /// 由模型按标准判断
+    Llm(String),
+}
+
+/// 单条用例的结果
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub name: String,
+    pub passed: bool,
+    /// 未通过的断言（或请求失败）说明
+    pub failures: Vec<String>,
+    /// 跳过的断言数
+    pub skipped: usize,
+    pub latency_ms: u64,
+    pub tokens: u64,
+    pub answer: String,
+}
+
+/// 逐条运行用例
+///
+/// `grade` 为 false 时跳过 llm 断言（如使用 mock 提供商时）
+pub async fn run(agent: &Agent, suite: &Suite, grade: bool) -> Vec<CaseResult> {
+    let mut results = Vec::with_capacity(suite.cases.len());
+    for case in &suite.cases {
+        // 用例之间互不影响
+        agent.clear_context().await;
+        let options = ChatOptions {
+            model_override: suite.model.clone(),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let response = agent.chat_with_options(case.prompt.clone(), options).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let mut result = CaseResult {
+            name: case.name.clone(),
+            passed: false,
+            failures: Vec::new(),
+            skipped: 0,
+            latency_ms,
+            tokens: 0,
+            answer: String::new(),
+        };
+        match response {
+            Ok(response) => {
+                result.tokens = response.tokens;
+                result.answer = response.content;
+            }
+            Err(e) => {
+                result.failures.push(format!("请求失败: {:#}", e));
+                results.push(result);
+                continue;
+            }
+        }
+
+        for assertion in &case.assertions {
+            match assertion {
+                Assertion::Llm(_) if !grade => result.skipped += 1,
+                Assertion::Llm(criteria) => {
+                    match agent
+                        .grade_answer(&case.prompt, &result.answer, criteria, suite.grader_model.as_deref())
+                        .await
+                    {
+                        Ok(g) if g.passed => {}
+                        Ok(g) => result.failures.push(format!("llm: {}（{}）", criteria, g.reason)),
+                        Err(e) => result.failures.push(format!("llm: 评分失败: {}", e)),
+                    }
+                }
+                _ => {
+                    if let Err(e) = check(assertion, &result.answer) {
+                        result.failures.push(e);
+                    }
+                }
+            }
+        }
+        result.passed = result.failures.is_empty();
+        results.push(result);
+    }
+    results
+}
+
+/// 检查不需要模型的断言，未通过时返回说明
+pub fn check(assertion: &Assertion, answer: &str) -> std::result::Result<(), String> {
+    match assertion {
+        Assertion::Contains(text) if answer.contains(text.as_str()) => Ok(()),
+        Assertion::Contains(text) => Err(format!("contains: 回答中没有 {:?}", text)),
+        Assertion::NotContains(text) if !answer.contains(text.as_str()) => Ok(()),
+        Assertion::NotContains(text) => Err(format!("not_contains: 回答中出现了 {:?}", text)),
+        Assertion::Regex(pattern) => match Regex::new(pattern) {
+            Ok(re) if re.is_match(answer.trim()) => Ok(()),
+            Ok(_) => Err(format!("regex: 回答不匹配 {}", pattern)),
+            Err(e) => Err(format!("regex: 无效的正则表达式 {}: {}", pattern, e)),
+        },
+        Assertion::JsonPath { path, equals } => {
+            let json = extract_json(answer).ok_or_else(|| "json_path: 回答中没有 JSON".to_string())?;
+            let value = json_path(&json, path).map_err(|e| format!("json_path: {}", e))?;
+            match (value, equals) {
+                (None, _) => Err(format!("json_path: {} 不存在", path)),
+                (Some(actual), Some(expected)) if actual != expected => {
+                    Err(format!("json_path: {} 为 {}，期望 {}", path, actual, expected))
+                }
+                _ => Ok(()),
+            }
+        }
+        Assertion::Llm(_) => Ok(()),
+    }
+}
+
+/// 取出回答中的 JSON（整段、代码块或第一个 {…} / […]）
+fn extract_json(answer: &str) -> Option<Value> {
+    let trimmed = answer.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+    if let Some(start) = trimmed.find("```") {
+        let body = &trimmed[start + 3..];
+        let body = body.split_once('\n').map(|(_, rest)| rest).unwrap_or(body);
+        if let Some(end) = body.find("```") {
+            if let Ok(value) = serde_json::from_str(body[..end].trim()) {
+                return Some(value);
+            }
+        }
+    }
+    for (open, close) in [('{', '}'), ('[', ']')] {
+        if let (Some(start), Some(end)) = (trimmed.find(open), trimmed.rfind(close)) {
+            if start < end {
+                if let Ok(value) = serde_json::from_str(&trimmed[start..=end]) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// 按 `$.a.b[0]` 形式的路径取值，路径不存在时返回 None
+fn json_path<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>> {
+    let rest = path.trim().strip_prefix('$').ok_or_else(|| anyhow!("路径须以 $ 开头: {}", path))?;
+    let mut current = value;
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err(anyhow!("路径中有空的字段名: {}", path));
+                }
+                current.get(&key)
+            }
+            '[' => {
+                let mut index = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    index.push(c);
+                }
+                let index = index.trim();
+                match index.parse::<usize>() {
+                    Ok(i) => current.get(i),
+                    Err(_) => current.get(index.trim_matches(['"', '\''])),
+                }
+            }
+            _ => return Err(anyhow!("无效的路径: {}", path)),
+        };
+        match next {
+            Some(v) => current = v,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+/// 基线：每条用例上一次的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    #[serde(default)]
+    pub cases: BTreeMap<String, BaselineEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub passed: bool,
+    pub latency_ms: u64,
+    pub tokens: u64,
+}
+
+impl Baseline {
+    pub fn from_results(results: &[CaseResult]) -> Self {
+        Self {
+            cases: results
+                .iter()
+                .map(|r| {
+                    let entry = BaselineEntry {
+                        passed: r.passed,
+                        latency_ms: r.latency_ms,
+                        tokens: r.tokens,
+                    };
+                    (r.name.clone(), entry)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("读取基线文件 {} 失败", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("解析基线文件 {} 失败", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("写入基线文件 {} 失败", path.display()))
+    }
+}
+
+/// 相对基线的变化百分比（基线为 0 时返回 None）
+pub fn percent_change(baseline: u64, current: u64) -> Option<f64> {
+    (baseline > 0).then(|| (current as f64 - baseline as f64) / baseline as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::llm::mock::MockProvider;
+    use std::sync::Arc;
+
+    const SUITE: &str = r#"
+name: 基础回归
+cases:
+  - name: 算术
+    prompt: 1+1 等于几？
+    mock_response: "2"
+    assert:
+      - contains: "2"
+      - regex: '^\d+$'
+      - llm: 回答正确
+  - name: 结构化
+    prompt: 输出 JSON
+    mock_response: "```json\n{\"items\": [{\"id\": 7}]}\n```"
+    assert:
+      - json_path: { path: "$.items[0].id", equals: 7 }
+      - not_contains: 错误
+  - name: 回显
+    prompt: 你好
+    assert:
+      - contains: 再见
+"#;
+
+    #[test]
+    fn test_check_assertions() {
+        assert!(check(&Assertion::Contains("北京".into()), "首都是北京").is_ok());
+        assert!(check(&Assertion::NotContains("北京".into()), "首都是北京").is_err());
+        assert!(check(&Assertion::Regex(r"^\d+$".into()), " 42\n").is_ok());
+        assert!(check(&Assertion::Regex("(".into()), "x").is_err());
+
+        let answer = "结果如下：{\"a\": {\"b\": [1, {\"c\": \"x\"}]}}";
+        let path = |path: &str, equals: Option<Value>| Assertion::JsonPath { path: path.into(), equals };
+        assert!(check(&path("$.a.b[1].c", Some(Value::from("x"))), answer).is_ok());
+        assert!(check(&path("$.a.b[0]", Some(Value::from(2))), answer).is_err());
+        assert!(check(&path("$.a.missing", None), answer).is_err());
+        assert!(check(&path("$.a", None), "没有 JSON").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_suite_with_mock() {
+        let suite = Suite::parse(SUITE).unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "mock".to_string();
+        config.channel.incognito_channels = vec!["eval".to_string()];
+
+        let mut mock = MockProvider::new();
+        for case in &suite.cases {
+            if let Some(ref reply) = case.mock_response {
+                mock = mock.with_response(&case.prompt, reply.clone());
+            }
+        }
+        let agent = Agent::new(config, Some("eval:test".to_string()))
+            .await
+            .unwrap()
+            .with_llm_provider("mock", Arc::new(mock));
+
+        let results = run(&agent, &suite, false).await;
+        assert!(results[0].passed, "{:?}", results[0].failures);
+        assert_eq!(results[0].skipped, 1);
+        assert!(results[0].tokens > 0);
+        assert!(results[1].passed, "{:?}", results[1].failures);
+        assert!(!results[2].passed);
+        assert_eq!(results[2].answer, "[mock] 你好");
+
+        let baseline = Baseline::from_results(&results);
+        let path = temp_dir.path().join("baseline.json");
+        baseline.save(&path).unwrap();
+        let loaded = Baseline::load(&path).unwrap();
+        assert!(loaded.cases["算术"].passed);
+        assert!(!loaded.cases["回显"].passed);
+        assert_eq!(percent_change(100, 150), Some(50.0));
+        assert_eq!(percent_change(0, 10), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! English texts
+
+pub const TEXTS: &[(&str, &str)] = &[
+    ("common.on", "on"),
+    ("common.off", "off"),
+    ("common.error", "❌ Error: {error}"),
+    // 会话命令
+    ("cmd.unsupported", "This command is not supported"),
+    ("cmd.turn_cancelled", "⏹ Reply cancelled"),
+    ("cmd.cancel_none", "No reply in progress"),
+    ("cmd.cleared", "🧹 Conversation context cleared."),
+    ("cmd.status", "📊 Status\n\nSession ID: {session}\nContext messages: {context}\nModel: {model}\nIncognito: {incognito}"),
+    ("cmd.pinned", "📌 Pinned, {count} in total"),
+    ("cmd.pin_failed", "❌ Failed to pin: {error}"),
+    ("cmd.unpinned", "Unpinned: {content}"),
+    ("cmd.unpin_missing", "There is no pin #{index}"),
+    ("cmd.unpin_failed", "❌ Failed to unpin: {error}"),
+    ("cmd.pins_empty", "Nothing pinned yet"),
+    ("cmd.instructions_cleared", "Session instructions cleared"),
+    ("cmd.instructions_set", "📝 Session instructions set: {text}"),
+    ("cmd.set_failed", "❌ Failed to set: {error}"),
+    ("cmd.incognito_on", "🕶 Incognito on: new messages won't be saved to the conversation history"),
+    ("cmd.incognito_off", "Incognito off"),
+    ("cmd.persona_switched", "🎭 Switched persona: {name}"),
+    ("cmd.persona_default", "Default persona restored"),
+    ("cmd.switch_failed", "❌ Failed to switch: {error}"),
+    ("cmd.personas_none", "No personas configured. Add them under [personas.<name>] in the config file"),
+    ("cmd.personas_current", "🎭 Current persona: {name}"),
+    ("cmd.personas_hint", "\nUse /persona <name> to switch, /persona default to restore the default"),
+    ("cmd.research_usage", "Usage: /research <question>"),
+    ("cmd.bestof_usage", "Usage: /bestof [n] <question> (3 samples by default)"),
+    ("cmd.research_failed", "❌ Research failed: {error}"),
+    ("cmd.title_current", "📝 Session title: {title}\n\nUse /title <title> to change it"),
+    ("cmd.title_none", "This session has no title yet, use /title <title> to set one"),
+    ("cmd.title_set", "📝 Session title set: {title}"),
+    ("cmd.model_current", "🤖 Current model: {model}"),
+    ("cmd.model_default", "Default model restored: {model}"),
+    ("cmd.model_switched", "🤖 Switched model: {model}"),
+    ("cmd.providers", "Available providers: {providers}\nCurrent model: {model}"),
+    ("cmd.sessions_disabled", "Session storage is not enabled"),
+    ("cmd.sessions_empty", "No sessions yet"),
+    ("cmd.sessions_header", "💬 Recent sessions ({count})"),
+    ("cmd.sessions_line", "{id} messages {messages}, tokens {tokens}, {time}"),
+    ("cmd.sessions_title", "\n  📝 {title}"),
+    ("cmd.usage", "📈 Usage\n\nSessions: {sessions}\nMessages: {messages}\nTool calls: {tool_calls}\nTokens: {tokens}"),
+    ("cmd.usage_personas", "\n\nBy persona:"),
+    ("cmd.usage_persona_line", "\n  {persona}: {tokens} tokens"),
+    ("cmd.usage_session", "\n\nThis session: messages {messages}, tool calls {tool_calls}, tokens {tokens}"),
+    ("cmd.jobs_empty", "No scheduled jobs"),
+    ("cmd.jobs_header", "⏰ Scheduled jobs ({count})"),
+    ("cmd.jobs_line", "{id} {name} [{status}] next: {next}"),
+    ("citations.header", "Sources:"),
+    // 通知
+    ("notice.busy", "⏳ {provider} is busy, retrying in {secs}s (attempt {attempt})…"),
+    ("notice.progress", "⚙️ {tool} running…\n{message}"),
+    // Telegram
+    ("telegram.help", "🤖 *Nanobot Help*\n\nCommands:\n/help - Show this help\n/start - Start chatting\n/clear - Clear the conversation context\n/status - Show status\n/pin - Pin content\n/unpin - Unpin\n/pins - Show pins\n/instruct - Set instructions for this session\n/cancel - Cancel the reply in progress\n/incognito - Incognito mode (don't save the conversation)\n/verbose - Show execution details after each reply\n/research - Deep research\n/bestof - Sample several answers and reply with the most consistent one\n/title - Show or set the session title\n/onthisday - Look back at a day's notes and conversations\n\nJust send a message to chat with the AI."),
+    ("telegram.admin_help", "Admin commands:\n/model — Show or switch the model (default restores the default)\n/provider — Show or switch the provider\n/sessions — List recent sessions\n/usage — Show token usage\n/jobs — List scheduled jobs (--all includes completed one-shot jobs)\n/job — Pause, resume or delete a scheduled job\n/memories — Review, approve or reject pending memories\n/broadcast — Broadcast a message to all Telegram chats"),
+    ("telegram.start", "👋 Hi! I'm Nanobot, your personal AI assistant.\n\nJust send a message to get started."),
+    ("telegram.unpin_usage", "Usage: /unpin <number>, see /pins for numbers"),
+    ("telegram.incognito_usage", "Usage: /incognito [on|off]"),
+    ("telegram.admin_only", "⛔ Only admins can use this command."),
+    ("telegram.unauthorized", "⛔ You are not allowed to use this bot."),
+    ("telegram.broadcast_usage", "Usage: /broadcast <message>"),
+    ("telegram.broadcast_list_failed", "❌ Failed to list chats: {error}"),
+    ("telegram.broadcast_done", "📣 Broadcast to {sent} chats ({failed} failed)"),
+    ("telegram.page", "{header} (page {page}/{pages})"),
+    ("telegram.prev_page", "◀ Previous"),
+    ("telegram.next_page", "Next ▶"),
+    // status 命令
+    ("status.title", "🤖 Nanobot Status\n"),
+    ("status.config", "📁 Config:"),
+    ("status.default_provider", "  Default provider: {value}"),
+    ("status.default_model", "  Default model: {value}"),
+    ("status.max_context", "  Max context: {value}"),
+    ("status.providers", "\n🧠 LLM providers:"),
+    ("status.provider_health", "\n📈 Provider availability (last 24h):"),
+    ("status.provider_health_empty", "  No probes recorded yet"),
+    ("status.provider_health_line", "  {name}: {rate}% available ({successes}/{checks}), avg latency {latency}"),
+    ("status.provider_last_ok", "    Last probe: ✅ {time}, {latency} ms"),
+    ("status.provider_last_failed", "    Last probe: ❌ {time}, {error}"),
+    ("status.channels", "\n📡 Channels:"),
+    ("status.configured", "  ✅ {name}"),
+    ("status.not_configured", "  ❌ {name} (not configured)"),
+    ("status.health", "\n🩺 Channel health (updated {age}s ago):"),
+    ("status.health_line", "  {name}: {state}, in {inbound} / out {outbound}, errors {errors}, restarts {restarts}"),
+    ("status.last_activity", "    Last activity: {time}"),
+    ("status.last_error", "    Last error: {error}"),
+    ("status.outbox", "\n📮 Delivery:"),
+    ("status.outbox_empty", "  No records"),
+    ("status.outbox_line", "  {channel}: delivered {delivered}, pending {pending}, failed {failed}"),
+    ("status.sessions", "\n💬 Sessions:"),
+    ("status.session_count", "  Sessions: {count}"),
+    ("status.message_count", "  Messages: {total} (user {user}, assistant {assistant})"),
+    ("status.tool_calls", "  Tool calls: {count}"),
+    ("status.tokens", "  Tokens: {count}"),
+    ("status.tools", "\n🔧 Tools:"),
+    ("status.web_search", "Web search"),
+    ("status.memory", "\n💾 Memory:"),
+    ("status.workspace", "  Workspace: {path}"),
+    ("status.max_memories", "  Max memories: {count}"),
+    ("status.embeddings", "\n🧮 Embeddings:"),
+    ("status.embedding_ok", "  ✅ {name} ({model})"),
+    ("status.embedding_default_model", "default model"),
+    ("status.embedding_error", "  ❌ {backend}: {error}"),
+    ("config.schema_written", "✅ JSON Schema written to {path}"),
+    ("config.unknown_keys", "⚠️ {count} unknown key(s) in {path} (they have no effect):"),
+    ("config.check_ok", "✅ {path} looks good"),
+    ("models.header", "\n📋 {provider} ({count} models)"),
+    ("models.failed", "\n❌ {provider}: {error}"),
+    ("models.col_id", "Model"),
+    ("models.col_context", "Context"),
+    ("models.col_price", "Price (input/output, USD per 1M tokens)"),
+    ("models.hint", "\n⭐ marks the current default model. Results are cached for 24 hours; use --refresh to query again"),
+    ("trash.empty", "Trash is empty"),
+    ("trash.header", "🗑️ Trash ({count} entries):\n"),
+    ("trash.retention", "\nEntries older than {days} days are removed automatically; use trash restore <ID> to restore"),
+    ("trash.restored", "✅ Restored to {path}"),
+    ("backup.created", "✅ Backed up {files} files ({size} KB) to {path}"),
+    ("backup.secrets_excluded", "Secrets in the config were not included; fill them in again after restoring (or use --include-secrets)"),
+    ("backup.secrets_encrypted", "🔒 Secrets in the config were encrypted with the vault passphrase; the same passphrase is needed to restore them"),
+    ("backup.restored", "✅ Restored {files} files\n  Workspace: {workspace}\n  Config: {config}"),
+    ("backup.workspace_rewritten", "workspace_path in the config was changed to this machine's workspace {workspace}"),
+    ("backup.secrets_restored", "🔓 Secrets in the config were restored"),
+    ("backup.secrets_failed", "⚠️ Could not restore secrets: {error}; fill them in the config file again"),
+    ("postprocess.truncated", "…(truncated)"),
+    ("cmd.verbose_on", "🔍 Verbose on: replies will show tool timings, tokens and model"),
+    ("cmd.verbose_off", "Verbose off"),
+    ("cmd.verbose_usage", "Usage: /verbose [on|off]"),
+    ("verbose.tokens", "{tokens} tokens"),
+    ("cmd.job_usage", "Usage: /job <pause|resume|delete> <job ID>"),
+    ("cmd.admin_only", "⛔ Only admins can use this command."),
+    ("cmd.job_paused", "⏸ Paused job: {name}"),
+    ("cmd.job_resumed", "▶️ Resumed job: {name}"),
+    ("cmd.job_deleted", "🗑 Deleted job: {name}"),
+    ("cmd.job_failed", "Failed: {error}"),
+    ("cmd.status_degraded", "\n⚠️ Database unavailable, running in memory (changes stay in memory until it reconnects): {stores}"),
+    ("status.store_jobs", "scheduled jobs"),
+    ("status.store_sessions", "sessions"),
+    ("db.file", "🗄️ {path}"),
+    ("db.encrypted", "  Encrypted, skipped (run nanobot vault unlock first)"),
+    ("db.failed", "  ❌ Maintenance failed: {error}"),
+    ("db.integrity_ok", "  ✅ Integrity check passed"),
+    ("db.integrity_failed", "  ❌ Integrity check found {count} problem(s), not compacted:"),
+    ("db.size", "  File size: {before} → {after}"),
+    ("db.table", "  {name}: {rows} rows, {size}"),
+    ("token.created", "✅ Created token {name}:"),
+    ("token.created_hint", "Save it now, it will not be shown again. Send it as the header Authorization: Bearer <token>"),
+    ("token.revoked", "🗑 Revoked token {name}"),
+    ("token.none", "No API tokens yet; create one with nanobot token create <name> --scope <scope>"),
+    ("token.header", "🔑 API tokens ({count}):\n"),
+    ("token.line", "  {name}  [{source}]  scopes {scopes}  created {created}  expires {expires}  last used {last_used}"),
+    ("token.expired", "expired"),
+    ("token.never", "never"),
+    ("status.hint", "\nRun `nanobot agent` for an interactive chat\nRun `nanobot gateway` to start the gateway"),
+    ("notify.reminder", "⏰ Reminder: {text}"),
+    ("notify.job_failed", "❌ Failed: {error}"),
+    ("notify.budget_warning", "⚠️ {scope} {period} budget {percent}% used: ${spent} / ${limit}"),
+    ("notify.budget_exceeded", "⛔ {scope} {period} budget exceeded: ${spent} / ${limit}"),
+    ("notify.period_daily", "daily"),
+    ("notify.period_monthly", "monthly"),
+    ("notify.watch_update", "📂 {watch}: {action} {path}"),
+    ("notify.watch_created", "new file"),
+    ("notify.watch_modified", "file modified"),
+    ("notify.watch_removed", "file removed"),
+    ("memory_review.empty", "No memories pending review"),
+    ("memory_review.title", "🧠 Memories pending review ({count}):"),
+    ("memory_review.line", "#{id} [{owner}] {key}: {value} ({category}, importance {importance})"),
+    ("memory_review.shared", "shared"),
+    ("memory_review.hint", "Use /memories approve <id|all> to approve, /memories reject <id|all> to reject"),
+    ("memory_review.approved", "✅ Approved {count} memories: {keys}"),
+    ("memory_review.rejected", "🗑️ Rejected {count} memories: {keys}"),
+    ("memory_review.usage", "Usage: /memories [pending], /memories approve <id...|all>, /memories reject <id...|all>"),
+    ("onthisday.question", "What did I do that day? Please give a brief recap based on the notes and conversations."),
+    ("onthisday.empty", "📭 No notes or conversations on {date}"),
+    ("onthisday.unavailable", "Memory is disabled or incognito mode is on, nothing to look back at"),
+    ("onthisday.failed", "❌ Failed to read: {error}"),
+];
@@ -0,0 +1,98 @@
+//! 界面文本本地化
+//!
+//! 命令行输出、通道命令回复等面向用户的文本按键从语言包中取出，语言由 `[ui] language` 选择，
+//! 启动时通过 [`set_language`] 设置。当前语言缺少某个键时回退到简体中文，仍然没有时返回键本身。
+//! 文本中的 `{name}` 由 [`t!`](crate::t) 的同名参数替换
+
+mod en;
+mod zh_cn;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::config::Language;
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+lazy_static::lazy_static! {
+    static ref BUNDLES: HashMap<Language, HashMap<&'static str, &'static str>> = HashMap::from([
+        (Language::ZhCn, zh_cn::TEXTS.iter().copied().collect()),
+        (Language::En, en::TEXTS.iter().copied().collect()),
+    ]);
+}
+
+/// 设置界面语言（只在启动时设置一次，之后的调用被忽略）
+pub fn set_language(language: Language) {
+    let _ = LANGUAGE.set(language);
+}
+
+/// 当前界面语言
+pub fn language() -> Language {
+    LANGUAGE.get().copied().unwrap_or_default()
+}
+
+/// 指定语言中键对应的文本
+pub fn lookup(language: Language, key: &str) -> &str {
+    BUNDLES
+        .get(&language)
+        .and_then(|b| b.get(key))
+        .or_else(|| BUNDLES[&Language::ZhCn].get(key))
+        .copied()
+        .unwrap_or(key)
+}
+
+/// 替换文本中的 `{name}` 参数
+pub fn format(text: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// 按当前语言取文本：`t!("cmd.cleared")`、`t!("cmd.pinned", count = 3)`
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::lookup($crate::i18n::language(), $key).to_string()
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::lookup($crate::i18n::language(), $key),
+            &[$((stringify!($name), $value.to_string())),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// 文本中的参数名
+    fn params(text: &str) -> HashSet<&str> {
+        text.split('{').skip(1).filter_map(|s| s.split_once('}')).map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_bundles_consistent() {
+        let zh = &BUNDLES[&Language::ZhCn];
+        let en = &BUNDLES[&Language::En];
+        assert_eq!(zh.len(), zh_cn::TEXTS.len(), "简体中文语言包中有重复的键");
+        assert_eq!(en.len(), en::TEXTS.len(), "英文语言包中有重复的键");
+        for (key, text) in zh {
+            let translated = en.get(key).unwrap_or_else(|| panic!("英文语言包缺少 {}", key));
+            assert_eq!(params(text), params(translated), "{} 的参数不一致", key);
+        }
+        assert_eq!(zh.len(), en.len());
+    }
+
+    #[test]
+    fn test_lookup_and_format() {
+        assert_eq!(lookup(Language::En, "cmd.cleared"), "🧹 Conversation context cleared.");
+        assert_eq!(lookup(Language::En, "missing.key"), "missing.key");
+        assert_eq!(
+            format(lookup(Language::En, "telegram.broadcast_done"), &[("sent", "3".to_string()), ("failed", "0".to_string())]),
+            "📣 Broadcast to 3 chats (0 failed)"
+        );
+        // 未设置时使用简体中文
+        assert_eq!(t!("cmd.pinned", count = 2), "📌 已置顶，当前共 2 条");
+    }
+}
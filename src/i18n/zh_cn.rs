@@ -0,0 +1,178 @@
+//! 简体中文文本
+
+pub const TEXTS: &[(&str, &str)] = &[
+    ("common.on", "开"),
+    ("common.off", "关"),
+    ("common.error", "❌ 错误: {error}"),
+    // 会话命令
+    ("cmd.unsupported", "不支持该命令"),
+    ("cmd.turn_cancelled", "⏹ 已取消本轮回复"),
+    ("cmd.cancel_none", "当前没有进行中的回复"),
+    ("cmd.cleared", "🧹 对话上下文已清空。"),
+    ("cmd.status", "📊 状态信息\n\n会话 ID: {session}\n上下文消息数: {context}\n模型: {model}\n无痕模式: {incognito}"),
+    ("cmd.pinned", "📌 已置顶，当前共 {count} 条"),
+    ("cmd.pin_failed", "❌ 置顶失败: {error}"),
+    ("cmd.unpinned", "已取消置顶: {content}"),
+    ("cmd.unpin_missing", "没有第 {index} 条置顶"),
+    ("cmd.unpin_failed", "❌ 取消置顶失败: {error}"),
+    ("cmd.pins_empty", "暂无置顶内容"),
+    ("cmd.instructions_cleared", "已清除会话指令"),
+    ("cmd.instructions_set", "📝 已设置会话指令: {text}"),
+    ("cmd.set_failed", "❌ 设置失败: {error}"),
+    ("cmd.incognito_on", "🕶 已开启无痕模式：之后的消息不会保存到对话历史"),
+    ("cmd.incognito_off", "已关闭无痕模式"),
+    ("cmd.persona_switched", "🎭 已切换角色: {name}"),
+    ("cmd.persona_default", "已恢复默认角色"),
+    ("cmd.switch_failed", "❌ 切换失败: {error}"),
+    ("cmd.personas_none", "未配置角色，可在配置文件的 [personas.<名称>] 中添加"),
+    ("cmd.personas_current", "🎭 当前角色: {name}"),
+    ("cmd.personas_hint", "\n使用 /persona <名称> 切换，/persona default 恢复默认"),
+    ("cmd.research_usage", "用法: /research <问题>"),
+    ("cmd.bestof_usage", "用法: /bestof [次数] <问题>（默认采样 3 次）"),
+    ("cmd.research_failed", "❌ 调研失败: {error}"),
+    ("cmd.title_current", "📝 会话标题: {title}\n\n使用 /title <标题> 修改"),
+    ("cmd.title_none", "当前会话还没有标题，使用 /title <标题> 设置"),
+    ("cmd.title_set", "📝 已设置会话标题: {title}"),
+    ("cmd.model_current", "🤖 当前模型: {model}"),
+    ("cmd.model_default", "已恢复默认模型: {model}"),
+    ("cmd.model_switched", "🤖 已切换模型: {model}"),
+    ("cmd.providers", "可用提供商: {providers}\n当前模型: {model}"),
+    ("cmd.sessions_disabled", "未启用会话存储"),
+    ("cmd.sessions_empty", "暂无会话记录"),
+    ("cmd.sessions_header", "💬 最近会话（{count} 个）"),
+    ("cmd.sessions_line", "{id} 消息 {messages}，令牌 {tokens}，{time}"),
+    ("cmd.sessions_title", "\n  📝 {title}"),
+    ("cmd.usage", "📈 用量统计\n\n会话数: {sessions}\n消息数: {messages}\n工具调用: {tool_calls}\n令牌用量: {tokens}"),
+    ("cmd.usage_personas", "\n\n按角色:"),
+    ("cmd.usage_persona_line", "\n  {persona}: {tokens} 令牌"),
+    ("cmd.usage_session", "\n\n当前会话: 消息 {messages}，工具调用 {tool_calls}，令牌 {tokens}"),
+    ("cmd.jobs_empty", "暂无定时任务"),
+    ("cmd.jobs_header", "⏰ 定时任务（{count} 个）"),
+    ("cmd.jobs_line", "{id} {name} [{status}] 下次: {next}"),
+    ("citations.header", "来源："),
+    // 通知
+    ("notice.busy", "⏳ {provider} 繁忙，{secs} 秒后自动重试（第 {attempt} 次）…"),
+    ("notice.progress", "⚙️ {tool} 执行中…\n{message}"),
+    // Telegram
+    ("telegram.help", "🤖 *Nanobot 帮助*\n\n可用命令:\n/help - 显示此帮助\n/start - 开始对话\n/clear - 清空对话上下文\n/status - 查看状态\n/pin - 置顶内容\n/unpin - 取消置顶\n/pins - 查看置顶\n/instruct - 设置本会话指令\n/cancel - 取消正在进行的回复\n/incognito - 无痕模式（不保存对话）\n/verbose - 回复后显示执行详情\n/research - 深度调研\n/bestof - 多次采样后给出最一致的回答\n/title - 查看或设置会话标题\n/onthisday - 回顾某天的日记和对话\n\n直接发送消息即可与 AI 对话。"),
+    ("telegram.admin_help", "管理员命令:\n/model — 查看或切换模型（default 恢复默认）\n/provider — 查看或切换提供商\n/sessions — 列出最近的会话\n/usage — 查看令牌用量\n/jobs — 列出定时任务（--all 包括已完成的一次性任务）\n/job — 暂停、恢复或删除定时任务\n/memories — 查看、批准或拒绝待审核的记忆\n/broadcast — 向所有 Telegram 会话广播消息"),
+    ("telegram.start", "👋 你好！我是 Nanobot，你的个人 AI 助手。\n\n直接发送消息即可开始对话。"),
+    ("telegram.unpin_usage", "用法: /unpin <序号>，序号见 /pins"),
+    ("telegram.incognito_usage", "用法: /incognito [on|off]"),
+    ("telegram.admin_only", "⛔ 仅管理员可使用此命令。"),
+    ("telegram.unauthorized", "⛔ 你无权使用此 Bot。"),
+    ("telegram.broadcast_usage", "用法: /broadcast <消息>"),
+    ("telegram.broadcast_list_failed", "❌ 获取会话列表失败: {error}"),
+    ("telegram.broadcast_done", "📣 已广播到 {sent} 个会话（失败 {failed} 个）"),
+    ("telegram.page", "{header}（第 {page}/{pages} 页）"),
+    ("telegram.prev_page", "◀ 上一页"),
+    ("telegram.next_page", "下一页 ▶"),
+    // status 命令
+    ("status.title", "🤖 Nanobot 状态\n"),
+    ("status.config", "📁 配置:"),
+    ("status.default_provider", "  默认提供商: {value}"),
+    ("status.default_model", "  默认模型: {value}"),
+    ("status.max_context", "  最大上下文: {value}"),
+    ("status.providers", "\n🧠 LLM 提供商:"),
+    ("status.provider_health", "\n📈 提供商可用性（近 24 小时）:"),
+    ("status.provider_health_empty", "  暂无探测记录"),
+    ("status.provider_health_line", "  {name}: 可用率 {rate}%（{successes}/{checks}），平均延迟 {latency}"),
+    ("status.provider_last_ok", "    最近探测: ✅ {time}，{latency} ms"),
+    ("status.provider_last_failed", "    最近探测: ❌ {time}，{error}"),
+    ("status.channels", "\n📡 通道:"),
+    ("status.configured", "  ✅ {name}"),
+    ("status.not_configured", "  ❌ {name}（未配置）"),
+    ("status.health", "\n🩺 通道运行状态（{age} 秒前更新）:"),
+    ("status.health_line", "  {name}: {state}，收 {inbound} / 发 {outbound}，错误 {errors}，重启 {restarts}"),
+    ("status.last_activity", "    最近收发: {time}"),
+    ("status.last_error", "    最近错误: {error}"),
+    ("status.outbox", "\n📮 消息投递:"),
+    ("status.outbox_empty", "  暂无记录"),
+    ("status.outbox_line", "  {channel}: 已投递 {delivered}，待投递 {pending}，失败 {failed}"),
+    ("status.sessions", "\n💬 会话:"),
+    ("status.session_count", "  会话数: {count}"),
+    ("status.message_count", "  消息数: {total}（用户 {user}，助手 {assistant}）"),
+    ("status.tool_calls", "  工具调用: {count}"),
+    ("status.tokens", "  令牌用量: {count}"),
+    ("status.tools", "\n🔧 工具:"),
+    ("status.web_search", "Web 搜索"),
+    ("status.memory", "\n💾 内存:"),
+    ("status.workspace", "  工作目录: {path}"),
+    ("status.max_memories", "  最大记忆数: {count}"),
+    ("status.embeddings", "\n🧮 向量嵌入:"),
+    ("status.embedding_ok", "  ✅ {name}（{model}）"),
+    ("status.embedding_default_model", "默认模型"),
+    ("status.embedding_error", "  ❌ {backend}: {error}"),
+    ("config.schema_written", "✅ JSON Schema 已写入 {path}"),
+    ("config.unknown_keys", "⚠️ 配置文件 {path} 中有 {count} 个未知的配置项（不会生效）："),
+    ("config.check_ok", "✅ 配置文件 {path} 检查通过"),
+    ("models.header", "\n📋 {provider}（{count} 个模型）"),
+    ("models.failed", "\n❌ {provider}: {error}"),
+    ("models.col_id", "模型"),
+    ("models.col_context", "上下文"),
+    ("models.col_price", "价格（输入/输出，美元/百万令牌）"),
+    ("models.hint", "\n⭐ 为当前默认模型。结果缓存 24 小时，使用 --refresh 重新查询"),
+    ("trash.empty", "回收站为空"),
+    ("trash.header", "🗑️ 回收站（{count} 个条目）:\n"),
+    ("trash.retention", "\n超过 {days} 天的条目会被自动清理，使用 trash restore <ID> 恢复"),
+    ("trash.restored", "✅ 已恢复到 {path}"),
+    ("backup.created", "✅ 已备份 {files} 个文件（{size} KB）到 {path}"),
+    ("backup.secrets_excluded", "配置中的密钥未包含在备份中，恢复后需重新填写（或使用 --include-secrets）"),
+    ("backup.secrets_encrypted", "🔒 配置中的密钥已使用 vault 口令加密保存，恢复时需要同一口令"),
+    ("backup.restored", "✅ 已恢复 {files} 个文件\n  工作目录: {workspace}\n  配置文件: {config}"),
+    ("backup.workspace_rewritten", "配置中的 workspace_path 已改为本机工作目录 {workspace}"),
+    ("backup.secrets_restored", "🔓 已恢复配置中的密钥"),
+    ("backup.secrets_failed", "⚠️ 未能恢复密钥: {error}，请在配置文件中重新填写"),
+    ("postprocess.truncated", "…（已截断）"),
+    ("cmd.verbose_on", "🔍 已开启执行详情：回复后显示工具耗时、令牌数和模型"),
+    ("cmd.verbose_off", "已关闭执行详情"),
+    ("cmd.verbose_usage", "用法: /verbose [on|off]"),
+    ("verbose.tokens", "{tokens} 令牌"),
+    ("cmd.job_usage", "用法: /job <pause|resume|delete> <任务 ID>"),
+    ("cmd.admin_only", "⛔ 仅管理员可使用此命令。"),
+    ("cmd.job_paused", "⏸ 已暂停任务: {name}"),
+    ("cmd.job_resumed", "▶️ 已恢复任务: {name}"),
+    ("cmd.job_deleted", "🗑 已删除任务: {name}"),
+    ("cmd.job_failed", "操作失败: {error}"),
+    ("cmd.status_degraded", "\n⚠️ 数据库不可用，以内存模式运行（恢复连接前的变更只保存在内存中）: {stores}"),
+    ("status.store_jobs", "定时任务"),
+    ("status.store_sessions", "会话"),
+    ("db.file", "🗄️ {path}"),
+    ("db.encrypted", "  已加密，跳过（先运行 nanobot vault unlock）"),
+    ("db.failed", "  ❌ 维护失败: {error}"),
+    ("db.integrity_ok", "  ✅ 完整性检查通过"),
+    ("db.integrity_failed", "  ❌ 完整性检查发现 {count} 个问题，未整理:"),
+    ("db.size", "  文件大小: {before} → {after}"),
+    ("db.table", "  {name}: {rows} 行，{size}"),
+    ("token.created", "✅ 已创建令牌 {name}："),
+    ("token.created_hint", "请立即保存，令牌不会再次显示。请求时使用请求头 Authorization: Bearer <令牌>"),
+    ("token.revoked", "🗑 已吊销令牌 {name}"),
+    ("token.none", "暂无 API 令牌，使用 nanobot token create <名称> --scope <权限> 创建"),
+    ("token.header", "🔑 API 令牌（{count} 个）:\n"),
+    ("token.line", "  {name}  [{source}]  权限 {scopes}  创建 {created}  过期 {expires}  最近使用 {last_used}"),
+    ("token.expired", "已过期"),
+    ("token.never", "永不"),
+    ("status.hint", "\n使用 `nanobot agent` 启动交互式对话\n使用 `nanobot gateway` 启动网关服务"),
+    ("notify.reminder", "⏰ 提醒: {text}"),
+    ("notify.job_failed", "❌ 执行失败: {error}"),
+    ("notify.budget_warning", "⚠️ {scope} {period}预算已用 {percent}%：${spent} / ${limit}"),
+    ("notify.budget_exceeded", "⛔ {scope} {period}预算已超出：${spent} / ${limit}"),
+    ("notify.period_daily", "今日"),
+    ("notify.period_monthly", "本月"),
+    ("notify.watch_update", "📂 {watch}: {action} {path}"),
+    ("notify.watch_created", "新文件"),
+    ("notify.watch_modified", "文件已修改"),
+    ("notify.watch_removed", "文件已删除"),
+    ("memory_review.empty", "没有待审核的记忆"),
+    ("memory_review.title", "🧠 待审核的记忆（{count} 条）:"),
+    ("memory_review.line", "#{id} [{owner}] {key}: {value}（{category}，重要性 {importance}）"),
+    ("memory_review.shared", "共享"),
+    ("memory_review.hint", "用 /memories approve <序号|all> 批准，/memories reject <序号|all> 拒绝"),
+    ("memory_review.approved", "✅ 已批准 {count} 条记忆: {keys}"),
+    ("memory_review.rejected", "🗑️ 已拒绝 {count} 条记忆: {keys}"),
+    ("memory_review.usage", "用法: /memories [pending]、/memories approve <序号...|all>、/memories reject <序号...|all>"),
+    ("onthisday.question", "那天我都做了什么？请根据日记和对话简要回顾。"),
+    ("onthisday.empty", "📭 {date} 没有日常笔记和对话记录"),
+    ("onthisday.unavailable", "未启用内存系统或当前为无痕模式，无法回顾"),
+    ("onthisday.failed", "❌ 读取失败: {error}"),
+];
@@ -4,19 +4,17 @@
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::json;
-use std::sync::Arc;
 
-use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, Tool, ToolCall};
+use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 /// Anthropic API 响应
 #[derive(Debug, Deserialize)]
 struct AnthropicResponse {
     id: String,
-    #[serde(rename = "type")]
-    response_type: String,
-    role: String,
     content: Vec<AnthropicContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     usage: Option<AnthropicUsage>,
@@ -41,7 +39,7 @@ struct AnthropicUsage {
 pub struct AnthropicProvider {
     api_key: String,
     base_url: String,
-    timeout_secs: u64,
+    client: reqwest::Client,
 }
 
 impl AnthropicProvider {
@@ -49,11 +47,21 @@ impl AnthropicProvider {
         Self {
             api_key,
             base_url: base_url.unwrap_or_else(|| "https://api.anthropic.com/v1".to_string()),
-            timeout_secs: timeout_secs.unwrap_or(60),
+            client: super::build_http_client(&ProviderConfig {
+                timeout_secs: timeout_secs.unwrap_or(60),
+                ..Default::default()
+            })
+            .expect("创建 HTTP 客户端失败"),
         }
     }
 
-    fn build_api_url(&self, model: &str) -> String {
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验、连接池）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
+
+    fn build_api_url(&self) -> String {
         // Anthropic 使用 /messages API
         format!("{}/messages", self.base_url.trim_end_matches("/"))
     }
@@ -65,11 +73,16 @@ impl LlmProvider for AnthropicProvider {
         "anthropic"
     }
 
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_secs))
-            .build()?;
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url.trim_end_matches('/')))
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01");
+        Ok(super::models::parse_openai_compatible(&super::models::fetch_json(request, "Anthropic").await?))
+    }
 
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         // 构建消息
         let messages: Vec<_> = request
             .messages
@@ -96,7 +109,13 @@ impl LlmProvider for AnthropicProvider {
 
         // 添加 temperature
         if let Some(temp) = request.temperature {
-            body["temperature"] = temp;
+            body["temperature"] = temp.into();
+        }
+        if let Some(top_p) = request.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(stop) = &request.stop {
+            body["stop_sequences"] = json!(stop);
         }
 
         // 添加工具（如果需要）
@@ -104,8 +123,9 @@ impl LlmProvider for AnthropicProvider {
             body["tools"] = json!(tools);
         }
 
-        let response = client
-            .post(self.build_api_url(&request.model))
+        super::debug::record_request(&body);
+        let response = self.client
+            .post(self.build_api_url())
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
@@ -113,12 +133,13 @@ impl LlmProvider for AnthropicProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("Anthropic", response).await?;
         if !response.status().is_success() {
             let error_text = response.text().await?;
             return Err(anyhow!("Anthropic API 错误: {}", error_text));
         }
 
-        let response_data: AnthropicResponse = response.json().await?;
+        let response_data: AnthropicResponse = super::debug::read_json(response).await?;
 
         // 解析响应内容
         let content = response_data
@@ -126,7 +147,7 @@ impl LlmProvider for AnthropicProvider {
             .first()
             .ok_or_else(|| anyhow!("Empty response from Anthropic"))?;
 
-        let message = match &content.content_type {
+        let message = match content.content_type.as_str() {
             "text" => Message::assistant(content.text.as_ref().unwrap_or(&String::new())),
             "tool_use" => {
                 // 处理工具调用
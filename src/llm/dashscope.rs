@@ -7,9 +7,9 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
-use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, Usage};
+use crate::config::ProviderConfig;
 
 pub struct DashScopeProvider {
     api_key: String,
@@ -32,9 +32,10 @@ impl DashScopeProvider {
         }
     }
 
-    /// 获取默认模型
-    pub fn default_model() -> &'static str {
-        "qwen-turbo"
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
     }
 }
 
@@ -49,6 +50,7 @@ impl LlmProvider for DashScopeProvider {
 
         let body = DashScopeRequest::from(request);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -58,13 +60,14 @@ impl LlmProvider for DashScopeProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("DashScope", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("DashScope API 错误: {} - {}", status, text));
         }
 
-        let completion: DashScopeResponse = response.json().await?;
+        let completion: DashScopeResponse = super::debug::read_json(response).await?;
         
         if completion.output.choices.is_empty() {
             return Err(anyhow!("DashScope 返回空响应"));
@@ -121,6 +124,12 @@ struct DashScopeParameters {
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     result_format: Option<String>,
 }
 
@@ -129,7 +138,6 @@ struct DashScopeParameters {
 struct DashScopeResponse {
     output: DashScopeOutput,
     usage: Option<Usage>,
-    request_id: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -140,9 +148,7 @@ struct DashScopeOutput {
 
 #[derive(Debug, Deserialize)]
 struct DashScopeChoice {
-    index: u32,
     message: DashScopeResponseMessage,
-    finish_reason: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -169,6 +175,10 @@ impl From<ChatRequest> for DashScopeRequest {
             parameters: DashScopeParameters {
                 temperature: req.temperature,
                 max_tokens: req.max_tokens,
+                top_p: req.top_p,
+                stop: req.stop,
+                // DashScope 不支持 frequency_penalty
+                presence_penalty: req.presence_penalty,
                 result_format: Some("message".to_string()),
             },
         }
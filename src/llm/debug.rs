@@ -0,0 +1,292 @@
+//! LLM 请求/响应调试日志
+//!
+//! 启用 `[llm] debug_log_dir` 或 `--debug-llm` 后，每次调用都会把请求与响应
+//! （或错误信息）写成一个带序号的 JSON 文件，密钥类字段会被脱敏。
+//! 除通用的请求参数外，还记录提供商实际发送的请求体（`sent_body`）和原始响应体（`raw_response`）
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+use super::{ChatRequest, ChatResponse, LlmProvider};
+use super::models::ModelInfo;
+
+/// 需要脱敏的字段名（小写比较）
+const SENSITIVE_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "authorization",
+    "x-api-key",
+    "token",
+    "secret",
+    "password",
+];
+
+tokio::task_local! {
+    /// 当前调用中提供商实际收发的内容
+    static CAPTURE: Arc<Mutex<Capture>>;
+}
+
+#[derive(Default)]
+struct Capture {
+    sent_body: Option<Value>,
+    raw_response: Option<String>,
+}
+
+/// 记录提供商实际发送的请求体（只在启用调试日志的调用中生效）
+pub(crate) fn record_request(body: &impl Serialize) {
+    let _ = CAPTURE.try_with(|capture| {
+        capture.lock().unwrap().sent_body = serde_json::to_value(body).ok();
+    });
+}
+
+/// 读取并解析 JSON 响应体，启用调试日志时同时保留原始响应体
+pub(crate) async fn read_json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let text = response.text().await?;
+    let _ = CAPTURE.try_with(|capture| {
+        capture.lock().unwrap().raw_response = Some(text.clone());
+    });
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// 调试日志目录（各提供商共享序号）
+pub struct DebugLog {
+    dir: PathBuf,
+    seq: AtomicU64,
+}
+
+impl DebugLog {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Arc<Self>> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        // 从目录中已有的最大序号继续，避免重启后覆盖旧文件
+        let seq = next_sequence(&dir);
+
+        Ok(Arc::new(Self {
+            dir,
+            seq: AtomicU64::new(seq),
+        }))
+    }
+
+    async fn write_entry(&self, provider: &str, entry: Value) {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let path = self.dir.join(format!("{:06}-{}.json", seq, provider));
+
+        let content = match serde_json::to_string_pretty(&redact(entry)) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("序列化 LLM 调试日志失败: {}", e);
+                return;
+            }
+        };
+
+        match tokio::fs::write(&path, content).await {
+            Ok(_) => debug!("LLM 调试日志已写入: {}", path.display()),
+            Err(e) => warn!("写入 LLM 调试日志失败 {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// 记录请求/响应的提供商包装
+pub struct DebugLoggingProvider {
+    inner: Arc<dyn LlmProvider>,
+    log: Arc<DebugLog>,
+}
+
+impl DebugLoggingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, log: Arc<DebugLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for DebugLoggingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let request_json = json!({
+            "model": request.model,
+            "messages": request.messages,
+            "tools": request.tools,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "top_p": request.top_p,
+            "stop": request.stop,
+            "presence_penalty": request.presence_penalty,
+            "frequency_penalty": request.frequency_penalty,
+        });
+
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let capture = Arc::new(Mutex::new(Capture::default()));
+        let result = CAPTURE.scope(capture.clone(), self.inner.chat(request)).await;
+        let Capture { sent_body, raw_response } = std::mem::take(&mut *capture.lock().unwrap());
+        // 原始响应体尽量按 JSON 记录，便于脱敏和阅读
+        let raw_response = raw_response.map(|text| serde_json::from_str(&text).unwrap_or(Value::String(text)));
+
+        let response_json = match &result {
+            Ok(response) => json!({
+                "model": response.model,
+                "message": response.message,
+                "usage": response.usage.as_ref().map(|u| json!({
+                    "prompt_tokens": u.prompt_tokens,
+                    "completion_tokens": u.completion_tokens,
+                    "total_tokens": u.total_tokens,
+                })),
+            }),
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+
+        self.log.write_entry(self.inner.name(), json!({
+            "provider": self.inner.name(),
+            "started_at": started_at.to_rfc3339(),
+            "duration_ms": timer.elapsed().as_millis() as u64,
+            "request": request_json,
+            "sent_body": sent_body,
+            "response": response_json,
+            "raw_response": raw_response,
+        }))
+        .await;
+
+        result
+    }
+
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+/// 目录中下一个可用序号
+fn next_sequence(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    name.split('-').next()?.parse::<u64>().ok()
+                })
+                .max()
+                .map(|n| n + 1)
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+}
+
+/// 递归脱敏：敏感字段的值替换为 "***"，字符串中的 Bearer 令牌也会被遮盖
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let lower = k.to_lowercase();
+                    if SENSITIVE_KEYS.contains(&lower.as_str()) && !v.is_null() {
+                        (k, Value::String("***".to_string()))
+                    } else {
+                        (k, redact(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        Value::String(s) => Value::String(redact_bearer(&s)),
+        other => other,
+    }
+}
+
+/// 遮盖文本中的 `Bearer <token>`
+fn redact_bearer(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("Bearer ") {
+        let (head, tail) = rest.split_at(pos + "Bearer ".len());
+        result.push_str(head);
+        let token_len = tail
+            .find(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+            .unwrap_or(tail.len());
+        if token_len > 0 {
+            result.push_str("***");
+        }
+        rest = &tail[token_len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let value = json!({
+            "api_key": "sk-123",
+            "headers": { "Authorization": "Bearer sk-abc" },
+            "messages": [{ "content": "header was Bearer sk-xyz ok" }],
+            "max_tokens": 100,
+        });
+
+        let redacted = redact(value);
+        assert_eq!(redacted["api_key"], "***");
+        assert_eq!(redacted["headers"]["Authorization"], "***");
+        assert_eq!(redacted["messages"][0]["content"], "header was Bearer *** ok");
+        assert_eq!(redacted["max_tokens"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_logs_sent_body_and_raw_response() {
+        use axum::{routing::post, Json, Router};
+
+        async fn handler(Json(_): Json<Value>) -> Json<Value> {
+            Json(json!({
+                "id": "1",
+                "model": "m",
+                "system_fingerprint": "fp-1",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                }],
+            }))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/v1/chat/completions", post(handler));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = crate::config::ProviderConfig {
+            base_url: Some(format!("http://{}/v1", addr)),
+            ..Default::default()
+        };
+        let inner = super::super::LlmProviderFactory::create("vllm", &config).unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let provider = DebugLoggingProvider::new(inner, DebugLog::new(temp_dir.path()).unwrap());
+
+        let mut request = ChatRequest::new("m", vec![super::super::Message::user("hi")]);
+        request.top_p = Some(0.9);
+        request.stop = Some(vec!["END".to_string()]);
+        provider.chat(request).await.unwrap();
+
+        let path = std::fs::read_dir(temp_dir.path()).unwrap().next().unwrap().unwrap().path();
+        let entry: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let top_p = entry["sent_body"]["top_p"].as_f64().unwrap();
+        assert!((top_p - 0.9).abs() < 1e-6);
+        assert_eq!(entry["sent_body"]["stop"], json!(["END"]));
+        assert_eq!(entry["raw_response"]["system_fingerprint"], "fp-1");
+    }
+}
@@ -9,6 +9,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 pub struct DeepSeekProvider {
     api_key: String,
@@ -30,6 +32,12 @@ impl DeepSeekProvider {
             client,
         }
     }
+
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -38,11 +46,16 @@ impl LlmProvider for DeepSeekProvider {
         "deepseek"
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "DeepSeek").await
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.base_url);
 
         let body = DeepSeekRequest::from(request);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -51,13 +64,14 @@ impl LlmProvider for DeepSeekProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("DeepSeek", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("DeepSeek API 错误: {} - {}", status, text));
         }
 
-        let completion: DeepSeekResponse = response.json().await?;
+        let completion: DeepSeekResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("DeepSeek 返回空响应"));
@@ -99,6 +113,14 @@ struct DeepSeekRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -128,7 +150,6 @@ struct DeepSeekFunction {
 // DeepSeek API 响应结构
 #[derive(Debug, Deserialize)]
 struct DeepSeekResponse {
-    id: String,
     model: String,
     choices: Vec<DeepSeekChoice>,
     usage: Option<Usage>,
@@ -136,9 +157,7 @@ struct DeepSeekResponse {
 
 #[derive(Debug, Deserialize)]
 struct DeepSeekChoice {
-    index: u32,
     message: DeepSeekResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -183,6 +202,10 @@ impl From<ChatRequest> for DeepSeekRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
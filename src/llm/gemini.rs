@@ -6,59 +6,60 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 /// Gemini API 响应
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     candidates: Option<Vec<GeminiCandidate>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    usageMetadata: Option<GeminiUsage>,
+    usage_metadata: Option<GeminiUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GeminiCandidate {
     #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<GeminiContent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    finishReason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GeminiContent {
     parts: Vec<GeminiPart>,
-    role: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiPart {
     #[serde(skip_serializing_if = "Option::is_none")]
     text: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    functionCall: Option<GeminiFunctionCall>,
+    function_call: Option<GeminiFunctionCall>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct GeminiFunctionCall {
     name: String,
     args: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiUsage {
-    promptTokenCount: u32,
-    candidatesTokenCount: u32,
-    totalTokenCount: u32,
+    prompt_token_count: u32,
+    candidates_token_count: u32,
+    total_token_count: u32,
 }
 
 /// Gemini Provider 实现
 pub struct GeminiProvider {
     api_key: String,
     base_url: String,
-    timeout_secs: u64,
+    client: reqwest::Client,
 }
 
 impl GeminiProvider {
@@ -68,10 +69,20 @@ impl GeminiProvider {
             base_url: base_url.unwrap_or_else(|| {
                 "https://generativelanguage.googleapis.com/v1beta/models".to_string()
             }),
-            timeout_secs: timeout_secs.unwrap_or(60),
+            client: super::build_http_client(&ProviderConfig {
+                timeout_secs: timeout_secs.unwrap_or(60),
+                ..Default::default()
+            })
+            .expect("创建 HTTP 客户端失败"),
         }
     }
 
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验、连接池）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
+
     fn build_api_url(&self, model: &str) -> String {
         format!("{}/{}:generateContent", self.base_url.trim_end_matches("/"), model)
     }
@@ -83,35 +94,48 @@ impl LlmProvider for GeminiProvider {
         "gemini"
     }
 
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_secs))
-            .build()?;
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let request = self.client
+            .get(self.base_url.trim_end_matches('/'))
+            .query(&[("key", self.api_key.as_str()), ("pageSize", "1000")]);
+        let body = super::models::fetch_json(request, "Gemini").await?;
+        Ok(body["models"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| {
+                Some(ModelInfo {
+                    context_length: m.get("inputTokenLimit").and_then(|v| v.as_u64()),
+                    ..ModelInfo::new(m.get("name")?.as_str()?.trim_start_matches("models/"))
+                })
+            })
+            .collect())
+    }
 
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         // 构建内容
         let contents: Vec<_> = request
             .messages
             .iter()
             .filter(|m| m.role != Role::System) // Gemini 处理系统提示的方式不同
             .map(|m| {
-                let parts = if m.content.is_empty() && m.tool_calls.is_some() {
+                let parts = match &m.tool_calls {
                     // 工具调用
-                    vec![GeminiPart {
+                    Some(tool_calls) if m.content.is_empty() => vec![GeminiPart {
                         text: None,
-                        functionCall: Some(GeminiFunctionCall {
-                            name: m.tool_calls.as_ref().unwrap().first()
+                        function_call: Some(GeminiFunctionCall {
+                            name: tool_calls.first()
                                 .map(|tc| tc.function.name.clone())
                                 .unwrap_or_default(),
-                            args: m.tool_calls.as_ref().unwrap().first()
+                            args: tool_calls.first()
                                 .map(|tc| serde_json::from_str(&tc.function.arguments).unwrap_or_default())
                                 .unwrap_or_default(),
                         }),
-                    }]
-                } else {
-                    vec![GeminiPart {
+                    }],
+                    _ => vec![GeminiPart {
                         text: Some(m.content.clone()),
-                        functionCall: None,
-                    }]
+                        function_call: None,
+                    }],
                 };
                 json!({
                     "role": match m.role {
@@ -133,26 +157,40 @@ impl LlmProvider for GeminiProvider {
         // 添加 generationConfig
         let mut config = json!({});
         if let Some(temp) = request.temperature {
-            config["temperature"] = temp;
+            config["temperature"] = temp.into();
         }
         if let Some(max_tokens) = request.max_tokens {
-            config["maxOutputTokens"] = max_tokens;
+            config["maxOutputTokens"] = max_tokens.into();
+        }
+        if let Some(top_p) = request.top_p {
+            config["topP"] = json!(top_p);
+        }
+        if let Some(stop) = &request.stop {
+            config["stopSequences"] = json!(stop);
+        }
+        if let Some(penalty) = request.presence_penalty {
+            config["presencePenalty"] = json!(penalty);
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            config["frequencyPenalty"] = json!(penalty);
         }
         body["generationConfig"] = config;
 
-        let response = client
+        super::debug::record_request(&body);
+        let response = self.client
             .post(self.build_api_url(&request.model))
             .query(&[("key", &self.api_key)])
             .json(&body)
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("Gemini", response).await?;
         if !response.status().is_success() {
             let error_text = response.text().await?;
             return Err(anyhow!("Gemini API 错误: {}", error_text));
         }
 
-        let response_data: GeminiResponse = response.json().await?;
+        let response_data: GeminiResponse = super::debug::read_json(response).await?;
 
         let content = response_data
             .candidates
@@ -164,10 +202,10 @@ impl LlmProvider for GeminiProvider {
 
         Ok(ChatResponse {
             message: Message::assistant(content),
-            usage: response_data.usageMetadata.map(|u| super::Usage {
-                prompt_tokens: u.promptTokenCount,
-                completion_tokens: u.candidatesTokenCount,
-                total_tokens: u.totalTokenCount,
+            usage: response_data.usage_metadata.map(|u| super::Usage {
+                prompt_tokens: u.prompt_token_count,
+                completion_tokens: u.candidates_token_count,
+                total_tokens: u.total_token_count,
             }),
             model: request.model,
         })
@@ -10,6 +10,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 pub struct GroqProvider {
     api_key: String,
@@ -32,9 +34,10 @@ impl GroqProvider {
         }
     }
 
-    /// 获取默认模型
-    pub fn default_model() -> &'static str {
-        "llama-3.1-70b-versatile"
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
     }
 }
 
@@ -44,11 +47,16 @@ impl LlmProvider for GroqProvider {
         "groq"
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "Groq").await
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.base_url);
 
         let body = GroqRequest::from(request);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -57,13 +65,14 @@ impl LlmProvider for GroqProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("Groq", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("Groq API 错误: {} - {}", status, text));
         }
 
-        let completion: GroqResponse = response.json().await?;
+        let completion: GroqResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("Groq 返回空响应"));
@@ -105,6 +114,14 @@ struct GroqRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -134,7 +151,6 @@ struct GroqFunction {
 // Groq API 响应结构
 #[derive(Debug, Deserialize)]
 struct GroqResponse {
-    id: String,
     model: String,
     choices: Vec<GroqChoice>,
     usage: Option<Usage>,
@@ -142,9 +158,7 @@ struct GroqResponse {
 
 #[derive(Debug, Deserialize)]
 struct GroqChoice {
-    index: u32,
     message: GroqResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -180,6 +194,10 @@ impl From<ChatRequest> for GroqRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
@@ -9,31 +9,13 @@ use serde::Deserialize;
 use serde_json::json;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role};
-
-/// MiniMax 提供商配置
-#[derive(Debug, Clone)]
-pub struct MiniMaxConfig {
-    pub api_key: String,
-    pub base_url: Option<String>,
-    pub timeout_secs: Option<u64>,
-}
-
-impl Default for MiniMaxConfig {
-    fn default() -> Self {
-        Self {
-            api_key: String::new(),
-            base_url: Some("https://api.minimax.io/v1".to_string()),
-            timeout_secs: Some(60),
-        }
-    }
-}
+use crate::config::ProviderConfig;
 
 /// MiniMax LLM 提供商
 #[derive(Debug)]
 pub struct MiniMaxProvider {
     client: Client,
     base_url: String,
-    model: String,
     api_key: String,
 }
 
@@ -46,28 +28,20 @@ impl MiniMaxProvider {
 
         let client = Client::builder()
             .timeout(timeout)
-            .default_headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                let auth_value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))
-                    .expect("Invalid API key");
-                headers.insert(reqwest::header::AUTHORIZATION, auth_value);
-                headers
-            })
             .build()
             .expect("Failed to create HTTP client");
 
         Self {
             client,
             base_url,
-            model: "MiniMax-M2.1".to_string(), // 默认模型
             api_key,
         }
     }
 
-    /// 设置模型
-    pub fn with_model(mut self, model: impl Into<String>) -> Self {
-        self.model = model.into();
-        self
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
     }
 }
 
@@ -108,27 +82,35 @@ impl LlmProvider for MiniMaxProvider {
         if let Some(max_tokens) = request.max_tokens {
             body["max_tokens"] = json!(max_tokens);
         }
+        if let Some(top_p) = request.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(stop) = &request.stop {
+            body["stop"] = json!(stop);
+        }
         if let Some(tools) = &request.tools {
             body["tools"] = json!(tools);
         }
 
+        super::debug::record_request(&body);
         let response = self
             .client
             .post(&url)
+            .bearer_auth(&self.api_key)
             .json(&body)
             .send()
             .await
             .map_err(|e| anyhow!("MiniMax API 请求失败: {}", e))?;
 
         // 处理错误响应
+        let response = super::queue::check_rate_limit("MiniMax", response).await?;
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
             tracing::error!("MiniMax API 错误: {}", error_text);
             return Err(anyhow!("MiniMax API 错误: {}", error_text));
         }
 
-        let response_json: MiniMaxResponse = response
-            .json()
+        let response_json: MiniMaxResponse = super::debug::read_json(response)
             .await
             .map_err(|e| anyhow!("解析 MiniMax 响应失败: {}", e))?;
 
@@ -185,9 +167,6 @@ impl LlmProvider for MiniMaxProvider {
 /// MiniMax API 响应
 #[derive(Debug, Deserialize)]
 struct MiniMaxResponse {
-    id: String,
-    object: String,
-    created: u64,
     model: String,
     choices: Vec<MiniMaxChoice>,
     usage: Option<MiniMaxUsage>,
@@ -195,14 +174,11 @@ struct MiniMaxResponse {
 
 #[derive(Debug, Deserialize)]
 struct MiniMaxChoice {
-    index: u32,
     message: MiniMaxMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct MiniMaxMessage {
-    role: String,
     content: Option<String>,
     #[serde(default)]
     tool_calls: Vec<MiniMaxToolCall>,
@@ -211,8 +187,6 @@ struct MiniMaxMessage {
 #[derive(Debug, Deserialize, Clone)]
 struct MiniMaxToolCall {
     id: String,
-    #[serde(rename = "type")]
-    call_type: String,
     function: MiniMaxFunctionCall,
 }
 
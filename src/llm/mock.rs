@@ -0,0 +1,70 @@
+//! 离线模拟提供商
+//!
+//! `agent.default_provider = "mock"` 时注册，不访问网络：按最后一条用户消息返回预设回复，
+//! 没有预设时原样回显。用于 `nanobot eval --mock` 和本地调试，令牌数按字符数粗略估算
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, Usage};
+
+/// 按用户消息返回预设回复的提供商
+#[derive(Default)]
+pub struct MockProvider {
+    responses: HashMap<String, String>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 用户消息（去掉首尾空白后）为 `prompt` 时回复 `reply`
+    pub fn with_response(mut self, prompt: impl AsRef<str>, reply: impl Into<String>) -> Self {
+        self.responses.insert(prompt.as_ref().trim().to_string(), reply.into());
+        self
+    }
+}
+
+/// 粗略估算令牌数（约 4 个字符一个令牌）
+fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let prompt = request
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.role == Role::User)
+            .map(|m| m.content.trim())
+            .unwrap_or_default();
+        let reply = match self.responses.get(prompt) {
+            Some(reply) => reply.clone(),
+            None => format!("[mock] {}", prompt),
+        };
+
+        let prompt_tokens = request.messages.iter().map(|m| estimate_tokens(&m.content)).sum();
+        let completion_tokens = estimate_tokens(&reply);
+        Ok(ChatResponse {
+            message: Message::assistant(reply),
+            usage: Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }),
+            model: request.model,
+        })
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+}
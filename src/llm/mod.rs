@@ -1,6 +1,6 @@
 //! LLM 提供商模块
 //!
-//! 支持多个 LLM 提供商：OpenRouter、DeepSeek、Moonshot/Kimi、MiniMax、vLLM、OpenAI、Anthropic、Google Gemini、Zhipu、DashScope、Groq
+//! 支持多个 LLM 提供商：OpenRouter、DeepSeek、Moonshot/Kimi、MiniMax、vLLM、OpenAI、Anthropic、Google Gemini、Zhipu、DashScope、Groq、SiliconFlow、Together
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -8,14 +8,24 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
 
+pub use crate::config::GenerationParams;
+
 pub mod anthropic;
 pub mod dashscope;
+pub mod debug;
 pub mod deepseek;
 pub mod gemini;
 pub mod groq;
 pub mod minimax;
+pub mod mock;
+pub mod models;
 pub mod moonshot;
 pub mod openrouter;
+pub mod probe;
+pub mod queue;
+pub mod router;
+pub mod siliconflow;
+pub mod together;
 pub mod vllm;
 pub mod zhipu;
 
@@ -114,6 +124,10 @@ pub struct ChatRequest {
     pub tools: Option<Vec<Tool>>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
 }
 
 impl ChatRequest {
@@ -124,20 +138,99 @@ impl ChatRequest {
             tools: None,
             temperature: Some(0.7),
             max_tokens: None,
+            top_p: None,
+            stop: None,
+            presence_penalty: None,
+            frequency_penalty: None,
         }
     }
 
+    /// 应用生成参数，已设置的字段覆盖请求中的值
+    pub fn with_params(mut self, params: &GenerationParams) -> Self {
+        if params.temperature.is_some() {
+            self.temperature = params.temperature;
+        }
+        if params.max_tokens.is_some() {
+            self.max_tokens = params.max_tokens;
+        }
+        if params.top_p.is_some() {
+            self.top_p = params.top_p;
+        }
+        if params.stop.is_some() {
+            self.stop = params.stop.clone();
+        }
+        if params.presence_penalty.is_some() {
+            self.presence_penalty = params.presence_penalty;
+        }
+        if params.frequency_penalty.is_some() {
+            self.frequency_penalty = params.frequency_penalty;
+        }
+        self
+    }
+
     pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
         self.tools = Some(tools);
         self
     }
 
-    pub fn with_temperature(mut self, temp: f32) -> Self {
-        self.temperature = Some(temp);
-        self
+    /// 发送前检查消息序列：系统消息最多一条且必须在最前，tool 消息必须紧跟带对应
+    /// tool_call_id 的助手消息，助手消息不能既没有内容也没有工具调用
+    pub fn validate(&self) -> std::result::Result<(), InvalidRequestError> {
+        let invalid = |index: usize, reason: String| InvalidRequestError { index, reason };
+        // 最近一条助手消息的工具调用 ID（之后只出现 tool 消息时有效）
+        let mut call_ids: Vec<&str> = Vec::new();
+        for (index, message) in self.messages.iter().enumerate() {
+            match message.role {
+                Role::System if index > 0 => {
+                    return Err(invalid(index, "系统消息最多一条且必须是第一条消息".to_string()));
+                }
+                Role::System => {}
+                Role::User => call_ids.clear(),
+                Role::Assistant => {
+                    call_ids = message
+                        .tool_calls
+                        .iter()
+                        .flatten()
+                        .map(|c| c.id.as_str())
+                        .collect();
+                    if call_ids.is_empty() && message.content.trim().is_empty() {
+                        return Err(invalid(index, "助手消息内容为空且没有工具调用".to_string()));
+                    }
+                }
+                Role::Tool => {
+                    let id = message
+                        .tool_call_id
+                        .as_deref()
+                        .ok_or_else(|| invalid(index, "tool 消息缺少 tool_call_id".to_string()))?;
+                    if call_ids.is_empty() {
+                        return Err(invalid(index, format!("tool 消息 {} 前面没有带工具调用的助手消息", id)));
+                    }
+                    if !call_ids.contains(&id) {
+                        return Err(invalid(index, format!("tool 消息的 tool_call_id {} 与前一条助手消息的工具调用不匹配", id)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 请求的消息序列不符合 API 要求（发送前在本地检查，代替提供商返回的 400）
+#[derive(Debug)]
+pub struct InvalidRequestError {
+    /// 出错消息的序号（从 0 开始）
+    pub index: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for InvalidRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "请求消息序列无效（第 {} 条消息）: {}", self.index + 1, self.reason)
     }
 }
 
+impl std::error::Error for InvalidRequestError {}
+
 /// LLM 响应
 #[derive(Debug, Clone)]
 pub struct ChatResponse {
@@ -153,6 +246,80 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
+/// 所有提供商共用的 TLS 配置
+///
+/// 根证书只解析一次，TLS 会话缓存在各客户端之间共享，重连时可以恢复会话
+fn shared_tls_config() -> rustls::ClientConfig {
+    static TLS: std::sync::OnceLock<rustls::ClientConfig> = std::sync::OnceLock::new();
+    TLS.get_or_init(|| {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        let mut tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        tls
+    })
+    .clone()
+}
+
+/// 按提供商配置构建 HTTP 客户端
+///
+/// 应用超时、代理、自定义请求头、TLS 校验和连接池设置。提供商在创建时构建一次并在请求之间复用。
+/// 未配置 `proxy` 时由 reqwest 读取 HTTPS_PROXY / HTTP_PROXY / NO_PROXY 环境变量
+pub fn build_http_client(config: &crate::config::ProviderConfig) -> Result<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &config.extra_headers {
+        let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| anyhow!("无效的请求头名称 '{}': {}", name, e))?;
+        let header_value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| anyhow!("无效的请求头 '{}' 的值: {}", name, e))?;
+        headers.insert(header_name, header_value);
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(config.timeout_secs))
+        .default_headers(headers)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(
+            (config.pool_idle_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.pool_idle_timeout_secs)),
+        );
+
+    if config.keepalive_secs > 0 {
+        let interval = std::time::Duration::from_secs(config.keepalive_secs);
+        builder = builder
+            .tcp_keepalive(interval)
+            .http2_keep_alive_interval(interval)
+            .http2_keep_alive_timeout(std::time::Duration::from_secs(20))
+            .http2_keep_alive_while_idle(true);
+    }
+
+    if let Some(proxy) = config.proxy.as_deref().filter(|p| !p.is_empty()) {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| anyhow!("无效的代理地址 '{}': {}", proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if !config.verify_tls {
+        tracing::warn!("已关闭 TLS 证书校验，仅应在可信网络中使用");
+        builder = builder.danger_accept_invalid_certs(true);
+    } else {
+        builder = builder.use_preconfigured_tls(shared_tls_config());
+    }
+
+    builder
+        .build()
+        .map_err(|e| anyhow!("创建 HTTP 客户端失败: {}", e))
+}
+
 /// LLM 提供商 trait
 #[async_trait]
 pub trait LlmProvider: Send + Sync {
@@ -164,6 +331,11 @@ pub trait LlmProvider: Send + Sync {
     
     /// 检查是否可用
     fn is_available(&self) -> bool;
+
+    /// 查询可用模型（提供商不支持时返回错误）
+    async fn list_models(&self) -> Result<Vec<models::ModelInfo>> {
+        Err(anyhow!("{} 不支持查询模型列表", self.name()))
+    }
 }
 
 /// LLM 提供商工厂
@@ -181,7 +353,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "deepseek" => {
@@ -191,7 +364,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "moonshot" => {
@@ -201,7 +375,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "minimax" => {
@@ -211,7 +386,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     Some(config.timeout_secs),
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "vllm" => {
@@ -221,7 +397,8 @@ impl LlmProviderFactory {
                     config.base_url.clone(),
                     config.timeout_secs,
                     config.default_model.clone(),
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "anthropic" => {
@@ -231,7 +408,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     Some(config.timeout_secs),
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "gemini" => {
@@ -241,7 +419,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     Some(config.timeout_secs),
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "zhipu" => {
@@ -251,7 +430,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "dashscope" => {
@@ -261,7 +441,8 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             "groq" => {
@@ -271,7 +452,30 @@ impl LlmProviderFactory {
                     api_key.clone(),
                     config.base_url.clone(),
                     config.timeout_secs,
-                );
+                )
+                .with_http_config(config)?;
+                Ok(Arc::new(provider))
+            }
+            "siliconflow" => {
+                let api_key = config.api_key.as_ref()
+                    .ok_or_else(|| anyhow!("SiliconFlow 需要 API Key"))?;
+                let provider = siliconflow::SiliconFlowProvider::new(
+                    api_key.clone(),
+                    config.base_url.clone(),
+                    config.timeout_secs,
+                )
+                .with_http_config(config)?;
+                Ok(Arc::new(provider))
+            }
+            "together" => {
+                let api_key = config.api_key.as_ref()
+                    .ok_or_else(|| anyhow!("Together 需要 API Key"))?;
+                let provider = together::TogetherProvider::new(
+                    api_key.clone(),
+                    config.base_url.clone(),
+                    config.timeout_secs,
+                )
+                .with_http_config(config)?;
                 Ok(Arc::new(provider))
             }
             _ => Err(anyhow!("未知的 LLM 提供商: {}", name)),
@@ -389,10 +593,60 @@ impl LlmManager {
             }
         }
 
+        // 注册 SiliconFlow
+        if config.llm.siliconflow.api_key.is_some() {
+            match LlmProviderFactory::create("siliconflow", &config.llm.siliconflow) {
+                Ok(provider) => {
+                    providers.insert("siliconflow".to_string(), provider);
+                }
+                Err(e) => tracing::warn!("无法创建 SiliconFlow 提供商: {}", e),
+            }
+        }
+
+        // 注册 Together
+        if config.llm.together.api_key.is_some() {
+            match LlmProviderFactory::create("together", &config.llm.together) {
+                Ok(provider) => {
+                    providers.insert("together".to_string(), provider);
+                }
+                Err(e) => tracing::warn!("无法创建 Together 提供商: {}", e),
+            }
+        }
+
+        // 离线模拟提供商（评测、本地调试）
+        if config.agent.default_provider == "mock" {
+            providers.insert("mock".to_string(), Arc::new(mock::MockProvider::new()) as Arc<dyn LlmProvider>);
+        }
+
+        // 跳过配置不完整的提供商（如 API Key 为空字符串）
+        providers.retain(|name, provider| {
+            let available = provider.is_available();
+            if !available {
+                tracing::warn!("提供商 {} 配置不完整，已跳过", name);
+            }
+            available
+        });
+
         if providers.is_empty() {
             anyhow::bail!("没有可用的 LLM 提供商，请配置 API Key");
         }
 
+        // 调试模式：记录每次请求/响应
+        if let Some(dir) = &config.llm.debug_log_dir {
+            tracing::info!("LLM 调试日志已启用: {}", dir.display());
+            let log = debug::DebugLog::new(dir)?;
+            for provider in providers.values_mut() {
+                *provider = Arc::new(debug::DebugLoggingProvider::new(provider.clone(), log.clone()));
+            }
+        }
+
+        // 并发限制与限流重试
+        for (name, provider) in providers.iter_mut() {
+            if let Some(provider_config) = config.llm.provider(name) {
+                *provider = Arc::new(queue::QueuedProvider::new(provider.clone(), provider_config));
+            }
+        }
+
         Ok(Self {
             providers,
             default_provider: config.agent.default_provider.clone(),
@@ -413,8 +667,146 @@ impl LlmManager {
         self.get_provider(None)
     }
 
+    /// 注册（或替换）提供商
+    pub fn register(&mut self, name: impl Into<String>, provider: Arc<dyn LlmProvider>) {
+        self.providers.insert(name.into(), provider);
+    }
+
     /// 列出可用提供商
     pub fn list_providers(&self) -> Vec<&str> {
         self.providers.keys().map(|s| s.as_str()).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderConfig;
+
+    #[test]
+    fn test_validate_request() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall { name: "shell".to_string(), arguments: "{}".to_string() },
+        };
+        let valid = ChatRequest::new(
+            "m",
+            vec![
+                Message::system("s"),
+                Message::user("u"),
+                Message::assistant("").with_tool_calls(vec![call.clone()]),
+                Message::tool_result("call_1", "ok"),
+                Message::assistant("done"),
+            ],
+        );
+        assert!(valid.validate().is_ok());
+        assert!(ChatRequest::new("m", vec![]).validate().is_ok());
+
+        let cases = [
+            (vec![Message::user("u"), Message::system("s")], 1, "系统消息"),
+            (vec![Message::system("s"), Message::tool_result("call_1", "ok")], 1, "前面没有"),
+            (
+                vec![Message::assistant("").with_tool_calls(vec![call]), Message::tool_result("call_2", "ok")],
+                1,
+                "call_2",
+            ),
+            (vec![Message::user("u"), Message::assistant(" ")], 1, "内容为空"),
+        ];
+        for (messages, index, reason) in cases {
+            let err = ChatRequest::new("m", messages).validate().unwrap_err();
+            assert_eq!(err.index, index);
+            assert!(err.to_string().contains(reason), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_skip_unavailable_providers() {
+        let mut config = crate::config::Config::default();
+        config.agent.default_provider = "mock".to_string();
+        config.llm.groq.api_key = Some(String::new());
+        config.llm.deepseek.api_key = Some("k".to_string());
+
+        let manager = LlmManager::new(&config).unwrap();
+        let providers = manager.list_providers();
+        assert!(providers.contains(&"deepseek"));
+        assert!(!providers.contains(&"groq"));
+        assert!(manager.get_provider(Some("groq")).is_err());
+    }
+
+    #[test]
+    fn test_build_http_client() {
+        let mut config = ProviderConfig {
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            verify_tls: false,
+            ..Default::default()
+        };
+        config
+            .extra_headers
+            .insert("APP-Code".to_string(), "abc".to_string());
+        assert!(build_http_client(&config).is_ok());
+
+        config.extra_headers.insert("bad header".to_string(), "x".to_string());
+        assert!(build_http_client(&config).is_err());
+    }
+
+    #[test]
+    fn test_build_http_client_pool_settings() {
+        let config: ProviderConfig = toml::from_str(
+            r#"
+            pool_idle_timeout_secs = 0
+            pool_max_idle_per_host = 2
+            keepalive_secs = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.pool_max_idle_per_host, 2);
+        assert!(build_http_client(&config).is_ok());
+
+        let config = ProviderConfig::default();
+        assert_eq!(config.pool_idle_timeout_secs, 90);
+        assert_eq!(config.keepalive_secs, 30);
+        assert!(build_http_client(&config).is_ok());
+        assert!(build_http_client(&config).is_ok());
+    }
+
+    #[test]
+    fn test_generation_params_precedence() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [agent]
+            max_tokens = 1024
+            stop = ["END"]
+
+            [llm.deepseek]
+            api_key = "k"
+            max_tokens = 4096
+            top_p = 0.9
+            "#,
+        )
+        .unwrap();
+
+        let request = ChatRequest::new("m", vec![])
+            .with_params(&config.llm.deepseek.generation)
+            .with_params(&config.agent.generation)
+            .with_params(&GenerationParams {
+                frequency_penalty: Some(0.5),
+                ..Default::default()
+            });
+
+        assert_eq!(request.max_tokens, Some(1024));
+        assert_eq!(request.top_p, Some(0.9));
+        assert_eq!(request.stop, Some(vec!["END".to_string()]));
+        assert_eq!(request.frequency_penalty, Some(0.5));
+        assert_eq!(request.temperature, Some(0.7));
+    }
+
+    #[test]
+    fn test_build_http_client_invalid_proxy() {
+        let config = ProviderConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(build_http_client(&config).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! 提供商模型列表
+//!
+//! 各提供商通过 [`LlmProvider::list_models`](super::LlmProvider::list_models) 查询可用模型，
+//! 结果缓存到工作目录（`<workspace>/models_cache.json`），供 `nanobot models` 命令使用。
+//! 上下文长度和价格只在提供商返回时显示，价格统一换算为每百万令牌的美元价格
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 模型信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    /// 上下文长度（令牌数）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u64>,
+    /// 输入价格（美元 / 百万令牌）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_price: Option<f64>,
+    /// 输出价格（美元 / 百万令牌）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_price: Option<f64>,
+}
+
+impl ModelInfo {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            context_length: None,
+            input_price: None,
+            output_price: None,
+        }
+    }
+}
+
+/// 发送模型列表请求并返回 JSON
+pub async fn fetch_json(request: RequestBuilder, provider: &str) -> Result<Value> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("{} 模型列表请求失败: {} - {}", provider, status, text));
+    }
+    Ok(response.json().await?)
+}
+
+/// 查询 OpenAI 兼容的 `GET {base_url}/models`
+pub async fn fetch_openai_compatible(client: &Client, base_url: &str, api_key: &str, provider: &str) -> Result<Vec<ModelInfo>> {
+    let mut request = client.get(format!("{}/models", base_url.trim_end_matches('/')));
+    if !api_key.is_empty() {
+        request = request.bearer_auth(api_key);
+    }
+    Ok(parse_openai_compatible(&fetch_json(request, provider).await?))
+}
+
+/// 解析 OpenAI 兼容格式的模型列表
+///
+/// 兼容各家的扩展字段：OpenRouter 的 `context_length` 和按令牌计的 `pricing.prompt/completion`，
+/// Together 直接返回数组且 `pricing.input/output` 按百万令牌计，vLLM 的 `max_model_len`，Groq 的 `context_window`
+pub fn parse_openai_compatible(body: &Value) -> Vec<ModelInfo> {
+    let items = body.get("data").unwrap_or(body).as_array().cloned().unwrap_or_default();
+    let mut models: Vec<ModelInfo> = items
+        .iter()
+        .filter_map(|item| {
+            let id = item.get("id")?.as_str()?;
+            let context_length = ["context_length", "max_model_len", "context_window"]
+                .iter()
+                .find_map(|key| item.get(*key)?.as_u64());
+            let pricing = item.get("pricing");
+            let price = |key: &str| pricing?.get(key).and_then(number);
+            let (input_price, output_price) = match (price("prompt"), price("completion")) {
+                (None, None) => (price("input"), price("output")),
+                // 按令牌计价
+                (prompt, completion) => (prompt.map(|p| p * 1e6), completion.map(|p| p * 1e6)),
+            };
+            Some(ModelInfo {
+                id: id.to_string(),
+                context_length,
+                input_price,
+                output_price,
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
+
+/// 数字或数字字符串
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// 模型列表缓存
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ModelCache {
+    #[serde(default)]
+    providers: HashMap<String, CachedModels>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedModels {
+    fetched_at: DateTime<Utc>,
+    models: Vec<ModelInfo>,
+}
+
+impl ModelCache {
+    /// 读取缓存，文件不存在或无法解析时返回空缓存
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 未过期的缓存结果
+    pub fn get(&self, provider: &str, max_age: Duration) -> Option<&[ModelInfo]> {
+        self.providers
+            .get(provider)
+            .filter(|cached| Utc::now() - cached.fetched_at < max_age)
+            .map(|cached| cached.models.as_slice())
+    }
+
+    pub fn insert(&mut self, provider: &str, models: Vec<ModelInfo>) {
+        self.providers.insert(
+            provider.to_string(),
+            CachedModels {
+                fetched_at: Utc::now(),
+                models,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_openai_compatible() {
+        let openrouter = json!({"data": [
+            {"id": "openai/gpt-4o", "context_length": 128000, "pricing": {"prompt": "0.0000025", "completion": "0.00001"}},
+            {"id": "anthropic/claude-3.5-sonnet", "context_length": 200000, "pricing": {"prompt": "0.000003", "completion": "0.000015"}}
+        ]});
+        let models = parse_openai_compatible(&openrouter);
+        assert_eq!(models[0].id, "anthropic/claude-3.5-sonnet");
+        assert_eq!(models[0].context_length, Some(200000));
+        assert!((models[1].input_price.unwrap() - 2.5).abs() < 1e-9);
+        assert!((models[1].output_price.unwrap() - 10.0).abs() < 1e-9);
+
+        let together = json!([{"id": "meta-llama/Llama-3.3-70B-Instruct-Turbo", "context_length": 131072, "pricing": {"input": 0.88, "output": 0.88}}]);
+        assert_eq!(parse_openai_compatible(&together)[0].input_price, Some(0.88));
+
+        let vllm = json!({"object": "list", "data": [{"id": "Qwen/Qwen2.5-7B-Instruct", "max_model_len": 32768}, {"object": "model"}]});
+        assert_eq!(
+            parse_openai_compatible(&vllm),
+            vec![ModelInfo {
+                context_length: Some(32768),
+                ..ModelInfo::new("Qwen/Qwen2.5-7B-Instruct")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_model_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("models_cache.json");
+        let mut cache = ModelCache::load(&path);
+        assert!(cache.get("vllm", Duration::hours(1)).is_none());
+
+        cache.insert("vllm", vec![ModelInfo::new("default")]);
+        cache.save(&path).unwrap();
+        let cache = ModelCache::load(&path);
+        assert_eq!(cache.get("vllm", Duration::hours(1)).unwrap()[0].id, "default");
+        assert!(cache.get("vllm", Duration::zero()).is_none());
+    }
+}
@@ -10,6 +10,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 pub struct MoonshotProvider {
     api_key: String,
@@ -32,9 +34,10 @@ impl MoonshotProvider {
         }
     }
 
-    /// 获取默认模型
-    pub fn default_model() -> &'static str {
-        "moonshot-v1-8k"
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
     }
 
     /// 检查是否需要固定 temperature（如 kimi-k2.5 只支持 temperature=1.0）
@@ -53,6 +56,10 @@ impl LlmProvider for MoonshotProvider {
         "moonshot"
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "Moonshot").await
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.base_url);
 
@@ -61,6 +68,7 @@ impl LlmProvider for MoonshotProvider {
         // 调整 temperature（某些模型有特殊要求）
         body.temperature = self.adjust_temperature(&body.model, request.temperature);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -69,13 +77,14 @@ impl LlmProvider for MoonshotProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("Moonshot", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("Moonshot API 错误: {} - {}", status, text));
         }
 
-        let completion: MoonshotResponse = response.json().await?;
+        let completion: MoonshotResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("Moonshot 返回空响应"));
@@ -117,6 +126,14 @@ struct MoonshotRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -146,7 +163,6 @@ struct MoonshotFunction {
 // Moonshot API 响应结构
 #[derive(Debug, Deserialize)]
 struct MoonshotResponse {
-    id: String,
     model: String,
     choices: Vec<MoonshotChoice>,
     usage: Option<Usage>,
@@ -154,9 +170,7 @@ struct MoonshotResponse {
 
 #[derive(Debug, Deserialize)]
 struct MoonshotChoice {
-    index: u32,
     message: MoonshotResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -192,6 +206,10 @@ impl From<ChatRequest> for MoonshotRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
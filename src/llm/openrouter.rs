@@ -9,6 +9,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 pub struct OpenRouterProvider {
     api_key: String,
@@ -30,6 +32,12 @@ impl OpenRouterProvider {
             client,
         }
     }
+
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -38,11 +46,16 @@ impl LlmProvider for OpenRouterProvider {
         "openrouter"
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "OpenRouter").await
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.base_url);
 
         let body = OpenRouterRequest::from(request);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -53,13 +66,14 @@ impl LlmProvider for OpenRouterProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("OpenRouter", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("OpenRouter API 错误: {} - {}", status, text));
         }
 
-        let completion: OpenRouterResponse = response.json().await?;
+        let completion: OpenRouterResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("OpenRouter 返回空响应"));
@@ -101,6 +115,14 @@ struct OpenRouterRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -130,7 +152,6 @@ struct OpenRouterFunction {
 // OpenRouter API 响应结构
 #[derive(Debug, Deserialize)]
 struct OpenRouterResponse {
-    id: String,
     model: String,
     choices: Vec<OpenRouterChoice>,
     usage: Option<Usage>,
@@ -138,9 +159,7 @@ struct OpenRouterResponse {
 
 #[derive(Debug, Deserialize)]
 struct OpenRouterChoice {
-    index: u32,
     message: OpenRouterResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -176,6 +195,10 @@ impl From<ChatRequest> for OpenRouterRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
@@ -0,0 +1,394 @@
+//! 提供商可用性探测
+//!
+//! gateway 按 `[llm.probe]` 定期向每个提供商的默认模型发一个最小请求（`max_tokens = 1`），
+//! 结果和延迟记录到 `<workspace>/usage.db`，`nanobot status` 据此显示各提供商的可用率。
+//! 连续失败达到阈值的提供商标记为不可用，Agent 按 `fallback` 顺序改用第一个可用的提供商，
+//! 下一次探测成功后恢复
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use super::{ChatRequest, LlmProvider, Message};
+use crate::config::{Config, ProbeConfig};
+
+/// 错误信息最多保留的字符数
+const MAX_ERROR_CHARS: usize = 200;
+
+/// 单次探测结果
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub provider: String,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// 一段时间内的可用性统计
+#[derive(Debug, Clone)]
+pub struct ProviderAvailability {
+    pub provider: String,
+    /// 探测次数
+    pub checks: u64,
+    /// 成功次数
+    pub successes: u64,
+    /// 成功探测的平均延迟
+    pub avg_latency_ms: Option<u64>,
+    /// 最近一次探测
+    pub last: Option<ProbeResult>,
+}
+
+impl ProviderAvailability {
+    /// 可用率（百分比）
+    pub fn success_rate(&self) -> f64 {
+        if self.checks == 0 {
+            return 0.0;
+        }
+        self.successes as f64 * 100.0 / self.checks as f64
+    }
+}
+
+/// 探测记录（用量数据库中的 provider_probes 表）
+#[derive(Clone)]
+pub struct ProbeStore {
+    pool: Pool<Sqlite>,
+}
+
+impl ProbeStore {
+    /// 打开用量数据库
+    pub async fn open(db_path: &str) -> Result<Self> {
+        let pool = crate::db::connect(db_path, 2)
+            .await
+            .context("连接用量数据库失败")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS provider_probes (
+                provider TEXT NOT NULL,
+                ok INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                error TEXT,
+                checked_at TEXT NOT NULL
+            )
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_provider_probes_checked ON provider_probes(checked_at)")
+            .execute(&pool)
+            .await?;
+
+        Ok(Self { pool })
+    }
+
+    /// 记录一次探测
+    pub async fn record(&self, result: &ProbeResult) -> Result<()> {
+        sqlx::query("INSERT INTO provider_probes (provider, ok, latency_ms, error, checked_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(&result.provider)
+            .bind(result.ok)
+            .bind(result.latency_ms as i64)
+            .bind(&result.error)
+            .bind(timestamp(result.checked_at))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 删除早于指定时间的记录，返回删除条数
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM provider_probes WHERE checked_at < ?")
+            .bind(timestamp(before))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 指定时间以来各提供商的可用性（按提供商名排序）
+    pub async fn availability(&self, since: DateTime<Utc>) -> Result<Vec<ProviderAvailability>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT provider, COUNT(*) AS checks, SUM(ok) AS successes,
+                   AVG(CASE WHEN ok THEN latency_ms END) AS avg_latency
+            FROM provider_probes
+            WHERE checked_at >= ?
+            GROUP BY provider
+            ORDER BY provider
+            "#
+        )
+        .bind(timestamp(since))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut summary = Vec::with_capacity(rows.len());
+        for row in rows {
+            let provider: String = row.get("provider");
+            let last = self.last(&provider).await?;
+            summary.push(ProviderAvailability {
+                checks: row.get::<i64, _>("checks") as u64,
+                successes: row.get::<Option<i64>, _>("successes").unwrap_or(0) as u64,
+                avg_latency_ms: row.get::<Option<f64>, _>("avg_latency").map(|ms| ms.round() as u64),
+                last,
+                provider,
+            });
+        }
+        Ok(summary)
+    }
+
+    /// 提供商最近一次探测
+    async fn last(&self, provider: &str) -> Result<Option<ProbeResult>> {
+        let row = sqlx::query(
+            "SELECT ok, latency_ms, error, checked_at FROM provider_probes WHERE provider = ? ORDER BY checked_at DESC LIMIT 1",
+        )
+        .bind(provider)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| ProbeResult {
+            provider: provider.to_string(),
+            ok: row.get("ok"),
+            latency_ms: row.get::<i64, _>("latency_ms") as u64,
+            error: row.get("error"),
+            checked_at: DateTime::parse_from_rfc3339(row.get::<&str, _>("checked_at"))
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_default(),
+        }))
+    }
+}
+
+/// 统一格式的时间戳，按字符串比较即按时间比较
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 各提供商当前的可用状态
+#[derive(Debug, Default)]
+pub struct ProviderHealth {
+    /// 连续失败多少次后视为不可用
+    threshold: u32,
+    /// 备用提供商顺序
+    fallback: Vec<String>,
+    /// 各提供商连续失败次数
+    failures: RwLock<HashMap<String, u32>>,
+}
+
+impl ProviderHealth {
+    pub fn new(config: &ProbeConfig) -> Self {
+        Self {
+            threshold: config.failure_threshold.max(1),
+            fallback: config.fallback.clone(),
+            failures: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 记录探测结果
+    pub fn record(&self, provider: &str, ok: bool) {
+        let mut failures = self.failures.write().unwrap();
+        let count = failures.entry(provider.to_string()).or_insert(0);
+        let was_healthy = *count < self.threshold;
+        *count = if ok { 0 } else { count.saturating_add(1) };
+        match (was_healthy, *count < self.threshold) {
+            (true, false) => warn!("提供商 {} 连续 {} 次探测失败，标记为不可用", provider, count),
+            (false, true) => info!("提供商 {} 已恢复可用", provider),
+            _ => {}
+        }
+    }
+
+    /// 提供商是否可用（尚未探测的视为可用）
+    pub fn is_healthy(&self, provider: &str) -> bool {
+        self.failures
+            .read()
+            .unwrap()
+            .get(provider)
+            .is_none_or(|&count| count < self.threshold)
+    }
+
+    /// 提供商不可用时按顺序返回可用的备用提供商；提供商可用时返回空
+    pub fn failover(&self, provider: &str) -> Vec<&str> {
+        if self.is_healthy(provider) {
+            return Vec::new();
+        }
+        self.fallback
+            .iter()
+            .map(String::as_str)
+            .filter(|&name| name != provider && self.is_healthy(name))
+            .collect()
+    }
+}
+
+/// 探测使用的模型：默认提供商用 Agent 的默认模型，其它提供商用各自配置的默认模型
+pub fn probe_model(config: &Config, provider: &str) -> Option<String> {
+    if provider == config.agent.default_provider {
+        return Some(config.agent.default_model.clone());
+    }
+    config.llm.provider(provider).and_then(|p| p.default_model.clone())
+}
+
+/// 探测目标
+struct Target {
+    name: String,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+/// 后台探测器
+pub struct Prober {
+    targets: Vec<Target>,
+    store: Option<ProbeStore>,
+    health: Arc<ProviderHealth>,
+    interval: Duration,
+    timeout: Duration,
+    history_days: u32,
+}
+
+impl Prober {
+    /// 为已注册的提供商创建探测器，没有默认模型的提供商不探测
+    pub fn new(config: &Config, providers: Vec<(String, Arc<dyn LlmProvider>)>, store: Option<ProbeStore>) -> Self {
+        let probe = &config.llm.probe;
+        let targets = providers
+            .into_iter()
+            .filter_map(|(name, provider)| match probe_model(config, &name) {
+                Some(model) => Some(Target { name, provider, model }),
+                None => {
+                    warn!("提供商 {} 未配置默认模型，不探测可用性", name);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            targets,
+            store,
+            health: Arc::new(ProviderHealth::new(probe)),
+            interval: Duration::from_secs(probe.interval_secs.max(1)),
+            timeout: Duration::from_secs(probe.timeout_secs.max(1)),
+            history_days: probe.history_days,
+        }
+    }
+
+    /// 可用状态句柄（交给 Agent 用于故障切换）
+    pub fn health(&self) -> Arc<ProviderHealth> {
+        self.health.clone()
+    }
+
+    /// 启动后台探测（立即探测一次，之后按间隔探测）
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        crate::crash::spawn("probe", async move {
+            let mut interval = tokio::time::interval(self.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                self.probe_all().await;
+            }
+        })
+    }
+
+    /// 并发探测所有提供商并记录结果
+    async fn probe_all(&self) {
+        let results =
+            futures_util::future::join_all(self.targets.iter().map(|t| probe(t, self.timeout))).await;
+        for result in results {
+            self.health.record(&result.provider, result.ok);
+            match result.error {
+                Some(ref e) => debug!("提供商 {} 探测失败: {}", result.provider, e),
+                None => debug!("提供商 {} 探测成功，延迟 {} ms", result.provider, result.latency_ms),
+            }
+            if let Some(ref store) = self.store {
+                if let Err(e) = store.record(&result).await {
+                    warn!("记录提供商探测结果失败: {}", e);
+                }
+            }
+        }
+
+        if let Some(ref store) = self.store {
+            if self.history_days > 0 {
+                let before = Utc::now() - ChronoDuration::days(self.history_days as i64);
+                if let Err(e) = store.prune(before).await {
+                    warn!("清理过期的探测记录失败: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 发送一个最小请求
+async fn probe(target: &Target, timeout: Duration) -> ProbeResult {
+    let mut request = ChatRequest::new(target.model.clone(), vec![Message::user("ping")]);
+    request.max_tokens = Some(1);
+
+    let checked_at = Utc::now();
+    let started = Instant::now();
+    let error = match tokio::time::timeout(timeout, target.provider.chat(request)).await {
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => Some(e.to_string().chars().take(MAX_ERROR_CHARS).collect()),
+        Err(_) => Some(format!("超时（{} 秒）", timeout.as_secs())),
+    };
+    ProbeResult {
+        provider: target.name.clone(),
+        ok: error.is_none(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+        checked_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover() {
+        let health = ProviderHealth::new(&ProbeConfig {
+            failure_threshold: 2,
+            fallback: vec!["deepseek".to_string(), "openrouter".to_string()],
+            ..Default::default()
+        });
+        assert!(health.failover("openai").is_empty());
+
+        health.record("openai", false);
+        assert!(health.is_healthy("openai"));
+        health.record("openai", false);
+        assert!(!health.is_healthy("openai"));
+        assert_eq!(health.failover("openai"), vec!["deepseek", "openrouter"]);
+
+        // 跳过不可用的备用提供商
+        health.record("deepseek", false);
+        health.record("deepseek", false);
+        assert_eq!(health.failover("openai"), vec!["openrouter"]);
+
+        health.record("openai", true);
+        assert!(health.failover("openai").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_availability() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = ProbeStore::open(&dir.path().join("usage.db").to_string_lossy()).await.unwrap();
+        let now = Utc::now();
+        for (ok, latency_ms, minutes_ago) in [(true, 100, 3), (true, 300, 2), (false, 5000, 1)] {
+            store
+                .record(&ProbeResult {
+                    provider: "deepseek".to_string(),
+                    ok,
+                    latency_ms,
+                    error: (!ok).then(|| "timeout".to_string()),
+                    checked_at: now - ChronoDuration::minutes(minutes_ago),
+                })
+                .await
+                .unwrap();
+        }
+
+        let summary = store.availability(now - ChronoDuration::hours(1)).await.unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].checks, 3);
+        assert_eq!(summary[0].successes, 2);
+        assert_eq!(summary[0].avg_latency_ms, Some(200));
+        let last = summary[0].last.as_ref().unwrap();
+        assert!(!last.ok);
+        assert_eq!(last.error.as_deref(), Some("timeout"));
+
+        assert_eq!(store.prune(now - ChronoDuration::seconds(90)).await.unwrap(), 2);
+    }
+}
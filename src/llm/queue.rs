@@ -0,0 +1,458 @@
+//! 提供商请求队列与限流重试
+//!
+//! 每个提供商包装为 [`QueuedProvider`]：按 `max_concurrent_requests` 限制并发，
+//! 超出的请求按会话轮流获得名额（[`with_request_session`] 设置请求所属的会话），
+//! 避免一个会话的大量请求让其他会话长时间排队。
+//! 收到 429 时按提供商返回的 Retry-After 等待后重试。等待期间通过
+//! [`with_busy_notifier`] 设置的回调通知调用方（如向聊天推送"提供商繁忙"）
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use super::{ChatRequest, ChatResponse, LlmProvider};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
+
+/// 提供商限流错误（HTTP 429）
+#[derive(Debug)]
+pub struct RateLimitError {
+    pub provider: String,
+    /// 提供商要求的等待时间
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl std::fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} API 限流 (429): {}", self.provider, self.message)
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+/// 响应为 429 时转换为 [`RateLimitError`]，其他响应原样返回
+pub async fn check_rate_limit(provider: &str, response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Ok(response);
+    }
+
+    let header_wait = retry_after_from_headers(response.headers());
+    let message = response.text().await.unwrap_or_default();
+    Err(RateLimitError {
+        provider: provider.to_string(),
+        retry_after: header_wait.or_else(|| retry_after_from_body(&message)),
+        message,
+    }
+    .into())
+}
+
+/// 从响应头解析等待时间（retry-after-ms、Retry-After 秒数或 HTTP 日期）
+fn retry_after_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return secs_to_duration(ms / 1000.0);
+    }
+
+    let value = header("retry-after")?;
+    if let Ok(secs) = value.parse::<f64>() {
+        return secs_to_duration(secs);
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+/// 从响应体解析等待时间
+///
+/// 支持 Gemini 的 `error.details[].retryDelay`、`retry_after` 字段，
+/// 以及 OpenAI 兼容接口错误信息中的 "try again in 1.5s"
+fn retry_after_from_body(body: &str) -> Option<Duration> {
+    if let Ok(json) = serde_json::from_str::<Value>(body) {
+        let delay = json
+            .pointer("/error/details")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find_map(|d| d.get("retryDelay").and_then(Value::as_str).and_then(parse_duration));
+        if delay.is_some() {
+            return delay;
+        }
+
+        for pointer in ["/retry_after", "/error/retry_after"] {
+            if let Some(secs) = json.pointer(pointer).and_then(Value::as_f64) {
+                return secs_to_duration(secs);
+            }
+        }
+    }
+
+    let lower = body.to_lowercase();
+    let pos = lower.find("try again in ")?;
+    parse_duration(&lower[pos + "try again in ".len()..])
+}
+
+/// 解析 "30s"、"1.5 s"、"20ms"、"2m"、"10 seconds" 之类的时长（无单位按秒）
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let value: f64 = text[..end].trim_end_matches('.').parse().ok()?;
+    let unit = text[end..].trim_start();
+
+    let secs = if unit.starts_with("ms") || unit.starts_with("millisecond") {
+        value / 1000.0
+    } else if unit.starts_with("min") || (unit.starts_with('m') && !unit.starts_with("ms")) {
+        value * 60.0
+    } else {
+        value
+    };
+    secs_to_duration(secs)
+}
+
+/// 秒数转换为时长：负数按 0，超出范围的按最大值（随后因超过等待上限放弃重试），非有限值忽略
+fn secs_to_duration(secs: f64) -> Option<Duration> {
+    if !secs.is_finite() {
+        return None;
+    }
+    Some(Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX))
+}
+
+/// 限流等待回调：提供商名称、等待时间、第几次重试
+pub type BusyNotifier = Arc<dyn Fn(&str, Duration, u32) + Send + Sync>;
+
+tokio::task_local! {
+    static BUSY_NOTIFIER: BusyNotifier;
+}
+
+/// 在 `fut` 执行期间，提供商限流等待时调用 `notifier`
+pub async fn with_busy_notifier<F: Future>(notifier: BusyNotifier, fut: F) -> F::Output {
+    BUSY_NOTIFIER.scope(notifier, fut).await
+}
+
+tokio::task_local! {
+    static REQUEST_SESSION: String;
+}
+
+/// 在 `fut` 执行期间发出的请求按 `session` 排队（未设置的请求共用一个队列）
+pub async fn with_request_session<F: Future>(session: impl Into<String>, fut: F) -> F::Output {
+    REQUEST_SESSION.scope(session.into(), fut).await
+}
+
+/// 按会话轮流分配名额的并发限制器
+///
+/// 名额用完后，每个会话有自己的等待队列，释放的名额依次交给下一个有请求在等待的会话
+pub struct FairLimiter {
+    state: std::sync::Mutex<FairState>,
+}
+
+struct FairState {
+    /// 空闲名额
+    available: usize,
+    /// 有请求在等待的会话（轮转顺序）
+    order: VecDeque<String>,
+    /// 各会话等待中的请求
+    waiters: HashMap<String, VecDeque<oneshot::Sender<FairPermit>>>,
+}
+
+/// 并发名额，释放时交给下一个等待的请求
+pub struct FairPermit {
+    limiter: Option<Arc<FairLimiter>>,
+}
+
+impl Drop for FairPermit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.limiter.take() {
+            limiter.release();
+        }
+    }
+}
+
+impl FairLimiter {
+    pub fn new(permits: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: std::sync::Mutex::new(FairState {
+                available: permits,
+                order: VecDeque::new(),
+                waiters: HashMap::new(),
+            }),
+        })
+    }
+
+    /// 获取名额，没有空闲名额时在会话的队列中等待
+    pub async fn acquire(self: &Arc<Self>, session: &str) -> FairPermit {
+        let receiver = {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            if state.available > 0 {
+                state.available -= 1;
+                return FairPermit { limiter: Some(self.clone()) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let queue = state.waiters.entry(session.to_string()).or_default();
+            if queue.is_empty() {
+                state.order.push_back(session.to_string());
+            }
+            queue.push_back(sender);
+            receiver
+        };
+        // 等待中的发送端只会在交出名额时移除，持有 Arc 期间限制器不会被释放
+        receiver.await.expect("限制器在等待期间被释放")
+    }
+
+    /// 名额交给下一个会话的第一个等待者，没有等待者时放回
+    fn release(self: &Arc<Self>) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        while let Some(session) = state.order.pop_front() {
+            let Some(queue) = state.waiters.get_mut(&session) else {
+                continue;
+            };
+            let sender = queue.pop_front();
+            if queue.is_empty() {
+                state.waiters.remove(&session);
+            } else {
+                state.order.push_back(session);
+            }
+            let Some(sender) = sender else {
+                continue;
+            };
+            match sender.send(FairPermit { limiter: Some(self.clone()) }) {
+                Ok(()) => return,
+                // 等待者已取消，名额交给下一个
+                Err(mut permit) => {
+                    permit.limiter = None;
+                }
+            }
+        }
+        state.available += 1;
+    }
+}
+
+/// 带并发限制和限流重试的提供商
+pub struct QueuedProvider {
+    inner: Arc<dyn LlmProvider>,
+    /// 并发名额（None 表示不限制）
+    permits: Option<Arc<FairLimiter>>,
+    max_retries: u32,
+    max_wait: Duration,
+}
+
+impl QueuedProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, config: &ProviderConfig) -> Self {
+        Self {
+            inner,
+            permits: (config.max_concurrent_requests > 0)
+                .then(|| FairLimiter::new(config.max_concurrent_requests)),
+            max_retries: config.rate_limit_retries,
+            max_wait: Duration::from_secs(config.rate_limit_max_wait_secs),
+        }
+    }
+
+    /// 未指定 Retry-After 时的退避时间：2、4、8… 秒
+    fn backoff(attempt: u32) -> Duration {
+        Duration::from_secs(1 << attempt.clamp(1, 6))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for QueuedProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        // 消息序列有误时直接返回，不占用名额也不发送
+        request.validate()?;
+
+        // 重试等待期间保持占用名额，避免排队中的请求继续触发限流
+        let _permit = match self.permits {
+            Some(ref permits) => {
+                let session = REQUEST_SESSION.try_with(String::clone).unwrap_or_default();
+                Some(permits.acquire(&session).await)
+            }
+            None => None,
+        };
+
+        let mut attempt = 0;
+        loop {
+            let err = match self.inner.chat(request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            let Some(limit) = err.downcast_ref::<RateLimitError>() else {
+                return Err(err);
+            };
+            if attempt >= self.max_retries {
+                return Err(err);
+            }
+
+            let wait = limit.retry_after.unwrap_or_else(|| Self::backoff(attempt + 1));
+            if wait > self.max_wait {
+                warn!("{} 要求等待 {} 秒，超过上限，放弃重试", self.name(), wait.as_secs());
+                return Err(err);
+            }
+
+            attempt += 1;
+            warn!(
+                "{} 限流，{:.1} 秒后第 {} 次重试",
+                self.name(),
+                wait.as_secs_f64(),
+                attempt
+            );
+            let _ = BUSY_NOTIFIER.try_with(|notify| notify(self.name(), wait, attempt));
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_retry_after_parsing() {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "3".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::from_secs(3)));
+        headers.insert("retry-after-ms", "1500".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::from_millis(1500)));
+
+        let gemini = r#"{"error":{"code":429,"details":[{"@type":"type.googleapis.com/google.rpc.RetryInfo","retryDelay":"27s"}]}}"#;
+        assert_eq!(retry_after_from_body(gemini), Some(Duration::from_secs(27)));
+        let openai = r#"{"error":{"message":"Rate limit reached. Please try again in 20ms. Visit ..."}}"#;
+        assert_eq!(retry_after_from_body(openai), Some(Duration::from_millis(20)));
+        assert_eq!(retry_after_from_body("rate limited"), None);
+    }
+
+    #[test]
+    fn test_retry_after_out_of_range() {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "1e300".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::MAX));
+        headers.insert("retry-after", "-5".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), Some(Duration::ZERO));
+        headers.insert("retry-after-ms", "inf".parse().unwrap());
+        assert_eq!(retry_after_from_headers(&headers), None);
+
+        assert_eq!(retry_after_from_body(r#"{"retry_after":1e300}"#), Some(Duration::MAX));
+        let huge = format!("please try again in {}s", "9".repeat(400));
+        assert_eq!(retry_after_from_body(&huge), None);
+    }
+
+    /// 前两次返回 429 的提供商
+    struct FlakyProvider {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                return Err(RateLimitError {
+                    provider: "flaky".to_string(),
+                    retry_after: Some(Duration::from_millis(10)),
+                    message: "busy".to_string(),
+                }
+                .into());
+            }
+            Ok(ChatResponse {
+                message: Message::assistant("ok"),
+                usage: None,
+                model: "m".to_string(),
+            })
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fair_limiter_rotates_sessions() {
+        let limiter = FairLimiter::new(1);
+        let held = limiter.acquire("a").await;
+        let granted = Arc::new(Mutex::new(Vec::new()));
+
+        // 会话 a 先排了三个请求，b 后排一个
+        let mut tasks = Vec::new();
+        for name in ["a1", "a2", "a3", "b1"] {
+            let limiter = limiter.clone();
+            let granted = granted.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = limiter.acquire(&name[..1]).await;
+                granted.lock().unwrap().push(name);
+            }));
+            tokio::task::yield_now().await;
+        }
+        // 取消的等待者不占用名额
+        let cancelled = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter.acquire("c").await;
+            }
+        });
+        tokio::task::yield_now().await;
+        cancelled.abort();
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*granted.lock().unwrap(), vec!["a1", "b1", "a2", "a3"]);
+        assert_eq!(limiter.state.lock().unwrap().available, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limited_requests() {
+        let inner = Arc::new(FlakyProvider { calls: AtomicU32::new(0) });
+        let provider = QueuedProvider::new(inner.clone(), &ProviderConfig::default());
+
+        let notices = Arc::new(Mutex::new(Vec::new()));
+        let sink = notices.clone();
+        let notifier: BusyNotifier = Arc::new(move |name: &str, _wait: Duration, attempt: u32| {
+            sink.lock().unwrap().push(format!("{}#{}", name, attempt));
+        });
+
+        let response = with_busy_notifier(notifier, provider.chat(ChatRequest::new("m", vec![])))
+            .await
+            .unwrap();
+        assert_eq!(response.message.content, "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(*notices.lock().unwrap(), vec!["flaky#1", "flaky#2"]);
+
+        // 重试次数用尽时返回限流错误
+        let inner = Arc::new(FlakyProvider { calls: AtomicU32::new(0) });
+        let config = ProviderConfig {
+            rate_limit_retries: 1,
+            ..Default::default()
+        };
+        let err = QueuedProvider::new(inner, &config)
+            .chat(ChatRequest::new("m", vec![]))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<RateLimitError>().is_some());
+    }
+}
@@ -0,0 +1,188 @@
+//! LLM 路由
+//!
+//! 按配置的规则为每次请求选择提供商和模型，例如闲聊使用便宜的模型，
+//! 需要多轮工具调用或包含 "think"/"code" 等关键词的请求使用更强的模型。
+//! 规则按顺序匹配，第一条满足全部条件的规则生效，都不满足时使用默认模型
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::{RouteRule, RouterConfig};
+
+/// 未命中任何规则时的路由名
+pub const DEFAULT_ROUTE: &str = "default";
+
+/// 路由输入
+#[derive(Debug, Clone, Default)]
+pub struct RouteContext<'a> {
+    /// 本轮用户消息
+    pub text: &'a str,
+    /// 消息来源通道
+    pub channel: Option<&'a str>,
+    /// 本轮是否已产生工具调用
+    pub has_tool_results: bool,
+}
+
+/// 路由结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// 命中的规则名
+    pub name: String,
+    /// 提供商（None 表示默认提供商）
+    pub provider: Option<String>,
+    /// 模型
+    pub model: String,
+}
+
+/// LLM 路由器
+pub struct LlmRouter {
+    rules: Vec<RouteRule>,
+    /// 各路由的命中次数
+    hits: Mutex<HashMap<String, u64>>,
+}
+
+impl LlmRouter {
+    pub fn new(config: &RouterConfig) -> Self {
+        Self {
+            rules: config.rules.clone(),
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 选择路由，未命中时使用 `default_model`
+    pub fn route(&self, ctx: &RouteContext, default_model: &str) -> Route {
+        let route = self
+            .rules
+            .iter()
+            .find(|rule| rule_matches(rule, ctx))
+            .map(|rule| Route {
+                name: rule.name.clone(),
+                provider: rule.provider.clone(),
+                model: rule.model.clone().unwrap_or_else(|| default_model.to_string()),
+            })
+            .unwrap_or_else(|| Route {
+                name: DEFAULT_ROUTE.to_string(),
+                provider: None,
+                model: default_model.to_string(),
+            });
+
+        if let Ok(mut hits) = self.hits.lock() {
+            *hits.entry(route.name.clone()).or_insert(0) += 1;
+        }
+        route
+    }
+
+    /// 各路由的命中次数
+    pub fn stats(&self) -> HashMap<String, u64> {
+        self.hits.lock().map(|h| h.clone()).unwrap_or_default()
+    }
+}
+
+/// 规则中未设置的条件视为满足
+fn rule_matches(rule: &RouteRule, ctx: &RouteContext) -> bool {
+    let chars = ctx.text.chars().count();
+
+    if rule.min_chars.map(|min| chars < min).unwrap_or(false) {
+        return false;
+    }
+    if rule.max_chars.map(|max| chars > max).unwrap_or(false) {
+        return false;
+    }
+    if rule
+        .has_tools
+        .map(|expected| expected != ctx.has_tool_results)
+        .unwrap_or(false)
+    {
+        return false;
+    }
+    if !rule.channels.is_empty()
+        && !ctx
+            .channel
+            .map(|c| rule.channels.iter().any(|rc| rc == c))
+            .unwrap_or(false)
+    {
+        return false;
+    }
+    if !rule.keywords.is_empty() {
+        let text = ctx.text.to_lowercase();
+        if !rule.keywords.iter().any(|k| text.contains(&k.to_lowercase())) {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str) -> RouteRule {
+        RouteRule {
+            name: name.to_string(),
+            model: Some(format!("{}-model", name)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_route_rules() {
+        let config = RouterConfig {
+            enabled: true,
+            rules: vec![
+                RouteRule {
+                    keywords: vec!["think".to_string(), "代码".to_string()],
+                    ..rule("strong")
+                },
+                RouteRule {
+                    has_tools: Some(true),
+                    ..rule("tools")
+                },
+                RouteRule {
+                    max_chars: Some(20),
+                    channels: vec!["telegram".to_string()],
+                    ..rule("cheap")
+                },
+            ],
+        };
+        let router = LlmRouter::new(&config);
+
+        let ctx = RouteContext {
+            text: "Please THINK about it",
+            ..Default::default()
+        };
+        assert_eq!(router.route(&ctx, "base").name, "strong");
+
+        let ctx = RouteContext {
+            text: "帮我写段代码",
+            ..Default::default()
+        };
+        assert_eq!(router.route(&ctx, "base").model, "strong-model");
+
+        let ctx = RouteContext {
+            text: "hi",
+            has_tool_results: true,
+            ..Default::default()
+        };
+        assert_eq!(router.route(&ctx, "base").name, "tools");
+
+        let ctx = RouteContext {
+            text: "hi",
+            channel: Some("telegram"),
+            ..Default::default()
+        };
+        assert_eq!(router.route(&ctx, "base").name, "cheap");
+
+        // 通道不符，回落到默认
+        let ctx = RouteContext {
+            text: "hi",
+            channel: Some("feishu"),
+            ..Default::default()
+        };
+        let route = router.route(&ctx, "base");
+        assert_eq!(route.name, DEFAULT_ROUTE);
+        assert_eq!(route.model, "base");
+
+        assert_eq!(router.stats()["strong"], 2);
+    }
+}
@@ -0,0 +1,256 @@
+//! SiliconFlow（硅基流动）提供商实现
+//! 
+//! OpenAI 兼容接口，模型名为 "组织/模型" 形式（如 deepseek-ai/DeepSeek-V3）
+//! API 文档: https://docs.siliconflow.cn/
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
+
+pub struct SiliconFlowProvider {
+    api_key: String,
+    base_url: String,
+    client: Client,
+}
+
+impl SiliconFlowProvider {
+    pub fn new(api_key: String, base_url: Option<String>, timeout_secs: u64) -> Self {
+        let base_url = base_url.unwrap_or_else(|| "https://api.siliconflow.cn/v1".to_string());
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(timeout_secs))
+            .build()
+            .expect("创建 HTTP 客户端失败");
+
+        Self {
+            api_key,
+            base_url,
+            client,
+        }
+    }
+
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
+
+    /// 获取默认模型
+    pub fn default_model() -> &'static str {
+        "deepseek-ai/DeepSeek-V3"
+    }
+
+    /// 规范化模型名：去掉 "siliconflow/" 前缀，未指定时使用默认模型
+    fn normalize_model(model: &str) -> String {
+        let model = model.strip_prefix("siliconflow/").unwrap_or(model);
+        if model.is_empty() {
+            Self::default_model().to_string()
+        } else {
+            model.to_string()
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for SiliconFlowProvider {
+    fn name(&self) -> &str {
+        "siliconflow"
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "SiliconFlow").await
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let mut body = SiliconFlowRequest::from(request);
+        body.model = Self::normalize_model(&body.model);
+
+        super::debug::record_request(&body);
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        let response = super::queue::check_rate_limit("SiliconFlow", response).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!(format_error(status, &text)));
+        }
+
+        let completion: SiliconFlowResponse = super::debug::read_json(response).await?;
+        
+        if completion.choices.is_empty() {
+            return Err(anyhow!("SiliconFlow 返回空响应"));
+        }
+
+        let choice = &completion.choices[0];
+        let message = Message {
+            role: match choice.message.role.as_str() {
+                "system" => Role::System,
+                "assistant" => Role::Assistant,
+                "tool" => Role::Tool,
+                _ => Role::User,
+            },
+            content: choice.message.content.clone().unwrap_or_default(),
+            tool_calls: choice.message.tool_calls.clone(),
+            tool_call_id: None,
+        };
+
+        Ok(ChatResponse {
+            message,
+            usage: completion.usage,
+            model: completion.model,
+        })
+    }
+
+    fn is_available(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+}
+
+/// SiliconFlow 错误响应: {"code": 20012, "message": "Model does not exist.", "data": null}
+#[derive(Debug, Deserialize)]
+struct SiliconFlowError {
+    code: Option<i64>,
+    message: String,
+}
+
+/// 解析错误响应，无法解析时保留原始文本
+fn format_error(status: reqwest::StatusCode, text: &str) -> String {
+    match serde_json::from_str::<SiliconFlowError>(text) {
+        Ok(err) => match err.code {
+            Some(code) => format!("SiliconFlow API 错误: {} - [{}] {}", status, code, err.message),
+            None => format!("SiliconFlow API 错误: {} - {}", status, err.message),
+        },
+        Err(_) => format!("SiliconFlow API 错误: {} - {}", status, text),
+    }
+}
+
+// SiliconFlow API 请求结构
+#[derive(Debug, Serialize)]
+struct SiliconFlowRequest {
+    model: String,
+    messages: Vec<SiliconFlowMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<SiliconFlowTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct SiliconFlowMessage {
+    role: String,
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SiliconFlowTool {
+    #[serde(rename = "type")]
+    tool_type: String,
+    function: SiliconFlowFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct SiliconFlowFunction {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+// SiliconFlow API 响应结构
+#[derive(Debug, Deserialize)]
+struct SiliconFlowResponse {
+    model: String,
+    choices: Vec<SiliconFlowChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SiliconFlowChoice {
+    message: SiliconFlowResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct SiliconFlowResponseMessage {
+    role: String,
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCall>>,
+}
+
+impl From<ChatRequest> for SiliconFlowRequest {
+    fn from(req: ChatRequest) -> Self {
+        Self {
+            model: req.model,
+            messages: req.messages.into_iter().map(|m| SiliconFlowMessage {
+                role: match m.role {
+                    Role::System => "system".to_string(),
+                    Role::User => "user".to_string(),
+                    Role::Assistant => "assistant".to_string(),
+                    Role::Tool => "tool".to_string(),
+                },
+                content: m.content,
+                tool_calls: m.tool_calls,
+                tool_call_id: m.tool_call_id,
+            }).collect(),
+            tools: req.tools.map(|tools| tools.into_iter().map(|t| SiliconFlowTool {
+                tool_type: "function".to_string(),
+                function: SiliconFlowFunction {
+                    name: t.name,
+                    description: t.description,
+                    parameters: t.parameters,
+                },
+            }).collect()),
+            temperature: req.temperature,
+            max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_model() {
+        assert_eq!(SiliconFlowProvider::normalize_model("siliconflow/Qwen/Qwen2.5-7B-Instruct"), "Qwen/Qwen2.5-7B-Instruct");
+        assert_eq!(SiliconFlowProvider::normalize_model("Qwen/Qwen2.5-7B-Instruct"), "Qwen/Qwen2.5-7B-Instruct");
+        assert_eq!(SiliconFlowProvider::normalize_model(""), SiliconFlowProvider::default_model());
+    }
+
+    #[test]
+    fn test_format_error() {
+        let status = reqwest::StatusCode::BAD_REQUEST;
+        assert_eq!(format_error(status, r#"{"code": 20012, "message": "Model does not exist.", "data": null}"#), "SiliconFlow API 错误: 400 Bad Request - [20012] Model does not exist.");
+        assert_eq!(format_error(status, "oops"), "SiliconFlow API 错误: 400 Bad Request - oops");
+    }
+}
@@ -0,0 +1,262 @@
+//! Together.ai 提供商实现
+//! 
+//! OpenAI 兼容接口，模型名为 "组织/模型" 形式（如 meta-llama/Llama-3.3-70B-Instruct-Turbo）
+//! API 文档: https://docs.together.ai/
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
+
+pub struct TogetherProvider {
+    api_key: String,
+    base_url: String,
+    client: Client,
+}
+
+impl TogetherProvider {
+    pub fn new(api_key: String, base_url: Option<String>, timeout_secs: u64) -> Self {
+        let base_url = base_url.unwrap_or_else(|| "https://api.together.xyz/v1".to_string());
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(timeout_secs))
+            .build()
+            .expect("创建 HTTP 客户端失败");
+
+        Self {
+            api_key,
+            base_url,
+            client,
+        }
+    }
+
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
+
+    /// 获取默认模型
+    pub fn default_model() -> &'static str {
+        "meta-llama/Llama-3.3-70B-Instruct-Turbo"
+    }
+
+    /// 规范化模型名：去掉 "together/" 前缀，未指定时使用默认模型
+    fn normalize_model(model: &str) -> String {
+        let model = model.strip_prefix("together/").unwrap_or(model);
+        if model.is_empty() {
+            Self::default_model().to_string()
+        } else {
+            model.to_string()
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for TogetherProvider {
+    fn name(&self) -> &str {
+        "together"
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "Together").await
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let mut body = TogetherRequest::from(request);
+        body.model = Self::normalize_model(&body.model);
+
+        super::debug::record_request(&body);
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        let response = super::queue::check_rate_limit("Together", response).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!(format_error(status, &text)));
+        }
+
+        let completion: TogetherResponse = super::debug::read_json(response).await?;
+        
+        if completion.choices.is_empty() {
+            return Err(anyhow!("Together 返回空响应"));
+        }
+
+        let choice = &completion.choices[0];
+        let message = Message {
+            role: match choice.message.role.as_str() {
+                "system" => Role::System,
+                "assistant" => Role::Assistant,
+                "tool" => Role::Tool,
+                _ => Role::User,
+            },
+            content: choice.message.content.clone().unwrap_or_default(),
+            tool_calls: choice.message.tool_calls.clone(),
+            tool_call_id: None,
+        };
+
+        Ok(ChatResponse {
+            message,
+            usage: completion.usage,
+            model: completion.model,
+        })
+    }
+
+    fn is_available(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+}
+
+/// Together 错误响应: {"error": {"message": "...", "type": "invalid_request_error", "code": "model_not_available"}}
+#[derive(Debug, Deserialize)]
+struct TogetherErrorBody {
+    error: TogetherError,
+}
+
+#[derive(Debug, Deserialize)]
+struct TogetherError {
+    message: String,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+}
+
+/// 解析错误响应，无法解析时保留原始文本
+fn format_error(status: reqwest::StatusCode, text: &str) -> String {
+    match serde_json::from_str::<TogetherErrorBody>(text) {
+        Ok(body) => match body.error.error_type {
+            Some(t) => format!("Together API 错误: {} - [{}] {}", status, t, body.error.message),
+            None => format!("Together API 错误: {} - {}", status, body.error.message),
+        },
+        Err(_) => format!("Together API 错误: {} - {}", status, text),
+    }
+}
+
+// Together API 请求结构
+#[derive(Debug, Serialize)]
+struct TogetherRequest {
+    model: String,
+    messages: Vec<TogetherMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<TogetherTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct TogetherMessage {
+    role: String,
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TogetherTool {
+    #[serde(rename = "type")]
+    tool_type: String,
+    function: TogetherFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct TogetherFunction {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+// Together API 响应结构
+#[derive(Debug, Deserialize)]
+struct TogetherResponse {
+    model: String,
+    choices: Vec<TogetherChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TogetherChoice {
+    message: TogetherResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct TogetherResponseMessage {
+    role: String,
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCall>>,
+}
+
+impl From<ChatRequest> for TogetherRequest {
+    fn from(req: ChatRequest) -> Self {
+        Self {
+            model: req.model,
+            messages: req.messages.into_iter().map(|m| TogetherMessage {
+                role: match m.role {
+                    Role::System => "system".to_string(),
+                    Role::User => "user".to_string(),
+                    Role::Assistant => "assistant".to_string(),
+                    Role::Tool => "tool".to_string(),
+                },
+                content: m.content,
+                tool_calls: m.tool_calls,
+                tool_call_id: m.tool_call_id,
+            }).collect(),
+            tools: req.tools.map(|tools| tools.into_iter().map(|t| TogetherTool {
+                tool_type: "function".to_string(),
+                function: TogetherFunction {
+                    name: t.name,
+                    description: t.description,
+                    parameters: t.parameters,
+                },
+            }).collect()),
+            temperature: req.temperature,
+            max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_model() {
+        assert_eq!(TogetherProvider::normalize_model("together/meta-llama/Llama-3-8b-chat-hf"), "meta-llama/Llama-3-8b-chat-hf");
+        assert_eq!(TogetherProvider::normalize_model("meta-llama/Llama-3-8b-chat-hf"), "meta-llama/Llama-3-8b-chat-hf");
+        assert_eq!(TogetherProvider::normalize_model(""), TogetherProvider::default_model());
+    }
+
+    #[test]
+    fn test_format_error() {
+        let status = reqwest::StatusCode::BAD_REQUEST;
+        assert_eq!(format_error(status, r#"{"error": {"message": "Unable to access model", "type": "invalid_request_error"}}"#), "Together API 错误: 400 Bad Request - [invalid_request_error] Unable to access model");
+        assert_eq!(format_error(status, "oops"), "Together API 错误: 400 Bad Request - oops");
+    }
+}
@@ -11,6 +11,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use super::models::ModelInfo;
+use crate::config::ProviderConfig;
 
 pub struct VllmProvider {
     api_key: String,
@@ -40,30 +42,17 @@ impl VllmProvider {
         }
     }
 
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
+    }
+
     /// 获取默认模型名称
+    #[allow(dead_code)]
     pub fn default_model(&self) -> &str {
         &self.default_model
     }
-
-    /// 列出可用模型
-    pub async fn list_models(&self) -> Result<Vec<String>> {
-        let url = format!("{}/models", self.base_url);
-        
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("vLLM API 错误: {} - {}", status, text));
-        }
-
-        let models: VllmModelsResponse = response.json().await?;
-        Ok(models.data.into_iter().map(|m| m.id).collect())
-    }
 }
 
 #[async_trait]
@@ -72,6 +61,10 @@ impl LlmProvider for VllmProvider {
         "vllm"
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        super::models::fetch_openai_compatible(&self.client, &self.base_url, &self.api_key, "vLLM").await
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.base_url);
 
@@ -81,6 +74,7 @@ impl LlmProvider for VllmProvider {
             body.model = self.default_model.clone();
         }
 
+        super::debug::record_request(&body);
         let mut request_builder = self.client
             .post(&url)
             .header("Content-Type", "application/json")
@@ -93,13 +87,14 @@ impl LlmProvider for VllmProvider {
 
         let response = request_builder.send().await?;
 
+        let response = super::queue::check_rate_limit("vLLM", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("vLLM API 错误: {} - {}", status, text));
         }
 
-        let completion: VllmResponse = response.json().await?;
+        let completion: VllmResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("vLLM 返回空响应"));
@@ -146,6 +141,8 @@ struct VllmRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     presence_penalty: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     frequency_penalty: Option<f32>,
@@ -178,7 +175,6 @@ struct VllmFunction {
 // vLLM API 响应结构
 #[derive(Debug, Deserialize)]
 struct VllmResponse {
-    id: String,
     model: String,
     choices: Vec<VllmChoice>,
     usage: Option<Usage>,
@@ -186,9 +182,7 @@ struct VllmResponse {
 
 #[derive(Debug, Deserialize)]
 struct VllmChoice {
-    index: u32,
     message: VllmResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -200,18 +194,6 @@ struct VllmResponseMessage {
 }
 
 // 模型列表响应
-#[derive(Debug, Deserialize)]
-struct VllmModelsResponse {
-    object: String,
-    data: Vec<VllmModel>,
-}
-
-#[derive(Debug, Deserialize)]
-struct VllmModel {
-    id: String,
-    object: String,
-}
-
 impl From<ChatRequest> for VllmRequest {
     fn from(req: ChatRequest) -> Self {
         Self {
@@ -237,9 +219,10 @@ impl From<ChatRequest> for VllmRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
-            top_p: None,
-            presence_penalty: None,
-            frequency_penalty: None,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::{ChatRequest, ChatResponse, LlmProvider, Message, Role, ToolCall, Usage};
+use crate::config::ProviderConfig;
 
 pub struct ZhipuProvider {
     api_key: String,
@@ -32,9 +33,10 @@ impl ZhipuProvider {
         }
     }
 
-    /// 获取默认模型
-    pub fn default_model() -> &'static str {
-        "glm-4"
+    /// 按提供商配置重建 HTTP 客户端（代理、自定义请求头、TLS 校验）
+    pub fn with_http_config(mut self, config: &ProviderConfig) -> Result<Self> {
+        self.client = super::build_http_client(config)?;
+        Ok(self)
     }
 }
 
@@ -49,6 +51,7 @@ impl LlmProvider for ZhipuProvider {
 
         let body = ZhipuRequest::from(request);
 
+        super::debug::record_request(&body);
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -57,13 +60,14 @@ impl LlmProvider for ZhipuProvider {
             .send()
             .await?;
 
+        let response = super::queue::check_rate_limit("智谱 AI", response).await?;
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(anyhow!("智谱 AI API 错误: {} - {}", status, text));
         }
 
-        let completion: ZhipuResponse = response.json().await?;
+        let completion: ZhipuResponse = super::debug::read_json(response).await?;
         
         if completion.choices.is_empty() {
             return Err(anyhow!("智谱 AI 返回空响应"));
@@ -105,6 +109,14 @@ struct ZhipuRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -134,7 +146,6 @@ struct ZhipuFunction {
 // 智谱 AI API 响应结构
 #[derive(Debug, Deserialize)]
 struct ZhipuResponse {
-    id: String,
     model: String,
     choices: Vec<ZhipuChoice>,
     usage: Option<Usage>,
@@ -142,9 +153,7 @@ struct ZhipuResponse {
 
 #[derive(Debug, Deserialize)]
 struct ZhipuChoice {
-    index: u32,
     message: ZhipuResponseMessage,
-    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -180,6 +189,10 @@ impl From<ChatRequest> for ZhipuRequest {
             }).collect()),
             temperature: req.temperature,
             max_tokens: req.max_tokens,
+            top_p: req.top_p,
+            stop: req.stop,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
         }
     }
 }
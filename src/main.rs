@@ -7,17 +7,30 @@ use clap::{Parser, Subcommand};
 use tracing::{info, warn};
 
 mod agent;
+mod api;
+mod backup;
+mod budget;
 mod bus;
 mod channel;
 mod cli;
 mod config;
+mod crash;
 mod cron;
+mod db;
+mod embeddings;
 mod error;
+mod eval;
+mod i18n;
 mod llm;
 mod memory;
 mod module_tests;
+mod privacy;
 mod session;
+mod state;
+mod tasks;
 mod tools;
+mod vault;
+mod watch;
 
 #[cfg(test)]
 mod tests;
@@ -36,6 +49,14 @@ struct Cli {
     /// 配置文件路径
     #[arg(short, long, global = true)]
     config: Option<String>,
+
+    /// 记录 LLM 请求/响应到调试目录（默认 <workspace>/llm-debug）
+    #[arg(long, global = true)]
+    debug_llm: bool,
+
+    /// 演练模式：有副作用的工具不实际执行（同 [tools] dry_run = true）
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Subcommand)]
@@ -45,6 +66,9 @@ enum Commands {
         /// 初始提示词
         #[arg(short, long)]
         prompt: Option<String>,
+        /// 使用的角色（[personas.<名称>]）
+        #[arg(long)]
+        persona: Option<String>,
     },
     /// 启动网关服务（Telegram Bot 等）
     Gateway {
@@ -54,12 +78,141 @@ enum Commands {
     },
     /// 查看系统状态
     Status,
+    /// 检查运行中网关的健康状态（不健康时以非零状态退出）
+    Health {
+        #[command(flatten)]
+        args: cli::health::HealthArgs,
+    },
     /// 初始化配置文件
     Init {
         /// 强制覆盖已有配置
         #[arg(short, long)]
         force: bool,
     },
+    /// 创建定时提醒，如 "明天早上八点: 开会"、"every weekday at 9am: standup"
+    Remind {
+        /// "<时间>: <内容>"
+        input: String,
+        /// 提醒发送的通道（如 telegram）
+        #[arg(long)]
+        channel: Option<String>,
+        /// 提醒发送的聊天 ID
+        #[arg(long)]
+        chat_id: Option<String>,
+        /// gateway 停机错过提醒时的处理：skip（跳过）、run_once（补发一次）、run_all（补发所有错过的次数），
+        /// 默认一次性提醒补发一次、重复提醒跳过
+        #[arg(long)]
+        misfire: Option<crate::cron::MisfirePolicy>,
+    },
+    /// 查询已配置提供商的可用模型（上下文长度、价格），结果缓存 24 小时
+    Models {
+        /// 只查询指定提供商
+        #[arg(short, long)]
+        provider: Option<String>,
+        /// 只显示 ID 包含该文本的模型
+        #[arg(short, long)]
+        filter: Option<String>,
+        /// 忽略缓存重新查询
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// 深度调研：拆分子问题、多轮搜索后输出带引用的报告
+    Research {
+        /// 调研的问题
+        question: String,
+    },
+    /// 运行 YAML 评测用例（contains / regex / json_path / llm 断言），可与基线比较
+    Eval {
+        /// 用例文件
+        suite: std::path::PathBuf,
+        /// 使用的提供商（mock 为离线模拟，回复取用例的 mock_response）
+        #[arg(short, long)]
+        provider: Option<String>,
+        /// 使用的模型（"provider/model" 或仅模型名），覆盖用例文件中的 model
+        #[arg(short, long)]
+        model: Option<String>,
+        /// 与基线文件比较耗时、令牌数和通过情况
+        #[arg(long)]
+        baseline: Option<std::path::PathBuf>,
+        /// 把本次结果保存为基线文件
+        #[arg(long)]
+        save_baseline: Option<std::path::PathBuf>,
+    },
+    /// 不经过 Agent 直接通过通道发送通知，如 `send --channel telegram --to 123 "构建完成"`
+    Send {
+        /// 通道（如 telegram）
+        #[arg(long)]
+        channel: String,
+        /// 目标聊天 ID（可指定多个）
+        #[arg(long, required = true)]
+        to: Vec<String>,
+        /// 消息内容
+        message: String,
+    },
+    /// 把会话导出为 HTML 报告（聊天记录、工具调用、用量汇总）
+    Report {
+        /// 会话 ID（如 telegram:123456）
+        #[arg(long)]
+        session: String,
+        /// 输出文件（默认 <会话 ID>.html）
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// 导出打码后的会话（HTML 或 Markdown），可上传后分享链接
+    Share {
+        #[command(flatten)]
+        args: cli::share::ShareArgs,
+    },
+    /// 查看长期记忆
+    Memory {
+        #[command(subcommand)]
+        command: cli::memory::MemoryCommand,
+    },
+    /// 查看会话统计
+    Session {
+        #[command(subcommand)]
+        command: cli::session::SessionCommand,
+    },
+    /// 清除用户数据（对话历史、会话统计、发件箱记录、提醒等）
+    Purge {
+        #[command(flatten)]
+        target: cli::purge::PurgeArgs,
+    },
+    /// 查看后台任务
+    Tasks {
+        #[command(subcommand)]
+        command: cli::tasks::TasksCommand,
+    },
+    /// 加密/解密工作目录中的数据
+    Vault {
+        #[command(subcommand)]
+        command: cli::vault::VaultCommand,
+    },
+    /// 导出配置的 JSON Schema、检查配置文件中的未知配置项
+    Config {
+        #[command(subcommand)]
+        command: cli::config::ConfigCommand,
+    },
+    /// 备份配置、记忆和数据库到一个归档，或从归档恢复
+    Backup {
+        #[command(subcommand)]
+        command: cli::backup::BackupCommand,
+    },
+    /// 查看和恢复回收站中的文件（delete_file 删除的文件）
+    Trash {
+        #[command(subcommand)]
+        command: cli::trash::TrashCommand,
+    },
+    /// SQLite 数据库维护（完整性检查、VACUUM、重建索引）
+    Db {
+        #[command(subcommand)]
+        command: cli::db::DbCommand,
+    },
+    /// 管理 HTTP API 的访问令牌（权限范围、过期时间）
+    Token {
+        #[command(subcommand)]
+        command: cli::token::TokenCommand,
+    },
     /// 执行单个工具
     Tool {
         /// 工具名称
@@ -87,7 +240,7 @@ async fn main() -> Result<()> {
 
     // 加载配置
     let config_path = cli.config.as_deref();
-    let config = match Config::load(config_path) {
+    let mut config = match Config::load(config_path) {
         Ok(cfg) => cfg,
         Err(e) => {
             warn!("加载配置失败: {}，使用默认配置", e);
@@ -95,9 +248,22 @@ async fn main() -> Result<()> {
         }
     };
 
+    i18n::set_language(config.ui.language);
+    crash::install_hook(&config.crash);
+
+    if cli.debug_llm && config.llm.debug_log_dir.is_none() {
+        config.llm.debug_log_dir = Some(config.default_llm_debug_dir());
+    }
+    if cli.dry_run {
+        config.tools.dry_run = true;
+    }
+    if config.tools.dry_run {
+        warn!("演练模式已启用：shell、write_file、message 等有副作用的工具不会实际执行");
+    }
+
     match cli.command {
-        Commands::Agent { prompt } => {
-            cli::agent::run(config, prompt).await?;
+        Commands::Agent { prompt, persona } => {
+            cli::agent::run(config, prompt, persona).await?;
         }
         Commands::Gateway { channel } => {
             cli::gateway::run(config, channel).await?;
@@ -105,9 +271,64 @@ async fn main() -> Result<()> {
         Commands::Status => {
             cli::status::run(config).await?;
         }
+        Commands::Health { args } => {
+            cli::health::run(config, args).await?;
+        }
         Commands::Init { force } => {
             cli::init::run(config_path, force).await?;
         }
+        Commands::Remind { input, channel, chat_id, misfire } => {
+            cli::remind::run(config, &input, channel, chat_id, misfire).await?;
+        }
+        Commands::Models { provider, filter, refresh } => {
+            cli::models::run(config, provider, filter, refresh).await?;
+        }
+        Commands::Research { question } => {
+            cli::research::run(config, &question).await?;
+        }
+        Commands::Eval { suite, provider, model, baseline, save_baseline } => {
+            let args = cli::eval::EvalArgs { suite, provider, model, baseline, save_baseline };
+            cli::eval::run(config, args).await?;
+        }
+        Commands::Send { channel, to, message } => {
+            cli::send::run(config, &channel, to, &message).await?;
+        }
+        Commands::Report { session, output } => {
+            cli::report::run(config, &session, output).await?;
+        }
+        Commands::Share { args } => {
+            cli::share::run(config, args).await?;
+        }
+        Commands::Memory { command } => {
+            cli::memory::run(config, command).await?;
+        }
+        Commands::Session { command } => {
+            cli::session::run(config, command).await?;
+        }
+        Commands::Purge { target } => {
+            cli::purge::run(config, target).await?;
+        }
+        Commands::Tasks { command } => {
+            cli::tasks::run(config, command).await?;
+        }
+        Commands::Vault { command } => {
+            cli::vault::run(config, command).await?;
+        }
+        Commands::Config { command } => {
+            cli::config::run(config_path, command).await?;
+        }
+        Commands::Backup { command } => {
+            cli::backup::run(config, config_path, command).await?;
+        }
+        Commands::Trash { command } => {
+            cli::trash::run(config, command).await?;
+        }
+        Commands::Db { command } => {
+            cli::db::run(config, command).await?;
+        }
+        Commands::Token { command } => {
+            cli::token::run(config, command).await?;
+        }
         Commands::Tool { name, args } => {
             cli::tool::run(config, &name, args).await?;
         }
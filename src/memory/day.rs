@@ -0,0 +1,304 @@
+//! 回顾某一天
+//!
+//! 把指定日期的日常笔记和当天的对话摘录整理成一段素材（`recall_day` 工具和 `/onthisday` 命令使用），
+//! 便于回答"上周二我在做什么"之类的问题。笔记、每条消息和对话摘录的总长度都有上限，避免撑满上下文
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, Weekday};
+
+use super::MemoryStore;
+use crate::cron::reminder::{english_weekday, take_chinese_weekday, take_number};
+
+/// 日常笔记最多保留的字符数
+const NOTE_MAX_CHARS: usize = 4000;
+/// 单条消息最多保留的字符数
+const MESSAGE_MAX_CHARS: usize = 300;
+/// 每个会话最多摘录的消息数
+const SESSION_MAX_MESSAGES: usize = 30;
+/// 对话摘录的总字符数上限
+const EXCERPTS_MAX_CHARS: usize = 12000;
+
+/// 某一天的日常笔记和对话摘录
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayRecall {
+    pub date: NaiveDate,
+    /// 当天的日常笔记（已截断）
+    pub note: String,
+    /// 各会话当天的消息（会话 ID、"时刻 角色: 内容" 行）
+    pub conversations: Vec<(String, Vec<String>)>,
+    /// 超出总长度上限而省略的会话数
+    pub omitted: usize,
+}
+
+impl DayRecall {
+    /// 那天既没有笔记也没有对话
+    pub fn is_empty(&self) -> bool {
+        self.note.is_empty() && self.conversations.is_empty()
+    }
+
+    /// 整理为发给模型的 Markdown 素材
+    pub fn render(&self) -> String {
+        let mut text = format!("# {} 的日记和对话\n", self.date.format("%Y-%m-%d"));
+        text.push_str("\n## 日常笔记\n");
+        text.push_str(if self.note.is_empty() { "（无）" } else { &self.note });
+        text.push('\n');
+        for (session_id, lines) in &self.conversations {
+            text.push_str(&format!("\n## 对话 {}\n{}\n", session_id, lines.join("\n")));
+        }
+        if self.omitted > 0 {
+            text.push_str(&format!("\n（另有 {} 个会话因长度限制省略）\n", self.omitted));
+        }
+        text
+    }
+}
+
+impl MemoryStore {
+    /// 读取某天的日常笔记和 `can_access` 允许的会话中当天的消息（用户和助手的发言）
+    pub async fn recall_day(&self, date: NaiveDate, can_access: impl Fn(&str) -> bool) -> Result<DayRecall> {
+        let note = truncate(self.read_day(date).await?.trim(), NOTE_MAX_CHARS);
+
+        let mut sessions = Vec::new();
+        for id in self.list_sessions().await? {
+            // 最后修改早于那天的会话不会有当天的消息
+            let modified = self.conversation_modified(&id).await;
+            if can_access(&id) && modified.is_none_or(|m| m.date_naive() >= date) {
+                sessions.push(id);
+            }
+        }
+        sessions.sort();
+
+        let mut conversations = Vec::new();
+        let mut omitted = 0;
+        let mut total = 0;
+        for id in sessions {
+            let messages: Vec<_> = self
+                .get_conversation(&id, 0)
+                .await?
+                .into_iter()
+                .filter(|m| matches!(m.role.as_str(), "user" | "assistant") && !m.content.trim().is_empty())
+                .filter(|m| m.created_at.with_timezone(&Local).date_naive() == date)
+                .collect();
+            if messages.is_empty() {
+                continue;
+            }
+            let mut lines: Vec<String> = messages
+                .iter()
+                .take(SESSION_MAX_MESSAGES)
+                .map(|m| {
+                    format!(
+                        "- {} {}: {}",
+                        m.created_at.with_timezone(&Local).format("%H:%M"),
+                        if m.role == "user" { "用户" } else { "助手" },
+                        truncate(&m.content.trim().replace('\n', " "), MESSAGE_MAX_CHARS)
+                    )
+                })
+                .collect();
+            if messages.len() > SESSION_MAX_MESSAGES {
+                lines.push(format!("- …（还有 {} 条消息）", messages.len() - SESSION_MAX_MESSAGES));
+            }
+            let len: usize = lines.iter().map(|l| l.chars().count()).sum();
+            if total + len > EXCERPTS_MAX_CHARS {
+                omitted += 1;
+                continue;
+            }
+            total += len;
+            conversations.push((id, lines));
+        }
+
+        Ok(DayRecall {
+            date,
+            note,
+            conversations,
+            omitted,
+        })
+    }
+}
+
+/// 解析过去的日期：`2025-03-14`、`3月14日`、`昨天`、`上周二`、`3 天前`、`yesterday`、`last tuesday`、`2 weeks ago` 等，
+/// 空字符串或"去年今天"为一年前的今天。只写星期时为最近过去的那一天，只写月日时为最近过去的那一天
+pub fn parse_day(text: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let text = text.trim().to_lowercase();
+    let date = parse_relative(&text, today)
+        .or_else(|| parse_absolute(&text, today))
+        .ok_or_else(|| anyhow!("无法识别的日期: {}", text))?;
+    if date > today {
+        bail!("日期在未来: {}", date);
+    }
+    Ok(date)
+}
+
+/// 拆分开头的日期和后面的问题（日期最多三个词），开头不是日期时为一年前的今天和全部文本
+pub fn split_day(text: &str, today: NaiveDate) -> (NaiveDate, String) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    for n in (1..=words.len().min(3)).rev() {
+        if let Ok(date) = parse_day(&words[..n].join(" "), today) {
+            return (date, words[n..].join(" "));
+        }
+    }
+    (a_year_ago(today), text.trim().to_string())
+}
+
+fn a_year_ago(today: NaiveDate) -> NaiveDate {
+    today.checked_sub_months(Months::new(12)).unwrap_or(today)
+}
+
+/// 今天、昨天、几天前、星期几等相对日期
+fn parse_relative(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let days_ago = |n: u32| today - Duration::days(n as i64);
+    let date = match text {
+        "" | "去年今天" | "去年的今天" | "a year ago" | "one year ago" | "last year" => a_year_ago(today),
+        "today" | "今天" => today,
+        "yesterday" | "昨天" => days_ago(1),
+        "前天" => days_ago(2),
+        "大前天" => days_ago(3),
+        _ => {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            return match words.as_slice() {
+                [n, unit, "ago"] => {
+                    let n: u32 = n.parse().ok()?;
+                    match unit.trim_end_matches('s') {
+                        "day" => Some(days_ago(n)),
+                        "week" => Some(days_ago(n * 7)),
+                        _ => None,
+                    }
+                }
+                ["last", day] => Some(previous_week(today, english_weekday(day)?)),
+                ["on", day] | [day] if english_weekday(day).is_some() => {
+                    Some(most_recent(today, english_weekday(day)?))
+                }
+                _ => parse_chinese_relative(text, today),
+            };
+        }
+    };
+    Some(date)
+}
+
+/// "3天前"、"两周前"、"上周二"、"这周一"、"周五"
+fn parse_chinese_relative(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Some((n, rest)) = take_number(text) {
+        let rest = rest.trim().trim_start_matches('个');
+        return match rest {
+            "天前" => Some(today - Duration::days(n as i64)),
+            "周前" | "星期前" | "礼拜前" => Some(today - Duration::days(n as i64 * 7)),
+            _ => None,
+        };
+    }
+    for prefix in ["上个", "上"] {
+        if let Some((day, "")) = text.strip_prefix(prefix).and_then(take_chinese_weekday) {
+            return Some(previous_week(today, day));
+        }
+    }
+    for prefix in ["这个", "这", "本"] {
+        if let Some((day, "")) = text.strip_prefix(prefix).and_then(take_chinese_weekday) {
+            return Some(this_week(today, day));
+        }
+    }
+    match take_chinese_weekday(text)? {
+        (day, "") => Some(most_recent(today, day)),
+        _ => None,
+    }
+}
+
+/// `2025-03-14`、`2025/3/14`、`3-14`、`2025年3月14日`、`3月14号`
+fn parse_absolute(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let text = text.trim_end_matches(['日', '号']);
+    let parts: Vec<&str> = text.split(['-', '/', '.', '年', '月']).map(str::trim).collect();
+    let number = |s: &str| take_number(s).filter(|(_, rest)| rest.is_empty()).map(|(n, _)| n);
+    match parts.as_slice() {
+        [year, month, day] => NaiveDate::from_ymd_opt(year.parse().ok()?, number(month)?, number(day)?),
+        [month, day] => {
+            // 没有年份时取最近过去的那一天
+            let (month, day) = (number(month)?, number(day)?);
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if date > today {
+                NaiveDate::from_ymd_opt(today.year() - 1, month, day)
+            } else {
+                Some(date)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 上周的星期几（周一为一周开始）
+fn previous_week(today: NaiveDate, day: Weekday) -> NaiveDate {
+    this_week(today, day) - Duration::days(7)
+}
+
+/// 本周的星期几
+fn this_week(today: NaiveDate, day: Weekday) -> NaiveDate {
+    today - Duration::days(today.weekday().num_days_from_monday() as i64) + Duration::days(day.num_days_from_monday() as i64)
+}
+
+/// 最近过去的星期几（不含今天）
+fn most_recent(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let back = (today.weekday().num_days_from_monday() as i64 - day.num_days_from_monday() as i64).rem_euclid(7);
+    today - Duration::days(if back == 0 { 7 } else { back })
+}
+
+/// 截断到指定字符数
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_day() {
+        // 2026-10-15 是星期四
+        let today = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let day = |text: &str| parse_day(text, today).unwrap().to_string();
+
+        assert_eq!(day("2026-03-14"), "2026-03-14");
+        assert_eq!(day("2026年3月14日"), "2026-03-14");
+        assert_eq!(day("12月1号"), "2025-12-01");
+        assert_eq!(day("昨天"), "2026-10-14");
+        assert_eq!(day("3天前"), "2026-10-12");
+        assert_eq!(day("两周前"), "2026-10-01");
+        assert_eq!(day("上周二"), "2026-10-06");
+        assert_eq!(day("这周一"), "2026-10-12");
+        assert_eq!(day("周四"), "2026-10-08");
+        assert_eq!(day("last tuesday"), "2026-10-06");
+        assert_eq!(day("tuesday"), "2026-10-13");
+        assert_eq!(day("2 weeks ago"), "2026-10-01");
+        assert_eq!(day(""), "2025-10-15");
+        assert!(parse_day("明天", today).is_err());
+        assert!(parse_day("这周五", today).is_err());
+        assert!(parse_day("2026-02-30", today).is_err());
+
+        let (date, question) = split_day("last tuesday what did I work on", today);
+        assert_eq!((date.to_string(), question.as_str()), ("2026-10-06".to_string(), "what did I work on"));
+        let (date, question) = split_day("那天做了什么", today);
+        assert_eq!((date.to_string(), question.as_str()), ("2025-10-15".to_string(), "那天做了什么"));
+    }
+
+    #[tokio::test]
+    async fn test_recall_day() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+        let today = Local::now().date_naive();
+
+        store.append_today("- 修复了调度器的时区问题").await.unwrap();
+        store.add_message("telegram:1", "user", "帮我看看 cron 的报错", None).await.unwrap();
+        store.add_message("telegram:1", "assistant", "是时区设置的问题", None).await.unwrap();
+        store.add_message("telegram:2", "user", "别人的对话", None).await.unwrap();
+
+        let recall = store.recall_day(today, |id| id == "telegram:1").await.unwrap();
+        assert!(recall.note.contains("时区问题"));
+        assert_eq!(recall.conversations.len(), 1);
+        assert_eq!(recall.conversations[0].1.len(), 2);
+        let text = recall.render();
+        assert!(text.contains("## 对话 telegram:1"));
+        assert!(text.contains("用户: 帮我看看 cron 的报错"));
+        assert!(!text.contains("别人的对话"));
+
+        let empty = store.recall_day(today - Duration::days(30), |_| true).await.unwrap();
+        assert!(empty.is_empty());
+    }
+}
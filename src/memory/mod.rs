@@ -2,14 +2,31 @@
 //!
 //! 使用 Markdown 文件格式，与 Python 版本保持一致
 //! - 日常笔记: memory/YYYY-MM-DD.md
-//! - 长期记忆: memory/MEMORY.md
-//! - 对话历史: memory/conversations/{session_id}.md
+//! - 长期记忆: memory/MEMORY.md（重要性等元数据在 memory/memory_index.json）
+//! - 用户的长期记忆: memory/users/{user}/MEMORY.md（按用户分区时，元数据在同目录的 memory_index.json）
+//! - 用户资料: memory/users/{user}.md
+//! - 对话历史: memory/conversations/{session_id}.md（或 JSONL 格式的 {session_id}.jsonl）
+//! - 待审核的记忆: memory/pending_memories.json（启用审核时，见 [`review`]）
+//!
+//! 设置 [`Vault`] 后文件加密存储，读取时自动解密
+
+pub mod day;
+pub mod review;
+pub mod semantic;
 
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+use crate::config::ConversationFormat;
+use crate::llm::{Message, Role};
+
+use crate::vault::Vault;
 
 /// Memory 存储
 pub struct MemoryStore {
@@ -19,8 +36,26 @@ pub struct MemoryStore {
     memory_dir: PathBuf,
     /// 长期记忆文件
     memory_file: PathBuf,
+    /// 长期记忆的重要性等元数据
+    index_file: PathBuf,
     /// 对话历史目录
     conversations_dir: PathBuf,
+    /// 待审核的记忆
+    pending_file: PathBuf,
+    /// 对话历史格式
+    conversation_format: ConversationFormat,
+    /// 设置后加密存储
+    vault: Option<Arc<Vault>>,
+    /// 长期记忆条数上限（0 表示不限制）
+    max_memories: usize,
+    /// 是否按用户分区长期记忆
+    per_user: bool,
+    /// 用户分区的所属用户（共享记忆为 None）
+    user: Option<String>,
+    /// 模型写入的记忆是否先经过审核
+    review: bool,
+    /// 语义检索（启用 `[embeddings]` 时）
+    semantic: Option<Arc<semantic::SemanticIndex>>,
 }
 
 impl MemoryStore {
@@ -28,7 +63,9 @@ impl MemoryStore {
     pub async fn new(workspace: &Path) -> Result<Self> {
         let memory_dir = workspace.join("memory");
         let memory_file = memory_dir.join("MEMORY.md");
+        let index_file = memory_dir.join("memory_index.json");
         let conversations_dir = memory_dir.join("conversations");
+        let pending_file = memory_dir.join("pending_memories.json");
 
         // 确保目录存在
         fs::create_dir_all(&memory_dir).await
@@ -42,28 +79,140 @@ impl MemoryStore {
             workspace: workspace.to_path_buf(),
             memory_dir,
             memory_file,
+            index_file,
             conversations_dir,
+            pending_file,
+            conversation_format: ConversationFormat::default(),
+            vault: None,
+            max_memories: 0,
+            per_user: false,
+            user: None,
+            review: false,
+            semantic: None,
         })
     }
 
+    /// 按用户分区长期记忆
+    pub fn with_per_user(mut self, per_user: bool) -> Self {
+        self.per_user = per_user;
+        self
+    }
+
+    /// 指定用户的长期记忆分区（memory/users/<用户>/），对话历史、日常笔记等仍然共用
+    pub fn for_user(&self, user: &str) -> Self {
+        let dir = self.memory_dir.join("users").join(user_file_name(user));
+        Self {
+            workspace: self.workspace.clone(),
+            memory_dir: self.memory_dir.clone(),
+            memory_file: dir.join("MEMORY.md"),
+            index_file: dir.join("memory_index.json"),
+            conversations_dir: self.conversations_dir.clone(),
+            pending_file: self.pending_file.clone(),
+            conversation_format: self.conversation_format,
+            vault: self.vault.clone(),
+            max_memories: self.max_memories,
+            per_user: self.per_user,
+            user: Some(user.to_string()),
+            review: self.review,
+            semantic: self.semantic.clone(),
+        }
+    }
+
+    /// 用户保存记忆的分区：按用户分区且知道当前用户时为该用户的分区，否则为共享记忆
+    fn scope(&self, user: Option<&str>) -> Option<Self> {
+        user.filter(|_| self.per_user && self.user.is_none()).map(|u| self.for_user(u))
+    }
+
+    /// 设置长期记忆条数上限
+    pub fn with_max_memories(mut self, max_memories: usize) -> Self {
+        self.max_memories = max_memories;
+        self
+    }
+
+    /// 设置对话历史格式
+    pub fn with_conversation_format(mut self, format: ConversationFormat) -> Self {
+        self.conversation_format = format;
+        self
+    }
+
+    /// 启用加密存储
+    pub fn with_vault(mut self, vault: Option<Arc<Vault>>) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    /// 读取文件（加密时自动解密）
+    async fn read_file(&self, path: &Path) -> Result<String> {
+        let data = match self.vault {
+            Some(ref vault) => vault.read_file(path).await?,
+            None => {
+                let data = fs::read(path).await?;
+                if Vault::is_encrypted(&data) {
+                    anyhow::bail!("{} 已加密，请启用 [vault] 并提供口令", path.display());
+                }
+                data
+            }
+        };
+        String::from_utf8(data).with_context(|| format!("文件不是有效的 UTF-8: {}", path.display()))
+    }
+
+    /// 写入文件（启用加密时加密）
+    async fn write_file(&self, path: &Path, content: &str) -> Result<()> {
+        match self.vault {
+            Some(ref vault) => vault.write_file(path, content.as_bytes()).await,
+            None => Ok(fs::write(path, content).await?),
+        }
+    }
+
     /// 获取今天的 memory 文件路径
     pub fn get_today_file(&self) -> PathBuf {
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        self.memory_dir.join(format!("{}.md", today))
+        self.get_day_file(Local::now().date_naive())
+    }
+
+    /// 获取指定日期的 memory 文件路径
+    fn get_day_file(&self, date: NaiveDate) -> PathBuf {
+        self.memory_dir.join(format!("{}.md", date.format("%Y-%m-%d")))
     }
 
     /// 读取今天的 memory
+    #[allow(dead_code)]
     pub async fn read_today(&self) -> Result<String> {
-        let today_file = self.get_today_file();
-        
-        if today_file.exists() {
-            fs::read_to_string(&today_file).await
-                .with_context(|| format!("读取今天的 memory 失败: {}", today_file.display()))
+        self.read_day(Local::now().date_naive()).await
+    }
+
+    /// 读取指定日期的 memory，不存在时返回空字符串
+    pub async fn read_day(&self, date: NaiveDate) -> Result<String> {
+        let day_file = self.get_day_file(date);
+
+        if day_file.exists() {
+            self.read_file(&day_file).await
+                .with_context(|| format!("读取 {} 的 memory 失败: {}", date, day_file.display()))
         } else {
             Ok(String::new())
         }
     }
 
+    /// 昨天和今天的日常笔记，每天最多 `max_chars` 个字符（超出时保留最新的部分）
+    pub async fn recent_notes(&self, max_chars: usize) -> Result<String> {
+        let today = Local::now().date_naive();
+        let mut notes = Vec::new();
+        for date in [today - Duration::days(1), today] {
+            let content = self.read_day(date).await?;
+            let content = content.trim();
+            if content.is_empty() {
+                continue;
+            }
+            let len = content.chars().count();
+            if max_chars > 0 && len > max_chars {
+                let tail: String = content.chars().skip(len - max_chars).collect();
+                notes.push(format!("# {}\n…{}", date.format("%Y-%m-%d"), tail));
+            } else {
+                notes.push(content.to_string());
+            }
+        }
+        Ok(notes.join("\n\n"))
+    }
+
     /// 追加内容到今天的 memory
     pub async fn append_today(
         &self,
@@ -73,7 +222,7 @@ impl MemoryStore {
         let content = content.as_ref();
 
         let existing = if today_file.exists() {
-            fs::read_to_string(&today_file).await.unwrap_or_default()
+            self.read_file(&today_file).await.unwrap_or_default()
         } else {
             // 新文件，添加标题
             let today = Local::now().format("%Y-%m-%d").to_string();
@@ -82,7 +231,7 @@ impl MemoryStore {
 
         let new_content = format!("{}\n{}", existing, content);
         
-        fs::write(&today_file, new_content).await
+        self.write_file(&today_file, &new_content).await
             .with_context(|| format!("写入今天的 memory 失败: {}", today_file.display()))?;
 
         debug!("已追加内容到今天的 memory: {}", today_file.display());
@@ -92,7 +241,7 @@ impl MemoryStore {
     /// 读取长期记忆 (MEMORY.md)
     pub async fn read_long_term(&self) -> Result<String> {
         if self.memory_file.exists() {
-            fs::read_to_string(&self.memory_file).await
+            self.read_file(&self.memory_file).await
                 .with_context(|| format!("读取长期记忆失败: {}", self.memory_file.display()))
         } else {
             Ok(String::new())
@@ -105,17 +254,107 @@ impl MemoryStore {
         content: impl AsRef<str>,
     ) -> Result<()> {
         let content = content.as_ref();
+        if let Some(dir) = self.memory_file.parent() {
+            fs::create_dir_all(dir).await
+                .with_context(|| format!("创建记忆目录失败: {}", dir.display()))?;
+        }
         
-        fs::write(&self.memory_file, content).await
+        self.write_file(&self.memory_file, content).await
             .with_context(|| format!("写入长期记忆失败: {}", self.memory_file.display()))?;
 
         info!("已更新长期记忆: {}", self.memory_file.display());
         Ok(())
     }
 
+    /// 获取用户资料文件路径（memory/users/<用户>.md）
+    fn get_user_profile_file(&self, user: &str) -> PathBuf {
+        self.memory_dir.join("users").join(format!("{}.md", user_file_name(user)))
+    }
+
+    /// 读取用户资料，不存在时返回 None
+    pub async fn read_user_profile(&self, user: &str) -> Result<Option<String>> {
+        let file = self.get_user_profile_file(user);
+        if !file.exists() {
+            return Ok(None);
+        }
+        self.read_file(&file).await
+            .map(Some)
+            .with_context(|| format!("读取用户资料失败: {}", file.display()))
+    }
+
+    /// 读取用户资料中的一项
+    pub async fn user_fact(&self, user: &str, key: &str) -> Result<Option<String>> {
+        let prefix = format!("- **{}**:", key);
+        Ok(self
+            .read_user_profile(user)
+            .await?
+            .and_then(|content| {
+                content
+                    .lines()
+                    .find_map(|line| line.strip_prefix(&prefix).map(|v| v.trim().to_string()))
+            }))
+    }
+
+    /// 保存用户资料中的一项，已存在时覆盖
+    pub async fn save_user_fact(&self, user: &str, key: &str, value: &str) -> Result<()> {
+        let file = self.get_user_profile_file(user);
+        let content = self
+            .read_user_profile(user)
+            .await?
+            .unwrap_or_else(|| format!("# {}\n\n", user));
+
+        let prefix = format!("- **{}**:", key);
+        let entry = format!("{} {}", prefix, value.trim());
+        let mut replaced = false;
+        let mut lines: Vec<String> = content
+            .lines()
+            .map(|line| {
+                if line.starts_with(&prefix) {
+                    replaced = true;
+                    entry.clone()
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+        if !replaced {
+            lines.push(entry);
+        }
+
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).await
+                .with_context(|| format!("创建用户资料目录失败: {}", dir.display()))?;
+        }
+        self.write_file(&file, &(lines.join("\n") + "\n")).await
+            .with_context(|| format!("写入用户资料失败: {}", file.display()))?;
+        debug!("已保存用户资料: {} {} = {}", user, key, value);
+        Ok(())
+    }
+
+    /// 删除用户资料、该用户的长期记忆分区和待审核记忆，返回是否存在
+    pub async fn delete_user_profile(&self, user: &str) -> Result<bool> {
+        let file = self.get_user_profile_file(user);
+        let dir = self.memory_dir.join("users").join(user_file_name(user));
+        let existed = self.remove_user_pending(user).await? > 0 || file.exists() || dir.exists();
+        if file.exists() {
+            fs::remove_file(&file).await
+                .with_context(|| format!("删除用户资料失败: {}", file.display()))?;
+        }
+        if dir.exists() {
+            fs::remove_dir_all(&dir).await
+                .with_context(|| format!("删除用户记忆失败: {}", dir.display()))?;
+        }
+        Ok(existed)
+    }
+
     /// 获取对话历史文件路径
     fn get_conversation_file(&self, session_id: &str) -> PathBuf {
-        self.conversations_dir.join(format!("{}.md", session_id))
+        self.conversation_file(session_id, self.conversation_format)
+    }
+
+    /// 指定格式的对话历史文件路径
+    fn conversation_file(&self, session_id: &str, format: ConversationFormat) -> PathBuf {
+        self.conversations_dir.join(format!("{}.{}", session_id, format.extension()))
     }
 
     /// 添加对话消息
@@ -126,42 +365,114 @@ impl MemoryStore {
         content: &str,
         tool_call_id: Option<&str>,
     ) -> Result<()> {
-        let conv_file = self.get_conversation_file(session_id);
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.add_message_with_tool_calls(session_id, role, content, None, tool_call_id).await
+    }
 
-        // 保存 tool_call_id（如果有）- 格式: **tool**: content [call_id:xxx]
-        let tool_call_id_str = if let Some(id) = tool_call_id {
-            format!(" [call_id:{}]", id)
-        } else {
-            String::new()
+    /// 添加对话消息，`tool_calls` 为助手消息中工具调用的 JSON
+    pub async fn add_message_with_tool_calls(
+        &self,
+        session_id: &str,
+        role: &str,
+        content: &str,
+        tool_calls: Option<&str>,
+        tool_call_id: Option<&str>,
+    ) -> Result<()> {
+        let conv_file = self.get_conversation_file(session_id);
+        let message = ConversationMessage {
+            id: 0,
+            session_id: session_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: tool_calls.map(str::to_string),
+            tool_call_id: tool_call_id.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        let entry = match self.conversation_format {
+            ConversationFormat::Markdown => format_markdown_entry(&message),
+            ConversationFormat::Jsonl => format_jsonl_entry(&message)?,
         };
-
-        let entry = format!(
-            "## {}\n**{}**:{}{}\n\n",
-            timestamp, role, content, tool_call_id_str
-        );
 
         let existing = if conv_file.exists() {
-            fs::read_to_string(&conv_file).await.unwrap_or_default()
+            self.read_file(&conv_file).await.unwrap_or_default()
         } else {
-            // 新对话，添加标题
-            format!("# Conversation: {}\n\n", session_id)
+            conversation_header(session_id, self.conversation_format)
         };
 
         let new_content = format!("{}{}", existing, entry);
         
-        fs::write(&conv_file, new_content).await
+        self.write_file(&conv_file, &new_content).await
             .with_context(|| format!("写入对话历史失败: {}", conv_file.display()))?;
 
         debug!("已添加消息到对话历史: {} - {}", session_id, role);
         Ok(())
     }
 
+    /// 对话历史的当前长度，配合 [`Self::truncate_conversation`] 撤销之后追加的消息
+    pub async fn conversation_len(&self, session_id: &str) -> u64 {
+        let conv_file = self.get_conversation_file(session_id);
+        if self.vault.is_some() {
+            // 加密文件按明文长度计算
+            return match self.read_file(&conv_file).await {
+                Ok(content) => content.len() as u64,
+                Err(_) => 0,
+            };
+        }
+        fs::metadata(&conv_file).await.map(|m| m.len()).unwrap_or(0)
+    }
+
+    /// 将对话历史截断到指定长度（长度为 0 时删除文件）
+    pub async fn truncate_conversation(&self, session_id: &str, len: u64) -> Result<()> {
+        let conv_file = self.get_conversation_file(session_id);
+        if !conv_file.exists() {
+            return Ok(());
+        }
+        if len == 0 {
+            fs::remove_file(&conv_file).await
+        } else if self.vault.is_some() {
+            let mut content = self.read_file(&conv_file).await?;
+            if content.is_char_boundary(len as usize) {
+                content.truncate(len as usize);
+            }
+            return self.write_file(&conv_file, &content).await;
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .open(&conv_file)
+                .await?
+                .set_len(len)
+                .await
+        }
+        .with_context(|| format!("截断对话历史失败: {}", conv_file.display()))
+    }
+
+    /// 读取对话历史原文（Markdown，JSONL 格式时转换为 Markdown），从第 `offset` 字节开始
+    pub async fn read_conversation_text(&self, session_id: &str, offset: u64) -> Result<String> {
+        let conv_file = self.get_conversation_file(session_id);
+        if !conv_file.exists() {
+            return Ok(String::new());
+        }
+        let content = self.read_file(&conv_file).await
+            .with_context(|| format!("读取对话历史失败: {}", conv_file.display()))?;
+        let mut offset = (offset as usize).min(content.len());
+        while !content.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        match self.conversation_format {
+            ConversationFormat::Markdown => Ok(content[offset..].to_string()),
+            ConversationFormat::Jsonl => Ok(parse_conversation_jsonl(&content[offset..], session_id)
+                .iter()
+                .map(format_markdown_entry)
+                .collect()),
+        }
+    }
+
     /// 获取对话历史
+    ///
+    /// `limit` 大于 0 时只返回最近的 `limit` 条消息
     pub async fn get_conversation(
         &self,
         session_id: &str,
-        _limit: i64,
+        limit: i64,
     ) -> Result<Vec<ConversationMessage>> {
         let conv_file = self.get_conversation_file(session_id);
 
@@ -169,125 +480,334 @@ impl MemoryStore {
             return Ok(Vec::new());
         }
 
-        let content = fs::read_to_string(&conv_file).await
+        let content = self.read_file(&conv_file).await
             .with_context(|| format!("读取对话历史失败: {}", conv_file.display()))?;
 
-        // 解析 Markdown 格式的对话历史
-        let messages = parse_conversation_markdown(&content, session_id);
-        
+        let mut messages = match self.conversation_format {
+            ConversationFormat::Markdown => parse_conversation_markdown(&content, session_id),
+            ConversationFormat::Jsonl => parse_conversation_jsonl(&content, session_id),
+        };
+        if limit > 0 && messages.len() > limit as usize {
+            messages.drain(..messages.len() - limit as usize);
+        }
+
         Ok(messages)
     }
 
-    /// 保存记忆（简化实现）
+    /// 保存记忆，`importance` 为 0-10，超过 `max_memories` 时淘汰得分最低的记忆
+    ///
+    /// 键已存在时更新其内容；指定的分类与原分类不同时移动到新分类下
     pub async fn save_memory(
         &self,
         key: &str,
         value: &str,
         category: Option<&str>,
-        _importance: i32,
+        importance: i32,
     ) -> Result<()> {
-        let mut content = self.read_long_term().await?;
-        
-        // 如果文件为空，初始化基本结构
-        if content.is_empty() {
-            content = "# Long-term Memory\n\n".to_string();
+        let content = self.read_long_term().await?;
+        let content = upsert_memory_entry(&content, category, key, value);
+        self.write_long_term(&content).await?;
+
+        let now = Utc::now();
+        let mut index = self.read_index().await;
+        if let Some(meta) = index.get_mut(key) {
+            // 重复保存同一记忆视为一次使用
+            meta.hits += 1;
+            meta.updated_at = now;
         }
+        index
+            .entry(key.to_string())
+            .or_insert_with(|| MemoryMeta::new(now))
+            .importance = importance.clamp(0, MAX_IMPORTANCE);
+        self.write_index(&index).await?;
 
-        let category_display = category.unwrap_or("General");
-        let section_header = format!("## {}", category_display);
-        let entry = format!("- **{}**: {}", key, value);
+        info!("已保存记忆: {} = {}（重要性 {}）", key, value, importance);
+        self.enforce_limit().await?;
+        Ok(())
+    }
 
-        // 简单实现：直接追加到文件末尾
-        if !content.contains(&section_header) {
-            content.push_str(&format!("\n{}\n\n{}\n", section_header, entry));
-        } else {
-            // 在现有分类下追加
-            content.push_str(&format!("{}\n", entry));
+    /// 更新已有记忆的内容（保留分类和重要性），返回记忆是否存在
+    #[allow(dead_code)]
+    pub async fn update_memory(&self, key: &str, value: &str) -> Result<bool> {
+        let content = self.read_long_term().await?;
+        if !parse_memory_entries(&content).iter().any(|(_, k, _)| k == key) {
+            return Ok(false);
         }
-
+        let content = upsert_memory_entry(&content, None, key, value);
         self.write_long_term(&content).await?;
-        info!("已保存记忆: {} = {}", key, value);
-        Ok(())
+
+        let now = Utc::now();
+        let mut index = self.read_index().await;
+        let meta = index.entry(key.to_string()).or_insert_with(|| MemoryMeta::new(now));
+        meta.hits += 1;
+        meta.updated_at = now;
+        self.write_index(&index).await?;
+
+        info!("已更新记忆: {} = {}", key, value);
+        Ok(true)
+    }
+
+    /// 所有分类（按在文件中出现的顺序）
+    pub async fn categories(&self) -> Result<Vec<String>> {
+        let content = self.read_long_term().await?;
+        Ok(content
+            .lines()
+            .filter_map(|l| l.strip_prefix("## "))
+            .map(|h| h.trim().to_string())
+            .collect())
+    }
+
+    /// 所有记忆（同一键保留最后一条），附带重要性等元数据
+    pub async fn list_memories(&self) -> Result<Vec<Memory>> {
+        let content = self.read_long_term().await?;
+        let index = self.read_index().await;
+        // 没有元数据的记忆（如手动编辑）以文件修改时间为准
+        let modified: DateTime<Utc> = fs::metadata(&self.memory_file)
+            .await
+            .and_then(|m| m.modified())
+            .map(DateTime::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        let mut memories: Vec<Memory> = Vec::new();
+        for (category, key, value) in parse_memory_entries(&content) {
+            let meta = index.get(&key).cloned().unwrap_or(MemoryMeta {
+                importance: DEFAULT_IMPORTANCE,
+                hits: 0,
+                created_at: modified,
+                updated_at: modified,
+                accessed_at: None,
+            });
+            memories.retain(|m| m.key != key);
+            memories.push(Memory {
+                key,
+                value,
+                category,
+                importance: meta.importance,
+                hits: meta.hits,
+                created_at: meta.created_at,
+                updated_at: meta.updated_at,
+                accessed_at: meta.accessed_at,
+            });
+        }
+        Ok(memories)
     }
 
     /// 获取记忆
+    #[allow(dead_code)]
     pub async fn get_memory(
         &self,
         key: &str,
     ) -> Result<Option<Memory>> {
-        let content = self.read_long_term().await?;
-        
-        // 简单实现：在 Markdown 中搜索键
-        for line in content.lines() {
-            if line.contains(&format!("**{}**:", key)) {
-                // 解析值
-                if let Some(value) = line.split(':').nth(1) {
-                    return Ok(Some(Memory {
-                        key: key.to_string(),
-                        value: value.trim().to_string(),
-                        category: None,
-                        importance: 0,
-                        created_at: Utc::now(),
-                        updated_at: Utc::now(),
-                    }));
-                }
-            }
+        let memory = self.list_memories().await?.into_iter().find(|m| m.key == key);
+        if let Some(ref memory) = memory {
+            self.touch(std::slice::from_ref(&memory.key)).await?;
         }
-        
-        Ok(None)
+        Ok(memory)
     }
 
-    /// 搜索记忆
+    /// 搜索记忆，按得分从高到低返回（`limit` 为 0 时不限制数量）
     pub async fn search_memories(
         &self,
         query: &str,
-        _limit: i64,
+        limit: i64,
     ) -> Result<Vec<Memory>> {
-        let content = self.read_long_term().await?;
-        let mut results = Vec::new();
-        
-        for line in content.lines() {
-            if line.to_lowercase().contains(&query.to_lowercase()) {
-                // 尝试解析为记忆条目
-                if line.starts_with("- **") {
-                    if let Some(key_end) = line.find("**:") {
-                        let key = line[4..key_end].to_string();
-                        let value = line[key_end + 3..].trim().to_string();
-                        results.push(Memory {
-                            key,
-                            value,
-                            category: None,
-                            importance: 0,
-                            created_at: Utc::now(),
-                            updated_at: Utc::now(),
-                        });
-                    }
-                }
-            }
+        let query = query.to_lowercase();
+        let now = Utc::now();
+        let mut results: Vec<Memory> = self
+            .list_memories()
+            .await?
+            .into_iter()
+            .filter(|m| {
+                m.key.to_lowercase().contains(&query)
+                    || m.value.to_lowercase().contains(&query)
+                    || m.category.as_ref().is_some_and(|c| c.to_lowercase().contains(&query))
+            })
+            .collect();
+        results.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+        if limit > 0 {
+            results.truncate(limit as usize);
+        }
+
+        let keys: Vec<String> = results.iter().map(|m| m.key.clone()).collect();
+        self.touch(&keys).await?;
+        Ok(results)
+    }
+
+    /// 为用户保存记忆（按用户分区时保存到该用户的分区，否则保存为共享记忆）
+    pub async fn save_memory_for(
+        &self,
+        user: Option<&str>,
+        key: &str,
+        value: &str,
+        category: Option<&str>,
+        importance: i32,
+    ) -> Result<()> {
+        match self.scope(user) {
+            Some(store) => store.save_memory(key, value, category, importance).await,
+            None => self.save_memory(key, value, category, importance).await,
+        }
+    }
+
+    /// 用户可见的所有记忆：该用户分区中的和共享的（同一键以用户自己的为准）
+    pub async fn list_visible(&self, user: Option<&str>) -> Result<Vec<Memory>> {
+        let mut memories = self.list_memories().await?;
+        if let Some(store) = self.scope(user) {
+            let own = store.list_memories().await?;
+            memories.retain(|m| !own.iter().any(|o| o.key == m.key));
+            memories.extend(own);
+        }
+        Ok(memories)
+    }
+
+    /// 在用户可见的记忆中搜索，按得分从高到低返回（`limit` 为 0 时不限制数量）
+    pub async fn search_visible(
+        &self,
+        user: Option<&str>,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<Memory>> {
+        let Some(store) = self.scope(user) else {
+            return self.search_memories(query, limit).await;
+        };
+        let own = store.search_memories(query, limit).await?;
+        let mut results: Vec<Memory> = self
+            .search_memories(query, limit)
+            .await?
+            .into_iter()
+            .filter(|m| !own.iter().any(|o| o.key == m.key))
+            .collect();
+        results.extend(own);
+        let now = Utc::now();
+        results.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+        if limit > 0 {
+            results.truncate(limit as usize);
         }
-        
         Ok(results)
     }
 
+    /// 把记忆移动到另一个分区（保留分类和元数据），`keys` 为 None 时移动全部，返回移动的键
+    pub async fn move_memories(&self, to: &MemoryStore, keys: Option<&[String]>) -> Result<Vec<String>> {
+        let memories: Vec<Memory> = self
+            .list_memories()
+            .await?
+            .into_iter()
+            .filter(|m| keys.is_none_or(|keys| keys.contains(&m.key)))
+            .collect();
+        if memories.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut content = to.read_long_term().await?;
+        let mut index = to.read_index().await;
+        for m in &memories {
+            content = upsert_memory_entry(&content, m.category.as_deref(), &m.key, &m.value);
+            index.insert(
+                m.key.clone(),
+                MemoryMeta {
+                    importance: m.importance,
+                    hits: m.hits,
+                    created_at: m.created_at,
+                    updated_at: m.updated_at,
+                    accessed_at: m.accessed_at,
+                },
+            );
+        }
+        to.write_long_term(&content).await?;
+        to.write_index(&index).await?;
+
+        let moved: Vec<String> = memories.into_iter().map(|m| m.key).collect();
+        self.remove_entries(&moved).await?;
+        info!("已移动 {} 条记忆到 {}", moved.len(), to.memory_file.display());
+        Ok(moved)
+    }
+
     /// 删除记忆
+    #[allow(dead_code)]
     pub async fn delete_memory(
         &self,
         key: &str,
     ) -> Result<()> {
+        self.remove_entries(&[key.to_string()]).await?;
+        info!("已删除记忆: {}", key);
+        Ok(())
+    }
+
+    /// 超过记忆上限时淘汰得分最低的记忆，返回被淘汰的键
+    pub async fn enforce_limit(&self) -> Result<Vec<String>> {
+        if self.max_memories == 0 {
+            return Ok(Vec::new());
+        }
+        let mut memories = self.list_memories().await?;
+        if memories.len() <= self.max_memories {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        memories.sort_by(|a, b| a.score(now).total_cmp(&b.score(now)));
+        let evicted: Vec<String> = memories
+            .iter()
+            .take(memories.len() - self.max_memories)
+            .map(|m| m.key.clone())
+            .collect();
+        self.remove_entries(&evicted).await?;
+        info!("记忆超过上限 {}，已淘汰: {}", self.max_memories, evicted.join(", "));
+        Ok(evicted)
+    }
+
+    /// 从长期记忆和元数据中删除指定的键
+    async fn remove_entries(&self, keys: &[String]) -> Result<()> {
         let content = self.read_long_term().await?;
-        let mut new_content = String::new();
-        
-        for line in content.lines() {
-            if !line.contains(&format!("**{}**:", key)) {
-                new_content.push_str(line);
-                new_content.push('\n');
+        self.write_long_term(remove_memory_entries(&content, keys)).await?;
+
+        let mut index = self.read_index().await;
+        for key in keys {
+            index.remove(key);
+        }
+        self.write_index(&index).await
+    }
+
+    /// 记录记忆被使用（计入使用次数，刷新最近使用时间）
+    async fn touch(&self, keys: &[String]) -> Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now();
+        let mut index = self.read_index().await;
+        for key in keys {
+            let meta = index.entry(key.clone()).or_insert_with(|| MemoryMeta::new(now));
+            meta.hits += 1;
+            meta.accessed_at = Some(now);
+        }
+        self.write_index(&index).await
+    }
+
+    /// 读取记忆元数据，文件不存在或损坏时返回空
+    async fn read_index(&self) -> HashMap<String, MemoryMeta> {
+        if !self.index_file.exists() {
+            return HashMap::new();
+        }
+        match self.read_file(&self.index_file).await.map(|c| serde_json::from_str(&c)) {
+            Ok(Ok(index)) => index,
+            Ok(Err(e)) => {
+                warn!("记忆元数据格式错误，已忽略: {}", e);
+                HashMap::new()
+            }
+            Err(e) => {
+                warn!("读取记忆元数据失败: {}", e);
+                HashMap::new()
             }
         }
-        
-        self.write_long_term(new_content).await?;
-        info!("已删除记忆: {}", key);
-        
-        Ok(())
+    }
+
+    /// 写入记忆元数据
+    async fn write_index(&self, index: &HashMap<String, MemoryMeta>) -> Result<()> {
+        let content = serde_json::to_string_pretty(index)?;
+        if let Some(dir) = self.index_file.parent() {
+            fs::create_dir_all(dir).await
+                .with_context(|| format!("创建记忆目录失败: {}", dir.display()))?;
+        }
+        self.write_file(&self.index_file, &content).await
+            .with_context(|| format!("写入记忆元数据失败: {}", self.index_file.display()))
     }
 
     /// 获取所有会话 ID
@@ -300,7 +820,7 @@ impl MemoryStore {
         
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
-            if path.extension().map(|e| e == "md").unwrap_or(false) {
+            if path.extension().map(|e| e == self.conversation_format.extension()).unwrap_or(false) {
                 if let Some(stem) = path.file_stem() {
                     sessions.push(stem.to_string_lossy().to_string());
                 }
@@ -310,71 +830,279 @@ impl MemoryStore {
         Ok(sessions)
     }
 
-    /// 获取 memory 目录路径
-    pub fn memory_dir(&self) -> &Path {
-        &self.memory_dir
+    /// 把其他格式的对话历史转换为 `to` 格式，返回转换的会话数
+    ///
+    /// 目标格式的文件已存在时跳过该会话，转换成功后删除原文件
+    pub async fn convert_conversations(&self, to: ConversationFormat) -> Result<usize> {
+        let from = match to {
+            ConversationFormat::Markdown => ConversationFormat::Jsonl,
+            ConversationFormat::Jsonl => ConversationFormat::Markdown,
+        };
+        let mut converted = 0;
+        let mut entries = fs::read_dir(&self.conversations_dir).await
+            .with_context(|| "读取对话目录失败")?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != from.extension()) {
+                continue;
+            }
+            let Some(session_id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let target = self.conversation_file(&session_id, to);
+            if target.exists() {
+                warn!("{} 已存在，跳过转换", target.display());
+                continue;
+            }
+
+            let content = self.read_file(&path).await
+                .with_context(|| format!("读取对话历史失败: {}", path.display()))?;
+            let messages = match from {
+                ConversationFormat::Markdown => parse_conversation_markdown(&content, &session_id),
+                ConversationFormat::Jsonl => parse_conversation_jsonl(&content, &session_id),
+            };
+            let mut output = conversation_header(&session_id, to);
+            for message in &messages {
+                match to {
+                    ConversationFormat::Markdown => output.push_str(&format_markdown_entry(message)),
+                    ConversationFormat::Jsonl => output.push_str(&format_jsonl_entry(message)?),
+                }
+            }
+            self.write_file(&target, &output).await
+                .with_context(|| format!("写入对话历史失败: {}", target.display()))?;
+            fs::remove_file(&path).await
+                .with_context(|| format!("删除对话历史失败: {}", path.display()))?;
+            converted += 1;
+        }
+        Ok(converted)
     }
 
-    /// 获取工作区路径
-    pub fn workspace(&self) -> &Path {
-        &self.workspace
+    /// 对话历史最后修改时间
+    pub async fn conversation_modified(&self, session_id: &str) -> Option<DateTime<Local>> {
+        let conv_file = self.get_conversation_file(session_id);
+        fs::metadata(&conv_file).await.and_then(|m| m.modified()).ok().map(DateTime::from)
+    }
+
+    /// 会话是否有对话历史
+    pub fn has_conversation(&self, session_id: &str) -> bool {
+        self.get_conversation_file(session_id).exists()
+    }
+
+    /// 删除会话的对话历史（两种格式），返回文件是否存在
+    pub async fn delete_conversation(&self, session_id: &str) -> Result<bool> {
+        let mut removed = false;
+        for format in [ConversationFormat::Markdown, ConversationFormat::Jsonl] {
+            let conv_file = self.conversation_file(session_id, format);
+            if conv_file.exists() {
+                fs::remove_file(&conv_file).await
+                    .with_context(|| format!("删除对话历史失败: {}", conv_file.display()))?;
+                removed = true;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 删除最后修改时间早于 `before` 的对话历史，返回删除数量
+    pub async fn prune_conversations(&self, before: std::time::SystemTime) -> Result<usize> {
+        let mut removed = 0;
+        for session_id in self.list_sessions().await? {
+            let conv_file = self.get_conversation_file(&session_id);
+            let modified = fs::metadata(&conv_file).await.and_then(|m| m.modified());
+            if matches!(modified, Ok(t) if t < before) && self.delete_conversation(&session_id).await? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 删除日常笔记、长期记忆（含元数据和各用户的分区）和用户资料，返回删除的文件数
+    pub async fn delete_notes(&self) -> Result<usize> {
+        let mut removed = 0;
+        for dir in [self.memory_dir.clone(), self.memory_dir.join("users")] {
+            if !dir.exists() {
+                continue;
+            }
+            let mut entries = fs::read_dir(&dir).await
+                .with_context(|| format!("读取目录失败: {}", dir.display()))?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.is_dir() && dir != self.memory_dir {
+                    // 用户的长期记忆分区
+                    fs::remove_dir_all(&path).await
+                        .with_context(|| format!("删除用户记忆失败: {}", path.display()))?;
+                    removed += 1;
+                } else if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
+                    fs::remove_file(&path).await
+                        .with_context(|| format!("删除笔记失败: {}", path.display()))?;
+                    removed += 1;
+                }
+            }
+        }
+        for file in [&self.index_file, &self.pending_file] {
+            if file.exists() {
+                fs::remove_file(file).await
+                    .with_context(|| format!("删除记忆元数据失败: {}", file.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 新对话文件的开头（Markdown 为标题，JSONL 没有）
+fn conversation_header(session_id: &str, format: ConversationFormat) -> String {
+    match format {
+        ConversationFormat::Markdown => format!("# Conversation: {}\n\n", session_id),
+        ConversationFormat::Jsonl => String::new(),
+    }
+}
+
+/// 格式化一条 Markdown 对话消息
+///
+/// 工具调用（单行 JSON）和 tool_call_id 附在内容末尾:
+/// `**assistant**:content [tool_calls:[...]]`、`**tool**:content [call_id:xxx]`
+fn format_markdown_entry(message: &ConversationMessage) -> String {
+    let mut suffix = String::new();
+    if let Some(ref calls) = message.tool_calls {
+        suffix.push_str(&format!(" [tool_calls:{}]", calls.replace('\n', " ")));
+    }
+    if let Some(ref id) = message.tool_call_id {
+        suffix.push_str(&format!(" [call_id:{}]", id));
+    }
+    format!(
+        "## {}\n**{}**:{}{}\n\n",
+        message.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+        message.role,
+        message.content,
+        suffix
+    )
+}
+
+/// JSONL 对话历史中的一行：完整的 [`Message`] 加时间戳
+#[derive(Serialize, Deserialize)]
+struct TranscriptLine {
+    timestamp: DateTime<Local>,
+    #[serde(flatten)]
+    message: Message,
+}
+
+/// 格式化一条 JSONL 对话消息（含换行）
+fn format_jsonl_entry(message: &ConversationMessage) -> Result<String> {
+    let role: Role = serde_json::from_value(serde_json::Value::String(message.role.clone()))
+        .with_context(|| format!("未知的消息角色: {}", message.role))?;
+    let tool_calls = match message.tool_calls {
+        Some(ref calls) => Some(serde_json::from_str(calls).context("工具调用不是合法的 JSON")?),
+        None => None,
+    };
+    let line = TranscriptLine {
+        timestamp: message.created_at.with_timezone(&Local),
+        message: Message {
+            role,
+            content: message.content.clone(),
+            tool_calls,
+            tool_call_id: message.tool_call_id.clone(),
+        },
+    };
+    Ok(format!("{}\n", serde_json::to_string(&line)?))
+}
+
+/// 解析 JSONL 对话历史，跳过无法解析的行
+fn parse_conversation_jsonl(content: &str, session_id: &str) -> Vec<ConversationMessage> {
+    let mut messages = Vec::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let line: TranscriptLine = match serde_json::from_str(line) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("跳过无法解析的对话记录（{}）: {}", session_id, e);
+                continue;
+            }
+        };
+        let role = serde_json::to_value(&line.message.role)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        messages.push(ConversationMessage {
+            id: messages.len() as i64,
+            session_id: session_id.to_string(),
+            role,
+            content: line.message.content,
+            tool_calls: line.message.tool_calls.and_then(|c| serde_json::to_string(&c).ok()),
+            tool_call_id: line.message.tool_call_id,
+            created_at: line.timestamp.with_timezone(&Utc),
+        });
     }
+    messages
 }
 
 /// 解析对话历史 Markdown
+///
+/// 每条消息以时间戳标题开头：`## 2026-02-07 12:30:00`，下一行为 `**role**:内容`，
+/// 内容可以有多行；带工具调用的助手消息以 ` [tool_calls:JSON]` 结尾，工具结果以 ` [call_id:xxx]` 结尾
 fn parse_conversation_markdown(content: &str, session_id: &str) -> Vec<ConversationMessage> {
-    let mut messages = Vec::new();
+    let mut messages: Vec<ConversationMessage> = Vec::new();
     let mut current_timestamp = Utc::now();
-    
+    // 最后一条消息是否还有后续内容行
+    let mut open = false;
+
     for line in content.lines() {
-        // 解析时间戳行: ## 2026-02-07 12:30:00
-        if line.starts_with("## ") {
-            let timestamp_str = &line[3..];
-            if let Ok(dt) = DateTime::parse_from_str(timestamp_str, "%Y-%m-%d %H:%M:%S") {
-                current_timestamp = dt.with_timezone(&Utc);
+        // 时间戳行（内容中的其他标题作为正文）
+        let timestamp = line
+            .strip_prefix("## ")
+            .and_then(|t| NaiveDateTime::parse_from_str(t.trim(), "%Y-%m-%d %H:%M:%S").ok());
+        if let Some(timestamp) = timestamp {
+            if let Some(local) = Local.from_local_datetime(&timestamp).earliest() {
+                current_timestamp = local.with_timezone(&Utc);
             }
+            open = false;
+            continue;
         }
-        // 解析消息行: **User**: content 或 **tool**: content [call_id:xxx]
-        else if line.starts_with("**") {
-            // 找到 **role**: 的模式
-            if let Some(colon_idx) = line.find("**:") {
-                // 提取 role (在 ** 和 ** 之间)
-                let role_part = &line[2..colon_idx];
-                if let Some(role_end) = role_part.find("**") {
-                    let role = &role_part[..role_end];
-                    let after_colon = &line[colon_idx + 3..];
-                    
-                    // 解析内容和可选的 call_id
-                    let (content, tool_call_id) = if let Some(call_start) = after_colon.find(" [call_id:") {
-                        let content = &after_colon[..call_start];
-                        let call_id = &after_colon[call_start + 10..];
-                        let call_id = call_id.trim_end_matches(']').to_string();
-                        (content.to_string(), Some(call_id))
-                    } else {
-                        (after_colon.to_string(), None)
-                    };
-                    
-                    messages.push(ConversationMessage {
-                        id: messages.len() as i64,
-                        session_id: session_id.to_string(),
-                        role: role.to_lowercase(),
-                        content,
-                        tool_calls: None,
-                        tool_call_id,
-                        created_at: current_timestamp,
-                    });
-                }
+
+        // 消息行: **user**:content
+        let message = line.strip_prefix("**").and_then(|rest| rest.split_once("**:"));
+        if let Some((role, content)) = message.filter(|(role, _)| {
+            matches!(*role, "user" | "assistant" | "tool" | "system" | "User" | "Assistant")
+        }) {
+            messages.push(ConversationMessage {
+                id: messages.len() as i64,
+                session_id: session_id.to_string(),
+                role: role.to_lowercase(),
+                content: content.to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+                created_at: current_timestamp,
+            });
+            open = true;
+        } else if let (true, Some(last)) = (open, messages.last_mut()) {
+            last.content.push('\n');
+            last.content.push_str(line);
+        }
+    }
+
+    for message in &mut messages {
+        let mut content = message.content.trim_end();
+        if let Some((rest, call_id)) = content.strip_suffix(']').and_then(|c| c.rsplit_once(" [call_id:")) {
+            message.tool_call_id = Some(call_id.to_string());
+            content = rest;
+        }
+        if let Some((rest, calls)) = content.strip_suffix(']').and_then(|c| c.rsplit_once(" [tool_calls:")) {
+            // 只接受合法的 JSON 数组，避免误解析正文
+            if serde_json::from_str::<Vec<serde_json::Value>>(calls).is_ok() {
+                message.tool_calls = Some(calls.to_string());
+                content = rest;
             }
         }
+        message.content = content.to_string();
     }
-    
+
     messages
 }
 
 /// 对话消息
 #[derive(Debug, Clone)]
 pub struct ConversationMessage {
+    #[allow(dead_code)]
     pub id: i64,
+    #[allow(dead_code)]
     pub session_id: String,
     pub role: String,
     pub content: String,
@@ -389,9 +1117,213 @@ pub struct Memory {
     pub key: String,
     pub value: String,
     pub category: Option<String>,
+    /// 重要性（0-10）
     pub importance: i32,
+    /// 使用次数（被检索或重复保存）
+    pub hits: u32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 最近一次被检索的时间
+    pub accessed_at: Option<DateTime<Utc>>,
+}
+
+/// 从对话中提取的记忆
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtractedMemory {
+    /// 分类：Facts、Preferences 或 TODOs
+    #[serde(default = "default_extracted_category")]
+    pub category: String,
+    pub key: String,
+    pub value: String,
+    #[serde(default = "default_extracted_importance")]
+    pub importance: i32,
+}
+
+fn default_extracted_category() -> String {
+    DEFAULT_CATEGORY.to_string()
+}
+
+fn default_extracted_importance() -> i32 {
+    DEFAULT_IMPORTANCE
+}
+
+/// 重要性上限
+pub const MAX_IMPORTANCE: i32 = 10;
+/// 未指定重要性时的默认值
+pub const DEFAULT_IMPORTANCE: i32 = 5;
+/// 最近使用加分的上限
+const RECENCY_WEIGHT: f64 = 3.0;
+/// 最近使用加分的半衰期（天）
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+impl Memory {
+    /// 淘汰时使用的得分：重要性 + 最近使用加分（按半衰期衰减）+ 使用次数加分（对数增长）
+    pub fn score(&self, now: DateTime<Utc>) -> f64 {
+        let last_used = self.accessed_at.map_or(self.updated_at, |a| a.max(self.updated_at));
+        let age_days = (now - last_used).num_seconds().max(0) as f64 / 86400.0;
+        let recency = RECENCY_WEIGHT * 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        let frequency = (1.0 + self.hits as f64).ln();
+        self.importance as f64 + recency + frequency
+    }
+}
+
+/// 长期记忆的元数据（保存在 memory/memory_index.json，MEMORY.md 保持原格式）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MemoryMeta {
+    importance: i32,
+    #[serde(default)]
+    hits: u32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    accessed_at: Option<DateTime<Utc>>,
+}
+
+impl MemoryMeta {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            importance: DEFAULT_IMPORTANCE,
+            hits: 0,
+            created_at: now,
+            updated_at: now,
+            accessed_at: None,
+        }
+    }
+}
+
+/// 未指定分类时使用的分类
+pub const DEFAULT_CATEGORY: &str = "General";
+
+/// 用户资料和记忆分区使用的文件名（替换路径分隔符）
+fn user_file_name(user: &str) -> String {
+    user.chars()
+        .map(|c| if matches!(c, '/' | '\\') { '_' } else { c })
+        .collect()
+}
+
+/// 在长期记忆中写入一条记忆，返回新的文件内容
+///
+/// - 键已存在：原地更新内容（删除重复的条目）；`category` 与原分类不同时移动到新分类
+/// - 键不存在：追加到 `category`（默认 General）小节的最后一条之后，小节不存在时新建
+fn upsert_memory_entry(content: &str, category: Option<&str>, key: &str, value: &str) -> String {
+    let prefix = format!("- **{}**:", key);
+    let entry = format!("{} {}", prefix, value.trim());
+    let mut lines: Vec<String> = if content.trim().is_empty() {
+        vec!["# Long-term Memory".to_string(), String::new()]
+    } else {
+        content.lines().map(str::to_string).collect()
+    };
+
+    // 每行所在的分类
+    let mut current = None;
+    let sections: Vec<Option<String>> = lines
+        .iter()
+        .map(|line| {
+            if let Some(header) = line.strip_prefix("## ") {
+                current = Some(header.trim().to_string());
+            }
+            current.clone()
+        })
+        .collect();
+
+    let existing: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].starts_with(&prefix)).collect();
+    if let Some(&first) = existing.first() {
+        let same_section = category.is_none_or(|c| sections[first].as_deref() == Some(c));
+        if same_section {
+            lines[first] = entry;
+            for &i in existing[1..].iter().rev() {
+                lines.remove(i);
+            }
+            return lines.join("\n") + "\n";
+        }
+        for &i in existing.iter().rev() {
+            lines.remove(i);
+        }
+    }
+
+    let category = category.unwrap_or(DEFAULT_CATEGORY);
+    let header = lines
+        .iter()
+        .position(|l| l.strip_prefix("## ").is_some_and(|h| h.trim() == category));
+    match header {
+        Some(header) => {
+            // 小节结束于下一个标题
+            let end = lines[header + 1..]
+                .iter()
+                .position(|l| l.starts_with('#'))
+                .map_or(lines.len(), |i| header + 1 + i);
+            // 插入到小节最后一个非空行之后
+            let at = (header + 1..end)
+                .rev()
+                .find(|&i| !lines[i].trim().is_empty())
+                .map_or(header + 1, |i| i + 1);
+            if at == header + 1 {
+                lines.insert(at, String::new());
+                lines.insert(at + 1, entry);
+            } else {
+                lines.insert(at, entry);
+            }
+        }
+        None => {
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+            lines.extend([String::new(), format!("## {}", category), String::new(), entry]);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// 从长期记忆中删除指定键的条目，删除后变空的小节一并删除
+fn remove_memory_entries(content: &str, keys: &[String]) -> String {
+    let prefixes: Vec<String> = keys.iter().map(|k| format!("- **{}**:", k)).collect();
+    let is_removed = |line: &str| prefixes.iter().any(|p| line.starts_with(p.as_str()));
+
+    let mut result: Vec<&str> = Vec::new();
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.next() {
+        if !line.starts_with("## ") {
+            if !is_removed(line) {
+                result.push(line);
+            }
+            continue;
+        }
+        // 收集整个小节，删除条目后只剩空行时丢弃该小节
+        let mut section = vec![line];
+        let mut removed_any = false;
+        while let Some(&next) = lines.peek() {
+            if next.starts_with('#') {
+                break;
+            }
+            lines.next();
+            if is_removed(next) {
+                removed_any = true;
+            } else {
+                section.push(next);
+            }
+        }
+        if removed_any && section[1..].iter().all(|l| l.trim().is_empty()) {
+            continue;
+        }
+        result.extend(section);
+    }
+    result.join("\n") + "\n"
+}
+
+/// 解析长期记忆中的条目，返回 (分类, 键, 值)
+fn parse_memory_entries(content: &str) -> Vec<(Option<String>, String, String)> {
+    let mut category = None;
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix("## ") {
+            category = Some(header.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("- **") {
+            if let Some((key, value)) = rest.split_once("**:") {
+                entries.push((category.clone(), key.to_string(), value.trim().to_string()));
+            }
+        }
+    }
+    entries
 }
 
 #[cfg(test)]
@@ -419,6 +1351,24 @@ mod tests {
         assert_eq!(long_term, "# Test Memory\n");
     }
 
+    #[tokio::test]
+    async fn test_recent_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+        assert_eq!(store.recent_notes(100).await.unwrap(), "");
+
+        let yesterday = Local::now().date_naive() - Duration::days(1);
+        fs::write(store.get_day_file(yesterday), "# 昨天\n\n- 计划发布 v0.2").await.unwrap();
+        store.append_today(format!("- 开头\n{}\n- 最新事件", "x".repeat(200))).await.unwrap();
+
+        let notes = store.recent_notes(50).await.unwrap();
+        assert!(notes.contains("计划发布 v0.2"));
+        // 超出长度时保留最新的部分
+        assert!(notes.contains("最新事件"));
+        assert!(!notes.contains("开头"));
+        assert!(notes.find("计划发布").unwrap() < notes.find("最新事件").unwrap());
+    }
+
     #[tokio::test]
     async fn test_conversation() {
         let temp_dir = TempDir::new().unwrap();
@@ -434,4 +1384,224 @@ mod tests {
         assert_eq!(messages[0].role, "user");
         assert_eq!(messages[0].content.trim(), "Hello");
     }
+
+    #[test]
+    fn test_parse_conversation_markdown() {
+        let content = "# Conversation: s\n\n## 2026-02-07 12:30:00\n**user**:第一行\n## 小标题\n\n- 列表\n\n## 2026-02-07 12:30:05\n**tool**:结果 [call_id:call_1]\n\n";
+        let messages = parse_conversation_markdown(content, "s");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "第一行\n## 小标题\n\n- 列表");
+        assert_eq!(messages[1].content, "结果");
+        assert_eq!(messages[1].tool_call_id.as_deref(), Some("call_1"));
+        assert!(messages[1].created_at > messages[0].created_at);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_transcript() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+        let calls = r#"[{"id":"call_1","type":"function","function":{"name":"shell","arguments":"{\"command\":\"ls\"}"}}]"#;
+
+        store.add_message("s", "user", "列出文件", None).await.unwrap();
+        store.add_message_with_tool_calls("s", "assistant", "", Some(calls), None).await.unwrap();
+        store.add_message("s", "tool", "a.txt\nb.txt", Some("call_1")).await.unwrap();
+        store.add_message("s", "assistant", "有两个文件", None).await.unwrap();
+
+        let messages = store.get_conversation("s", 0).await.unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].tool_calls.as_deref(), Some(calls));
+        assert_eq!(messages[1].content, "");
+        assert_eq!(messages[2].content, "a.txt\nb.txt");
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert!(messages[3].tool_calls.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_conversation_and_convert() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap()
+            .with_conversation_format(ConversationFormat::Jsonl);
+        let calls = r#"[{"id":"call_1","type":"function","function":{"name":"shell","arguments":"{}"}}]"#;
+
+        store.add_message("s", "user", "第一行\n第二行", None).await.unwrap();
+        store.add_message_with_tool_calls("s", "assistant", "", Some(calls), None).await.unwrap();
+        store.add_message("s", "tool", "a.txt", Some("call_1")).await.unwrap();
+        assert!(temp_dir.path().join("memory/conversations/s.jsonl").exists());
+        assert_eq!(store.list_sessions().await.unwrap(), vec!["s".to_string()]);
+
+        let messages = store.get_conversation("s", 0).await.unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "第一行\n第二行");
+        assert_eq!(messages[1].tool_calls.as_deref(), Some(calls));
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert!(store.read_conversation_text("s", 0).await.unwrap().contains("**tool**:a.txt [call_id:call_1]"));
+
+        // 转换为 Markdown 后内容不变
+        assert_eq!(store.convert_conversations(ConversationFormat::Markdown).await.unwrap(), 1);
+        let markdown = MemoryStore::new(temp_dir.path()).await.unwrap();
+        assert!(!temp_dir.path().join("memory/conversations/s.jsonl").exists());
+        let converted = markdown.get_conversation("s", 0).await.unwrap();
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[0].content, messages[0].content);
+        assert_eq!(converted[1].tool_calls, messages[1].tool_calls);
+        assert_eq!(converted[2].tool_call_id, messages[2].tool_call_id);
+
+        // 再转换回 JSONL
+        assert_eq!(markdown.convert_conversations(ConversationFormat::Jsonl).await.unwrap(), 1);
+        assert_eq!(store.get_conversation("s", 0).await.unwrap().len(), 3);
+        assert!(store.delete_conversation("s").await.unwrap());
+        assert!(!store.has_conversation("s"));
+    }
+
+    #[test]
+    fn test_upsert_memory_entry() {
+        let content = "# Long-term Memory\n\n## Facts\n\n- **生日**: 5 月 1 日\n\n## Preferences\n\n- **语言**: Rust\n";
+
+        // 追加到已有小节的末尾，而不是文件末尾
+        let content = upsert_memory_entry(content, Some("Facts"), "城市", "上海");
+        let entries = parse_memory_entries(&content);
+        assert_eq!(entries[1], (Some("Facts".to_string()), "城市".to_string(), "上海".to_string()));
+        assert!(content.find("城市").unwrap() < content.find("## Preferences").unwrap());
+
+        // 原地更新，不产生重复
+        let content = upsert_memory_entry(&content, None, "语言", "Go");
+        assert_eq!(content.matches("**语言**").count(), 1);
+        assert!(content.contains("- **语言**: Go"));
+
+        // 指定其他分类时移动
+        let content = upsert_memory_entry(&content, Some("Preferences"), "城市", "北京");
+        let entries = parse_memory_entries(&content);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2], (Some("Preferences".to_string()), "城市".to_string(), "北京".to_string()));
+
+        // 新分类追加到文件末尾
+        let content = upsert_memory_entry(&content, None, "备注", "无");
+        assert!(content.ends_with("## General\n\n- **备注**: 无\n"));
+
+        let content = remove_memory_entries(&content, &["备注".to_string(), "生日".to_string()]);
+        assert!(!content.contains("## General"));
+        assert!(!content.contains("## Facts"));
+        assert!(content.contains("## Preferences"));
+
+        let empty = upsert_memory_entry("", Some("Facts"), "a", "b");
+        assert_eq!(empty, "# Long-term Memory\n\n## Facts\n\n- **a**: b\n");
+    }
+
+    #[tokio::test]
+    async fn test_memory_eviction() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap().with_max_memories(2);
+
+        store.save_memory("生日", "5 月 1 日", Some("Facts"), 9).await.unwrap();
+        store.save_memory("午饭", "面条", None, 1).await.unwrap();
+        store.save_memory("语言", "Rust", Some("Preferences"), 3).await.unwrap();
+
+        // 超过上限时淘汰重要性最低的记忆
+        let memories = store.list_memories().await.unwrap();
+        let keys: Vec<&str> = memories.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["生日", "语言"]);
+        assert_eq!(memories[0].category.as_deref(), Some("Facts"));
+        assert_eq!(memories[0].importance, 9);
+
+        // 检索计入使用次数
+        let found = store.search_memories("rust", 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(store.get_memory("语言").await.unwrap().unwrap().hits, 1);
+
+        let now = Utc::now();
+        let fresh = &memories[1];
+        let stale = Memory {
+            updated_at: now - Duration::days(90),
+            ..fresh.clone()
+        };
+        assert!(fresh.score(now) > stale.score(now));
+
+        assert!(store.update_memory("语言", "Go").await.unwrap());
+        assert!(!store.update_memory("午饭", "米饭").await.unwrap());
+        let updated = store.get_memory("语言").await.unwrap().unwrap();
+        assert_eq!(updated.value, "Go");
+        assert_eq!(updated.category.as_deref(), Some("Preferences"));
+        assert_eq!(store.categories().await.unwrap(), ["Facts", "Preferences"]);
+
+        store.delete_memory("生日").await.unwrap();
+        assert!(store.get_memory("生日").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_user_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+
+        assert!(store.read_user_profile("telegram:42").await.unwrap().is_none());
+        store.save_user_fact("telegram:42", "name", "小明").await.unwrap();
+        store.save_user_fact("telegram:42", "timezone", "Asia/Shanghai").await.unwrap();
+        store.save_user_fact("telegram:42", "name", "明明").await.unwrap();
+
+        assert_eq!(store.user_fact("telegram:42", "name").await.unwrap().as_deref(), Some("明明"));
+        let profile = store.read_user_profile("telegram:42").await.unwrap().unwrap();
+        assert_eq!(profile.matches("**name**").count(), 1);
+        assert!(profile.contains("- **timezone**: Asia/Shanghai"));
+        // 不同用户互不影响
+        assert!(store.user_fact("telegram:43", "name").await.unwrap().is_none());
+
+        assert_eq!(store.delete_notes().await.unwrap(), 1);
+        assert!(store.read_user_profile("telegram:42").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_per_user_memories() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap().with_per_user(true);
+
+        store.save_memory("团队周会", "周一 10 点", None, 5).await.unwrap();
+        store.save_memory("老王的生日", "3 月 1 日", Some("Facts"), 7).await.unwrap();
+        store.save_memory_for(Some("telegram:1"), "咖啡", "美式", None, 5).await.unwrap();
+        store.save_memory_for(Some("telegram:2"), "咖啡", "拿铁", None, 5).await.unwrap();
+        assert!(temp_dir.path().join("memory/users/telegram:1/MEMORY.md").exists());
+
+        // 每个用户只看到自己的和共享的记忆
+        let visible = store.search_visible(Some("telegram:1"), "咖啡", 0).await.unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].value, "美式");
+        assert_eq!(store.list_visible(Some("telegram:2")).await.unwrap().len(), 3);
+        assert_eq!(store.list_visible(None).await.unwrap().len(), 2);
+
+        // 迁移共享记忆到用户分区，保留分类和重要性
+        let user = store.for_user("telegram:1");
+        let moved = store.move_memories(&user, Some(&["老王的生日".to_string()])).await.unwrap();
+        assert_eq!(moved, vec!["老王的生日".to_string()]);
+        let birthday = user.get_memory("老王的生日").await.unwrap().unwrap();
+        assert_eq!((birthday.category.as_deref(), birthday.importance), (Some("Facts"), 7));
+        assert!(store.list_visible(Some("telegram:2")).await.unwrap().iter().all(|m| m.key != "老王的生日"));
+
+        // 删除用户资料时一并删除该用户的记忆
+        assert!(store.delete_user_profile("telegram:1").await.unwrap());
+        assert!(store.list_visible(Some("telegram:1")).await.unwrap().iter().all(|m| m.key != "咖啡"));
+    }
+
+    #[tokio::test]
+    async fn test_vault_encrypts_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = Arc::new(Vault::from_key(&[7u8; 32]));
+        let store = MemoryStore::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_vault(Some(vault));
+
+        store.write_long_term("机密").await.unwrap();
+        let raw = std::fs::read(temp_dir.path().join("memory/MEMORY.md")).unwrap();
+        assert!(Vault::is_encrypted(&raw));
+        assert_eq!(store.read_long_term().await.unwrap(), "机密");
+
+        // 撤销按明文长度截断
+        store.add_message("s", "user", "one", None).await.unwrap();
+        let len = store.conversation_len("s").await;
+        store.add_message("s", "user", "two", None).await.unwrap();
+        store.truncate_conversation("s", len).await.unwrap();
+        assert_eq!(store.conversation_len("s").await, len);
+
+        // 未提供口令时拒绝读取加密文件
+        let plain = MemoryStore::new(temp_dir.path()).await.unwrap();
+        assert!(plain.read_long_term().await.is_err());
+    }
 }
@@ -0,0 +1,343 @@
+//! 记忆审核
+//!
+//! 启用 `[memory.review]` 后，模型写入的记忆（`remember` 工具和对话记忆提取）不直接保存，
+//! 而是进入待审核队列（memory/pending_memories.json）。管理员用 `/memories pending` 或
+//! `nanobot memory pending` 查看，批准后才成为长期记忆；也可按 `digest_schedule` 定时收到待审核摘要
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use super::{MemoryStore, DEFAULT_CATEGORY};
+use crate::channel::outbox::Outbox;
+use crate::channel::quiet::{send_proactive, QuietHours};
+use crate::channel::{Channel, ChannelTarget};
+use crate::config::MemoryReviewConfig;
+use crate::cron::reminder::{ReminderSchedule, Timezone};
+use crate::cron::{Job, JobHandler};
+use crate::t;
+
+/// 待审核摘要任务使用的处理器名称
+pub const MEMORY_DIGEST_HANDLER: &str = "memory_digest";
+
+/// 待审核的记忆
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingMemory {
+    /// 序号（审核时使用）
+    pub id: u64,
+    /// 所属用户，None 为共享记忆
+    #[serde(default)]
+    pub user: Option<String>,
+    pub key: String,
+    pub value: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    pub importance: i32,
+    /// 来源：remember（工具）或 extraction（对话提取）
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 审核操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviewAction {
+    /// 查看待审核的记忆
+    Pending,
+    /// 批准（None 表示全部）
+    Approve(Option<Vec<u64>>),
+    /// 拒绝（None 表示全部）
+    Reject(Option<Vec<u64>>),
+}
+
+impl ReviewAction {
+    /// 解析 `pending`、`approve <序号...|all>`、`reject <序号...|all>`，参数为空时为 pending
+    pub fn parse(args: &str) -> Option<Self> {
+        let mut parts = args.split_whitespace();
+        let action = parts.next().map(str::to_lowercase);
+        let ids: Vec<&str> = parts.collect();
+        let selection = || -> Option<Option<Vec<u64>>> {
+            match ids.as_slice() {
+                [] => None,
+                ["all" | "全部"] => Some(None),
+                ids => ids
+                    .iter()
+                    .map(|id| id.trim_start_matches('#').parse().ok())
+                    .collect::<Option<Vec<u64>>>()
+                    .map(Some),
+            }
+        };
+        match action.as_deref() {
+            None | Some("pending" | "待审核") => Some(Self::Pending),
+            Some("approve" | "批准") => selection().map(Self::Approve),
+            Some("reject" | "拒绝") => selection().map(Self::Reject),
+            Some(_) => None,
+        }
+    }
+}
+
+impl MemoryStore {
+    /// 是否启用记忆审核
+    pub fn with_review(mut self, review: bool) -> Self {
+        self.review = review;
+        self
+    }
+
+    /// 保存模型写入的记忆：启用审核时加入待审核队列并返回 true，否则直接保存
+    ///
+    /// 同一用户同一键已在队列中时更新为新的内容
+    pub async fn submit_memory(
+        &self,
+        user: Option<&str>,
+        key: &str,
+        value: &str,
+        category: Option<&str>,
+        importance: i32,
+        source: &str,
+    ) -> Result<bool> {
+        if !self.review {
+            self.save_memory_for(user, key, value, category, importance).await?;
+            return Ok(false);
+        }
+        // 未按用户分区时记忆都是共享的
+        let user = user.filter(|_| self.per_user).map(str::to_string);
+        let mut pending = self.pending_memories().await?;
+        let id = pending.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        pending.retain(|p| !(p.user == user && p.key == key));
+        pending.push(PendingMemory {
+            id,
+            user,
+            key: key.to_string(),
+            value: value.to_string(),
+            category: category.map(str::to_string),
+            importance,
+            source: source.to_string(),
+            created_at: Utc::now(),
+        });
+        self.write_pending(&pending).await?;
+        info!("记忆 {} 已加入待审核队列（#{}）", key, id);
+        Ok(true)
+    }
+
+    /// 待审核的记忆（按提交顺序）
+    pub async fn pending_memories(&self) -> Result<Vec<PendingMemory>> {
+        if !self.pending_file.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_file(&self.pending_file).await?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("待审核记忆格式错误: {}", e))
+    }
+
+    /// 批准待审核的记忆（`ids` 为 None 时全部批准），返回已保存的记忆
+    pub async fn approve_pending(&self, ids: Option<&[u64]>) -> Result<Vec<PendingMemory>> {
+        let approved = self.take_pending(ids).await?;
+        for p in &approved {
+            self.save_memory_for(p.user.as_deref(), &p.key, &p.value, p.category.as_deref(), p.importance)
+                .await?;
+        }
+        Ok(approved)
+    }
+
+    /// 拒绝待审核的记忆（`ids` 为 None 时全部拒绝），返回被丢弃的记忆
+    pub async fn reject_pending(&self, ids: Option<&[u64]>) -> Result<Vec<PendingMemory>> {
+        let rejected = self.take_pending(ids).await?;
+        if !rejected.is_empty() {
+            info!("已拒绝 {} 条待审核记忆", rejected.len());
+        }
+        Ok(rejected)
+    }
+
+    /// 删除某个用户的待审核记忆，返回删除的条数
+    pub(super) async fn remove_user_pending(&self, user: &str) -> Result<usize> {
+        let mut pending = self.pending_memories().await?;
+        let before = pending.len();
+        pending.retain(|p| p.user.as_deref() != Some(user));
+        if pending.len() != before {
+            self.write_pending(&pending).await?;
+        }
+        Ok(before - pending.len())
+    }
+
+    /// 从队列中取出指定的记忆，序号不存在时报错
+    async fn take_pending(&self, ids: Option<&[u64]>) -> Result<Vec<PendingMemory>> {
+        let pending = self.pending_memories().await?;
+        if let Some(ids) = ids {
+            if let Some(missing) = ids.iter().find(|id| !pending.iter().any(|p| p.id == **id)) {
+                bail!("待审核记忆 #{} 不存在", missing);
+            }
+        }
+        let (taken, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|p| ids.is_none_or(|ids| ids.contains(&p.id)));
+        if !taken.is_empty() {
+            self.write_pending(&rest).await?;
+        }
+        Ok(taken)
+    }
+
+    async fn write_pending(&self, pending: &[PendingMemory]) -> Result<()> {
+        let content = serde_json::to_string_pretty(pending)?;
+        self.write_file(&self.pending_file, &content).await
+    }
+}
+
+/// 待审核记忆列表，首行为标题，没有待审核记忆时返回提示
+pub fn render_pending(pending: &[PendingMemory]) -> String {
+    if pending.is_empty() {
+        return t!("memory_review.empty");
+    }
+    let mut lines = vec![t!("memory_review.title", count = pending.len())];
+    for p in pending {
+        lines.push(t!(
+            "memory_review.line",
+            id = p.id,
+            owner = p.user.clone().unwrap_or_else(|| t!("memory_review.shared")),
+            key = p.key,
+            value = p.value,
+            category = p.category.as_deref().unwrap_or(DEFAULT_CATEGORY),
+            importance = p.importance
+        ));
+    }
+    lines.push(t!("memory_review.hint"));
+    lines.join("\n")
+}
+
+/// 根据配置创建待审核摘要任务（不持久化），未设置 digest_schedule 时返回 None
+pub fn digest_job(config: &MemoryReviewConfig, timezone: Timezone) -> Result<Option<Job>> {
+    let Some(ref schedule) = config.digest_schedule else {
+        return Ok(None);
+    };
+    let job = match timezone.parse_when(schedule, Utc::now())? {
+        ReminderSchedule::Cron(expression) => Job::new_cron("记忆审核摘要", expression, MEMORY_DIGEST_HANDLER),
+        ReminderSchedule::ZonedCron(expression, tz) => {
+            Job::new_cron("记忆审核摘要", expression, MEMORY_DIGEST_HANDLER).with_timezone(tz)
+        }
+        ReminderSchedule::Interval(seconds) => Job::new_interval("记忆审核摘要", seconds, MEMORY_DIGEST_HANDLER),
+        ReminderSchedule::Once(_) => bail!("摘要时间需要是重复的时间（如\"每天晚上八点\"）: {}", schedule),
+    };
+    Ok(Some(
+        job.with_description(format!("待审核记忆摘要（{}）", schedule))
+            .with_args(json!({
+                "channel": config.channel,
+                "chat_id": config.chat_id,
+            }))
+            .non_persistent(),
+    ))
+}
+
+/// 待审核摘要任务处理器
+///
+/// 有待审核的记忆时把列表发送到任务参数中的 channel / chat_id，未指定目标时按任务的投递方式处理
+pub struct MemoryDigestHandler {
+    memory: Arc<MemoryStore>,
+    channels: Vec<Arc<dyn Channel>>,
+    quiet_hours: QuietHours,
+    outbox: Option<Arc<Outbox>>,
+}
+
+impl MemoryDigestHandler {
+    pub fn new(memory: Arc<MemoryStore>, channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self {
+            memory,
+            channels,
+            quiet_hours: QuietHours::default(),
+            outbox: None,
+        }
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// 设置发件箱（免打扰时段内的摘要排队到其中）
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+}
+
+#[async_trait]
+impl JobHandler for MemoryDigestHandler {
+    fn name(&self) -> &str {
+        MEMORY_DIGEST_HANDLER
+    }
+
+    async fn execute(&self, _job: &Job, args: Option<Value>) -> Result<Option<String>> {
+        let pending = self.memory.pending_memories().await?;
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        let digest = render_pending(&pending);
+
+        let args = args.unwrap_or(Value::Null);
+        let channel = args.get("channel").and_then(|v| v.as_str());
+        let chat_id = args.get("chat_id").and_then(|v| v.as_str());
+        match (channel, chat_id) {
+            (Some(channel), Some(chat_id)) => {
+                let target = self
+                    .channels
+                    .iter()
+                    .find(|c| c.name() == channel)
+                    .ok_or_else(|| anyhow!("记忆审核摘要的目标通道不存在: {}", channel))?;
+                let to = ChannelTarget::parse(chat_id)?;
+                send_proactive(&self.quiet_hours, self.outbox.as_ref(), target.clone(), &to, &digest).await?;
+                Ok(None)
+            }
+            // 未指定聊天时按任务的投递方式处理
+            _ => Ok(Some(digest)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(ReviewAction::parse(""), Some(ReviewAction::Pending));
+        assert_eq!(ReviewAction::parse("approve 1 #3"), Some(ReviewAction::Approve(Some(vec![1, 3]))));
+        assert_eq!(ReviewAction::parse("拒绝 全部"), Some(ReviewAction::Reject(None)));
+        assert_eq!(ReviewAction::parse("approve"), None);
+        assert_eq!(ReviewAction::parse("approve x"), None);
+        assert_eq!(ReviewAction::parse("forget 1"), None);
+    }
+
+    #[tokio::test]
+    async fn test_review_queue() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path())
+            .await
+            .unwrap()
+            .with_per_user(true)
+            .with_review(true);
+
+        assert!(store.submit_memory(Some("telegram:1"), "生日", "5 月 1 日", None, 6, "remember").await.unwrap());
+        assert!(store.submit_memory(None, "周会", "周一", None, 5, "extraction").await.unwrap());
+        // 同一键再次提交时更新
+        store.submit_memory(Some("telegram:1"), "生日", "5 月 2 日", None, 6, "remember").await.unwrap();
+
+        let pending = store.pending_memories().await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[1].value, "5 月 2 日");
+        // 审核前不会成为长期记忆
+        assert!(store.list_visible(Some("telegram:1")).await.unwrap().is_empty());
+        assert!(render_pending(&pending).contains("#3"));
+
+        assert!(store.approve_pending(Some(&[9])).await.is_err());
+        let approved = store.approve_pending(Some(&[pending[1].id])).await.unwrap();
+        assert_eq!(approved.len(), 1);
+        let visible = store.list_visible(Some("telegram:1")).await.unwrap();
+        assert_eq!(visible[0].value, "5 月 2 日");
+        assert!(store.list_visible(None).await.unwrap().is_empty());
+
+        assert_eq!(store.reject_pending(None).await.unwrap().len(), 1);
+        assert!(store.pending_memories().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! 记忆的语义检索
+//!
+//! 启用 `[embeddings]` 后，`recall` 工具在关键词检索之外按向量相似度补充相关的记忆
+//! （如查询“饮料”找到“喜欢喝美式咖啡”）。记忆的向量在进程内缓存，内容不变时不重复计算
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use super::{Memory, MemoryStore};
+use crate::embeddings::{cosine_similarity, EmbeddingProvider};
+
+/// 按向量相似度排序记忆
+pub struct SemanticIndex {
+    provider: Arc<dyn EmbeddingProvider>,
+    /// 低于该相似度的记忆不返回
+    min_similarity: f32,
+    /// 记忆文本 -> 向量
+    cache: Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl SemanticIndex {
+    pub fn new(provider: Arc<dyn EmbeddingProvider>, min_similarity: f32) -> Self {
+        Self {
+            provider,
+            min_similarity,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 参与计算向量的记忆文本
+    fn text(memory: &Memory) -> String {
+        format!("{}: {}", memory.key, memory.value)
+    }
+
+    /// 按与查询的相似度从高到低排列记忆，去掉相似度不足的
+    pub async fn rank(&self, query: &str, memories: Vec<Memory>) -> Result<Vec<Memory>> {
+        if memories.is_empty() {
+            return Ok(memories);
+        }
+        let texts: Vec<String> = memories.iter().map(Self::text).collect();
+        let missing: Vec<String> = {
+            let cache = self.cache.lock().unwrap();
+            texts.iter().filter(|t| !cache.contains_key(*t)).cloned().collect()
+        };
+        if !missing.is_empty() {
+            let vectors = self.provider.embed(&missing).await?;
+            let mut cache = self.cache.lock().unwrap();
+            cache.extend(missing.into_iter().zip(vectors));
+        }
+        let query = self.provider.embed_one(query).await?;
+        // 提供商声明了维度时按声明校验，否则以查询向量为准；维度不符的记忆向量不参与排序
+        let dimensions = self.provider.dimensions().unwrap_or(query.len());
+        if query.len() != dimensions {
+            bail!("查询向量的维度为 {}，与提供商 {} 的 {} 不符", query.len(), self.provider.name(), dimensions);
+        }
+
+        let mut scored: Vec<(f32, Memory)> = {
+            let cache = self.cache.lock().unwrap();
+            memories
+                .into_iter()
+                .zip(&texts)
+                .filter_map(|(m, text)| {
+                    let vector = cache.get(text).filter(|v| v.len() == dimensions)?;
+                    let score = cosine_similarity(&query, vector);
+                    (score >= self.min_similarity).then_some((score, m))
+                })
+                .collect()
+        };
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(scored.into_iter().map(|(_, m)| m).collect())
+    }
+}
+
+impl MemoryStore {
+    /// 设置语义检索（未设置时只按关键词检索）
+    pub fn with_semantic(mut self, semantic: Option<Arc<SemanticIndex>>) -> Self {
+        self.semantic = semantic;
+        self
+    }
+
+    /// 检索用户可见的记忆：先按关键词，不足 `limit` 条时按语义相似度补充（`limit` 为 0 时不限制数量）
+    ///
+    /// 计算向量失败时只返回关键词检索的结果
+    pub async fn recall(&self, user: Option<&str>, query: &str, limit: i64) -> Result<Vec<Memory>> {
+        let mut results = self.search_visible(user, query, limit).await?;
+        let Some(ref semantic) = self.semantic else {
+            return Ok(results);
+        };
+        let wanted = if limit > 0 { limit as usize } else { usize::MAX };
+        if results.len() >= wanted {
+            return Ok(results);
+        }
+
+        let candidates: Vec<Memory> = self
+            .list_visible(user)
+            .await?
+            .into_iter()
+            .filter(|m| !results.iter().any(|r| r.key == m.key))
+            .collect();
+        let similar = match semantic.rank(query, candidates).await {
+            Ok(similar) => similar,
+            Err(e) => {
+                warn!("语义检索记忆失败: {}，只使用关键词检索的结果", e);
+                return Ok(results);
+            }
+        };
+        let similar: Vec<Memory> = similar.into_iter().take(wanted - results.len()).collect();
+
+        // 与关键词检索一样记录使用次数（用户自己的记忆记在其分区中）
+        let keys: Vec<String> = similar.iter().map(|m| m.key.clone()).collect();
+        match self.scope(user) {
+            Some(store) => {
+                let own = store.list_memories().await?;
+                let (own_keys, shared_keys): (Vec<String>, Vec<String>) =
+                    keys.into_iter().partition(|k| own.iter().any(|o| &o.key == k));
+                store.touch(&own_keys).await?;
+                self.touch(&shared_keys).await?;
+            }
+            None => self.touch(&keys).await?,
+        }
+        results.extend(similar);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 按是否包含几个关键字生成向量，记录计算过的文本条数
+    struct Keywords(AtomicUsize);
+
+    #[async_trait]
+    impl EmbeddingProvider for Keywords {
+        fn name(&self) -> &str {
+            "keywords"
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.0.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let has = |words: &[&str]| words.iter().any(|w| t.contains(w)) as u8 as f32;
+                    vec![has(&["咖啡", "饮料", "茶"]), has(&["猫", "宠物"]), 0.1]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recall_semantic() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let provider = Arc::new(Keywords(AtomicUsize::new(0)));
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+        store.save_memory("drink", "喜欢喝美式咖啡", None, 5).await.unwrap();
+        store.save_memory("pet", "养了一只猫", None, 5).await.unwrap();
+        store.save_memory("饮料偏好", "不加糖", None, 5).await.unwrap();
+
+        // 未启用语义检索时只按关键词
+        let found = store.recall(None, "饮料", 10).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.key.as_str()).collect::<Vec<_>>(), vec!["饮料偏好"]);
+
+        let store = store.with_semantic(Some(Arc::new(SemanticIndex::new(provider.clone(), 0.5))));
+        let found = store.recall(None, "饮料", 10).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.key.as_str()).collect::<Vec<_>>(), vec!["饮料偏好", "drink"]);
+        assert_eq!(store.get_memory("drink").await.unwrap().unwrap().hits, 1);
+
+        // 记忆的向量已缓存，再次检索只计算查询
+        let calls = provider.0.load(Ordering::SeqCst);
+        store.recall(None, "饮料", 10).await.unwrap();
+        assert_eq!(provider.0.load(Ordering::SeqCst), calls + 1);
+
+        let found = store.recall(None, "宠物", 1).await.unwrap();
+        assert_eq!(found.iter().map(|m| m.key.as_str()).collect::<Vec<_>>(), vec!["pet"]);
+    }
+
+    /// 声明的维度与实际返回的向量不符
+    struct WrongDimensions;
+
+    #[async_trait]
+    impl EmbeddingProvider for WrongDimensions {
+        fn name(&self) -> &str {
+            "wrong"
+        }
+
+        fn dimensions(&self) -> Option<usize> {
+            Some(4)
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|_| vec![1.0, 0.0, 0.0]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recall_rejects_wrong_dimensions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = MemoryStore::new(temp_dir.path()).await.unwrap();
+        store.save_memory("drink", "喜欢喝美式咖啡", None, 5).await.unwrap();
+
+        let index = SemanticIndex::new(Arc::new(WrongDimensions), 0.0);
+        let memories = store.list_visible(None).await.unwrap();
+        assert!(index.rank("饮料", memories).await.is_err());
+
+        // 语义检索失败时只返回关键词检索的结果
+        let store = store.with_semantic(Some(Arc::new(index)));
+        assert!(store.recall(None, "饮料", 10).await.unwrap().is_empty());
+    }
+}
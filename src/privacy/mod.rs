@@ -0,0 +1,477 @@
+//! 数据保留与清除
+//!
+//! - `[privacy] retention_days`：网关定期删除超过保留期的对话历史、会话（含用量统计）、
+//!   已完成的发件箱记录、LLM 调试日志和审计日志，并去掉过期用量记录中的用户标识
+//! - `nanobot purge`：按用户、会话或全部清除各存储中的数据
+//! - [`redact`]：导出分享前给对话打码
+
+pub mod redact;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::budget::Budget;
+use crate::channel::outbox::Outbox;
+use crate::config::Config;
+use crate::cron::reminder::REMINDER_HANDLER;
+use crate::cron::Scheduler;
+use crate::memory::MemoryStore;
+use crate::session::SessionManager;
+
+/// 保留期检查间隔
+const RETENTION_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// 清除范围
+#[derive(Debug, Clone, PartialEq)]
+pub enum PurgeTarget {
+    /// 指定用户（用户 ID 或聊天 ID）的所有会话
+    User(String),
+    /// 指定会话（如 telegram:123456）
+    Session(String),
+    /// 所有数据
+    All,
+}
+
+/// 清除结果
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PurgeReport {
+    pub conversations: usize,
+    pub sessions: usize,
+    pub outbox: u64,
+    pub reminders: usize,
+    pub notes: usize,
+    pub debug_logs: usize,
+    /// 去掉用户标识的用量记录
+    pub usage: u64,
+    /// API 审计日志条目
+    pub api_audit: usize,
+    /// 内容过滤审计日志条目
+    pub filter_audit: usize,
+}
+
+impl PurgeReport {
+    /// 删除的条目总数
+    pub fn total(&self) -> u64 {
+        (self.conversations
+            + self.sessions
+            + self.reminders
+            + self.notes
+            + self.debug_logs
+            + self.api_audit
+            + self.filter_audit) as u64
+            + self.outbox
+            + self.usage
+    }
+}
+
+impl std::fmt::Display for PurgeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "对话历史 {} 个，会话 {} 个，发件箱记录 {} 条，提醒 {} 个，笔记 {} 个，调试日志 {} 个，\
+             用量记录 {} 条，API 审计日志 {} 条，内容过滤日志 {} 条",
+            self.conversations,
+            self.sessions,
+            self.outbox,
+            self.reminders,
+            self.notes,
+            self.debug_logs,
+            self.usage,
+            self.api_audit,
+            self.filter_audit
+        )
+    }
+}
+
+/// 保存用户数据的各个存储
+pub struct DataStores {
+    memory: Option<MemoryStore>,
+    sessions: Option<Arc<SessionManager>>,
+    outbox: Option<Arc<Outbox>>,
+    scheduler: Option<Arc<Scheduler>>,
+    budget: Option<Budget>,
+    debug_dir: PathBuf,
+    api_audit_log: PathBuf,
+    filter_audit_log: PathBuf,
+}
+
+impl DataStores {
+    /// 打开工作目录中已存在的存储（不会创建新的数据库）
+    pub async fn open(config: &Config) -> Result<Self> {
+        let workspace = &config.memory.workspace_path;
+        let memory = if workspace.join("memory").exists() {
+            Some(MemoryStore::new(workspace).await?.with_conversation_format(config.memory.conversation_format))
+        } else {
+            None
+        };
+
+        let sessions = match existing(config.sessions_db_path()) {
+            Some(path) => Some(SessionManager::with_db(&path).await?),
+            None => None,
+        };
+        let outbox = match existing(config.outbox_db_path()) {
+            Some(path) => Some(Outbox::new(&path).await?),
+            None => None,
+        };
+        let scheduler = match existing(config.cron_db_path()) {
+            Some(path) => Some(Scheduler::with_db(&path).await?),
+            None => None,
+        };
+        let budget = match existing(config.usage_db_path()) {
+            Some(path) => Some(Budget::new(config.budget.clone(), &path).await?),
+            None => None,
+        };
+
+        Ok(Self {
+            memory,
+            sessions,
+            outbox,
+            scheduler,
+            budget,
+            debug_dir: config
+                .llm
+                .debug_log_dir
+                .clone()
+                .unwrap_or_else(|| config.default_llm_debug_dir()),
+            api_audit_log: config.api_audit_log_path(),
+            filter_audit_log: config.content_filter_log_path(),
+        })
+    }
+
+    /// 使用共享的会话管理器（网关中同时清理内存中的会话）
+    pub fn with_sessions(mut self, sessions: Arc<SessionManager>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// 清除指定范围内的数据
+    pub async fn purge(&self, target: &PurgeTarget) -> Result<PurgeReport> {
+        if *target == PurgeTarget::All {
+            return self.purge_all().await;
+        }
+
+        // 要删除的会话，以及这些会话对应的 (通道, 聊天 ID)
+        let mut session_ids = Vec::new();
+        let mut chats: Vec<(Option<String>, String)> = Vec::new();
+        match target {
+            PurgeTarget::Session(id) => session_ids.push(id.clone()),
+            PurgeTarget::User(user) => {
+                if let Some(ref sessions) = self.sessions {
+                    session_ids.extend(sessions.find_sessions_of(user).await?);
+                }
+                if let Some(ref memory) = self.memory {
+                    let suffix = format!(":{}", user);
+                    session_ids.extend(
+                        memory
+                            .list_sessions()
+                            .await?
+                            .into_iter()
+                            .filter(|id| id == user || id.ends_with(&suffix)),
+                    );
+                }
+                chats.push((None, user.clone()));
+            }
+            PurgeTarget::All => unreachable!(),
+        }
+        session_ids.sort();
+        session_ids.dedup();
+        for id in &session_ids {
+            if let Some((channel, chat_id)) = id.split_once(':') {
+                chats.push((Some(channel.to_string()), chat_id.to_string()));
+            }
+        }
+
+        let mut report = PurgeReport::default();
+        for id in &session_ids {
+            if let Some(ref memory) = self.memory {
+                if memory.delete_conversation(id).await? {
+                    report.conversations += 1;
+                }
+                if memory.delete_user_profile(id).await? {
+                    report.notes += 1;
+                }
+            }
+            if let Some(ref sessions) = self.sessions {
+                if sessions.delete_session(id).await? {
+                    report.sessions += 1;
+                }
+            }
+        }
+
+        for (channel, chat_id) in &chats {
+            if let Some(ref outbox) = self.outbox {
+                report.outbox += outbox.purge_target(channel.as_deref(), chat_id).await?;
+            }
+        }
+
+        if let Some(ref scheduler) = self.scheduler {
+            for job in scheduler.list_all_jobs().await {
+                let args = job.handler_args.as_ref();
+                let arg = |key: &str| args.and_then(|a| a.get(key)).and_then(|v| v.as_str());
+                let matches = chats.iter().any(|(channel, chat_id)| {
+                    arg("chat_id") == Some(chat_id.as_str())
+                        && channel.as_deref().is_none_or(|c| arg("channel") == Some(c))
+                });
+                if job.handler == REMINDER_HANDLER && matches {
+                    scheduler.remove_job(&job.id).await?;
+                    report.reminders += 1;
+                }
+            }
+        }
+
+        // 用量和审计日志中以会话 ID（channel:chat_id）或聊天 ID 标识用户
+        let is_user = |id: &str| {
+            session_ids.iter().any(|s| s == id)
+                || chats.iter().any(|(channel, chat_id)| match channel {
+                    Some(channel) => id.split_once(':') == Some((channel.as_str(), chat_id.as_str())),
+                    None => id == chat_id,
+                })
+        };
+        if let Some(ref budget) = self.budget {
+            report.usage = budget.forget_users(is_user).await?;
+        }
+        report.api_audit = prune_jsonl(&self.api_audit_log, |entry| {
+            let path = entry.get("path").and_then(|v| v.as_str()).unwrap_or_default();
+            path.split('/').any(is_user)
+        })
+        .await?;
+        report.filter_audit = prune_jsonl(&self.filter_audit_log, |entry| {
+            let field = |key: &str| entry.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+            let matches_chat = chats.iter().any(|(channel, chat_id)| {
+                field("chat_id") == chat_id && channel.as_deref().is_none_or(|c| field("channel") == c)
+            });
+            matches_chat || matches!(target, PurgeTarget::User(user) if field("sender") == user)
+        })
+        .await?;
+
+        Ok(report)
+    }
+
+    /// 清除所有对话、会话、发件箱、提醒、笔记、调试日志和审计日志，并去掉用量记录中的用户标识
+    async fn purge_all(&self) -> Result<PurgeReport> {
+        let mut report = PurgeReport::default();
+
+        if let Some(ref memory) = self.memory {
+            for id in memory.list_sessions().await? {
+                if memory.delete_conversation(&id).await? {
+                    report.conversations += 1;
+                }
+            }
+            report.notes = memory.delete_notes().await?;
+        }
+        if let Some(ref sessions) = self.sessions {
+            // 所有会话的最后活动时间都早于明天
+            report.sessions = sessions.prune_inactive(Utc::now() + Duration::days(1)).await?;
+        }
+        if let Some(ref outbox) = self.outbox {
+            report.outbox = outbox.purge_all().await?;
+        }
+        if let Some(ref scheduler) = self.scheduler {
+            for job in scheduler.list_all_jobs().await {
+                if job.handler == REMINDER_HANDLER {
+                    scheduler.remove_job(&job.id).await?;
+                    report.reminders += 1;
+                }
+            }
+        }
+        report.debug_logs = prune_dir(&self.debug_dir, None).await?;
+        if let Some(ref budget) = self.budget {
+            report.usage = budget.forget_users(|_| true).await?;
+        }
+        report.api_audit = prune_jsonl(&self.api_audit_log, |_| true).await?;
+        report.filter_audit = prune_jsonl(&self.filter_audit_log, |_| true).await?;
+
+        Ok(report)
+    }
+
+    /// 删除超过保留期的对话历史、会话、已完成的发件箱记录、调试日志和审计日志，
+    /// 并去掉过期用量记录中的用户标识
+    pub async fn prune(&self, retention_days: u64) -> Result<PurgeReport> {
+        let cutoff = Utc::now() - Duration::days(retention_days as i64);
+        let cutoff_time = SystemTime::from(cutoff);
+        let mut report = PurgeReport::default();
+
+        if let Some(ref memory) = self.memory {
+            report.conversations = memory.prune_conversations(cutoff_time).await?;
+        }
+        if let Some(ref sessions) = self.sessions {
+            report.sessions = sessions.prune_inactive(cutoff).await?;
+        }
+        if let Some(ref outbox) = self.outbox {
+            report.outbox = outbox.prune_finished(cutoff).await?;
+        }
+        report.debug_logs = prune_dir(&self.debug_dir, Some(cutoff_time)).await?;
+        if let Some(ref budget) = self.budget {
+            report.usage = budget.forget_users_before(cutoff).await?;
+        }
+        let expired = |key: &'static str| {
+            move |entry: &serde_json::Value| {
+                entry
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .is_some_and(|t| t < cutoff)
+            }
+        };
+        report.api_audit = prune_jsonl(&self.api_audit_log, expired("time")).await?;
+        report.filter_audit = prune_jsonl(&self.filter_audit_log, expired("timestamp")).await?;
+
+        Ok(report)
+    }
+
+    /// 启动后台任务，按保留期定期清理（启动时立即执行一次）
+    pub fn start_retention(self: Arc<Self>, retention_days: u64) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RETENTION_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                match self.prune(retention_days).await {
+                    Ok(report) if report.total() > 0 => {
+                        info!("已清理超过 {} 天的数据: {}", retention_days, report)
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("清理过期数据失败: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// 文件存在时返回路径字符串
+fn existing(path: PathBuf) -> Option<String> {
+    path.exists().then(|| path.to_string_lossy().to_string())
+}
+
+/// 删除目录中修改时间早于 `before` 的文件（None 表示全部），返回删除数量
+async fn prune_dir(dir: &Path, before: Option<SystemTime>) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("读取目录失败: {}", dir.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let expired = match before {
+            Some(before) => metadata.modified().map(|t| t < before).unwrap_or(false),
+            None => true,
+        };
+        if expired {
+            tokio::fs::remove_file(entry.path()).await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// 重写 JSONL 日志，删除 `remove` 返回 true 的条目（无法解析的行保留），返回删除数量
+async fn prune_jsonl(path: &Path, remove: impl Fn(&serde_json::Value) -> bool) -> Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("读取日志失败: {}", path.display()))?;
+    let mut kept = String::with_capacity(content.len());
+    let mut removed = 0;
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(entry) if remove(&entry) => removed += 1,
+            _ => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+    }
+    if removed == 0 {
+        return Ok(0);
+    }
+
+    let tmp = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&tmp, kept)
+        .await
+        .with_context(|| format!("写入日志失败: {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_purge_user() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap();
+        memory.add_message("telegram:42", "user", "hi", None).await.unwrap();
+        memory.add_message("telegram:7", "user", "hey", None).await.unwrap();
+        let sessions = SessionManager::with_db(&config.sessions_db_path().to_string_lossy())
+            .await
+            .unwrap();
+        sessions.get_or_create("telegram:42", "telegram", "42").await.unwrap();
+        sessions.get_or_create("telegram:7", "telegram", "7").await.unwrap();
+        let outbox = Outbox::new(&config.outbox_db_path().to_string_lossy()).await.unwrap();
+        outbox.enqueue("telegram", &crate::channel::ChannelTarget::chat("42"), "reply").await.unwrap();
+        let budget = Budget::new(config.budget.clone(), &config.usage_db_path().to_string_lossy())
+            .await
+            .unwrap();
+        let usage = crate::llm::Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        };
+        budget.record("openai", "gpt-4o", Some("telegram:42"), &usage).await.unwrap();
+        budget.record("openai", "gpt-4o", Some("telegram:7"), &usage).await.unwrap();
+        let now = Utc::now().to_rfc3339();
+        std::fs::write(
+            config.api_audit_log_path(),
+            format!(
+                "{{\"time\":\"{now}\",\"token\":\"t\",\"method\":\"GET\",\"path\":\"/api/sessions/telegram:42\",\"status\":200}}\n\
+                 {{\"time\":\"{now}\",\"token\":\"t\",\"method\":\"GET\",\"path\":\"/api/status\",\"status\":200}}\n"
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            config.content_filter_log_path(),
+            format!(
+                "{{\"channel\":\"telegram\",\"chat_id\":\"42\",\"sender\":\"42\",\"direction\":\"inbound\",\"action\":\"flag\",\"reason\":\"r\",\"timestamp\":\"{now}\"}}\n\
+                 {{\"channel\":\"telegram\",\"chat_id\":\"7\",\"sender\":\"7\",\"direction\":\"inbound\",\"action\":\"flag\",\"reason\":\"r\",\"timestamp\":\"{now}\"}}\n"
+            ),
+        )
+        .unwrap();
+
+        let stores = DataStores::open(&config).await.unwrap();
+        let report = stores.purge(&PurgeTarget::User("42".to_string())).await.unwrap();
+        assert_eq!(report.conversations, 1);
+        assert_eq!(report.sessions, 1);
+        assert_eq!(report.outbox, 1);
+        assert_eq!(report.usage, 1);
+        assert_eq!(report.api_audit, 1);
+        assert_eq!(report.filter_audit, 1);
+        assert!(budget.user_usage("telegram:42").await.unwrap().is_empty());
+        assert_eq!(budget.user_usage("telegram:7").await.unwrap().len(), 1);
+
+        // 其他用户的数据不受影响
+        assert_eq!(memory.list_sessions().await.unwrap(), vec!["telegram:7".to_string()]);
+        assert!(stores.sessions.as_ref().unwrap().load_session("telegram:7").await.unwrap().is_some());
+        let filter_log = std::fs::read_to_string(config.content_filter_log_path()).unwrap();
+        assert!(filter_log.contains("\"chat_id\":\"7\"") && !filter_log.contains("\"chat_id\":\"42\""));
+
+        // 保留期内的数据不会被清理
+        let report = stores.prune(30).await.unwrap();
+        assert_eq!(report.total(), 0);
+    }
+}
@@ -0,0 +1,157 @@
+//! 敏感信息打码
+//!
+//! 导出对话给他人查看（`nanobot share`）前，把配置中的密钥、常见格式的令牌和个人信息
+//! （邮箱、手机号、身份证号、IP 地址）替换为占位符；`[content_filter]` 中 action 为 redact 的规则
+//! 和 `[share] redact_patterns` 一并生效
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::{Config, FilterAction};
+use crate::memory::ConversationMessage;
+
+/// 配置中的密钥短于该长度时不按原文替换（避免误伤普通文本）
+const MIN_SECRET_LEN: usize = 8;
+
+/// 内置规则（正则, 占位符），按顺序替换
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    (r"(?i)\bBearer\s+[A-Za-z0-9._~+/=-]+", "Bearer [令牌]"),
+    (r"\b(?:sk|pk|rk|ghp|gho|github_pat|xox[abp])[-_][A-Za-z0-9_-]{16,}", "[密钥]"),
+    (r"\bAKIA[0-9A-Z]{16}\b", "[密钥]"),
+    (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[邮箱]"),
+    (r"\b\d{17}[\dXx]\b", "[证件号]"),
+    (r"\+\d{1,3}[ -]?\d{3,4}[ -]?\d{3,4}[ -]?\d{0,4}\b", "[电话]"),
+    (r"\b1[3-9]\d{9}\b", "[电话]"),
+    (r"\b(?:\d{1,3}\.){3}\d{1,3}\b", "[IP]"),
+];
+
+/// 自定义规则的占位符
+const CUSTOM_PLACEHOLDER: &str = "[已脱敏]";
+
+/// 打码器
+pub struct Redactor {
+    /// 配置中的密钥原文（按长度降序，先替换长的）
+    secrets: Vec<String>,
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    /// 按配置创建：收集配置中的密钥，加载内置规则、内容过滤的打码规则和自定义规则
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut secrets = Vec::new();
+        if let Ok(value) = serde_json::to_value(config) {
+            collect_secrets(&value, &mut secrets);
+        }
+
+        let mut rules = Vec::new();
+        for (pattern, placeholder) in BUILTIN_RULES {
+            rules.push((Regex::new(pattern)?, placeholder.to_string()));
+        }
+        for rule in config
+            .content_filter
+            .rules
+            .iter()
+            .filter(|r| r.action == FilterAction::Redact)
+        {
+            let regex = Regex::new(&rule.pattern).with_context(|| format!("内容过滤规则无效: {}", rule.pattern))?;
+            rules.push((regex, config.content_filter.redact_with.clone()));
+        }
+        for pattern in &config.share.redact_patterns {
+            let regex = Regex::new(pattern).with_context(|| format!("打码规则无效: {}", pattern))?;
+            rules.push((regex, CUSTOM_PLACEHOLDER.to_string()));
+        }
+
+        Ok(Self::new(secrets, rules))
+    }
+
+    fn new(mut secrets: Vec<String>, rules: Vec<(Regex, String)>) -> Self {
+        secrets.retain(|s| s.chars().count() >= MIN_SECRET_LEN);
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        secrets.dedup();
+        Self { secrets, rules }
+    }
+
+    /// 打码文本
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), "[密钥]");
+            }
+        }
+        for (regex, placeholder) in &self.rules {
+            if regex.is_match(&text) {
+                text = regex.replace_all(&text, placeholder.as_str()).into_owned();
+            }
+        }
+        text
+    }
+
+    /// 打码对话消息（内容和工具调用参数）
+    pub fn redact_message(&self, message: &ConversationMessage) -> ConversationMessage {
+        ConversationMessage {
+            content: self.redact(&message.content),
+            tool_calls: message.tool_calls.as_deref().map(|calls| self.redact(calls)),
+            ..message.clone()
+        }
+    }
+
+    /// 打码会话 ID：保留通道前缀，去掉聊天 ID（如 `telegram:123456` -> `telegram:***`）
+    pub fn redact_session_id(&self, session_id: &str) -> String {
+        match session_id.split_once(':') {
+            Some((channel, _)) => format!("{}:***", channel),
+            None => "***".to_string(),
+        }
+    }
+}
+
+/// 字段名是否表示密钥
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["api_key", "apikey", "token", "secret", "password", "encrypt_key"]
+        .iter()
+        .any(|k| key.contains(k))
+}
+
+/// 递归收集配置中密钥类字段的值
+fn collect_secrets(value: &Value, secrets: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(s) if is_secret_key(key) => secrets.push(s.clone()),
+                    Value::Array(items) if is_secret_key(key) => {
+                        secrets.extend(items.iter().filter_map(|v| v.as_str().map(str::to_string)))
+                    }
+                    _ => collect_secrets(value, secrets),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_secrets(v, secrets)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let mut config = Config::default();
+        config.llm.deepseek.api_key = Some("my-deepseek-key-123".to_string());
+        config.share.redact_patterns = vec![r"EMP-\d+".to_string()];
+        let redactor = Redactor::from_config(&config).unwrap();
+
+        let text = "key my-deepseek-key-123，Authorization: Bearer abc.def，联系 alice@example.com 或 13812345678，\
+                    工号 EMP-0042，服务器 10.0.0.12，openai sk-proj-abcdefghijklmnopqrstu";
+        assert_eq!(
+            redactor.redact(text),
+            "key [密钥]，Authorization: Bearer [令牌]，联系 [邮箱] 或 [电话]，\
+             工号 [已脱敏]，服务器 [IP]，openai [密钥]"
+        );
+        assert_eq!(redactor.redact("今天 3 点开会"), "今天 3 点开会");
+        assert_eq!(redactor.redact_session_id("telegram:123456"), "telegram:***");
+    }
+}
@@ -2,17 +2,33 @@
 //!
 //! 独立会话管理，支持多会话并发
 //! 会话状态持久化，与会话 ID 关联的上下文
+//! 数据库不可用时以内存模式运行并在后台重试连接
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+use sqlx::{Pool, Sqlite};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::bus::{EventBus, SessionEndedEvent};
+use crate::config::SessionConfig;
+use crate::vault::Vault;
+
+/// 置顶内容在会话上下文中的键
+const PINS_KEY: &str = "pinned";
+/// 会话指令在会话上下文中的键
+const INSTRUCTIONS_KEY: &str = "instructions";
+/// 会话元数据中记录当前角色的属性名
+pub const PERSONA_PROPERTY: &str = "persona";
+/// 会话元数据中记录标题的属性名
+pub const TITLE_PROPERTY: &str = "title";
+/// 数据库不可用时重试连接的间隔
+const DB_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
 /// 会话状态
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -27,6 +43,26 @@ pub enum SessionState {
     Ended,
 }
 
+impl SessionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionState::Active => "active",
+            SessionState::Idle => "idle",
+            SessionState::Paused => "paused",
+            SessionState::Ended => "ended",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "idle" => SessionState::Idle,
+            "paused" => SessionState::Paused,
+            "ended" => SessionState::Ended,
+            _ => SessionState::Active,
+        }
+    }
+}
+
 /// 会话元数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionMetadata {
@@ -64,6 +100,23 @@ pub struct SessionStats {
     pub tool_call_count: u64,
     /// 总令牌数（估算）
     pub total_tokens: u64,
+    /// 各角色（/persona）使用的令牌数
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub persona_tokens: HashMap<String, u64>,
+}
+
+impl SessionStats {
+    /// 累加另一份统计
+    pub fn merge(&mut self, other: &SessionStats) {
+        self.message_count += other.message_count;
+        self.user_message_count += other.user_message_count;
+        self.assistant_message_count += other.assistant_message_count;
+        self.tool_call_count += other.tool_call_count;
+        self.total_tokens += other.total_tokens;
+        for (persona, tokens) in &other.persona_tokens {
+            *self.persona_tokens.entry(persona.clone()).or_default() += tokens;
+        }
+    }
 }
 
 /// 会话上下文
@@ -95,11 +148,13 @@ impl SessionContext {
     }
 
     /// 删除值
+    #[allow(dead_code)]
     pub async fn remove(&self, key: &str) -> Option<serde_json::Value> {
         self.data.write().await.remove(key)
     }
 
     /// 清空所有数据
+    #[allow(dead_code)]
     pub async fn clear(&self) {
         self.data.write().await.clear();
     }
@@ -153,12 +208,14 @@ impl Session {
     }
 
     /// 设置用户 ID
+    #[allow(dead_code)]
     pub fn with_user_id(mut self, user_id: impl Into<String>) -> Self {
         self.metadata.user_id = Some(user_id.into());
         self
     }
 
     /// 添加属性
+    #[allow(dead_code)]
     pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.metadata.properties.insert(key.into(), value.into());
         self
@@ -191,7 +248,21 @@ impl Session {
         self.stats.total_tokens += tokens;
     }
 
+    /// 记录角色及其令牌用量，并把当前角色写入会话元数据
+    pub fn record_persona(&mut self, persona: &str, tokens: u64) {
+        *self.stats.persona_tokens.entry(persona.to_string()).or_default() += tokens;
+        self.metadata
+            .properties
+            .insert(PERSONA_PROPERTY.to_string(), persona.to_string());
+    }
+
+    /// 会话标题（自动生成或 /title 设置）
+    pub fn title(&self) -> Option<&str> {
+        self.metadata.properties.get(TITLE_PROPERTY).map(String::as_str)
+    }
+
     /// 暂停会话
+    #[allow(dead_code)]
     pub fn pause(&mut self) {
         self.state = SessionState::Paused;
         info!("会话 {} 已暂停", self.id);
@@ -218,6 +289,7 @@ impl Session {
     }
 
     /// 获取持续时间（秒）
+    #[allow(dead_code)]
     pub fn duration_secs(&self) -> i64 {
         let end = self.ended_at.unwrap_or_else(Utc::now);
         end.signed_duration_since(self.created_at).num_seconds()
@@ -228,100 +300,175 @@ impl Session {
 pub struct SessionManager {
     /// 活跃会话
     sessions: Arc<RwLock<HashMap<String, Arc<RwLock<Session>>>>>,
-    /// 数据库连接池
-    pool: Option<Pool<Sqlite>>,
+    /// 数据库连接池（内存模式或数据库不可用时为空）
+    pool: std::sync::RwLock<Option<Pool<Sqlite>>>,
+    /// 数据库路径（内存模式时为空）
+    db_path: Option<String>,
     /// 空闲超时（秒）
     idle_timeout: u64,
+    /// 会话结束时发布 SessionEndedEvent
+    event_bus: Option<Arc<EventBus>>,
+    /// 设置后会话上下文（置顶、指令等）加密存储
+    vault: Option<Arc<Vault>>,
 }
 
 impl SessionManager {
     /// 创建内存模式的会话管理器
+    #[allow(dead_code)]
     pub fn new() -> Arc<Self> {
-        Arc::new(Self {
-            sessions: Arc::new(RwLock::new(HashMap::new())),
-            pool: None,
-            idle_timeout: 3600, // 默认 1 小时
-        })
+        Arc::new(Self::default())
     }
 
     /// 创建带持久化的会话管理器
     pub async fn with_db(db_path: &str) -> Result<Arc<Self>> {
-        // 确保目录存在
-        if let Some(parent) = std::path::Path::new(db_path).parent() {
-            tokio::fs::create_dir_all(parent).await?;
+        Ok(Arc::new(Self::open(db_path).await?))
+    }
+
+    /// 按 `[session]` 配置创建持久化会话管理器
+    ///
+    /// 数据库不可用（文件被锁定、目录只读等）时以内存模式运行，并在后台重试连接
+    pub async fn from_config(
+        config: &SessionConfig,
+        db_path: &str,
+        event_bus: Option<Arc<EventBus>>,
+        vault: Option<Arc<Vault>>,
+    ) -> Result<Arc<Self>> {
+        let (manager, degraded) = match Self::open(db_path).await {
+            Ok(manager) => (manager, false),
+            Err(e) => {
+                warn!("会话数据库 {} 不可用: {:#}，以内存模式运行，每 {} 秒重试连接", db_path, e, DB_RETRY_INTERVAL.as_secs());
+                let manager = Self {
+                    db_path: Some(db_path.to_string()),
+                    ..Self::default()
+                };
+                (manager, true)
+            }
+        };
+        let mut manager = manager.with_idle_timeout(config.idle_timeout_secs);
+        manager.event_bus = event_bus;
+        manager.vault = vault;
+        let manager = Arc::new(manager);
+        if degraded {
+            manager.clone().start_reconnect();
         }
+        Ok(manager)
+    }
+
+    async fn open(db_path: &str) -> Result<Self> {
+        Ok(Self {
+            pool: std::sync::RwLock::new(Some(Self::connect(db_path).await?)),
+            db_path: Some(db_path.to_string()),
+            ..Self::default()
+        })
+    }
+
+    /// 是否处于降级模式（配置了数据库但当前不可用）
+    pub fn is_degraded(&self) -> bool {
+        self.db_path.is_some() && self.pool().is_none()
+    }
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(&format!("sqlite:{}", db_path))
+    fn pool(&self) -> Option<Pool<Sqlite>> {
+        self.pool.read().unwrap().clone()
+    }
+
+    /// 连接数据库并初始化表
+    async fn connect(db_path: &str) -> Result<Pool<Sqlite>> {
+        let pool = crate::db::connect(db_path, 5)
             .await
             .context("连接数据库失败")?;
 
-        let manager = Arc::new(Self {
-            sessions: Arc::new(RwLock::new(HashMap::new())),
-            pool: Some(pool),
-            idle_timeout: 3600,
-        });
-
         // 初始化数据库
-        manager.init_db().await?;
+        Self::init_db(&pool).await?;
+        Ok(pool)
+    }
 
-        Ok(manager)
+    /// 后台重试连接数据库，成功后保存内存中的会话并退出
+    fn start_reconnect(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let Some(db_path) = self.db_path.clone() else {
+                return;
+            };
+            loop {
+                tokio::time::sleep(DB_RETRY_INTERVAL).await;
+                let pool = match Self::connect(&db_path).await {
+                    Ok(pool) => pool,
+                    Err(e) => {
+                        warn!("会话数据库仍不可用: {:#}", e);
+                        continue;
+                    }
+                };
+                let sessions: Vec<Arc<RwLock<Session>>> = self.sessions.read().await.values().cloned().collect();
+                let mut saved = Ok(());
+                for session in sessions {
+                    saved = self.save_session_to_db(&*session.read().await, &pool).await;
+                    if saved.is_err() {
+                        break;
+                    }
+                }
+                match saved {
+                    Ok(()) => {
+                        *self.pool.write().unwrap() = Some(pool);
+                        info!("会话数据库已恢复: {}", db_path);
+                        return;
+                    }
+                    Err(e) => warn!("会话数据库恢复失败: {:#}", e),
+                }
+            }
+        })
     }
 
     /// 初始化数据库表
-    async fn init_db(&self) -> Result<()> {
-        if let Some(ref pool) = self.pool {
-            sqlx::query(
-                r#"
-                CREATE TABLE IF NOT EXISTS sessions (
-                    id TEXT PRIMARY KEY,
-                    state TEXT NOT NULL,
-                    user_id TEXT,
-                    channel TEXT NOT NULL,
-                    channel_id TEXT NOT NULL,
-                    properties TEXT,
-                    stats TEXT,
-                    created_at TIMESTAMP NOT NULL,
-                    last_activity TIMESTAMP NOT NULL,
-                    ended_at TIMESTAMP
-                )
-                "#
+    async fn init_db(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                state TEXT NOT NULL,
+                user_id TEXT,
+                channel TEXT NOT NULL,
+                channel_id TEXT NOT NULL,
+                properties TEXT,
+                stats TEXT,
+                created_at TIMESTAMP NOT NULL,
+                last_activity TIMESTAMP NOT NULL,
+                ended_at TIMESTAMP
             )
-            .execute(pool)
-            .await?;
+            "#
+        )
+        .execute(pool)
+        .await?;
 
-            sqlx::query(
-                "CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id)"
-            )
-            .execute(pool)
-            .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id)"
+        )
+        .execute(pool)
+        .await?;
 
-            sqlx::query(
-                "CREATE INDEX IF NOT EXISTS idx_sessions_channel ON sessions(channel, channel_id)"
-            )
-            .execute(pool)
-            .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_channel ON sessions(channel, channel_id)"
+        )
+        .execute(pool)
+        .await?;
 
-            // 会话上下文表
-            sqlx::query(
-                r#"
-                CREATE TABLE IF NOT EXISTS session_context (
-                    session_id TEXT NOT NULL,
-                    key TEXT NOT NULL,
-                    value TEXT NOT NULL,
-                    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    PRIMARY KEY (session_id, key)
-                )
-                "#
+        // 会话上下文表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_context (
+                session_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (session_id, key)
             )
-            .execute(pool)
-            .await?;
-        }
+            "#
+        )
+        .execute(pool)
+        .await?;
         Ok(())
     }
 
     /// 创建新会话
+    #[allow(dead_code)]
     pub async fn create_session(
         &self,
         channel: impl Into<String>,
@@ -338,21 +485,367 @@ impl SessionManager {
             .insert(session_id.clone(), session_arc.clone());
 
         // 持久化
-        if let Some(ref pool) = self.pool {
+        if let Some(ref pool) = self.pool() {
             let session_guard = session_arc.read().await;
-            self.save_session_to_db(&*session_guard, pool).await?;
+            self.save_session_to_db(&session_guard, pool).await?;
         }
 
         info!("创建会话: {}", session_id);
         Ok(session_arc)
     }
 
+    /// 获取指定 ID 的会话，内存中没有时从数据库恢复，仍不存在则创建
+    ///
+    /// Agent 以自身的会话 ID（如 `telegram:123`）调用，保证重启后统计能延续
+    pub async fn get_or_create(
+        &self,
+        session_id: &str,
+        channel: &str,
+        channel_id: &str,
+    ) -> Result<Arc<RwLock<Session>>> {
+        if let Some(session) = self.get_session(session_id).await {
+            return Ok(session);
+        }
+
+        let session = match self.load_session(session_id).await? {
+            // 已因空闲结束的会话在收到新消息时重新激活
+            Some(mut s) if s.state == SessionState::Ended => {
+                s.ended_at = None;
+                s.resume();
+                s
+            }
+            Some(s) => s,
+            None => {
+                let mut s = Session::new(channel, channel_id);
+                s.id = session_id.to_string();
+                if let Some(ref pool) = self.pool() {
+                    self.save_session_to_db(&s, pool).await?;
+                }
+                info!("创建会话: {}", session_id);
+                s
+            }
+        };
+
+        let mut sessions = self.sessions.write().await;
+        // 并发调用时以先插入的为准
+        Ok(sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| Arc::new(RwLock::new(session)))
+            .clone())
+    }
+
+    /// 修改会话并持久化
+    pub async fn update<F>(&self, session: &Arc<RwLock<Session>>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Session),
+    {
+        let mut s = session.write().await;
+        f(&mut s);
+        if let Some(ref pool) = self.pool() {
+            self.save_session_to_db(&s, pool).await?;
+        }
+        Ok(())
+    }
+
+    /// 从数据库读取会话（不加入活跃会话）
+    pub async fn load_session(&self, session_id: &str) -> Result<Option<Session>> {
+        let Some(ref pool) = self.pool() else {
+            return Ok(None);
+        };
+
+        let row: Option<SessionRow> = sqlx::query_as("SELECT * FROM sessions WHERE id = ?1")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+
+        row.map(SessionRow::into_session).transpose()
+    }
+
+    /// 按最后活动时间倒序列出数据库中的会话
+    pub async fn list_sessions(&self, limit: i64) -> Result<Vec<Session>> {
+        let Some(ref pool) = self.pool() else {
+            let sessions = self.sessions.read().await;
+            let mut list = Vec::with_capacity(sessions.len());
+            for s in sessions.values() {
+                list.push(s.read().await.clone());
+            }
+            list.sort_by_key(|s| std::cmp::Reverse(s.last_activity));
+            list.truncate(limit.max(0) as usize);
+            return Ok(list);
+        };
+
+        let rows: Vec<SessionRow> =
+            sqlx::query_as("SELECT * FROM sessions ORDER BY last_activity DESC LIMIT ?1")
+                .bind(limit)
+                .fetch_all(pool)
+                .await?;
+
+        rows.into_iter().map(SessionRow::into_session).collect()
+    }
+
+    /// 数据库中所有会话的累计统计
+    pub async fn stored_stats(&self) -> Result<(usize, SessionStats)> {
+        let Some(ref pool) = self.pool() else {
+            return Ok(self.get_global_stats().await);
+        };
+
+        let rows: Vec<(Option<String>,)> = sqlx::query_as("SELECT stats FROM sessions")
+            .fetch_all(pool)
+            .await?;
+
+        let mut total = SessionStats::default();
+        for (stats,) in &rows {
+            if let Some(s) = stats
+                .as_deref()
+                .and_then(|s| serde_json::from_str::<SessionStats>(s).ok())
+            {
+                total.merge(&s);
+            }
+        }
+
+        Ok((rows.len(), total))
+    }
+
+    /// 按标题搜索会话（不区分大小写的子串匹配），按最后活动时间倒序
+    pub async fn search_sessions(&self, query: &str, limit: i64) -> Result<Vec<Session>> {
+        let Some(ref pool) = self.pool() else {
+            let query = query.to_lowercase();
+            let mut list = self.list_sessions(i64::MAX).await?;
+            list.retain(|s| s.title().is_some_and(|t| t.to_lowercase().contains(&query)));
+            list.truncate(limit.max(0) as usize);
+            return Ok(list);
+        };
+
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let rows: Vec<SessionRow> = sqlx::query_as(
+            "SELECT * FROM sessions WHERE json_extract(properties, '$.title') LIKE ?1 ESCAPE '\\' \
+             ORDER BY last_activity DESC LIMIT ?2",
+        )
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        rows.into_iter().map(SessionRow::into_session).collect()
+    }
+
+    /// 设置会话标题（None 或空白表示清除），返回会话是否存在
+    pub async fn set_title(&self, session_id: &str, title: Option<&str>) -> Result<bool> {
+        let session = match self.get_session(session_id).await {
+            Some(session) => session,
+            None => match self.load_session(session_id).await? {
+                Some(session) => Arc::new(RwLock::new(session)),
+                None => return Ok(false),
+            },
+        };
+        let title = title.map(str::trim).filter(|t| !t.is_empty()).map(str::to_string);
+        self.update(&session, |s| match title {
+            Some(title) => {
+                s.metadata.properties.insert(TITLE_PROPERTY.to_string(), title);
+            }
+            None => {
+                s.metadata.properties.remove(TITLE_PROPERTY);
+            }
+        })
+        .await?;
+        Ok(true)
+    }
+
+    /// 查找属于指定用户的会话（用户 ID、聊天 ID 或会话 ID 相同）
+    pub async fn find_sessions_of(&self, user: &str) -> Result<Vec<String>> {
+        let Some(ref pool) = self.pool() else {
+            let sessions = self.sessions.read().await;
+            let mut ids = Vec::new();
+            for (id, s) in sessions.iter() {
+                let s = s.read().await;
+                if id == user
+                    || s.metadata.channel_id == user
+                    || s.metadata.user_id.as_deref() == Some(user)
+                {
+                    ids.push(id.clone());
+                }
+            }
+            return Ok(ids);
+        };
+
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM sessions WHERE id = ?1 OR channel_id = ?1 OR user_id = ?1",
+        )
+        .bind(user)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// 删除会话及其上下文（统计、置顶、指令），返回会话是否存在
+    pub async fn delete_session(&self, session_id: &str) -> Result<bool> {
+        let mut found = self.sessions.write().await.remove(session_id).is_some();
+
+        if let Some(ref pool) = self.pool() {
+            let result = sqlx::query("DELETE FROM sessions WHERE id = ?1")
+                .bind(session_id)
+                .execute(pool)
+                .await?;
+            sqlx::query("DELETE FROM session_context WHERE session_id = ?1")
+                .bind(session_id)
+                .execute(pool)
+                .await?;
+            found |= result.rows_affected() > 0;
+        }
+
+        Ok(found)
+    }
+
+    /// 删除最后活动早于 `before` 的会话，返回删除数量
+    pub async fn prune_inactive(&self, before: DateTime<Utc>) -> Result<usize> {
+        let mut ids = Vec::new();
+        for (id, s) in self.sessions.read().await.iter() {
+            if s.read().await.last_activity < before {
+                ids.push(id.clone());
+            }
+        }
+
+        if let Some(ref pool) = self.pool() {
+            let rows: Vec<(String,)> =
+                sqlx::query_as("SELECT id FROM sessions WHERE last_activity < ?1")
+                    .bind(before)
+                    .fetch_all(pool)
+                    .await?;
+            for (id,) in rows {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        for id in &ids {
+            self.delete_session(id).await?;
+        }
+        Ok(ids.len())
+    }
+
+    /// 读取会话上下文值（持久化模式下读数据库）
+    pub async fn get_context_value(
+        &self,
+        session_id: &str,
+        key: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        if let Some(ref pool) = self.pool() {
+            let row: Option<(String,)> = sqlx::query_as(
+                "SELECT value FROM session_context WHERE session_id = ?1 AND key = ?2",
+            )
+            .bind(session_id)
+            .bind(key)
+            .fetch_optional(pool)
+            .await?;
+            let Some((value,)) = row else {
+                return Ok(None);
+            };
+            let value = match self.vault {
+                Some(ref vault) => vault.decrypt_text(&value)?,
+                None => value,
+            };
+            return Ok(serde_json::from_str(&value).ok());
+        }
+
+        match self.get_session(session_id).await {
+            Some(session) => {
+                let context = session.read().await.context.clone();
+                Ok(context.get(key).await)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 写入会话上下文值，同时更新内存中的会话
+    pub async fn set_context_value(
+        &self,
+        session_id: &str,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<()> {
+        if let Some(session) = self.get_session(session_id).await {
+            let context = session.read().await.context.clone();
+            context.set(key, value).await?;
+        }
+
+        if let Some(ref pool) = self.pool() {
+            let value = serde_json::to_string(value)?;
+            let value = match self.vault {
+                Some(ref vault) => vault.encrypt_text(&value)?,
+                None => value,
+            };
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO session_context (session_id, key, value, updated_at)
+                VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+                "#,
+            )
+            .bind(session_id)
+            .bind(key)
+            .bind(value)
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 会话的置顶内容
+    pub async fn pins(&self, session_id: &str) -> Result<Vec<String>> {
+        Ok(self
+            .get_context_value(session_id, PINS_KEY)
+            .await?
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default())
+    }
+
+    /// 添加置顶内容，返回置顶总数（重复内容不会再次添加）
+    pub async fn add_pin(&self, session_id: &str, text: &str) -> Result<usize> {
+        let mut pins = self.pins(session_id).await?;
+        if !pins.iter().any(|p| p == text) {
+            pins.push(text.to_string());
+            self.set_context_value(session_id, PINS_KEY, &serde_json::to_value(&pins)?)
+                .await?;
+        }
+        Ok(pins.len())
+    }
+
+    /// 按序号（从 1 开始）取消置顶，返回被移除的内容
+    pub async fn remove_pin(&self, session_id: &str, index: usize) -> Result<Option<String>> {
+        let mut pins = self.pins(session_id).await?;
+        if index == 0 || index > pins.len() {
+            return Ok(None);
+        }
+        let removed = pins.remove(index - 1);
+        self.set_context_value(session_id, PINS_KEY, &serde_json::to_value(&pins)?)
+            .await?;
+        Ok(Some(removed))
+    }
+
+    /// 会话指令（/instruct）
+    pub async fn instructions(&self, session_id: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_context_value(session_id, INSTRUCTIONS_KEY)
+            .await?
+            .and_then(|v| v.as_str().map(str::to_string))
+            .filter(|s| !s.is_empty()))
+    }
+
+    /// 设置会话指令，None 表示清除
+    pub async fn set_instructions(&self, session_id: &str, text: Option<&str>) -> Result<()> {
+        let value = serde_json::Value::String(text.unwrap_or_default().to_string());
+        self.set_context_value(session_id, INSTRUCTIONS_KEY, &value).await
+    }
+
     /// 获取会话
     pub async fn get_session(&self, session_id: &str) -> Option<Arc<RwLock<Session>>> {
         self.sessions.read().await.get(session_id).cloned()
     }
 
     /// 通过通道 ID 查找会话
+    #[allow(dead_code)]
     pub async fn find_by_channel(
         &self,
         channel: &str,
@@ -370,6 +863,7 @@ impl SessionManager {
     }
 
     /// 列出所有活跃会话
+    #[allow(dead_code)]
     pub async fn list_active_sessions(&self) -> Vec<Arc<RwLock<Session>>> {
         let sessions = self.sessions.read().await;
         sessions
@@ -383,38 +877,74 @@ impl SessionManager {
     pub async fn end_session(&self, session_id: &str, reason: impl Into<String>) -> Result<()> {
         let reason = reason.into();
 
-        if let Some(session) = self.sessions.read().await.get(session_id) {
+        let session = self.sessions.read().await.get(session_id).cloned();
+        if let Some(session) = session {
             let mut s = session.write().await;
             s.end(reason.clone());
 
             // 持久化
-            if let Some(ref pool) = self.pool {
+            if let Some(ref pool) = self.pool() {
                 self.save_session_to_db(&s, pool).await?;
             }
+
+            if let Some(ref bus) = self.event_bus {
+                if let Err(e) = bus.publish(SessionEndedEvent {
+                    session_id: session_id.to_string(),
+                    reason,
+                    timestamp: Utc::now(),
+                }) {
+                    warn!("发布会话结束事件失败: {}", e);
+                }
+            }
         }
 
         Ok(())
     }
 
     /// 清理空闲会话
+    ///
+    /// 结束超时的活跃会话并将其移出内存（统计已持久化，收到新消息时会重新加载）
     pub async fn cleanup_idle_sessions(&self) -> Result<usize> {
-        let mut count = 0;
-        let sessions = self.sessions.read().await;
+        let idle: Vec<String> = {
+            let sessions = self.sessions.read().await;
+            let mut ids = Vec::new();
+            for (id, session) in sessions.iter() {
+                let s = session.read().await;
+                if s.state == SessionState::Active && s.is_idle(self.idle_timeout) {
+                    ids.push(id.clone());
+                }
+            }
+            ids
+        };
 
-        for (id, session) in sessions.iter() {
-            let s = session.read().await;
-            if s.state == SessionState::Active && s.is_idle(self.idle_timeout) {
-                drop(s);
-                self.end_session(id, "空闲超时").await?;
-                count += 1;
+        for id in &idle {
+            self.end_session(id, "空闲超时").await?;
+            if self.pool().is_some() {
+                self.sessions.write().await.remove(id);
             }
         }
 
-        if count > 0 {
-            info!("清理了 {} 个空闲会话", count);
+        if !idle.is_empty() {
+            info!("清理了 {} 个空闲会话", idle.len());
         }
 
-        Ok(count)
+        Ok(idle.len())
+    }
+
+    /// 启动后台任务，定期清理空闲会话
+    pub fn start_cleanup(self: Arc<Self>, interval_secs: u64) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+            // 第一次 tick 立即返回
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.cleanup_idle_sessions().await {
+                    warn!("清理空闲会话失败: {}", e);
+                }
+            }
+        })
     }
 
     /// 获取会话统计
@@ -424,12 +954,7 @@ impl SessionManager {
         let mut global_stats = SessionStats::default();
 
         for session in sessions.values() {
-            let s = session.read().await;
-            global_stats.message_count += s.stats.message_count;
-            global_stats.user_message_count += s.stats.user_message_count;
-            global_stats.assistant_message_count += s.stats.assistant_message_count;
-            global_stats.tool_call_count += s.stats.tool_call_count;
-            global_stats.total_tokens += s.stats.total_tokens;
+            global_stats.merge(&session.read().await.stats);
         }
 
         (total, global_stats)
@@ -453,12 +978,7 @@ impl SessionManager {
             "#
         )
         .bind(&session.id)
-        .bind(match session.state {
-            SessionState::Active => "active",
-            SessionState::Idle => "idle",
-            SessionState::Paused => "paused",
-            SessionState::Ended => "ended",
-        })
+        .bind(session.state.as_str())
         .bind(&session.metadata.user_id)
         .bind(&session.metadata.channel)
         .bind(&session.metadata.channel_id)
@@ -484,12 +1004,59 @@ impl Default for SessionManager {
     fn default() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
-            pool: None,
-            idle_timeout: 3600,
+            pool: std::sync::RwLock::new(None),
+            db_path: None,
+            idle_timeout: 3600, // 默认 1 小时
+            event_bus: None,
+            vault: None,
         }
     }
 }
 
+/// 数据库行结构
+#[derive(sqlx::FromRow)]
+struct SessionRow {
+    id: String,
+    state: String,
+    user_id: Option<String>,
+    channel: String,
+    channel_id: String,
+    properties: Option<String>,
+    stats: Option<String>,
+    created_at: DateTime<Utc>,
+    last_activity: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+}
+
+impl SessionRow {
+    fn into_session(self) -> Result<Session> {
+        let properties = match self.properties {
+            Some(p) => serde_json::from_str(&p)?,
+            None => HashMap::new(),
+        };
+        let stats = match self.stats {
+            Some(s) => serde_json::from_str(&s)?,
+            None => SessionStats::default(),
+        };
+
+        Ok(Session {
+            id: self.id,
+            state: SessionState::parse(&self.state),
+            metadata: SessionMetadata {
+                user_id: self.user_id,
+                channel: self.channel,
+                channel_id: self.channel_id,
+                properties,
+            },
+            context: SessionContext::new(),
+            stats,
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+            ended_at: self.ended_at,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -542,4 +1109,139 @@ mod tests {
         let s = session.read().await;
         assert_eq!(s.state, SessionState::Ended);
     }
+
+    #[tokio::test]
+    async fn test_session_stats_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("sessions.db");
+        let db = db.to_string_lossy();
+
+        {
+            let manager = SessionManager::with_db(&db).await.unwrap();
+            let session = manager.get_or_create("telegram:42", "telegram", "42").await.unwrap();
+            manager
+                .update(&session, |s| {
+                    s.record_message(true);
+                    s.record_tool_call();
+                    s.record_message(false);
+                    s.record_tokens(150);
+                })
+                .await
+                .unwrap();
+        }
+
+        // 重新打开后统计仍在，并在原会话上继续累加
+        let manager = SessionManager::with_db(&db).await.unwrap();
+        let session = manager.get_or_create("telegram:42", "telegram", "42").await.unwrap();
+        manager.update(&session, |s| s.record_tokens(50)).await.unwrap();
+
+        let loaded = manager.load_session("telegram:42").await.unwrap().unwrap();
+        assert_eq!(loaded.metadata.channel, "telegram");
+        assert_eq!(loaded.stats.message_count, 2);
+        assert_eq!(loaded.stats.tool_call_count, 1);
+        assert_eq!(loaded.stats.total_tokens, 200);
+
+        let (count, total) = manager.stored_stats().await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(total.total_tokens, 200);
+    }
+    #[tokio::test]
+    async fn test_session_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("sessions.db");
+        let manager = SessionManager::with_db(&db.to_string_lossy()).await.unwrap();
+        manager.get_or_create("cli:1", "cli", "1").await.unwrap();
+        manager.get_or_create("cli:2", "cli", "2").await.unwrap();
+
+        assert!(manager.set_title("cli:1", Some(" Rust 异步运行时 ")).await.unwrap());
+        assert!(manager.set_title("cli:2", Some("周末旅行计划")).await.unwrap());
+        assert!(!manager.set_title("cli:3", Some("不存在")).await.unwrap());
+
+        let loaded = manager.load_session("cli:1").await.unwrap().unwrap();
+        assert_eq!(loaded.title(), Some("Rust 异步运行时"));
+
+        let found = manager.search_sessions("rust", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "cli:1");
+        assert!(manager.search_sessions("100%", 10).await.unwrap().is_empty());
+
+        manager.set_title("cli:1", None).await.unwrap();
+        assert!(manager.search_sessions("Rust", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pins_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("sessions.db");
+        let db = db.to_string_lossy();
+
+        {
+            let manager = SessionManager::with_db(&db).await.unwrap();
+            assert_eq!(manager.add_pin("cli:1", "我的生日是 5 月 1 日").await.unwrap(), 1);
+            assert_eq!(manager.add_pin("cli:1", "回答使用中文").await.unwrap(), 2);
+            // 重复置顶不增加
+            assert_eq!(manager.add_pin("cli:1", "回答使用中文").await.unwrap(), 2);
+        }
+
+        let manager = SessionManager::with_db(&db).await.unwrap();
+        assert_eq!(manager.pins("cli:1").await.unwrap().len(), 2);
+        assert_eq!(
+            manager.remove_pin("cli:1", 1).await.unwrap().as_deref(),
+            Some("我的生日是 5 月 1 日")
+        );
+        assert_eq!(manager.remove_pin("cli:1", 5).await.unwrap(), None);
+        assert_eq!(manager.pins("cli:1").await.unwrap(), vec!["回答使用中文".to_string()]);
+        assert!(manager.pins("cli:2").await.unwrap().is_empty());
+
+        manager.set_instructions("cli:1", Some("用英文回答")).await.unwrap();
+        assert_eq!(manager.instructions("cli:1").await.unwrap().as_deref(), Some("用英文回答"));
+        manager.set_instructions("cli:1", None).await.unwrap();
+        assert_eq!(manager.instructions("cli:1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_idle_cleanup_publishes_event() {
+        use crate::bus::EventHandler;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counter(Arc<AtomicUsize>);
+
+        #[async_trait::async_trait]
+        impl EventHandler<SessionEndedEvent> for Counter {
+            async fn handle(&self, event: &SessionEndedEvent) {
+                assert_eq!(event.reason, "空闲超时");
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let bus = EventBus::new();
+        let ended = Arc::new(AtomicUsize::new(0));
+        bus.subscribe::<SessionEndedEvent, _>(Counter(ended.clone())).await;
+        tokio::spawn(bus.clone().start());
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("sessions.db");
+        let config = SessionConfig {
+            idle_timeout_secs: 60,
+            ..Default::default()
+        };
+        let manager = SessionManager::from_config(&config, &db.to_string_lossy(), Some(bus), None)
+            .await
+            .unwrap();
+
+        let idle = manager.get_or_create("telegram:1", "telegram", "1").await.unwrap();
+        idle.write().await.last_activity = Utc::now() - chrono::Duration::seconds(120);
+        manager.get_or_create("telegram:2", "telegram", "2").await.unwrap();
+
+        assert_eq!(manager.cleanup_idle_sessions().await.unwrap(), 1);
+        assert!(manager.get_session("telegram:1").await.is_none());
+        assert!(manager.get_session("telegram:2").await.is_some());
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(ended.load(Ordering::SeqCst), 1);
+
+        // 新消息到来时重新激活
+        let session = manager.get_or_create("telegram:1", "telegram", "1").await.unwrap();
+        assert_eq!(session.read().await.state, SessionState::Active);
+    }
 }
@@ -0,0 +1,122 @@
+//! 共享状态后端
+//!
+//! 多个进程（如多个 gateway 实例）共同处理同一批通道时，去重记录和任务执行标记需要共享。
+//! 配置 `[redis] url` 后使用 Redis，未配置或连接失败时自动回退到进程内存（单进程部署的行为不变）。
+//! 键统一加上 `[redis] prefix` 前缀
+
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::RedisConfig;
+
+/// 内存后端超过该条目数时清理过期记录
+const MEMORY_PURGE_THRESHOLD: usize = 4096;
+
+enum Backend {
+    Memory(std::sync::Mutex<HashMap<String, Instant>>),
+    Redis(ConnectionManager),
+}
+
+/// 带过期时间的键值状态（Redis 或进程内存）
+pub struct StateStore {
+    prefix: String,
+    backend: Backend,
+}
+
+impl StateStore {
+    /// 进程内存后端
+    pub fn memory() -> Arc<Self> {
+        Arc::new(Self {
+            prefix: String::new(),
+            backend: Backend::Memory(std::sync::Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// 连接 Redis
+    pub async fn connect(url: &str, prefix: &str, timeout: Duration) -> Result<Arc<Self>> {
+        let client = redis::Client::open(url).context("Redis 地址无效")?;
+        let manager = tokio::time::timeout(timeout, ConnectionManager::new(client))
+            .await
+            .context("连接 Redis 超时")?
+            .context("连接 Redis 失败")?;
+        Ok(Arc::new(Self {
+            prefix: prefix.to_string(),
+            backend: Backend::Redis(manager),
+        }))
+    }
+
+    /// 按配置创建：配置了 url 时连接 Redis，失败时回退到内存
+    pub async fn from_config(config: &RedisConfig) -> Arc<Self> {
+        let Some(ref url) = config.url else {
+            return Self::memory();
+        };
+        match Self::connect(url, &config.prefix, Duration::from_secs(config.connect_timeout_secs)).await {
+            Ok(store) => {
+                info!("共享状态使用 Redis（前缀 {}）", config.prefix);
+                store
+            }
+            Err(e) => {
+                warn!("{:#}，共享状态回退到进程内存", e);
+                Self::memory()
+            }
+        }
+    }
+
+    /// 是否为多个进程共享的后端
+    pub fn is_shared(&self) -> bool {
+        matches!(self.backend, Backend::Redis(_))
+    }
+
+    /// 键不存在（或已过期）时写入并返回 true，已存在时返回 false
+    pub async fn set_nx(&self, key: &str, ttl: Duration) -> Result<bool> {
+        match self.backend {
+            Backend::Memory(ref entries) => {
+                let now = Instant::now();
+                let mut entries = entries.lock().unwrap();
+                if entries.len() > MEMORY_PURGE_THRESHOLD {
+                    entries.retain(|_, expires| *expires > now);
+                }
+                if entries.get(key).is_some_and(|expires| *expires > now) {
+                    return Ok(false);
+                }
+                entries.insert(key.to_string(), now + ttl);
+                Ok(true)
+            }
+            Backend::Redis(ref manager) => {
+                let reply: Option<String> = redis::cmd("SET")
+                    .arg(format!("{}{}", self.prefix, key))
+                    .arg(1)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl.as_millis().max(1) as u64)
+                    .query_async(&mut manager.clone())
+                    .await
+                    .context("Redis SET 失败")?;
+                Ok(reply.is_some())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_set_nx() {
+        let store = StateStore::memory();
+        assert!(!store.is_shared());
+        assert!(store.set_nx("dedupe:telegram:1", Duration::from_secs(60)).await.unwrap());
+        assert!(!store.set_nx("dedupe:telegram:1", Duration::from_secs(60)).await.unwrap());
+        assert!(store.set_nx("dedupe:telegram:2", Duration::from_secs(60)).await.unwrap());
+
+        // 过期后可以重新写入
+        assert!(store.set_nx("lock", Duration::from_millis(20)).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(store.set_nx("lock", Duration::from_millis(20)).await.unwrap());
+    }
+}
@@ -0,0 +1,298 @@
+//! 后台任务
+//!
+//! 把耗时的工具调用（长时间运行的 shell 命令、调研等）放到后台执行，模型立即拿到任务 ID，
+//! 之后通过 `check_task` / `cancel_task` 查询或取消，不阻塞当前这一轮对话。
+//! 任务状态和输出记录在 `<workspace>/tasks.db`，`nanobot tasks list` 可在其他进程中查看
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::tools::{truncate_output, ToolContext, ToolRegistry};
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Running => "running",
+            TaskStatus::Completed => "completed",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "completed" => TaskStatus::Completed,
+            "failed" => TaskStatus::Failed,
+            "cancelled" => TaskStatus::Cancelled,
+            _ => TaskStatus::Running,
+        }
+    }
+
+    /// 中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskStatus::Running => "运行中",
+            TaskStatus::Completed => "已完成",
+            TaskStatus::Failed => "失败",
+            TaskStatus::Cancelled => "已取消",
+        }
+    }
+}
+
+/// 后台任务
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub id: String,
+    /// 执行的工具
+    pub tool: String,
+    /// 工具参数（JSON）
+    pub args: String,
+    /// 发起任务的会话
+    pub session_id: Option<String>,
+    pub status: TaskStatus,
+    /// 工具输出（完成或失败后）
+    pub output: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TaskRow {
+    id: String,
+    tool: String,
+    args: String,
+    session_id: Option<String>,
+    status: String,
+    output: Option<String>,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<TaskRow> for Task {
+    fn from(row: TaskRow) -> Self {
+        Self {
+            id: row.id,
+            tool: row.tool,
+            args: row.args,
+            session_id: row.session_id,
+            status: TaskStatus::parse(&row.status),
+            output: row.output,
+            created_at: row.created_at,
+            finished_at: row.finished_at,
+        }
+    }
+}
+
+/// 后台任务管理器
+pub struct TaskManager {
+    pool: Pool<Sqlite>,
+    /// 同时运行的任务数上限
+    max_running: usize,
+    /// 本进程中运行的任务及其取消令牌
+    running: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl TaskManager {
+    /// 打开任务数据库
+    pub async fn new(db_path: &str, max_running: usize) -> Result<Arc<Self>> {
+        let pool = crate::db::connect(db_path, 2)
+            .await
+            .context("连接任务数据库失败")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tasks (
+                id TEXT PRIMARY KEY,
+                tool TEXT NOT NULL,
+                args TEXT NOT NULL,
+                session_id TEXT,
+                status TEXT NOT NULL,
+                output TEXT,
+                created_at TIMESTAMP NOT NULL,
+                finished_at TIMESTAMP
+            )
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_created ON tasks(created_at)")
+            .execute(&pool)
+            .await?;
+
+        Ok(Arc::new(Self {
+            pool,
+            max_running,
+            running: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// 在后台执行工具，立即返回任务 ID
+    ///
+    /// 输出按 `tools.max_output_chars` / `output_limits` 截断后保存
+    pub async fn spawn(
+        self: &Arc<Self>,
+        registry: ToolRegistry,
+        tool: &str,
+        args: Value,
+        ctx: ToolContext,
+    ) -> Result<String> {
+        if registry.get(tool).is_none() {
+            anyhow::bail!("未知工具: {}", tool);
+        }
+        let running = self.running.lock().unwrap().len();
+        if self.max_running > 0 && running >= self.max_running {
+            anyhow::bail!("已有 {} 个后台任务在运行，请等待完成或取消后再试", running);
+        }
+
+        let id = Uuid::new_v4().to_string()[..8].to_string();
+        sqlx::query(
+            "INSERT INTO tasks (id, tool, args, session_id, status, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(&id)
+        .bind(tool)
+        .bind(args.to_string())
+        .bind(&ctx.session_id)
+        .bind(TaskStatus::Running.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        let token = CancellationToken::new();
+        self.running.lock().unwrap().insert(id.clone(), token.clone());
+        info!("启动后台任务 {}: {}", id, tool);
+
+        let manager = self.clone();
+        let (task_id, tool) = (id.clone(), tool.to_string());
+        tokio::spawn(async move {
+            let limit = ctx.config.output_limit(&tool);
+            let (status, output) = tokio::select! {
+                result = registry.execute(&tool, args, &ctx) => match result {
+                    Ok(r) if r.success => (TaskStatus::Completed, truncate_output(&r.output, limit)),
+                    Ok(r) => (TaskStatus::Failed, truncate_output(&r.to_string(), limit)),
+                    Err(e) => (TaskStatus::Failed, format!("工具执行错误: {}", e)),
+                },
+                _ = token.cancelled() => (TaskStatus::Cancelled, "任务已取消".to_string()),
+            };
+            manager.running.lock().unwrap().remove(&task_id);
+            if let Err(e) = manager.finish(&task_id, status, &output).await {
+                warn!("保存后台任务 {} 结果失败: {}", task_id, e);
+            }
+            info!("后台任务 {} {}", task_id, status.label());
+        });
+
+        Ok(id)
+    }
+
+    /// 记录任务结果
+    async fn finish(&self, id: &str, status: TaskStatus, output: &str) -> Result<()> {
+        sqlx::query("UPDATE tasks SET status = ?1, output = ?2, finished_at = ?3 WHERE id = ?4")
+            .bind(status.as_str())
+            .bind(output)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 取消本进程中运行的任务，返回任务是否在运行
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().remove(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 等待任务结束
+    #[cfg(test)]
+    pub async fn wait(&self, id: &str) -> Result<Option<Task>> {
+        while self.running.lock().unwrap().contains_key(id) {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        // 取消后结果由任务自身写入
+        for _ in 0..20 {
+            let task = self.get(id).await?;
+            if task.as_ref().is_none_or(|t| t.status != TaskStatus::Running) {
+                return Ok(task);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        self.get(id).await
+    }
+
+    /// 查询任务
+    pub async fn get(&self, id: &str) -> Result<Option<Task>> {
+        let row: Option<TaskRow> = sqlx::query_as("SELECT * FROM tasks WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(Task::from))
+    }
+
+    /// 最近的任务（新的在前）
+    pub async fn list(&self, limit: i64) -> Result<Vec<Task>> {
+        let rows: Vec<TaskRow> = sqlx::query_as("SELECT * FROM tasks ORDER BY created_at DESC LIMIT ?1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(Task::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_background_task() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = temp_dir.path().join("tasks.db");
+        let manager = TaskManager::new(&db.to_string_lossy(), 1).await.unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(crate::tools::shell::ShellTool);
+        let config = crate::config::ToolsConfig {
+            shell_whitelist: vec!["echo".to_string(), "sleep".to_string()],
+            ..Default::default()
+        };
+        let ctx = ToolContext::new(config).with_session("s");
+
+        // 完成后保存输出
+        let id = manager.spawn(registry.clone(), "shell", json!({"command": "echo done"}), ctx.clone()).await.unwrap();
+        let task = manager.wait(&id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.output.as_deref(), Some("done\n"));
+        assert_eq!(task.session_id.as_deref(), Some("s"));
+
+        // 超过并发上限时拒绝，取消后记录状态
+        let id = manager.spawn(registry.clone(), "shell", json!({"command": "sleep 10"}), ctx.clone()).await.unwrap();
+        assert!(manager.spawn(registry.clone(), "shell", json!({"command": "echo"}), ctx.clone()).await.is_err());
+        assert_eq!(manager.get(&id).await.unwrap().unwrap().status, TaskStatus::Running);
+        assert!(manager.cancel(&id));
+        assert!(!manager.cancel(&id));
+        assert_eq!(manager.wait(&id).await.unwrap().unwrap().status, TaskStatus::Cancelled);
+
+        assert!(manager.spawn(registry, "missing", json!({}), ctx).await.is_err());
+        assert_eq!(manager.list(10).await.unwrap().len(), 2);
+    }
+}
@@ -1,8 +1,8 @@
 //! 测试模块
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use super::*;
     use crate::config::Config;
     use crate::llm::{Message, Role};
     use crate::tools::{ToolContext, ToolRegistry};
@@ -40,6 +40,566 @@ mod tests {
     }
 
     #[tokio::test]
+    async fn test_tool_registry_runtime_changes() {
+        use crate::bus::{EventBus, EventHandler, ToolRegistryEvent};
+        use std::sync::{Arc, Mutex};
+
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        #[async_trait::async_trait]
+        impl EventHandler<ToolRegistryEvent> for Recorder {
+            async fn handle(&self, event: &ToolRegistryEvent) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}:{}", event.action, event.tool_name));
+            }
+        }
+
+        let bus = EventBus::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        bus.subscribe::<ToolRegistryEvent, _>(Recorder(events.clone())).await;
+        tokio::spawn(bus.clone().start());
+
+        let registry = ToolRegistry::new();
+        // 克隆的句柄共享同一份工具和事件总线
+        let handle = registry.clone();
+        registry.set_event_bus(bus);
+
+        handle.register(crate::tools::shell::ShellTool);
+        assert!(registry.get("shell").is_some());
+        assert_eq!(registry.list_tools().len(), 1);
+
+        assert!(registry.unregister("shell"));
+        assert!(!registry.unregister("shell"));
+        assert!(handle.get("shell").is_none());
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        let mut events = events.lock().unwrap().clone();
+        events.sort();
+        assert_eq!(events, vec!["registered:shell", "unregistered:shell"]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_args_validation() {
+        use serde_json::json;
+
+        let config = Config::default();
+        let registry = ToolRegistry::default_with_config(&config);
+        let ctx = ToolContext::new(config.tools.clone());
+
+        // 缺少必填参数、类型错误时返回可读的校验错误，而不是执行工具
+        let result = registry
+            .execute("read_file", json!({ "path": 42 }), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("参数校验失败"));
+        assert!(error.contains("/path"));
+
+        let result = registry.execute("write_file", json!({}), &ctx).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("content"));
+
+        assert!(registry.validate("read_file", &json!({ "path": "/tmp/a" })).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool() {
+        use crate::tools::typed::{Typed, TypedTool};
+        use crate::tools::{Tool, ToolResult};
+        use serde_json::json;
+
+        /// 测试工具参数
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        struct RepeatArgs {
+            /// 要重复的文本
+            text: String,
+            /// 重复次数
+            times: Option<u32>,
+        }
+
+        struct RepeatTool;
+
+        #[async_trait::async_trait]
+        impl TypedTool for RepeatTool {
+            type Args = RepeatArgs;
+            const NAME: &'static str = "repeat";
+            const DESCRIPTION: &'static str = "重复文本";
+
+            async fn run(&self, args: RepeatArgs, _ctx: &ToolContext) -> anyhow::Result<ToolResult> {
+                Ok(ToolResult::success(args.text.repeat(args.times.unwrap_or(1) as usize)))
+            }
+        }
+
+        let tool = Typed::new(RepeatTool);
+        let params = &tool.definition().parameters;
+        assert_eq!(params["type"], "object");
+        assert_eq!(params["required"], json!(["text"]));
+        assert_eq!(params["properties"]["text"]["description"], "要重复的文本");
+        assert_eq!(params["properties"]["times"]["type"], "integer");
+        assert!(params.get("$schema").is_none());
+
+        let registry = ToolRegistry::new();
+        registry.register(tool);
+        let ctx = ToolContext::new(Config::default().tools);
+
+        let result = registry
+            .execute("repeat", json!({ "text": "ab", "times": 2 }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(result.output, "abab");
+
+        let result = registry.execute("repeat", json!({ "times": 2 }), &ctx).await.unwrap();
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_tool_output_paging() {
+        use crate::tools::{truncate_output, Tool};
+        use serde_json::json;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.tools.allowed_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        let ctx = ToolContext::new(config.tools.clone());
+
+        let file = temp_dir.path().join("long.txt");
+        let content: Vec<String> = (1..=10).map(|i| format!("line {}", i)).collect();
+        std::fs::write(&file, content.join("\n")).unwrap();
+
+        let read_tool = crate::tools::file::ReadFileTool;
+        let path = file.to_string_lossy().to_string();
+
+        let result = read_tool
+            .execute(json!({ "path": path, "limit": 4 }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.output.starts_with("line 1\nline 2\nline 3\nline 4\n"));
+        assert!(result.output.contains("offset=4"));
+
+        let result = read_tool
+            .execute(json!({ "path": path, "offset": 8, "limit": 4 }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.output.starts_with("line 9\nline 10\n"));
+        assert!(!result.output.contains("继续读取"));
+
+        // 全局输出上限与按工具覆盖
+        assert_eq!(truncate_output("abc", 0), "abc");
+        assert!(truncate_output(&"x".repeat(20), 10).starts_with(&"x".repeat(10)));
+        assert!(truncate_output(&"x".repeat(20), 10).contains("输出已截断"));
+
+        config.tools.output_limits.insert("read_file".to_string(), 5);
+        let registry = ToolRegistry::default_with_config(&config);
+        let ctx = ToolContext::new(config.tools.clone());
+        let result = registry
+            .execute("read_file", json!({ "path": path }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.output.starts_with("line \n\n[输出已截断"));
+    }
+
+    #[tokio::test]
+    async fn test_message_tool_defaults_to_origin() {
+        use crate::channel::Channel;
+        use crate::tools::message::MessageTool;
+        use crate::tools::Tool;
+        use serde_json::json;
+        use std::sync::{Arc, Mutex};
+
+        struct RecordingChannel(Mutex<Vec<(String, String)>>);
+
+        #[async_trait::async_trait]
+        impl Channel for RecordingChannel {
+            fn name(&self) -> &str {
+                "telegram"
+            }
+            async fn start(&self) -> anyhow::Result<()> {
+                Ok(())
+            }
+            async fn stop(&self) -> anyhow::Result<()> {
+                Ok(())
+            }
+            async fn send_message(&self, target: &crate::channel::ChannelTarget, content: &str) -> anyhow::Result<()> {
+                self.0.lock().unwrap().push((target.to_string(), content.to_string()));
+                Ok(())
+            }
+        }
+
+        let channel = Arc::new(RecordingChannel(Mutex::new(Vec::new())));
+        let tool = MessageTool::new(vec![channel.clone()]);
+        let ctx = ToolContext::new(Config::default().tools).with_origin(Some("telegram"), Some("42"));
+
+        let result = tool.execute(json!({ "content": "hi" }), &ctx).await.unwrap();
+        assert!(result.success);
+        let result = tool
+            .execute(json!({ "content": "yo", "chat_id": "7" }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(
+            *channel.0.lock().unwrap(),
+            vec![("42".to_string(), "hi".to_string()), ("7".to_string(), "yo".to_string())]
+        );
+
+        // 发往其他通道时不沿用当前聊天 ID
+        let result = tool
+            .execute(json!({ "content": "hi", "channel": "feishu" }), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_chat_cancel_rolls_back() {
+        use crate::agent::{Agent, ChatOptions, TurnAborted};
+        use tokio_util::sync::CancellationToken;
+
+        // 接受连接但从不响应的提供商
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        let agent = Agent::new(config, Some("cancel-test".to_string())).await.unwrap();
+        let context_len = agent.context_length().await;
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let err = agent
+            .chat_with_options("你好", ChatOptions::default().with_cancel_token(token))
+            .await
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TurnAborted>(), Some(&TurnAborted::Cancelled));
+        // 本轮的用户消息已撤销
+        assert_eq!(agent.context_length().await, context_len);
+        assert!(!temp_dir.path().join("memory/conversations/cancel-test.md").exists());
+
+        let err = agent
+            .chat_with_options(
+                "你好",
+                ChatOptions::default().with_timeout(std::time::Duration::from_millis(50)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TurnAborted>(), Some(&TurnAborted::DeadlineExceeded));
+        assert_eq!(agent.context_length().await, context_len);
+    }
+
+    #[tokio::test]
+    async fn test_incognito_skips_conversation_history() {
+        use crate::agent::Agent;
+
+        // 拒绝连接的提供商：用户消息写入历史后请求失败
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        config.channel.incognito_channels = vec!["telegram".to_string()];
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let history = temp_dir.path().join("memory/conversations/telegram:42.md");
+
+        // 通道默认无痕
+        assert!(agent.is_incognito().await);
+        assert!(agent.chat("秘密").await.is_err());
+        assert!(!history.exists());
+
+        agent.set_incognito(false).await;
+        assert!(agent.chat("你好").await.is_err());
+        assert!(history.exists());
+    }
+
+    #[tokio::test]
+    async fn test_undo_last_turn() {
+        use crate::agent::Agent;
+
+        // 拒绝连接的提供商：用户消息写入上下文和历史后请求失败
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let history = temp_dir.path().join("memory/conversations/telegram:42.md");
+
+        assert!(agent.chat("第一条").await.is_err());
+        assert!(agent.chat("第二条").await.is_err());
+        assert_eq!(agent.context_length().await, 3);
+
+        // 只撤销最后一轮
+        assert_eq!(agent.undo_last_turn().await.as_deref(), Some("第二条"));
+        assert_eq!(agent.context_length().await, 2);
+        let content = std::fs::read_to_string(&history).unwrap();
+        assert!(content.contains("第一条") && !content.contains("第二条"));
+    }
+
+    #[tokio::test]
+    async fn test_persona_switch() {
+        use crate::agent::Agent;
+        use crate::config::PersonaConfig;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some("http://127.0.0.1:1/v1".to_string());
+        config.personas.insert(
+            "coder".to_string(),
+            PersonaConfig {
+                tools: Some(vec!["read_file".to_string()]),
+                ..Default::default()
+            },
+        );
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+
+        assert_eq!(agent.persona().await, None);
+        assert!(agent.set_persona(Some("poet")).await.is_err());
+        agent.set_persona(Some("coder")).await.unwrap();
+        assert_eq!(agent.persona().await.as_deref(), Some("coder"));
+
+        // 角色按会话保存
+        agent.set_session_id("telegram:7").await;
+        assert_eq!(agent.persona().await, None);
+        agent.set_session_id("telegram:42").await;
+        assert_eq!(agent.persona().await.as_deref(), Some("coder"));
+
+        agent.set_persona(Some("default")).await.unwrap();
+        assert_eq!(agent.persona().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_persona_phase_params() {
+        use crate::agent::Agent;
+        use crate::config::{GenerationParams, PersonaConfig};
+        use axum::{extract::State, routing::post, Json, Router};
+        use serde_json::{json, Value};
+        use std::sync::{Arc, Mutex};
+
+        // 记录每次请求的生成参数：还没有工具结果时调用 read_file，之后直接回答
+        type Seen = Arc<Mutex<Vec<(Option<f64>, Option<u64>)>>>;
+        async fn complete(State(seen): State<Seen>, Json(body): Json<Value>) -> Json<Value> {
+            seen.lock().unwrap().push((body["temperature"].as_f64(), body["max_tokens"].as_u64()));
+            // 本轮（最后一条用户消息之后）是否已有工具结果
+            let messages = body["messages"].as_array().unwrap();
+            let has_result = messages.iter().rev().take_while(|m| m["role"] != "user").any(|m| m["role"] == "tool");
+            let message = if has_result || body["tools"].is_null() {
+                json!({ "role": "assistant", "content": "完成" })
+            } else {
+                json!({
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "read_file", "arguments": "{\"path\": \"missing.txt\"}" }
+                    }]
+                })
+            };
+            Json(json!({
+                "id": "test",
+                "model": "test",
+                "choices": [{ "index": 0, "message": message, "finish_reason": "stop" }]
+            }))
+        }
+        let seen: Seen = Arc::new(Mutex::new(Vec::new()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/v1/chat/completions", post(complete)).with_state(seen.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some(format!("http://{}/v1", addr));
+        config.agent.generation = GenerationParams {
+            temperature: Some(0.7),
+            max_tokens: Some(2000),
+            ..Default::default()
+        };
+        config.personas.insert(
+            "phased".to_string(),
+            PersonaConfig {
+                tool_params: Some(GenerationParams {
+                    temperature: Some(0.1),
+                    max_tokens: Some(100),
+                    ..Default::default()
+                }),
+                answer_params: Some(GenerationParams {
+                    temperature: Some(0.9),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        config.personas.insert("plain".to_string(), PersonaConfig::default());
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let rounded = |v: Vec<(Option<f64>, Option<u64>)>| -> Vec<(Option<i64>, Option<u64>)> {
+            v.into_iter().map(|(t, m)| (t.map(|t| (t * 10.0).round() as i64), m)).collect()
+        };
+
+        // 工具阶段和回答阶段各用自己的参数，未设置的项沿用 Agent 配置
+        agent.set_persona(Some("phased")).await.unwrap();
+        assert_eq!(agent.chat("读一下文件").await.unwrap().content, "完成");
+        let requests = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!(rounded(requests), vec![(Some(1), Some(100)), (Some(9), Some(2000))]);
+
+        // 角色没有设置阶段参数时两个阶段都使用 Agent 配置
+        agent.set_persona(Some("plain")).await.unwrap();
+        assert_eq!(agent.chat("再读一次").await.unwrap().content, "完成");
+        let requests = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!(rounded(requests), vec![(Some(7), Some(2000)), (Some(7), Some(2000))]);
+    }
+
+    #[tokio::test]
+    async fn test_onboarding_first_contact() {
+        use crate::agent::Agent;
+        use crate::memory::MemoryStore;
+        use serde_json::json;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "vllm".to_string();
+        config.llm.vllm.base_url = Some("http://127.0.0.1:1/v1".to_string());
+        config.onboarding.enabled = true;
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap();
+
+        // 首次对话开始引导，之后不再重复
+        assert!(agent.start_onboarding_if_new("telegram:42").await.unwrap());
+        assert!(!agent.start_onboarding_if_new("telegram:42").await.unwrap());
+        assert_eq!(memory.user_fact("telegram:42", "onboarding").await.unwrap().as_deref(), Some("pending"));
+
+        // 引导中通过工具保存回答
+        let ctx = ToolContext::new(Default::default())
+            .with_session("telegram:42".to_string())
+            .with_origin(Some("telegram"), Some("42"));
+        let tools = agent.tools();
+        tools.execute("remember_user", json!({"key": "name", "value": "小明"}), &ctx).await.unwrap();
+        tools.execute("remember_user", json!({"key": "onboarding", "value": "done"}), &ctx).await.unwrap();
+        assert_eq!(memory.user_fact("telegram:42", "name").await.unwrap().as_deref(), Some("小明"));
+        assert_eq!(memory.user_fact("telegram:42", "onboarding").await.unwrap().as_deref(), Some("done"));
+
+        // 已有对话历史的会话不视为新用户
+        agent.set_session_id("telegram:7").await;
+        memory.add_message("telegram:7", "user", "你好", None).await.unwrap();
+        assert!(!agent.start_onboarding_if_new("telegram:7").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_incognito_does_not_write_memory() {
+        use crate::agent::Agent;
+        use crate::memory::MemoryStore;
+        use serde_json::json;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "mock".to_string();
+        let agent = Agent::new(config, Some("telegram:42".to_string())).await.unwrap();
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap();
+
+        let ctx = ToolContext::new(Default::default())
+            .with_session("telegram:42".to_string())
+            .with_origin(Some("telegram"), Some("42"))
+            .with_incognito(true);
+        let tools = agent.tools();
+        let result = tools
+            .execute("remember", json!({"key": "饮品", "value": "喜欢乌龙茶", "shared": true}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        let result = tools.execute("remember_user", json!({"key": "name", "value": "小明"}), &ctx).await.unwrap();
+        assert!(!result.success);
+
+        assert!(memory.get_memory("饮品").await.unwrap().is_none());
+        assert!(memory.user_fact("telegram:42", "name").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_incognito_usage_not_recorded_per_user() {
+        use crate::agent::{Agent, ChatOptions};
+        use crate::budget::Budget;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        config.agent.default_provider = "mock".to_string();
+        config.budget.daily_usd = 100.0;
+        config.channel.incognito_channels = vec!["secret".to_string()];
+        let agent = Agent::new(config.clone(), Some("secret:1".to_string())).await.unwrap();
+
+        agent
+            .chat_with_options("你好".to_string(), ChatOptions::default().with_origin("secret", "1"))
+            .await
+            .unwrap();
+        agent.set_session_id("telegram:1").await;
+        agent
+            .chat_with_options("你好".to_string(), ChatOptions::default().with_origin("telegram", "1"))
+            .await
+            .unwrap();
+
+        let budget = Budget::from_config(&config).await.unwrap().unwrap();
+        assert!(budget.user_usage("secret:1").await.unwrap().is_empty());
+        assert!(!budget.user_usage("telegram:1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_tool_call_history() {
+        use crate::agent::restore_history;
+        use crate::memory::MemoryStore;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap();
+        let call = |id: &str| format!(r#"{{"id":"{}","type":"function","function":{{"name":"shell","arguments":"{{}}"}}}}"#, id);
+
+        // 孤立的 tool 消息（历史被截断）
+        memory.add_message("s", "tool", "孤立结果", Some("call_0")).await.unwrap();
+        memory.add_message("s", "user", "列出文件", None).await.unwrap();
+        // 完整的工具调用组：两个调用都有结果
+        let calls = format!("[{},{}]", call("call_1"), call("call_2"));
+        memory.add_message_with_tool_calls("s", "assistant", "", Some(&calls), None).await.unwrap();
+        memory.add_message("s", "tool", "a.txt", Some("call_1")).await.unwrap();
+        memory.add_message("s", "tool", "b.txt", Some("call_2")).await.unwrap();
+        memory.add_message("s", "assistant", "有两个文件", None).await.unwrap();
+        // 不完整的组（执行中断，缺少 call_4 的结果）
+        memory.add_message("s", "user", "再看看", None).await.unwrap();
+        let calls = format!("[{},{}]", call("call_3"), call("call_4"));
+        memory.add_message_with_tool_calls("s", "assistant", "", Some(&calls), None).await.unwrap();
+        memory.add_message("s", "tool", "c.txt", Some("call_3")).await.unwrap();
+
+        let history = memory.get_conversation("s", 0).await.unwrap();
+        let messages = restore_history(history);
+        let roles: Vec<Role> = messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(roles, vec![Role::User, Role::Assistant, Role::Tool, Role::Tool, Role::Assistant, Role::User]);
+        assert_eq!(messages[1].tool_calls.as_ref().unwrap().len(), 2);
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_2"));
+    }
+
+        #[tokio::test]
     async fn test_shell_tool_whitelist() {
         use crate::tools::Tool;
         use serde_json::json;
@@ -66,6 +626,87 @@ mod tests {
         assert!(tool_result.output.contains("hello"));
     }
 
+    #[tokio::test]
+    async fn test_shell_cwd_and_env() {
+        use crate::tools::Tool;
+        use serde_json::json;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("work")).unwrap();
+        let mut config = Config::default();
+        config.tools.shell_whitelist = vec!["pwd".to_string(), "echo".to_string()];
+        config.tools.allowed_paths = vec![temp_dir.path().display().to_string()];
+        config.tools.shell_env_allowlist = vec!["GREETING".to_string()];
+        let ctx = ToolContext::new(config.tools.clone());
+        let shell_tool = crate::tools::shell::ShellTool;
+
+        // 工作目录必须在允许的路径内
+        let cwd = temp_dir.path().join("work");
+        let result = shell_tool.execute(json!({"command": "pwd", "cwd": cwd}), &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.trim_end().ends_with("work"));
+        let result = shell_tool.execute(json!({"command": "pwd", "cwd": "/"}), &ctx).await.unwrap();
+        assert!(!result.success);
+
+        // 只能设置允许的环境变量
+        let result = shell_tool
+            .execute(json!({"command": "echo $GREETING", "env": {"GREETING": "你好"}}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(result.output.trim(), "你好");
+        let result = shell_tool
+            .execute(json!({"command": "echo $PATH", "env": {"PATH": "/tmp"}}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+
+        // 清空继承的环境变量，只保留 shell_pass_env
+        let mut tools = config.tools.clone();
+        tools.shell_clear_env = true;
+        tools.shell_pass_env = vec!["PATH".to_string()];
+        let ctx = ToolContext::new(tools);
+        let result = shell_tool.execute(json!({"command": "echo [$HOME]"}), &ctx).await.unwrap();
+        assert_eq!(result.output.trim(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_shell_streaming() {
+        use crate::tools::{with_progress_notifier, Tool};
+        use serde_json::json;
+        use std::sync::{Arc, Mutex};
+
+        let mut config = Config::default();
+        config.tools.shell_whitelist = vec!["echo".to_string(), "sleep".to_string(), "yes".to_string()];
+        config.tools.shell_progress_interval_secs = 1;
+        config.tools.shell_max_stream_bytes = 1000;
+        let ctx = ToolContext::new(config.tools.clone());
+        let shell_tool = crate::tools::shell::ShellTool;
+
+        // 运行期间推送已有的输出
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let sink = progress.clone();
+        let notifier = Arc::new(move |tool: &str, message: &str| {
+            sink.lock().unwrap().push(format!("{}: {}", tool, message));
+        });
+        let args = json!({"command": "echo start; sleep 2; echo end", "stream": true});
+        let result = with_progress_notifier(notifier, shell_tool.execute(args, &ctx)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, "start\nend\n");
+        assert_eq!(progress.lock().unwrap().first().map(String::as_str), Some("shell: start"));
+
+        // 超时终止
+        let args = json!({"command": "sleep 10", "stream": true, "timeout": 1});
+        let started = std::time::Instant::now();
+        let result = shell_tool.execute(args, &ctx).await.unwrap();
+        assert!(!result.success);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        // 输出超过上限时终止
+        let result = shell_tool.execute(json!({"command": "yes", "stream": true}), &ctx).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("上限"));
+    }
+
     #[tokio::test]
     async fn test_file_operations() {
         use crate::tools::Tool;
@@ -113,4 +754,107 @@ mod tests {
         assert!(result.success);
         assert!(result.output.contains("test.txt"));
     }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_mutating_tools() {
+        use serde_json::json;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("dry.txt");
+        std::fs::write(temp_dir.path().join("existing.txt"), "内容").unwrap();
+
+        let mut config = Config::default();
+        config.tools.allowed_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.tools.shell_whitelist = vec!["touch".to_string()];
+        config.tools.dry_run = true;
+        let ctx = ToolContext::new(config.tools.clone());
+        let registry = ToolRegistry::default_with_config(&config);
+
+        let result = registry
+            .execute("write_file", json!({"path": file_path.to_string_lossy(), "content": "x"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert!(result.output.starts_with("[演练模式]"));
+        assert!(result.output.contains("dry.txt"));
+        assert!(!file_path.exists());
+
+        let command = format!("touch {}", file_path.display());
+        registry.execute("shell", json!({"command": command}), &ctx).await.unwrap();
+        assert!(!file_path.exists());
+
+        // 只读工具照常执行
+        let result = registry
+            .execute("read_file", json!({"path": temp_dir.path().join("existing.txt").to_string_lossy()}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(result.output, "内容");
+    }
+
+    #[tokio::test]
+    async fn test_file_management_tools() {
+        use crate::tools::trash::Trash;
+        use serde_json::json;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(root.join("b.txt"), "b").unwrap();
+
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().join("workspace");
+        config.tools.allowed_paths = vec![root.to_string_lossy().to_string()];
+        let ctx = ToolContext::new(config.tools.clone());
+        let registry = ToolRegistry::default_with_config(&config);
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+
+        // 复制到目录中，目标已存在时需要 overwrite
+        let result = registry
+            .execute("copy_file", json!({"source": path("a.txt"), "destination": path("docs")}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.success, "{}", result.output);
+        assert_eq!(std::fs::read_to_string(root.join("docs/a.txt")).unwrap(), "a");
+        let result = registry
+            .execute("move_file", json!({"source": path("b.txt"), "destination": path("docs/a.txt")}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        let result = registry
+            .execute("move_file", json!({"source": path("b.txt"), "destination": path("docs/a.txt"), "overwrite": true}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.success, "{}", result.output);
+        assert!(!root.join("b.txt").exists());
+        assert_eq!(std::fs::read_to_string(root.join("docs/a.txt")).unwrap(), "b");
+
+        // 删除移到回收站，可以恢复
+        let result = registry.execute("delete_file", json!({"path": path("a.txt")}), &ctx).await.unwrap();
+        assert!(result.success, "{}", result.output);
+        assert!(!root.join("a.txt").exists());
+        let trash = Trash::new(config.trash_dir());
+        let entries = trash.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        let deleted = entries.iter().find(|e| e.original_path.ends_with("files/a.txt")).unwrap();
+        trash.restore(&deleted.id, None).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).unwrap(), "a");
+
+        // 不能删除或移动到允许范围之外
+        let outside = temp_dir.path().join("outside.txt");
+        std::fs::write(&outside, "x").unwrap();
+        let result = registry
+            .execute("delete_file", json!({"path": outside.to_string_lossy()}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(outside.exists());
+        let result = registry
+            .execute("copy_file", json!({"source": path("docs"), "destination": path("docs/nested")}), &ctx)
+            .await
+            .unwrap();
+        assert!(!result.success);
+    }
 }
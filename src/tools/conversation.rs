@@ -0,0 +1,223 @@
+//! 对话历史工具 - 让 Agent 查阅之前的会话（如"上周我们讨论过的"）
+
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::typed::TypedTool;
+use super::{ToolContext, ToolResult};
+use crate::config::ConversationAccess;
+use crate::memory::MemoryStore;
+
+/// 默认列出的会话数
+const DEFAULT_LIST_LIMIT: usize = 20;
+/// 单次最多列出的会话数
+const MAX_LIST_LIMIT: usize = 100;
+/// 默认读取的消息数
+const DEFAULT_READ_LIMIT: usize = 20;
+/// 单次最多读取的消息数
+const MAX_READ_LIMIT: usize = 100;
+/// 单条消息最多返回的字符数
+const MAX_MESSAGE_CHARS: usize = 1000;
+/// 会话列表中预览的字符数
+const PREVIEW_CHARS: usize = 60;
+
+/// 是否允许当前调用方访问指定会话
+pub(super) fn can_access(access: ConversationAccess, ctx: &ToolContext, session_id: &str) -> bool {
+    match access {
+        ConversationAccess::All => true,
+        ConversationAccess::Off => false,
+        ConversationAccess::Own => match (&ctx.channel, &ctx.chat_id) {
+            (Some(channel), Some(chat_id)) => {
+                session_id == format!("{}:{}", channel, chat_id)
+                    || ctx.session_id.as_deref() == Some(session_id)
+            }
+            // 本地命令行（无消息来源）由机器所有者使用
+            _ => true,
+        },
+    }
+}
+
+/// 截断到指定字符数
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// 列出对话历史工具
+pub struct ListConversationsTool {
+    memory: Arc<MemoryStore>,
+    access: ConversationAccess,
+}
+
+impl ListConversationsTool {
+    pub fn new(memory: Arc<MemoryStore>, access: ConversationAccess) -> Self {
+        Self { memory, access }
+    }
+}
+
+/// list_conversations 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListConversationsArgs {
+    /// 最多列出的会话数，默认 20
+    #[serde(default)]
+    #[schemars(range(min = 1, max = 100))]
+    pub limit: Option<usize>,
+}
+
+#[async_trait]
+impl TypedTool for ListConversationsTool {
+    type Args = ListConversationsArgs;
+
+    const NAME: &'static str = "list_conversations";
+    const DESCRIPTION: &'static str =
+        "列出可访问的历史会话（按最后活动时间倒序），包含会话 ID、消息数和第一条用户消息的预览";
+
+    async fn run(&self, args: ListConversationsArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let limit = args.limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+
+        let mut sessions = Vec::new();
+        for id in self.memory.list_sessions().await? {
+            if can_access(self.access, ctx, &id) {
+                let modified = self.memory.conversation_modified(&id).await;
+                sessions.push((modified, id));
+            }
+        }
+        if sessions.is_empty() {
+            return Ok(ToolResult::success("没有可访问的历史会话"));
+        }
+        sessions.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        let total = sessions.len();
+
+        let mut lines = Vec::new();
+        for (modified, id) in sessions.into_iter().take(limit) {
+            let messages = self.memory.get_conversation(&id, 0).await?;
+            let preview = messages
+                .iter()
+                .find(|m| m.role == "user")
+                .map(|m| truncate(&m.content.replace('\n', " "), PREVIEW_CHARS))
+                .unwrap_or_default();
+            lines.push(format!(
+                "- {}  {}  {} 条消息  {}",
+                id,
+                modified.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+                messages.len(),
+                preview
+            ));
+        }
+        if total > limit {
+            lines.push(format!("（共 {} 个会话，只显示最近 {} 个）", total, limit));
+        }
+        Ok(ToolResult::success(lines.join("\n")))
+    }
+}
+
+/// 读取对话历史工具
+pub struct ReadConversationTool {
+    memory: Arc<MemoryStore>,
+    access: ConversationAccess,
+}
+
+impl ReadConversationTool {
+    pub fn new(memory: Arc<MemoryStore>, access: ConversationAccess) -> Self {
+        Self { memory, access }
+    }
+}
+
+/// read_conversation 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadConversationArgs {
+    /// 会话 ID（来自 list_conversations）
+    #[schemars(length(min = 1))]
+    pub session_id: String,
+    /// 起始消息序号（从 0 开始），未指定时读取最近的消息
+    #[serde(default)]
+    pub start: Option<usize>,
+    /// 读取的消息数，默认 20
+    #[serde(default)]
+    #[schemars(range(min = 1, max = 100))]
+    pub limit: Option<usize>,
+}
+
+#[async_trait]
+impl TypedTool for ReadConversationTool {
+    type Args = ReadConversationArgs;
+
+    const NAME: &'static str = "read_conversation";
+    const DESCRIPTION: &'static str =
+        "读取历史会话中的一段消息，用于回顾之前讨论过的内容；可用 start、limit 分页";
+
+    async fn run(&self, args: ReadConversationArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let session_id = args.session_id.trim();
+        if !can_access(self.access, ctx, session_id) {
+            return Ok(ToolResult::error(format!("无权访问会话 {}", session_id)));
+        }
+
+        let messages = self.memory.get_conversation(session_id, 0).await?;
+        if messages.is_empty() {
+            return Ok(ToolResult::error(format!("会话 {} 没有对话历史", session_id)));
+        }
+
+        let total = messages.len();
+        let limit = args.limit.unwrap_or(DEFAULT_READ_LIMIT).clamp(1, MAX_READ_LIMIT);
+        let start = args.start.unwrap_or(total.saturating_sub(limit)).min(total);
+        let end = (start + limit).min(total);
+
+        let mut lines = vec![format!("会话 {} 第 {}-{} 条（共 {} 条）", session_id, start, end.saturating_sub(1), total)];
+        for (i, m) in messages[start..end].iter().enumerate() {
+            lines.push(format!(
+                "[{}] {} {}: {}",
+                start + i,
+                m.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                m.role,
+                truncate(&m.content, MAX_MESSAGE_CHARS)
+            ));
+        }
+        Ok(ToolResult::success(lines.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::tools::typed::Typed;
+    use crate::tools::Tool;
+
+    #[tokio::test]
+    async fn test_conversation_tools() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let memory = Arc::new(MemoryStore::new(temp_dir.path()).await.unwrap());
+        for i in 0..30 {
+            memory.add_message("telegram:1", "user", &format!("消息 {}", i), None).await.unwrap();
+        }
+        memory.add_message("telegram:2", "user", "别人的秘密", None).await.unwrap();
+
+        let ctx = ToolContext::new(Default::default()).with_origin(Some("telegram"), Some("1"));
+        let list = Typed::new(ListConversationsTool::new(memory.clone(), ConversationAccess::Own));
+        let output = list.execute(json!({}), &ctx).await.unwrap().output;
+        assert!(output.contains("telegram:1") && output.contains("30 条消息"));
+        assert!(!output.contains("telegram:2"));
+
+        let read = Typed::new(ReadConversationTool::new(memory.clone(), ConversationAccess::Own));
+        let result = read.execute(json!({"session_id": "telegram:1", "limit": 5}), &ctx).await.unwrap();
+        assert!(result.output.contains("[25]") && result.output.contains("消息 29"));
+        assert!(!result.output.contains("[24]"));
+
+        let result = read.execute(json!({"session_id": "telegram:1", "start": 0, "limit": 2}), &ctx).await.unwrap();
+        assert!(result.output.contains("消息 0") && !result.output.contains("消息 2"));
+
+        // 不能读取其他聊天的对话
+        let result = read.execute(json!({"session_id": "telegram:2"}), &ctx).await.unwrap();
+        assert!(!result.success);
+
+        let all = Typed::new(ReadConversationTool::new(memory, ConversationAccess::All));
+        let result = all.execute(json!({"session_id": "telegram:2"}), &ctx).await.unwrap();
+        assert!(result.output.contains("别人的秘密"));
+    }
+}
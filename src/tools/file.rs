@@ -1,33 +1,22 @@
-//! 文件操作工具 - 读写文件、列出目录
+//! 文件操作工具 - 读写文件、列出目录，删除（移到回收站）、移动和复制文件
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use super::{Tool, ToolContext, ToolDef, ToolResult};
+use super::paths::{check_path, PathAccess};
+use super::trash::{copy_path, move_path, Trash};
+use super::{Page, Tool, ToolContext, ToolDef, ToolResult};
 
-/// 验证路径是否在允许范围内
-fn validate_path(path: &Path, allowed_paths: &[String]) -> Result<()> {
-    if allowed_paths.is_empty() {
-        return Ok(());
-    }
+/// read_file 默认每页行数
+const DEFAULT_READ_LINES: usize = 500;
 
-    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+/// list_dir 默认每页条目数
+const DEFAULT_LIST_ENTRIES: usize = 200;
 
-    for allowed in allowed_paths {
-        let allowed_path = Path::new(allowed).canonicalize().unwrap_or_else(|_| Path::new(allowed).to_path_buf());
-        if canonical_path.starts_with(&allowed_path) {
-            return Ok(());
-        }
-    }
-
-    Err(anyhow::anyhow!(
-        "路径 '{}' 不在允许范围内。允许的路径: {:?}",
-        path.display(),
-        allowed_paths
-    ))
-}
+/// 可读取的最大文件大小（分页读取，不会一次性进入上下文）
+const MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
 
 /// 读取文件工具
 pub struct ReadFileTool;
@@ -38,8 +27,8 @@ impl Tool for ReadFileTool {
         lazy_static::lazy_static! {
             static ref DEF: ToolDef = ToolDef {
                 name: "read_file".to_string(),
-                description: "读取文件内容".to_string(),
-                parameters: json!({
+                description: "读取文件内容，按行分页，长文件可通过 offset 继续读取".to_string(),
+                parameters: Page::with_schema(json!({
                     "type": "object",
                     "properties": {
                         "path": {
@@ -48,7 +37,7 @@ impl Tool for ReadFileTool {
                         }
                     },
                     "required": ["path"]
-                }),
+                }), "行", DEFAULT_READ_LINES),
             };
         }
         &DEF
@@ -62,25 +51,32 @@ impl Tool for ReadFileTool {
         let path = Path::new(path_str);
 
         // 验证路径
-        if let Err(e) = validate_path(path, &ctx.config.allowed_paths) {
+        if let Err(e) = check_path(path, PathAccess::Read, &ctx.config) {
             return Ok(ToolResult::error(e.to_string()));
         }
 
-        // 检查文件大小限制（1MB）
+        // 检查文件大小限制
         let metadata = match tokio::fs::metadata(path).await {
             Ok(m) => m,
             Err(e) => return Ok(ToolResult::error(format!("无法读取文件: {}", e))),
         };
 
-        if metadata.len() > 1024 * 1024 {
-            return Ok(ToolResult::error("文件超过 1MB 限制".to_string()));
+        if metadata.len() > MAX_READ_BYTES {
+            return Ok(ToolResult::error("文件超过 10MB 限制".to_string()));
         }
 
         // 读取文件
-        match tokio::fs::read_to_string(path).await {
-            Ok(content) => Ok(ToolResult::success(content)),
-            Err(e) => Ok(ToolResult::error(format!("读取失败: {}", e))),
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) => return Ok(ToolResult::error(format!("读取失败: {}", e))),
+        };
+
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            return Ok(ToolResult::success(content));
         }
+        let page = Page::from_args(&args, DEFAULT_READ_LINES);
+        Ok(ToolResult::success(page.apply(&lines, "行")))
     }
 }
 
@@ -89,6 +85,10 @@ pub struct WriteFileTool;
 
 #[async_trait]
 impl Tool for WriteFileTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
     fn definition(&self) -> &ToolDef {
         lazy_static::lazy_static! {
             static ref DEF: ToolDef = ToolDef {
@@ -125,7 +125,7 @@ impl Tool for WriteFileTool {
         let path = Path::new(path_str);
 
         // 验证路径
-        if let Err(e) = validate_path(path, &ctx.config.allowed_paths) {
+        if let Err(e) = check_path(path, PathAccess::Write, &ctx.config) {
             return Ok(ToolResult::error(e.to_string()));
         }
 
@@ -153,8 +153,8 @@ impl Tool for ListDirTool {
         lazy_static::lazy_static! {
             static ref DEF: ToolDef = ToolDef {
                 name: "list_dir".to_string(),
-                description: "列出目录内容".to_string(),
-                parameters: json!({
+                description: "列出目录内容，条目较多时可通过 offset 分页".to_string(),
+                parameters: Page::with_schema(json!({
                     "type": "object",
                     "properties": {
                         "path": {
@@ -163,7 +163,7 @@ impl Tool for ListDirTool {
                         }
                     },
                     "required": ["path"]
-                }),
+                }), "条", DEFAULT_LIST_ENTRIES),
             };
         }
         &DEF
@@ -177,7 +177,7 @@ impl Tool for ListDirTool {
         let path = Path::new(path_str);
 
         // 验证路径
-        if let Err(e) = validate_path(path, &ctx.config.allowed_paths) {
+        if let Err(e) = check_path(path, PathAccess::Read, &ctx.config) {
             return Ok(ToolResult::error(e.to_string()));
         }
 
@@ -215,13 +215,240 @@ impl Tool for ListDirTool {
                 "-".to_string()
             };
 
-            result.push(format!("{} {:<10} {}", file_type, size, name));
+            result.push((name.clone(), format!("{} {:<10} {}", file_type, size, name)));
         }
 
         if result.is_empty() {
             Ok(ToolResult::success("目录为空".to_string()))
         } else {
-            Ok(ToolResult::success(result.join("\n")))
+            // 按名称排序，保证分页稳定
+            result.sort_by(|a, b| a.0.cmp(&b.0));
+            let lines: Vec<String> = result.into_iter().map(|(_, line)| line).collect();
+            let page = Page::from_args(&args, DEFAULT_LIST_ENTRIES);
+            Ok(ToolResult::success(page.apply(&lines, "条")))
+        }
+    }
+}
+
+/// 删除文件工具（移到回收站）
+pub struct DeleteFileTool {
+    trash: Trash,
+    retention_days: u64,
+}
+
+impl DeleteFileTool {
+    pub fn new(trash: Trash, retention_days: u64) -> Self {
+        Self { trash, retention_days }
+    }
+}
+
+#[async_trait]
+impl Tool for DeleteFileTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "delete_file".to_string(),
+                description: "删除文件或目录（移到回收站，用户可以恢复）".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "文件或目录路径"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let path_str = args.get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("缺少 path 参数"))?;
+
+        let path = PathBuf::from(path_str);
+
+        // 验证路径
+        if let Err(e) = check_path(&path, PathAccess::Write, &ctx.config) {
+            return Ok(ToolResult::error(e.to_string()));
+        }
+
+        let trash = self.trash.clone();
+        match blocking(move || trash.put(&path)).await {
+            Ok(entry) => {
+                let mut output = format!("已移到回收站: {}（条目 {}，可用 nanobot trash restore {} 恢复", path_str, entry.id, entry.id);
+                if self.retention_days > 0 {
+                    output.push_str(&format!("，保留 {} 天", self.retention_days));
+                }
+                output.push('）');
+                Ok(ToolResult::success(output))
+            }
+            Err(e) => Ok(ToolResult::error(format!("删除失败: {}", e))),
+        }
+    }
+}
+
+/// move_file / copy_file 的参数定义
+fn transfer_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "source": {
+                "type": "string",
+                "description": "源文件或目录路径"
+            },
+            "destination": {
+                "type": "string",
+                "description": "目标路径，已存在的目录表示放到该目录下"
+            },
+            "overwrite": {
+                "type": "boolean",
+                "description": "目标已存在时是否覆盖（原目标移到回收站），默认 false"
+            }
+        },
+        "required": ["source", "destination"]
+    })
+}
+
+/// 移动或复制
+#[derive(Clone, Copy, PartialEq)]
+enum Transfer {
+    Move,
+    Copy,
+}
+
+/// 检查 move_file / copy_file 的路径并执行
+async fn transfer(kind: Transfer, args: &Value, ctx: &ToolContext, trash: &Trash) -> Result<ToolResult> {
+    let source = args.get("source")
+        .and_then(|v| v.as_str())
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("缺少 source 参数"))?;
+    let destination = args.get("destination")
+        .and_then(|v| v.as_str())
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("缺少 destination 参数"))?;
+    let overwrite = args.get("overwrite").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    // 已存在的目录表示放到其中
+    let target = match (destination.is_dir(), source.file_name()) {
+        (true, Some(name)) => destination.join(name),
+        _ => destination,
+    };
+
+    // 验证路径：移动需要源和目标都可写，复制只需源可读
+    let source_access = if kind == Transfer::Move { PathAccess::Write } else { PathAccess::Read };
+    let checked = check_path(&source, source_access, &ctx.config)
+        .and_then(|_| check_path(&target, PathAccess::Write, &ctx.config));
+    if let Err(e) = checked {
+        return Ok(ToolResult::error(e.to_string()));
+    }
+
+    let trash = trash.clone();
+    let (from, to) = (source.clone(), target.clone());
+    let result = blocking(move || {
+        let source = from.canonicalize().with_context(|| format!("文件不存在: {}", from.display()))?;
+        let parent = to.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent).with_context(|| format!("创建目录失败: {}", parent.display()))?;
+        if parent.canonicalize()?.starts_with(&source) {
+            anyhow::bail!("不能把目录移动或复制到它自身内部");
+        }
+        if std::fs::symlink_metadata(&to).is_ok() {
+            if to.canonicalize().is_ok_and(|t| t == source) {
+                anyhow::bail!("源和目标相同: {}", to.display());
+            }
+            if !overwrite {
+                anyhow::bail!("目标已存在: {}（如需覆盖请设置 overwrite = true）", to.display());
+            }
+            trash.put(&to)?;
+        }
+        match kind {
+            Transfer::Move => move_path(&from, &to),
+            Transfer::Copy => copy_path(&from, &to),
+        }
+    })
+    .await;
+
+    let action = if kind == Transfer::Move { "移动" } else { "复制" };
+    match result {
+        Ok(()) => Ok(ToolResult::success(format!("已{}: {} -> {}", action, source.display(), target.display()))),
+        Err(e) => Ok(ToolResult::error(format!("{}失败: {}", action, e))),
+    }
+}
+
+/// 移动文件工具
+pub struct MoveFileTool {
+    trash: Trash,
+}
+
+impl MoveFileTool {
+    pub fn new(trash: Trash) -> Self {
+        Self { trash }
+    }
+}
+
+#[async_trait]
+impl Tool for MoveFileTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "move_file".to_string(),
+                description: "移动或重命名文件、目录".to_string(),
+                parameters: transfer_schema(),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        transfer(Transfer::Move, &args, ctx, &self.trash).await
+    }
+}
+
+/// 复制文件工具
+pub struct CopyFileTool {
+    trash: Trash,
+}
+
+impl CopyFileTool {
+    pub fn new(trash: Trash) -> Self {
+        Self { trash }
+    }
+}
+
+#[async_trait]
+impl Tool for CopyFileTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "copy_file".to_string(),
+                description: "复制文件或目录（目录递归复制）".to_string(),
+                parameters: transfer_schema(),
+            };
         }
+        &DEF
     }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        transfer(Transfer::Copy, &args, ctx, &self.trash).await
+    }
+}
+
+/// 在阻塞线程池中执行文件系统操作
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f).await?
 }
@@ -0,0 +1,174 @@
+//! 长期记忆工具 - 保存和检索跨会话的事实、偏好，回顾某一天的日记和对话
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::conversation::can_access;
+use super::profile::current_user;
+use super::typed::TypedTool;
+use super::{ToolContext, ToolResult};
+use crate::config::ConversationAccess;
+use crate::memory::day::parse_day;
+use crate::memory::{MemoryStore, DEFAULT_IMPORTANCE, MAX_IMPORTANCE};
+
+/// 默认返回的记忆条数
+const DEFAULT_RECALL_LIMIT: i64 = 10;
+
+/// 保存长期记忆工具
+pub struct RememberTool {
+    memory: Arc<MemoryStore>,
+}
+
+impl RememberTool {
+    pub fn new(memory: Arc<MemoryStore>) -> Self {
+        Self { memory }
+    }
+}
+
+/// remember 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RememberArgs {
+    /// 记忆的键，简短且唯一，如 "项目截止日期"
+    #[schemars(length(min = 1))]
+    pub key: String,
+    /// 记忆内容
+    pub value: String,
+    /// 分类（对应 MEMORY.md 中的 ## 小节），如 Preferences、Projects，默认 General；已有记忆指定其他分类时移动
+    #[serde(default)]
+    pub category: Option<String>,
+    /// 重要性 0-10，越重要越不容易在记忆满时被淘汰，默认 5
+    #[serde(default)]
+    #[schemars(range(min = 0, max = 10))]
+    pub importance: Option<i32>,
+    /// 保存为所有用户共享的记忆（如团队约定），默认只对当前用户可见
+    #[serde(default)]
+    pub shared: bool,
+}
+
+#[async_trait]
+impl TypedTool for RememberTool {
+    type Args = RememberArgs;
+
+    const NAME: &'static str = "remember";
+    const DESCRIPTION: &'static str =
+        "把值得长期记住的事实、偏好或约定保存到长期记忆，并标注重要性（0-10）；同一键再次保存时更新原记忆";
+    const MUTATING: bool = true;
+
+    async fn run(&self, args: RememberArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        if ctx.incognito {
+            return Ok(ToolResult::error("无痕模式下不保存长期记忆"));
+        }
+        let key = args.key.trim();
+        if key.is_empty() || key.contains("**") || key.contains('\n') {
+            return Ok(ToolResult::error("记忆的键无效"));
+        }
+        let importance = args.importance.unwrap_or(DEFAULT_IMPORTANCE).clamp(0, MAX_IMPORTANCE);
+        let value = args.value.replace('\n', " ");
+        let category = args.category.as_deref().map(str::trim).filter(|c| !c.is_empty());
+
+        let user = current_user(ctx).filter(|_| !args.shared);
+        let queued = self
+            .memory
+            .submit_memory(user.as_deref(), key, value.trim(), category, importance, "remember")
+            .await?;
+        if queued {
+            return Ok(ToolResult::success(format!("已提交 {}，管理员审核通过后成为长期记忆", key)));
+        }
+        Ok(ToolResult::success(format!("已记住 {}（重要性 {}）", key, importance)))
+    }
+}
+
+/// 检索长期记忆工具
+pub struct RecallTool {
+    memory: Arc<MemoryStore>,
+}
+
+impl RecallTool {
+    pub fn new(memory: Arc<MemoryStore>) -> Self {
+        Self { memory }
+    }
+}
+
+/// recall 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecallArgs {
+    /// 关键词，匹配记忆的键、内容或分类
+    pub query: String,
+    /// 最多返回的条数，默认 10
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub limit: Option<i64>,
+}
+
+#[async_trait]
+impl TypedTool for RecallTool {
+    type Args = RecallArgs;
+
+    const NAME: &'static str = "recall";
+    const DESCRIPTION: &'static str = "按关键词检索当前用户的和共享的长期记忆，结果按重要性和使用情况排序（启用语义检索时补充含义相近的记忆）";
+
+    async fn run(&self, args: RecallArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let limit = args.limit.unwrap_or(DEFAULT_RECALL_LIMIT);
+        let user = current_user(ctx);
+        let memories = self
+            .memory
+            .recall(user.as_deref(), args.query.trim(), limit)
+            .await?;
+        if memories.is_empty() {
+            return Ok(ToolResult::success("没有找到相关记忆"));
+        }
+        let lines: Vec<String> = memories
+            .iter()
+            .map(|m| format!("- {}: {}（重要性 {}）", m.key, m.value, m.importance))
+            .collect();
+        Ok(ToolResult::success(lines.join("\n")))
+    }
+}
+
+/// 回顾某一天工具
+pub struct RecallDayTool {
+    memory: Arc<MemoryStore>,
+    access: ConversationAccess,
+}
+
+impl RecallDayTool {
+    pub fn new(memory: Arc<MemoryStore>, access: ConversationAccess) -> Self {
+        Self { memory, access }
+    }
+}
+
+/// recall_day 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecallDayArgs {
+    /// 过去的日期，如 "2025-03-14"、"3月14日"、"昨天"、"上周二"、"3天前"、"last tuesday"
+    #[schemars(length(min = 1))]
+    pub date: String,
+}
+
+#[async_trait]
+impl TypedTool for RecallDayTool {
+    type Args = RecallDayArgs;
+
+    const NAME: &'static str = "recall_day";
+    const DESCRIPTION: &'static str =
+        "读取过去某一天的日常笔记和当天的对话摘录，用于回答\"上周二我在做什么\"之类关于过去某天的问题";
+
+    async fn run(&self, args: RecallDayArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let date = match parse_day(&args.date, Local::now().date_naive()) {
+            Ok(date) => date,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        let recall = self
+            .memory
+            .recall_day(date, |id| can_access(self.access, ctx, id))
+            .await?;
+        if recall.is_empty() {
+            return Ok(ToolResult::success(format!("{} 没有日常笔记和对话记录", date)));
+        }
+        Ok(ToolResult::success(recall.render()))
+    }
+}
@@ -4,94 +4,121 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
 
-use crate::channel::Channel;
+use crate::channel::outbox::{self, Outbox};
+use crate::channel::{Channel, ChannelTarget};
 
 /// 消息工具配置
-#[derive(Debug, Clone)]
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
 pub struct MessageToolConfig {
     pub default_channel: String,
     pub default_chat_id: String,
 }
 
-impl Default for MessageToolConfig {
-    fn default() -> Self {
-        Self {
-            default_channel: String::new(),
-            default_chat_id: String::new(),
-        }
-    }
-}
-
 /// 消息工具
 #[derive(Clone)]
 pub struct MessageTool {
     /// 通道管理器引用
-    channels: Vec<Arc<dyn crate::channel::Channel>>,
+    channels: Vec<Arc<dyn Channel>>,
     /// 默认通道
     default_channel: String,
     /// 默认聊天 ID
     default_chat_id: String,
+    /// 发件箱（启用时消息经发件箱投递）
+    outbox: Option<Arc<Outbox>>,
 }
 
 impl MessageTool {
-    pub fn new(channels: Vec<Arc<dyn crate::channel::Channel>>) -> Self {
+    pub fn new(channels: Vec<Arc<dyn Channel>>) -> Self {
         Self {
             channels,
             default_channel: String::new(),
             default_chat_id: String::new(),
+            outbox: None,
         }
     }
 
+    /// 设置发件箱
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
     /// 设置当前上下文
+    #[allow(dead_code)]
     pub fn set_context(&mut self, channel: &str, chat_id: &str) {
         self.default_channel = channel.to_string();
         self.default_chat_id = chat_id.to_string();
     }
+
+    /// 解析发送目标：参数 > 当前对话（ToolContext）> 手动设置的默认值
+    fn resolve_target<'a>(
+        &'a self,
+        args: &'a Value,
+        ctx: &'a crate::tools::ToolContext,
+    ) -> (&'a str, &'a str) {
+        let channel = args.get("channel")
+            .and_then(|v| v.as_str())
+            .or(ctx.channel.as_deref())
+            .unwrap_or(&self.default_channel);
+
+        let chat_id = args.get("chat_id")
+            .and_then(|v| v.as_str())
+            .or_else(|| {
+                // 只有发往当前通道时才沿用当前对话的聊天 ID
+                (ctx.channel.as_deref() == Some(channel))
+                    .then_some(ctx.chat_id.as_deref())
+                    .flatten()
+            })
+            .unwrap_or(&self.default_chat_id);
+
+        (channel, chat_id)
+    }
 }
 
 #[async_trait]
 impl crate::tools::Tool for MessageTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
     fn definition(&self) -> &crate::tools::ToolDef {
-        &crate::tools::ToolDef {
-            name: "message".to_string(),
-            description: "Send a message to the user. Use this when you want to communicate something to the user on the chat platform.".to_string(),
-            parameters: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "content": {
-                        "type": "string",
-                        "description": "The message content to send"
-                    },
-                    "channel": {
-                        "type": "string", 
-                        "description": "Optional: target channel (telegram, discord, feishu, whatsapp)"
+        lazy_static::lazy_static! {
+            static ref DEF: crate::tools::ToolDef = crate::tools::ToolDef {
+                name: "message".to_string(),
+                description: "Send a message to the user. Use this when you want to communicate something to the user on the chat platform. Defaults to the current conversation.".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "content": {
+                            "type": "string",
+                            "description": "The message content to send"
+                        },
+                        "channel": {
+                            "type": "string",
+                            "description": "Optional: target channel (telegram, discord, feishu, whatsapp), defaults to the current one"
+                        },
+                        "chat_id": {
+                            "type": "string",
+                            "description": "Optional: target chat ID, defaults to the current conversation. Also accepts user:<id> (direct message), <chat_id>#thread:<thread_id> (topic/thread), tel:<phone number>, mailto:<email> where the channel supports them"
+                        }
                     },
-                    "chat_id": {
-                        "type": "string",
-                        "description": "Optional: target chat/user ID"
-                    }
-                },
-                "required": ["content"]
-            }),
+                    "required": ["content"]
+                }),
+            };
         }
+        &DEF
     }
 
-    async fn execute(&self, args: Value, _ctx: &crate::tools::ToolContext) -> Result<crate::tools::ToolResult> {
+    async fn execute(&self, args: Value, ctx: &crate::tools::ToolContext) -> Result<crate::tools::ToolResult> {
         let content = args.get("content")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing 'content' parameter"))?;
 
-        let channel = args.get("channel")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&self.default_channel);
-
-        let chat_id = args.get("chat_id")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&self.default_chat_id);
+        let (channel, chat_id) = self.resolve_target(&args, ctx);
 
         if channel.is_empty() || chat_id.is_empty() {
             return Ok(crate::tools::ToolResult::error(
@@ -99,16 +126,17 @@ impl crate::tools::Tool for MessageTool {
             ));
         }
 
-        // 查找目标通道
-        let target_channel = if channel.is_empty() {
-            self.channels.first()
-        } else {
-            self.channels.iter().find(|c| c.name() == channel)
+        let target = match ChannelTarget::parse(chat_id) {
+            Ok(target) => target,
+            Err(e) => return Ok(crate::tools::ToolResult::error(format!("Invalid chat_id: {}", e))),
         };
 
+        // 查找目标通道
+        let target_channel = self.channels.iter().find(|c| c.name() == channel);
+
         match target_channel {
             Some(ch) => {
-                match ch.send_message(chat_id, content).await {
+                match outbox::deliver(self.outbox.as_ref(), ch.as_ref(), &target, content).await {
                     Ok(_) => Ok(crate::tools::ToolResult::success(
                         format!("Message sent to {}:{}", channel, chat_id)
                     )),
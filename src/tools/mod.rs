@@ -7,18 +7,58 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
 
+use crate::bus::{EventBus, ToolRegistryEvent};
+
+pub mod conversation;
 pub mod file;
+pub mod guard;
+pub mod memory;
 pub mod message;
+pub mod paths;
+pub mod pin;
+pub mod profile;
+pub mod reminder;
 pub mod shell;
+pub mod task;
+pub mod trash;
+pub mod typed;
+pub mod url_policy;
 pub mod web;
 
+/// 工具执行进度回调：工具名、进度内容
+pub type ProgressNotifier = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+tokio::task_local! {
+    static PROGRESS_NOTIFIER: ProgressNotifier;
+}
+
+/// 在 `fut` 执行期间，工具报告进度时调用 `notifier`
+pub async fn with_progress_notifier<F: Future>(notifier: ProgressNotifier, fut: F) -> F::Output {
+    PROGRESS_NOTIFIER.scope(notifier, fut).await
+}
+
+/// 报告工具执行进度（未设置回调时忽略）
+pub fn report_progress(tool: &str, message: &str) {
+    let _ = PROGRESS_NOTIFIER.try_with(|notify| notify(tool, message));
+}
+
 /// 工具执行上下文
 #[derive(Debug, Clone)]
 pub struct ToolContext {
     pub config: crate::config::ToolsConfig,
     pub working_dir: std::path::PathBuf,
+    /// 当前会话 ID（由 Agent 设置）
+    pub session_id: Option<String>,
+    /// 当前消息来源通道，message 等工具的默认发送目标
+    pub channel: Option<String>,
+    /// 当前消息来源聊天 ID
+    pub chat_id: Option<String>,
+    /// 当前会话处于无痕模式（不写入长期记忆）
+    pub incognito: bool,
 }
 
 impl ToolContext {
@@ -26,8 +66,31 @@ impl ToolContext {
         Self {
             config,
             working_dir: std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("/tmp")),
+            session_id: None,
+            channel: None,
+            chat_id: None,
+            incognito: false,
         }
     }
+
+    /// 设置当前消息来源（通道与聊天 ID）
+    pub fn with_origin(mut self, channel: Option<&str>, chat_id: Option<&str>) -> Self {
+        self.channel = channel.map(str::to_string);
+        self.chat_id = chat_id.map(str::to_string);
+        self
+    }
+
+    /// 设置当前会话
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// 标记当前会话是否处于无痕模式
+    pub fn with_incognito(mut self, incognito: bool) -> Self {
+        self.incognito = incognito;
+        self
+    }
 }
 
 /// 工具定义
@@ -72,12 +135,14 @@ impl ToolResult {
             error: Some(error.into()),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for ToolResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.success {
-            self.output.clone()
+            f.write_str(&self.output)
         } else {
-            format!("错误: {}", self.error.as_ref().unwrap_or(&"未知错误".to_string()))
+            write!(f, "错误: {}", self.error.as_deref().unwrap_or("未知错误"))
         }
     }
 }
@@ -95,39 +160,255 @@ pub trait Tool: Send + Sync {
     fn name(&self) -> &str {
         &self.definition().name
     }
+
+    /// 是否有副作用（执行命令、写文件、发送消息、修改记忆等），演练模式下不实际执行
+    fn is_mutating(&self) -> bool {
+        false
+    }
+}
+
+/// 参数校验错误最多列出的条数
+const MAX_VALIDATION_ERRORS: usize = 5;
+
+/// 按字符数截断工具输出，并注明截断位置（limit 为 0 时不截断）
+pub fn truncate_output(output: &str, limit: usize) -> String {
+    if limit == 0 {
+        return output.to_string();
+    }
+    let total = output.chars().count();
+    if total <= limit {
+        return output.to_string();
+    }
+
+    let kept: String = output.chars().take(limit).collect();
+    format!(
+        "{}\n\n[输出已截断：共 {} 字符，仅显示前 {} 字符。支持分页的工具可通过 offset/limit 参数读取后续内容，其他命令请缩小输出范围]",
+        kept, total, limit
+    )
+}
+
+/// 演练模式下代替执行结果返回的说明
+fn dry_run_output(name: &str, args: &Value) -> String {
+    format!(
+        "[演练模式] 未实际执行工具 {}，实际执行时将使用以下参数：\n{}\n请按操作成功继续，并在回复中说明这是演练、没有产生实际效果。",
+        name,
+        serde_json::to_string_pretty(args).unwrap_or_default()
+    )
+}
+
+/// 分页参数（offset 从 0 开始）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    /// 从工具参数中读取 `offset`/`limit`
+    pub fn from_args(args: &Value, default_limit: usize) -> Self {
+        let get = |key: &str| args.get(key).and_then(|v| v.as_u64()).map(|v| v as usize);
+        Self {
+            offset: get("offset").unwrap_or(0),
+            limit: get("limit").filter(|&l| l > 0).unwrap_or(default_limit),
+        }
+    }
+
+    /// 取出当前页，不足一页时附加说明，还有后续内容时提示下一页的 offset
+    pub fn apply(&self, items: &[String], unit: &str) -> String {
+        let total = items.len();
+        if self.offset >= total {
+            return format!("[offset {} 超出范围，共 {} {}]", self.offset, total, unit);
+        }
+
+        let end = (self.offset + self.limit).min(total);
+        let mut out = items[self.offset..end].join("\n");
+        if self.offset > 0 || end < total {
+            out.push_str(&format!(
+                "\n\n[第 {}-{} {}，共 {} {}",
+                self.offset + 1,
+                end,
+                unit,
+                total,
+                unit
+            ));
+            if end < total {
+                out.push_str(&format!("；使用 offset={} 继续读取", end));
+            }
+            out.push(']');
+        }
+        out
+    }
+
+    /// 在工具参数定义中加入 offset/limit 字段
+    pub fn with_schema(mut parameters: Value, unit: &str, default_limit: usize) -> Value {
+        if let Some(props) = parameters.get_mut("properties").and_then(|p| p.as_object_mut()) {
+            props.insert(
+                "offset".to_string(),
+                serde_json::json!({
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": format!("从第几{}开始（从 0 开始），用于分页读取", unit)
+                }),
+            );
+            props.insert(
+                "limit".to_string(),
+                serde_json::json!({
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": format!("最多返回多少{}，默认 {}", unit, default_limit)
+                }),
+            );
+        }
+        parameters
+    }
+}
+
+/// 注册表内部状态
+#[derive(Default)]
+struct RegistryInner {
+    tools: HashMap<String, Arc<dyn Tool>>,
+    /// 各工具参数的 JSON Schema 校验器（注册时编译）
+    validators: HashMap<String, Arc<jsonschema::Validator>>,
+    /// 注册/注销时发布 ToolRegistryEvent
+    event_bus: Option<Arc<EventBus>>,
 }
 
 /// 工具注册表
+///
+/// 克隆得到的是同一注册表的句柄，运行期间可随时注册/注销工具（MCP、插件等），
+/// 对所有持有者立即生效
+#[derive(Clone)]
 pub struct ToolRegistry {
-    tools: HashMap<String, Arc<dyn Tool>>,
+    inner: Arc<RwLock<RegistryInner>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
-            tools: HashMap::new(),
+            inner: Arc::new(RwLock::new(RegistryInner::default())),
         }
     }
 
-    /// 注册工具
-    pub fn register<T: Tool + 'static>(&mut self, tool: T) {
+    /// 设置事件总线（对所有句柄生效，之后的注册/注销发布事件）
+    pub fn set_event_bus(&self, event_bus: Arc<EventBus>) {
+        self.inner.write().unwrap_or_else(|e| e.into_inner()).event_bus = Some(event_bus);
+    }
+
+    /// 注册工具（同名工具会被替换）
+    pub fn register<T: Tool + 'static>(&self, tool: T) {
+        self.register_arc(Arc::new(tool));
+    }
+
+    /// 注册已共享的工具实例
+    pub fn register_arc(&self, tool: Arc<dyn Tool>) {
         let name = tool.name().to_string();
-        self.tools.insert(name, Arc::new(tool));
+        let validator = match jsonschema::validator_for(&tool.definition().parameters) {
+            Ok(validator) => Some(Arc::new(validator)),
+            Err(e) => {
+                warn!("工具 {} 的参数 Schema 无效，跳过参数校验: {}", name, e);
+                None
+            }
+        };
+
+        {
+            let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+            match validator {
+                Some(v) => inner.validators.insert(name.clone(), v),
+                None => inner.validators.remove(&name),
+            };
+            inner.tools.insert(name.clone(), tool);
+        }
+
+        debug!("注册工具: {}", name);
+        self.publish(&name, "registered");
+    }
+
+    /// 注销工具，返回工具是否存在
+    pub fn unregister(&self, name: &str) -> bool {
+        let removed = {
+            let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+            inner.validators.remove(name);
+            inner.tools.remove(name).is_some()
+        };
+
+        if removed {
+            debug!("注销工具: {}", name);
+            self.publish(name, "unregistered");
+        }
+        removed
+    }
+
+    fn publish(&self, name: &str, action: &str) {
+        let event_bus = self.inner.read().unwrap_or_else(|e| e.into_inner()).event_bus.clone();
+        if let Some(bus) = event_bus {
+            let _ = bus.publish(ToolRegistryEvent {
+                tool_name: name.to_string(),
+                action: action.to_string(),
+                timestamp: chrono::Utc::now(),
+            });
+        }
+    }
+
+    /// 按工具定义校验参数，失败时返回可供模型自行修正的错误说明
+    pub fn validate(&self, name: &str, args: &Value) -> Option<String> {
+        let validator = self
+            .inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .validators
+            .get(name)
+            .cloned()?;
+        let errors: Vec<String> = validator
+            .iter_errors(args)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                let path = if path.is_empty() { "/".to_string() } else { path };
+                format!("- {}: {}", path, e)
+            })
+            .collect();
+        if errors.is_empty() {
+            return None;
+        }
+
+        let mut message = format!("工具 {} 参数校验失败:\n", name);
+        for line in errors.iter().take(MAX_VALIDATION_ERRORS) {
+            message.push_str(line);
+            message.push('\n');
+        }
+        if errors.len() > MAX_VALIDATION_ERRORS {
+            message.push_str(&format!("- ……另有 {} 处错误\n", errors.len() - MAX_VALIDATION_ERRORS));
+        }
+        message.push_str("请按照工具的参数定义修正后重试。");
+        Some(message)
     }
 
     /// 获取工具
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
-        self.tools.get(name).cloned()
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .tools
+            .get(name)
+            .cloned()
     }
 
-    /// 列出所有工具
-    pub fn list_tools(&self) -> Vec<&ToolDef> {
-        self.tools.values().map(|t| t.definition()).collect()
+    /// 列出所有工具（按名称排序）
+    pub fn list_tools(&self) -> Vec<ToolDef> {
+        let mut defs: Vec<ToolDef> = self
+            .inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .tools
+            .values()
+            .map(|t| t.definition().clone())
+            .collect();
+        defs.sort_by(|a, b| a.name.cmp(&b.name));
+        defs
     }
 
     /// 获取 LLM 可用的工具列表
     pub fn to_llm_tools(&self) -> Vec<crate::llm::Tool> {
-        self.list_tools().into_iter().map(|t| t.to_llm_tool()).collect()
+        self.list_tools().iter().map(|t| t.to_llm_tool()).collect()
     }
 
     /// 执行工具
@@ -137,16 +418,27 @@ impl ToolRegistry {
         args: Value,
         ctx: &ToolContext,
     ) -> Result<ToolResult> {
-        let tool = self.tools
-            .get(name)
+        let tool = self.get(name)
             .ok_or_else(|| anyhow!("未知工具: {}", name))?;
-        
-        tool.execute(args, ctx).await
+
+        if let Some(error) = self.validate(name, &args) {
+            return Ok(ToolResult::error(error));
+        }
+
+        if ctx.config.dry_run && tool.is_mutating() {
+            info!("演练模式：跳过工具 {}", name);
+            return Ok(ToolResult::success(dry_run_output(name, &args)));
+        }
+
+        let mut result = tool.execute(args, ctx).await?;
+        result.output = truncate_output(&result.output, ctx.config.output_limit(name));
+        result.output = guard::sanitize_output(name, &result.output, &ctx.config.injection_guard);
+        Ok(result)
     }
 
     /// 创建默认工具集
     pub fn default_with_config(config: &crate::config::Config) -> Self {
-        let mut registry = Self::new();
+        let registry = Self::new();
         
         // 注册 Shell 工具
         registry.register(shell::ShellTool);
@@ -155,7 +447,14 @@ impl ToolRegistry {
         registry.register(file::ReadFileTool);
         registry.register(file::WriteFileTool);
         registry.register(file::ListDirTool);
+        let trash = trash::Trash::new(config.trash_dir());
+        registry.register(file::DeleteFileTool::new(trash.clone(), config.tools.trash_retention_days));
+        registry.register(file::MoveFileTool::new(trash.clone()));
+        registry.register(file::CopyFileTool::new(trash));
         
+        // 注册网页读取工具
+        registry.register(web::FetchPageTool::new());
+
         // 注册 Web 搜索工具（如果配置了 API Key）
         if config.tools.search_api_key.is_some() {
             registry.register(web::WebSearchTool::new(
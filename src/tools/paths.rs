@@ -0,0 +1,201 @@
+//! 文件路径权限
+//!
+//! 文件工具和 shell 的工作目录统一经过这里检查。`read_paths` 中的路径只能读取，`write_paths` 中的路径可读写，
+//! 旧的 `allowed_paths` 等同于可读写；三者都为空时不限制。`deny_paths` 优先于其他配置，对读写都生效。
+//! 每一项可以是目录（包括其下所有文件），也可以是 glob 模式：`*` 匹配一级路径中的任意字符，
+//! `**` 匹配任意多级目录（如 `**/.env`、`/home/*/projects`），模式匹配路径本身或它的任意上级目录
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::ToolsConfig;
+
+/// 访问方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    Read,
+    Write,
+}
+
+/// 检查路径是否允许以指定方式访问
+pub fn check_path(path: &Path, access: PathAccess, config: &ToolsConfig) -> Result<()> {
+    let path = normalize(path);
+
+    if let Some(pattern) = config.deny_paths.iter().find(|p| matches(p, &path)) {
+        bail!("路径 '{}' 被禁止访问（{}）", path.display(), pattern);
+    }
+
+    let writable = config.allowed_paths.iter().chain(&config.write_paths);
+    let allowed: Vec<&String> = match access {
+        PathAccess::Read => writable.chain(&config.read_paths).collect(),
+        PathAccess::Write => writable.collect(),
+    };
+    let unrestricted = config.allowed_paths.is_empty() && config.read_paths.is_empty() && config.write_paths.is_empty();
+    if unrestricted || allowed.iter().any(|p| matches(p, &path)) {
+        return Ok(());
+    }
+
+    match access {
+        PathAccess::Read => bail!("路径 '{}' 不在允许读取的范围内。允许的路径: {:?}", path.display(), allowed),
+        PathAccess::Write => bail!("路径 '{}' 不在允许写入的范围内。允许写入的路径: {:?}", path.display(), allowed),
+    }
+}
+
+/// 解析为绝对路径：存在的部分解析符号链接，不存在的部分（如待写入的新文件）按字面处理 `.` 和 `..`
+fn normalize(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    let base = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => break existing.to_path_buf(),
+        }
+    };
+
+    let mut result = base;
+    for component in rest.iter().rev().flat_map(|part| Path::new(part).components()) {
+        match component {
+            Component::ParentDir => {
+                result.pop();
+            }
+            Component::CurDir => {}
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+/// 路径是否匹配配置项（目录前缀或 glob 模式）
+fn matches(entry: &str, path: &Path) -> bool {
+    if entry.contains(['*', '?', '[']) {
+        return glob_matches(entry, path);
+    }
+    let entry = Path::new(entry);
+    let entry = entry.canonicalize().unwrap_or_else(|_| entry.to_path_buf());
+    path.starts_with(entry)
+}
+
+/// 路径（或其任意上级目录）是否匹配 glob 模式
+pub fn glob_matches(pattern: &str, path: &Path) -> bool {
+    glob_regex(pattern).is_some_and(|regex| path.ancestors().any(|p| regex.is_match(&p.to_string_lossy())))
+}
+
+/// 把 glob 模式转换为正则表达式，相对模式（如 `.env`）匹配任意目录下的同名路径
+fn glob_regex(pattern: &str) -> Option<Regex> {
+    let pattern = if pattern.starts_with('/') || pattern.starts_with("**") {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+
+    let mut regex = String::from("^");
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i..].iter().position(|&c| c == ']') {
+                Some(end) => {
+                    let class: String = chars[i + 1..i + end].iter().collect();
+                    regex.push('[');
+                    regex.push_str(&class.replacen('!', "^", 1).replace('\\', "\\\\"));
+                    regex.push(']');
+                    i += end + 1;
+                    continue;
+                }
+                None => regex.push_str("\\["),
+            },
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_regex() {
+        let env = glob_regex("**/.env").unwrap();
+        assert!(env.is_match("/home/user/project/.env"));
+        assert!(!env.is_match("/home/user/project/.env.example"));
+        assert!(glob_regex(".env").unwrap().is_match("/srv/.env"));
+
+        let projects = glob_regex("/home/*/projects").unwrap();
+        assert!(projects.is_match("/home/alice/projects"));
+        assert!(!projects.is_match("/home/alice/work/projects"));
+
+        assert!(glob_regex("/var/log/*.log").unwrap().is_match("/var/log/app.log"));
+        assert!(glob_regex("**/id_[!.]*").unwrap().is_match("/home/a/.ssh/id_rsa"));
+    }
+
+    #[test]
+    fn test_check_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let projects = root.join("projects");
+        let scratch = root.join("scratch");
+        std::fs::create_dir_all(projects.join("app")).unwrap();
+        std::fs::create_dir_all(&scratch).unwrap();
+        std::fs::write(projects.join("app/.env"), "SECRET=1").unwrap();
+
+        let config = ToolsConfig {
+            allowed_paths: Vec::new(),
+            read_paths: vec![projects.to_string_lossy().to_string()],
+            write_paths: vec![scratch.to_string_lossy().to_string()],
+            deny_paths: vec!["**/.env".to_string(), "**/.git".to_string()],
+            ..Default::default()
+        };
+        let check = |path: PathBuf, access| check_path(&path, access, &config).is_ok();
+
+        assert!(check(projects.join("app/main.rs"), PathAccess::Read));
+        assert!(!check(projects.join("app/main.rs"), PathAccess::Write));
+        assert!(check(scratch.join("new/notes.md"), PathAccess::Write));
+        assert!(check(scratch.join("notes.md"), PathAccess::Read));
+        assert!(!check(projects.join("app/.env"), PathAccess::Read));
+        assert!(!check(projects.join("app/.git/config"), PathAccess::Read));
+        assert!(!check(root.join("other.txt"), PathAccess::Read));
+        // 不能借助 .. 跳出允许的目录
+        assert!(!check(scratch.join("../projects/app/main.rs"), PathAccess::Write));
+
+        // 只配置 allowed_paths 时保持原来的读写权限
+        let legacy = ToolsConfig {
+            allowed_paths: vec![projects.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        assert!(check_path(&projects.join("app/x"), PathAccess::Write, &legacy).is_ok());
+        assert!(check_path(&root.join("x"), PathAccess::Read, &legacy).is_err());
+        let unrestricted = ToolsConfig {
+            allowed_paths: Vec::new(),
+            ..Default::default()
+        };
+        assert!(check_path(&root.join("x"), PathAccess::Write, &unrestricted).is_ok());
+    }
+}
@@ -0,0 +1,56 @@
+//! 置顶工具 - 将重要内容固定在会话上下文中
+
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::typed::TypedTool;
+use super::{ToolContext, ToolResult};
+use crate::session::SessionManager;
+
+/// 置顶上下文工具
+///
+/// 置顶内容随系统提示词一起发送，不会在上下文裁剪时丢弃
+pub struct PinContextTool {
+    sessions: Arc<SessionManager>,
+}
+
+impl PinContextTool {
+    pub fn new(sessions: Arc<SessionManager>) -> Self {
+        Self { sessions }
+    }
+}
+
+/// pin_context 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PinArgs {
+    /// 要置顶的内容，尽量简洁完整
+    #[schemars(length(min = 1))]
+    pub text: String,
+}
+
+#[async_trait]
+impl TypedTool for PinContextTool {
+    type Args = PinArgs;
+
+    const NAME: &'static str = "pin_context";
+    const DESCRIPTION: &'static str =
+        "置顶当前会话中的重要信息（如用户偏好、关键事实、任务约束），置顶内容在整个会话中始终保留";
+    const MUTATING: bool = true;
+
+    async fn run(&self, args: PinArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let text = args.text.trim();
+        if text.is_empty() {
+            return Ok(ToolResult::error("置顶内容不能为空"));
+        }
+
+        let Some(ref session_id) = ctx.session_id else {
+            return Ok(ToolResult::error("当前没有会话，无法置顶"));
+        };
+
+        let count = self.sessions.add_pin(session_id, text).await?;
+        Ok(ToolResult::success(format!("已置顶（当前共 {} 条）", count)))
+    }
+}
@@ -0,0 +1,70 @@
+//! 用户资料工具 - 记住当前用户的称呼、时区、偏好等
+
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::typed::TypedTool;
+use super::{ToolContext, ToolResult};
+use crate::memory::MemoryStore;
+
+/// 用户资料工具
+///
+/// 资料按用户（通道:聊天 ID）分别保存，随系统提示词一起发送
+pub struct RememberUserTool {
+    memory: Arc<MemoryStore>,
+}
+
+impl RememberUserTool {
+    pub fn new(memory: Arc<MemoryStore>) -> Self {
+        Self { memory }
+    }
+}
+
+/// remember_user 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RememberUserArgs {
+    /// 资料项，如 name、timezone、preferences
+    #[schemars(length(min = 1))]
+    pub key: String,
+    /// 资料内容
+    pub value: String,
+}
+
+/// 当前用户：优先使用消息来源，否则使用会话 ID
+pub fn current_user(ctx: &ToolContext) -> Option<String> {
+    match (&ctx.channel, &ctx.chat_id) {
+        (Some(channel), Some(chat_id)) => Some(format!("{}:{}", channel, chat_id)),
+        _ => ctx.session_id.clone(),
+    }
+}
+
+#[async_trait]
+impl TypedTool for RememberUserTool {
+    type Args = RememberUserArgs;
+
+    const NAME: &'static str = "remember_user";
+    const DESCRIPTION: &'static str =
+        "记住当前用户的个人资料（如称呼、时区、偏好），同一资料项再次保存时覆盖旧值";
+    const MUTATING: bool = true;
+
+    async fn run(&self, args: RememberUserArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        if ctx.incognito {
+            return Ok(ToolResult::error("无痕模式下不保存用户资料"));
+        }
+        let key = args.key.trim();
+        if key.is_empty() || key.contains("**") || key.contains('\n') {
+            return Ok(ToolResult::error("资料项名称无效"));
+        }
+
+        let Some(user) = current_user(ctx) else {
+            return Ok(ToolResult::error("无法确定当前用户"));
+        };
+
+        let value = args.value.replace('\n', " ");
+        self.memory.save_user_fact(&user, key, &value).await?;
+        Ok(ToolResult::success(format!("已记住 {}: {}", key, value.trim())))
+    }
+}
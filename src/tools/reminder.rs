@@ -0,0 +1,386 @@
+//! 提醒工具 - 用自然语言创建、查看和取消定时提醒
+//!
+//! Agent 创建的任务记录创建者（通道:聊天 ID），查看和取消时只涉及当前用户自己的任务，
+//! 取消时按名称模糊匹配（"取消健身提醒"）。时间按用户资料中的时区（引导时保存的 timezone）
+//! 解释，用户没有保存时区时使用 agent.timezone
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::{Tool, ToolContext, ToolDef, ToolResult};
+use crate::agent::bestof::similarity;
+use crate::cron::reminder::{split_reminder, Timezone};
+use crate::cron::{Job, JobType, Scheduler};
+use crate::memory::MemoryStore;
+
+/// 模糊匹配的最低相似度
+const MIN_MATCH_SIMILARITY: f64 = 0.3;
+
+/// 用户资料中保存时区的资料项
+const TIMEZONE_KEY: &str = "timezone";
+
+/// 任务创建者：当前对话（通道:聊天 ID），没有来源时为会话 ID
+fn owner(ctx: &ToolContext) -> Option<String> {
+    match (ctx.channel.as_deref(), ctx.chat_id.as_deref()) {
+        (Some(channel), Some(chat_id)) => Some(format!("{}:{}", channel, chat_id)),
+        _ => ctx.session_id.clone(),
+    }
+}
+
+/// 用户时区：用户资料中保存的时区优先，否则使用默认时区
+#[derive(Clone)]
+pub struct UserTimezone {
+    default: Timezone,
+    memory: Option<Arc<MemoryStore>>,
+}
+
+impl UserTimezone {
+    pub fn new(default: Timezone) -> Self {
+        Self { default, memory: None }
+    }
+
+    /// 从用户资料读取时区
+    pub fn with_memory(mut self, memory: Arc<MemoryStore>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// 当前用户的时区，资料中的时区无法识别时返回错误
+    async fn resolve(&self, ctx: &ToolContext) -> Result<Timezone> {
+        let (Some(memory), Some(user)) = (&self.memory, owner(ctx)) else {
+            return Ok(self.default);
+        };
+        match memory.user_fact(&user, TIMEZONE_KEY).await? {
+            Some(tz) => tz.parse().map_err(|_| {
+                anyhow!(
+                    "无法识别用户资料中的时区 \"{}\"，请用 remember_user 把 timezone 改存为 IANA 时区名（如 Asia/Shanghai）或 UTC 偏移（如 +08:00）",
+                    tz
+                )
+            }),
+            None => Ok(self.default),
+        }
+    }
+
+    /// 当前用户的时区，无法识别时使用默认时区（仅用于显示）
+    async fn resolve_or_default(&self, ctx: &ToolContext) -> Timezone {
+        self.resolve(ctx).await.unwrap_or(self.default)
+    }
+}
+
+/// 提醒内容（没有时用任务名称）
+fn reminder_text(job: &Job) -> &str {
+    job.handler_args
+        .as_ref()
+        .and_then(|a| a.get("text"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(&job.name)
+}
+
+/// 一行任务说明
+fn describe_job(job: &Job, timezone: Timezone) -> String {
+    let when = match &job.job_type {
+        JobType::Once { run_at } => run_at
+            .with_timezone(&timezone.offset_at(*run_at))
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        JobType::Cron { expression } => format!("cron({}) {}", job.timezone.as_deref().unwrap_or("UTC"), expression),
+        JobType::Interval { seconds } => format!("每 {} 秒", seconds),
+        JobType::Webhook { .. } => "Webhook 触发".to_string(),
+    };
+    format!("[{}] {}（{}，{:?}）", &job.id[..8.min(job.id.len())], reminder_text(job), when, job.status)
+}
+
+/// 按 ID（前缀）或名称匹配任务：先找包含关系，再按相似度取最接近的
+fn match_jobs<'a>(jobs: &'a [Job], query: &str) -> Vec<&'a Job> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let by_id: Vec<&Job> = jobs.iter().filter(|j| j.id.starts_with(&query)).collect();
+    if !by_id.is_empty() {
+        return by_id;
+    }
+    let contains: Vec<&Job> = jobs
+        .iter()
+        .filter(|j| {
+            let text = reminder_text(j).to_lowercase();
+            text.contains(&query) || query.contains(&text)
+        })
+        .collect();
+    if !contains.is_empty() {
+        return contains;
+    }
+    jobs.iter()
+        .map(|j| (similarity(&reminder_text(j).to_lowercase(), &query), j))
+        .filter(|(score, _)| *score >= MIN_MATCH_SIMILARITY)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, j)| vec![j])
+        .unwrap_or_default()
+}
+
+/// 定时提醒工具
+pub struct ScheduleReminderTool {
+    scheduler: Arc<Scheduler>,
+    timezone: UserTimezone,
+}
+
+impl ScheduleReminderTool {
+    pub fn new(scheduler: Arc<Scheduler>, timezone: UserTimezone) -> Self {
+        Self { scheduler, timezone }
+    }
+}
+
+#[async_trait]
+impl Tool for ScheduleReminderTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "schedule_reminder".to_string(),
+                description: "创建定时提醒。when 支持自然语言，如 \"in 20 minutes\"、\"every weekday at 9am\"、\"next Friday 15:00\"、\"明天早上八点\"、\"每周一上午十点\"".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "when": {
+                            "type": "string",
+                            "description": "提醒时间（自然语言）"
+                        },
+                        "text": {
+                            "type": "string",
+                            "description": "提醒内容"
+                        },
+                        "channel": {
+                            "type": "string",
+                            "description": "可选：提醒发送的通道（telegram、feishu 等），默认为当前对话"
+                        },
+                        "chat_id": {
+                            "type": "string",
+                            "description": "可选：提醒发送的聊天 ID，默认为当前对话"
+                        }
+                    },
+                    "required": ["when", "text"]
+                }),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let when = args.get("when")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("缺少 when 参数"))?;
+
+        // 兼容把 "<时间>: <内容>" 整体放进 when 的写法
+        let (when, text) = match args.get("text").and_then(|v| v.as_str()) {
+            Some(text) => (when, text),
+            None => split_reminder(when).ok_or_else(|| anyhow::anyhow!("缺少 text 参数"))?,
+        };
+
+        // 未指定时发送到当前对话
+        let channel = args.get("channel").and_then(|v| v.as_str()).or(ctx.channel.as_deref());
+        let chat_id = args.get("chat_id").and_then(|v| v.as_str()).or_else(|| {
+            (ctx.channel.as_deref() == channel)
+                .then_some(ctx.chat_id.as_deref())
+                .flatten()
+        });
+
+        let timezone = match self.timezone.resolve(ctx).await {
+            Ok(tz) => tz,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        let schedule = match timezone.parse_when(when, Utc::now()) {
+            Ok(s) => s,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        let description = schedule.describe(timezone);
+
+        let mut job = schedule.into_job(text, channel, chat_id);
+        if let Some(owner) = owner(ctx) {
+            job = job.with_owner(owner);
+        }
+        match self.scheduler.add_job(job).await {
+            Ok(job_id) => Ok(ToolResult::success(format!(
+                "已创建提醒 {}：{}（{}）",
+                job_id, text, description
+            ))),
+            Err(e) => Ok(ToolResult::error(format!("创建提醒失败: {}", e))),
+        }
+    }
+}
+
+/// 查看当前用户的提醒
+pub struct ListRemindersTool {
+    scheduler: Arc<Scheduler>,
+    timezone: UserTimezone,
+}
+
+impl ListRemindersTool {
+    pub fn new(scheduler: Arc<Scheduler>, timezone: UserTimezone) -> Self {
+        Self { scheduler, timezone }
+    }
+}
+
+#[async_trait]
+impl Tool for ListRemindersTool {
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "list_reminders".to_string(),
+                description: "列出当前用户通过 schedule_reminder 创建、尚未完成的提醒".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, _args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let Some(owner) = owner(ctx) else {
+            return Ok(ToolResult::error("无法确定当前用户"));
+        };
+        let jobs = self.scheduler.jobs_for_owner(&owner).await;
+        if jobs.is_empty() {
+            return Ok(ToolResult::success("当前没有提醒"));
+        }
+        let timezone = self.timezone.resolve_or_default(ctx).await;
+        let lines: Vec<String> = jobs.iter().map(|j| format!("- {}", describe_job(j, timezone))).collect();
+        Ok(ToolResult::success(format!("共 {} 个提醒:\n{}", jobs.len(), lines.join("\n"))))
+    }
+}
+
+/// 按名称或 ID 取消当前用户的提醒
+pub struct CancelReminderTool {
+    scheduler: Arc<Scheduler>,
+    timezone: UserTimezone,
+}
+
+impl CancelReminderTool {
+    pub fn new(scheduler: Arc<Scheduler>, timezone: UserTimezone) -> Self {
+        Self { scheduler, timezone }
+    }
+}
+
+#[async_trait]
+impl Tool for CancelReminderTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "cancel_reminder".to_string(),
+                description: "取消当前用户的提醒。query 可以是提醒内容中的关键词（如 \"健身\"）或 list_reminders 显示的 ID；匹配到多个时返回候选，请确认后用 ID 取消".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "提醒内容关键词或 ID"
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let query = args.get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("缺少 query 参数"))?;
+        let Some(owner) = owner(ctx) else {
+            return Ok(ToolResult::error("无法确定当前用户"));
+        };
+
+        let jobs = self.scheduler.jobs_for_owner(&owner).await;
+        let timezone = self.timezone.resolve_or_default(ctx).await;
+        let matched = match_jobs(&jobs, query);
+        match matched.as_slice() {
+            [] => Ok(ToolResult::error(format!("没有找到与 \"{}\" 匹配的提醒", query))),
+            [job] => match self.scheduler.remove_job(&job.id).await {
+                Ok(()) => Ok(ToolResult::success(format!("已取消提醒: {}", describe_job(job, timezone)))),
+                Err(e) => Ok(ToolResult::error(format!("取消提醒失败: {}", e))),
+            },
+            candidates => {
+                let lines: Vec<String> = candidates.iter().map(|j| format!("- {}", describe_job(j, timezone))).collect();
+                Ok(ToolResult::error(format!("匹配到多个提醒，请用 ID 指定:\n{}", lines.join("\n"))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::reminder::REMINDER_HANDLER;
+
+    fn reminder(text: &str) -> Job {
+        Job::new_interval(format!("提醒: {}", text), 86400, REMINDER_HANDLER).with_args(json!({ "text": text }))
+    }
+
+    #[test]
+    fn test_match_jobs() {
+        let jobs = vec![reminder("去健身房锻炼"), reminder("给妈妈打电话"), reminder("交房租")];
+        assert_eq!(match_jobs(&jobs, "健身")[0].id, jobs[0].id);
+        assert_eq!(match_jobs(&jobs, &jobs[2].id[..8])[0].id, jobs[2].id);
+        // 没有包含关系时按相似度匹配
+        assert_eq!(match_jobs(&jobs, "给妈打电话").len(), 1);
+        assert!(match_jobs(&jobs, "开会").is_empty());
+        assert!(match_jobs(&jobs, " ").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reminders_are_per_user() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let timezone = UserTimezone::new("+08:00".parse().unwrap());
+        let alice = ToolContext::new(Default::default()).with_origin(Some("telegram"), Some("1"));
+        let bob = ToolContext::new(Default::default()).with_origin(Some("telegram"), Some("2"));
+
+        let create = ScheduleReminderTool::new(scheduler.clone(), timezone.clone());
+        let args = json!({"when": "every day at 7am", "text": "去健身房"});
+        assert!(create.execute(args, &alice).await.unwrap().success);
+
+        let list = ListRemindersTool::new(scheduler.clone(), timezone.clone());
+        assert!(list.execute(json!({}), &alice).await.unwrap().output.contains("去健身房"));
+        assert_eq!(list.execute(json!({}), &bob).await.unwrap().output, "当前没有提醒");
+
+        let cancel = CancelReminderTool::new(scheduler.clone(), timezone);
+        assert!(!cancel.execute(json!({"query": "健身"}), &bob).await.unwrap().success);
+        assert!(cancel.execute(json!({"query": "健身"}), &alice).await.unwrap().success);
+        assert!(scheduler.list_jobs().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_user_timezone_from_profile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let memory = Arc::new(MemoryStore::new(temp_dir.path()).await.unwrap());
+        let scheduler = Scheduler::new().await.unwrap();
+        let create = ScheduleReminderTool::new(
+            scheduler.clone(),
+            UserTimezone::new("UTC".parse().unwrap()).with_memory(memory.clone()),
+        );
+        let ctx = ToolContext::new(Default::default()).with_origin(Some("telegram"), Some("42"));
+        let args = json!({"when": "tomorrow at 9am", "text": "开会"});
+
+        // 引导时保存的时区优先于默认时区
+        memory.save_user_fact("telegram:42", "timezone", "Asia/Shanghai").await.unwrap();
+        let result = create.execute(args.clone(), &ctx).await.unwrap();
+        assert!(result.success && result.output.contains("09:00 +08:00"), "{}", result.output);
+
+        // 无法识别的时区不会静默回退到默认时区
+        memory.save_user_fact("telegram:42", "timezone", "北京").await.unwrap();
+        let result = create.execute(args, &ctx).await.unwrap();
+        assert!(result.error.unwrap().contains("Asia/Shanghai"));
+        assert_eq!(scheduler.list_jobs().await.len(), 1);
+    }
+}
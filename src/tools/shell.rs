@@ -3,8 +3,18 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
 
-use super::{Tool, ToolContext, ToolDef, ToolResult};
+use super::paths::{check_path, PathAccess};
+use super::{report_progress, Tool, ToolContext, ToolDef, ToolResult};
+
+/// 每次推送进度时附带的最近输出行数
+const PROGRESS_TAIL_LINES: usize = 10;
 
 /// Shell 命令执行工具
 pub struct ShellTool;
@@ -34,10 +44,130 @@ impl ShellTool {
 
         Ok(())
     }
+
+    /// 解析工作目录（相对路径基于当前工作目录），必须在允许写入的路径内
+    fn resolve_cwd(&self, cwd: Option<&str>, ctx: &ToolContext) -> Result<PathBuf> {
+        let Some(cwd) = cwd else {
+            return Ok(ctx.working_dir.clone());
+        };
+        let path = ctx.working_dir.join(cwd);
+        if !path.is_dir() {
+            anyhow::bail!("工作目录不存在: {}", path.display());
+        }
+        check_path(&path, PathAccess::Write, &ctx.config)?;
+        Ok(path)
+    }
+
+    /// 解析 env 参数，变量名必须在 shell_env_allowlist 中
+    fn parse_env(&self, env: Option<&Value>, config: &crate::config::ToolsConfig) -> Result<HashMap<String, String>> {
+        let Some(env) = env else {
+            return Ok(HashMap::new());
+        };
+        let env = env.as_object()
+            .ok_or_else(|| anyhow::anyhow!("env 参数必须是对象"))?;
+        let mut vars = HashMap::new();
+        for (name, value) in env {
+            if !config.shell_env_allowlist.iter().any(|a| a == name) {
+                anyhow::bail!(
+                    "环境变量 '{}' 不允许设置。允许的变量: {:?}",
+                    name, config.shell_env_allowlist
+                );
+            }
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            vars.insert(name.clone(), value);
+        }
+        Ok(vars)
+    }
+
+    /// 流式执行：逐步读取输出并定期报告进度，超时或输出超过上限时终止命令
+    async fn run_streaming(&self, mut cmd: Command, timeout: u64, config: &crate::config::ToolsConfig) -> ToolResult {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return ToolResult::error(format!("执行失败: {}", e)),
+        };
+        let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return ToolResult::error("无法读取命令输出");
+        };
+
+        // stdout 和 stderr 按到达顺序合并
+        let mut output = Vec::new();
+        let (mut out_buf, mut err_buf) = ([0u8; 4096], [0u8; 4096]);
+        let (mut out_open, mut err_open) = (true, true);
+        let mut reported = 0;
+        let interval = Duration::from_secs(config.shell_progress_interval_secs.max(1));
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        let deadline = tokio::time::sleep(Duration::from_secs(timeout));
+        tokio::pin!(deadline);
+
+        let failure = loop {
+            tokio::select! {
+                n = read_chunk(&mut stdout, &mut out_buf), if out_open => match n {
+                    Some(n) => output.extend_from_slice(&out_buf[..n]),
+                    None => out_open = false,
+                },
+                n = read_chunk(&mut stderr, &mut err_buf), if err_open => match n {
+                    Some(n) => output.extend_from_slice(&err_buf[..n]),
+                    None => err_open = false,
+                },
+                status = child.wait(), if !out_open && !err_open => break match status {
+                    Ok(status) if status.success() => None,
+                    Ok(status) => Some(format!("退出码: {}", status.code().unwrap_or(-1))),
+                    Err(e) => Some(format!("执行失败: {}", e)),
+                },
+                _ = ticker.tick() => {
+                    if output.len() > reported {
+                        reported = output.len();
+                        report_progress("shell", &tail_lines(&String::from_utf8_lossy(&output), PROGRESS_TAIL_LINES));
+                    }
+                }
+                _ = &mut deadline => {
+                    let _ = child.kill().await;
+                    break Some(format!("命令执行超时（{}秒），已终止", timeout));
+                }
+            }
+            if output.len() > config.shell_max_stream_bytes {
+                let _ = child.kill().await;
+                output.truncate(config.shell_max_stream_bytes);
+                break Some(format!("输出超过 {} 字节上限，已终止", config.shell_max_stream_bytes));
+            }
+        };
+
+        let output = String::from_utf8_lossy(&output);
+        match failure {
+            None if output.is_empty() => ToolResult::success("命令执行成功（无输出）"),
+            None => ToolResult::success(output.to_string()),
+            Some(reason) => ToolResult::error(format!("{}\n输出:\n{}", reason, output)),
+        }
+    }
+}
+
+/// 读取一块输出，结束或出错时返回 None
+async fn read_chunk<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Option<usize> {
+    match reader.read(buf).await {
+        Ok(0) | Err(_) => None,
+        Ok(n) => Some(n),
+    }
+}
+
+/// 最后 `n` 行
+pub fn tail_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
 }
 
 #[async_trait]
 impl Tool for ShellTool {
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
     fn definition(&self) -> &ToolDef {
         lazy_static::lazy_static! {
             static ref DEF: ToolDef = ToolDef {
@@ -54,6 +184,20 @@ impl Tool for ShellTool {
                             "type": "integer",
                             "description": "超时时间（秒），默认 30",
                             "default": 30
+                        },
+                        "stream": {
+                            "type": "boolean",
+                            "description": "流式执行长时间运行的命令（构建、跟踪日志等），定期向用户推送最新输出",
+                            "default": false
+                        },
+                        "cwd": {
+                            "type": "string",
+                            "description": "工作目录（需在允许的路径内），默认为当前目录"
+                        },
+                        "env": {
+                            "type": "object",
+                            "description": "额外设置的环境变量（仅限配置允许的变量名）",
+                            "additionalProperties": { "type": "string" }
                         }
                     },
                     "required": ["command"]
@@ -72,19 +216,40 @@ impl Tool for ShellTool {
             .and_then(|v| v.as_u64())
             .unwrap_or(30);
 
-        // 验证命令
+        // 验证命令、工作目录和环境变量
         if let Err(e) = self.validate_command(command, &ctx.config) {
             return Ok(ToolResult::error(e.to_string()));
         }
+        let cwd = match self.resolve_cwd(args.get("cwd").and_then(|v| v.as_str()), ctx) {
+            Ok(cwd) => cwd,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        let env = match self.parse_env(args.get("env"), &ctx.config) {
+            Ok(env) => env,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command).current_dir(&cwd);
+        if ctx.config.shell_clear_env {
+            cmd.env_clear();
+            for name in &ctx.config.shell_pass_env {
+                if let Some(value) = std::env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+        }
+        cmd.envs(&env);
+
+        if args.get("stream").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return Ok(self.run_streaming(cmd, timeout, &ctx.config).await);
+        }
 
-        // 执行命令
+        // 执行命令（超时时终止进程）
+        cmd.kill_on_drop(true);
         let output = tokio::time::timeout(
             std::time::Duration::from_secs(timeout),
-            tokio::process::Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .current_dir(&ctx.working_dir)
-                .output()
+            cmd.output()
         ).await;
 
         match output {
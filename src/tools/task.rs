@@ -0,0 +1,150 @@
+//! 后台任务工具 - 启动、查询、取消后台执行的工具调用
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use super::typed::TypedTool;
+use super::{ToolContext, ToolRegistry, ToolResult};
+use crate::tasks::{Task, TaskManager, TaskStatus};
+
+/// 不能放到后台执行的工具（任务工具自身）
+const TASK_TOOLS: [&str; 3] = ["start_task", "check_task", "cancel_task"];
+
+/// 查询任务，只能访问当前会话发起的任务（本地命令行由机器所有者使用，不受限制）
+async fn find_task(tasks: &TaskManager, id: &str, ctx: &ToolContext) -> Result<Option<Task>> {
+    let task = tasks.get(id).await?;
+    Ok(task.filter(|t| ctx.channel.is_none() || t.session_id == ctx.session_id))
+}
+
+/// 启动后台任务工具
+pub struct StartTaskTool {
+    tasks: Arc<TaskManager>,
+    registry: ToolRegistry,
+}
+
+impl StartTaskTool {
+    pub fn new(tasks: Arc<TaskManager>, registry: ToolRegistry) -> Self {
+        Self { tasks, registry }
+    }
+}
+
+/// start_task 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StartTaskArgs {
+    /// 要在后台执行的工具名，如 shell
+    #[schemars(length(min = 1))]
+    pub tool: String,
+    /// 传给该工具的参数
+    #[serde(default)]
+    pub args: Map<String, Value>,
+}
+
+#[async_trait]
+impl TypedTool for StartTaskTool {
+    type Args = StartTaskArgs;
+
+    const NAME: &'static str = "start_task";
+    const DESCRIPTION: &'static str =
+        "在后台执行耗时的工具调用（如长时间运行的 shell 命令），立即返回任务 ID，之后用 check_task 查询结果";
+
+    async fn run(&self, args: StartTaskArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        if TASK_TOOLS.contains(&args.tool.as_str()) {
+            return Ok(ToolResult::error(format!("{} 不能在后台执行", args.tool)));
+        }
+        match self.tasks.spawn(self.registry.clone(), &args.tool, Value::Object(args.args), ctx.clone()).await {
+            Ok(id) => Ok(ToolResult::success(format!(
+                "已在后台启动任务 {}（{}），稍后用 check_task 查询结果",
+                id, args.tool
+            ))),
+            Err(e) => Ok(ToolResult::error(e.to_string())),
+        }
+    }
+}
+
+/// 查询后台任务工具
+pub struct CheckTaskTool {
+    tasks: Arc<TaskManager>,
+}
+
+impl CheckTaskTool {
+    pub fn new(tasks: Arc<TaskManager>) -> Self {
+        Self { tasks }
+    }
+}
+
+/// check_task / cancel_task 参数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TaskIdArgs {
+    /// 任务 ID（来自 start_task）
+    #[schemars(length(min = 1))]
+    pub task_id: String,
+}
+
+#[async_trait]
+impl TypedTool for CheckTaskTool {
+    type Args = TaskIdArgs;
+
+    const NAME: &'static str = "check_task";
+    const DESCRIPTION: &'static str = "查询后台任务的状态，任务结束后返回其输出";
+
+    async fn run(&self, args: TaskIdArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let Some(task) = find_task(&self.tasks, &args.task_id, ctx).await? else {
+            return Ok(ToolResult::error(format!("任务不存在: {}", args.task_id)));
+        };
+        let report = match task.status {
+            TaskStatus::Running => format!(
+                "任务 {}（{}）运行中，已运行 {} 秒",
+                task.id,
+                task.tool,
+                (Utc::now() - task.created_at).num_seconds()
+            ),
+            status => format!(
+                "任务 {}（{}）{}\n{}",
+                task.id,
+                task.tool,
+                status.label(),
+                task.output.unwrap_or_default()
+            ),
+        };
+        Ok(ToolResult::success(report))
+    }
+}
+
+/// 取消后台任务工具
+pub struct CancelTaskTool {
+    tasks: Arc<TaskManager>,
+}
+
+impl CancelTaskTool {
+    pub fn new(tasks: Arc<TaskManager>) -> Self {
+        Self { tasks }
+    }
+}
+
+#[async_trait]
+impl TypedTool for CancelTaskTool {
+    type Args = TaskIdArgs;
+
+    const NAME: &'static str = "cancel_task";
+    const DESCRIPTION: &'static str = "取消运行中的后台任务";
+    const MUTATING: bool = true;
+
+    async fn run(&self, args: TaskIdArgs, ctx: &ToolContext) -> Result<ToolResult> {
+        let Some(task) = find_task(&self.tasks, &args.task_id, ctx).await? else {
+            return Ok(ToolResult::error(format!("任务不存在: {}", args.task_id)));
+        };
+        if self.tasks.cancel(&task.id) {
+            return Ok(ToolResult::success(format!("已取消任务 {}", task.id)));
+        }
+        let reason = match task.status {
+            TaskStatus::Running => "不在当前进程中运行".to_string(),
+            status => format!("已{}", status.label()),
+        };
+        Ok(ToolResult::error(format!("任务 {} {}，无法取消", task.id, reason)))
+    }
+}
@@ -0,0 +1,271 @@
+//! 回收站
+//!
+//! delete_file 不直接删除文件，而是移动到 `<workspace>/trash/<日期>/<条目>/` 下，同目录的 `meta.json` 记录原路径，
+//! 可通过 `nanobot trash restore` 恢复。gateway 定期删除超过 `tools.trash_retention_days` 天的日期目录
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// 条目元数据文件名
+const META_FILE: &str = "meta.json";
+
+/// 过期清理的检查间隔
+const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// 回收站中的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// 条目 ID（<日期>/<时间>-<序号>）
+    #[serde(skip)]
+    pub id: String,
+    /// 删除前的路径
+    pub original_path: PathBuf,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// 回收站
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+}
+
+impl Trash {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// 把文件或目录移到回收站（符号链接只移动链接本身）
+    pub fn put(&self, path: &Path) -> Result<TrashEntry> {
+        let absolute = std::path::absolute(path)?;
+        let name = absolute.file_name().ok_or_else(|| anyhow!("路径无效: {}", path.display()))?.to_os_string();
+        std::fs::symlink_metadata(&absolute).with_context(|| format!("文件不存在: {}", path.display()))?;
+        let parent = absolute.parent().ok_or_else(|| anyhow!("路径无效: {}", path.display()))?;
+        let original_path = parent.canonicalize()?.join(&name);
+        let trash_dir = self.dir.canonicalize().unwrap_or_else(|_| self.dir.clone());
+        if original_path.starts_with(&trash_dir) || trash_dir.starts_with(&original_path) {
+            bail!("不能删除回收站中的文件: {}", path.display());
+        }
+
+        let now = Local::now();
+        let day_dir = self.dir.join(now.format("%Y-%m-%d").to_string());
+        std::fs::create_dir_all(&day_dir).with_context(|| format!("创建回收站目录失败: {}", day_dir.display()))?;
+        let (entry_name, entry_dir) = (1..1000)
+            .map(|n| {
+                let entry_name = format!("{}-{}", now.format("%H%M%S"), n);
+                let entry_dir = day_dir.join(&entry_name);
+                (entry_name, entry_dir)
+            })
+            .find(|(_, dir)| std::fs::create_dir(dir).is_ok())
+            .ok_or_else(|| anyhow!("创建回收站条目失败"))?;
+
+        let entry = TrashEntry {
+            id: format!("{}/{}", now.format("%Y-%m-%d"), entry_name),
+            original_path,
+            deleted_at: now.with_timezone(&Utc),
+        };
+        std::fs::write(entry_dir.join(META_FILE), serde_json::to_string_pretty(&entry)?)?;
+        if let Err(e) = move_path(&entry.original_path, &entry_dir.join(name)) {
+            let _ = std::fs::remove_dir_all(&entry_dir);
+            return Err(e);
+        }
+        Ok(entry)
+    }
+
+    /// 列出回收站中的条目（最近删除的在前）
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = Vec::new();
+        for day in read_dirs(&self.dir)? {
+            for entry_dir in read_dirs(&day)? {
+                match read_meta(&entry_dir) {
+                    Ok(mut entry) => {
+                        entry.id = format!("{}/{}", file_name(&day), file_name(&entry_dir));
+                        entries.push(entry);
+                    }
+                    Err(e) => warn!("读取回收站条目 {} 失败: {}", entry_dir.display(), e),
+                }
+            }
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.deleted_at));
+        Ok(entries)
+    }
+
+    /// 恢复条目到原路径（或指定路径），目标已存在时报错
+    pub fn restore(&self, id: &str, to: Option<&Path>) -> Result<PathBuf> {
+        if id.split('/').any(|part| part.is_empty() || part == "..") || id.split('/').count() != 2 {
+            bail!("回收站条目 ID 无效: {}", id);
+        }
+        let entry_dir = self.dir.join(id);
+        let entry = read_meta(&entry_dir).map_err(|_| anyhow!("回收站条目不存在: {}", id))?;
+        let name = entry.original_path.file_name().ok_or_else(|| anyhow!("回收站条目已损坏: {}", id))?;
+        let target = to.map(Path::to_path_buf).unwrap_or_else(|| entry.original_path.clone());
+        if target.exists() {
+            bail!("目标已存在: {}", target.display());
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        move_path(&entry_dir.join(name), &target)?;
+        std::fs::remove_dir_all(&entry_dir)?;
+        remove_if_empty(entry_dir.parent());
+        Ok(target)
+    }
+
+    /// 删除早于保留天数的日期目录，返回删除的条目数
+    pub fn sweep(&self, retention_days: u64) -> Result<usize> {
+        let cutoff = Local::now().date_naive() - Duration::days(retention_days as i64);
+        let mut removed = 0;
+        for day in read_dirs(&self.dir)? {
+            let expired = NaiveDate::parse_from_str(&file_name(&day), "%Y-%m-%d").is_ok_and(|date| date < cutoff);
+            if expired {
+                removed += read_dirs(&day)?.len();
+                std::fs::remove_dir_all(&day)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 启动后台任务，定期清理过期条目（启动时立即执行一次）
+    pub fn start_sweep(self: Arc<Self>, retention_days: u64) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let trash = self.clone();
+                match tokio::task::spawn_blocking(move || trash.sweep(retention_days)).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(removed)) => info!("已清理回收站中超过 {} 天的 {} 个条目", retention_days, removed),
+                    Ok(Err(e)) => warn!("清理回收站失败: {}", e),
+                    Err(e) => warn!("清理回收站失败: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// 移动文件或目录（目标不能已存在），跨文件系统时复制后删除原文件
+pub fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if std::fs::symlink_metadata(to).is_ok() {
+        bail!("目标已存在: {}", to.display());
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = copy_path(from, to) {
+        let _ = remove_path(to);
+        return Err(e);
+    }
+    remove_path(from).with_context(|| format!("删除原文件失败: {}", from.display()))
+}
+
+/// 复制文件或目录（目录递归复制，符号链接按链接本身复制）
+pub fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(from).with_context(|| format!("文件不存在: {}", from.display()))?;
+    if metadata.is_symlink() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(std::fs::read_link(from)?, to)?;
+        #[cfg(not(unix))]
+        std::fs::copy(from, to)?;
+    } else if metadata.is_dir() {
+        std::fs::create_dir(to).with_context(|| format!("创建目录失败: {}", to.display()))?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to).with_context(|| format!("复制失败: {}", from.display()))?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_if_empty(dir: Option<&Path>) {
+    if let Some(dir) = dir {
+        // 目录非空时删除失败，忽略即可
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+fn read_meta(entry_dir: &Path) -> Result<TrashEntry> {
+    let content = std::fs::read_to_string(entry_dir.join(META_FILE))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// 目录下的子目录（目录不存在时为空）
+fn read_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_put_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let trash = Trash::new(temp_dir.path().join("trash"));
+        let file = temp_dir.path().join("notes.md");
+        let dir = temp_dir.path().join("project");
+        std::fs::write(&file, "笔记").unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let first = trash.put(&file).unwrap();
+        let second = trash.put(&dir).unwrap();
+        assert!(!file.exists() && !dir.exists());
+        assert_ne!(first.id, second.id);
+        assert_eq!(trash.list().unwrap().len(), 2);
+        assert!(trash.put(&temp_dir.path().join("trash")).is_err());
+
+        // 原路径被占用时不覆盖
+        std::fs::write(&file, "新内容").unwrap();
+        assert!(trash.restore(&first.id, None).is_err());
+        let restored = trash.restore(&first.id, Some(&temp_dir.path().join("notes.old.md"))).unwrap();
+        assert_eq!(std::fs::read_to_string(restored).unwrap(), "笔记");
+
+        trash.restore(&second.id, None).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("src/main.rs")).unwrap(), "fn main() {}");
+        assert!(trash.list().unwrap().is_empty());
+        assert!(trash.restore("../x", None).is_err());
+    }
+
+    #[test]
+    fn test_trash_sweep() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let trash = Trash::new(temp_dir.path().join("trash"));
+        let old = temp_dir.path().join("trash/2020-01-01");
+        std::fs::create_dir_all(old.join("080000-1")).unwrap();
+        let file = temp_dir.path().join("a.txt");
+        std::fs::write(&file, "a").unwrap();
+        trash.put(&file).unwrap();
+
+        assert_eq!(trash.sweep(30).unwrap(), 1);
+        assert!(!old.exists());
+        assert_eq!(trash.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+//! 类型化工具定义
+//!
+//! 参数用结构体描述，派生 `Deserialize` 和 `JsonSchema`，字段上的文档注释即参数说明：
+//!
+//! ```ignore
+//! /// 置顶工具参数
+//! #[derive(Deserialize, JsonSchema)]
+//! struct PinArgs {
+//!     /// 要置顶的内容
+//!     text: String,
+//! }
+//!
+//! #[async_trait]
+//! impl TypedTool for PinContextTool {
+//!     type Args = PinArgs;
+//!     const NAME: &'static str = "pin_context";
+//!     const DESCRIPTION: &'static str = "置顶重要信息";
+//!
+//!     async fn run(&self, args: PinArgs, ctx: &ToolContext) -> Result<ToolResult> { ... }
+//! }
+//!
+//! registry.register(Typed::new(PinContextTool::new(...)));
+//! ```
+//!
+//! 参数 Schema 由结构体生成，始终与反序列化代码一致
+
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{Tool, ToolContext, ToolDef, ToolResult};
+
+/// 类型化工具 trait
+#[async_trait]
+pub trait TypedTool: Send + Sync {
+    /// 参数类型
+    type Args: DeserializeOwned + JsonSchema + Send;
+
+    /// 工具名称
+    const NAME: &'static str;
+
+    /// 工具描述
+    const DESCRIPTION: &'static str;
+
+    /// 是否有副作用（见 [`Tool::is_mutating`]）
+    const MUTATING: bool = false;
+
+    /// 执行工具
+    async fn run(&self, args: Self::Args, ctx: &ToolContext) -> Result<ToolResult>;
+}
+
+/// 将类型化工具适配为 [`Tool`]
+pub struct Typed<T: TypedTool> {
+    tool: T,
+    def: ToolDef,
+}
+
+impl<T: TypedTool> Typed<T> {
+    pub fn new(tool: T) -> Self {
+        Self {
+            def: ToolDef {
+                name: T::NAME.to_string(),
+                description: T::DESCRIPTION.to_string(),
+                parameters: schema_for_args::<T::Args>(),
+            },
+            tool,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: TypedTool> Tool for Typed<T> {
+    fn definition(&self) -> &ToolDef {
+        &self.def
+    }
+
+    fn is_mutating(&self) -> bool {
+        T::MUTATING
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let args: T::Args = match serde_json::from_value(args) {
+            Ok(args) => args,
+            Err(e) => return Ok(ToolResult::error(format!("参数格式错误: {}", e))),
+        };
+        self.tool.run(args, ctx).await
+    }
+}
+
+/// 生成参数的 JSON Schema
+///
+/// 内联子结构、去掉 `$schema`/`title` 等元信息，可选字段不加 null 类型，
+/// 以兼容对 Schema 要求较严格的提供商（如 Gemini）
+pub fn schema_for_args<A: JsonSchema>() -> Value {
+    let settings = SchemaSettings::draft07().with(|s| {
+        s.inline_subschemas = true;
+        s.option_add_null_type = false;
+        s.meta_schema = None;
+    });
+    let schema = settings.into_generator().into_root_schema_for::<A>();
+
+    let mut value = serde_json::to_value(schema).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("title");
+        obj.remove("definitions");
+        // 无参数工具也要声明 properties
+        obj.entry("properties")
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+    value
+}
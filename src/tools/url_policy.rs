@@ -0,0 +1,263 @@
+//! 出站网址策略
+//!
+//! 访问外部网址的工具统一经过这里检查：只允许 http/https，按 `[tools.url_policy]` 的域名白名单/黑名单过滤，
+//! 拒绝解析到内网、回环等地址的网址（防止 SSRF），手动跟随重定向并逐跳检查，限制读取的响应大小。
+//! 请求只连接检查过的地址（客户端固定域名的解析结果），解析结果在检查后变化（DNS rebinding）不影响实际连接
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::{Client, Response, Url};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use crate::config::UrlPolicyConfig;
+
+/// 出站网址策略
+pub struct UrlPolicy<'a> {
+    config: &'a UrlPolicyConfig,
+}
+
+impl<'a> UrlPolicy<'a> {
+    pub fn new(config: &'a UrlPolicyConfig) -> Self {
+        Self { config }
+    }
+
+    /// 检查协议、域名和字面 IP 地址（不解析域名）
+    pub fn check_url(&self, url: &Url) -> Result<()> {
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("只支持 http/https 网址");
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("网址缺少主机名"))?
+            .trim_matches(['[', ']'])
+            .trim_end_matches('.')
+            .to_lowercase();
+
+        if self.config.deny_domains.iter().any(|d| domain_matches(&host, d)) {
+            bail!("域名 {} 在黑名单中", host);
+        }
+        if !self.config.allow_domains.is_empty() && !self.config.allow_domains.iter().any(|d| domain_matches(&host, d)) {
+            bail!("域名 {} 不在白名单中", host);
+        }
+        if self.config.block_private {
+            if let Ok(ip) = host.parse::<IpAddr>() {
+                if is_private_ip(ip) {
+                    bail!("不允许访问内网地址 {}", ip);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 网址是否符合策略（不解析域名），用于过滤搜索结果等
+    pub fn allows(&self, url: &str) -> bool {
+        Url::parse(url).is_ok_and(|u| self.check_url(&u).is_ok())
+    }
+
+    /// 完整检查：包括解析域名后的地址
+    pub async fn check(&self, url: &Url) -> Result<()> {
+        self.resolve(url).await.map(|_| ())
+    }
+
+    /// 检查网址并返回域名解析出的地址（字面 IP 或未启用内网检查时为空）
+    async fn resolve(&self, url: &Url) -> Result<Vec<SocketAddr>> {
+        self.check_url(url)?;
+        if !self.config.block_private {
+            return Ok(Vec::new());
+        }
+        let host = url.host_str().unwrap_or_default().trim_matches(['[', ']']);
+        if host.parse::<IpAddr>().is_ok() {
+            return Ok(Vec::new());
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| anyhow!("解析域名 {} 失败: {}", host, e))?
+            .collect();
+        for addr in &addrs {
+            if is_private_ip(addr.ip()) {
+                bail!("域名 {} 解析到内网地址 {}，已拒绝", host, addr.ip());
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// 检查网址并创建只连接检查过的地址的客户端（不自动跟随重定向）
+    pub async fn client(&self, url: &Url, timeout: Duration) -> Result<Client> {
+        let addrs = self.resolve(url).await?;
+        pinned_client(url, &addrs, timeout)
+    }
+
+    /// 发送 GET 请求，逐跳检查并跟随重定向
+    pub async fn get(&self, url: &str, timeout: Duration) -> Result<Response> {
+        let mut url = Url::parse(url).map_err(|e| anyhow!("URL 无效: {}", e))?;
+        for _ in 0..=self.config.max_redirects {
+            let client = self.client(&url, timeout).await?;
+            let response = client.get(url.clone()).send().await?;
+            if !response.status().is_redirection() {
+                return Ok(response);
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| anyhow!("重定向响应缺少 Location"))?;
+            url = url.join(location).map_err(|e| anyhow!("重定向地址无效: {}", e))?;
+        }
+        bail!("重定向次数超过 {} 次", self.config.max_redirects)
+    }
+
+    /// 读取响应体，超过 `max_content_bytes` 的部分丢弃（0 表示不限制）
+    pub async fn read_body(&self, mut response: Response) -> Result<Vec<u8>> {
+        let limit = self.config.max_content_bytes;
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if limit > 0 && body.len() >= limit {
+                body.truncate(limit);
+                break;
+            }
+        }
+        Ok(body)
+    }
+}
+
+/// 创建不自动跟随重定向的客户端，`addrs` 非空时网址的域名固定解析到这些地址（端口沿用网址的）
+///
+/// 不使用系统代理：经代理时由代理解析域名，固定的解析结果不起作用
+fn pinned_client(url: &Url, addrs: &[SocketAddr], timeout: Duration) -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("nanobot/", env!("CARGO_PKG_VERSION")))
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy();
+    if let (false, Some(host)) = (addrs.is_empty(), url.host_str()) {
+        builder = builder.resolve_to_addrs(host, addrs);
+    }
+    Ok(builder.build()?)
+}
+
+/// 主机名是否为该域名或其子域名（允许写成 `*.example.com`）
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("*.").trim_end_matches('.').to_lowercase();
+    !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+}
+
+/// 是否为内网、回环、链路本地等不应从外部工具访问的地址
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped().or_else(|| nat64_ipv4(ip)) {
+            Some(v4) => is_private_ipv4(v4),
+            None => is_private_ipv6(ip),
+        },
+    }
+}
+
+/// NAT64 地址 64:ff9b::/96 中嵌入的 IPv4 地址（经 NAT64 网关访问的实际地址）
+fn nat64_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [.., a, b, c, d] = ip.octets();
+    (ip.segments()[..6] == [0x64, 0xff9b, 0, 0, 0, 0]).then(|| Ipv4Addr::new(a, b, c, d))
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || a == 0
+        // 运营商级 NAT 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // 基准测试 198.18.0.0/15
+        || (a == 198 && (18..20).contains(&b))
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // 唯一本地地址 fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // 链路本地地址 fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(config: &UrlPolicyConfig, url: &str) -> Result<()> {
+        UrlPolicy::new(config).check_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_url_policy() {
+        let config = UrlPolicyConfig::default();
+        assert!(check(&config, "https://example.com/a").is_ok());
+        assert!(check(&config, "ftp://example.com/a").is_err());
+        assert!(check(&config, "http://127.0.0.1:8080/").is_err());
+        assert!(check(&config, "http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check(&config, "http://[::1]/").is_err());
+        assert!(check(&config, "http://[::ffff:10.0.0.1]/").is_err());
+        assert!(check(&config, "http://[64:ff9b::10.0.0.1]/").is_err());
+        assert!(check(&config, "http://[64:ff9b::7f00:1]/").is_err());
+        assert!(check(&config, "http://[64:ff9b::8.8.8.8]/").is_ok());
+        assert!(check(&config, "http://100.100.1.1/").is_err());
+        assert!(check(&config, "http://8.8.8.8/").is_ok());
+
+        let config = UrlPolicyConfig {
+            allow_domains: vec!["*.rust-lang.org".to_string(), "github.com".to_string()],
+            deny_domains: vec!["gist.github.com".to_string()],
+            block_private: false,
+            ..Default::default()
+        };
+        let policy = UrlPolicy::new(&config);
+        assert!(policy.allows("https://doc.rust-lang.org/std/"));
+        assert!(policy.allows("https://GitHub.com/rust-lang"));
+        assert!(!policy.allows("https://gist.github.com/x"));
+        assert!(!policy.allows("https://notgithub.com/"));
+        assert!(!policy.allows("not a url"));
+        assert!(check(&config, "http://localhost/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_resolves_host() {
+        let config = UrlPolicyConfig::default();
+        let policy = UrlPolicy::new(&config);
+        assert!(policy.check(&Url::parse("http://localhost:1/").unwrap()).await.is_err());
+
+        let config = UrlPolicyConfig {
+            block_private: false,
+            ..Default::default()
+        };
+        assert!(UrlPolicy::new(&config).check(&Url::parse("http://localhost:1/").unwrap()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_pins_checked_addresses() {
+        let config = UrlPolicyConfig::default();
+        let policy = UrlPolicy::new(&config);
+        assert!(policy.resolve(&Url::parse("http://8.8.8.8/").unwrap()).await.unwrap().is_empty());
+        assert!(policy.client(&Url::parse("http://localhost:1/").unwrap(), Duration::from_secs(1)).await.is_err());
+
+        // 客户端连接检查时解析出的地址，而不是请求时重新解析
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let _ = socket.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await;
+            }
+        });
+        let url = Url::parse(&format!("http://pinned.invalid:{}/", port)).unwrap();
+        let timeout = Duration::from_secs(5);
+        let pinned = pinned_client(&url, &[SocketAddr::from(([127, 0, 0, 1], 0))], timeout).unwrap();
+        assert_eq!(pinned.get(url.clone()).send().await.unwrap().status(), 204);
+        // 未固定时按系统解析（该域名无法解析）
+        let unpinned = pinned_client(&url, &[], timeout).unwrap();
+        assert!(unpinned.get(url).send().await.is_err());
+    }
+}
@@ -1,11 +1,20 @@
-//! Web 搜索工具 - 使用 Brave Search API
+//! Web 工具 - 使用 Brave Search API 搜索、读取网页正文
 
 use anyhow::Result;
 use async_trait::async_trait;
+use regex::Regex;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::time::Duration;
 
-use super::{Tool, ToolContext, ToolDef, ToolResult};
+use super::url_policy::UrlPolicy;
+use super::{Page, Tool, ToolContext, ToolDef, ToolResult};
+
+/// fetch_page 默认每页行数
+const DEFAULT_PAGE_LINES: usize = 200;
+
+/// fetch_page 请求超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
 
 /// Web 搜索工具
 pub struct WebSearchTool {
@@ -87,18 +96,21 @@ impl Tool for WebSearchTool {
         &DEF
     }
 
-    async fn execute(&self, args: Value, _ctx: &ToolContext) -> Result<ToolResult> {
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
         let query = args.get("query")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("缺少 query 参数"))?;
 
         let count = args.get("count")
             .and_then(|v| v.as_u64())
-            .map(|c| c.min(10).max(1) as u32)
+            .map(|c| c.clamp(1, 10) as u32)
             .unwrap_or(5);
 
+        // 不列出网址策略不允许访问的结果，避免模型再去读取
+        let policy = UrlPolicy::new(&ctx.config.url_policy);
         match self.search(query, count, Some("US")).await {
             Ok(results) => {
+                let results: Vec<SearchResult> = results.into_iter().filter(|r| policy.allows(&r.url)).collect();
                 if results.is_empty() {
                     Ok(ToolResult::success("未找到相关结果".to_string()))
                 } else {
@@ -118,6 +130,125 @@ impl Tool for WebSearchTool {
     }
 }
 
+/// 网页读取工具，返回网页的纯文本内容
+pub struct FetchPageTool;
+
+impl FetchPageTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 按网址策略读取网页，HTML 转为纯文本
+    async fn fetch(&self, url: &str, policy: &UrlPolicy<'_>) -> Result<String> {
+        let response = policy.get(url, FETCH_TIMEOUT).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("请求失败: {}", response.status());
+        }
+        let is_html = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_none_or(|t| t.contains("html"));
+
+        let body = policy.read_body(response).await?;
+        let text = String::from_utf8_lossy(&body);
+        Ok(if is_html { html_to_text(&text) } else { text.to_string() })
+    }
+}
+
+impl Default for FetchPageTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for FetchPageTool {
+    fn definition(&self) -> &ToolDef {
+        lazy_static::lazy_static! {
+            static ref DEF: ToolDef = ToolDef {
+                name: "fetch_page".to_string(),
+                description: "读取网页的正文内容（纯文本），用于查看搜索结果的详细内容".to_string(),
+                parameters: Page::with_schema(json!({
+                    "type": "object",
+                    "properties": {
+                        "url": {
+                            "type": "string",
+                            "description": "网页地址（http/https）"
+                        }
+                    },
+                    "required": ["url"]
+                }), "行", DEFAULT_PAGE_LINES),
+            };
+        }
+        &DEF
+    }
+
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let url = args.get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("缺少 url 参数"))?;
+
+        match self.fetch(url, &UrlPolicy::new(&ctx.config.url_policy)).await {
+            Ok(text) if text.trim().is_empty() => Ok(ToolResult::success("网页没有可读的文本内容")),
+            Ok(text) => {
+                let lines: Vec<String> = text.lines().map(str::to_string).collect();
+                Ok(ToolResult::success(Page::from_args(&args, DEFAULT_PAGE_LINES).apply(&lines, "行")))
+            }
+            Err(e) => Ok(ToolResult::error(format!("读取网页失败: {}", e))),
+        }
+    }
+}
+
+/// 把 HTML 转为纯文本：去掉脚本、样式和标签，块级元素换行，合并空白
+pub fn html_to_text(html: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref TITLE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+        static ref HIDDEN: Regex =
+            Regex::new(r"(?is)<!--.*?-->|<(script|style|noscript|svg|head)\b.*?</(script|style|noscript|svg|head)>").unwrap();
+        static ref BLOCK: Regex =
+            Regex::new(r"(?i)<br\s*/?>|</?(p|div|li|tr|h[1-6]|section|article|header|footer|blockquote|pre|table|ul|ol)\b[^>]*>").unwrap();
+        static ref TAG: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+    }
+
+    let title = TITLE
+        .captures(html)
+        .map(|c| decode_entities(c[1].trim()))
+        .filter(|t| !t.is_empty());
+    let text = HIDDEN.replace_all(html, "");
+    let text = BLOCK.replace_all(&text, "\n");
+    let text = decode_entities(&TAG.replace_all(&text, ""));
+
+    let mut lines: Vec<String> = title.map(|t| format!("# {}", t)).into_iter().collect();
+    lines.extend(
+        text.lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|l| !l.is_empty()),
+    );
+    lines.join("\n")
+}
+
+/// 解码常见的 HTML 实体
+fn decode_entities(text: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref NUMERIC: Regex = Regex::new(r"&#(x?)([0-9a-fA-F]+);").unwrap();
+    }
+    let text = NUMERIC.replace_all(text, |c: &regex::Captures| {
+        let radix = if c[1].is_empty() { 10 } else { 16 };
+        u32::from_str_radix(&c[2], radix)
+            .ok()
+            .and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default()
+    });
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
 #[derive(Debug)]
 struct SearchResult {
     title: String,
@@ -142,3 +273,16 @@ struct WebResult {
     url: String,
     description: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>Rust &amp; 异步</title><style>p { color: red }</style></head>
+<body><script>alert("x")</script><h1>标题</h1><p>第一段   <b>加粗</b><br>换行</p>
+<!-- 注释 --><ul><li>a &lt; b</li><li>&#20320;&#x597D;</li></ul></body></html>"#;
+        assert_eq!(html_to_text(html), "# Rust & 异步\n标题\n第一段 加粗\n换行\na < b\n你好");
+    }
+}
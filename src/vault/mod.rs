@@ -0,0 +1,412 @@
+//! 静态数据加密（vault）
+//!
+//! 启用 `[vault]` 后使用 XChaCha20-Poly1305 加密，读取时自动解密（未加密的旧数据仍可读取）：
+//! - MemoryStore 写入的笔记、长期记忆和对话历史按文件加密
+//! - 发件箱消息内容、会话置顶和指令等敏感字段在写入 SQLite 前加密
+//!
+//! 其余数据库内容（统计、提醒等）按整个文件加密：gateway 启动时解密，正常退出时重新加密，
+//! 也可在 gateway 停止后用 `nanobot vault lock` 手动加密。gateway 运行期间或异常退出后，
+//! 数据库文件本身是明文（上面按字段加密的内容仍为密文）。
+//! 文件先写入同目录的临时文件并同步到磁盘，再替换原文件，写入中途失败不会损坏原文件。
+//! 密钥由口令经 Argon2id 派生，盐和校验值保存在 `<workspace>/vault.json`
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tracing::info;
+
+use crate::config::Config;
+
+/// 口令环境变量（未配置 key_file 时使用）
+pub const PASSPHRASE_ENV: &str = "NANOBOT_VAULT_PASSPHRASE";
+
+/// 加密文件头
+const MAGIC: &[u8] = b"NBVAULT1";
+/// 加密字段前缀（其后为 hex 编码的密文）
+const TEXT_PREFIX: &str = "nbvault:";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+/// 用于校验口令的明文
+const CHECK_PLAINTEXT: &[u8] = b"nanobot-vault";
+
+/// 保存在 vault.json 中的密钥派生参数
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultHeader {
+    version: u32,
+    /// Argon2id 盐（hex）
+    salt: String,
+    /// 加密后的校验值（hex），口令错误时无法解密
+    check: String,
+}
+
+/// 加解密器
+pub struct Vault {
+    cipher: XChaCha20Poly1305,
+}
+
+impl Vault {
+    /// 使用 32 字节密钥创建
+    pub fn from_key(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(key.into()),
+        }
+    }
+
+    /// 按口令打开 vault，头文件不存在时创建
+    pub fn open(header_path: &Path, passphrase: &[u8]) -> Result<Self> {
+        if header_path.exists() {
+            let content = std::fs::read_to_string(header_path)
+                .with_context(|| format!("读取 {} 失败", header_path.display()))?;
+            let header: VaultHeader = serde_json::from_str(&content).context("解析 vault 头失败")?;
+            let vault = Self::from_key(&derive_key(passphrase, &hex::decode(&header.salt)?)?);
+            let check = vault
+                .decrypt(&hex::decode(&header.check)?)
+                .map_err(|_| anyhow!("vault 口令错误"))?;
+            if check != CHECK_PLAINTEXT {
+                bail!("vault 口令错误");
+            }
+            return Ok(vault);
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        let vault = Self::from_key(&derive_key(passphrase, &salt)?);
+        let header = VaultHeader {
+            version: 1,
+            salt: hex::encode(salt),
+            check: hex::encode(vault.encrypt(CHECK_PLAINTEXT)?),
+        };
+        if let Some(parent) = header_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(header_path, serde_json::to_string_pretty(&header)?)
+            .with_context(|| format!("写入 {} 失败", header_path.display()))?;
+        info!("已创建 vault: {}", header_path.display());
+        Ok(vault)
+    }
+
+    /// 按配置读取口令（key_file 或环境变量）并打开 vault，不检查是否启用
+    pub fn load(config: &Config) -> Result<Arc<Self>> {
+        let passphrase = match config.vault.key_file {
+            Some(ref path) => std::fs::read_to_string(path)
+                .with_context(|| format!("读取密钥文件失败: {}", path.display()))?
+                .trim()
+                .to_string(),
+            None => std::env::var(PASSPHRASE_ENV)
+                .map_err(|_| anyhow!("未找到 vault 口令，请配置 vault.key_file 或设置 {}", PASSPHRASE_ENV))?,
+        };
+        if passphrase.is_empty() {
+            bail!("vault 口令为空");
+        }
+        Ok(Arc::new(Self::open(&config.vault_header_path(), passphrase.as_bytes())?))
+    }
+
+    /// 启用 `[vault]` 时打开 vault，否则返回 None
+    pub fn from_config(config: &Config) -> Result<Option<Arc<Self>>> {
+        if !config.vault.enabled {
+            return Ok(None);
+        }
+        Self::load(config).map(Some)
+    }
+
+    /// 数据是否为加密格式
+    pub fn is_encrypted(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// 加密：文件头 + 随机 nonce + 密文
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .map_err(|_| anyhow!("加密失败"))?;
+
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// 解密，未加密的数据原样返回
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !Self::is_encrypted(data) {
+            return Ok(data.to_vec());
+        }
+        let body = &data[MAGIC.len()..];
+        if body.len() < NONCE_LEN {
+            bail!("加密数据已损坏");
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("解密失败：口令错误或数据已损坏"))
+    }
+
+    /// 加密文本字段
+    pub fn encrypt_text(&self, text: &str) -> Result<String> {
+        Ok(format!("{}{}", TEXT_PREFIX, hex::encode(self.encrypt(text.as_bytes())?)))
+    }
+
+    /// 解密文本字段，未加密的文本原样返回
+    pub fn decrypt_text(&self, text: &str) -> Result<String> {
+        let Some(encoded) = text.strip_prefix(TEXT_PREFIX) else {
+            return Ok(text.to_string());
+        };
+        let data = hex::decode(encoded).context("加密字段已损坏")?;
+        String::from_utf8(self.decrypt(&data)?).context("解密结果不是有效的 UTF-8")
+    }
+
+    /// 读取并解密文件
+    pub async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let data = fs::read(path)
+            .await
+            .with_context(|| format!("读取文件失败: {}", path.display()))?;
+        self.decrypt(&data)
+            .with_context(|| format!("解密文件失败: {}", path.display()))
+    }
+
+    /// 加密并写入文件
+    pub async fn write_file(&self, path: &Path, plaintext: &[u8]) -> Result<()> {
+        write_atomic(path, &self.encrypt(plaintext)?).await
+    }
+
+    /// 原地加密文件，已加密时跳过，返回是否加密
+    pub async fn lock_file(&self, path: &Path) -> Result<bool> {
+        let data = fs::read(path).await?;
+        if Self::is_encrypted(&data) {
+            return Ok(false);
+        }
+        self.write_file(path, &data).await?;
+        Ok(true)
+    }
+
+    /// 原地解密文件，未加密时跳过，返回是否解密
+    pub async fn unlock_file(&self, path: &Path) -> Result<bool> {
+        let data = fs::read(path).await?;
+        if !Self::is_encrypted(&data) {
+            return Ok(false);
+        }
+        let plaintext = self
+            .decrypt(&data)
+            .with_context(|| format!("解密文件失败: {}", path.display()))?;
+        write_atomic(path, &plaintext).await?;
+        Ok(true)
+    }
+
+    /// 加密 SQLite 数据库，返回加密的文件数
+    pub async fn lock_databases(&self, config: &Config) -> Result<usize> {
+        let mut count = 0;
+        for path in database_files(config) {
+            // 有 WAL 文件说明数据库仍在使用（或未正常关闭）
+            if wal_path(&path).exists() {
+                bail!("数据库正在使用: {}，请先停止 gateway", path.display());
+            }
+            if self.lock_file(&path).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// 解密 SQLite 数据库，返回解密的文件数
+    pub async fn unlock_databases(&self, config: &Config) -> Result<usize> {
+        let mut count = 0;
+        for path in database_files(config) {
+            if self.unlock_file(&path).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// 加密 memory 目录中的 Markdown 文件和 SQLite 数据库
+    pub async fn lock(&self, config: &Config) -> Result<usize> {
+        let mut count = self.lock_databases(config).await?;
+        for path in memory_files(config).await? {
+            if self.lock_file(&path).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// 解密所有加密文件
+    pub async fn unlock(&self, config: &Config) -> Result<usize> {
+        let mut count = self.unlock_databases(config).await?;
+        for path in memory_files(config).await? {
+            if self.unlock_file(&path).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// 从口令派生 32 字节密钥（Argon2id）
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| anyhow!("派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+/// 工作目录中存在的 SQLite 数据库
+fn database_files(config: &Config) -> Vec<PathBuf> {
+    config
+        .database_paths()
+        .into_iter()
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// 先写入同目录的临时文件并同步到磁盘，再替换原文件
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("无效的文件路径: {}", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.vault-tmp", file_name));
+    let result = async {
+        let mut file = fs::File::create(&temp).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&temp, path).await?;
+        // 同步目录，确保替换本身也已落盘
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::File::open(dir).await?.sync_all().await?;
+        }
+        Ok::<_, std::io::Error>(())
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&temp).await;
+    }
+    result.with_context(|| format!("写入文件失败: {}", path.display()))
+}
+
+fn wal_path(db: &Path) -> PathBuf {
+    let mut name = db.as_os_str().to_owned();
+    name.push("-wal");
+    PathBuf::from(name)
+}
+
+/// memory 目录（含对话历史和按用户分区的记忆）中的 Markdown、JSON 和 JSONL 文件
+async fn memory_files(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![config.memory.workspace_path.join("memory")];
+    while let Some(dir) = dirs.pop() {
+        if !dir.exists() {
+            continue;
+        }
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|e| e == "md" || e == "json" || e == "jsonl") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let header = temp_dir.path().join("vault.json");
+
+        let vault = Vault::open(&header, b"correct horse").unwrap();
+        let data = vault.encrypt("你好".as_bytes()).unwrap();
+        assert!(Vault::is_encrypted(&data));
+        assert_eq!(vault.decrypt(&data).unwrap(), "你好".as_bytes());
+        // 未加密的数据原样返回
+        assert_eq!(vault.decrypt(b"plain").unwrap(), b"plain");
+
+        let text = vault.encrypt_text("置顶内容").unwrap();
+        assert!(!text.contains("置顶内容"));
+        assert_eq!(vault.decrypt_text(&text).unwrap(), "置顶内容");
+        assert_eq!(vault.decrypt_text("plain").unwrap(), "plain");
+
+        // 重新打开得到同一密钥，错误口令被拒绝
+        let reopened = Vault::open(&header, b"correct horse").unwrap();
+        assert_eq!(reopened.decrypt(&data).unwrap(), "你好".as_bytes());
+        assert!(Vault::open(&header, b"wrong").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_unlock_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let vault = Vault::open(&temp_dir.path().join("vault.json"), b"correct horse").unwrap();
+        let path = temp_dir.path().join("stats.db");
+        fs::write(&path, b"SQLite format 3").await.unwrap();
+
+        assert!(vault.lock_file(&path).await.unwrap());
+        assert!(!vault.lock_file(&path).await.unwrap());
+        assert!(Vault::is_encrypted(&fs::read(&path).await.unwrap()));
+        assert!(vault.unlock_file(&path).await.unwrap());
+        assert_eq!(fs::read(&path).await.unwrap(), b"SQLite format 3");
+
+        // 替换完成后不留下临时文件
+        let mut entries = fs::read_dir(temp_dir.path()).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            assert!(!entry.file_name().to_string_lossy().ends_with(".vault-tmp"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lock_covers_user_namespaces() {
+        use crate::memory::MemoryStore;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.memory.workspace_path = temp_dir.path().to_path_buf();
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap().with_per_user(true);
+        memory.save_user_fact("telegram:42", "name", "小明").await.unwrap();
+        memory
+            .submit_memory(Some("telegram:42"), "饮品", "喜欢乌龙茶", None, 5, "remember")
+            .await
+            .unwrap();
+        memory.submit_memory(None, "约定", "周五发布", None, 5, "remember").await.unwrap();
+
+        let memory_dir = temp_dir.path().join("memory");
+        let user_dir = memory_dir.join("users").join("telegram:42");
+        let files = memory_files(&config).await.unwrap();
+        for path in [
+            memory_dir.join("MEMORY.md"),
+            memory_dir.join("memory_index.json"),
+            memory_dir.join("users").join("telegram:42.md"),
+            user_dir.join("MEMORY.md"),
+            user_dir.join("memory_index.json"),
+        ] {
+            assert!(files.contains(&path), "{} 未覆盖", path.display());
+        }
+
+        let vault = Vault::open(&temp_dir.path().join("vault.json"), b"correct horse").unwrap();
+        vault.lock(&config).await.unwrap();
+        for path in &files {
+            assert!(Vault::is_encrypted(&fs::read(path).await.unwrap()), "{} 未加密", path.display());
+        }
+        vault.unlock(&config).await.unwrap();
+        let memory = MemoryStore::new(temp_dir.path()).await.unwrap();
+        assert_eq!(memory.user_fact("telegram:42", "name").await.unwrap().as_deref(), Some("小明"));
+    }
+}
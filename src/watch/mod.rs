@@ -0,0 +1,348 @@
+//! 文件监视
+//!
+//! 按 `[watch]` 的间隔扫描配置的目录，比较文件的修改时间和大小，发现新增、修改或删除时发布
+//! [`FileChangedEvent`]（主题 `file.created` / `file.modified` / `file.removed`）。
+//! 文件在两次扫描之间保持不变才报告，避免把正在写入的文件交给 Agent。
+//! [`FileWatchHandler`] 订阅事件，按目录配置发送通知，或把提示交给 Agent 处理后把回复发送到通道
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::bus::{EventBus, EventHandler, FileChangedEvent};
+use crate::channel::outbox::Outbox;
+use crate::channel::quiet::{send_proactive, QuietHours};
+use crate::channel::template::{NotificationKind, NotificationTemplates};
+use crate::channel::{Channel, ChannelTarget, InboundMessage, MessageHandler};
+use crate::config::{FileChangeKind, WatchConfig, WatchDirConfig};
+use crate::t;
+use crate::tools::paths::glob_matches;
+
+/// 交给 Agent 处理时使用的发送者 ID
+const WATCH_SENDER: &str = "watch";
+
+/// 文件状态（修改时间和大小）
+type Stamp = (Option<SystemTime>, u64);
+
+#[derive(Debug, Clone, Copy)]
+struct FileState {
+    /// 上次扫描到的状态
+    seen: Stamp,
+    /// 上次报告时的状态，None 表示新文件尚未报告
+    reported: Option<Stamp>,
+}
+
+/// 单个目录的扫描状态
+struct DirWatch {
+    config: WatchDirConfig,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl DirWatch {
+    /// 以当前目录内容为基准（已有文件不报告）
+    fn new(config: WatchDirConfig) -> Self {
+        let files = scan(&config)
+            .into_iter()
+            .map(|(path, stamp)| (path, FileState { seen: stamp, reported: Some(stamp) }))
+            .collect();
+        Self { config, files }
+    }
+
+    /// 与上次扫描比较，返回已稳定的变化
+    fn poll(&mut self, current: HashMap<PathBuf, Stamp>) -> Vec<(PathBuf, FileChangeKind)> {
+        let mut changes = Vec::new();
+        self.files.retain(|path, state| {
+            let keep = current.contains_key(path);
+            if !keep && state.reported.is_some() {
+                changes.push((path.clone(), FileChangeKind::Removed));
+            }
+            keep
+        });
+        for (path, stamp) in current {
+            let Some(state) = self.files.get_mut(&path) else {
+                // 新文件，等下次扫描确认写入完成
+                self.files.insert(path, FileState { seen: stamp, reported: None });
+                continue;
+            };
+            if state.seen != stamp {
+                // 仍在变化，等下次扫描
+                state.seen = stamp;
+            } else if state.reported != Some(stamp) {
+                let kind = if state.reported.is_some() { FileChangeKind::Modified } else { FileChangeKind::Created };
+                state.reported = Some(stamp);
+                changes.push((path, kind));
+            }
+        }
+        changes.retain(|(_, kind)| self.config.events.contains(kind));
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        changes
+    }
+}
+
+/// 扫描目录中匹配的文件
+fn scan(config: &WatchDirConfig) -> HashMap<PathBuf, Stamp> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![config.path.clone()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = entry.path();
+            if metadata.is_dir() {
+                if config.recursive {
+                    dirs.push(path);
+                }
+            } else if config.patterns.is_empty() || config.patterns.iter().any(|p| glob_matches(p, &path)) {
+                files.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    files
+}
+
+/// 目录监视器
+pub struct Watcher {
+    interval: Duration,
+    dirs: Vec<DirWatch>,
+    bus: Arc<EventBus>,
+}
+
+impl Watcher {
+    pub fn new(config: &WatchConfig, bus: Arc<EventBus>) -> Self {
+        let dirs = config
+            .dirs
+            .iter()
+            .filter(|dir| {
+                let exists = dir.path.is_dir();
+                if !exists {
+                    warn!("监视目录不存在: {}（{}）", dir.path.display(), dir.name);
+                }
+                exists
+            })
+            .cloned()
+            .map(DirWatch::new)
+            .collect();
+        Self {
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            dirs,
+            bus,
+        }
+    }
+
+    /// 启动后台扫描任务
+    pub fn start(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("监视 {} 个目录（每 {} 秒扫描）", self.dirs.len(), self.interval.as_secs());
+            let mut interval = tokio::time::interval(self.interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                for dir in &mut self.dirs {
+                    let config = dir.config.clone();
+                    let current = match tokio::task::spawn_blocking(move || scan(&config)).await {
+                        Ok(current) => current,
+                        Err(e) => {
+                            warn!("扫描监视目录失败: {}", e);
+                            continue;
+                        }
+                    };
+                    for (path, kind) in dir.poll(current) {
+                        info!("监视目录 {}: {} {}", dir.config.name, kind.as_str(), path.display());
+                        let _ = self.bus.publish(FileChangedEvent {
+                            watch: dir.config.name.clone(),
+                            path,
+                            kind,
+                            timestamp: Utc::now(),
+                        });
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// 文件变化处理器
+///
+/// 目录配置了 prompt 时把提示交给 Agent（在目标聊天的会话中）处理并发送回复，否则只发送通知。
+/// 未配置 channel / chat_id 的目录不处理，事件仍可通过总线订阅。通道处于免打扰时段时排队发送
+pub struct FileWatchHandler {
+    dirs: Vec<WatchDirConfig>,
+    handler: Arc<dyn MessageHandler>,
+    channels: Vec<Arc<dyn Channel>>,
+    quiet_hours: QuietHours,
+    outbox: Option<Arc<Outbox>>,
+    templates: NotificationTemplates,
+}
+
+impl FileWatchHandler {
+    pub fn new(config: &WatchConfig, handler: Arc<dyn MessageHandler>, channels: Vec<Arc<dyn Channel>>) -> Self {
+        Self {
+            dirs: config.dirs.clone(),
+            handler,
+            channels,
+            quiet_hours: QuietHours::default(),
+            outbox: None,
+            templates: NotificationTemplates::default(),
+        }
+    }
+
+    /// 设置通知模板
+    pub fn with_templates(mut self, templates: NotificationTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// 设置免打扰时段
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// 设置发件箱（免打扰时段内的消息排队到其中）
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    async fn process(&self, event: &FileChangedEvent) -> Result<()> {
+        let Some(dir) = self.dirs.iter().find(|d| d.name == event.watch) else {
+            return Ok(());
+        };
+        let (Some(channel), Some(chat_id)) = (&dir.channel, &dir.chat_id) else {
+            return Ok(());
+        };
+        let target = self
+            .channels
+            .iter()
+            .find(|c| c.name() == channel)
+            .ok_or_else(|| anyhow!("监视目录 {} 的目标通道不存在: {}", dir.name, channel))?;
+
+        let content = match dir.prompt {
+            Some(ref prompt) => {
+                let msg = InboundMessage::new(channel, chat_id, WATCH_SENDER, render_prompt(prompt, event));
+                self.handler.handle(msg).await?
+            }
+            None => notification(&self.templates, channel, event),
+        };
+        if content.trim().is_empty() {
+            return Ok(());
+        }
+        let to = ChannelTarget::parse(chat_id)?;
+        send_proactive(&self.quiet_hours, self.outbox.as_ref(), target.clone(), &to, &content).await
+    }
+}
+
+#[async_trait]
+impl EventHandler<FileChangedEvent> for FileWatchHandler {
+    async fn handle(&self, event: &FileChangedEvent) {
+        if let Err(e) = self.process(event).await {
+            warn!("处理文件变化 {} 失败: {}", event.path.display(), e);
+        }
+    }
+}
+
+/// 替换提示中的 {path}、{name}、{event}
+fn render_prompt(prompt: &str, event: &FileChangedEvent) -> String {
+    prompt
+        .replace("{path}", &event.path.to_string_lossy())
+        .replace("{name}", &file_name(&event.path))
+        .replace("{event}", event.kind.as_str())
+}
+
+fn notification(templates: &NotificationTemplates, channel: &str, event: &FileChangedEvent) -> String {
+    let action = match event.kind {
+        FileChangeKind::Created => t!("notify.watch_created"),
+        FileChangeKind::Modified => t!("notify.watch_modified"),
+        FileChangeKind::Removed => t!("notify.watch_removed"),
+    };
+    templates.render(
+        channel,
+        NotificationKind::WatchUpdate,
+        &[
+            ("watch", event.watch.clone()),
+            ("action", action),
+            ("path", event.path.display().to_string()),
+            ("name", file_name(&event.path)),
+        ],
+    )
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_config(path: &Path) -> WatchDirConfig {
+        WatchDirConfig {
+            name: "inbox".to_string(),
+            path: path.to_path_buf(),
+            patterns: vec!["*.md".to_string()],
+            recursive: false,
+            events: vec![FileChangeKind::Created, FileChangeKind::Modified, FileChangeKind::Removed],
+            channel: None,
+            chat_id: None,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_poll_reports_stable_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("old.md"), "old").unwrap();
+        let mut watch = DirWatch::new(dir_config(root));
+        let config = watch.config.clone();
+
+        // 已有文件不报告；新文件等到不再变化才报告，不匹配的和隐藏文件忽略
+        std::fs::write(root.join("new.md"), "写入中").unwrap();
+        std::fs::write(root.join("image.png"), "x").unwrap();
+        std::fs::write(root.join(".draft.md"), "x").unwrap();
+        assert!(watch.poll(scan(&config)).is_empty());
+        assert_eq!(watch.poll(scan(&config)), vec![(root.join("new.md"), FileChangeKind::Created)]);
+        assert!(watch.poll(scan(&config)).is_empty());
+
+        std::fs::write(root.join("old.md"), "old, updated").unwrap();
+        std::fs::remove_file(root.join("new.md")).unwrap();
+        assert_eq!(watch.poll(scan(&config)), vec![(root.join("new.md"), FileChangeKind::Removed)]);
+        assert_eq!(watch.poll(scan(&config)), vec![(root.join("old.md"), FileChangeKind::Modified)]);
+
+        // 只关注新增时忽略修改和删除
+        watch.config.events = vec![FileChangeKind::Created];
+        std::fs::remove_file(root.join("old.md")).unwrap();
+        assert!(watch.poll(scan(&config)).is_empty());
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let event = FileChangedEvent {
+            watch: "inbox".to_string(),
+            path: PathBuf::from("/home/user/inbox/report.pdf"),
+            kind: FileChangeKind::Created,
+            timestamp: Utc::now(),
+        };
+        assert_eq!(
+            render_prompt("{name} ({event}) 出现在 {path}，请总结", &event),
+            "report.pdf (created) 出现在 /home/user/inbox/report.pdf，请总结"
+        );
+        let templates = NotificationTemplates::default();
+        assert_eq!(
+            notification(&templates, "telegram", &event),
+            "📂 inbox: 新文件 /home/user/inbox/report.pdf"
+        );
+    }
+}